@@ -0,0 +1,42 @@
+//! Benchmarks of a single ballot going through ingestion
+//!
+//! Run with `cargo bench --bench add_vote`, see `vote_load` for many voters at once
+mod common;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use fip_voting::store::VoteStore;
+use testcontainers::clients::Cli;
+use tokio::runtime::Runtime;
+
+use common::{connect, fip, signed_votes, start_redis, VOTE_LENGTH};
+
+fn ingestion(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let docker = Cli::default();
+    let (_container, url) = start_redis(&docker);
+    let mut redis = connect(&url);
+
+    let (voter, ballot) = runtime.block_on(signed_votes(&mut redis, 1)).pop().unwrap();
+    let vote = ballot.vote().unwrap();
+
+    let mut group = c.benchmark_group("ingestion");
+
+    // Recovering the voter from the signature, done before the store is reached
+    group.bench_function("recover_vote", |b| {
+        b.iter(|| black_box(&ballot).vote().unwrap())
+    });
+
+    // The same voter voting again, which replaces their ballot
+    group.bench_function("add_vote", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(redis.add_vote(fip(), vote.clone(), voter, VOTE_LENGTH, true))
+                .unwrap()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, ingestion);
+criterion_main!(benches);
@@ -0,0 +1,105 @@
+//! Setup shared by the benchmarks
+//!
+//! Like the tests, each benchmark starts a Redis container of its own, so
+//! Docker has to be running. Ballots are cast by storage provider voters whose
+//! power is fixed rather than read from the chain, so only the backend and
+//! Redis are measured
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::Address,
+};
+use fip_voting::{
+    fip::FipNumber,
+    messages::votes::ReceivedVote,
+    redis::Redis,
+    storage::{Network, StorageFetchError, StoragePowerProvider},
+    store::VoteStore,
+};
+use testcontainers::{clients::Cli, images::redis::Redis as RedisImage, Container};
+use url::Url;
+
+/// Port redis listens on inside the container
+const REDIS_PORT: u16 = 6379;
+
+/// Network ballots are cast on
+const NTW: Network = Network::Testnet;
+
+/// Power of every storage provider
+const SP_POWER: u128 = 10240000;
+
+/// Storage provider id of the first voter, each voter is registered for one of its own
+const FIRST_SP_ID: u32 = 1000;
+
+/// Vote length the ballots are cast with, long enough to outlast any run
+pub const VOTE_LENGTH: u64 = 24 * 60 * 60;
+
+/// The same power for every storage provider
+struct FixedPower;
+
+#[async_trait]
+impl StoragePowerProvider for FixedPower {
+    async fn storage_amounts(
+        &self,
+        sp_ids: &[u32],
+        _ntw: Network,
+    ) -> Result<BTreeMap<u32, u128>, StorageFetchError> {
+        Ok(sp_ids.iter().map(|sp_id| (*sp_id, SP_POWER)).collect())
+    }
+}
+
+/// FIP the ballots are cast on
+pub fn fip() -> FipNumber {
+    FipNumber::try_from(1u32).unwrap()
+}
+
+/// Starts an empty server, which is removed once the container is dropped
+pub fn start_redis(docker: &Cli) -> (Container<'_, RedisImage>, Url) {
+    let container = docker.run(RedisImage::default());
+
+    let port = container.get_host_port_ipv4(REDIS_PORT);
+    let url = Url::parse(&format!("redis://127.0.0.1:{}", port)).unwrap();
+
+    (container, url)
+}
+
+/// Opens a connection weighting ballots with the fixed power
+pub fn connect(url: &Url) -> Redis {
+    let mut redis = Redis::new(url.clone()).unwrap();
+    redis.set_power_provider(FixedPower);
+
+    redis
+}
+
+/// Starts the vote and registers `count` voters, returning a signed yay ballot
+/// of each with their address
+pub async fn signed_votes(redis: &mut Redis, count: usize) -> Vec<(Address, ReceivedVote)> {
+    redis.start_vote(fip(), Address::zero(), NTW).await.unwrap();
+
+    let mut ballots = Vec::with_capacity(count);
+    for i in 0..count {
+        // Keys are numbered from 1 so every run casts the same ballots
+        let mut key = [0u8; 32];
+        key[24..].copy_from_slice(&(i as u64 + 1).to_be_bytes());
+        let wallet = LocalWallet::from_bytes(&key).unwrap();
+
+        let sp_id = FIRST_SP_ID + i as u32;
+        redis
+            .register_voter(wallet.address(), NTW, vec![sp_id])
+            .unwrap();
+
+        let message = format!("YAY: FIP-{}", fip());
+        let signature = wallet.sign_message(&message).await.unwrap();
+        let ballot = serde_json::from_value(serde_json::json!({
+            "signature": format!("0x{}", signature),
+            "message": message,
+        }))
+        .unwrap();
+
+        ballots.push((wallet.address(), ballot));
+    }
+
+    ballots
+}
@@ -0,0 +1,106 @@
+//! Load generator for vote ingestion
+//!
+//! Casts `--votes` signed ballots, one per voter, over `--concurrency`
+//! connections at once against a Redis container of its own, and reports the
+//! latency of `add_vote` and the throughput, for example
+//! `cargo bench --bench vote_load -- --votes 5000 --concurrency 100`
+mod common;
+
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use fip_voting::store::VoteStore;
+use testcontainers::clients::Cli;
+use tokio::runtime::Builder;
+
+use common::{connect, fip, signed_votes, start_redis, VOTE_LENGTH};
+
+#[derive(Parser)]
+struct Args {
+    /// Number of voters, who each cast one ballot
+    #[arg(long, default_value_t = 1000)]
+    votes: usize,
+    /// Ballots being cast at once, each over a connection of its own
+    #[arg(long, default_value_t = 50)]
+    concurrency: usize,
+    /// Passed by `cargo bench`
+    #[arg(long, hide = true)]
+    bench: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+    let concurrency = args.concurrency.max(1);
+
+    // The redis connection is blocking, so every connection gets a thread
+    let runtime = Builder::new_multi_thread()
+        .worker_threads(concurrency)
+        .enable_all()
+        .build()
+        .unwrap();
+    let docker = Cli::default();
+    let (_container, url) = start_redis(&docker);
+
+    let ballots = runtime.block_on(signed_votes(&mut connect(&url), args.votes));
+    let mut connections: Vec<Vec<_>> = (0..concurrency).map(|_| Vec::new()).collect();
+    for (i, ballot) in ballots.into_iter().enumerate() {
+        connections[i % concurrency].push(ballot);
+    }
+
+    let started = Instant::now();
+    let mut latencies = runtime.block_on(async {
+        let tasks: Vec<_> = connections
+            .into_iter()
+            .map(|ballots| {
+                let mut redis = connect(&url);
+                tokio::spawn(async move {
+                    let mut latencies = Vec::with_capacity(ballots.len());
+                    for (voter, ballot) in ballots {
+                        let vote = ballot.vote().unwrap();
+                        let sent = Instant::now();
+                        redis
+                            .add_vote(fip(), vote, voter, VOTE_LENGTH, false)
+                            .await
+                            .unwrap();
+                        latencies.push(sent.elapsed());
+                    }
+                    latencies
+                })
+            })
+            .collect();
+
+        let mut latencies = Vec::with_capacity(args.votes);
+        for task in tasks {
+            latencies.extend(task.await.unwrap());
+        }
+        latencies
+    });
+    let elapsed = started.elapsed();
+
+    if latencies.is_empty() {
+        println!("No votes were cast");
+        return;
+    }
+    latencies.sort();
+
+    println!(
+        "Cast {} votes over {} connections in {:?}, {:.0} votes/s",
+        latencies.len(),
+        concurrency,
+        elapsed,
+        latencies.len() as f64 / elapsed.as_secs_f64()
+    );
+    println!(
+        "add_vote latency p50 {:?} p90 {:?} p99 {:?} max {:?}",
+        percentile(&latencies, 50),
+        percentile(&latencies, 90),
+        percentile(&latencies, 99),
+        latencies[latencies.len() - 1]
+    );
+}
+
+/// Latency `p` percent of the sorted latencies are within
+fn percentile(latencies: &[Duration], p: usize) -> Duration {
+    let rank = (latencies.len() * p).div_ceil(100).max(1);
+    latencies[rank - 1]
+}
@@ -0,0 +1,416 @@
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Responder};
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    errors::*,
+    messages::class_registration::VoterClass,
+    redis::{VoteResults, VoteStatus},
+    storage::Network,
+    store::{StoreError, StoreFactory, VoteStore},
+    Args, NtwFipParams, NtwParams,
+};
+
+/// A voter, their class and the storage providers they are registered to vote for
+///
+/// Used to dump and restore registrations when migrating databases
+#[derive(Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+pub struct RegistrationDump {
+    pub address: Address,
+    pub sp_ids: Vec<u32>,
+    /// Dumps written before voter classes existed are all storage providers
+    #[serde(default)]
+    pub class: VoterClass,
+    /// Unix timestamp the registration expires at, left out if it never expires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// Worker or multisig address that signed the registration, left out if unknown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worker_address: Option<String>,
+}
+
+impl RegistrationDump {
+    pub fn dump(
+        redis: &mut impl VoteStore,
+        address: Address,
+        ntw: Network,
+    ) -> Result<Self, StoreError> {
+        Ok(Self {
+            address,
+            sp_ids: redis.voter_delegates(address, ntw)?,
+            class: redis.voter_class(address, ntw)?,
+            expires_at: redis.registration_expiry(address, ntw)?,
+            worker_address: redis.voter_worker(address, ntw)?,
+        })
+    }
+
+    /// Writes the registration back, returns false if there was nothing to restore
+    pub fn restore(&self, redis: &mut impl VoteStore, ntw: Network) -> Result<bool, StoreError> {
+        match self.class {
+            VoterClass::StorageProvider if self.sp_ids.is_empty() => return Ok(false),
+            VoterClass::StorageProvider => {
+                redis.register_voter(self.address, ntw, self.sp_ids.clone())?
+            }
+            class => redis.register_voter_class(self.address, ntw, class)?,
+        }
+        redis.set_registration_expiry(self.address, ntw, self.expires_at)?;
+        redis.set_voter_worker(self.address, ntw, self.worker_address.as_deref())?;
+
+        Ok(true)
+    }
+}
+
+/// Registers the admin endpoints under `/admin`, working on the stores `F` opens
+pub fn scope<F: StoreFactory>() -> actix_web::Scope {
+    web::scope("/admin")
+        .route("/vote", web::delete().to(delete_vote::<F>))
+        .route("/concludevote", web::post().to(conclude_vote::<F>))
+        .route("/votes", web::get().to(get_raw_votes::<F>))
+        .route("/registrations", web::get().to(get_registrations::<F>))
+        .route("/registrations", web::post().to(restore_registrations::<F>))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/vote",
+    tag = "admin",
+    params(NtwFipParams),
+    responses(
+        (status = 200, description = "The vote was deleted"),
+        (status = 401, description = "Invalid admin token", body = String),
+        (status = 403, description = "The admin API is disabled", body = String),
+        (status = 500, description = "Database error", body = String),
+    ),
+    security(("admin_token" = []))
+)]
+async fn delete_vote<F: StoreFactory>(
+    req: HttpRequest,
+    query_params: web::Query<NtwFipParams>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    if let Err(res) = authorize(&req, &config) {
+        return res;
+    }
+
+    let ntw = query_params.network;
+    let num = query_params.fip_number;
+
+    println!("Admin deleting vote for FIP-{} on {:?}", num, ntw);
+
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    match redis.delete_vote(num, ntw).await {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_DELETE_ERROR, e);
+            println!("{}", res);
+            HttpResponse::InternalServerError().body(res)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/concludevote",
+    tag = "admin",
+    params(NtwFipParams),
+    responses(
+        (status = 200, description = "Results of the vote, stored if they weren't already", body = VoteResults),
+        (status = 401, description = "Invalid admin token", body = String),
+        (status = 403, description = "The admin API is disabled", body = String),
+        (status = 404, description = "The vote does not exist"),
+        (status = 409, description = "The vote has not ended, the body ends with the seconds left", body = String),
+        (status = 410, description = "The vote was cancelled", body = String),
+        (status = 500, description = "Database error", body = String),
+    ),
+    security(("admin_token" = []))
+)]
+async fn conclude_vote<F: StoreFactory>(
+    req: HttpRequest,
+    query_params: web::Query<NtwFipParams>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    if let Err(res) = authorize(&req, &config) {
+        return res;
+    }
+
+    let ntw = query_params.network;
+    let num = query_params.fip_number;
+
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    // Only votes past their deadline are concluded, ending a vote early is
+    // up to its vote starters
+    match redis.vote_status(num, config.vote_length(), ntw) {
+        Ok(VoteStatus::Concluded) => (),
+        Ok(VoteStatus::InProgress(time_left)) | Ok(VoteStatus::Scheduled(time_left)) => {
+            let res = format!("{}: {}", VOTE_NOT_ENDED_ERROR, time_left);
+            return HttpResponse::Conflict().body(res);
+        }
+        Ok(VoteStatus::DoesNotExist) => return HttpResponse::NotFound().finish(),
+        Ok(VoteStatus::Cancelled) => return HttpResponse::Gone().body(VOTE_CANCELLED),
+        Ok(VoteStatus::Unknown) => return HttpResponse::Conflict().body(VOTE_STATUS_UNKNOWN),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_STATUS_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    }
+
+    match redis.conclude_vote(num, config.vote_length(), ntw).await {
+        Ok(Some(results)) => {
+            println!("Admin concluded the vote for FIP-{} on {:?}", num, ntw);
+            HttpResponse::Ok().json(results)
+        }
+        // A new round started since the status was read
+        Ok(None) => HttpResponse::Conflict().body(VOTE_NOT_ENDED_ERROR),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_CONCLUDE_ERROR, e);
+            println!("{}", res);
+            HttpResponse::InternalServerError().body(res)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/votes",
+    tag = "admin",
+    params(NtwFipParams),
+    responses(
+        (status = 200, description = "Every recorded ballot of the vote", body = [Vote]),
+        (status = 401, description = "Invalid admin token", body = String),
+        (status = 403, description = "The admin API is disabled", body = String),
+        (status = 500, description = "Database error", body = String),
+    ),
+    security(("admin_token" = []))
+)]
+async fn get_raw_votes<F: StoreFactory>(
+    req: HttpRequest,
+    query_params: web::Query<NtwFipParams>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    if let Err(res) = authorize(&req, &config) {
+        return res;
+    }
+
+    let ntw = query_params.network;
+    let num = query_params.fip_number;
+
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    match redis.votes(num, ntw) {
+        Ok(votes) => HttpResponse::Ok().json(votes),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_RESULTS_ERROR, e);
+            println!("{}", res);
+            HttpResponse::InternalServerError().body(res)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/registrations",
+    tag = "admin",
+    params(NtwParams),
+    responses(
+        (status = 200, description = "Every registered voter", body = [RegistrationDump]),
+        (status = 401, description = "Invalid admin token", body = String),
+        (status = 403, description = "The admin API is disabled", body = String),
+        (status = 500, description = "Database error", body = String),
+    ),
+    security(("admin_token" = []))
+)]
+async fn get_registrations<F: StoreFactory>(
+    req: HttpRequest,
+    query_params: web::Query<NtwParams>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    if let Err(res) = authorize(&req, &config) {
+        return res;
+    }
+
+    let ntw = query_params.network;
+
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    let voters = match redis.registered_voters(ntw) {
+        Ok(voters) => voters,
+        Err(e) => {
+            let res = format!("{}: {}", REGISTRATIONS_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    let mut dump = Vec::with_capacity(voters.len());
+    for address in voters {
+        match RegistrationDump::dump(&mut redis, address, ntw) {
+            Ok(registration) => dump.push(registration),
+            Err(e) => {
+                let res = format!("{}: {}", REGISTRATIONS_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::InternalServerError().body(res);
+            }
+        }
+    }
+
+    HttpResponse::Ok().json(dump)
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/registrations",
+    tag = "admin",
+    params(NtwParams),
+    request_body = [RegistrationDump],
+    responses(
+        (status = 200, description = "The registrations were restored"),
+        (status = 401, description = "Invalid admin token", body = String),
+        (status = 403, description = "The admin API is disabled", body = String),
+        (status = 500, description = "Database error", body = String),
+    ),
+    security(("admin_token" = []))
+)]
+async fn restore_registrations<F: StoreFactory>(
+    req: HttpRequest,
+    query_params: web::Query<NtwParams>,
+    dump: web::Json<Vec<RegistrationDump>>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    if let Err(res) = authorize(&req, &config) {
+        return res;
+    }
+
+    let ntw = query_params.network;
+
+    println!("Admin restoring {} registrations on {:?}", dump.len(), ntw);
+
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    for registration in dump.into_inner() {
+        if let Err(e) = registration.restore(&mut redis, ntw) {
+            let res = format!("{}: {}", REGISTRATIONS_RESTORE_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    }
+
+    HttpResponse::Ok().finish()
+}
+
+/// Checks the `Authorization: Bearer <token>` header against the configured admin token
+fn authorize(req: &HttpRequest, config: &Args) -> Result<(), HttpResponse> {
+    let token = match config.admin_token() {
+        Some(token) if !token.is_empty() => token,
+        _ => return Err(HttpResponse::Forbidden().body(ADMIN_DISABLED_ERROR)),
+    };
+
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .unwrap_or("");
+
+    if !constant_time_eq(provided.as_bytes(), token.as_bytes()) {
+        println!("{}", ADMIN_UNAUTHORIZED_ERROR);
+        return Err(HttpResponse::Unauthorized().body(ADMIN_UNAUTHORIZED_ERROR));
+    }
+
+    Ok(())
+}
+
+/// Compares without short circuiting so the token can't be guessed byte by byte
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admin_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(!constant_time_eq(b"", b"secret"));
+    }
+
+    #[test]
+    fn admin_registration_dump_serde() {
+        let dump = RegistrationDump {
+            address: Address::zero(),
+            sp_ids: vec![6024],
+            class: VoterClass::StorageProvider,
+            expires_at: Some(1685000000),
+            worker_address: Some("t3qejyqmrirddrsb2w2thbaco3q6emuljumlhuonp3al35g3kkzx4zpeecycw7gim2meegemwot3gp3qr6alpa".to_string()),
+        };
+
+        let json = serde_json::to_string(&dump).unwrap();
+        let res: RegistrationDump = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(res, dump);
+    }
+
+    #[test]
+    fn admin_registration_dump_legacy() {
+        let json = r#"{"address":"0x0000000000000000000000000000000000000000","sp_ids":[6024]}"#;
+
+        let res: RegistrationDump = serde_json::from_str(json).unwrap();
+
+        assert_eq!(res.class, VoterClass::StorageProvider);
+    }
+}
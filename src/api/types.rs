@@ -0,0 +1,574 @@
+//! Stable JSON shapes of the responses, sent to clients that ask for them
+//!
+//! The internal types serialize with the field names they are stored with,
+//! which change as the database does. Clients that send
+//! `Accept: application/vnd.fipvote.v1+json` get the types of this module
+//! instead, with camelCase names, wrapped in an envelope holding the version
+//! of the shape. Every other client keeps getting the responses as they were
+//!
+//! Version 2 sends storage sizes, power and balances as decimal strings, and
+//! adds shapes for the concluded votes, voting power, turnout and stats
+use std::collections::BTreeMap;
+
+use actix_web::{http::header, HttpRequest, HttpResponse};
+use ethers::types::Address;
+use serde::{Serialize, Serializer};
+use utoipa::ToSchema;
+
+use crate::{
+    fip::FipNumber,
+    messages::{
+        vote_registration::RegistrationReceipt,
+        votes::{BallotAudit, VoteOption},
+    },
+    redis::{CohortSilence, SilenceReport, Turnout, VoteOutcome, VoteResults},
+    registrations::RegistrationStatus,
+    storage::{sp_id_format, Network, SpCohort},
+    tally::Weighting,
+};
+
+/// Latest version of the shapes in this module, bumped when a field changes
+/// meaning, type or is removed
+pub const API_VERSION: u32 = 2;
+
+/// Media type clients accept to get the shapes of `version`, such as
+/// `application/vnd.fipvote.v1+json`
+pub fn media_type(version: u32) -> String {
+    format!("application/vnd.fipvote.v{}+json", version)
+}
+
+/// The latest version of the shapes the request accepts, `None` when it
+/// wants the internal ones
+pub fn requested_version(req: &HttpRequest) -> Option<u32> {
+    let accepted: Vec<String> = req
+        .headers()
+        .get_all(header::ACCEPT)
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(','))
+        .filter_map(|media| media.split(';').next())
+        .map(|media| media.trim().to_ascii_lowercase())
+        .collect();
+
+    (1..=API_VERSION)
+        .rev()
+        .find(|version| accepted.contains(&media_type(*version)))
+}
+
+/// Wraps the body in the envelope of its version
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope<T> {
+    pub api_version: u32,
+    pub data: T,
+}
+
+impl<T: Serialize> Envelope<T> {
+    pub fn new(data: T, version: u32) -> Self {
+        Self {
+            api_version: version,
+            data,
+        }
+    }
+
+    /// A 200 with the envelope as the body
+    pub fn respond(self) -> HttpResponse {
+        HttpResponse::Ok()
+            .content_type(media_type(self.api_version))
+            .body(serde_json::to_string(&self).unwrap())
+    }
+}
+
+/// A storage size, power or balance
+///
+/// Sent as a number in version 1, which JavaScript rounds once it is past
+/// 2^53, and as a decimal string from version 2
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Amount {
+    value: u128,
+    as_string: bool,
+}
+
+impl Amount {
+    pub fn new(value: u128, version: u32) -> Self {
+        Self {
+            value,
+            as_string: version >= 2,
+        }
+    }
+}
+
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.as_string {
+            true => serializer.serialize_str(&self.value.to_string()),
+            false => serializer.serialize_u128(self.value),
+        }
+    }
+}
+
+/// A recorded ballot
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VoteDto {
+    /// The voter address, or the hex encoded keccak256 hash of it when anonymized
+    pub voter: String,
+    /// `Yay`, `Nay`, `Abstain` or the name of an option
+    pub choice: String,
+    /// Unix timestamp the ballot was recorded, 0 for ballots recorded before it was kept
+    pub recorded_at: u64,
+    /// Left out when anonymized since they identify the voter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// Set when the signature is over the EIP-712 typed data of `message`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub typed_chain_id: Option<u64>,
+    /// Storage providers the ballot was cast for, empty when anonymized
+    pub storage_providers: Vec<StorageProviderDto>,
+    /// Raw byte power the ballot was cast with
+    #[schema(value_type = String)]
+    pub power: Amount,
+    /// What the ballot added to the storage size of its choice, only set by
+    /// `/filecoin/vote/breakdown`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub weighted_power: Option<Amount>,
+}
+
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageProviderDto {
+    /// Such as `f01000`
+    pub sp_id: String,
+    /// Raw byte power in bytes
+    #[schema(value_type = String)]
+    pub power: Amount,
+    /// Worker or owner address the storage provider registered with, only
+    /// set on registrations
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub worker_address: Option<String>,
+}
+
+impl VoteDto {
+    pub fn new(
+        audit: BallotAudit,
+        ntw: Network,
+        weighted_power: Option<u128>,
+        version: u32,
+    ) -> Self {
+        Self {
+            voter: audit.voter,
+            choice: choice_name(&audit.choice),
+            recorded_at: audit.recorded_at,
+            message: audit.message,
+            signature: audit.signature,
+            typed_chain_id: audit.typed_chain_id,
+            storage_providers: audit
+                .storage_providers
+                .into_iter()
+                .map(|(sp_id, power)| StorageProviderDto {
+                    sp_id: sp_id_format(ntw, sp_id),
+                    power: Amount::new(power, version),
+                    worker_address: None,
+                })
+                .collect(),
+            power: Amount::new(audit.storage_size, version),
+            weighted_power: weighted_power.map(|power| Amount::new(power, version)),
+        }
+    }
+}
+
+/// Results of a concluded vote
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultsDto {
+    #[schema(value_type = u32)]
+    pub fip_number: FipNumber,
+    #[schema(value_type = String)]
+    pub network: Network,
+    pub choices: Vec<ChoiceDto>,
+    /// Storage behind every choice
+    #[schema(value_type = String)]
+    pub total_storage_size: Amount,
+    pub weighting: Weighting,
+    /// Set for votes started with quorum rules
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quorum_met: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<VoteOutcome>,
+    /// Ballots left out of the choices for being cast with less power than
+    /// the vote requires
+    pub excluded_ballots: u64,
+}
+
+/// Ballots, storage and token holder balance behind a choice
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChoiceDto {
+    /// `Yay`, `Nay`, `Abstain` or the name of an option
+    pub choice: String,
+    pub ballots: u64,
+    /// Storage power in bytes, weighted as the vote was started with
+    #[schema(value_type = String)]
+    pub storage_size: Amount,
+    /// Token holder balance in attoFIL
+    #[schema(value_type = String)]
+    pub fil: Amount,
+}
+
+impl ResultsDto {
+    pub fn new(results: &VoteResults, fip_number: FipNumber, ntw: Network, version: u32) -> Self {
+        Self {
+            fip_number,
+            network: ntw,
+            choices: results
+                .choices()
+                .iter()
+                .map(|choice| ChoiceDto {
+                    choice: choice_name(choice),
+                    ballots: results.ballots(choice),
+                    storage_size: Amount::new(results.storage_size(choice), version),
+                    fil: Amount::new(results.fil(choice), version),
+                })
+                .collect(),
+            total_storage_size: Amount::new(results.total_storage_size(), version),
+            weighting: results.weighting(),
+            quorum_met: results.quorum_met(),
+            outcome: results.outcome(),
+            excluded_ballots: results.below_min_power(),
+        }
+    }
+}
+
+/// Where the last registration of an address is at
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistrationDto {
+    #[schema(value_type = String)]
+    pub address: Address,
+    /// `pending`, `registered`, `offered` or `failed`
+    pub status: String,
+    /// Unix timestamp a pending registration was queued at
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queued_at: Option<u64>,
+    /// Storage providers of a registered voter, or of the delegation offered to them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub storage_providers: Vec<StorageProviderDto>,
+    /// Sum of the storage power of every storage provider in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub voting_power: Option<Amount>,
+    /// Unix timestamp the registration has to be renewed by
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// Why a failed registration was rejected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RegistrationDto {
+    pub fn new(address: Address, status: RegistrationStatus, version: u32) -> Self {
+        let mut dto = Self {
+            address,
+            status: String::new(),
+            queued_at: None,
+            storage_providers: Vec::new(),
+            voting_power: None,
+            expires_at: None,
+            error: None,
+        };
+
+        match status {
+            RegistrationStatus::Pending { queued_at } => {
+                dto.status = "pending".to_string();
+                dto.queued_at = Some(queued_at);
+            }
+            RegistrationStatus::Registered { receipt } => {
+                dto.status = "registered".to_string();
+                dto.set_receipt(receipt, version);
+            }
+            RegistrationStatus::Offered { receipt } => {
+                dto.status = "offered".to_string();
+                dto.set_receipt(receipt, version);
+            }
+            RegistrationStatus::Failed { error } => {
+                dto.status = "failed".to_string();
+                dto.error = Some(error);
+            }
+        }
+
+        dto
+    }
+
+    fn set_receipt(&mut self, receipt: RegistrationReceipt, version: u32) {
+        self.storage_providers = receipt
+            .storage_providers
+            .into_iter()
+            .map(|sp| StorageProviderDto {
+                sp_id: sp.sp_id,
+                power: Amount::new(sp.storage_size, version),
+                worker_address: Some(sp.worker_address),
+            })
+            .collect();
+        self.voting_power = Some(Amount::new(receipt.voting_power, version));
+        self.expires_at = receipt.expires_at;
+    }
+}
+
+/// Raw byte power of a voter, the body of `/filecoin/votingpower` from version 2
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VotingPowerDto {
+    #[schema(value_type = String)]
+    pub address: Address,
+    #[schema(value_type = String)]
+    pub network: Network,
+    /// Sum of the raw byte power of the storage providers of the voter
+    #[schema(value_type = String)]
+    pub voting_power: Amount,
+    /// Height the power was read at, only set by `/filecoin/votingpower/at`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u64>,
+}
+
+impl VotingPowerDto {
+    pub fn new(
+        address: Address,
+        ntw: Network,
+        voting_power: u128,
+        height: Option<u64>,
+        version: u32,
+    ) -> Self {
+        Self {
+            address,
+            network: ntw,
+            voting_power: Amount::new(voting_power, version),
+            height,
+        }
+    }
+}
+
+/// Participation in a vote compared to the network power, from version 2
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TurnoutDto {
+    pub voters: u64,
+    pub storage_providers: u64,
+    #[schema(value_type = String)]
+    pub storage_size: Amount,
+    #[schema(value_type = String)]
+    pub network_storage_size: Amount,
+    /// Share of the network power that voted, in percent
+    pub turnout_percentage: f64,
+}
+
+impl TurnoutDto {
+    pub fn new(turnout: &Turnout, version: u32) -> Self {
+        Self {
+            voters: turnout.voters,
+            storage_providers: turnout.storage_providers,
+            storage_size: Amount::new(turnout.storage_size, version),
+            network_storage_size: Amount::new(turnout.network_storage_size, version),
+            turnout_percentage: turnout.turnout_percentage,
+        }
+    }
+}
+
+/// Registered storage that did not vote, from version 2
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SilenceDto {
+    pub silent_voters: u64,
+    #[schema(value_type = String)]
+    pub silent_storage_size: Amount,
+    pub cohorts: BTreeMap<SpCohort, CohortDto>,
+}
+
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CohortDto {
+    pub sp_count: u64,
+    #[schema(value_type = String)]
+    pub storage_size: Amount,
+}
+
+impl SilenceDto {
+    pub fn new(report: &SilenceReport, version: u32) -> Self {
+        let cohort = |silence: &CohortSilence| CohortDto {
+            sp_count: silence.sp_count(),
+            storage_size: Amount::new(silence.storage_size(), version),
+        };
+
+        Self {
+            silent_voters: report.silent_voters(),
+            silent_storage_size: Amount::new(report.silent_storage_size(), version),
+            cohorts: report
+                .cohorts()
+                .iter()
+                .map(|(sp_cohort, silence)| (*sp_cohort, cohort(silence)))
+                .collect(),
+        }
+    }
+}
+
+/// Named options are sent by name rather than as `{"Named": name}`
+fn choice_name(choice: &VoteOption) -> String {
+    match choice {
+        VoteOption::Yay => "Yay".to_string(),
+        VoteOption::Nay => "Nay".to_string(),
+        VoteOption::Abstain => "Abstain".to_string(),
+        VoteOption::Unknown(byte) => byte.to_string(),
+        VoteOption::Named(name) => name.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use serde_json::json;
+
+    use super::*;
+    use crate::messages::vote_registration::StorageProviderReceipt;
+
+    #[test]
+    fn types_requested_version() {
+        let req = TestRequest::default()
+            .insert_header((
+                header::ACCEPT,
+                "text/html, application/vnd.fipvote.v1+json;q=0.9",
+            ))
+            .to_http_request();
+        assert_eq!(requested_version(&req), Some(1));
+
+        // The latest version accepted wins
+        let req = TestRequest::default()
+            .insert_header((
+                header::ACCEPT,
+                "application/vnd.fipvote.v1+json, application/vnd.fipvote.v2+json",
+            ))
+            .to_http_request();
+        assert_eq!(requested_version(&req), Some(2));
+
+        let req = TestRequest::default()
+            .insert_header((header::ACCEPT, "application/vnd.fipvote.v9+json"))
+            .to_http_request();
+        assert_eq!(requested_version(&req), None);
+        let req = TestRequest::default()
+            .insert_header((header::ACCEPT, "application/json"))
+            .to_http_request();
+        assert_eq!(requested_version(&req), None);
+        assert_eq!(
+            requested_version(&TestRequest::default().to_http_request()),
+            None
+        );
+    }
+
+    #[test]
+    fn types_amount() {
+        let power = u128::from(u64::MAX) * 4;
+
+        assert_eq!(
+            serde_json::to_string(&Amount::new(power, 1)).unwrap(),
+            "73786976294838206460"
+        );
+        assert_eq!(
+            serde_json::to_string(&Amount::new(power, 2)).unwrap(),
+            "\"73786976294838206460\""
+        );
+    }
+
+    #[test]
+    fn types_results_dto() {
+        let results: VoteResults = serde_json::from_value(json!({
+            "yay": 2,
+            "nay": 1,
+            "abstain": 0,
+            "yay_storage_size": 300,
+            "nay_storage_size": 100,
+            "abstain_storage_size": 0,
+            "options": {"Option A": {"votes": 1, "storage_size": 50, "fil": 0}},
+        }))
+        .unwrap();
+
+        let fip_number = FipNumber::try_from(42).unwrap();
+        let dto = ResultsDto::new(&results, fip_number, Network::Mainnet, 1);
+        let json = serde_json::to_value(Envelope::new(dto, 1)).unwrap();
+
+        assert_eq!(json["apiVersion"], 1);
+        assert_eq!(json["data"]["fipNumber"], 42);
+        assert_eq!(json["data"]["network"], "mainnet");
+        assert_eq!(json["data"]["totalStorageSize"], 450);
+        assert_eq!(json["data"]["weighting"], "raw");
+        assert_eq!(
+            json["data"]["choices"][0],
+            json!({"choice": "Yay", "ballots": 2, "storageSize": 300, "fil": 0})
+        );
+        assert_eq!(json["data"]["choices"][3]["choice"], "Option A");
+        assert_eq!(json["data"]["excludedBallots"], 0);
+        assert!(json["data"].get("outcome").is_none());
+
+        // Version 2 sends the amounts as strings
+        let dto = ResultsDto::new(&results, fip_number, Network::Mainnet, 2);
+        let json = serde_json::to_value(Envelope::new(dto, 2)).unwrap();
+
+        assert_eq!(json["apiVersion"], 2);
+        assert_eq!(json["data"]["totalStorageSize"], "450");
+        assert_eq!(
+            json["data"]["choices"][0],
+            json!({"choice": "Yay", "ballots": 2, "storageSize": "300", "fil": "0"})
+        );
+    }
+
+    #[test]
+    fn types_registration_dto() {
+        let address = Address::from_low_u64_be(42);
+
+        let receipt = RegistrationReceipt {
+            address,
+            storage_providers: vec![StorageProviderReceipt {
+                sp_id: "t06024".to_string(),
+                worker_address: "t3abc".to_string(),
+                storage_size: 1024,
+            }],
+            voting_power: 1024,
+            expires_at: None,
+        };
+        let json = serde_json::to_value(RegistrationDto::new(
+            address,
+            RegistrationStatus::Registered {
+                receipt: receipt.clone(),
+            },
+            1,
+        ))
+        .unwrap();
+        assert_eq!(json["status"], "registered");
+        assert_eq!(json["votingPower"], 1024);
+        assert_eq!(
+            json["storageProviders"][0],
+            json!({"spId": "t06024", "power": 1024, "workerAddress": "t3abc"})
+        );
+        assert!(json.get("queuedAt").is_none());
+
+        let json = serde_json::to_value(RegistrationDto::new(
+            address,
+            RegistrationStatus::Registered { receipt },
+            2,
+        ))
+        .unwrap();
+        assert_eq!(json["votingPower"], "1024");
+        assert_eq!(json["storageProviders"][0]["power"], "1024");
+
+        let json = serde_json::to_value(RegistrationDto::new(
+            address,
+            RegistrationStatus::Pending {
+                queued_at: 1700000000,
+            },
+            1,
+        ))
+        .unwrap();
+        assert_eq!(json["status"], "pending");
+        assert_eq!(json["queuedAt"], 1700000000);
+        assert!(json.get("storageProviders").is_none());
+    }
+}
@@ -0,0 +1,265 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    admin::RegistrationDump,
+    fip::FipNumber,
+    messages::{vote_metadata::VoteMetadata, vote_start::QuorumRules, votes::Vote},
+    redis::{EarlyEnd, VoteResults},
+    storage::Network,
+    store::{StoreError, VoteStore},
+    tally::Weighting,
+};
+
+/// Version written by `export`, bumped whenever the bundle layout changes
+pub const ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum ArchiveError {
+    #[error("Error reading or writing archive: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Error decoding archive: {0}")]
+    Serde(#[from] serde_json::Error),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+    #[error("Unsupported archive version {0}")]
+    UnsupportedVersion(u32),
+    #[error("FIP-{0} already exists on {1:?}, import into a fresh instance")]
+    VoteExists(FipNumber, Network),
+}
+
+/// A full copy of the votes and registrations on both networks
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ArchiveBundle {
+    pub version: u32,
+    /// Unix timestamp the bundle was exported at
+    pub exported_at: u64,
+    #[serde(default)]
+    pub mainnet: NetworkArchive,
+    #[serde(default)]
+    pub calibration: NetworkArchive,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct NetworkArchive {
+    #[serde(default)]
+    pub votes: Vec<VoteArchive>,
+    #[serde(default)]
+    pub registrations: Vec<RegistrationDump>,
+}
+
+/// Everything needed to rebuild a vote without querying the chain
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VoteArchive {
+    pub fip_number: FipNumber,
+    /// Unix timestamp the vote was started at
+    pub started_at: u64,
+    pub ballots: Vec<Vote>,
+    /// Tallies at the time of export, storage totals are restored from these
+    pub results: VoteResults,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, VoteMetadata>,
+    /// Options of a vote that is not Yay, Nay or Abstain
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quorum: Option<QuorumRules>,
+    #[serde(default, skip_serializing_if = "Weighting::is_raw")]
+    pub weighting: Weighting,
+    /// Raw byte power a ballot needs to be counted, 0 when every ballot is
+    #[serde(default)]
+    pub min_power: u128,
+    /// Set when a vote starter ended the vote before its voting period was over
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended: Option<EarlyEnd>,
+    /// Seconds the vote runs past the configured vote length
+    #[serde(default)]
+    pub extension: u64,
+}
+
+/// Counts of what an import restored
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    pub votes: usize,
+    pub ballots: usize,
+    pub registrations: usize,
+}
+
+/// Writes every vote and registration in the database to a bundle file
+pub async fn export(
+    redis: &mut impl VoteStore,
+    path: &Path,
+    vote_length: u64,
+) -> Result<ArchiveBundle, ArchiveError> {
+    let bundle = ArchiveBundle {
+        version: ARCHIVE_VERSION,
+        exported_at: redis.now(),
+        mainnet: export_network(redis, Network::Mainnet, vote_length).await?,
+        calibration: export_network(redis, Network::Testnet, vote_length).await?,
+    };
+
+    fs::write(path, serde_json::to_string_pretty(&bundle)?)?;
+
+    Ok(bundle)
+}
+
+/// Restores a bundle file into the database
+///
+/// Every vote in the bundle is checked before anything is written, so an
+/// import into an instance that already has one of the votes changes nothing
+pub async fn import(
+    redis: &mut impl VoteStore,
+    path: &Path,
+) -> Result<ImportSummary, ArchiveError> {
+    let bundle: ArchiveBundle = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+    if bundle.version != ARCHIVE_VERSION {
+        return Err(ArchiveError::UnsupportedVersion(bundle.version));
+    }
+
+    let networks = [
+        (Network::Mainnet, &bundle.mainnet),
+        (Network::Testnet, &bundle.calibration),
+    ];
+
+    for (ntw, archive) in networks.iter() {
+        for vote in archive.votes.iter() {
+            if redis.vote_exists(*ntw, vote.fip_number)? {
+                return Err(ArchiveError::VoteExists(vote.fip_number, *ntw));
+            }
+        }
+    }
+
+    let mut summary = ImportSummary::default();
+    for (ntw, archive) in networks {
+        for registration in archive.registrations.iter() {
+            if registration.restore(redis, ntw)? {
+                summary.registrations += 1;
+            }
+        }
+
+        for vote in archive.votes.iter() {
+            redis.set_vote_options(vote.fip_number, ntw, &vote.options)?;
+            redis.set_vote_quorum(vote.fip_number, ntw, vote.quorum)?;
+            redis.set_vote_weighting(vote.fip_number, ntw, vote.weighting)?;
+            redis.set_vote_min_power(vote.fip_number, ntw, vote.min_power)?;
+            redis
+                .import_vote(
+                    vote.fip_number,
+                    ntw,
+                    vote.started_at,
+                    &vote.ballots,
+                    &vote.results,
+                    &vote.metadata,
+                )
+                .await?;
+            redis.set_vote_ending(vote.fip_number, ntw, vote.ended.as_ref())?;
+            redis.set_vote_extension(vote.fip_number, ntw, vote.extension)?;
+            summary.votes += 1;
+            summary.ballots += vote.ballots.len();
+        }
+    }
+
+    Ok(summary)
+}
+
+async fn export_network(
+    redis: &mut impl VoteStore,
+    ntw: Network,
+    vote_length: u64,
+) -> Result<NetworkArchive, ArchiveError> {
+    let mut archive = NetworkArchive::default();
+
+    for fip_number in redis.all_votes(ntw)? {
+        archive.votes.push(VoteArchive {
+            fip_number,
+            started_at: redis.vote_start(fip_number, ntw)?,
+            ballots: redis.votes(fip_number, ntw)?,
+            results: redis.vote_results(fip_number, vote_length, ntw).await?,
+            metadata: redis.vote_metadata(fip_number, ntw)?,
+            options: redis.vote_options(fip_number, ntw)?,
+            quorum: redis.vote_quorum(fip_number, ntw)?,
+            weighting: redis.vote_weighting(fip_number, ntw)?,
+            min_power: redis.vote_min_power(fip_number, ntw)?,
+            ended: redis.vote_ending(fip_number, ntw)?,
+            extension: redis.vote_extension(fip_number, ntw)?,
+        });
+    }
+
+    for address in redis.registered_voters(ntw)? {
+        archive
+            .registrations
+            .push(RegistrationDump::dump(redis, address, ntw)?);
+    }
+
+    Ok(archive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        messages::votes::{test_votes::test_vote, VoteOption},
+        test_redis::TestRedis,
+    };
+
+    fn redis() -> TestRedis {
+        TestRedis::start()
+    }
+
+    #[tokio::test]
+    async fn archive_round_trip() {
+        let mut redis = redis();
+        let path = std::env::temp_dir().join("fip-voting-test-archive.json");
+
+        let fip = FipNumber::try_from(5u32).unwrap();
+        let ntw = Network::Testnet;
+        let vote = test_vote(VoteOption::Yay, 5).vote().unwrap();
+        let voter = vote.voter();
+        redis.register_voter(voter, ntw, vec![6024]).unwrap();
+        redis
+            .start_vote(fip, crate::authorized_voters()[0], ntw)
+            .await
+            .unwrap();
+        redis
+            .add_vote(fip, vote, voter, 60u64, false)
+            .await
+            .unwrap();
+
+        let exported = export(&mut *redis, &path, 60).await.unwrap();
+        let results =
+            serde_json::to_string(&redis.vote_results(fip, 60u64, ntw).await.unwrap()).unwrap();
+
+        // Importing over the same votes is rejected
+        let res = import(&mut *redis, &path).await;
+        assert!(matches!(res, Err(ArchiveError::VoteExists(_, _))));
+
+        redis.flush_all().unwrap();
+
+        let summary = import(&mut *redis, &path).await.unwrap();
+
+        assert_eq!(summary.votes, exported.calibration.votes.len());
+        assert_eq!(summary.registrations, 1);
+        assert_eq!(
+            redis.vote_start(fip, ntw).unwrap(),
+            exported.calibration.votes[0].started_at
+        );
+        assert_eq!(
+            serde_json::to_string(&redis.vote_results(fip, 60u64, ntw).await.unwrap()).unwrap(),
+            results
+        );
+    }
+
+    #[tokio::test]
+    async fn archive_unsupported_version() {
+        let mut redis = redis();
+        let path = std::env::temp_dir().join("fip-voting-test-archive-version.json");
+        fs::write(&path, r#"{"version":999,"exported_at":0}"#).unwrap();
+
+        let res = import(&mut *redis, &path).await;
+
+        assert!(matches!(res, Err(ArchiveError::UnsupportedVersion(999))));
+    }
+}
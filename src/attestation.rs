@@ -0,0 +1,276 @@
+use std::path::{Path, PathBuf};
+
+use actix_web::{web, HttpResponse, Responder};
+use ethers::{
+    signers::{LocalWallet, Signer, WalletError},
+    types::Address,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::{
+    errors::*,
+    fip::FipNumber,
+    redis::{VoteResults, VoteStatus},
+    storage::Network,
+    store::{StoreFactory, VoteStore},
+    Args, NtwFipParams,
+};
+
+#[derive(Debug, Error)]
+pub enum AttestationError {
+    #[error("Error reading {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("Invalid signing key in {0}: {1}")]
+    InvalidKey(PathBuf, WalletError),
+    #[error("Error serializing results: {0}")]
+    Serde(#[from] serde_json::Error),
+    #[error("Error signing results: {0}")]
+    Signing(#[from] WalletError),
+}
+
+/// The statement signed by the backend for a concluded vote
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AttestedResults {
+    pub fip_number: FipNumber,
+    pub network: String,
+    pub results: VoteResults,
+}
+
+impl AttestedResults {
+    pub fn new(fip_number: FipNumber, ntw: Network, results: VoteResults) -> Self {
+        Self {
+            fip_number,
+            network: ntw.name().to_string(),
+            results,
+        }
+    }
+
+    /// The json that is signed, and hashed when publishing results on-chain
+    pub fn message(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+/// Results signed with the backend key
+///
+/// `message` is the exact json that was signed with an EIP-191 personal
+/// message signature, so verifiers should recover the signer from it
+/// rather than from a re-serialization of `results`
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct Attestation {
+    pub results: VoteResults,
+    pub message: String,
+    pub signature: String,
+    pub signer: Address,
+}
+
+/// Signs the results served by this backend
+pub struct Attestor {
+    wallet: LocalWallet,
+}
+
+impl Attestor {
+    pub fn new(wallet: LocalWallet) -> Self {
+        Self { wallet }
+    }
+
+    /// Loads the hex encoded private key in `path`
+    pub fn load(path: &Path) -> Result<Self, AttestationError> {
+        Ok(Self::new(load_wallet(path)?))
+    }
+
+    pub fn address(&self) -> Address {
+        self.wallet.address()
+    }
+
+    pub async fn attest(
+        &self,
+        fip_number: FipNumber,
+        ntw: Network,
+        results: VoteResults,
+    ) -> Result<Attestation, AttestationError> {
+        let statement = AttestedResults::new(fip_number, ntw, results);
+        let message = statement.message()?;
+        let signature = self.wallet.sign_message(&message).await?;
+
+        Ok(Attestation {
+            results: statement.results,
+            message,
+            signature: format!("0x{}", signature),
+            signer: self.address(),
+        })
+    }
+}
+
+/// Reads a wallet from a file holding its hex encoded private key
+pub fn load_wallet(path: &Path) -> Result<LocalWallet, AttestationError> {
+    let key =
+        std::fs::read_to_string(path).map_err(|e| AttestationError::Io(path.to_path_buf(), e))?;
+
+    key.trim()
+        .parse::<LocalWallet>()
+        .map_err(|e| AttestationError::InvalidKey(path.to_path_buf(), e))
+}
+
+/// Registers the attestation endpoint, reading from the stores `F` opens
+pub fn routes<F: StoreFactory>(cfg: &mut web::ServiceConfig) {
+    cfg.route(
+        "/filecoin/vote/attestation",
+        web::get().to(get_vote_attestation::<F>),
+    );
+}
+
+/// The results of a concluded vote signed by the backend
+#[utoipa::path(
+    get,
+    path = "/filecoin/vote/attestation",
+    tag = "votes",
+    params(NtwFipParams),
+    responses(
+        (status = 200, description = "Results of the concluded vote signed by the backend", body = Attestation),
+        (status = 400, description = "Invalid network or request", body = String),
+        (status = 403, description = "Attestation is disabled, the vote is in progress, or it has not opened yet", body = String),
+        (status = 404, description = "The vote does not exist"),
+        (status = 410, description = "The vote was cancelled", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_vote_attestation<F: StoreFactory>(
+    query_params: web::Query<NtwFipParams>,
+    config: web::Data<Args>,
+    attestor: Option<web::Data<Attestor>>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let attestor = match attestor {
+        Some(attestor) => attestor,
+        None => return HttpResponse::Forbidden().body(ATTESTATION_DISABLED_ERROR),
+    };
+
+    let ntw = query_params.network;
+    let num = query_params.fip_number;
+
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    // Only final results are attested
+    match redis.vote_status(num, config.vote_length(), ntw) {
+        Ok(VoteStatus::Concluded) => (),
+        Ok(VoteStatus::InProgress(time_left)) => {
+            return HttpResponse::Forbidden().body(time_left.to_string())
+        }
+        Ok(VoteStatus::Scheduled(opens_in)) => {
+            return HttpResponse::Forbidden().body(format!("{}: {}", VOTE_NOT_OPEN_ERROR, opens_in))
+        }
+        Ok(VoteStatus::DoesNotExist) => return HttpResponse::NotFound().finish(),
+        Ok(VoteStatus::Cancelled) => return HttpResponse::Gone().body(VOTE_CANCELLED),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_STATUS_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    }
+
+    let results = match redis.vote_results(num, config.vote_length(), ntw).await {
+        Ok(results) => results,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_RESULTS_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    match attestor.attest(num, ntw, results).await {
+        Ok(attestation) => HttpResponse::Ok().json(attestation),
+        Err(e) => {
+            let res = format!("{}: {}", ATTESTATION_ERROR, e);
+            println!("{}", res);
+            HttpResponse::InternalServerError().body(res)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ethers::types::Signature;
+
+    use super::*;
+
+    fn attestor() -> Attestor {
+        Attestor::new(
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+                .parse()
+                .unwrap(),
+        )
+    }
+
+    fn results() -> VoteResults {
+        serde_json::from_str(
+            r#"{"yay":2,"nay":1,"abstain":0,"yay_storage_size":1024,"nay_storage_size":512,"abstain_storage_size":0}"#,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn attestation_verifies() {
+        let attestor = attestor();
+        let num = FipNumber::try_from(1).unwrap();
+
+        let attestation = attestor
+            .attest(num, Network::Mainnet, results())
+            .await
+            .unwrap();
+
+        assert_eq!(attestation.signer, attestor.address());
+
+        let signature = Signature::from_str(&attestation.signature).unwrap();
+        signature
+            .verify(attestation.message.as_str(), attestor.address())
+            .unwrap();
+
+        let statement: AttestedResults = serde_json::from_str(&attestation.message).unwrap();
+        assert_eq!(statement.fip_number, num);
+        assert_eq!(statement.network, "mainnet");
+        assert_eq!(
+            serde_json::to_value(&statement.results).unwrap(),
+            serde_json::to_value(&attestation.results).unwrap()
+        );
+    }
+
+    #[test]
+    fn attestation_load() {
+        let path = std::env::temp_dir().join("fip-voting-attestation-key");
+
+        std::fs::write(
+            &path,
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318\n",
+        )
+        .unwrap();
+        assert_eq!(
+            Attestor::load(&path).unwrap().address(),
+            attestor().address()
+        );
+
+        std::fs::write(&path, "not a key").unwrap();
+        assert!(matches!(
+            Attestor::load(&path),
+            Err(AttestationError::InvalidKey(_, _))
+        ));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            Attestor::load(&path),
+            Err(AttestationError::Io(_, _))
+        ));
+    }
+}
@@ -0,0 +1,170 @@
+//! Vote starters every network starts with
+//!
+//! They are registered on every network at startup and can always start and
+//! end votes, so a fresh database has someone to add the other starters. The
+//! built in list is only used when none is configured
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::RwLock,
+};
+
+use ethers::types::Address;
+use thiserror::Error;
+
+const DEFAULT_AUTHORIZED_VOTERS: [&str; 3] = [
+    "0x3B9705F0EF88Ee74B9924e34A5Af578d2E24F300",
+    "0x47f033Ed0F9485677008dC30507273607A74E92C",
+    "0xe662D77E7e3096683BAC8f1Ad526FB033E3810eB",
+];
+
+/// The configured starters, empty until they are loaded
+static AUTHORIZED_VOTERS: RwLock<Vec<Address>> = RwLock::new(Vec::new());
+
+#[derive(Debug, Error)]
+pub enum BootstrapError {
+    #[error("Error reading {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("Error parsing {0}: {1}")]
+    Parse(PathBuf, serde_json::Error),
+    #[error("Invalid authorized voter address {0}, expected 0x hex")]
+    InvalidAddress(String),
+    #[error("The zero address can't be an authorized voter")]
+    ZeroAddress,
+    #[error("Authorized voter {0:?} is listed twice")]
+    Duplicate(Address),
+    #[error("At least one authorized voter is needed")]
+    Empty,
+}
+
+/// The vote starters every network starts with
+pub fn authorized_voters() -> Vec<Address> {
+    let configured = AUTHORIZED_VOTERS.read().unwrap();
+    if !configured.is_empty() {
+        return configured.clone();
+    }
+
+    DEFAULT_AUTHORIZED_VOTERS
+        .iter()
+        .map(|s| Address::from_str(s).unwrap())
+        .collect()
+}
+
+/// Sets the starters from the `--authorized-voters` addresses or the file,
+/// keeping the built in ones when neither is given
+///
+/// The file is a json list of addresses
+///
+/// ```json
+/// ["0x3B9705F0EF88Ee74B9924e34A5Af578d2E24F300", "0x47f033Ed0F9485677008dC30507273607A74E92C"]
+/// ```
+pub fn load_authorized_voters(
+    addresses: &[String],
+    file: Option<&Path>,
+) -> Result<Vec<Address>, BootstrapError> {
+    let addresses = match file {
+        Some(path) => {
+            let file =
+                fs::read_to_string(path).map_err(|e| BootstrapError::Io(path.to_path_buf(), e))?;
+            serde_json::from_str(&file).map_err(|e| BootstrapError::Parse(path.to_path_buf(), e))?
+        }
+        None if addresses.is_empty() => return Ok(authorized_voters()),
+        None => addresses.to_vec(),
+    };
+
+    let voters = parse_voters(&addresses)?;
+    *AUTHORIZED_VOTERS.write().unwrap() = voters.clone();
+
+    Ok(voters)
+}
+
+fn parse_voters(addresses: &[String]) -> Result<Vec<Address>, BootstrapError> {
+    let mut voters = Vec::with_capacity(addresses.len());
+    for address in addresses.iter().map(|a| a.trim()) {
+        // `Address::from_str` also takes hex without the 0x
+        let voter = match address.starts_with("0x") && address.len() == 42 {
+            true => Address::from_str(address)
+                .map_err(|_| BootstrapError::InvalidAddress(address.to_string()))?,
+            false => return Err(BootstrapError::InvalidAddress(address.to_string())),
+        };
+
+        if voter.is_zero() {
+            return Err(BootstrapError::ZeroAddress);
+        }
+        if voters.contains(&voter) {
+            return Err(BootstrapError::Duplicate(voter));
+        }
+        voters.push(voter);
+    }
+
+    match voters.is_empty() {
+        true => Err(BootstrapError::Empty),
+        false => Ok(voters),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addresses(addresses: &[&str]) -> Vec<String> {
+        addresses.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn bootstrap_parse_voters() {
+        let voters = parse_voters(&addresses(&[
+            "0x3B9705F0EF88Ee74B9924e34A5Af578d2E24F300",
+            " 0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56",
+        ]))
+        .unwrap();
+        assert_eq!(voters[0], authorized_voters()[0]);
+        assert_eq!(voters.len(), 2);
+
+        let res = parse_voters(&addresses(&["3B9705F0EF88Ee74B9924e34A5Af578d2E24F300"]));
+        assert!(matches!(res, Err(BootstrapError::InvalidAddress(_))));
+
+        let res = parse_voters(&addresses(&["0x3B9705F0EF88Ee74B9924e34A5Af578d2E24F3zz"]));
+        assert!(matches!(res, Err(BootstrapError::InvalidAddress(_))));
+
+        let res = parse_voters(&addresses(&["0x0000000000000000000000000000000000000000"]));
+        assert!(matches!(res, Err(BootstrapError::ZeroAddress)));
+
+        let res = parse_voters(&addresses(&[
+            "0x3B9705F0EF88Ee74B9924e34A5Af578d2E24F300",
+            "0x3b9705f0ef88ee74b9924e34a5af578d2e24f300",
+        ]));
+        assert!(matches!(res, Err(BootstrapError::Duplicate(_))));
+
+        assert!(matches!(parse_voters(&[]), Err(BootstrapError::Empty)));
+    }
+
+    #[test]
+    fn bootstrap_load_file() {
+        let path = std::env::temp_dir().join("fip-voting-test-authorized-voters.json");
+
+        // Only invalid files are loaded, a valid one would replace the starters of other tests
+        fs::write(&path, r#"{"voters": []}"#).unwrap();
+        let res = load_authorized_voters(&[], Some(&path));
+        assert!(matches!(res, Err(BootstrapError::Parse(_, _))));
+
+        fs::write(
+            &path,
+            r#"["0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56", "f01234"]"#,
+        )
+        .unwrap();
+        let res = load_authorized_voters(&[], Some(&path));
+        assert!(matches!(res, Err(BootstrapError::InvalidAddress(_))));
+
+        fs::remove_file(&path).unwrap();
+        let res = load_authorized_voters(&[], Some(&path));
+        assert!(matches!(res, Err(BootstrapError::Io(_, _))));
+
+        // Nothing configured keeps the built in starters
+        assert_eq!(
+            load_authorized_voters(&[], None).unwrap().len(),
+            DEFAULT_AUTHORIZED_VOTERS.len()
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time,
+};
+
+/// Timestamp the clock is frozen at, 0 when the clock is running
+#[cfg(feature = "e2e")]
+static FROZEN: AtomicU64 = AtomicU64::new(0);
+
+/// Current unix timestamp in seconds, read through [`SystemClock`]
+///
+/// With the `e2e` feature this returns the frozen timestamp when one is set
+fn now() -> u64 {
+    #[cfg(feature = "e2e")]
+    {
+        let frozen = FROZEN.load(Ordering::SeqCst);
+        if frozen != 0 {
+            return frozen;
+        }
+    }
+
+    time::SystemTime::now()
+        .duration_since(time::UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+/// Freezes the clock at the timestamp, or unfreezes it when `None`
+#[cfg(feature = "e2e")]
+pub fn freeze(timestamp: Option<u64>) {
+    FROZEN.store(timestamp.unwrap_or(0), Ordering::SeqCst);
+}
+
+/// Where the store and the background workers read the time from
+pub trait Clock: Send + Sync {
+    /// Current unix timestamp in seconds
+    fn now(&self) -> u64;
+}
+
+/// The system time, see [`now`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        now()
+    }
+}
+
+/// A clock that only moves when told to, so tests can pass the end of a vote
+/// or the expiry of a registration without waiting for it
+#[derive(Debug, Default)]
+pub struct MockClock(AtomicU64);
+
+impl MockClock {
+    pub fn new(timestamp: u64) -> Self {
+        Self(AtomicU64::new(timestamp))
+    }
+
+    /// Starts at the current system time
+    pub fn from_system() -> Self {
+        Self::new(now())
+    }
+
+    pub fn set(&self, timestamp: u64) {
+        self.0.store(timestamp, Ordering::SeqCst);
+    }
+
+    /// Moves the clock forward by `secs` seconds
+    pub fn advance(&self, secs: u64) {
+        self.0.fetch_add(secs, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_mock_clock() {
+        let clock = MockClock::new(100);
+        assert_eq!(clock.now(), 100);
+
+        clock.advance(60);
+        assert_eq!(clock.now(), 160);
+
+        clock.set(42);
+        assert_eq!(clock.now(), 42);
+    }
+
+    #[cfg(feature = "e2e")]
+    #[test]
+    fn clock_freeze() {
+        freeze(Some(42));
+        assert_eq!(now(), 42);
+
+        freeze(None);
+        assert!(now() > 42);
+    }
+}
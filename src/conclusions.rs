@@ -0,0 +1,120 @@
+//! Stores the results of votes as they conclude
+//!
+//! A vote concludes on its own once its voting period and grace period are
+//! over, with no request to store its results. Votes whose results aren't
+//! stored yet are looked for every few seconds and concluded under their lock,
+//! so the results are fixed when the vote concludes rather than on whichever
+//! read comes first
+use std::time::Duration;
+
+use crate::{
+    fip::FipNumber,
+    redis::Redis,
+    storage::Network,
+    store::{StoreError, VoteStore},
+    Args,
+};
+
+/// How often votes are checked for having concluded
+const CONCLUDE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Concludes votes whose voting period is over
+pub struct Concluder {
+    config: Args,
+}
+
+impl Concluder {
+    pub fn from_args(args: &Args) -> Self {
+        Self {
+            config: args.clone(),
+        }
+    }
+
+    /// Concludes votes every interval until the server stops
+    pub fn spawn(self) {
+        actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(CONCLUDE_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.conclude().await {
+                    println!("Error concluding votes: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Stores the results of every concluded vote that has none yet,
+    /// returning the votes that were concluded
+    pub async fn conclude(&self) -> Result<Vec<(FipNumber, Network)>, StoreError> {
+        let mut redis = Redis::new(self.config.redis_path())?;
+        redis.set_grace_period(self.config.vote_grace_period());
+        conclude_stale(&mut redis, self.config.vote_length()).await
+    }
+}
+
+/// Stores the results of every concluded vote of the store that has none yet
+pub async fn conclude_stale(
+    redis: &mut impl VoteStore,
+    vote_length: u64,
+) -> Result<Vec<(FipNumber, Network)>, StoreError> {
+    let mut concluded = Vec::new();
+    for ntw in Network::all() {
+        for fip_number in redis.stale_votes(ntw, vote_length)? {
+            if redis
+                .conclude_vote(fip_number, vote_length, ntw)
+                .await?
+                .is_some()
+            {
+                println!("Vote concluded for FIP: {} on {}", fip_number, ntw.name());
+                concluded.push((fip_number, ntw));
+            }
+        }
+    }
+
+    Ok(concluded)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{authorized_voters, clock::MockClock, redis::VoteStatus, test_redis::TestRedis};
+
+    #[tokio::test]
+    async fn conclusions_conclude_stale() {
+        let mut redis = TestRedis::start();
+        let clock = Arc::new(MockClock::from_system());
+        redis.set_clock(clock.clone());
+
+        let ntw = Network::Testnet;
+        let fip = FipNumber::try_from(5u32).unwrap();
+        let vote_length = 60u64;
+        redis
+            .start_vote(fip, authorized_voters()[0], ntw)
+            .await
+            .unwrap();
+
+        assert!(conclude_stale(&mut *redis, vote_length)
+            .await
+            .unwrap()
+            .is_empty());
+
+        clock.advance(vote_length + 1);
+        assert_eq!(
+            redis.vote_status(fip, vote_length, ntw).unwrap(),
+            VoteStatus::Concluded
+        );
+        assert_eq!(
+            conclude_stale(&mut *redis, vote_length).await.unwrap(),
+            vec![(fip, ntw)]
+        );
+
+        // Stored results aren't concluded again
+        assert!(conclude_stale(&mut *redis, vote_length)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(redis.stale_votes(ntw, vote_length).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+use actix_cors::Cors;
+use url::Url;
+
+use crate::Args;
+
+/// Rate limit headers frontends may read from responses
+const EXPOSED_HEADERS: [&str; 4] = [
+    "ratelimit-limit",
+    "ratelimit-remaining",
+    "ratelimit-reset",
+    "retry-after",
+];
+
+/// Seconds browsers may cache the answer to a preflight request
+const MAX_AGE: usize = 3600;
+
+/// CORS middleware allowing the origins given with `--allowed-origins`
+///
+/// Every origin is allowed with `--cors-allow-all` and none when neither is
+/// set, in which case the API can only be called from the same origin or
+/// outside of a browser
+pub fn from_args(args: &Args) -> Cors {
+    let cors = match args.cors_allow_all() {
+        true => Cors::default().allow_any_origin(),
+        false => args
+            .allowed_origins()
+            .iter()
+            .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin)),
+    };
+
+    cors.allow_any_method()
+        .allow_any_header()
+        .expose_headers(EXPOSED_HEADERS)
+        .max_age(MAX_AGE)
+}
+
+/// Parses an origin such as `https://sp-vote.com`, a scheme and host with an optional port
+///
+/// Origins are checked when the arguments are parsed since the middleware
+/// panics on a malformed one
+pub fn parse_origin(origin: &str) -> Result<String, String> {
+    let url = match Url::parse(origin) {
+        Ok(url) => url,
+        Err(e) => return Err(format!("Invalid origin {}: {}", origin, e)),
+    };
+
+    let bare = url.path() == "/"
+        && url.query().is_none()
+        && url.fragment().is_none()
+        && url.username().is_empty()
+        && url.password().is_none();
+    match url.origin().is_tuple() && bare {
+        true => Ok(url.origin().ascii_serialization()),
+        false => Err(format!(
+            "Invalid origin {}: expected a scheme and host without a path",
+            origin
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cors_parse_origin() {
+        for (origin, parsed) in [
+            ("https://sp-vote.com", "https://sp-vote.com"),
+            ("https://sp-vote.com/", "https://sp-vote.com"),
+            ("http://localhost:3000", "http://localhost:3000"),
+            ("HTTPS://SP-Vote.com", "https://sp-vote.com"),
+            // The default port is left out of the origin browsers send
+            ("https://sp-vote.com:443", "https://sp-vote.com"),
+        ] {
+            assert_eq!(parse_origin(origin).unwrap(), parsed);
+        }
+
+        for origin in [
+            "sp-vote.com",
+            "*",
+            "https://sp-vote.com/vote",
+            "https://sp-vote.com?fip=1",
+            "file:///index.html",
+        ] {
+            assert!(parse_origin(origin).is_err(), "{}", origin);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Checks the configuration and everything the server connects to without serving
+//!
+//! `filecoin-vote doctor` runs every check and prints a report, so a deploy can be
+//! checked before it fails at startup or on the first request that needs what
+//! is misconfigured
+use std::{fmt, time::Duration};
+
+use redis::RedisError;
+
+use crate::{
+    bootstrap::load_authorized_voters,
+    clock::{Clock, SystemClock},
+    redis::{Redis, SCHEMA_VERSION},
+    storage::{fetch_chain_height, load_networks, Network},
+    tls, Args,
+};
+
+/// Days left on the TLS certificate below which it is reported for renewal
+const CERT_RENEWAL_DAYS: u64 = 14;
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Ok,
+    /// The server starts, but something needs looking at soon
+    Warning,
+    /// The server won't start or can't answer some requests
+    Failed,
+}
+
+#[derive(Debug)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+}
+
+/// Outcome of every check, in the order they were run
+#[derive(Debug, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    fn push(&mut self, name: impl Into<String>, (status, detail): (Status, String)) {
+        self.checks.push(Check {
+            name: name.into(),
+            status,
+            detail,
+        });
+    }
+
+    /// Whether no check failed, warnings don't keep the server from starting
+    pub fn passed(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.status != Status::Failed)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let status = match check.status {
+                Status::Ok => "OK",
+                Status::Warning => "WARN",
+                Status::Failed => "FAIL",
+            };
+            writeln!(f, "[{}] {}: {}", status, check.name, check.detail)?;
+        }
+
+        let failed = self
+            .checks
+            .iter()
+            .filter(|check| check.status == Status::Failed)
+            .count();
+        match failed {
+            0 => write!(f, "Every check passed"),
+            failed => write!(f, "{} of {} checks failed", failed, self.checks.len()),
+        }
+    }
+}
+
+/// Runs every check against the configuration in `args`
+pub async fn run(args: &Args) -> Report {
+    let mut report = Report::default();
+
+    report.push(
+        "serve address",
+        match args.listen_addresses() {
+            Ok(addresses) => (Status::Ok, format!("listening on {}", addresses.join(", "))),
+            Err(e) => (Status::Failed, e.to_string()),
+        },
+    );
+
+    // Networks have to be known before their nodes can be asked
+    if let Some(path) = args.networks_file() {
+        report.push(
+            "networks",
+            match load_networks(&path) {
+                Ok(ntws) => {
+                    let names = ntws.iter().map(|ntw| ntw.name()).collect::<Vec<&str>>();
+                    (Status::Ok, format!("configured {}", names.join(", ")))
+                }
+                Err(e) => (Status::Failed, e.to_string()),
+            },
+        );
+    }
+
+    let voters = args.authorized_voters();
+    report.push(
+        "authorized voters",
+        match load_authorized_voters(&voters, args.authorized_voters_file().as_deref()) {
+            Ok(voters) => (Status::Ok, format!("{} vote starters", voters.len())),
+            Err(e) => (Status::Failed, e.to_string()),
+        },
+    );
+
+    report.push("redis", schema_status(Redis::new(args.redis_path())));
+    if let Some(replica) = args.redis_replica_path() {
+        report.push("redis replica", schema_status(Redis::replica(replica)));
+    }
+
+    for ntw in Network::all() {
+        let status = chain_status(ntw, args.rpc_timeout()).await;
+        report.push(format!("{} rpc", ntw.name()), status);
+    }
+
+    if args.serves_https() {
+        report.push(
+            "tls certificate",
+            match tls::certificate_expiry(&args.tls_cert(), &args.tls_key()) {
+                Ok(expires_at) => certificate_status(expires_at, SystemClock.now()),
+                Err(e) => (Status::Failed, e.to_string()),
+            },
+        );
+    }
+
+    report
+}
+
+/// Whether the database answers and can be migrated to the layout this version reads
+fn schema_status(redis: Result<Redis, RedisError>) -> (Status, String) {
+    let version = match redis.and_then(|mut redis| redis.schema_version()) {
+        Ok(version) => version,
+        Err(e) => return (Status::Failed, e.to_string()),
+    };
+
+    match version {
+        version if version > SCHEMA_VERSION => (
+            Status::Failed,
+            format!(
+                "schema version {} was written by a newer version, which reads {}",
+                version, SCHEMA_VERSION
+            ),
+        ),
+        version if version < SCHEMA_VERSION => (
+            Status::Warning,
+            format!(
+                "schema version {} is migrated to {} at startup",
+                version, SCHEMA_VERSION
+            ),
+        ),
+        version => (Status::Ok, format!("schema version {}", version)),
+    }
+}
+
+/// Whether the node of the network answers within `rpc_timeout` seconds, 0 waits for it
+async fn chain_status(ntw: Network, rpc_timeout: u64) -> (Status, String) {
+    let height = fetch_chain_height(ntw);
+    let height = match rpc_timeout {
+        0 => height.await,
+        secs => match tokio::time::timeout(Duration::from_secs(secs), height).await {
+            Ok(height) => height,
+            Err(_) => {
+                return (
+                    Status::Failed,
+                    format!("{} didn't answer within {} seconds", ntw.rpc(), secs),
+                )
+            }
+        },
+    };
+
+    match height {
+        Ok(height) => (Status::Ok, format!("{} at height {}", ntw.rpc(), height)),
+        Err(e) => (Status::Failed, format!("{}: {}", ntw.rpc(), e)),
+    }
+}
+
+fn certificate_status(expires_at: u64, now: u64) -> (Status, String) {
+    if expires_at <= now {
+        let days = (now - expires_at) / SECS_PER_DAY;
+        return (Status::Failed, format!("expired {} days ago", days));
+    }
+
+    match (expires_at - now) / SECS_PER_DAY {
+        days if days < CERT_RENEWAL_DAYS => (
+            Status::Warning,
+            format!("expires in {} days, renew it soon", days),
+        ),
+        days => (Status::Ok, format!("expires in {} days", days)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doctor_certificate_status() {
+        let now = 1_700_000_000;
+
+        assert_eq!(
+            certificate_status(now + 90 * SECS_PER_DAY, now),
+            (Status::Ok, "expires in 90 days".to_string())
+        );
+        assert_eq!(
+            certificate_status(now + 3 * SECS_PER_DAY, now).0,
+            Status::Warning
+        );
+        assert_eq!(
+            certificate_status(now - 2 * SECS_PER_DAY, now),
+            (Status::Failed, "expired 2 days ago".to_string())
+        );
+        assert_eq!(certificate_status(now, now).0, Status::Failed);
+    }
+
+    #[test]
+    fn doctor_report() {
+        let mut report = Report::default();
+        report.push("redis", (Status::Ok, "schema version 5".to_string()));
+        report.push(
+            "tls certificate",
+            (Status::Warning, "expires in 3 days".to_string()),
+        );
+        assert!(report.passed());
+
+        report.push("mainnet rpc", (Status::Failed, "no result".to_string()));
+        assert!(!report.passed());
+        assert_eq!(
+            report.to_string(),
+            "[OK] redis: schema version 5\n\
+             [WARN] tls certificate: expires in 3 days\n\
+             [FAIL] mainnet rpc: no result\n\
+             1 of 3 checks failed"
+        );
+    }
+}
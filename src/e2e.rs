@@ -0,0 +1,94 @@
+//! Test-only endpoints for the frontend end-to-end suites
+//!
+//! Only compiled with the `e2e` feature, never enable it for a deployed server
+
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::Deserialize;
+
+use crate::{
+    authorized_voters, clock,
+    errors::*,
+    storage::{clear_power_fixtures, set_power_fixture, Network},
+    store::{StoreFactory, VoteStore},
+};
+
+#[derive(Deserialize, Debug)]
+pub struct ClockFreeze {
+    /// Unix timestamp in seconds, `null` lets the clock run again
+    timestamp: Option<u64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PowerFixture {
+    network: Network,
+    sp_id: u32,
+    power: u128,
+}
+
+/// Registers the e2e endpoints under `/e2e`, resetting the stores `F` opens
+pub fn scope<F: StoreFactory>() -> actix_web::Scope {
+    web::scope("/e2e")
+        .route("/reset", web::post().to(reset::<F>))
+        .service(freeze_clock)
+        .service(inject_power)
+}
+
+/// Wipes the database, clears fixtures and unfreezes the clock
+async fn reset<F: StoreFactory>(store: web::Data<F>) -> impl Responder {
+    println!("E2E reset requested");
+
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    if let Err(e) = redis.flush_all() {
+        let res = format!("{}: {}", E2E_RESET_ERROR, e);
+        println!("{}", res);
+        return HttpResponse::InternalServerError().body(res);
+    }
+
+    // Restore the starting vote starters and admins the same way the server does on boot
+    for ntw in Network::all() {
+        for voter in authorized_voters() {
+            let restored = redis
+                .register_voter_starter(voter, ntw)
+                .and_then(|_| redis.register_admin(voter, ntw));
+            if let Err(e) = restored {
+                let res = format!("{}: {}", E2E_RESET_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::InternalServerError().body(res);
+            }
+        }
+    }
+
+    clear_power_fixtures();
+    clock::freeze(None);
+
+    HttpResponse::Ok().finish()
+}
+
+#[post("/clock")]
+async fn freeze_clock(body: web::Json<ClockFreeze>) -> impl Responder {
+    println!("E2E clock frozen at {:?}", body.timestamp);
+
+    clock::freeze(body.timestamp);
+
+    HttpResponse::Ok().finish()
+}
+
+#[post("/power")]
+async fn inject_power(fixtures: web::Json<Vec<PowerFixture>>) -> impl Responder {
+    for fixture in fixtures.iter() {
+        set_power_fixture(fixture.sp_id, fixture.network, fixture.power);
+    }
+
+    println!("E2E injected {} power fixtures", fixtures.len());
+
+    HttpResponse::Ok().finish()
+}
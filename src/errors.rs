@@ -6,14 +6,81 @@ pub const VOTE_RESULTS_ERROR: &str = "Error getting vote results";
 pub const VOTE_DESERIALIZE_ERROR: &str = "Error deserializing vote";
 pub const VOTE_RECOVER_ERROR: &str = "Error recovering vote";
 pub const VOTE_ADD_ERROR: &str = "Error adding vote";
+pub const FIP_MISMATCH_ERROR: &str = "Vote does not match requested FIP";
+pub const FIP_NOT_FOUND_ERROR: &str = "FIP does not exist";
+pub const FIP_LOOKUP_ERROR: &str = "Error looking up FIP";
 
 pub const VOTER_AUTH_DESERIALIZE_ERROR: &str = "Error deserializing voter authorization";
 pub const VOTER_AUTH_RECOVER_ERROR: &str = "Error recovering voter authorization";
 pub const VOTER_NOT_AUTHORIZED_ERROR: &str = "Voter not authorized to add new signers";
 pub const VOTER_AUTH_ERROR: &str = "Error getting voter authorization";
 pub const VOTER_DELEGATES_ERROR: &str = "Error getting voter delegates";
+pub const VOTER_HISTORY_ERROR: &str = "Error getting voter history";
+pub const REGISTERED_VOTERS_ERROR: &str = "Error getting registered voters";
+pub const VOTER_UNREGISTER_ERROR: &str = "Error unregistering voter";
+pub const REGISTRATION_EXPIRED_ERROR: &str = "Voter registration has expired";
+pub const RENEWAL_DESERIALIZE_ERROR: &str = "Error deserializing registration renewal";
+pub const REGISTRATION_QUEUE_ERROR: &str = "Error queueing voter registration";
+pub const REGISTRATION_STATUS_ERROR: &str = "Error getting registration status";
+pub const DELEGATION_OFFERS_ERROR: &str = "Error getting delegation offers";
+pub const DELEGATION_OFFER_NOT_FOUND: &str =
+    "No delegation was offered to the voter by that address";
+pub const ACCEPTANCE_RECOVER_ERROR: &str = "Error recovering delegation acceptance";
+pub const BULK_REGISTRATION_SIZE_ERROR: &str = "Too many registrations";
+pub const RENEWAL_RECOVER_ERROR: &str = "Error recovering registration renewal";
+pub const RENEWAL_STALE_ERROR: &str = "Registration renewal was not signed recently";
+pub const RENEWAL_ERROR: &str = "Error renewing voter registration";
+
+pub const NONCE_ERROR: &str = "Error issuing nonce";
+pub const MESSAGE_TEMPLATE_ERROR: &str = "Invalid message template request";
+pub const NONCE_REQUIRED_ERROR: &str = "Signed message has no nonce";
+pub const NONCE_INVALID_ERROR: &str = "Nonce was not issued, has expired or was already used";
+pub const NONCE_CHECK_ERROR: &str = "Error checking nonce";
+pub const MESSAGE_EXPIRED_ERROR: &str = "Signed message has expired";
+pub const CHAIN_ID_MISMATCH_ERROR: &str = "Typed data was signed for another network";
+pub const DOMAIN_ERROR: &str = "Message was not signed for this service and network";
+pub const VOTE_STARTER_REMOVE_ERROR: &str = "Error removing vote starter";
+
+pub const ROLES_ERROR: &str = "Error getting roles";
+pub const ROLE_GRANT_DESERIALIZE_ERROR: &str = "Error deserializing role grant";
+pub const ROLE_GRANT_RECOVER_ERROR: &str = "Error recovering role grant";
+pub const ROLE_CHANGE_ERROR: &str = "Error changing role";
+pub const NOT_ADMIN_ERROR: &str = "Signer is not an admin";
+pub const VOTE_FLUSH_DESERIALIZE_ERROR: &str = "Error deserializing vote flush";
+pub const VOTE_FLUSH_RECOVER_ERROR: &str = "Error recovering vote flush";
+pub const VOTE_FLUSH_ERROR: &str = "Error flushing vote";
+
+pub const CLASS_REGISTRATION_DESERIALIZE_ERROR: &str = "Error deserializing class registration";
+pub const CLASS_REGISTRATION_RECOVER_ERROR: &str = "Error recovering class registration";
+pub const CLASS_REGISTRATION_ERROR: &str = "Error registering voter class";
+pub const TOKEN_HOLDER_BALANCE_ERROR: &str = "Token holders need a FIL balance to register";
+pub const FIL_BALANCE_ERROR: &str = "Error fetching FIL balance";
+
+pub const UNREGISTRATION_DESERIALIZE_ERROR: &str = "Error deserializing unregistration";
+pub const UNREGISTRATION_RECOVER_ERROR: &str = "Error recovering unregistration";
+pub const DELEGATE_REMOVAL_RECOVER_ERROR: &str = "Error recovering delegate removal";
+pub const DELEGATE_NOT_FOUND: &str = "Storage provider is not delegated to a voter";
 
 pub const VOTE_START_ERROR: &str = "Error starting vote";
+pub const VOTE_END_DESERIALIZE_ERROR: &str = "Error deserializing vote end";
+pub const VOTE_END_ERROR: &str = "Error ending vote";
+pub const VOTE_CANCELLED: &str = "Vote was cancelled";
+pub const VOTE_STATUS_UNKNOWN: &str = "Vote was ended by a newer version of the server";
+pub const VOTE_EXTENSION_DESERIALIZE_ERROR: &str = "Error deserializing vote extension";
+pub const VOTE_EXTENSION_ERROR: &str = "Error extending vote";
+
+pub const VOTE_METADATA_ERROR: &str = "Error getting vote metadata";
+pub const FIP_INFO_ERROR: &str = "Error getting FIP info";
+pub const VOTE_METADATA_DESERIALIZE_ERROR: &str = "Error deserializing vote metadata";
+
+pub const VOTE_OPTIONS_ERROR: &str = "Error getting ballot options";
+pub const VOTE_OPTIONS_INVALID_ERROR: &str = "Invalid ballot options";
+pub const VOTE_QUORUM_INVALID_ERROR: &str = "Invalid quorum rules";
+pub const VOTE_WEIGHTING_INVALID_ERROR: &str = "Invalid weighting";
+pub const VOTE_OPENS_AT_INVALID_ERROR: &str = "Invalid opening time";
+pub const VOTE_METADATA_INVALID_ERROR: &str = "Invalid vote metadata";
+pub const VOTE_OPTIONS_ROUND_ERROR: &str =
+    "Later rounds use the options, quorum rules, weighting and minimum power of the first round and open right away";
 
 pub const VOTE_EXISTS_ERROR: &str = "Error checking if vote exists";
 
@@ -29,8 +96,40 @@ pub const ACTIVE_VOTES_ERROR: &str = "Error getting active votes";
 pub const VOTE_IS_ALREADY_STARTED: &str = "Vote is already started";
 pub const VOTE_ALREADY_EXISTS: &str = "Vote already exists";
 pub const CONCLUDED_VOTES_ERROR: &str = "Error getting concluded votes";
+pub const STALE_VOTES_ERROR: &str = "Error getting stale votes";
+pub const SILENCE_REPORT_ERROR: &str = "Error getting silence report";
+pub const SILENCE_REPORT_PENDING: &str = "Silence report is not stored yet";
+pub const TURNOUT_ERROR: &str = "Error getting vote turnout";
 
 pub const VOTER_NOT_REGISTERED_NETWORK: &str = "Voter is not registered for this network";
+pub const BALLOT_NETWORK_ERROR: &str = "Ballot matches votes on several networks";
+pub const VOTE_NOT_FOUND_ERROR: &str = "Vote does not exist";
+pub const VOTE_CONCLUDED_ERROR: &str = "Vote has concluded";
+pub const VOTE_NOT_OPEN_ERROR: &str = "Vote has not opened yet";
+
+pub const ADMIN_DISABLED_ERROR: &str = "Admin API is disabled";
+pub const ADMIN_UNAUTHORIZED_ERROR: &str = "Invalid admin token";
+pub const VOTE_DELETE_ERROR: &str = "Error deleting vote";
+pub const VOTE_CONCLUDE_ERROR: &str = "Error concluding vote";
+pub const VOTE_NOT_ENDED_ERROR: &str = "Vote has not ended";
+pub const REGISTRATIONS_ERROR: &str = "Error getting voter registrations";
+pub const REGISTRATIONS_RESTORE_ERROR: &str = "Error restoring voter registrations";
+
+pub const VOTE_RECEIPT_ERROR: &str = "Error getting vote receipt";
+pub const VOTE_AUDIT_ERROR: &str = "Error getting vote audit trail";
+pub const VOTE_BREAKDOWN_ERROR: &str = "Error getting vote breakdown";
+pub const RESULTS_VERSION_ERROR: &str = "Error getting results version";
+
+pub const ATTESTATION_DISABLED_ERROR: &str = "Results attestation is disabled";
+pub const ATTESTATION_ERROR: &str = "Error attesting vote results";
+
+pub const RATE_LIMITED_ERROR: &str = "Rate limit exceeded";
+pub const CHAIN_TIMEOUT_ERROR: &str = "Timed out waiting for the Filecoin RPC";
+
+pub const PAYLOAD_TOO_LARGE_ERROR: &str = "Request body is too large";
+pub const CONTENT_TYPE_ERROR: &str = "Request body must be sent as application/json";
+
+pub const E2E_RESET_ERROR: &str = "Error resetting state";
 
-pub const INVALID_NETWORK: &str = "Voter is not registered for this network";
 pub const INVALID_ADDRESS: &str = "Invalid address";
+pub const INVALID_HEIGHT: &str = "No tipset at height";
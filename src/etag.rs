@@ -0,0 +1,117 @@
+//! Entity tags of the large results responses, so clients polling them get a
+//! 304 while nothing they are built from changed
+//!
+//! Tags are computed from the results version of the database rather than the
+//! body, so an unchanged response is answered without being built
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use actix_web::{
+    http::header::{self, EntityTag, Header, HeaderValue, IfNoneMatch},
+    HttpRequest, HttpResponse,
+};
+
+/// Request headers that pick the representation of a tagged response
+const VARY: &str = "Accept, Accept-Language";
+
+/// Weak tag of the response to `req` built from the database at `version`
+///
+/// `parts` holds what the response depends on that can change without the
+/// version, such as the votes that concluded as time passed. Tags are weak
+/// since the response is sent compressed or not depending on the client
+pub fn entity_tag(req: &HttpRequest, version: u64, parts: &impl Hash) -> EntityTag {
+    let mut hasher = DefaultHasher::new();
+    for name in [header::ACCEPT, header::ACCEPT_LANGUAGE] {
+        for value in req.headers().get_all(name) {
+            value.as_bytes().hash(&mut hasher);
+        }
+    }
+    parts.hash(&mut hasher);
+
+    EntityTag::new_weak(format!("{}-{:016x}", version, hasher.finish()))
+}
+
+/// A 304 when the `If-None-Match` of the request holds `tag`
+pub fn not_modified(req: &HttpRequest, tag: &EntityTag) -> Option<HttpResponse> {
+    let matched = match IfNoneMatch::parse(req) {
+        Ok(IfNoneMatch::Any) => true,
+        Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|t| t.weak_eq(tag)),
+        Err(_) => false,
+    };
+
+    matched.then(|| tagged(HttpResponse::NotModified().finish(), tag))
+}
+
+/// Adds `tag` and the headers it varies by to the response
+pub fn tagged(mut res: HttpResponse, tag: &EntityTag) -> HttpResponse {
+    let headers = res.headers_mut();
+    headers.insert(
+        header::ETAG,
+        HeaderValue::from_str(&tag.to_string()).unwrap(),
+    );
+    headers.insert(header::VARY, HeaderValue::from_static(VARY));
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test::TestRequest};
+
+    use super::*;
+
+    #[test]
+    fn etag_entity_tag() {
+        let req = TestRequest::default().to_http_request();
+        let tag = entity_tag(&req, 7, &[1, 2]);
+
+        assert!(tag.weak);
+        assert!(tag.tag().starts_with("7-"));
+        assert_eq!(entity_tag(&req, 7, &[1, 2]), tag);
+        assert_ne!(entity_tag(&req, 8, &[1, 2]).tag(), tag.tag());
+        assert_ne!(entity_tag(&req, 7, &[1, 2, 3]).tag(), tag.tag());
+
+        // The versioned shapes and other languages are tagged apart
+        let v1 = TestRequest::default()
+            .insert_header((header::ACCEPT, "application/vnd.fipvote.v1+json"))
+            .to_http_request();
+        assert_ne!(entity_tag(&v1, 7, &[1, 2]).tag(), tag.tag());
+        let french = TestRequest::default()
+            .insert_header((header::ACCEPT_LANGUAGE, "fr"))
+            .to_http_request();
+        assert_ne!(entity_tag(&french, 7, &[1, 2]).tag(), tag.tag());
+    }
+
+    #[test]
+    fn etag_not_modified() {
+        let tag = EntityTag::new_weak("7-00000000000000ff".to_string());
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, "\"1-0\", W/\"7-00000000000000ff\""))
+            .to_http_request();
+        let res = not_modified(&req, &tag).unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(
+            res.headers().get(header::ETAG).unwrap(),
+            "W/\"7-00000000000000ff\""
+        );
+
+        // A strong tag of the same value still matches
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, "\"7-00000000000000ff\""))
+            .to_http_request();
+        assert!(not_modified(&req, &tag).is_some());
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, "*"))
+            .to_http_request();
+        assert!(not_modified(&req, &tag).is_some());
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, "W/\"6-00000000000000ff\""))
+            .to_http_request();
+        assert!(not_modified(&req, &tag).is_none());
+        assert!(not_modified(&TestRequest::default().to_http_request(), &tag).is_none());
+    }
+}
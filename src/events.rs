@@ -0,0 +1,550 @@
+//! Append-only log of every change made to the database
+//!
+//! The store records each registration, ballot, vote start and conclusion as
+//! an [`Event`] in the same transaction as the change, stamped with the time
+//! it was made at, so the log never misses a change or holds one that failed.
+//! Ballots are logged with the signed message, the signature and the weight
+//! they were counted with, so replaying the log rebuilds the database exactly
+//! without asking the chain again
+use std::{collections::BTreeMap, sync::Arc};
+
+use ethers::types::{Address, H256};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::MockClock,
+    fip::FipNumber,
+    hot_cache::Invalidation,
+    messages::{
+        class_registration::VoterClass, vote_metadata::VoteMetadata, vote_start::QuorumRules,
+        votes::Vote,
+    },
+    notifier::Announcement,
+    redis::{EarlyEnd, VoteReceipt, VoteResults},
+    registrations::DelegationOffer,
+    storage::Network,
+    store::{StoreError, VoteStore},
+    tally::Weighting,
+};
+
+/// Events read from the log at a time while replaying
+const REPLAY_BATCH: usize = 500;
+
+/// A ballot with the weight it is counted with, once it was fetched from the chain
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RecordedBallot {
+    pub fip_number: FipNumber,
+    pub ntw: Network,
+    pub voter: Address,
+    pub class: VoterClass,
+    /// The signed ballot, holding the power of each storage provider it was cast for
+    pub vote: Vote,
+    /// Raw byte power the ballot is counted with, 0 unless cast by a storage provider
+    pub storage: u128,
+    /// Balance in attoFIL the ballot is counted with, 0 unless cast by a token holder
+    pub fil: u128,
+}
+
+/// A change to the database, named after the operation of [`VoteStore`] that made it
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    VoteStarted {
+        fip_number: FipNumber,
+        ntw: Network,
+        signer: Address,
+        /// When a vote scheduled to open later opens for ballots
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        opens_at: Option<u64>,
+    },
+    RoundStarted {
+        fip_number: FipNumber,
+        ntw: Network,
+        signer: Address,
+        round: u64,
+        vote_length: u64,
+    },
+    VoteImported {
+        fip_number: FipNumber,
+        ntw: Network,
+        started_at: u64,
+        ballots: Vec<Vote>,
+        results: VoteResults,
+        metadata: BTreeMap<String, VoteMetadata>,
+    },
+    VoterRegistered {
+        voter: Address,
+        ntw: Network,
+        sp_ids: Vec<u32>,
+    },
+    VoterClassRegistered {
+        voter: Address,
+        ntw: Network,
+        class: VoterClass,
+    },
+    VoterUnregistered {
+        voter: Address,
+        ntw: Network,
+    },
+    StorageProvidersUnregistered {
+        voter: Address,
+        ntw: Network,
+        sp_ids: Vec<u32>,
+    },
+    /// The voter was unregistered from every network
+    VoterRemoved {
+        voter: Address,
+    },
+    StarterRegistered {
+        voter: Address,
+        ntw: Network,
+    },
+    StarterRemoved {
+        voter: Address,
+        ntw: Network,
+    },
+    AdminRegistered {
+        voter: Address,
+        ntw: Network,
+    },
+    AdminRemoved {
+        voter: Address,
+        ntw: Network,
+    },
+    WorkerChanged {
+        voter: Address,
+        ntw: Network,
+        worker_address: Option<String>,
+    },
+    ExpiryChanged {
+        voter: Address,
+        ntw: Network,
+        expires_at: Option<u64>,
+    },
+    DelegationOffered {
+        voter: Address,
+        ntw: Network,
+        offer: DelegationOffer,
+    },
+    /// The voter accepted the offer of the worker or multisig address
+    DelegationTaken {
+        voter: Address,
+        ntw: Network,
+        worker_address: String,
+    },
+    BallotRecorded {
+        ballot: RecordedBallot,
+        allow_revote: bool,
+        receipt: Option<VoteReceipt>,
+    },
+    ResultsPublished {
+        fip_number: FipNumber,
+        ntw: Network,
+        tx_hash: H256,
+    },
+    VoteAnnounced {
+        fip_number: FipNumber,
+        ntw: Network,
+        announcement: Announcement,
+    },
+    MetadataChanged {
+        fip_number: FipNumber,
+        ntw: Network,
+        language: String,
+        metadata: VoteMetadata,
+    },
+    OptionsChanged {
+        fip_number: FipNumber,
+        ntw: Network,
+        options: Vec<String>,
+    },
+    QuorumChanged {
+        fip_number: FipNumber,
+        ntw: Network,
+        rules: Option<QuorumRules>,
+    },
+    WeightingChanged {
+        fip_number: FipNumber,
+        ntw: Network,
+        weighting: Weighting,
+    },
+    MinPowerChanged {
+        fip_number: FipNumber,
+        ntw: Network,
+        min_power: u128,
+    },
+    /// The round was ended early, or its early end was undone when `ended` is `None`
+    EndingChanged {
+        fip_number: FipNumber,
+        ntw: Network,
+        ended: Option<EarlyEnd>,
+    },
+    ExtensionChanged {
+        fip_number: FipNumber,
+        ntw: Network,
+        extension: u64,
+    },
+    BallotsFlushed {
+        fip_number: FipNumber,
+        ntw: Network,
+    },
+    VoteDeleted {
+        fip_number: FipNumber,
+        ntw: Network,
+    },
+    /// Everything, the log included, was deleted before this event
+    DatabaseFlushed,
+}
+
+impl Event {
+    /// What the change leaves stale in the hot cache of every server
+    pub fn invalidation(&self) -> Invalidation {
+        match self {
+            Event::VoteStarted {
+                fip_number, ntw, ..
+            }
+            | Event::RoundStarted {
+                fip_number, ntw, ..
+            }
+            | Event::VoteImported {
+                fip_number, ntw, ..
+            }
+            | Event::ResultsPublished {
+                fip_number, ntw, ..
+            }
+            | Event::VoteAnnounced {
+                fip_number, ntw, ..
+            }
+            | Event::MetadataChanged {
+                fip_number, ntw, ..
+            }
+            | Event::OptionsChanged {
+                fip_number, ntw, ..
+            }
+            | Event::QuorumChanged {
+                fip_number, ntw, ..
+            }
+            | Event::WeightingChanged {
+                fip_number, ntw, ..
+            }
+            | Event::MinPowerChanged {
+                fip_number, ntw, ..
+            }
+            | Event::EndingChanged {
+                fip_number, ntw, ..
+            }
+            | Event::ExtensionChanged {
+                fip_number, ntw, ..
+            }
+            | Event::BallotsFlushed { fip_number, ntw }
+            | Event::VoteDeleted { fip_number, ntw } => Invalidation::Vote {
+                fip_number: *fip_number,
+                ntw: *ntw,
+            },
+            Event::BallotRecorded { ballot, .. } => Invalidation::Vote {
+                fip_number: ballot.fip_number,
+                ntw: ballot.ntw,
+            },
+            // Ballots are tallied by the class their voter is registered as
+            Event::VoterRegistered { ntw, .. }
+            | Event::VoterClassRegistered { ntw, .. }
+            | Event::VoterUnregistered { ntw, .. }
+            | Event::StorageProvidersUnregistered { ntw, .. }
+            | Event::StarterRegistered { ntw, .. }
+            | Event::StarterRemoved { ntw, .. }
+            | Event::AdminRegistered { ntw, .. }
+            | Event::AdminRemoved { ntw, .. }
+            | Event::WorkerChanged { ntw, .. }
+            | Event::ExpiryChanged { ntw, .. }
+            | Event::DelegationOffered { ntw, .. }
+            | Event::DelegationTaken { ntw, .. } => Invalidation::Network(*ntw),
+            Event::VoterRemoved { .. } | Event::DatabaseFlushed => Invalidation::All,
+        }
+    }
+}
+
+/// An event as read back from the log
+#[derive(Serialize, Deserialize)]
+pub struct LoggedEvent {
+    /// Id of the entry in the log, later events are read after it
+    pub id: String,
+    /// Unix timestamp the change was made at
+    pub timestamp: u64,
+    pub event: Event,
+}
+
+/// Events replayed into a database
+#[derive(Debug, Default, PartialEq)]
+pub struct ReplaySummary {
+    pub events: usize,
+    pub ballots: usize,
+}
+
+/// Rebuilds the database of `target` from the event log of `source`
+///
+/// Events are applied in the order they happened, each at the time it was
+/// first made, and are logged by `target` again as they are applied. The
+/// target must not hold any votes yet, and keeps timing changes by the last
+/// event replayed
+pub async fn replay(
+    source: &mut impl VoteStore,
+    target: &mut impl VoteStore,
+) -> Result<ReplaySummary, StoreError> {
+    for ntw in Network::all() {
+        if !target.all_votes(ntw)?.is_empty() {
+            return Err(StoreError::Invalid(
+                "Database to replay into already holds votes".to_string(),
+            ));
+        }
+    }
+
+    let clock = Arc::new(MockClock::default());
+    target.set_clock(clock.clone());
+
+    let mut summary = ReplaySummary::default();
+    let mut after = None;
+    loop {
+        let events = source.events(after.as_deref(), REPLAY_BATCH)?;
+        let last = match events.last() {
+            Some(last) => last.id.clone(),
+            None => break,
+        };
+
+        for logged in events {
+            if matches!(logged.event, Event::BallotRecorded { .. }) {
+                summary.ballots += 1;
+            }
+            clock.set(logged.timestamp);
+            apply(target, logged.event).await?;
+            summary.events += 1;
+        }
+
+        after = Some(last);
+    }
+
+    Ok(summary)
+}
+
+/// Makes the change an event records
+pub async fn apply(store: &mut impl VoteStore, event: Event) -> Result<(), StoreError> {
+    match event {
+        Event::VoteStarted {
+            fip_number,
+            ntw,
+            signer,
+            opens_at: Some(opens_at),
+        } => store.schedule_vote(fip_number, signer, ntw, opens_at).await,
+        Event::VoteStarted {
+            fip_number,
+            ntw,
+            signer,
+            opens_at: None,
+        } => store.start_vote(fip_number, signer, ntw).await,
+        Event::RoundStarted {
+            fip_number,
+            ntw,
+            signer,
+            round,
+            vote_length,
+        } => {
+            store
+                .start_round(fip_number, signer, ntw, round, vote_length)
+                .await
+        }
+        Event::VoteImported {
+            fip_number,
+            ntw,
+            started_at,
+            ballots,
+            results,
+            metadata,
+        } => {
+            store
+                .import_vote(fip_number, ntw, started_at, &ballots, &results, &metadata)
+                .await
+        }
+        Event::VoterRegistered { voter, ntw, sp_ids } => store.register_voter(voter, ntw, sp_ids),
+        Event::VoterClassRegistered { voter, ntw, class } => {
+            store.register_voter_class(voter, ntw, class)
+        }
+        Event::VoterUnregistered { voter, ntw } => store.unregister_voter(voter, ntw),
+        Event::StorageProvidersUnregistered { voter, ntw, sp_ids } => {
+            store.unregister_storage_providers(voter, ntw, &sp_ids)
+        }
+        Event::VoterRemoved { voter } => store.unregister_voter_all(voter),
+        Event::StarterRegistered { voter, ntw } => store.register_voter_starter(voter, ntw),
+        Event::StarterRemoved { voter, ntw } => store.remove_voter_starters(voter, ntw),
+        Event::AdminRegistered { voter, ntw } => store.register_admin(voter, ntw),
+        Event::AdminRemoved { voter, ntw } => store.remove_admin(voter, ntw),
+        Event::WorkerChanged {
+            voter,
+            ntw,
+            worker_address,
+        } => store.set_voter_worker(voter, ntw, worker_address.as_deref()),
+        Event::ExpiryChanged {
+            voter,
+            ntw,
+            expires_at,
+        } => store.set_registration_expiry(voter, ntw, expires_at),
+        Event::DelegationOffered { voter, ntw, offer } => {
+            store.offer_delegation(voter, ntw, &offer)
+        }
+        Event::DelegationTaken {
+            voter,
+            ntw,
+            worker_address,
+        } => store
+            .take_delegation_offer(voter, ntw, &worker_address)
+            .map(|_| ()),
+        Event::BallotRecorded {
+            ballot,
+            allow_revote,
+            receipt,
+        } => store.record_ballot(ballot, allow_revote, receipt.as_ref()),
+        Event::ResultsPublished {
+            fip_number,
+            ntw,
+            tx_hash,
+        } => store.set_published_results(fip_number, ntw, tx_hash),
+        Event::VoteAnnounced {
+            fip_number,
+            ntw,
+            announcement,
+        } => store.set_announced(fip_number, ntw, announcement),
+        Event::MetadataChanged {
+            fip_number,
+            ntw,
+            language,
+            metadata,
+        } => {
+            store
+                .set_vote_metadata(fip_number, ntw, language, metadata)
+                .await
+        }
+        Event::OptionsChanged {
+            fip_number,
+            ntw,
+            options,
+        } => store.set_vote_options(fip_number, ntw, &options),
+        Event::QuorumChanged {
+            fip_number,
+            ntw,
+            rules,
+        } => store.set_vote_quorum(fip_number, ntw, rules),
+        Event::WeightingChanged {
+            fip_number,
+            ntw,
+            weighting,
+        } => store.set_vote_weighting(fip_number, ntw, weighting),
+        Event::MinPowerChanged {
+            fip_number,
+            ntw,
+            min_power,
+        } => store.set_vote_min_power(fip_number, ntw, min_power),
+        Event::EndingChanged {
+            fip_number,
+            ntw,
+            ended,
+        } => store.set_vote_ending(fip_number, ntw, ended.as_ref()),
+        Event::ExtensionChanged {
+            fip_number,
+            ntw,
+            extension,
+        } => store.set_vote_extension(fip_number, ntw, extension),
+        Event::BallotsFlushed { fip_number, ntw } => store.flush_vote(fip_number, ntw),
+        Event::VoteDeleted { fip_number, ntw } => store.delete_vote(fip_number, ntw).await,
+        Event::DatabaseFlushed => store.flush_all(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        authorized_voters,
+        messages::votes::{test_votes::test_vote, VoteOption},
+        test_redis::TestRedis,
+    };
+
+    #[tokio::test]
+    async fn events_replay() {
+        let mut source = TestRedis::start();
+        let mut target = TestRedis::start();
+
+        let fip_number = FipNumber::try_from(5u32).unwrap();
+        let ntw = Network::Testnet;
+        let mut vote = test_vote(VoteOption::Yay, 5).vote().unwrap();
+        let voter = vote.voter();
+        vote.record(
+            source.now(),
+            VoterClass::StorageProvider,
+            BTreeMap::from([(6024, 10240000)]),
+            0,
+        );
+
+        source.register_voter(voter, ntw, vec![6024]).unwrap();
+        source
+            .start_vote(fip_number, authorized_voters()[0], ntw)
+            .await
+            .unwrap();
+        source
+            .set_vote_quorum(
+                fip_number,
+                ntw,
+                Some(QuorumRules {
+                    min_storage_size: 1024,
+                    pass_percentage: 50,
+                }),
+            )
+            .unwrap();
+        source
+            .set_vote_weighting(fip_number, ntw, Weighting::Sqrt)
+            .unwrap();
+        let ballot = RecordedBallot {
+            fip_number,
+            ntw,
+            voter,
+            class: VoterClass::StorageProvider,
+            vote,
+            storage: 10240000,
+            fil: 0,
+        };
+        source.record_ballot(ballot, false, None).unwrap();
+
+        let summary = replay(&mut *source, &mut *target).await.unwrap();
+        assert_eq!(
+            summary,
+            ReplaySummary {
+                events: 5,
+                ballots: 1
+            }
+        );
+
+        // Every change lands as it was first made, with the weight it was first counted with
+        assert_eq!(
+            target.vote_start(fip_number, ntw).unwrap(),
+            source.vote_start(fip_number, ntw).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_string(&target.votes(fip_number, ntw).unwrap()).unwrap(),
+            serde_json::to_string(&source.votes(fip_number, ntw).unwrap()).unwrap()
+        );
+        assert_eq!(
+            target.voter_history(voter, ntw).unwrap(),
+            source.voter_history(voter, ntw).unwrap()
+        );
+        assert_eq!(
+            target.vote_quorum(fip_number, ntw).unwrap(),
+            source.vote_quorum(fip_number, ntw).unwrap()
+        );
+        assert_eq!(
+            target.vote_weighting(fip_number, ntw).unwrap(),
+            Weighting::Sqrt
+        );
+        assert_eq!(target.voter_delegates(voter, ntw).unwrap(), vec![6024]);
+
+        // The replayed changes are logged again, and a database with votes is not replayed into
+        assert_eq!(target.events(None, 10).unwrap().len(), 5);
+        assert!(replay(&mut *source, &mut *target).await.is_err());
+    }
+}
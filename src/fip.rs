@@ -0,0 +1,157 @@
+use std::{fmt, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize};
+use thiserror::Error;
+
+/// FIPs are numbered with four digits starting at FIP-0001
+pub const MAX_FIP_NUMBER: u32 = 9999;
+
+/// A validated FIP number
+///
+/// Parses from `123`, `FIP-123` or `FIP-0123` and serializes as a plain number
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(into = "u32")]
+pub struct FipNumber(u32);
+
+#[derive(Debug, Error, PartialEq)]
+pub enum FipNumberError {
+    #[error("FIP number {0} is out of range 1-{MAX_FIP_NUMBER}")]
+    OutOfRange(u32),
+    #[error("Invalid FIP number: {0}")]
+    Invalid(String),
+}
+
+impl FipNumber {
+    pub fn get(&self) -> u32 {
+        self.0
+    }
+}
+
+impl TryFrom<u32> for FipNumber {
+    type Error = FipNumberError;
+
+    fn try_from(num: u32) -> Result<Self, Self::Error> {
+        match num {
+            1..=MAX_FIP_NUMBER => Ok(FipNumber(num)),
+            _ => Err(FipNumberError::OutOfRange(num)),
+        }
+    }
+}
+
+impl From<FipNumber> for u32 {
+    fn from(fip: FipNumber) -> Self {
+        fip.0
+    }
+}
+
+impl FromStr for FipNumber {
+    type Err = FipNumberError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix("FIP-").unwrap_or(s);
+
+        // Only plain ascii digits, `u32::from_str` would also accept a sign
+        if digits.is_empty() || digits.len() > 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(FipNumberError::Invalid(s.to_string()));
+        }
+
+        match digits.parse::<u32>() {
+            Ok(num) => FipNumber::try_from(num),
+            Err(_) => Err(FipNumberError::Invalid(s.to_string())),
+        }
+    }
+}
+
+/// Accepts both json numbers and query string values such as `FIP-0123`
+impl<'de> Deserialize<'de> for FipNumber {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct FipNumberVisitor;
+
+        impl<'de> de::Visitor<'de> for FipNumberVisitor {
+            type Value = FipNumber;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a FIP number between 1 and {}", MAX_FIP_NUMBER)
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                let num = u32::try_from(v)
+                    .map_err(|_| E::custom(FipNumberError::Invalid(v.to_string())))?;
+                FipNumber::try_from(num).map_err(E::custom)
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                Err(E::custom(FipNumberError::Invalid(v.to_string())))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                FipNumber::from_str(v).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(FipNumberVisitor)
+    }
+}
+
+impl fmt::Display for FipNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fip_number_try_from() {
+        assert_eq!(FipNumber::try_from(1u32).unwrap().get(), 1);
+        assert_eq!(FipNumber::try_from(MAX_FIP_NUMBER).unwrap().get(), 9999);
+        assert_eq!(
+            FipNumber::try_from(0u32),
+            Err(FipNumberError::OutOfRange(0))
+        );
+        assert_eq!(
+            FipNumber::try_from(10000u32),
+            Err(FipNumberError::OutOfRange(10000))
+        );
+    }
+
+    #[test]
+    fn fip_number_from_str() {
+        for s in ["42", "FIP-42", "FIP-0042"] {
+            assert_eq!(FipNumber::from_str(s).unwrap().get(), 42);
+        }
+        for s in [
+            "",
+            "FIP-",
+            "+42",
+            "FIP--42",
+            "fip-42",
+            "FIP-12345",
+            "FIP-0",
+            "4 2",
+        ] {
+            assert!(FipNumber::from_str(s).is_err());
+        }
+    }
+
+    #[test]
+    fn fip_number_serde() {
+        let fip: FipNumber = serde_json::from_str("42").unwrap();
+
+        assert_eq!(fip.get(), 42);
+        assert_eq!(serde_json::to_string(&fip).unwrap(), "42");
+
+        let fip: FipNumber = serde_json::from_str("\"FIP-0042\"").unwrap();
+
+        assert_eq!(fip.get(), 42);
+
+        for s in ["0", "-42", "4294967296", "\"FIP-0\""] {
+            assert!(serde_json::from_str::<FipNumber>(s).is_err());
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Titles, statuses and discussion links of FIPs, synced from the FIPs repository
+//!
+//! Votes are only identified by their FIP number, so the front matter of every
+//! FIP that was voted on is fetched periodically and kept in the database to be
+//! served next to the active votes and results
+use std::{collections::BTreeSet, time::Duration};
+
+use redis::RedisError;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use url::Url;
+use utoipa::ToSchema;
+
+use crate::{
+    fip::FipNumber,
+    redis::Redis,
+    storage::Network,
+    store::{StoreError, VoteStore},
+    Args,
+};
+
+#[derive(Debug, Error)]
+pub enum FipSyncError {
+    #[error("Redis error: {0}")]
+    Redis(#[from] RedisError),
+    #[error("Store error: {0}")]
+    Store(#[from] StoreError),
+    #[error("Error fetching FIP: {0}")]
+    Reqwest(#[from] reqwest::Error),
+    #[error("Invalid FIP url: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("FIP-{0} has no front matter")]
+    NoFrontMatter(FipNumber),
+    #[error("FIP-{0} has no {1} in its front matter")]
+    MissingField(FipNumber, &'static str),
+}
+
+/// What the FIPs repository says about a FIP
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct FipInfo {
+    #[schema(example = "FIP Purpose and Guidelines")]
+    pub title: String,
+    /// Status as written in the FIP, such as `Draft`, `Last Call` or `Final`
+    #[schema(example = "Active")]
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discussions_to: Option<String>,
+    /// Document the FIP was read from
+    pub url: String,
+}
+
+impl FipInfo {
+    /// Reads the front matter at the top of a FIP document
+    ///
+    /// Only `key: value` lines are read, which is all the FIP template uses
+    pub fn parse(fip_number: FipNumber, document: &str, url: &Url) -> Result<Self, FipSyncError> {
+        let mut lines = document.lines().map(str::trim);
+        if lines.find(|line| !line.is_empty()) != Some("---") {
+            return Err(FipSyncError::NoFrontMatter(fip_number));
+        }
+
+        let (mut title, mut status, mut discussions_to) = (None, None, None);
+        for line in lines.take_while(|line| *line != "---") {
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim(), unquote(value.trim())),
+                None => continue,
+            };
+            if value.is_empty() {
+                continue;
+            }
+            match key {
+                "title" => title = Some(value.to_string()),
+                "status" => status = Some(value.to_string()),
+                "discussions-to" => discussions_to = Some(value.to_string()),
+                _ => (),
+            }
+        }
+
+        Ok(Self {
+            title: title.ok_or(FipSyncError::MissingField(fip_number, "title"))?,
+            status: status.ok_or(FipSyncError::MissingField(fip_number, "status"))?,
+            discussions_to,
+            url: url.to_string(),
+        })
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value)
+}
+
+/// Location of a FIP in the repository, such as `.../FIPS/fip-0001.md`
+pub fn document_url(repo_url: &Url, fip_number: FipNumber) -> Result<Url, url::ParseError> {
+    let repo = repo_url.as_str().trim_end_matches('/');
+
+    Url::parse(&format!("{}/fip-{:04}.md", repo, fip_number.get()))
+}
+
+/// Keeps the FIPs of every vote in the database up to date with the repository
+pub struct FipRegistry {
+    client: Client,
+    repo_url: Url,
+    redis_path: Url,
+    interval: Duration,
+}
+
+impl FipRegistry {
+    /// Returns `None` when syncing is disabled with an interval of 0
+    pub fn from_args(args: &Args) -> Option<Self> {
+        match args.fip_sync_interval() {
+            0 => None,
+            interval => Some(Self {
+                client: Client::new(),
+                repo_url: args.fip_repo_url(),
+                redis_path: args.redis_path(),
+                interval: Duration::from_secs(interval),
+            }),
+        }
+    }
+
+    /// Syncs once right away, then every interval until the server stops
+    pub fn spawn(self) {
+        actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(self.interval);
+            loop {
+                interval.tick().await;
+                match self.sync().await {
+                    Ok(synced) => println!("Synced {} FIPs from {}", synced, self.repo_url),
+                    Err(e) => println!("Error syncing FIPs: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Fetches every FIP voted on in any network, returning how many were stored
+    ///
+    /// A FIP that can't be fetched or read is skipped so it doesn't hold up the others
+    pub async fn sync(&self) -> Result<usize, FipSyncError> {
+        let mut redis = Redis::new(self.redis_path.clone())?;
+
+        let mut fips = BTreeSet::new();
+        for ntw in Network::all() {
+            fips.extend(redis.all_votes(ntw)?);
+        }
+
+        let mut synced = 0;
+        for fip_number in fips {
+            match self.fetch(fip_number).await {
+                Ok(Some(info)) => {
+                    redis.set_fip_info(fip_number, &info)?;
+                    synced += 1;
+                }
+                Ok(None) => println!("FIP-{} is not in the repository yet", fip_number),
+                Err(e) => println!("Error syncing FIP-{}: {}", fip_number, e),
+            }
+        }
+
+        Ok(synced)
+    }
+
+    /// The synced metadata of the FIP, fetched from the repository and stored
+    /// first if it wasn't synced yet, `None` if the FIP is not in the repository
+    pub async fn lookup(
+        &self,
+        redis: &mut impl VoteStore,
+        fip_number: FipNumber,
+    ) -> Result<Option<FipInfo>, FipSyncError> {
+        if let Some(info) = redis.fip_info(fip_number)? {
+            return Ok(Some(info));
+        }
+
+        let info = self.fetch(fip_number).await?;
+        if let Some(info) = &info {
+            redis.set_fip_info(fip_number, info)?;
+        }
+
+        Ok(info)
+    }
+
+    /// Returns `None` if the FIP has not been merged into the repository
+    async fn fetch(&self, fip_number: FipNumber) -> Result<Option<FipInfo>, FipSyncError> {
+        let url = document_url(&self.repo_url, fip_number)?;
+
+        let res = self.client.get(url.clone()).send().await?;
+        if res.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let document = res.error_for_status()?.text().await?;
+
+        FipInfo::parse(fip_number, &document, &url).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPO: &str = "https://raw.githubusercontent.com/filecoin-project/FIPs/master/FIPS";
+
+    fn fip(num: u32) -> FipNumber {
+        FipNumber::try_from(num).unwrap()
+    }
+
+    #[test]
+    fn fip_registry_document_url() {
+        let url = document_url(&Url::parse(REPO).unwrap(), fip(1)).unwrap();
+        assert_eq!(url.as_str(), format!("{}/fip-0001.md", REPO));
+
+        let url = document_url(&Url::parse(&format!("{}/", REPO)).unwrap(), fip(1234)).unwrap();
+        assert_eq!(url.as_str(), format!("{}/fip-1234.md", REPO));
+    }
+
+    #[test]
+    fn fip_registry_parse() {
+        let url = document_url(&Url::parse(REPO).unwrap(), fip(36)).unwrap();
+        let document = r#"---
+fip: "0036"
+title: Introducing a Sector Duration Multiple for Longer Term Sector Commitment
+author: "@AxCortesCubero, @jbenet"
+discussions-to: https://github.com/filecoin-project/FIPs/discussions/386
+status: 'Rejected'
+type: Technical
+category: Core
+created: 2022-03-08
+---
+
+## Simple Summary
+title: not front matter
+"#;
+
+        let info = FipInfo::parse(fip(36), document, &url).unwrap();
+
+        assert_eq!(
+            info.title,
+            "Introducing a Sector Duration Multiple for Longer Term Sector Commitment"
+        );
+        assert_eq!(info.status, "Rejected");
+        assert_eq!(
+            info.discussions_to.as_deref(),
+            Some("https://github.com/filecoin-project/FIPs/discussions/386")
+        );
+        assert_eq!(info.url, url.to_string());
+    }
+
+    #[test]
+    fn fip_registry_parse_invalid() {
+        let url = document_url(&Url::parse(REPO).unwrap(), fip(2)).unwrap();
+
+        let res = FipInfo::parse(fip(2), "# FIP-0002\ntitle: Missing", &url);
+        assert!(matches!(res, Err(FipSyncError::NoFrontMatter(_))));
+
+        let res = FipInfo::parse(fip(2), "---\ntitle: No status\n---\n", &url);
+        assert!(matches!(res, Err(FipSyncError::MissingField(_, "status"))));
+
+        let info = FipInfo::parse(fip(2), "---\ntitle: T\nstatus: Draft\n---", &url).unwrap();
+        assert_eq!(info.discussions_to, None);
+    }
+}
@@ -1,31 +1,118 @@
-use std::{collections::HashMap, str::FromStr};
+use std::collections::HashMap;
 
-use actix_web::{get, web, HttpResponse, Responder};
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Responder};
 use ethers::types::Address;
+use serde::Serialize;
+use utoipa::ToSchema;
 
 use crate::{
+    api::types::{
+        requested_version, Envelope, RegistrationDto, ResultsDto, SilenceDto, TurnoutDto, VoteDto,
+        VotingPowerDto,
+    },
     errors::*,
-    redis::{Redis, VoteStatus},
-    storage::{fetch_storage_amount, Network},
-    Args, NtwAddrParams, NtwFipParams, NtwParams, STARTING_AUTHORIZED_VOTERS,
+    etag::{entity_tag, not_modified, tagged},
+    fip::FipNumber,
+    fip_registry::FipInfo,
+    messages::{
+        vote_metadata::{select_language, VoteMetadata},
+        votes::BallotWeight,
+    },
+    redis::VoteStatus,
+    storage::{
+        fetch_network_power, fetch_storage_amounts_at, parse_address, sp_id_format, Network,
+        StorageFetchError, StoragePowerProvider,
+    },
+    store::{StoreError, StoreFactory, VoteStore},
+    tally::TallyRules,
+    ActiveVotesParams, AddrParams, Args, AuditParams, NtwAddrHeightParams, NtwAddrParams,
+    NtwFipParams, NtwParams, VotersParams, VotingPowerParams,
 };
 
-#[get("/filecoin/vote")]
-async fn get_votes(
+/// Registers the endpoints that read votes, voters and voting power from the stores `F` opens
+pub fn routes<F: StoreFactory>(cfg: &mut web::ServiceConfig) {
+    cfg.route("/filecoin/vote", web::get().to(get_votes::<F>))
+        .route(
+            "/filecoin/vote/receipt/{id}",
+            web::get().to(get_vote_receipt::<F>),
+        )
+        .route("/filecoin/vote/audit", web::get().to(get_vote_audit::<F>))
+        .route(
+            "/filecoin/vote/breakdown",
+            web::get().to(get_vote_breakdown::<F>),
+        )
+        .route("/filecoin/delegates", web::get().to(get_delegates::<F>))
+        .route(
+            "/filecoin/register/status",
+            web::get().to(get_registration_status::<F>),
+        )
+        .route(
+            "/filecoin/delegations/pending",
+            web::get().to(get_delegation_offers::<F>),
+        )
+        .route(
+            "/filecoin/voter/history",
+            web::get().to(get_voter_history::<F>),
+        )
+        .route("/filecoin/voters", web::get().to(get_voters::<F>))
+        .route(
+            "/filecoin/activevotes",
+            web::get().to(get_active_votes::<F>),
+        )
+        .route(
+            "/filecoin/votehistory",
+            web::get().to(get_concluded_votes::<F>),
+        )
+        .route("/filecoin/votes/stale", web::get().to(get_stale_votes::<F>))
+        .route(
+            "/filecoin/allconcludedvotes",
+            web::get().to(get_all_concluded_votes::<F>),
+        )
+        .route(
+            "/filecoin/votingpower",
+            web::get().to(get_voting_power::<F>),
+        )
+        .route(
+            "/filecoin/votingpower/at",
+            web::get().to(get_voting_power_at::<F>),
+        )
+        .route(
+            "/filecoin/voterstarters",
+            web::get().to(get_vote_starters::<F>),
+        )
+        .route("/filecoin/votestats", web::get().to(get_vote_stats::<F>))
+        .route("/filecoin/turnout", web::get().to(get_turnout::<F>))
+        .route(
+            "/filecoin/votemetadata",
+            web::get().to(get_vote_metadata::<F>),
+        );
+}
+
+#[utoipa::path(
+    get,
+    path = "/filecoin/vote",
+    tag = "votes",
+    params(NtwFipParams),
+    responses(
+        (status = 200, description = "Results of a concluded vote, or the seconds left as text while it is in progress", body = VoteResults),
+        (status = 400, description = "Invalid network or request", body = String),
+        (status = 403, description = "The vote has not opened yet, the body ends with the seconds until it opens", body = String),
+        (status = 404, description = "The vote does not exist"),
+        (status = 410, description = "The vote was cancelled", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_votes<F: StoreFactory>(
+    req: HttpRequest,
     query_params: web::Query<NtwFipParams>,
     config: web::Data<Args>,
+    store: web::Data<F>,
 ) -> impl Responder {
-    println!("votes requested");
-
-    let ntw = match query_params.network.as_str() {
-        "mainnet" => Network::Mainnet,
-        "calibration" => Network::Testnet,
-        _ => return HttpResponse::BadRequest().body(INVALID_NETWORK),
-    };
+    let ntw = query_params.network;
     let num = query_params.fip_number;
 
     // Open a connection to the redis database
-    let mut redis = match Redis::new(config.redis_path()) {
+    let mut redis = match store.reader() {
         Ok(redis) => redis,
         Err(e) => {
             let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
@@ -49,8 +136,11 @@ async fn get_votes(
     // Return the appropriate response
     match status {
         VoteStatus::InProgress(time_left) => HttpResponse::Ok().body(time_left.to_string()),
+        VoteStatus::Scheduled(opens_in) => {
+            HttpResponse::Forbidden().body(format!("{}: {}", VOTE_NOT_OPEN_ERROR, opens_in))
+        }
         VoteStatus::Concluded => {
-            let vote_results = match redis.vote_results(num, ntw) {
+            let mut vote_results = match redis.vote_results(num, config.vote_length(), ntw).await {
                 Ok(results) => results,
                 Err(e) => {
                     let res = format!("{}: {}", VOTE_RESULTS_ERROR, e);
@@ -58,28 +148,290 @@ async fn get_votes(
                     return HttpResponse::InternalServerError().body(res);
                 }
             };
+            match request_metadata(&req, &mut redis, num, ntw) {
+                Ok(metadata) => vote_results.set_metadata(metadata.map(|(_, m)| m)),
+                Err(e) => {
+                    let res = format!("{}: {}", VOTE_METADATA_ERROR, e);
+                    println!("{}", res);
+                    return HttpResponse::InternalServerError().body(res);
+                }
+            }
+            match redis.fip_info(num) {
+                Ok(info) => vote_results.set_fip(info),
+                Err(e) => {
+                    let res = format!("{}: {}", FIP_INFO_ERROR, e);
+                    println!("{}", res);
+                    return HttpResponse::InternalServerError().body(res);
+                }
+            }
             println!("Vote results: {:?}", vote_results);
+            if let Some(version) = requested_version(&req) {
+                let dto = ResultsDto::new(&vote_results, num, ntw, version);
+                return Envelope::new(dto, version).respond();
+            }
             HttpResponse::Ok().json(vote_results)
         }
+        VoteStatus::Cancelled => HttpResponse::Gone().body(VOTE_CANCELLED),
         VoteStatus::DoesNotExist => HttpResponse::NotFound().finish(),
+        VoteStatus::Unknown => HttpResponse::Conflict().body(VOTE_STATUS_UNKNOWN),
     }
 }
 
-#[get("/filecoin/delegates")]
-async fn get_delegates(
-    query_params: web::Query<NtwAddrParams>,
+/// Looks up the receipt returned when a ballot was submitted
+#[utoipa::path(
+    get,
+    path = "/filecoin/vote/receipt/{id}",
+    tag = "votes",
+    params(("id" = String, Path, description = "Receipt id returned when the ballot was submitted")),
+    responses(
+        (status = 200, description = "The ballot was recorded", body = VoteReceipt),
+        (status = 404, description = "No ballot was recorded with the id"),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_vote_receipt<F: StoreFactory>(
+    id: web::Path<String>,
+    store: web::Data<F>,
+) -> impl Responder {
+    // Open a connection to the redis database
+    let mut redis = match store.reader() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    match redis.vote_receipt(&id) {
+        Ok(Some(receipt)) => HttpResponse::Ok().json(receipt),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_RECEIPT_ERROR, e);
+            println!("{}", res);
+            HttpResponse::InternalServerError().body(res)
+        }
+    }
+}
+
+/// Every ballot of a concluded vote with the time and power it was recorded with
+#[utoipa::path(
+    get,
+    path = "/filecoin/vote/audit",
+    tag = "votes",
+    params(AuditParams),
+    responses(
+        (status = 200, description = "Every ballot of the concluded vote", body = [BallotAudit]),
+        (status = 400, description = "Invalid network or request", body = String),
+        (status = 403, description = "The vote is in progress, the body is the seconds left, or it has not opened yet", body = String),
+        (status = 404, description = "The vote does not exist"),
+        (status = 410, description = "The vote was cancelled", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_vote_audit<F: StoreFactory>(
+    req: HttpRequest,
+    query_params: web::Query<AuditParams>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+    let num = query_params.fip_number;
+
+    // Open a connection to the redis database
+    let mut redis = match store.reader() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    // Ballots stay secret until the vote concludes
+    match redis.vote_status(num, config.vote_length(), ntw) {
+        Ok(VoteStatus::Concluded) => (),
+        Ok(VoteStatus::InProgress(time_left)) => {
+            return HttpResponse::Forbidden().body(time_left.to_string())
+        }
+        Ok(VoteStatus::Scheduled(opens_in)) => {
+            return HttpResponse::Forbidden().body(format!("{}: {}", VOTE_NOT_OPEN_ERROR, opens_in))
+        }
+        Ok(VoteStatus::DoesNotExist) => return HttpResponse::NotFound().finish(),
+        Ok(VoteStatus::Cancelled) => return HttpResponse::Gone().body(VOTE_CANCELLED),
+        Ok(VoteStatus::Unknown) => return HttpResponse::Conflict().body(VOTE_STATUS_UNKNOWN),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_STATUS_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    }
+
+    let tag = match redis.results_version() {
+        Ok(version) => entity_tag(&req, version, &(ntw, num)),
+        Err(e) => {
+            let res = format!("{}: {}", RESULTS_VERSION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+    if let Some(res) = not_modified(&req, &tag) {
+        return res;
+    }
+
+    let votes = match redis.votes(num, ntw) {
+        Ok(votes) => votes,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_AUDIT_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    let audit: Vec<_> = votes
+        .iter()
+        .map(|vote| vote.audit(query_params.anonymize))
+        .collect();
+
+    if let Some(version) = requested_version(&req) {
+        let ballots: Vec<_> = audit
+            .into_iter()
+            .map(|ballot| VoteDto::new(ballot, ntw, None, version))
+            .collect();
+        return Envelope::new(ballots, version).respond();
+    }
+
+    HttpResponse::Ok().json(audit)
+}
+
+/// The power each ballot of a concluded vote added to its choice, so the
+/// storage sizes of the results can be summed up again
+#[utoipa::path(
+    get,
+    path = "/filecoin/vote/breakdown",
+    tag = "votes",
+    params(NtwFipParams),
+    responses(
+        (status = 200, description = "Every ballot of the concluded vote with its power", body = [BallotWeight]),
+        (status = 304, description = "The breakdown is unchanged since the tag sent in If-None-Match"),
+        (status = 400, description = "Invalid network or request", body = String),
+        (status = 403, description = "The vote is in progress, the body is the seconds left, or it has not opened yet", body = String),
+        (status = 404, description = "The vote does not exist"),
+        (status = 410, description = "The vote was cancelled", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_vote_breakdown<F: StoreFactory>(
+    req: HttpRequest,
+    query_params: web::Query<NtwFipParams>,
     config: web::Data<Args>,
+    store: web::Data<F>,
 ) -> impl Responder {
-    println!("Delegates requested");
+    let ntw = query_params.network;
+    let num = query_params.fip_number;
+
+    // Open a connection to the redis database
+    let mut redis = match store.reader() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
 
-    let ntw = match query_params.network.as_str() {
-        "mainnet" => Network::Mainnet,
-        "calibration" => Network::Testnet,
-        _ => return HttpResponse::BadRequest().body(INVALID_NETWORK),
+    // Ballots stay secret until the vote concludes
+    match redis.vote_status(num, config.vote_length(), ntw) {
+        Ok(VoteStatus::Concluded) => (),
+        Ok(VoteStatus::InProgress(time_left)) => {
+            return HttpResponse::Forbidden().body(time_left.to_string())
+        }
+        Ok(VoteStatus::Scheduled(opens_in)) => {
+            return HttpResponse::Forbidden().body(format!("{}: {}", VOTE_NOT_OPEN_ERROR, opens_in))
+        }
+        Ok(VoteStatus::DoesNotExist) => return HttpResponse::NotFound().finish(),
+        Ok(VoteStatus::Cancelled) => return HttpResponse::Gone().body(VOTE_CANCELLED),
+        Ok(VoteStatus::Unknown) => return HttpResponse::Conflict().body(VOTE_STATUS_UNKNOWN),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_STATUS_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    }
+
+    let votes = match redis.votes(num, ntw) {
+        Ok(votes) => votes,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_BREAKDOWN_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    // Weighted like the results so the storage sizes can be summed from the
+    // ballots, ballots under the minimum power add nothing
+    let rules = redis.vote_weighting(num, ntw).and_then(|weighting| {
+        Ok(TallyRules {
+            weighting,
+            quorum: None,
+            min_power: redis.vote_min_power(num, ntw)?,
+        })
+    });
+    let rules = match rules {
+        Ok(rules) => rules,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_BREAKDOWN_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    let weights = match rules.ballot_weights(&votes) {
+        Ok(weights) => weights,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_BREAKDOWN_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
     };
+
+    if let Some(version) = requested_version(&req) {
+        let ballots: Vec<_> = votes
+            .iter()
+            .zip(weights)
+            .map(|(vote, weight)| VoteDto::new(vote.audit(false), ntw, Some(weight), version))
+            .collect();
+        return tagged(Envelope::new(ballots, version).respond(), &tag);
+    }
+
+    let breakdown: Vec<BallotWeight> = votes
+        .iter()
+        .zip(weights)
+        .map(|(vote, weight)| vote.weight(ntw, weight))
+        .collect();
+
+    tagged(HttpResponse::Ok().json(breakdown), &tag)
+}
+
+#[utoipa::path(
+    get,
+    path = "/filecoin/delegates",
+    tag = "voters",
+    params(NtwAddrParams),
+    responses(
+        (status = 200, description = "Storage providers the voter votes for", body = [String]),
+        (status = 400, description = "Invalid network or request", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_delegates<F: StoreFactory>(
+    query_params: web::Query<NtwAddrParams>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
     let address = query_params.address.clone();
 
-    let address = match Address::from_str(address.as_str()) {
+    let address = match parse_address(ntw, &address) {
         Ok(address) => address,
         Err(e) => {
             let res = format!("{}: {}", INVALID_ADDRESS, e);
@@ -89,7 +441,7 @@ async fn get_delegates(
     };
 
     // Open a connection to the redis database
-    let mut redis = match Redis::new(config.redis_path()) {
+    let mut redis = match store.reader() {
         Ok(redis) => redis,
         Err(e) => {
             let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
@@ -111,31 +463,228 @@ async fn get_delegates(
     println!("Delegates: {:?} for address: {}", delegates, address);
 
     let mut dgts: Vec<String> = Vec::new();
-    let prefix = match ntw {
-        Network::Mainnet => "f",
-        Network::Testnet => "t",
-    };
     for delegate in delegates {
-        dgts.push(format!("{}0{}", prefix, delegate));
+        dgts.push(sp_id_format(ntw, delegate));
     }
 
     HttpResponse::Ok().json(dgts)
 }
 
-#[get("/filecoin/activevotes")]
-async fn get_active_votes(
-    query_params: web::Query<NtwParams>,
-    config: web::Data<Args>,
+/// Where the last registration of a voter is at, see [`crate::registrations`]
+#[utoipa::path(
+    get,
+    path = "/filecoin/register/status",
+    tag = "voters",
+    params(AddrParams),
+    responses(
+        (status = 200, description = "Whether the registration is pending, registered, offered or failed", body = RegistrationStatus),
+        (status = 400, description = "Invalid address", body = String),
+        (status = 404, description = "The address has not registered recently"),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_registration_status<F: StoreFactory>(
+    req: HttpRequest,
+    query_params: web::Query<AddrParams>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let address = match query_params.address.parse::<Address>() {
+        Ok(address) => address,
+        Err(e) => {
+            let res = format!("{}: {}", INVALID_ADDRESS, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+
+    // Open a connection to the redis database
+    let mut redis = match store.reader() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    match redis.registration_status(address) {
+        Ok(Some(status)) => match requested_version(&req) {
+            Some(version) => {
+                Envelope::new(RegistrationDto::new(address, status, version), version).respond()
+            }
+            None => HttpResponse::Ok().json(status),
+        },
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(e) => {
+            let res = format!("{}: {}", REGISTRATION_STATUS_ERROR, e);
+            println!("{}", res);
+            HttpResponse::InternalServerError().body(res)
+        }
+    }
+}
+
+/// Delegations storage providers offered the voter that they haven't
+/// accepted yet, see `--require-acceptance`
+#[utoipa::path(
+    get,
+    path = "/filecoin/delegations/pending",
+    tag = "voters",
+    params(NtwAddrParams),
+    responses(
+        (status = 200, description = "Offers waiting for the voter to accept them, oldest first", body = [DelegationOffer]),
+        (status = 400, description = "Invalid network or address", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_delegation_offers<F: StoreFactory>(
+    query_params: web::Query<NtwAddrParams>,
+    store: web::Data<F>,
 ) -> impl Responder {
-    println!("Active votes requested");
-    let ntw = match query_params.network.as_str() {
-        "mainnet" => Network::Mainnet,
-        "calibration" => Network::Testnet,
-        _ => return HttpResponse::BadRequest().body(INVALID_NETWORK),
+    let ntw = query_params.network;
+
+    let address = match parse_address(ntw, &query_params.address) {
+        Ok(address) => address,
+        Err(e) => {
+            let res = format!("{}: {}", INVALID_ADDRESS, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+
+    // Open a connection to the redis database
+    let mut redis = match store.reader() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
     };
 
+    match redis.delegation_offers(address, ntw) {
+        Ok(offers) => HttpResponse::Ok().json(offers),
+        Err(e) => {
+            let res = format!("{}: {}", DELEGATION_OFFERS_ERROR, e);
+            println!("{}", res);
+            HttpResponse::InternalServerError().body(res)
+        }
+    }
+}
+
+/// Every FIP the voter cast a ballot on with the choice and weight counted
+#[utoipa::path(
+    get,
+    path = "/filecoin/voter/history",
+    tag = "voters",
+    params(NtwAddrParams),
+    responses(
+        (status = 200, description = "Ballots of the voter ordered by FIP number", body = [VoterBallot]),
+        (status = 400, description = "Invalid network or address", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_voter_history<F: StoreFactory>(
+    query_params: web::Query<NtwAddrParams>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+
+    let address = match parse_address(ntw, &query_params.address) {
+        Ok(address) => address,
+        Err(e) => {
+            let res = format!("{}: {}", INVALID_ADDRESS, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+
+    // Open a connection to the redis database
+    let mut redis = match store.reader() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    match redis.voter_history(address, ntw) {
+        Ok(history) => HttpResponse::Ok().json(history),
+        Err(e) => {
+            let res = format!("{}: {}", VOTER_HISTORY_ERROR, e);
+            println!("{}", res);
+            HttpResponse::InternalServerError().body(res)
+        }
+    }
+}
+
+/// Every registered voter with the storage providers they vote for
+#[utoipa::path(
+    get,
+    path = "/filecoin/voters",
+    tag = "voters",
+    params(VotersParams),
+    responses(
+        (status = 200, description = "A page of the registered voters", body = VoterPage),
+        (status = 400, description = "Invalid network or request", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_voters<F: StoreFactory>(
+    query_params: web::Query<VotersParams>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+
+    // Open a connection to the redis database
+    let mut redis = match store.reader() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    match redis.voter_page(ntw, query_params.offset, query_params.limit) {
+        Ok(page) => HttpResponse::Ok().json(page),
+        Err(e) => {
+            let res = format!("{}: {}", REGISTERED_VOTERS_ERROR, e);
+            println!("{}", res);
+            HttpResponse::InternalServerError().body(res)
+        }
+    }
+}
+
+/// A vote in progress with what the FIPs repository says about it
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ActiveVote {
+    #[schema(value_type = u32)]
+    fip_number: FipNumber,
+    /// Unset until the FIP has been synced
+    fip: Option<FipInfo>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/filecoin/activevotes",
+    tag = "votes",
+    params(ActiveVotesParams),
+    responses(
+        (status = 200, description = "FIP numbers of the votes in progress, or the votes with their FIP title and status when `details` is set", body = [u32]),
+        (status = 400, description = "Invalid network or request", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_active_votes<F: StoreFactory>(
+    query_params: web::Query<ActiveVotesParams>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+
     // Open a connection to the Redis Database
-    let mut redis = match Redis::new(config.redis_path()) {
+    let mut redis = match store.reader() {
         Ok(redis) => redis,
         Err(e) => {
             let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
@@ -156,23 +705,45 @@ async fn get_active_votes(
 
     println!("Active votes: {:?}", active_votes);
 
-    HttpResponse::Ok().json(active_votes)
+    if !query_params.details {
+        return HttpResponse::Ok().json(active_votes);
+    }
+
+    let mut votes = Vec::with_capacity(active_votes.len());
+    for fip_number in active_votes {
+        match redis.fip_info(fip_number) {
+            Ok(fip) => votes.push(ActiveVote { fip_number, fip }),
+            Err(e) => {
+                let res = format!("{}: {}", FIP_INFO_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::InternalServerError().body(res);
+            }
+        }
+    }
+
+    HttpResponse::Ok().json(votes)
 }
 
-#[get("/filecoin/votehistory")]
-async fn get_concluded_votes(
+#[utoipa::path(
+    get,
+    path = "/filecoin/votehistory",
+    tag = "votes",
+    params(NtwParams),
+    responses(
+        (status = 200, description = "FIP numbers of the concluded votes", body = [u32]),
+        (status = 400, description = "Invalid network or request", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_concluded_votes<F: StoreFactory>(
     query_params: web::Query<NtwParams>,
     config: web::Data<Args>,
+    store: web::Data<F>,
 ) -> impl Responder {
-    println!("Concluded votes requested");
-    let ntw = match query_params.network.as_str() {
-        "mainnet" => Network::Mainnet,
-        "calibration" => Network::Testnet,
-        _ => return HttpResponse::BadRequest().body(INVALID_NETWORK),
-    };
+    let ntw = query_params.network;
 
     // Open a connection to the Redis Database
-    let mut redis = match Redis::new(config.redis_path()) {
+    let mut redis = match store.reader() {
         Ok(redis) => redis,
         Err(e) => {
             let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
@@ -196,21 +767,66 @@ async fn get_concluded_votes(
     HttpResponse::Ok().json(concluded_votes)
 }
 
-#[get("/filecoin/allconcludedvotes")]
-async fn get_all_concluded_votes(
+#[utoipa::path(
+    get,
+    path = "/filecoin/votes/stale",
+    tag = "votes",
+    params(NtwParams),
+    responses(
+        (status = 200, description = "FIP numbers of the votes past their deadline whose results are not stored yet", body = [u32]),
+        (status = 400, description = "Invalid network or request", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_stale_votes<F: StoreFactory>(
     query_params: web::Query<NtwParams>,
     config: web::Data<Args>,
+    store: web::Data<F>,
 ) -> impl Responder {
-    println!("All concluded votes requested");
+    let ntw = query_params.network;
 
-    let ntw = match query_params.network.as_str() {
-        "mainnet" => Network::Mainnet,
-        "calibration" => Network::Testnet,
-        _ => return HttpResponse::BadRequest().body(INVALID_NETWORK),
+    // Open a connection to the Redis Database
+    let mut redis = match store.reader() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
     };
 
+    match redis.stale_votes(ntw, config.vote_length()) {
+        Ok(stale_votes) => HttpResponse::Ok().json(stale_votes),
+        Err(e) => {
+            let res = format!("{}: {}", STALE_VOTES_ERROR, e);
+            println!("{}", res);
+            HttpResponse::InternalServerError().body(res)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/filecoin/allconcludedvotes",
+    tag = "votes",
+    params(NtwParams),
+    responses(
+        (status = 200, description = "Results of every concluded vote keyed by FIP number", body = HashMap<u32, VoteResults>),
+        (status = 304, description = "The results are unchanged since the tag sent in If-None-Match"),
+        (status = 400, description = "Invalid network or request", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_all_concluded_votes<F: StoreFactory>(
+    req: HttpRequest,
+    query_params: web::Query<NtwParams>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+
     // Open a connection to the Redis Database
-    let mut redis = match Redis::new(config.redis_path()) {
+    let mut redis = match store.reader() {
         Ok(redis) => redis,
         Err(e) => {
             let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
@@ -229,9 +845,22 @@ async fn get_all_concluded_votes(
         }
     };
 
+    // Votes conclude as time passes without the database changing
+    let tag = match redis.results_version() {
+        Ok(version) => entity_tag(&req, version, &(ntw, &concluded_votes)),
+        Err(e) => {
+            let res = format!("{}: {}", RESULTS_VERSION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+    if let Some(res) = not_modified(&req, &tag) {
+        return res;
+    }
+
     let mut vote_res_map = HashMap::new();
     for vote in concluded_votes.into_iter() {
-        let results = match redis.vote_results(vote, ntw) {
+        let mut results = match redis.vote_results(vote, config.vote_length(), ntw).await {
             Ok(results) => results,
             Err(e) => {
                 let res = format!("{}: {}", VOTE_RESULTS_ERROR, e);
@@ -239,28 +868,60 @@ async fn get_all_concluded_votes(
                 return HttpResponse::InternalServerError().body(res);
             }
         };
+        match request_metadata(&req, &mut redis, vote, ntw) {
+            Ok(metadata) => results.set_metadata(metadata.map(|(_, m)| m)),
+            Err(e) => {
+                let res = format!("{}: {}", VOTE_METADATA_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::InternalServerError().body(res);
+            }
+        }
+        match redis.fip_info(vote) {
+            Ok(info) => results.set_fip(info),
+            Err(e) => {
+                let res = format!("{}: {}", FIP_INFO_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::InternalServerError().body(res);
+            }
+        }
         vote_res_map.insert(vote, results);
     }
 
     println!("Concluded votes: {:?}", vote_res_map);
 
-    HttpResponse::Ok().json(vote_res_map)
+    // Listed by FIP number from version 2, the keys of the map can't be typed
+    if let Some(version) = requested_version(&req).filter(|version| *version >= 2) {
+        let mut results: Vec<_> = vote_res_map
+            .iter()
+            .map(|(vote, results)| ResultsDto::new(results, *vote, ntw, version))
+            .collect();
+        results.sort_by_key(|results| results.fip_number);
+        return tagged(Envelope::new(results, version).respond(), &tag);
+    }
+
+    tagged(HttpResponse::Ok().json(vote_res_map), &tag)
 }
 
-#[get("/filecoin/votingpower")]
-async fn get_voting_power(
-    query_params: web::Query<NtwAddrParams>,
-    config: web::Data<Args>,
+#[utoipa::path(
+    get,
+    path = "/filecoin/votingpower",
+    tag = "voters",
+    params(VotingPowerParams),
+    responses(
+        (status = 200, description = "Raw byte power of the voter in bytes", body = String),
+        (status = 400, description = "Invalid network or request", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_voting_power<F: StoreFactory>(
+    req: HttpRequest,
+    query_params: web::Query<VotingPowerParams>,
+    store: web::Data<F>,
 ) -> impl Responder {
-    println!("Voting power requested");
     let address = query_params.address.clone();
-    let ntw = match query_params.network.as_str() {
-        "mainnet" => Network::Mainnet,
-        "calibration" => Network::Testnet,
-        _ => return HttpResponse::BadRequest().body(INVALID_NETWORK),
-    };
+    let ntw = query_params.network;
 
-    let address = match Address::from_str(address.as_str()) {
+    let address = match parse_address(ntw, &address) {
         Ok(address) => address,
         Err(e) => {
             let res = format!("{}: {}", INVALID_ADDRESS, e);
@@ -269,7 +930,7 @@ async fn get_voting_power(
         }
     };
 
-    let mut redis = match Redis::new(config.redis_path()) {
+    let mut redis = match store.reader() {
         Ok(redis) => redis,
         Err(e) => {
             let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
@@ -287,46 +948,131 @@ async fn get_voting_power(
         }
     };
 
-    let mut voting_power = 0;
-    if STARTING_AUTHORIZED_VOTERS
-        .map(|s| Address::from_str(s).unwrap())
-        .contains(&address)
-    {
-        voting_power += 10240000;
-    }
-    for delegate in authorized.iter() {
-        match fetch_storage_amount(*delegate, ntw).await {
-            Ok(amount) => voting_power += amount,
-            Err(e) => {
-                let res = format!("{}: {}", VOTING_POWER_ERROR, e);
-                println!("{}", res);
-                return HttpResponse::InternalServerError().body(res);
-            }
+    // A refresh reads the chain and replaces the cached power of every storage provider
+    let power = redis.power_provider();
+    let powers = match query_params.refresh {
+        true => power.refresh_storage_amounts(&authorized, ntw).await,
+        false => power.storage_amounts(&authorized, ntw).await,
+    };
+    let voting_power = match powers.map(|powers| powers.values().sum::<u128>()) {
+        Ok(voting_power) => voting_power,
+        Err(e) => {
+            let res = format!("{}: {}", VOTING_POWER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
         }
-    }
+    };
 
     println!(
         "Voting power: {} for address: {} and delegates {:?}",
         voting_power, address, authorized
     );
 
+    if let Some(version) = requested_version(&req).filter(|version| *version >= 2) {
+        let dto = VotingPowerDto::new(address, ntw, voting_power, None, version);
+        return Envelope::new(dto, version).respond();
+    }
+
     HttpResponse::Ok().body(voting_power.to_string())
 }
 
-#[get("/filecoin/voterstarters")]
-async fn get_vote_starters(
-    query_params: web::Query<NtwParams>,
-    config: web::Data<Args>,
+/// Voting power of the address at a fixed chain height
+///
+/// Uses the storage providers currently registered to the address, only the
+/// power is read from the snapshot
+#[utoipa::path(
+    get,
+    path = "/filecoin/votingpower/at",
+    tag = "voters",
+    params(NtwAddrHeightParams),
+    responses(
+        (status = 200, description = "Raw byte power of the voter in bytes at the height", body = String),
+        (status = 400, description = "Invalid network, address or height", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_voting_power_at<F: StoreFactory>(
+    req: HttpRequest,
+    query_params: web::Query<NtwAddrHeightParams>,
+    store: web::Data<F>,
 ) -> impl Responder {
-    println!("Vote starters requested");
-    let ntw = match query_params.network.as_str() {
-        "mainnet" => Network::Mainnet,
-        "calibration" => Network::Testnet,
-        _ => return HttpResponse::BadRequest().body(INVALID_NETWORK),
+    let address = query_params.address.clone();
+    let height = query_params.height;
+    let ntw = query_params.network;
+
+    let address = match parse_address(ntw, &address) {
+        Ok(address) => address,
+        Err(e) => {
+            let res = format!("{}: {}", INVALID_ADDRESS, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+
+    let mut redis = match store.reader() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    let authorized = match redis.voter_delegates(address, ntw) {
+        Ok(delegates) => delegates,
+        Err(e) => {
+            let res = format!("{}: {}", VOTER_DELEGATES_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
     };
 
+    let voting_power: u128 = match fetch_storage_amounts_at(&authorized, ntw, height).await {
+        Ok(powers) => powers.values().sum(),
+        Err(StorageFetchError::NoTipset(_)) => {
+            let res = format!("{}: {}", INVALID_HEIGHT, height);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+        Err(e) => {
+            let res = format!("{}: {}", VOTING_POWER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    println!(
+        "Voting power: {} for address: {} and delegates {:?} at height {}",
+        voting_power, address, authorized, height
+    );
+
+    if let Some(version) = requested_version(&req).filter(|version| *version >= 2) {
+        let dto = VotingPowerDto::new(address, ntw, voting_power, Some(height), version);
+        return Envelope::new(dto, version).respond();
+    }
+
+    HttpResponse::Ok().body(voting_power.to_string())
+}
+
+#[utoipa::path(
+    get,
+    path = "/filecoin/voterstarters",
+    tag = "starters",
+    params(NtwParams),
+    responses(
+        (status = 200, description = "Addresses authorized to start votes", body = [Address]),
+        (status = 400, description = "Invalid network or request", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_vote_starters<F: StoreFactory>(
+    query_params: web::Query<NtwParams>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+
     // Open a connection to the Redis Database
-    let mut redis = match Redis::new(config.redis_path()) {
+    let mut redis = match store.reader() {
         Ok(redis) => redis,
         Err(e) => {
             let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
@@ -349,3 +1095,367 @@ async fn get_vote_starters(
 
     HttpResponse::Ok().json(vote_starters)
 }
+
+#[utoipa::path(
+    get,
+    path = "/filecoin/votestats",
+    tag = "votes",
+    params(NtwFipParams),
+    responses(
+        (status = 200, description = "Registered storage that did not vote", body = SilenceReport),
+        (status = 400, description = "Invalid network or request", body = String),
+        (status = 403, description = "The vote is in progress, the body is the seconds left, or it has not opened yet", body = String),
+        (status = 404, description = "The vote does not exist"),
+        (status = 409, description = "The silence report is not stored yet", body = String),
+        (status = 410, description = "The vote was cancelled", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_vote_stats<F: StoreFactory>(
+    req: HttpRequest,
+    query_params: web::Query<NtwFipParams>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+    let num = query_params.fip_number;
+
+    // Open a connection to the Redis Database
+    let mut redis = match store.reader() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    // Only concluded votes have stats
+    match redis.vote_status(num, config.vote_length(), ntw) {
+        Ok(VoteStatus::Concluded) => (),
+        Ok(VoteStatus::InProgress(time_left)) => {
+            return HttpResponse::Forbidden().body(time_left.to_string())
+        }
+        Ok(VoteStatus::Scheduled(opens_in)) => {
+            return HttpResponse::Forbidden().body(format!("{}: {}", VOTE_NOT_OPEN_ERROR, opens_in))
+        }
+        Ok(VoteStatus::DoesNotExist) => return HttpResponse::NotFound().finish(),
+        Ok(VoteStatus::Cancelled) => return HttpResponse::Gone().body(VOTE_CANCELLED),
+        Ok(VoteStatus::Unknown) => return HttpResponse::Conflict().body(VOTE_STATUS_UNKNOWN),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_STATUS_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    }
+
+    // Get the registered storage that did not vote
+    let silence = match redis.silence_report(num, config.vote_length(), ntw) {
+        Ok(Some(silence)) => silence,
+        Ok(None) => return HttpResponse::Conflict().body(SILENCE_REPORT_PENDING),
+        Err(e) => {
+            let res = format!("{}: {}", SILENCE_REPORT_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    println!("Silence report: {:?} for FIP: {}", silence, num);
+
+    if let Some(version) = requested_version(&req).filter(|version| *version >= 2) {
+        return Envelope::new(SilenceDto::new(&silence, version), version).respond();
+    }
+
+    HttpResponse::Ok().json(silence)
+}
+
+/// Share of the network power that took part in a vote
+#[utoipa::path(
+    get,
+    path = "/filecoin/turnout",
+    tag = "votes",
+    params(NtwFipParams),
+    responses(
+        (status = 200, description = "Participation compared to the network power", body = Turnout),
+        (status = 400, description = "Invalid network or request", body = String),
+        (status = 404, description = "The vote does not exist"),
+        (status = 410, description = "The vote was cancelled", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_turnout<F: StoreFactory>(
+    req: HttpRequest,
+    query_params: web::Query<NtwFipParams>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+    let num = query_params.fip_number;
+
+    // Open a connection to the Redis Database
+    let mut redis = match store.reader() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    match redis.vote_status(num, config.vote_length(), ntw) {
+        Ok(VoteStatus::Concluded)
+        | Ok(VoteStatus::InProgress(_))
+        | Ok(VoteStatus::Scheduled(_)) => (),
+        Ok(VoteStatus::DoesNotExist) => return HttpResponse::NotFound().finish(),
+        Ok(VoteStatus::Cancelled) => return HttpResponse::Gone().body(VOTE_CANCELLED),
+        Ok(VoteStatus::Unknown) => return HttpResponse::Conflict().body(VOTE_STATUS_UNKNOWN),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_STATUS_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    }
+
+    let network_storage_size = match fetch_network_power(ntw).await {
+        Ok(power) => power,
+        Err(e) => {
+            let res = format!("{}: {}", STORAGE_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    match redis.turnout(num, ntw, network_storage_size) {
+        Ok(turnout) => match requested_version(&req).filter(|version| *version >= 2) {
+            Some(version) => Envelope::new(TurnoutDto::new(&turnout, version), version).respond(),
+            None => HttpResponse::Ok().json(turnout),
+        },
+        Err(e) => {
+            let res = format!("{}: {}", TURNOUT_ERROR, e);
+            println!("{}", res);
+            HttpResponse::InternalServerError().body(res)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/filecoin/votemetadata",
+    tag = "votes",
+    params(NtwFipParams),
+    responses(
+        (status = 200, description = "Metadata in the language preferred by the Accept-Language header", body = VoteMetadata),
+        (status = 400, description = "Invalid network or request", body = String),
+        (status = 404, description = "The vote has no metadata"),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_vote_metadata<F: StoreFactory>(
+    req: HttpRequest,
+    query_params: web::Query<NtwFipParams>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+    let num = query_params.fip_number;
+
+    // Open a connection to the Redis Database
+    let mut redis = match store.reader() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    // Get the metadata in the language the request prefers
+    let (language, metadata) = match request_metadata(&req, &mut redis, num, ntw) {
+        Ok(Some(metadata)) => metadata,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_METADATA_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    println!("Vote metadata: {:?} for FIP: {}", metadata, num);
+
+    HttpResponse::Ok()
+        .insert_header((header::CONTENT_LANGUAGE, language))
+        .json(metadata)
+}
+
+/// Storage power of the storage providers registered to the voter
+pub(crate) async fn voting_power(
+    power: &dyn StoragePowerProvider,
+    authorized: &[u32],
+    ntw: Network,
+) -> Result<u128, StorageFetchError> {
+    let powers = power.storage_amounts(authorized, ntw).await?;
+
+    Ok(powers.values().sum())
+}
+
+/// Returns the vote metadata in the language preferred by the `Accept-Language` header
+fn request_metadata(
+    req: &HttpRequest,
+    redis: &mut impl VoteStore,
+    fip_number: FipNumber,
+    ntw: Network,
+) -> Result<Option<(String, VoteMetadata)>, StoreError> {
+    let available = redis.vote_metadata(fip_number, ntw)?;
+
+    let accept_language = req
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("");
+
+    Ok(select_language(accept_language, &available))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{http::StatusCode, test, App};
+    use clap::Parser;
+
+    use super::*;
+    use crate::{
+        authorized_voters,
+        clock::MockClock,
+        conclusions::conclude_stale,
+        memory_store::MemoryStore,
+        messages::votes::{test_votes::test_vote, VoteOption},
+        redis::SilenceReport,
+        test_redis::{StaticPower, TEST_SP_ID, TEST_SP_POWER},
+    };
+
+    #[actix_web::test]
+    async fn get_vote_starters_from_store() {
+        let mut store = MemoryStore::default();
+        let starter = authorized_voters()[0];
+        store
+            .register_voter_starter(starter, Network::Testnet)
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(store))
+                .configure(routes::<MemoryStore>),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/filecoin/voterstarters?network=calibration")
+            .to_request();
+        let starters: Vec<Address> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(starters, vec![starter]);
+
+        let req = test::TestRequest::get()
+            .uri("/filecoin/voterstarters?network=mainnet")
+            .to_request();
+        let starters: Vec<Address> = test::call_and_read_body_json(&app, req).await;
+        assert!(starters.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn get_votes_status() {
+        let args = Args::parse_from(["filecoin-vote", "--vote-length", "60"]);
+        let clock = Arc::new(MockClock::new(1_700_000_000));
+        let mut store = MemoryStore::default();
+        store.set_clock(clock.clone());
+
+        let fip = FipNumber::try_from(5u32).unwrap();
+        store
+            .start_vote(fip, authorized_voters()[0], Network::Testnet)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(args))
+                .app_data(web::Data::new(store))
+                .configure(routes::<MemoryStore>),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/filecoin/vote?network=calibration&fip_number=6")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        clock.advance(20);
+        let req = test::TestRequest::get()
+            .uri("/filecoin/vote?network=calibration&fip_number=5")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(test::read_body(res).await, "40");
+    }
+
+    #[actix_web::test]
+    async fn get_vote_stats_from_store() {
+        let args = Args::parse_from(["filecoin-vote", "--vote-length", "60"]);
+        let clock = Arc::new(MockClock::new(1_700_000_000));
+        let mut store = MemoryStore::default();
+        store.set_clock(clock.clone());
+        store.set_power_provider(StaticPower::new([
+            (TEST_SP_ID, TEST_SP_POWER),
+            (1001, 5000),
+        ]));
+
+        let ntw = Network::Testnet;
+        let fip = FipNumber::try_from(2u32).unwrap();
+        let vote = test_vote(VoteOption::Yay, 2).vote().unwrap();
+        let silent = Address::from_low_u64_be(42);
+        store
+            .register_voter(vote.voter(), ntw, vec![TEST_SP_ID])
+            .unwrap();
+        store.register_voter(silent, ntw, vec![1001]).unwrap();
+        store
+            .start_vote(fip, authorized_voters()[0], ntw)
+            .await
+            .unwrap();
+        store
+            .add_vote(fip, vote.clone(), vote.voter(), 60, false)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(args))
+                .app_data(web::Data::new(store.clone()))
+                .configure(routes::<MemoryStore>),
+        )
+        .await;
+        let stats = || {
+            test::TestRequest::get()
+                .uri("/filecoin/votestats?network=calibration&fip_number=2")
+                .to_request()
+        };
+
+        // The report is stored when the vote is concluded
+        clock.advance(61);
+        let res = test::call_service(&app, stats()).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+
+        assert_eq!(
+            conclude_stale(&mut store, 60).await.unwrap(),
+            vec![(fip, ntw)]
+        );
+        let report: SilenceReport = test::call_and_read_body_json(&app, stats()).await;
+        assert_eq!(report.silent_voters(), 1);
+        assert_eq!(report.silent_storage_size(), 5000);
+
+        // Voters registered after the vote concluded aren't counted as silent
+        store
+            .register_voter(Address::from_low_u64_be(43), ntw, vec![1001])
+            .unwrap();
+        let report: SilenceReport = test::call_and_read_body_json(&app, stats()).await;
+        assert_eq!(report.silent_voters(), 1);
+    }
+}
@@ -0,0 +1,385 @@
+//! GraphQL queries over the same data as the REST endpoints
+//!
+//! Lets a frontend read votes, results, voters and voting power of several
+//! FIPs in one request. Everything is read through [`VoteStore`], there are
+//! no mutations since every change has to be signed through the REST endpoints
+use std::str::FromStr;
+
+use actix_web::{get, post, web, HttpResponse, Responder};
+use async_graphql::{
+    http::GraphiQLSource, Context, EmptyMutation, EmptySubscription, Enum, Error, Json, Object,
+    Result, Schema,
+};
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+
+use crate::{
+    errors::*,
+    fip::FipNumber,
+    fip_registry::FipInfo,
+    get::voting_power,
+    messages::vote_metadata::{select_language, VoteMetadata},
+    redis::{Redis, RedisFactory, VoteResults, VoteStatus, VoterBallot},
+    storage::{parse_address, sp_id_format, Network},
+    store::{StoreFactory, VoteStore},
+    Args,
+};
+
+pub type FipSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+/// Builds the schema, resolvers read the stores `store` opens
+pub fn schema(args: Args, store: RedisFactory) -> FipSchema {
+    Schema::build(Query, EmptyMutation, EmptySubscription)
+        .data(args)
+        .data(store)
+        .finish()
+}
+
+#[post("/graphql")]
+async fn post_graphql(schema: web::Data<FipSchema>, req: GraphQLRequest) -> GraphQLResponse {
+    schema.execute(req.into_inner()).await.into()
+}
+
+/// Serves GraphiQL so queries can be tried out from a browser
+#[get("/graphql")]
+async fn get_graphiql() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    /// A single vote, null if it was never started
+    async fn vote(
+        &self,
+        ctx: &Context<'_>,
+        network: String,
+        fip_number: u32,
+    ) -> Result<Option<VoteNode>> {
+        let ntw = network_arg(&network)?;
+        let fip_number = fip_arg(fip_number)?;
+
+        let mut redis = open(ctx)?;
+        match redis
+            .vote_exists(ntw, fip_number)
+            .map_err(|e| error(VOTE_EXISTS_ERROR, e))?
+        {
+            true => Ok(Some(VoteNode { ntw, fip_number })),
+            false => Ok(None),
+        }
+    }
+
+    /// Votes still in progress
+    async fn active_votes(&self, ctx: &Context<'_>, network: String) -> Result<Vec<VoteNode>> {
+        let ntw = network_arg(&network)?;
+
+        let config = ctx.data::<Args>()?;
+        let votes = open(ctx)?
+            .active_votes(ntw, config.vote_length())
+            .map_err(|e| error(ACTIVE_VOTES_ERROR, e))?;
+
+        Ok(votes
+            .into_iter()
+            .map(|fip_number| VoteNode { ntw, fip_number })
+            .collect())
+    }
+
+    /// Votes whose voting period is over
+    async fn concluded_votes(&self, ctx: &Context<'_>, network: String) -> Result<Vec<VoteNode>> {
+        let ntw = network_arg(&network)?;
+
+        let config = ctx.data::<Args>()?;
+        let votes = open(ctx)?
+            .concluded_votes(ntw, config.vote_length())
+            .map_err(|e| error(CONCLUDED_VOTES_ERROR, e))?;
+
+        Ok(votes
+            .into_iter()
+            .map(|fip_number| VoteNode { ntw, fip_number })
+            .collect())
+    }
+
+    /// A voter by its 0x or f410 address, registered or not
+    async fn voter(&self, network: String, address: String) -> Result<Voter> {
+        let ntw = network_arg(&network)?;
+        let address = parse_address(ntw, &address).map_err(|e| error(INVALID_ADDRESS, e))?;
+
+        Ok(Voter { ntw, address })
+    }
+
+    /// Registered voters ordered by address
+    async fn voters(
+        &self,
+        ctx: &Context<'_>,
+        network: String,
+        #[graphql(default)] offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<Voter>> {
+        let ntw = network_arg(&network)?;
+
+        let page = open(ctx)?
+            .voter_page(ntw, offset, limit)
+            .map_err(|e| error(REGISTERED_VOTERS_ERROR, e))?;
+
+        Ok(page
+            .voters
+            .into_iter()
+            .map(|voter| Voter {
+                ntw,
+                address: voter.address,
+            })
+            .collect())
+    }
+
+    /// Addresses authorized to start votes
+    async fn vote_starters(&self, ctx: &Context<'_>, network: String) -> Result<Vec<String>> {
+        let ntw = network_arg(&network)?;
+
+        let starters = open(ctx)?
+            .voter_starters(ntw)
+            .map_err(|e| error(VOTE_STARTERS_ERROR, e))?;
+
+        Ok(starters.iter().map(|s| format!("{:?}", s)).collect())
+    }
+}
+
+/// Whether a vote is still running
+#[derive(Copy, Clone, PartialEq, Eq, Enum)]
+pub enum VoteState {
+    /// Started to open for ballots later
+    Scheduled,
+    InProgress,
+    Concluded,
+    Cancelled,
+}
+
+pub struct VoteNode {
+    ntw: Network,
+    fip_number: FipNumber,
+}
+
+#[Object]
+impl VoteNode {
+    async fn fip_number(&self) -> u32 {
+        self.fip_number.get()
+    }
+
+    async fn network(&self) -> &'static str {
+        self.ntw.name()
+    }
+
+    async fn status(&self, ctx: &Context<'_>) -> Result<VoteState> {
+        match self.vote_status(ctx)? {
+            VoteStatus::Scheduled(_) => Ok(VoteState::Scheduled),
+            VoteStatus::InProgress(_) => Ok(VoteState::InProgress),
+            VoteStatus::Concluded => Ok(VoteState::Concluded),
+            VoteStatus::Cancelled => Ok(VoteState::Cancelled),
+            VoteStatus::DoesNotExist => Err(Error::new("Vote does not exist")),
+            VoteStatus::Unknown => Err(Error::new(VOTE_STATUS_UNKNOWN)),
+        }
+    }
+
+    /// Seconds until the vote concludes, null once it is over
+    async fn time_left(&self, ctx: &Context<'_>) -> Result<Option<u64>> {
+        match self.vote_status(ctx)? {
+            VoteStatus::InProgress(time_left) => Ok(Some(time_left)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Seconds until a scheduled vote opens for ballots, null once it is open
+    async fn opens_in(&self, ctx: &Context<'_>) -> Result<Option<u64>> {
+        match self.vote_status(ctx)? {
+            VoteStatus::Scheduled(opens_in) => Ok(Some(opens_in)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Results in the same format as `/filecoin/vote`, null until the vote concludes
+    async fn results(&self, ctx: &Context<'_>) -> Result<Option<Json<VoteResults>>> {
+        if self.vote_status(ctx)? != VoteStatus::Concluded {
+            return Ok(None);
+        }
+
+        let config = ctx.data::<Args>()?;
+        let results = open(ctx)?
+            .vote_results(self.fip_number, config.vote_length(), self.ntw)
+            .await
+            .map_err(|e| error(VOTE_RESULTS_ERROR, e))?;
+
+        Ok(Some(Json(results)))
+    }
+
+    /// Title, status and discussion link from the FIPs repository, null until synced
+    async fn fip(&self, ctx: &Context<'_>) -> Result<Option<Json<FipInfo>>> {
+        let info = open(ctx)?
+            .fip_info(self.fip_number)
+            .map_err(|e| error(FIP_INFO_ERROR, e))?;
+
+        Ok(info.map(Json))
+    }
+
+    /// Title and description in the language preferred by an `Accept-Language` style list
+    async fn metadata(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] language: String,
+    ) -> Result<Option<Json<VoteMetadata>>> {
+        let available = open(ctx)?
+            .vote_metadata(self.fip_number, self.ntw)
+            .map_err(|e| error(VOTE_METADATA_ERROR, e))?;
+
+        match select_language(&language, &available) {
+            Some((_, metadata)) => Ok(Some(Json(metadata))),
+            None => Ok(None),
+        }
+    }
+}
+
+impl VoteNode {
+    fn vote_status(&self, ctx: &Context<'_>) -> Result<VoteStatus> {
+        let config = ctx.data::<Args>()?;
+
+        open(ctx)?
+            .vote_status(self.fip_number, config.vote_length(), self.ntw)
+            .map_err(|e| error(VOTE_STATUS_ERROR, e))
+    }
+}
+
+pub struct Voter {
+    ntw: Network,
+    address: ethers::types::Address,
+}
+
+#[Object]
+impl Voter {
+    async fn address(&self) -> String {
+        format!("{:?}", self.address)
+    }
+
+    /// Storage providers the voter votes for
+    async fn delegates(&self, ctx: &Context<'_>) -> Result<Vec<String>> {
+        Ok(self
+            .delegate_ids(ctx)?
+            .into_iter()
+            .map(|d| sp_id_format(self.ntw, d))
+            .collect())
+    }
+
+    /// Raw byte power of the voter in bytes, as a string since it can exceed 64 bits
+    async fn voting_power(&self, ctx: &Context<'_>) -> Result<String> {
+        // The connection isn't held across the chain lookup
+        let (delegates, provider) = {
+            let mut redis = open(ctx)?;
+            let delegates = redis
+                .voter_delegates(self.address, self.ntw)
+                .map_err(|e| error(VOTER_DELEGATES_ERROR, e))?;
+            (delegates, redis.power_provider())
+        };
+
+        let power = voting_power(provider.as_ref(), &delegates, self.ntw)
+            .await
+            .map_err(|e| error(VOTING_POWER_ERROR, e))?;
+
+        Ok(power.to_string())
+    }
+
+    /// Ballots the voter cast ordered by FIP number
+    async fn history(&self, ctx: &Context<'_>) -> Result<Vec<Json<VoterBallot>>> {
+        let history = open(ctx)?
+            .voter_history(self.address, self.ntw)
+            .map_err(|e| error(VOTER_HISTORY_ERROR, e))?;
+
+        Ok(history.into_iter().map(Json).collect())
+    }
+}
+
+impl Voter {
+    fn delegate_ids(&self, ctx: &Context<'_>) -> Result<Vec<u32>> {
+        open(ctx)?
+            .voter_delegates(self.address, self.ntw)
+            .map_err(|e| error(VOTER_DELEGATES_ERROR, e))
+    }
+}
+
+fn open(ctx: &Context<'_>) -> Result<Redis> {
+    ctx.data::<RedisFactory>()?
+        .reader()
+        .map_err(|e| error(OPEN_CONNECTION_ERROR, e))
+}
+
+fn network_arg(network: &str) -> Result<Network> {
+    Network::from_str(network).map_err(|e| Error::new(e.to_string()))
+}
+
+fn fip_arg(fip_number: u32) -> Result<FipNumber> {
+    FipNumber::try_from(fip_number).map_err(|e| Error::new(e.to_string()))
+}
+
+/// Prints the error like the REST handlers and returns it to the client
+fn error(message: &str, e: impl std::fmt::Display) -> Error {
+    let res = format!("{}: {}", message, e);
+    println!("{}", res);
+    Error::new(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::test_redis::TestRedis;
+
+    fn schema_for(server: &TestRedis) -> FipSchema {
+        let args = Args::parse_from(["filecoin-vote", "--redis-path", server.url().as_str()]);
+        let store = RedisFactory::from_args(&args);
+
+        schema(args, store)
+    }
+
+    #[tokio::test]
+    async fn graphql_votes_and_voters() {
+        let mut server = TestRedis::start();
+        let starter = crate::authorized_voters()[0];
+        let fip = FipNumber::try_from(5).unwrap();
+        server
+            .start_vote(fip, starter, Network::Testnet)
+            .await
+            .unwrap();
+
+        let schema = schema_for(&server);
+        let res = schema
+            .execute(
+                r#"{
+                    vote(network: "calibnet", fipNumber: 5) { fipNumber network status }
+                    missing: vote(network: "calibration", fipNumber: 6) { fipNumber }
+                    activeVotes(network: "calibration") { fipNumber timeLeft }
+                    voters(network: "calibration") { address }
+                }"#,
+            )
+            .await;
+
+        assert!(res.errors.is_empty(), "{:?}", res.errors);
+        let data = res.data.into_json().unwrap();
+        assert_eq!(data["vote"]["network"], "calibration");
+        assert_eq!(data["vote"]["status"], "IN_PROGRESS");
+        assert!(data["missing"].is_null());
+        assert_eq!(data["activeVotes"][0]["fipNumber"], 5);
+        assert_eq!(data["voters"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn graphql_unknown_network() {
+        let server = TestRedis::start();
+        let schema = schema_for(&server);
+
+        let res = schema
+            .execute(r#"{ activeVotes(network: "devnet") { fipNumber } }"#)
+            .await;
+
+        assert_eq!(res.errors.len(), 1);
+        assert!(res.errors[0].message.contains("Unknown network"));
+    }
+}
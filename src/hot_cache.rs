@@ -0,0 +1,293 @@
+//! Per process cache of the running tallies and metadata of votes
+//!
+//! Results endpoints are read far more often than ballots are cast during the
+//! last hours of a vote. Every change the store logs is also published on
+//! [`CHANNEL`], and each server drops what the change left stale as soon as it
+//! hears of it. Entries expire after the configured ttl as well, in case a
+//! message is missed
+use std::{
+    collections::BTreeMap,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use redis::RedisError;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    fip::FipNumber, messages::vote_metadata::VoteMetadata, redis::VoteResults, storage::Network,
+};
+
+/// Channel the changes made to the database are published on
+pub const CHANNEL: &str = "fipvote:invalidations";
+
+/// Wait before subscribing again after losing the connection
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// What a change to the database leaves stale
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Invalidation {
+    /// A change to a single vote, its ballots, rules or metadata
+    Vote { fip_number: FipNumber, ntw: Network },
+    /// A change to the voters of a network, whose classes ballots are tallied by
+    Network(Network),
+    /// Everything, after the database was flushed or a voter left every network
+    All,
+}
+
+type Entries<T> = BTreeMap<(Network, FipNumber), (T, Instant)>;
+
+/// Running tallies and metadata of votes, kept until they are `ttl` old or invalidated
+struct HotCache {
+    ttl: Duration,
+    /// Bumped by every invalidation, so a value read before one isn't cached after it
+    generation: u64,
+    tallies: Entries<VoteResults>,
+    metadata: Entries<BTreeMap<String, VoteMetadata>>,
+}
+
+static HOT_CACHE: Mutex<HotCache> = Mutex::new(HotCache {
+    ttl: Duration::ZERO,
+    generation: 0,
+    tallies: BTreeMap::new(),
+    metadata: BTreeMap::new(),
+});
+
+/// Caches running tallies and vote metadata for `ttl` seconds, a ttl of 0 turns caching off
+pub fn configure_hot_cache(ttl: u64) {
+    let mut cache = HOT_CACHE.lock().unwrap();
+    cache.ttl = Duration::from_secs(ttl);
+    cache.tallies.clear();
+    cache.metadata.clear();
+}
+
+/// Taken before reading what is cached from the database and handed back when caching it
+pub fn generation() -> u64 {
+    HOT_CACHE.lock().unwrap().generation
+}
+
+/// Tally of a vote that hasn't concluded, if it is cached
+pub fn tally(fip_number: FipNumber, ntw: Network) -> Option<VoteResults> {
+    let cache = HOT_CACHE.lock().unwrap();
+    fresh(&cache.tallies, cache.ttl, fip_number, ntw, Instant::now())
+}
+
+pub fn insert_tally(fip_number: FipNumber, ntw: Network, results: VoteResults, generation: u64) {
+    let mut cache = HOT_CACHE.lock().unwrap();
+    cache.insert_tally(fip_number, ntw, results, generation, Instant::now());
+}
+
+/// Metadata of a vote in every language, if it is cached
+pub fn metadata(fip_number: FipNumber, ntw: Network) -> Option<BTreeMap<String, VoteMetadata>> {
+    let cache = HOT_CACHE.lock().unwrap();
+    fresh(&cache.metadata, cache.ttl, fip_number, ntw, Instant::now())
+}
+
+pub fn insert_metadata(
+    fip_number: FipNumber,
+    ntw: Network,
+    metadata: BTreeMap<String, VoteMetadata>,
+    generation: u64,
+) {
+    let mut cache = HOT_CACHE.lock().unwrap();
+    cache.insert_metadata(fip_number, ntw, metadata, generation, Instant::now());
+}
+
+/// Drops what a change left stale
+pub fn invalidate(invalidation: Invalidation) {
+    HOT_CACHE.lock().unwrap().invalidate(invalidation);
+}
+
+/// Listens for the changes every server makes to the database for as long as the process runs
+///
+/// Everything is dropped whenever the subscription is made, since changes
+/// made while it wasn't listening were missed
+pub fn subscribe(redis_path: Url) {
+    thread::spawn(move || loop {
+        if let Err(e) = listen(&redis_path) {
+            println!("Error listening for cache invalidations: {}", e);
+        }
+        invalidate(Invalidation::All);
+        thread::sleep(RECONNECT_DELAY);
+    });
+}
+
+fn listen(redis_path: &Url) -> Result<(), RedisError> {
+    let client = redis::Client::open(redis_path.clone())?;
+    let mut con = client.get_connection()?;
+    let mut pubsub = con.as_pubsub();
+    pubsub.subscribe(CHANNEL)?;
+
+    invalidate(Invalidation::All);
+
+    loop {
+        let payload: String = pubsub.get_message()?.get_payload()?;
+        match serde_json::from_str(&payload) {
+            Ok(invalidation) => invalidate(invalidation),
+            Err(_) => invalidate(Invalidation::All),
+        }
+    }
+}
+
+fn fresh<T: Clone>(
+    entries: &Entries<T>,
+    ttl: Duration,
+    fip_number: FipNumber,
+    ntw: Network,
+    now: Instant,
+) -> Option<T> {
+    if ttl.is_zero() {
+        return None;
+    }
+
+    match entries.get(&(ntw, fip_number)) {
+        Some((value, cached)) if now.duration_since(*cached) < ttl => Some(value.clone()),
+        _ => None,
+    }
+}
+
+impl HotCache {
+    /// Whether a value read at `generation` can be cached at `now`, dropping expired entries
+    fn admits(&mut self, generation: u64, now: Instant) -> bool {
+        if self.ttl.is_zero() || generation != self.generation {
+            return false;
+        }
+
+        let ttl = self.ttl;
+        self.tallies
+            .retain(|_, (_, cached)| now.duration_since(*cached) < ttl);
+        self.metadata
+            .retain(|_, (_, cached)| now.duration_since(*cached) < ttl);
+
+        true
+    }
+
+    fn insert_tally(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        results: VoteResults,
+        generation: u64,
+        now: Instant,
+    ) {
+        if self.admits(generation, now) {
+            self.tallies.insert((ntw, fip_number), (results, now));
+        }
+    }
+
+    fn insert_metadata(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        metadata: BTreeMap<String, VoteMetadata>,
+        generation: u64,
+        now: Instant,
+    ) {
+        if self.admits(generation, now) {
+            self.metadata.insert((ntw, fip_number), (metadata, now));
+        }
+    }
+
+    fn invalidate(&mut self, invalidation: Invalidation) {
+        self.generation += 1;
+
+        match invalidation {
+            Invalidation::Vote { fip_number, ntw } => {
+                self.tallies.remove(&(ntw, fip_number));
+                self.metadata.remove(&(ntw, fip_number));
+            }
+            Invalidation::Network(network) => {
+                self.tallies.retain(|(ntw, _), _| *ntw != network);
+                self.metadata.retain(|(ntw, _), _| *ntw != network);
+            }
+            Invalidation::All => {
+                self.tallies.clear();
+                self.metadata.clear();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fip(num: u32) -> FipNumber {
+        FipNumber::try_from(num).unwrap()
+    }
+
+    fn hot_cache(ttl: u64) -> HotCache {
+        HotCache {
+            ttl: Duration::from_secs(ttl),
+            generation: 0,
+            tallies: BTreeMap::new(),
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    fn results(yay: u64) -> VoteResults {
+        VoteResults {
+            yay,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn hot_cache_insert() {
+        let now = Instant::now();
+        let mut cache = hot_cache(60);
+
+        cache.insert_tally(fip(1), Network::Mainnet, results(1), 0, now);
+        let tally = |cache: &HotCache, ntw, at| fresh(&cache.tallies, cache.ttl, fip(1), ntw, at);
+
+        assert_eq!(tally(&cache, Network::Mainnet, now).unwrap().yay, 1);
+        assert!(tally(&cache, Network::Testnet, now).is_none());
+        assert!(tally(&cache, Network::Mainnet, now + Duration::from_secs(60)).is_none());
+
+        // Nothing is cached with a ttl of 0
+        let mut off = hot_cache(0);
+        off.insert_tally(fip(1), Network::Mainnet, results(1), 0, now);
+        assert!(off.tallies.is_empty());
+    }
+
+    #[test]
+    fn hot_cache_invalidate() {
+        let now = Instant::now();
+        let mut cache = hot_cache(60);
+
+        for (num, ntw) in [
+            (1, Network::Mainnet),
+            (2, Network::Mainnet),
+            (1, Network::Testnet),
+        ] {
+            cache.insert_tally(fip(num), ntw, results(num.into()), cache.generation, now);
+            cache.insert_metadata(fip(num), ntw, BTreeMap::new(), cache.generation, now);
+        }
+
+        cache.invalidate(Invalidation::Vote {
+            fip_number: fip(1),
+            ntw: Network::Mainnet,
+        });
+        assert!(!cache.tallies.contains_key(&(Network::Mainnet, fip(1))));
+        assert!(!cache.metadata.contains_key(&(Network::Mainnet, fip(1))));
+        assert_eq!(cache.tallies.len(), 2);
+
+        cache.invalidate(Invalidation::Network(Network::Testnet));
+        assert_eq!(
+            cache.tallies.keys().collect::<Vec<_>>(),
+            vec![&(Network::Mainnet, fip(2))]
+        );
+
+        cache.invalidate(Invalidation::All);
+        assert!(cache.tallies.is_empty() && cache.metadata.is_empty());
+
+        // A tally read before an invalidation isn't cached after it
+        let generation = cache.generation;
+        cache.invalidate(Invalidation::All);
+        cache.insert_tally(fip(1), Network::Mainnet, results(1), generation, now);
+        assert!(cache.tallies.is_empty());
+    }
+}
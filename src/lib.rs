@@ -1,42 +1,277 @@
+pub mod conclusions;
+pub mod fip;
+pub mod fip_registry;
+pub mod hot_cache;
+pub mod limits;
+#[cfg(test)]
+pub mod memory_store;
+pub mod metrics;
+pub mod notifier;
+pub mod openapi;
+pub mod payload;
+pub mod publisher;
 pub mod redis;
+pub mod registrations;
+pub mod request_log;
 pub mod storage;
+pub mod store;
+#[cfg(test)]
+pub mod test_redis;
+pub mod tls;
 pub mod messages {
+    pub mod acceptance;
     pub mod auth;
+    pub mod class_registration;
+    pub mod delegate_removal;
+    pub mod domain;
+    pub mod nonce;
+    pub mod parser;
+    pub mod renewal;
+    pub mod role_grant;
+    pub mod signature;
+    pub mod typed_data;
+    pub mod unregistration;
+    pub mod vote_end;
+    pub mod vote_extension;
+    pub mod vote_flush;
+    pub mod vote_metadata;
     pub mod vote_registration;
     pub mod vote_start;
     pub mod votes;
 }
+pub mod api {
+    pub mod types;
+}
+pub mod admin;
+pub mod archive;
+pub mod attestation;
+pub mod bootstrap;
+pub mod clock;
+pub mod cors;
+pub mod doctor;
+#[cfg(feature = "e2e")]
+pub mod e2e;
 pub mod errors;
+pub mod etag;
+pub mod events;
 pub mod get;
+pub mod graphql;
 pub mod post;
+pub mod roles;
+pub mod sse;
+pub mod tally;
+pub mod templates;
+pub mod timeouts;
+pub mod validate;
 
-use std::str::FromStr;
+use std::{net::SocketAddr, path::PathBuf};
 
-use clap::{arg, command, Parser};
+use clap::{arg, command, ArgAction, Parser, Subcommand};
 use ethers::types::Address;
 use serde::Deserialize;
+use thiserror::Error;
 use url::Url;
+use utoipa::IntoParams;
 
-const STARTING_AUTHORIZED_VOTERS: [&str; 3] = [
-    "0x3B9705F0EF88Ee74B9924e34A5Af578d2E24F300",
-    "0x47f033Ed0F9485677008dC30507273607A74E92C",
-    "0xe662D77E7e3096683BAC8f1Ad526FB033E3810eB",
-];
+pub use bootstrap::authorized_voters;
+use fip::FipNumber;
+use messages::domain::DomainRules;
+use storage::{Network, PowerCacheBackend};
+use templates::TemplateKind;
 
 // Default values for command line arguments
 const VOTE_LENGTH: &str = "60";
+const DEFAULT_VOTE_GRACE_PERIOD: &str = "0";
 const REDIS_DEFAULT_PATH: &str = "redis://127.0.0.1:6379";
 const DEFAULT_SERVE_ADDRESS: &str = "http://127.0.0.1:51634";
+const DEFAULT_TLS_CERT: &str = "/etc/letsencrypt/live/sp-vote.com/fullchain.pem";
+const DEFAULT_TLS_KEY: &str = "/etc/letsencrypt/live/sp-vote.com/privkey.pem";
+const DEFAULT_READ_LIMIT: &str = "600";
+const DEFAULT_WRITE_LIMIT: &str = "60";
+const DEFAULT_RATE_LIMIT_WINDOW: &str = "60";
+const DEFAULT_POWER_CACHE_TTL: &str = "300";
+const DEFAULT_CACHE_TTL: &str = "5";
+const DEFAULT_PUBLISH_RPC: &str = "https://api.node.glif.io/rpc/v1";
+const DEFAULT_SHUTDOWN_TIMEOUT: &str = "30";
+const DEFAULT_KEEP_ALIVE: &str = "5";
+const DEFAULT_CLIENT_TIMEOUT: &str = "5";
+const DEFAULT_MAX_CONNECTIONS: &str = "25000";
+const DEFAULT_BACKLOG: &str = "2048";
+const DEFAULT_RPC_TIMEOUT: &str = "30";
+const DEFAULT_REQUEST_LOG: &str = "true";
+const DEFAULT_REGISTRATION_TTL: &str = "0";
+const DEFAULT_PUBLISH_CHAIN_ID: &str = "314";
+const DEFAULT_FIP_REPO_URL: &str =
+    "https://raw.githubusercontent.com/filecoin-project/FIPs/master/FIPS";
+const DEFAULT_FIP_SYNC_INTERVAL: &str = "3600";
+const DEFAULT_SERVICE_ID: &str = "fipvote";
 
 #[derive(Parser, Clone)]
 #[command(name = "filecoin-vote")]
 pub struct Args {
-    #[arg(short, long, default_value = DEFAULT_SERVE_ADDRESS)]
+    /// Address the API is served on, over HTTPS when the scheme is `https`
+    ///
+    /// The port defaults to 80 or 443 when the address doesn't have one
+    #[arg(short, long, env = "SERVE_ADDRESS", default_value = DEFAULT_SERVE_ADDRESS)]
     pub serve_address: Url,
-    #[arg(short, long, default_value = REDIS_DEFAULT_PATH)]
+    /// Comma separated sockets to listen on, such as `0.0.0.0:51634,[::]:51634`, the host and port of the serve address when unset
+    #[arg(long, env = "BIND", value_delimiter = ',')]
+    pub bind: Vec<SocketAddr>,
+    /// Redis database votes and registrations are kept in
+    #[arg(short, long, env = "REDIS_PATH", default_value = REDIS_DEFAULT_PATH)]
     pub redis_path: Url,
-    #[arg(short, long, default_value = VOTE_LENGTH)]
+    /// Read replica of the redis database GET requests are served from, the database itself when unset
+    #[arg(long, env = "REDIS_REPLICA_PATH")]
+    pub redis_replica_path: Option<Url>,
+    /// Length of a vote in seconds
+    #[arg(short, long, env = "VOTE_LENGTH", default_value = VOTE_LENGTH)]
     pub vote_length: u64,
+    /// Seconds ballots are still taken for after a vote ends, the vote concludes once they are over
+    #[arg(long, env = "VOTE_GRACE_PERIOD", default_value = DEFAULT_VOTE_GRACE_PERIOD)]
+    pub vote_grace_period: u64,
+    /// Replace a voter's previous ballot instead of rejecting it
+    #[arg(long, env = "ALLOW_REVOTE")]
+    pub allow_revote: bool,
+    /// PEM certificate chain used when serving over HTTPS
+    #[arg(long, env = "TLS_CERT", default_value = DEFAULT_TLS_CERT)]
+    pub tls_cert: PathBuf,
+    /// PEM private key used when serving over HTTPS
+    #[arg(long, env = "TLS_KEY", default_value = DEFAULT_TLS_KEY)]
+    pub tls_key: PathBuf,
+    /// Bearer token for the `/admin` endpoints, which are disabled when unset
+    #[arg(long, env = "ADMIN_TOKEN")]
+    pub admin_token: Option<String>,
+    /// GET requests allowed per client in each window, 0 disables the limit
+    #[arg(long, env = "READ_LIMIT", default_value = DEFAULT_READ_LIMIT)]
+    pub read_limit: u64,
+    /// POST and DELETE requests allowed per client in each window, 0 disables the limit
+    #[arg(long, env = "WRITE_LIMIT", default_value = DEFAULT_WRITE_LIMIT)]
+    pub write_limit: u64,
+    /// Length of the rate limit window in seconds
+    #[arg(long, env = "RATE_LIMIT_WINDOW", default_value = DEFAULT_RATE_LIMIT_WINDOW)]
+    pub rate_limit_window: u64,
+    /// Seconds fetched storage power is reused before asking the chain again, 0 disables the cache
+    #[arg(long, env = "POWER_CACHE_TTL", default_value = DEFAULT_POWER_CACHE_TTL)]
+    pub power_cache_ttl: u64,
+    /// Keep cached storage power in this process or in redis
+    #[arg(long, env = "POWER_CACHE", value_enum, default_value_t = PowerCacheBackend::Memory)]
+    pub power_cache: PowerCacheBackend,
+    /// Seconds running tallies and vote metadata are kept in memory between changes, 0 disables the cache
+    #[arg(long, env = "CACHE_TTL", default_value = DEFAULT_CACHE_TTL)]
+    pub cache_ttl: u64,
+    /// File holding the hex private key that signs attested results, attestation is disabled when unset
+    #[arg(long, env = "ATTESTATION_KEY")]
+    pub attestation_key: Option<PathBuf>,
+    /// FEVM contract concluded results are published to, publishing is disabled when unset
+    #[arg(long, env = "PUBLISH_CONTRACT")]
+    pub publish_contract: Option<Address>,
+    /// File holding the hex private key of the account that publishes results
+    #[arg(long, env = "PUBLISH_KEY")]
+    pub publish_key: Option<PathBuf>,
+    /// Chain id of the FEVM network the contract is deployed on
+    #[arg(long, env = "PUBLISH_CHAIN_ID", default_value = DEFAULT_PUBLISH_CHAIN_ID)]
+    pub publish_chain_id: u64,
+    /// Ethereum RPC endpoint used to submit the results
+    #[arg(long, env = "PUBLISH_RPC", default_value = DEFAULT_PUBLISH_RPC)]
+    pub publish_rpc: Url,
+    /// Seconds a voter registration stays valid before it has to be renewed, 0 keeps registrations until they are removed
+    #[arg(long, env = "REGISTRATION_TTL", default_value = DEFAULT_REGISTRATION_TTL)]
+    pub registration_ttl: u64,
+    /// Reject ballots, vote starts and starter changes signed without a nonce from `/filecoin/nonce`
+    #[arg(long, env = "REQUIRE_NONCE")]
+    pub require_nonce: bool,
+    /// Offer verified registrations to the voter instead of registering them, counting them once the voter accepts
+    #[arg(long, env = "REQUIRE_ACCEPTANCE")]
+    pub require_acceptance: bool,
+    /// Seconds in-flight requests and results publishing are given to finish on shutdown
+    #[arg(long, env = "SHUTDOWN_TIMEOUT", default_value = DEFAULT_SHUTDOWN_TIMEOUT)]
+    pub shutdown_timeout: u64,
+    /// Worker threads serving requests, one per physical core when unset
+    #[arg(long, env = "WORKERS", value_parser = clap::value_parser!(usize).range(1..))]
+    pub workers: Option<usize>,
+    /// Seconds an idle connection is kept open for the next request, 0 closes it after each response
+    #[arg(long, env = "KEEP_ALIVE", default_value = DEFAULT_KEEP_ALIVE)]
+    pub keep_alive: u64,
+    /// Seconds a client is given to send the headers of its request, 0 disables the timeout
+    #[arg(long, env = "CLIENT_TIMEOUT", default_value = DEFAULT_CLIENT_TIMEOUT)]
+    pub client_timeout: u64,
+    /// Connections each worker serves at once before new ones wait
+    #[arg(long, env = "MAX_CONNECTIONS", default_value = DEFAULT_MAX_CONNECTIONS)]
+    pub max_connections: usize,
+    /// Connections waiting to be accepted before new ones are refused
+    #[arg(long, env = "BACKLOG", default_value = DEFAULT_BACKLOG)]
+    pub backlog: u32,
+    /// Seconds a request calling the Filecoin RPC may take before it is answered with a 504, 0 waits for the chain
+    #[arg(long, env = "RPC_TIMEOUT", default_value = DEFAULT_RPC_TIMEOUT)]
+    pub rpc_timeout: u64,
+    /// Log the method, path, status, latency and body of every request, with signatures masked
+    #[arg(long, env = "REQUEST_LOG", default_value = DEFAULT_REQUEST_LOG, action = ArgAction::Set)]
+    pub request_log: bool,
+    /// Comma separated origins browsers may call the API from, such as `https://sp-vote.com`
+    #[arg(long, env = "ALLOWED_ORIGINS", value_delimiter = ',', value_parser = cors::parse_origin)]
+    pub allowed_origins: Vec<String>,
+    /// Allow browsers on any origin to call the API, for local development only
+    #[arg(long, env = "CORS_ALLOW_ALL", conflicts_with = "allowed_origins")]
+    pub cors_allow_all: bool,
+    /// Comma separated addresses registered as vote starters on every network at startup, the built in starters when unset
+    #[arg(long, env = "AUTHORIZED_VOTERS", value_delimiter = ',')]
+    pub authorized_voters: Vec<String>,
+    /// JSON list of the addresses registered as vote starters on every network at startup
+    #[arg(
+        long,
+        env = "AUTHORIZED_VOTERS_FILE",
+        conflicts_with = "authorized_voters"
+    )]
+    pub authorized_voters_file: Option<PathBuf>,
+    /// JSON file of networks served alongside mainnet and calibration, such as butterfly or a local devnet
+    #[arg(long, env = "NETWORKS_FILE")]
+    pub networks_file: Option<PathBuf>,
+    /// Directory the `fip-0001.md` style FIP documents are fetched from
+    #[arg(long, env = "FIP_REPO_URL", default_value = DEFAULT_FIP_REPO_URL)]
+    pub fip_repo_url: Url,
+    /// Seconds between two syncs of the FIPs voted on, 0 disables syncing
+    #[arg(long, env = "FIP_SYNC_INTERVAL", default_value = DEFAULT_FIP_SYNC_INTERVAL)]
+    pub fip_sync_interval: u64,
+    /// Slack incoming webhook votes are announced to when they start and conclude
+    #[arg(long, env = "SLACK_WEBHOOK")]
+    pub slack_webhook: Option<Url>,
+    /// Discord webhook votes are announced to when they start and conclude
+    #[arg(long, env = "DISCORD_WEBHOOK")]
+    pub discord_webhook: Option<Url>,
+    /// Seconds before the deadline the webhooks are reminded of the voters yet to vote, such as `259200,86400,3600`
+    #[arg(long, env = "VOTE_REMINDERS", value_delimiter = ',')]
+    pub vote_reminders: Vec<u64>,
+    /// Service plain text messages have to be signed for, so they can't be replayed against another deployment
+    #[arg(long, env = "SERVICE_ID", default_value = DEFAULT_SERVICE_ID)]
+    pub service_id: String,
+    /// Unix timestamp messages signed without a service and network are accepted until, forever when unset
+    #[arg(long, env = "LEGACY_MESSAGES_UNTIL")]
+    pub legacy_messages_until: Option<u64>,
+    /// Runs a maintenance task instead of serving
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ServeAddressError {
+    #[error("Unsupported scheme {0}, expected http or https")]
+    UnsupportedScheme(String),
+    #[error("The serve address has no host")]
+    MissingHost,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum Command {
+    /// Check the configuration, database, chain nodes and TLS certificate and print a report
+    Doctor,
+    /// Write every vote and registration to an archive bundle
+    Export { path: PathBuf },
+    /// Restore an archive bundle into a fresh instance
+    Import { path: PathBuf },
+    /// Upgrade the database to the current key layout without serving
+    Migrate,
+    /// Rebuild a fresh instance from the event log of another database
+    Replay { from: Url },
 }
 
 impl Default for Args {
@@ -54,40 +289,396 @@ impl Args {
         self.vote_length
     }
 
+    pub fn vote_grace_period(&self) -> u64 {
+        self.vote_grace_period
+    }
+
     pub fn redis_path(&self) -> Url {
         self.redis_path.clone()
     }
 
+    pub fn redis_replica_path(&self) -> Option<Url> {
+        self.redis_replica_path.clone()
+    }
+
     pub fn serve_address(&self) -> Url {
         self.serve_address.clone()
     }
+
+    /// Whether the serve address is served over HTTPS
+    pub fn serves_https(&self) -> bool {
+        self.serve_address.scheme() == "https"
+    }
+
+    /// The sockets to listen on, `--bind` when given, or the host and port of
+    /// the serve address
+    pub fn listen_addresses(&self) -> Result<Vec<String>, ServeAddressError> {
+        let port = match self.serve_address.scheme() {
+            "http" | "https" => self.serve_address.port_or_known_default().unwrap(),
+            scheme => return Err(ServeAddressError::UnsupportedScheme(scheme.to_string())),
+        };
+
+        if !self.bind.is_empty() {
+            return Ok(self.bind.iter().map(SocketAddr::to_string).collect());
+        }
+
+        // IPv6 hosts keep their brackets so the port can follow
+        match self.serve_address.host_str() {
+            Some(host) => Ok(vec![format!("{}:{}", host, port)]),
+            None => Err(ServeAddressError::MissingHost),
+        }
+    }
+
+    pub fn allow_revote(&self) -> bool {
+        self.allow_revote
+    }
+
+    pub fn tls_cert(&self) -> PathBuf {
+        self.tls_cert.clone()
+    }
+
+    pub fn tls_key(&self) -> PathBuf {
+        self.tls_key.clone()
+    }
+
+    pub fn admin_token(&self) -> Option<String> {
+        self.admin_token.clone()
+    }
+
+    pub fn read_limit(&self) -> u64 {
+        self.read_limit
+    }
+
+    pub fn write_limit(&self) -> u64 {
+        self.write_limit
+    }
+
+    pub fn rate_limit_window(&self) -> u64 {
+        self.rate_limit_window
+    }
+
+    pub fn power_cache_ttl(&self) -> u64 {
+        self.power_cache_ttl
+    }
+
+    pub fn power_cache(&self) -> PowerCacheBackend {
+        self.power_cache
+    }
+
+    pub fn cache_ttl(&self) -> u64 {
+        self.cache_ttl
+    }
+
+    pub fn attestation_key(&self) -> Option<PathBuf> {
+        self.attestation_key.clone()
+    }
+
+    pub fn publish_contract(&self) -> Option<Address> {
+        self.publish_contract
+    }
+
+    pub fn publish_key(&self) -> Option<PathBuf> {
+        self.publish_key.clone()
+    }
+
+    pub fn publish_chain_id(&self) -> u64 {
+        self.publish_chain_id
+    }
+
+    pub fn publish_rpc(&self) -> Url {
+        self.publish_rpc.clone()
+    }
+
+    pub fn registration_ttl(&self) -> u64 {
+        self.registration_ttl
+    }
+
+    /// Expiry of a registration made or renewed at `timestamp`, `None` if registrations don't expire
+    pub fn registration_expiry(&self, timestamp: u64) -> Option<u64> {
+        match self.registration_ttl {
+            0 => None,
+            ttl => Some(timestamp.saturating_add(ttl)),
+        }
+    }
+
+    pub fn require_nonce(&self) -> bool {
+        self.require_nonce
+    }
+
+    pub fn require_acceptance(&self) -> bool {
+        self.require_acceptance
+    }
+
+    pub fn shutdown_timeout(&self) -> u64 {
+        self.shutdown_timeout
+    }
+
+    pub fn workers(&self) -> Option<usize> {
+        self.workers
+    }
+
+    pub fn keep_alive(&self) -> u64 {
+        self.keep_alive
+    }
+
+    pub fn client_timeout(&self) -> u64 {
+        self.client_timeout
+    }
+
+    pub fn max_connections(&self) -> usize {
+        self.max_connections
+    }
+
+    pub fn backlog(&self) -> u32 {
+        self.backlog
+    }
+
+    pub fn rpc_timeout(&self) -> u64 {
+        self.rpc_timeout
+    }
+
+    pub fn request_log(&self) -> bool {
+        self.request_log
+    }
+
+    pub fn allowed_origins(&self) -> Vec<String> {
+        self.allowed_origins.clone()
+    }
+
+    pub fn cors_allow_all(&self) -> bool {
+        self.cors_allow_all
+    }
+
+    pub fn authorized_voters(&self) -> Vec<String> {
+        self.authorized_voters.clone()
+    }
+
+    pub fn authorized_voters_file(&self) -> Option<PathBuf> {
+        self.authorized_voters_file.clone()
+    }
+
+    pub fn networks_file(&self) -> Option<PathBuf> {
+        self.networks_file.clone()
+    }
+
+    pub fn fip_repo_url(&self) -> Url {
+        self.fip_repo_url.clone()
+    }
+
+    pub fn fip_sync_interval(&self) -> u64 {
+        self.fip_sync_interval
+    }
+
+    pub fn slack_webhook(&self) -> Option<Url> {
+        self.slack_webhook.clone()
+    }
+
+    pub fn discord_webhook(&self) -> Option<Url> {
+        self.discord_webhook.clone()
+    }
+
+    pub fn vote_reminders(&self) -> Vec<u64> {
+        self.vote_reminders.clone()
+    }
+
+    pub fn service_id(&self) -> &str {
+        &self.service_id
+    }
+
+    pub fn legacy_messages_until(&self) -> Option<u64> {
+        self.legacy_messages_until
+    }
+
+    /// What plain text messages have to be signed for to be accepted
+    pub fn domain_rules(&self) -> DomainRules {
+        DomainRules {
+            service: self.service_id.clone(),
+            legacy_until: self.legacy_messages_until,
+        }
+    }
+
+    pub fn command(&self) -> Option<Command> {
+        self.command.clone()
+    }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct NtwFipParams {
-    network: String,
-    fip_number: u32,
+    #[param(value_type = String, example = "mainnet")]
+    network: Network,
+    #[param(value_type = String, example = "FIP-1")]
+    fip_number: FipNumber,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct NtwAddrParams {
-    network: String,
+    #[param(value_type = String, example = "mainnet")]
+    network: Network,
+    address: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AddrParams {
+    #[param(example = "0x0000000000000000000000000000000000000000")]
+    address: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct VotingPowerParams {
+    #[param(value_type = String, example = "mainnet")]
+    network: Network,
+    address: String,
+    /// Read the power from the chain instead of the power cache
+    #[serde(default)]
+    refresh: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NtwAddrHeightParams {
+    #[param(value_type = String, example = "mainnet")]
+    network: Network,
     address: String,
+    height: u64,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct FipParams {
-    fip_number: u32,
+    #[param(value_type = String, example = "FIP-1")]
+    fip_number: FipNumber,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct NtwParams {
-    network: String,
+    #[param(value_type = String, example = "mainnet")]
+    network: Network,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ActiveVotesParams {
+    #[param(value_type = String, example = "mainnet")]
+    network: Network,
+    /// Return each vote with its FIP title and status instead of only its number
+    #[serde(default)]
+    details: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct VotersParams {
+    #[param(value_type = String, example = "mainnet")]
+    network: Network,
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
 }
 
-pub fn authorized_voters() -> Vec<Address> {
-    STARTING_AUTHORIZED_VOTERS
-        .iter()
-        .map(|s| Address::from_str(s).unwrap())
-        .collect()
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditParams {
+    #[param(value_type = String, example = "mainnet")]
+    network: Network,
+    #[param(value_type = String, example = "FIP-1")]
+    fip_number: FipNumber,
+    #[serde(default)]
+    anonymize: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TemplateParams {
+    #[param(value_type = String, example = "vote")]
+    kind: TemplateKind,
+    /// Required for votes and vote starts
+    #[param(value_type = Option<String>, example = "FIP-1")]
+    fip_number: Option<FipNumber>,
+    /// YAY, NAY, ABSTAIN or the name of an option, required for votes
+    choice: Option<String>,
+    /// Round of the vote start, the first round when left out
+    round: Option<u64>,
+    /// Required for registrations, and for votes with named options
+    #[param(value_type = Option<String>, example = "mainnet")]
+    network: Option<Network>,
+    /// Voter being registered, required for registrations
+    address: Option<String>,
+    /// Comma separated storage provider ids, required for registrations
+    #[param(example = "f01000,f01001")]
+    sp_ids: Option<String>,
+    /// Issue a nonce and end the message with it, not accepted for registrations
+    #[serde(default)]
+    nonce: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(argv: &[&str]) -> Args {
+        Args::parse_from(["filecoin-vote"].iter().chain(argv))
+    }
+
+    #[test]
+    fn lib_listen_addresses() {
+        assert_eq!(
+            args(&[]).listen_addresses().unwrap(),
+            vec!["127.0.0.1:51634"]
+        );
+
+        let https = args(&["--serve-address", "https://sp-vote.com"]);
+        assert!(https.serves_https());
+        assert_eq!(https.listen_addresses().unwrap(), vec!["sp-vote.com:443"]);
+
+        assert_eq!(
+            args(&["--serve-address", "http://[::1]:8080"])
+                .listen_addresses()
+                .unwrap(),
+            vec!["[::1]:8080"]
+        );
+
+        // The listen sockets don't change the address the API is served on
+        let bound = args(&[
+            "--serve-address",
+            "https://sp-vote.com",
+            "--bind",
+            "0.0.0.0:51634,[::]:51634",
+        ]);
+        assert_eq!(
+            bound.listen_addresses().unwrap(),
+            vec!["0.0.0.0:51634", "[::]:51634"]
+        );
+        assert!(bound.serves_https());
+
+        assert_eq!(
+            args(&["--serve-address", "ftp://sp-vote.com"]).listen_addresses(),
+            Err(ServeAddressError::UnsupportedScheme("ftp".to_string()))
+        );
+    }
+
+    #[test]
+    fn lib_domain_rules() {
+        assert_eq!(
+            args(&[]).domain_rules(),
+            DomainRules {
+                service: "fipvote".to_string(),
+                legacy_until: None,
+            }
+        );
+
+        let rules = args(&[
+            "--service-id",
+            "fipvote-staging",
+            "--legacy-messages-until",
+            "1700000000",
+        ])
+        .domain_rules();
+        assert_eq!(rules.service, "fipvote-staging");
+        assert_eq!(rules.legacy_until, Some(1700000000));
+    }
 }
@@ -0,0 +1,326 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    body::BoxBody,
+    dev::{Service, ServiceRequest, ServiceResponse},
+    get,
+    http::{
+        header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER},
+        Method,
+    },
+    web, Error, HttpResponse, Responder,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{errors::*, Args};
+
+/// Windows are pruned once this many clients are being tracked
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Paths that are guarded some other way and never limited
+const EXEMPT_PREFIXES: [&str; 2] = ["/admin", "/e2e"];
+
+/// Requests that share a quota
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, ToSchema)]
+pub enum EndpointClass {
+    /// GET requests
+    Read,
+    /// POST and DELETE requests, which verify signatures and write to the database
+    Write,
+}
+
+/// The quota of a single endpoint class as reported by `GET /limits`
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+pub struct ClassQuota {
+    /// Requests allowed per window, 0 when the class is not limited
+    pub limit: u64,
+    /// Window length in seconds
+    pub window: u64,
+}
+
+/// Where a client stands in its current window
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quota {
+    pub limit: u64,
+    pub remaining: u64,
+    /// Seconds until the window resets
+    pub reset: u64,
+    pub allowed: bool,
+}
+
+struct Window {
+    started: Instant,
+    count: u64,
+}
+
+/// Fixed window request counter keyed by client address and endpoint class
+pub struct RateLimiter {
+    read_limit: u64,
+    write_limit: u64,
+    window: Duration,
+    windows: Mutex<HashMap<(IpAddr, EndpointClass), Window>>,
+}
+
+impl RateLimiter {
+    pub fn new(read_limit: u64, write_limit: u64, window: u64) -> Self {
+        Self {
+            read_limit,
+            write_limit,
+            window: Duration::from_secs(window.max(1)),
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_args(args: &Args) -> Self {
+        Self::new(
+            args.read_limit(),
+            args.write_limit(),
+            args.rate_limit_window(),
+        )
+    }
+
+    pub fn limit(&self, class: EndpointClass) -> u64 {
+        match class {
+            EndpointClass::Read => self.read_limit,
+            EndpointClass::Write => self.write_limit,
+        }
+    }
+
+    /// Configured quota of every endpoint class
+    pub fn quotas(&self) -> BTreeMap<EndpointClass, ClassQuota> {
+        [EndpointClass::Read, EndpointClass::Write]
+            .into_iter()
+            .map(|class| {
+                let quota = ClassQuota {
+                    limit: self.limit(class),
+                    window: self.window.as_secs(),
+                };
+                (class, quota)
+            })
+            .collect()
+    }
+
+    /// Counts a request from the client, returns `None` when the class is not limited
+    pub fn check(&self, client: IpAddr, class: EndpointClass, now: Instant) -> Option<Quota> {
+        let limit = self.limit(class);
+        if limit == 0 {
+            return None;
+        }
+
+        let mut windows = self.windows.lock().unwrap();
+
+        if windows.len() >= MAX_TRACKED_CLIENTS {
+            windows.retain(|_, w| now.duration_since(w.started) < self.window);
+        }
+
+        let window = windows.entry((client, class)).or_insert(Window {
+            started: now,
+            count: 0,
+        });
+        if now.duration_since(window.started) >= self.window {
+            window.started = now;
+            window.count = 0;
+        }
+
+        let allowed = window.count < limit;
+        if allowed {
+            window.count += 1;
+        }
+
+        let elapsed = now.duration_since(window.started);
+        Some(Quota {
+            limit,
+            remaining: limit - window.count,
+            reset: self.window.saturating_sub(elapsed).as_secs().max(1),
+            allowed,
+        })
+    }
+}
+
+impl Quota {
+    /// Writes the `RateLimit-*` headers, plus `Retry-After` once the quota is used up
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        for (name, value) in [
+            ("ratelimit-limit", self.limit),
+            ("ratelimit-remaining", self.remaining),
+            ("ratelimit-reset", self.reset),
+        ] {
+            headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+        }
+
+        if !self.allowed {
+            headers.insert(RETRY_AFTER, HeaderValue::from(self.reset));
+        }
+    }
+}
+
+/// The endpoint class of a request, `None` for requests that are never limited
+pub fn endpoint_class(method: &Method, path: &str) -> Option<EndpointClass> {
+    if EXEMPT_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+    {
+        return None;
+    }
+
+    match *method {
+        // Queries and ballot checks are posted but never change anything
+        Method::POST if path == "/graphql" || path == "/filecoin/vote/validate" => {
+            Some(EndpointClass::Read)
+        }
+        Method::GET | Method::HEAD => Some(EndpointClass::Read),
+        Method::POST | Method::DELETE => Some(EndpointClass::Write),
+        _ => None,
+    }
+}
+
+/// Middleware that enforces the quotas and adds the rate limit headers to every response
+///
+/// Clients are told apart by the peer address of the connection
+pub fn middleware<S>(
+    limiter: &RateLimiter,
+    req: ServiceRequest,
+    srv: &S,
+) -> impl Future<Output = Result<ServiceResponse<BoxBody>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error>,
+{
+    let quota = match (endpoint_class(req.method(), req.path()), req.peer_addr()) {
+        (Some(class), Some(peer)) => limiter.check(peer.ip(), class, Instant::now()),
+        _ => None,
+    };
+
+    let fut = match quota {
+        Some(quota) if !quota.allowed => Err(req),
+        _ => Ok(srv.call(req)),
+    };
+
+    async move {
+        let mut res = match fut {
+            Ok(fut) => fut.await?,
+            Err(req) => {
+                println!("{} for {:?}", RATE_LIMITED_ERROR, req.peer_addr());
+                req.into_response(HttpResponse::TooManyRequests().body(RATE_LIMITED_ERROR))
+            }
+        };
+
+        if let Some(quota) = quota {
+            quota.apply(res.headers_mut());
+        }
+
+        Ok(res)
+    }
+}
+
+/// Describes the configured quotas so clients can throttle themselves
+#[utoipa::path(
+    get,
+    path = "/limits",
+    tag = "meta",
+    responses(
+        (status = 200, description = "Quota of each endpoint class", body = BTreeMap<EndpointClass, ClassQuota>),
+    )
+)]
+#[get("/limits")]
+async fn get_limits(limiter: web::Data<RateLimiter>) -> impl Responder {
+    HttpResponse::Ok().json(limiter.quotas())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    #[test]
+    fn limits_endpoint_class() {
+        assert_eq!(
+            endpoint_class(&Method::GET, "/filecoin/vote"),
+            Some(EndpointClass::Read)
+        );
+        assert_eq!(
+            endpoint_class(&Method::POST, "/filecoin/vote"),
+            Some(EndpointClass::Write)
+        );
+        assert_eq!(
+            endpoint_class(&Method::POST, "/graphql"),
+            Some(EndpointClass::Read)
+        );
+        assert_eq!(
+            endpoint_class(&Method::POST, "/filecoin/vote/validate"),
+            Some(EndpointClass::Read)
+        );
+        assert_eq!(endpoint_class(&Method::OPTIONS, "/filecoin/vote"), None);
+        assert_eq!(endpoint_class(&Method::DELETE, "/admin/vote"), None);
+        assert_eq!(endpoint_class(&Method::POST, "/e2e/reset"), None);
+    }
+
+    #[test]
+    fn limits_check() {
+        let limiter = RateLimiter::new(2, 0, 60);
+        let now = Instant::now();
+
+        let quota = limiter.check(CLIENT, EndpointClass::Read, now).unwrap();
+        assert!(quota.allowed);
+        assert_eq!(quota.remaining, 1);
+        assert_eq!(quota.reset, 60);
+
+        let quota = limiter.check(CLIENT, EndpointClass::Read, now).unwrap();
+        assert!(quota.allowed);
+        assert_eq!(quota.remaining, 0);
+
+        let quota = limiter
+            .check(CLIENT, EndpointClass::Read, now + Duration::from_secs(20))
+            .unwrap();
+        assert!(!quota.allowed);
+        assert_eq!(quota.reset, 40);
+
+        // The window resets after it has elapsed
+        let quota = limiter
+            .check(CLIENT, EndpointClass::Read, now + Duration::from_secs(60))
+            .unwrap();
+        assert!(quota.allowed);
+
+        // A limit of 0 turns limiting off for the class
+        assert!(limiter.check(CLIENT, EndpointClass::Write, now).is_none());
+    }
+
+    #[test]
+    fn limits_headers() {
+        let mut headers = HeaderMap::new();
+        let quota = Quota {
+            limit: 10,
+            remaining: 0,
+            reset: 30,
+            allowed: false,
+        };
+
+        quota.apply(&mut headers);
+
+        assert_eq!(headers.get("RateLimit-Limit").unwrap(), "10");
+        assert_eq!(headers.get("RateLimit-Remaining").unwrap(), "0");
+        assert_eq!(headers.get("RateLimit-Reset").unwrap(), "30");
+        assert_eq!(headers.get(RETRY_AFTER).unwrap(), "30");
+    }
+
+    #[test]
+    fn limits_quotas() {
+        let limiter = RateLimiter::new(600, 60, 60);
+
+        let json = serde_json::to_string(&limiter.quotas()).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"Read":{"limit":600,"window":60},"Write":{"limit":60,"window":60}}"#
+        );
+    }
+}
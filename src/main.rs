@@ -1,112 +1,374 @@
-use std::{fs::File, io::BufReader};
+use std::{io, time::Duration};
 
-use actix_cors::Cors;
-use actix_web::{web, App, HttpServer};
-use rustls::ServerConfig;
-use rustls_pemfile::{certs, pkcs8_private_keys};
+use actix_web::{dev::ServerHandle, http::KeepAlive, middleware::Compress, web, App, HttpServer};
 
 use fip_voting::{
+    admin, archive,
+    attestation::{self, Attestor},
     authorized_voters,
-    get::{
-        get_active_votes, get_all_concluded_votes, get_concluded_votes, get_delegates,
-        get_vote_starters, get_votes, get_voting_power,
-    },
-    post::{register_vote, register_vote_starter, register_voter, start_vote, unregister_voter},
-    redis::Redis,
-    storage::Network,
-    Args,
+    bootstrap::load_authorized_voters,
+    conclusions::Concluder,
+    cors, doctor, events,
+    fip_registry::FipRegistry,
+    get,
+    graphql::{self, get_graphiql, post_graphql},
+    hot_cache::{self, configure_hot_cache},
+    limits::{self, get_limits, RateLimiter},
+    metrics,
+    notifier::Notifier,
+    openapi, payload, post,
+    publisher::Publisher,
+    redis::{Redis, RedisFactory, SCHEMA_VERSION},
+    registrations::RegistrationQueue,
+    request_log, roles, sse,
+    storage::{configure_power_cache, load_networks, Network},
+    store::{StoreFactory, VoteStore},
+    templates, timeouts, tls, validate, Args, Command,
 };
 
-fn load_certs() -> ServerConfig {
-    let cert_file =
-        &mut BufReader::new(File::open("/etc/letsencrypt/live/sp-vote.com/fullchain.pem").unwrap());
-    let key_file =
-        &mut BufReader::new(File::open("/etc/letsencrypt/live/sp-vote.com/privkey.pem").unwrap());
-
-    let cert_chain = certs(cert_file)
-        .unwrap()
-        .into_iter()
-        .map(rustls::Certificate)
-        .collect::<Vec<_>>();
-    let mut keys = pkcs8_private_keys(key_file)
-        .unwrap()
-        .into_iter()
-        .map(rustls::PrivateKey)
-        .collect::<Vec<_>>();
-
-    if keys.is_empty() {
-        panic!("No private keys found");
-    }
-
-    let config = ServerConfig::builder()
-        .with_safe_defaults()
-        .with_no_client_auth();
-    config.with_single_cert(cert_chain, keys.remove(0)).unwrap()
-}
-
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // Parse the command line arguments
     let args = Args::new();
+
+    // Checked before anything below can fail, so every problem is reported at once
+    if let Some(Command::Doctor) = args.command() {
+        let report = doctor::run(&args).await;
+        println!("{}", report);
+        return match report.passed() {
+            true => Ok(()),
+            false => Err(io::Error::other("Configuration check failed")),
+        };
+    }
     let serve_address = args.serve_address();
 
-    let port = match serve_address.scheme() {
-        "http" => 80,
-        "https" => 443,
-        _ => panic!("Invalid scheme"),
+    let listen_addresses = match args.listen_addresses() {
+        Ok(addresses) => addresses,
+        Err(e) => {
+            println!("Error reading the serve address: {}", e);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+        }
     };
 
+    // Networks have to be known before anything keyed by them is read
+    if let Some(path) = args.networks_file() {
+        match load_networks(&path) {
+            Ok(ntws) => {
+                let names = ntws.iter().map(|ntw| ntw.name()).collect::<Vec<&str>>();
+                println!("Serving configured networks: {}", names.join(", "));
+            }
+            Err(e) => {
+                println!("Error loading networks: {}", e);
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+            }
+        }
+    }
+
+    let voters = args.authorized_voters();
+    match load_authorized_voters(&voters, args.authorized_voters_file().as_deref()) {
+        Ok(voters) => println!("Authorized voters: {:?}", voters),
+        Err(e) => {
+            println!("Error loading authorized voters: {}", e);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+        }
+    }
+
     let mut redis = Redis::new(args.redis_path()).unwrap();
+    redis.set_grace_period(args.vote_grace_period());
+
+    match redis.migrate() {
+        Ok(version) if version < SCHEMA_VERSION => {
+            println!(
+                "Migrated the database from version {} to {}",
+                version, SCHEMA_VERSION
+            );
+        }
+        Ok(_) => (),
+        Err(e) => {
+            println!("Error migrating the database: {}", e);
+            return Err(io::Error::other(e));
+        }
+    }
+
+    // Handlers and the power cache read the time from its clock
+    let store = RedisFactory::from_args(&args);
 
-    let ntws = vec![Network::Mainnet, Network::Testnet];
-    for ntw in ntws {
+    if let Err(e) = configure_power_cache(
+        args.power_cache_ttl(),
+        args.power_cache(),
+        args.redis_path(),
+        store.clock(),
+    ) {
+        println!("Error opening the power cache: {}", e);
+        return Err(io::Error::other(e));
+    }
+
+    for ntw in Network::all() {
         let voter_starters = redis.voter_starters(ntw).unwrap();
+        let admins = redis.admins(ntw).unwrap();
         for voter in authorized_voters() {
-            if voter_starters.contains(&voter) {
-                continue;
-            } else {
+            if !voter_starters.contains(&voter) {
                 redis.register_voter_starter(voter, ntw).unwrap();
             }
+            if !admins.contains(&voter) {
+                redis.register_admin(voter, ntw).unwrap();
+            }
+        }
+    }
+
+    match args.command() {
+        Some(Command::Export { path }) => {
+            return match archive::export(&mut redis, &path, args.vote_length()).await {
+                Ok(bundle) => {
+                    println!(
+                        "Exported {} mainnet and {} calibration votes to {:?}",
+                        bundle.mainnet.votes.len(),
+                        bundle.calibration.votes.len(),
+                        path
+                    );
+                    Ok(())
+                }
+                Err(e) => {
+                    println!("Error exporting archive: {}", e);
+                    Err(io::Error::other(e))
+                }
+            };
+        }
+        Some(Command::Import { path }) => {
+            return match archive::import(&mut redis, &path).await {
+                Ok(summary) => {
+                    println!(
+                        "Imported {} votes with {} ballots and {} registrations from {:?}",
+                        summary.votes, summary.ballots, summary.registrations, path
+                    );
+                    Ok(())
+                }
+                Err(e) => {
+                    println!("Error importing archive: {}", e);
+                    Err(io::Error::other(e))
+                }
+            };
         }
+        Some(Command::Replay { from }) => {
+            let mut source = match Redis::new(from.clone()) {
+                Ok(source) => source,
+                Err(e) => {
+                    println!("Error connecting to {}: {}", from, e);
+                    return Err(io::Error::other(e));
+                }
+            };
+            return match events::replay(&mut source, &mut redis).await {
+                Ok(summary) => {
+                    println!(
+                        "Replayed {} events with {} ballots from {}",
+                        summary.events, summary.ballots, from
+                    );
+                    Ok(())
+                }
+                Err(e) => {
+                    println!("Error replaying the event log: {}", e);
+                    Err(io::Error::other(e))
+                }
+            };
+        }
+        // The database was migrated above
+        Some(Command::Migrate) => {
+            println!("Database is at version {}", SCHEMA_VERSION);
+            return Ok(());
+        }
+        // Reported before anything was loaded
+        Some(Command::Doctor) | None => (),
     }
 
-    let server = HttpServer::new(move || {
-        let cors = Cors::default()
-            .allow_any_origin()
-            .allow_any_method()
-            .allow_any_header()
-            .max_age(3600);
+    // Changes made by every server are heard over the channel they are published on
+    configure_hot_cache(args.cache_ttl());
+    if args.cache_ttl() > 0 {
+        hot_cache::subscribe(args.redis_path());
+    }
+
+    // Load the certificate up front so a bad path fails before serving
+    let certs = match args.serves_https() {
+        true => match tls::server_config(args.tls_cert(), args.tls_key()) {
+            Ok(certs) => Some(certs),
+            Err(e) => {
+                println!("Error loading TLS certificate: {}", e);
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+            }
+        },
+        false => None,
+    };
+
+    let attestor = match args.attestation_key() {
+        Some(path) => match Attestor::load(&path) {
+            Ok(attestor) => {
+                println!("Attesting results as {:?}", attestor.address());
+                Some(web::Data::new(attestor))
+            }
+            Err(e) => {
+                println!("Error loading attestation key: {}", e);
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+            }
+        },
+        None => None,
+    };
+
+    let publisher = match Publisher::from_args(&args) {
+        Ok(Some(publisher)) => {
+            println!("Publishing results as {:?}", publisher.address());
+            Some(publisher.spawn())
+        }
+        Ok(None) => None,
+        Err(e) => {
+            println!("Error configuring the results publisher: {}", e);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+        }
+    };
+
+    // Registrations are only written once their storage providers are verified here
+    RegistrationQueue::from_args(&args).spawn();
+
+    // Results are stored as votes conclude, not on the first read after
+    Concluder::from_args(&args).spawn();
+
+    match FipRegistry::from_args(&args) {
+        Some(registry) => registry.spawn(),
+        None => println!("FIP syncing is disabled"),
+    }
+
+    if let Some(notifier) = Notifier::from_args(&args) {
+        println!("Announcing votes to the configured webhooks");
+        notifier.spawn();
+    }
+
+    match (args.cors_allow_all(), args.allowed_origins().is_empty()) {
+        (true, _) => println!("Allowing requests from any origin"),
+        (false, true) => {
+            println!("No allowed origins configured, browsers on other origins are refused")
+        }
+        (false, false) => println!(
+            "Allowing requests from {}",
+            args.allowed_origins().join(", ")
+        ),
+    }
+
+    // Shared by every worker so quotas hold across the whole server
+    let limiter = web::Data::new(RateLimiter::from_args(&args));
+    let store = web::Data::new(store);
+    let schema = web::Data::new(graphql::schema(args.clone(), store.get_ref().clone()));
+    let shutdown_timeout = args.shutdown_timeout();
+    let rpc_timeout = args.rpc_timeout();
+    let request_log = args.request_log();
+    let keep_alive = match args.keep_alive() {
+        0 => KeepAlive::Disabled,
+        secs => KeepAlive::Timeout(Duration::from_secs(secs)),
+    };
+    let client_timeout = Duration::from_secs(args.client_timeout());
+    let max_connections = args.max_connections();
+    let backlog = args.backlog();
+    let workers = args.workers();
+
+    let mut server = HttpServer::new(move || {
+        let cors = cors::from_args(&args);
+
+        let middleware_limiter = limiter.clone();
+        let attestor = attestor.clone();
 
         App::new()
+            .wrap_fn(move |req, srv| timeouts::middleware(rpc_timeout, req, srv))
+            .wrap_fn(|req, srv| payload::middleware(req, srv))
+            .wrap_fn(move |req, srv| limits::middleware(&middleware_limiter, req, srv))
             .wrap(cors)
+            .wrap(Compress::default())
+            .wrap_fn(move |req, srv| request_log::middleware(request_log, req, srv))
             .app_data(web::Data::new(args.clone()))
-            .service(get_votes)
-            .service(get_voting_power)
-            .service(get_vote_starters)
-            .service(get_delegates)
-            .service(get_concluded_votes)
-            .service(get_active_votes)
-            .service(get_all_concluded_votes)
-            .service(register_vote)
-            .service(register_voter)
-            .service(unregister_voter)
-            .service(register_vote_starter)
-            .service(start_vote)
-    });
-    /*
-    .bind((serve_address.host().unwrap().to_string(), port))?
-    .run()
-    .await*/
-
-    if port == 443 {
-        let certs = load_certs();
-
-        println!("Serving over HTTPS at {}", serve_address);
-        server.bind_rustls((serve_address.host().unwrap().to_string(), port), certs)?
-    } else {
-        println!("Serving over HTTP at {}", serve_address);
-        server.bind((serve_address.host().unwrap().to_string(), port))?
+            .app_data(limiter.clone())
+            .app_data(schema.clone())
+            .app_data(store.clone())
+            .configure(get::routes::<RedisFactory>)
+            .configure(sse::routes::<RedisFactory>)
+            .configure(attestation::routes::<RedisFactory>)
+            .configure(metrics::routes::<RedisFactory>)
+            .service(get_limits)
+            .service(post_graphql)
+            .service(get_graphiql)
+            .configure(templates::routes::<RedisFactory>)
+            .configure(post::routes::<RedisFactory>)
+            .configure(validate::routes::<RedisFactory>)
+            .configure(roles::routes::<RedisFactory>)
+            .service(admin::scope::<RedisFactory>())
+            .service(openapi::swagger_ui())
+            .configure(|cfg| {
+                if let Some(attestor) = attestor {
+                    cfg.app_data(attestor);
+                }
+            })
+            .configure(|_cfg| {
+                #[cfg(feature = "e2e")]
+                _cfg.service(fip_voting::e2e::scope::<RedisFactory>());
+            })
+    })
+    // Signals are handled by stop_on_signal so shutdown is logged
+    .disable_signals()
+    .shutdown_timeout(shutdown_timeout)
+    .keep_alive(keep_alive)
+    .client_request_timeout(client_timeout)
+    .max_connections(max_connections)
+    // Applies to the sockets bound below
+    .backlog(backlog);
+    if let Some(workers) = workers {
+        server = server.workers(workers);
     }
-    .run()
-    .await
+
+    for address in &listen_addresses {
+        server = match &certs {
+            Some(certs) => server.bind_rustls(address.as_str(), certs.clone())?,
+            None => server.bind(address.as_str())?,
+        };
+    }
+    match certs {
+        Some(_) => println!("Serving over HTTPS at {}", serve_address),
+        None => println!("Serving over HTTP at {}", serve_address),
+    }
+    println!("Listening on {}", listen_addresses.join(", "));
+
+    let server = server.run();
+
+    actix_web::rt::spawn(stop_on_signal(server.handle()));
+    let served = server.await;
+
+    if let Some(publisher) = publisher {
+        publisher
+            .shutdown(Duration::from_secs(shutdown_timeout))
+            .await;
+    }
+    println!("Server stopped");
+
+    served
+}
+
+/// Stops accepting connections on SIGTERM or SIGINT, then waits for the
+/// requests in flight to finish so no ballot or registration is half written
+async fn stop_on_signal(server: ServerHandle) {
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => terminate.recv().await,
+            Err(e) => {
+                println!("Error listening for SIGTERM: {}", e);
+                std::future::pending().await
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<Option<()>>();
+
+    let signal = tokio::select! {
+        _ = terminate => "SIGTERM",
+        _ = tokio::signal::ctrl_c() => "SIGINT",
+    };
+
+    println!("Received {}, finishing requests in flight", signal);
+    server.stop(true).await;
 }
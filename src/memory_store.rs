@@ -0,0 +1,1458 @@
+//! A store kept in memory, so handlers can be tested without a database
+//!
+//! Keeps votes and their settings, ballots and their results, vote starters,
+//! admins, registrations, nonces and FIP metadata. Ballots are weighed with
+//! the power provider of the store, without looking up worker keys or token
+//! holder balances on the chain, so token holders are counted without FIL.
+//! Operations the handler tests don't reach return an error rather than an
+//! answer Redis wouldn't give.
+//! Every store opened from a [`MemoryStore`] shares what it keeps
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use ethers::types::{Address, H256};
+
+use crate::{
+    authorized_voters,
+    clock::{Clock, SystemClock},
+    events::{LoggedEvent, RecordedBallot},
+    fip::FipNumber,
+    fip_registry::FipInfo,
+    messages::{
+        class_registration::VoterClass,
+        nonce::IssuedNonce,
+        vote_end::VoteEnding,
+        vote_metadata::VoteMetadata,
+        vote_registration::PendingRegistration,
+        vote_start::QuorumRules,
+        votes::{Vote, VoteOption},
+    },
+    notifier::Announcement,
+    redis::{
+        EarlyEnd, RoundSummary, SilenceReport, Turnout, VoteReceipt, VoteResults, VoteStatus,
+        VoterBallot, VoterPage,
+    },
+    registrations::{DelegationOffer, RegistrationStatus},
+    storage::{Network, StoragePowerProvider},
+    store::{StoreError, StoreFactory, VoteStore},
+    tally::{self, ChoicePower, PowerMap, TallyRules, Weighting},
+    test_redis::StaticPower,
+};
+
+#[derive(Default)]
+struct StoredVote {
+    opens_at: u64,
+    ballots: Vec<Vote>,
+    metadata: BTreeMap<String, VoteMetadata>,
+    options: Vec<String>,
+    quorum: Option<QuorumRules>,
+    weighting: Weighting,
+    min_power: u128,
+    extension: u64,
+    ending: Option<EarlyEnd>,
+    /// Stored when the vote concludes
+    results: Option<VoteResults>,
+    silence: Option<SilenceReport>,
+}
+
+struct Registration {
+    class: VoterClass,
+    sp_ids: Vec<u32>,
+    worker: Option<String>,
+    expires_at: Option<u64>,
+}
+
+#[derive(Default)]
+struct State {
+    votes: BTreeMap<(Network, FipNumber), StoredVote>,
+    starters: BTreeMap<Network, BTreeSet<Address>>,
+    admins: BTreeMap<Network, BTreeSet<Address>>,
+    voters: BTreeMap<(Network, Address), Registration>,
+    /// Issued nonce to the timestamp it expires at
+    nonces: BTreeMap<[u8; 16], u64>,
+    fips: BTreeMap<FipNumber, FipInfo>,
+    receipts: BTreeMap<String, VoteReceipt>,
+    /// Latest ballot of each voter on each FIP
+    history: BTreeMap<(Network, Address), BTreeMap<FipNumber, VoterBallot>>,
+    version: u64,
+}
+
+#[derive(Clone)]
+pub struct MemoryStore {
+    state: Arc<Mutex<State>>,
+    clock: Arc<dyn Clock>,
+    power: Arc<dyn StoragePowerProvider>,
+    grace_period: u64,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self {
+            state: Arc::default(),
+            clock: Arc::new(SystemClock),
+            power: Arc::new(StaticPower::default()),
+            grace_period: 0,
+        }
+    }
+}
+
+impl MemoryStore {
+    /// Weighs ballots with the power from `power`, storage providers have none by default
+    pub fn set_power_provider(&mut self, power: impl StoragePowerProvider + 'static) {
+        self.power = Arc::new(power);
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Applies a change, counting it toward [`VoteStore::results_version`]
+    fn change<T>(
+        &self,
+        f: impl FnOnce(&mut State) -> Result<T, StoreError>,
+    ) -> Result<T, StoreError> {
+        let mut state = self.state();
+        let res = f(&mut state)?;
+        state.version += 1;
+
+        Ok(res)
+    }
+
+    fn with_vote<T>(
+        &self,
+        fip_number: FipNumber,
+        ntw: Network,
+        f: impl FnOnce(&StoredVote) -> T,
+    ) -> T
+    where
+        T: Default,
+    {
+        self.state()
+            .votes
+            .get(&(ntw, fip_number))
+            .map(f)
+            .unwrap_or_default()
+    }
+
+    fn change_vote(
+        &self,
+        fip_number: FipNumber,
+        ntw: Network,
+        f: impl FnOnce(&mut StoredVote),
+    ) -> Result<(), StoreError> {
+        self.change(|state| {
+            f(state.votes.entry((ntw, fip_number)).or_default());
+            Ok(())
+        })
+    }
+
+    fn status_at(
+        &self,
+        fip_number: FipNumber,
+        vote_length: u64,
+        ntw: Network,
+        now: u64,
+    ) -> VoteStatus {
+        let state = self.state();
+        let vote = match state.votes.get(&(ntw, fip_number)) {
+            Some(vote) => vote,
+            None => return VoteStatus::DoesNotExist,
+        };
+
+        match &vote.ending {
+            Some(ended) if ended.ending == VoteEnding::Cancelled => VoteStatus::Cancelled,
+            Some(ended) if ended.ending == VoteEnding::Unknown => VoteStatus::Unknown,
+            Some(_) => VoteStatus::Concluded,
+            None => VoteStatus::at(
+                now,
+                vote.opens_at,
+                vote_length.saturating_add(vote.extension),
+                self.grace_period,
+            ),
+        }
+    }
+
+    fn votes_with_status(
+        &mut self,
+        ntw: Network,
+        vote_length: u64,
+        keep: impl Fn(&VoteStatus) -> bool,
+    ) -> Result<Vec<FipNumber>, StoreError> {
+        let now = self.clock.now();
+        let mut votes = Vec::new();
+        for fip_number in self.all_votes(ntw)? {
+            if keep(&self.status_at(fip_number, vote_length, ntw, now)) {
+                votes.push(fip_number);
+            }
+        }
+
+        Ok(votes)
+    }
+
+    /// Removes `voter` from `holders`, with the errors Redis gives when they
+    /// don't hold the role or are the last who does
+    fn remove_role(
+        holders: &mut BTreeSet<Address>,
+        voter: Address,
+        not_held: &'static str,
+        last: &'static str,
+    ) -> Result<(), StoreError> {
+        if !holders.contains(&voter) {
+            return Err(StoreError::Invalid(not_held.to_string()));
+        }
+        if holders.len() <= 1 {
+            return Err(StoreError::Invalid(last.to_string()));
+        }
+        holders.remove(&voter);
+
+        Ok(())
+    }
+
+    /// Counts the ballots and the power they were recorded with, judged
+    /// against `quorum` when it is given
+    fn tally(
+        &self,
+        fip_number: FipNumber,
+        ntw: Network,
+        quorum: Option<QuorumRules>,
+    ) -> Result<VoteResults, StoreError> {
+        let (ballots, options, rules) = self.with_vote(fip_number, ntw, |vote| {
+            let rules = TallyRules {
+                weighting: vote.weighting,
+                quorum,
+                min_power: vote.min_power,
+            };
+            (vote.ballots.clone(), vote.options.clone(), rules)
+        });
+
+        // Every option of the ballot is listed, even those nobody voted for
+        let mut power = PowerMap {
+            options: options
+                .into_iter()
+                .map(|name| (name, ChoicePower::default()))
+                .collect(),
+            ..PowerMap::default()
+        };
+        for ballot in &ballots {
+            if let Some(choice) = power.get_mut(&ballot.choice()) {
+                let storage = tally::checked_sum(ballot.storage_powers().values().copied())?;
+                choice.storage_size = tally::checked_sum([choice.storage_size, storage])?;
+                choice.fil = tally::checked_sum([choice.fil, ballot.fil()])?;
+            }
+        }
+
+        Ok(tally::compute(&ballots, &power, &rules)?)
+    }
+
+    /// Results of a concluded vote, tallied against its quorum the first time
+    /// they are read and stored
+    fn concluded_results(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<VoteResults, StoreError> {
+        if let Some(results) = self.with_vote(fip_number, ntw, |vote| vote.results.clone()) {
+            return Ok(results);
+        }
+
+        let quorum = self.vote_quorum(fip_number, ntw)?;
+        let results = self.tally(fip_number, ntw, quorum)?;
+        self.change_vote(fip_number, ntw, |vote| vote.results = Some(results.clone()))?;
+
+        Ok(results)
+    }
+
+    /// Weighs the storage providers of the registered voters who didn't vote
+    async fn silence(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<SilenceReport, StoreError> {
+        let voted = self
+            .votes(fip_number, ntw)?
+            .iter()
+            .map(|v| v.voter())
+            .collect::<Vec<Address>>();
+
+        let mut silent_voters = 0;
+        let mut sp_ids = Vec::new();
+        for voter in self.registered_voters(ntw)? {
+            if voted.contains(&voter) {
+                continue;
+            }
+
+            silent_voters += 1;
+            sp_ids.extend(self.voter_delegates(voter, ntw)?);
+        }
+
+        let powers = self.ballot_storage(&sp_ids, ntw).await?;
+
+        Ok(SilenceReport::new(
+            silent_voters,
+            sp_ids.iter().map(|sp_id| powers[sp_id]),
+        ))
+    }
+
+    /// Checks that the ballot can be cast and weighs it with the power
+    /// provider, without writing anything
+    async fn prepare_ballot(
+        &mut self,
+        fip_number: FipNumber,
+        vote: Vote,
+        voter: Address,
+        vote_length: u64,
+        allow_revote: bool,
+    ) -> Result<RecordedBallot, StoreError> {
+        if vote.fip() != fip_number {
+            return Err(StoreError::Invalid(
+                "Vote does not match requested FIP".to_string(),
+            ));
+        }
+        if let VoteOption::Unknown(_) = vote.choice() {
+            return Err(StoreError::Invalid("Unsupported vote option".to_string()));
+        }
+
+        let ntw = self.ballot_network(voter, fip_number, vote.typed_chain_id(), vote_length)?;
+
+        if self.is_registration_expired(voter, ntw)? {
+            return Err(StoreError::Invalid(
+                "Voter registration has expired".to_string(),
+            ));
+        }
+
+        let options = self.vote_options(fip_number, ntw)?;
+        let on_ballot = match vote.choice() {
+            VoteOption::Named(name) => options.contains(&name),
+            _ => options.is_empty(),
+        };
+        if !on_ballot {
+            return Err(StoreError::Invalid(
+                "Vote option is not on the ballot".to_string(),
+            ));
+        }
+
+        if !self.is_open(fip_number, vote_length, ntw, self.clock.now())? {
+            return Err(StoreError::Invalid("Vote is not active".to_string()));
+        }
+
+        let class = self.voter_class(voter, ntw)?;
+        let authorized = match class {
+            VoterClass::StorageProvider => self.voter_delegates(voter, ntw)?,
+            _ => Vec::new(),
+        };
+        if class == VoterClass::StorageProvider && authorized.is_empty() {
+            return Err(StoreError::Invalid(
+                "Voter is not authorized for any storage providers".to_string(),
+            ));
+        }
+
+        if !allow_revote && self.votes(fip_number, ntw)?.contains(&vote) {
+            return Err(StoreError::Invalid("Vote already exists".to_string()));
+        }
+
+        let powers = self.ballot_storage(&authorized, ntw).await?;
+        let storage = tally::checked_sum(powers.values().copied())?;
+
+        let mut vote = vote;
+        vote.record(self.clock.now(), class, powers, 0);
+
+        Ok(RecordedBallot {
+            fip_number,
+            ntw,
+            voter,
+            class,
+            vote,
+            storage,
+            fil: 0,
+        })
+    }
+
+    /// Storage power of each storage provider a ballot is cast for
+    async fn ballot_storage(
+        &self,
+        sp_ids: &[u32],
+        ntw: Network,
+    ) -> Result<BTreeMap<u32, u128>, StoreError> {
+        self.power
+            .storage_amounts(sp_ids, ntw)
+            .await
+            .map_err(|_| StoreError::Invalid("Error fetching storage amount".to_string()))
+    }
+
+    /// Writes the ballots together, either all of them are recorded or none are
+    ///
+    /// A ballot whose receipt was already written is skipped
+    fn record_ballots(
+        &mut self,
+        ballots: Vec<(RecordedBallot, Option<VoteReceipt>)>,
+        allow_revote: bool,
+    ) -> Result<(), StoreError> {
+        let mut rounds = Vec::with_capacity(ballots.len());
+        for (ballot, _) in &ballots {
+            rounds.push(self.vote_round(ballot.fip_number, ballot.ntw)?);
+        }
+
+        self.change(|state| {
+            let ballots: Vec<_> = ballots
+                .into_iter()
+                .zip(rounds)
+                .filter(|((_, receipt), _)| {
+                    !receipt
+                        .as_ref()
+                        .is_some_and(|receipt| state.receipts.contains_key(&receipt.id))
+                })
+                .collect();
+
+            // Every ballot is checked before any is written
+            for ((ballot, _), _) in &ballots {
+                let vote = state.votes.get(&(ballot.ntw, ballot.fip_number));
+                if vote.is_some_and(|vote| vote.results.is_some()) {
+                    return Err(StoreError::Invalid("Vote is not active".to_string()));
+                }
+                if !allow_revote && vote.is_some_and(|vote| vote.ballots.contains(&ballot.vote)) {
+                    return Err(StoreError::Invalid("Vote already exists".to_string()));
+                }
+            }
+
+            for ((ballot, receipt), round) in ballots {
+                let RecordedBallot {
+                    fip_number,
+                    ntw,
+                    voter,
+                    class,
+                    mut vote,
+                    storage,
+                    fil,
+                } = ballot;
+
+                // Ballots logged before the class and balance were kept on
+                // them carry them beside the vote
+                let (recorded_at, powers) = (vote.recorded_at(), vote.storage_powers().clone());
+                vote.record(recorded_at, class, powers, fil);
+
+                state.history.entry((ntw, voter)).or_default().insert(
+                    fip_number,
+                    VoterBallot {
+                        fip_number,
+                        round,
+                        choice: vote.choice(),
+                        storage_size: storage,
+                        fil,
+                        recorded_at,
+                    },
+                );
+                if let Some(receipt) = receipt {
+                    state.receipts.insert(receipt.id.clone(), receipt);
+                }
+
+                // A revote replaces the previous ballot of the voter
+                let ballots = &mut state.votes.entry((ntw, fip_number)).or_default().ballots;
+                ballots.retain(|previous| *previous != vote);
+                ballots.push(vote);
+            }
+
+            Ok(())
+        })
+    }
+}
+
+impl StoreFactory for MemoryStore {
+    type Store = MemoryStore;
+
+    fn open(&self) -> Result<MemoryStore, StoreError> {
+        Ok(self.clone())
+    }
+
+    fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+}
+
+/// The error of operations the memory store doesn't keep what they need for
+fn unsupported(operation: &str) -> StoreError {
+    StoreError::Unsupported(operation.to_string())
+}
+
+#[async_trait(?Send)]
+impl VoteStore for MemoryStore {
+    async fn start_vote(
+        &mut self,
+        fip_number: FipNumber,
+        signer: Address,
+        ntw: Network,
+    ) -> Result<(), StoreError> {
+        let now = self.clock.now();
+        self.schedule_vote(fip_number, signer, ntw, now).await
+    }
+
+    async fn schedule_vote(
+        &mut self,
+        fip_number: FipNumber,
+        signer: Address,
+        ntw: Network,
+        opens_at: u64,
+    ) -> Result<(), StoreError> {
+        if !self.is_authorized_starter(signer, ntw)? && !authorized_voters().contains(&signer) {
+            return Err(StoreError::Invalid(
+                "Signer is not authorized to start a vote".to_string(),
+            ));
+        }
+
+        self.change(|state| {
+            let vote = state.votes.entry((ntw, fip_number)).or_default();
+            if vote.opens_at != 0 {
+                return Err(StoreError::Invalid("Vote already exists".to_string()));
+            }
+            vote.opens_at = opens_at;
+
+            Ok(())
+        })
+    }
+
+    async fn start_round(
+        &mut self,
+        _fip_number: FipNumber,
+        _signer: Address,
+        _ntw: Network,
+        _round: u64,
+        _vote_length: u64,
+    ) -> Result<(), StoreError> {
+        Err(unsupported("start_round"))
+    }
+
+    async fn end_vote(
+        &mut self,
+        _fip_number: FipNumber,
+        _signer: Address,
+        _ntw: Network,
+        _round: u64,
+        _ending: VoteEnding,
+        _vote_length: u64,
+    ) -> Result<(), StoreError> {
+        Err(unsupported("end_vote"))
+    }
+
+    async fn extend_vote(
+        &mut self,
+        _fip_number: FipNumber,
+        _signer: Address,
+        _ntw: Network,
+        _round: u64,
+        _extension: u64,
+        _vote_length: u64,
+    ) -> Result<(), StoreError> {
+        Err(unsupported("extend_vote"))
+    }
+
+    async fn import_vote(
+        &mut self,
+        _fip_number: FipNumber,
+        _ntw: Network,
+        _started_at: u64,
+        _ballots: &[Vote],
+        _results: &VoteResults,
+        _metadata: &BTreeMap<String, VoteMetadata>,
+    ) -> Result<(), StoreError> {
+        Err(unsupported("import_vote"))
+    }
+
+    fn register_voter(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        sp_ids: Vec<u32>,
+    ) -> Result<(), StoreError> {
+        self.change(|state| {
+            let registration = state.voters.entry((ntw, voter)).or_insert(Registration {
+                class: VoterClass::StorageProvider,
+                sp_ids: Vec::new(),
+                worker: None,
+                expires_at: None,
+            });
+            for sp_id in sp_ids {
+                if !registration.sp_ids.contains(&sp_id) {
+                    registration.sp_ids.push(sp_id);
+                }
+            }
+
+            Ok(())
+        })
+    }
+
+    fn register_voter_class(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        class: VoterClass,
+    ) -> Result<(), StoreError> {
+        if class == VoterClass::StorageProvider {
+            return Err(StoreError::Invalid(
+                "Storage providers must register with a worker signature".to_string(),
+            ));
+        }
+
+        self.change(|state| {
+            if let Some(registration) = state.voters.get(&(ntw, voter)) {
+                if !registration.sp_ids.is_empty() {
+                    return Err(StoreError::Invalid(
+                        "Voter is already registered as a storage provider".to_string(),
+                    ));
+                }
+            }
+            state.voters.insert(
+                (ntw, voter),
+                Registration {
+                    class,
+                    sp_ids: Vec::new(),
+                    worker: None,
+                    expires_at: None,
+                },
+            );
+
+            Ok(())
+        })
+    }
+
+    fn unregister_voter(&mut self, voter: Address, ntw: Network) -> Result<(), StoreError> {
+        self.change(|state| {
+            state.voters.remove(&(ntw, voter));
+            Ok(())
+        })
+    }
+
+    fn unregister_storage_providers(
+        &mut self,
+        _voter: Address,
+        _ntw: Network,
+        _sp_ids: &[u32],
+    ) -> Result<(), StoreError> {
+        Err(unsupported("unregister_storage_providers"))
+    }
+
+    fn unregister_voter_all(&mut self, voter: Address) -> Result<(), StoreError> {
+        self.change(|state| {
+            state.voters.retain(|(_, address), _| *address != voter);
+            Ok(())
+        })
+    }
+
+    fn register_voter_starter(&mut self, voter: Address, ntw: Network) -> Result<(), StoreError> {
+        self.change(|state| {
+            state.starters.entry(ntw).or_default().insert(voter);
+            Ok(())
+        })
+    }
+
+    fn register_admin(&mut self, voter: Address, ntw: Network) -> Result<(), StoreError> {
+        self.change(|state| {
+            state.admins.entry(ntw).or_default().insert(voter);
+            Ok(())
+        })
+    }
+
+    fn vote_exists(&mut self, ntw: Network, fip: FipNumber) -> Result<bool, StoreError> {
+        Ok(self.with_vote(fip, ntw, |vote| vote.opens_at != 0))
+    }
+
+    fn is_authorized_starter(&mut self, voter: Address, ntw: Network) -> Result<bool, StoreError> {
+        Ok(self
+            .state()
+            .starters
+            .get(&ntw)
+            .is_some_and(|starters| starters.contains(&voter)))
+    }
+
+    fn is_admin(&mut self, voter: Address, ntw: Network) -> Result<bool, StoreError> {
+        Ok(self
+            .state()
+            .admins
+            .get(&ntw)
+            .is_some_and(|admins| admins.contains(&voter)))
+    }
+
+    fn is_registered(&mut self, voter: Address, ntw: Network) -> bool {
+        self.state()
+            .voters
+            .get(&(ntw, voter))
+            .is_some_and(|registration| !registration.sp_ids.is_empty())
+    }
+
+    fn voter_worker(&mut self, voter: Address, ntw: Network) -> Result<Option<String>, StoreError> {
+        Ok(self
+            .state()
+            .voters
+            .get(&(ntw, voter))
+            .and_then(|registration| registration.worker.clone()))
+    }
+
+    fn set_voter_worker(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        worker_address: Option<&str>,
+    ) -> Result<(), StoreError> {
+        self.change(|state| {
+            if let Some(registration) = state.voters.get_mut(&(ntw, voter)) {
+                registration.worker = worker_address.map(str::to_string);
+            }
+            Ok(())
+        })
+    }
+
+    fn registration_expiry(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<Option<u64>, StoreError> {
+        Ok(self
+            .state()
+            .voters
+            .get(&(ntw, voter))
+            .and_then(|registration| registration.expires_at))
+    }
+
+    fn is_registration_expired(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<bool, StoreError> {
+        Ok(match self.registration_expiry(voter, ntw)? {
+            Some(expires_at) => expires_at <= self.clock.now(),
+            None => false,
+        })
+    }
+
+    fn set_registration_expiry(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        expires_at: Option<u64>,
+    ) -> Result<(), StoreError> {
+        self.change(|state| {
+            if let Some(registration) = state.voters.get_mut(&(ntw, voter)) {
+                registration.expires_at = expires_at;
+            }
+            Ok(())
+        })
+    }
+
+    fn renew_registration(
+        &mut self,
+        _voter: Address,
+        _ntw: Network,
+        _expires_at: Option<u64>,
+    ) -> Result<Option<u64>, StoreError> {
+        Err(unsupported("renew_registration"))
+    }
+
+    fn queue_registration(
+        &mut self,
+        _pending: &PendingRegistration,
+    ) -> Result<RegistrationStatus, StoreError> {
+        Err(unsupported("queue_registration"))
+    }
+
+    fn next_registration(&mut self) -> Result<Option<PendingRegistration>, StoreError> {
+        Err(unsupported("next_registration"))
+    }
+
+    fn registration_status(
+        &mut self,
+        _voter: Address,
+    ) -> Result<Option<RegistrationStatus>, StoreError> {
+        Err(unsupported("registration_status"))
+    }
+
+    fn set_registration_status(
+        &mut self,
+        _voter: Address,
+        _status: &RegistrationStatus,
+    ) -> Result<(), StoreError> {
+        Err(unsupported("set_registration_status"))
+    }
+
+    fn offer_delegation(
+        &mut self,
+        _voter: Address,
+        _ntw: Network,
+        _offer: &DelegationOffer,
+    ) -> Result<(), StoreError> {
+        Err(unsupported("offer_delegation"))
+    }
+
+    fn delegation_offers(
+        &mut self,
+        _voter: Address,
+        _ntw: Network,
+    ) -> Result<Vec<DelegationOffer>, StoreError> {
+        Err(unsupported("delegation_offers"))
+    }
+
+    fn take_delegation_offer(
+        &mut self,
+        _voter: Address,
+        _ntw: Network,
+        _worker_address: &str,
+    ) -> Result<Option<DelegationOffer>, StoreError> {
+        Err(unsupported("take_delegation_offer"))
+    }
+
+    fn issue_nonce(&mut self, ttl: u64) -> Result<IssuedNonce, StoreError> {
+        let nonce: [u8; 16] = ethers::core::rand::random();
+        let expires_at = self.clock.now().saturating_add(ttl);
+        self.state().nonces.insert(nonce, expires_at);
+
+        Ok(IssuedNonce {
+            nonce: hex::encode(nonce),
+            expires_at,
+        })
+    }
+
+    fn consume_nonce(&mut self, nonce: [u8; 16]) -> Result<bool, StoreError> {
+        let now = self.clock.now();
+        Ok(self
+            .state()
+            .nonces
+            .remove(&nonce)
+            .is_some_and(|expires_at| expires_at > now))
+    }
+
+    fn nonce_issued(&mut self, nonce: [u8; 16]) -> Result<bool, StoreError> {
+        let now = self.clock.now();
+        Ok(self
+            .state()
+            .nonces
+            .get(&nonce)
+            .is_some_and(|expires_at| *expires_at > now))
+    }
+
+    fn events(
+        &mut self,
+        _after: Option<&str>,
+        _count: usize,
+    ) -> Result<Vec<LoggedEvent>, StoreError> {
+        Err(unsupported("events"))
+    }
+
+    async fn vote_results(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: u64,
+        ntw: Network,
+    ) -> Result<VoteResults, StoreError> {
+        if self.vote_status(fip_number, vote_length, ntw)? != VoteStatus::Concluded {
+            return self.tally(fip_number, ntw, None);
+        }
+
+        self.concluded_results(fip_number, ntw)
+    }
+
+    fn vote_status(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: impl Into<u64>,
+        ntw: Network,
+    ) -> Result<VoteStatus, StoreError> {
+        let now = self.clock.now();
+        Ok(self.status_at(fip_number, vote_length.into(), ntw, now))
+    }
+
+    fn is_open(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: impl Into<u64>,
+        ntw: Network,
+        now: u64,
+    ) -> Result<bool, StoreError> {
+        let status = self.status_at(fip_number, vote_length.into(), ntw, now);
+
+        Ok(matches!(status, VoteStatus::InProgress(_)))
+    }
+
+    fn active_votes(
+        &mut self,
+        ntw: Network,
+        vote_length: impl Into<u64>,
+    ) -> Result<Vec<FipNumber>, StoreError> {
+        self.votes_with_status(ntw, vote_length.into(), |status| {
+            matches!(status, VoteStatus::InProgress(_))
+        })
+    }
+
+    fn concluded_votes(
+        &mut self,
+        ntw: Network,
+        vote_length: impl Into<u64>,
+    ) -> Result<Vec<FipNumber>, StoreError> {
+        self.votes_with_status(ntw, vote_length.into(), |status| {
+            *status == VoteStatus::Concluded
+        })
+    }
+
+    fn stale_votes(
+        &mut self,
+        ntw: Network,
+        vote_length: impl Into<u64>,
+    ) -> Result<Vec<FipNumber>, StoreError> {
+        let mut stale_votes = Vec::new();
+        for fip_number in self.concluded_votes(ntw, vote_length)? {
+            if self.with_vote(fip_number, ntw, |vote| {
+                vote.results.is_none() || vote.silence.is_none()
+            }) {
+                stale_votes.push(fip_number);
+            }
+        }
+
+        Ok(stale_votes)
+    }
+
+    async fn conclude_vote(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: u64,
+        ntw: Network,
+    ) -> Result<Option<VoteResults>, StoreError> {
+        if self.vote_status(fip_number, vote_length, ntw)? != VoteStatus::Concluded {
+            return Ok(None);
+        }
+
+        let results = self.concluded_results(fip_number, ntw)?;
+        if self.with_vote(fip_number, ntw, |vote| vote.silence.is_none()) {
+            let report = self.silence(fip_number, ntw).await?;
+            self.change_vote(fip_number, ntw, |vote| {
+                vote.silence.get_or_insert(report);
+            })?;
+        }
+
+        Ok(Some(results))
+    }
+
+    fn voter_delegates(&mut self, voter: Address, ntw: Network) -> Result<Vec<u32>, StoreError> {
+        Ok(self
+            .state()
+            .voters
+            .get(&(ntw, voter))
+            .map(|registration| registration.sp_ids.clone())
+            .unwrap_or_default())
+    }
+
+    fn delegators(&mut self, sp_id: u32, ntw: Network) -> Result<Vec<Address>, StoreError> {
+        Ok(self
+            .state()
+            .voters
+            .iter()
+            .filter(|((n, _), registration)| *n == ntw && registration.sp_ids.contains(&sp_id))
+            .map(|((_, voter), _)| *voter)
+            .collect())
+    }
+
+    fn voter_starters(&mut self, ntw: Network) -> Result<Vec<Address>, StoreError> {
+        Ok(self
+            .state()
+            .starters
+            .get(&ntw)
+            .map(|starters| starters.iter().copied().collect())
+            .unwrap_or_default())
+    }
+
+    fn admins(&mut self, ntw: Network) -> Result<Vec<Address>, StoreError> {
+        Ok(self
+            .state()
+            .admins
+            .get(&ntw)
+            .map(|admins| admins.iter().copied().collect())
+            .unwrap_or_default())
+    }
+
+    fn vote_start(&mut self, fip_number: FipNumber, ntw: Network) -> Result<u64, StoreError> {
+        Ok(self.with_vote(fip_number, ntw, |vote| vote.opens_at))
+    }
+
+    fn votes(&mut self, fip_number: FipNumber, ntw: Network) -> Result<Vec<Vote>, StoreError> {
+        Ok(self.with_vote(fip_number, ntw, |vote| vote.ballots.clone()))
+    }
+
+    fn vote_receipt(&mut self, id: &str) -> Result<Option<VoteReceipt>, StoreError> {
+        Ok(self.state().receipts.get(id).cloned())
+    }
+
+    fn voter_history(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<Vec<VoterBallot>, StoreError> {
+        Ok(self
+            .state()
+            .history
+            .get(&(ntw, voter))
+            .map(|ballots| ballots.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn vote_round(&mut self, _fip_number: FipNumber, _ntw: Network) -> Result<u64, StoreError> {
+        Ok(1)
+    }
+
+    fn previous_round(
+        &mut self,
+        _fip_number: FipNumber,
+        _ntw: Network,
+    ) -> Result<Option<RoundSummary>, StoreError> {
+        Ok(None)
+    }
+
+    fn published_results(
+        &mut self,
+        _fip_number: FipNumber,
+        _ntw: Network,
+    ) -> Result<Option<H256>, StoreError> {
+        Ok(None)
+    }
+
+    fn set_published_results(
+        &mut self,
+        _fip_number: FipNumber,
+        _ntw: Network,
+        _tx_hash: H256,
+    ) -> Result<(), StoreError> {
+        Err(unsupported("set_published_results"))
+    }
+
+    fn announced(
+        &mut self,
+        _fip_number: FipNumber,
+        _ntw: Network,
+        _announcement: Announcement,
+    ) -> Result<bool, StoreError> {
+        Err(unsupported("announced"))
+    }
+
+    fn set_announced(
+        &mut self,
+        _fip_number: FipNumber,
+        _ntw: Network,
+        _announcement: Announcement,
+    ) -> Result<(), StoreError> {
+        Err(unsupported("set_announced"))
+    }
+
+    fn voter_networks(&mut self, voter: Address) -> Result<Vec<Network>, StoreError> {
+        Ok(self
+            .state()
+            .voters
+            .keys()
+            .filter(|(_, address)| *address == voter)
+            .map(|(ntw, _)| *ntw)
+            .collect())
+    }
+
+    fn ballot_network(
+        &mut self,
+        voter: Address,
+        fip_number: FipNumber,
+        chain_id: Option<u64>,
+        vote_length: u64,
+    ) -> Result<Network, StoreError> {
+        let mut ntws = self.voter_networks(voter)?;
+        if ntws.len() > 1 {
+            if let Some(chain_id) = chain_id {
+                ntws.retain(|ntw| ntw.chain_id() == chain_id);
+            }
+        }
+        if ntws.len() > 1 {
+            let (mut voting, mut ended) = (Vec::new(), Vec::new());
+            for &ntw in &ntws {
+                match self.vote_status(fip_number, vote_length, ntw)? {
+                    VoteStatus::InProgress(_) => voting.push(ntw),
+                    VoteStatus::DoesNotExist => (),
+                    _ => ended.push(ntw),
+                }
+            }
+
+            ntws = match (voting.is_empty(), ended.first()) {
+                (false, _) => voting,
+                (true, Some(&ntw)) => vec![ntw],
+                (true, None) => vec![ntws[0]],
+            };
+        }
+
+        match ntws.as_slice() {
+            [ntw] => Ok(*ntw),
+            [] => Err(StoreError::NotAuthorized(
+                "Voter is not registered on the network of the ballot".to_string(),
+            )),
+            _ => Err(StoreError::Invalid(
+                "FIP is being voted on in several networks the voter is registered on, sign the ballot as typed data".to_string(),
+            )),
+        }
+    }
+
+    fn voter_class(&mut self, voter: Address, ntw: Network) -> Result<VoterClass, StoreError> {
+        Ok(self
+            .state()
+            .voters
+            .get(&(ntw, voter))
+            .map(|registration| registration.class)
+            .unwrap_or_default())
+    }
+
+    fn registered_voters(&mut self, ntw: Network) -> Result<Vec<Address>, StoreError> {
+        Ok(self
+            .state()
+            .voters
+            .keys()
+            .filter(|(n, _)| *n == ntw)
+            .map(|(_, voter)| *voter)
+            .collect())
+    }
+
+    fn voter_page(
+        &mut self,
+        _ntw: Network,
+        _offset: usize,
+        _limit: Option<usize>,
+    ) -> Result<VoterPage, StoreError> {
+        Err(unsupported("voter_page"))
+    }
+
+    fn turnout(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        network_storage_size: u128,
+    ) -> Result<Turnout, StoreError> {
+        let votes = self.votes(fip_number, ntw)?;
+        let storage_providers: BTreeSet<u32> =
+            votes.iter().flat_map(|v| v.storage_providers()).collect();
+
+        let storage_size = self.tally(fip_number, ntw, None)?.total_storage_size();
+
+        Ok(Turnout::new(
+            votes.len() as u64,
+            storage_providers.len() as u64,
+            storage_size,
+            network_storage_size,
+        ))
+    }
+
+    fn yet_to_vote(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Vec<Address>, StoreError> {
+        let round = self.vote_round(fip_number, ntw)?;
+        let voters = self.registered_voters(ntw)?;
+
+        // A ballot of an earlier round doesn't count toward this one
+        let state = self.state();
+        Ok(voters
+            .into_iter()
+            .filter(|voter| {
+                !state
+                    .history
+                    .get(&(ntw, *voter))
+                    .and_then(|ballots| ballots.get(&fip_number))
+                    .is_some_and(|ballot| ballot.round == round)
+            })
+            .collect())
+    }
+
+    fn silence_report(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: u64,
+        ntw: Network,
+    ) -> Result<Option<SilenceReport>, StoreError> {
+        if self.vote_status(fip_number, vote_length, ntw)? != VoteStatus::Concluded {
+            return Err(StoreError::Invalid("Vote is not concluded".to_string()));
+        }
+
+        Ok(self.with_vote(fip_number, ntw, |vote| vote.silence.clone()))
+    }
+
+    fn vote_metadata(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<BTreeMap<String, VoteMetadata>, StoreError> {
+        Ok(self.with_vote(fip_number, ntw, |vote| vote.metadata.clone()))
+    }
+
+    fn fip_info(&mut self, fip_number: FipNumber) -> Result<Option<FipInfo>, StoreError> {
+        Ok(self.state().fips.get(&fip_number).cloned())
+    }
+
+    fn results_version(&mut self) -> Result<u64, StoreError> {
+        Ok(self.state().version)
+    }
+
+    fn vote_options(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Vec<String>, StoreError> {
+        Ok(self.with_vote(fip_number, ntw, |vote| vote.options.clone()))
+    }
+
+    fn vote_quorum(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Option<QuorumRules>, StoreError> {
+        Ok(self.with_vote(fip_number, ntw, |vote| vote.quorum))
+    }
+
+    fn vote_weighting(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Weighting, StoreError> {
+        Ok(self.with_vote(fip_number, ntw, |vote| vote.weighting))
+    }
+
+    fn vote_min_power(&mut self, fip_number: FipNumber, ntw: Network) -> Result<u128, StoreError> {
+        Ok(self.with_vote(fip_number, ntw, |vote| vote.min_power))
+    }
+
+    fn vote_extension(&mut self, fip_number: FipNumber, ntw: Network) -> Result<u64, StoreError> {
+        Ok(self.with_vote(fip_number, ntw, |vote| vote.extension))
+    }
+
+    fn vote_ending(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Option<EarlyEnd>, StoreError> {
+        Ok(self.with_vote(fip_number, ntw, |vote| vote.ending.clone()))
+    }
+
+    fn all_votes(&mut self, ntw: Network) -> Result<Vec<FipNumber>, StoreError> {
+        Ok(self
+            .state()
+            .votes
+            .iter()
+            .filter(|((n, _), vote)| *n == ntw && vote.opens_at != 0)
+            .map(|((_, fip_number), _)| *fip_number)
+            .collect())
+    }
+
+    fn power_provider(&self) -> Arc<dyn StoragePowerProvider> {
+        self.power.clone()
+    }
+
+    fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    fn now(&self) -> u64 {
+        self.clock.now()
+    }
+
+    async fn add_vote(
+        &mut self,
+        fip_number: FipNumber,
+        vote: Vote,
+        voter: Address,
+        vote_length: u64,
+        allow_revote: bool,
+    ) -> Result<(), StoreError> {
+        let ballot = self
+            .prepare_ballot(fip_number, vote, voter, vote_length, allow_revote)
+            .await?;
+
+        self.record_ballot(ballot, allow_revote, None)
+    }
+
+    async fn submit_vote(
+        &mut self,
+        fip_number: FipNumber,
+        vote: Vote,
+        voter: Address,
+        vote_length: u64,
+        allow_revote: bool,
+        receipt_id: String,
+    ) -> Result<VoteReceipt, StoreError> {
+        if let Some(receipt) = self.vote_receipt(&receipt_id)? {
+            return Ok(receipt);
+        }
+
+        let receipt = VoteReceipt {
+            id: receipt_id,
+            fip_number,
+            choice: vote.choice(),
+            address: voter,
+            recorded_at: self.clock.now(),
+        };
+        let ballot = self
+            .prepare_ballot(fip_number, vote, voter, vote_length, allow_revote)
+            .await?;
+        self.record_ballot(ballot, allow_revote, Some(&receipt))?;
+
+        Ok(receipt)
+    }
+
+    async fn add_vote_batch(
+        &mut self,
+        votes: Vec<(Vote, String)>,
+        voter: Address,
+        vote_length: u64,
+        allow_revote: bool,
+    ) -> Result<Vec<VoteReceipt>, StoreError> {
+        let mut landed = Vec::with_capacity(votes.len());
+        for (_, id) in &votes {
+            landed.extend(self.vote_receipt(id)?);
+        }
+        if !votes.is_empty() && landed.len() == votes.len() {
+            return Ok(landed);
+        }
+
+        let recorded_at = self.clock.now();
+        let mut ballots = Vec::with_capacity(votes.len());
+        for (vote, id) in votes {
+            let receipt = VoteReceipt {
+                id,
+                fip_number: vote.fip(),
+                choice: vote.choice(),
+                address: voter,
+                recorded_at,
+            };
+            let ballot = self
+                .prepare_ballot(vote.fip(), vote, voter, vote_length, allow_revote)
+                .await?;
+            ballots.push((ballot, Some(receipt)));
+        }
+
+        let receipts: Vec<VoteReceipt> = ballots
+            .iter()
+            .filter_map(|(_, receipt)| receipt.clone())
+            .collect();
+        self.record_ballots(ballots, allow_revote)?;
+
+        // Ballots that landed before keep their original receipts
+        receipts
+            .into_iter()
+            .map(|receipt| Ok(self.vote_receipt(&receipt.id)?.unwrap_or(receipt)))
+            .collect()
+    }
+
+    fn record_ballot(
+        &mut self,
+        ballot: RecordedBallot,
+        allow_revote: bool,
+        receipt: Option<&VoteReceipt>,
+    ) -> Result<(), StoreError> {
+        self.record_ballots(vec![(ballot, receipt.cloned())], allow_revote)
+    }
+
+    fn set_fip_info(&mut self, fip_number: FipNumber, info: &FipInfo) -> Result<(), StoreError> {
+        self.state().fips.insert(fip_number, info.clone());
+        Ok(())
+    }
+
+    async fn set_vote_metadata(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        language: String,
+        metadata: VoteMetadata,
+    ) -> Result<(), StoreError> {
+        self.change_vote(fip_number, ntw, |vote| {
+            vote.metadata.insert(language, metadata);
+        })
+    }
+
+    fn set_vote_options(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        options: &[String],
+    ) -> Result<(), StoreError> {
+        self.change_vote(fip_number, ntw, |vote| vote.options = options.to_vec())
+    }
+
+    fn set_vote_quorum(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        rules: Option<QuorumRules>,
+    ) -> Result<(), StoreError> {
+        self.change_vote(fip_number, ntw, |vote| vote.quorum = rules)
+    }
+
+    fn set_vote_weighting(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        weighting: Weighting,
+    ) -> Result<(), StoreError> {
+        self.change_vote(fip_number, ntw, |vote| vote.weighting = weighting)
+    }
+
+    fn set_vote_min_power(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        min_power: u128,
+    ) -> Result<(), StoreError> {
+        self.change_vote(fip_number, ntw, |vote| vote.min_power = min_power)
+    }
+
+    fn set_vote_ending(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        ended: Option<&EarlyEnd>,
+    ) -> Result<(), StoreError> {
+        self.change_vote(fip_number, ntw, |vote| vote.ending = ended.cloned())
+    }
+
+    fn set_vote_extension(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        extension: u64,
+    ) -> Result<(), StoreError> {
+        self.change_vote(fip_number, ntw, |vote| vote.extension = extension)
+    }
+
+    fn remove_voter_starters(&mut self, voter: Address, ntw: Network) -> Result<(), StoreError> {
+        self.change(|state| {
+            Self::remove_role(
+                state.starters.entry(ntw).or_default(),
+                voter,
+                "Address is not a vote starter",
+                "Cannot remove the last vote starter",
+            )
+        })
+    }
+
+    fn remove_admin(&mut self, voter: Address, ntw: Network) -> Result<(), StoreError> {
+        self.change(|state| {
+            Self::remove_role(
+                state.admins.entry(ntw).or_default(),
+                voter,
+                "Address is not an admin",
+                "Cannot remove the last admin",
+            )
+        })
+    }
+
+    fn flush_vote(&mut self, fip_number: FipNumber, ntw: Network) -> Result<(), StoreError> {
+        self.change_vote(fip_number, ntw, |vote| vote.ballots.clear())
+    }
+
+    async fn delete_vote(&mut self, fip_number: FipNumber, ntw: Network) -> Result<(), StoreError> {
+        self.change(|state| {
+            state.votes.remove(&(ntw, fip_number));
+            Ok(())
+        })
+    }
+
+    fn flush_all(&mut self) -> Result<(), StoreError> {
+        *self.state() = State::default();
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use ethers::types::Address;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use super::{
+    domain::{signed_body, signed_domain, Domain},
+    nonce::{split_freshness, Freshness},
+    parser,
+    signature::parse_signature,
+    votes::VoteError,
+};
+
+/// Raw json for a voter to accept the delegation a storage provider offered them
+///
+/// Message scheme is `ACCEPT <worker address>` with the worker or multisig
+/// address that offered the delegation, optionally ending with the service and
+/// network it is signed for, see `Domain`, and a nonce and expiry, see `Freshness`
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct DelegationAcceptance {
+    signature: String,
+    #[schema(
+        example = "ACCEPT f3vvmn62lofvhjd2ugzca6sof2j2ubwok6cj4xxbfzz4yuxfkgobpihhd2thlanmsh3w2ptld2gqkn2jvlss4a"
+    )]
+    pub message: String,
+}
+
+impl DelegationAcceptance {
+    /// Returns a tuple of (signer, worker address), the worker address in lowercase
+    /// as registrations keep it
+    pub fn auth(&self) -> Result<(Address, String), VoteError> {
+        let signer = self.pub_key()?;
+        let message = signed_body(&self.message)?;
+        let worker_address = parser::acceptance(message)?;
+
+        Ok((signer, worker_address.to_lowercase()))
+    }
+    /// The nonce and expiry the message was signed with, if any
+    pub fn freshness(&self) -> Result<Option<Freshness>, VoteError> {
+        Ok(split_freshness(&self.message)?.1)
+    }
+    /// The service and network the message was signed for, if it names them
+    pub fn domain(&self) -> Result<Option<Domain>, VoteError> {
+        signed_domain(&self.message)
+    }
+    fn pub_key(&self) -> Result<Address, VoteError> {
+        let signature = parse_signature(&self.signature)?;
+        let msg = format!(
+            "\x19Ethereum Signed Message:\n{}{}",
+            self.message.len(),
+            self.message
+        );
+        let message_hash = ethers::utils::keccak256(msg);
+
+        let address = signature.recover(message_hash)?;
+
+        Ok(address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::signers::{LocalWallet, Signer};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn acceptance_auth() {
+        let wallet: LocalWallet =
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+                .parse()
+                .unwrap();
+
+        let message = "ACCEPT T3ABC SERVICE-fipvote NETWORK-calibration".to_string();
+        let signature = wallet.sign_message(&message).await.unwrap();
+        let acceptance = DelegationAcceptance {
+            signature: format!("0x{}", signature),
+            message,
+        };
+
+        assert_eq!(
+            acceptance.auth().unwrap(),
+            (wallet.address(), "t3abc".to_string())
+        );
+        assert_eq!(acceptance.domain().unwrap().unwrap().network, "calibration");
+
+        let acceptance = DelegationAcceptance {
+            signature: format!("0x{}", signature),
+            message: "REGISTER t3abc".to_string(),
+        };
+        assert!(matches!(
+            acceptance.auth(),
+            Err(VoteError::InvalidMessageFormat)
+        ));
+    }
+}
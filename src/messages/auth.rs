@@ -1,29 +1,86 @@
-use std::str::FromStr;
-
-use ethers::types::{Address, Signature};
+use ethers::types::Address;
 use serde::Deserialize;
+use utoipa::ToSchema;
 
-use super::votes::VoteError;
+use super::{
+    domain::{signed_body, signed_domain, Domain},
+    nonce::{split_freshness, Freshness},
+    signature::parse_signature,
+    typed_data::{self, TypedMessage, TypedStarterAuthorization},
+    votes::VoteError,
+};
+use crate::storage::{parse_address, Network};
 
-#[derive(Deserialize, Debug)]
+/// Raw json for a vote starter to add or remove another vote starter
+///
+/// Message scheme is the address being authorized, or `REMOVE 0x...` to revoke
+/// it, given as 0x hex or as its f410 address on the network, optionally ending with the service and network it is signed for, see `Domain`, and a nonce and expiry, see `Freshness`. It can be
+/// signed as EIP-712 typed data instead, sent as `typed_data` in place of the message
+#[derive(Deserialize, Debug, ToSchema)]
 pub struct VoterAuthorization {
     signature: String,
+    #[serde(default)]
     message: String,
+    #[serde(default)]
+    typed_data: Option<TypedStarterAuthorization>,
 }
 
 impl VoterAuthorization {
     /// Returns a tuple of (signer, authorized address)
-    pub fn auth(&self) -> Result<(Address, Address), VoteError> {
+    pub fn auth(&self, ntw: Network) -> Result<(Address, Address), VoteError> {
         let signer = self.pub_key()?;
-        let address = match Address::from_str(&self.message) {
+        let signed = self.signed_message();
+        let message = signed_body(&signed)?;
+        let address = match parse_address(ntw, message) {
             Ok(address) => address,
             Err(_) => return Err(VoteError::InvalidMessageFormat),
         };
 
         Ok((signer, address))
     }
+    /// Returns a tuple of (signer, revoked address)
+    ///
+    /// Message scheme is `REMOVE 0x...` so an authorization can't be replayed
+    /// to revoke the address it authorized
+    pub fn revocation(&self, ntw: Network) -> Result<(Address, Address), VoteError> {
+        let signer = self.pub_key()?;
+        let signed = self.signed_message();
+        let message = signed_body(&signed)?;
+        let address = match message
+            .strip_prefix("REMOVE ")
+            .map(|a| parse_address(ntw, a.trim()))
+        {
+            Some(Ok(address)) => address,
+            _ => return Err(VoteError::InvalidMessageFormat),
+        };
+
+        Ok((signer, address))
+    }
+    /// The nonce and expiry the message was signed with, if any
+    pub fn freshness(&self) -> Result<Option<Freshness>, VoteError> {
+        Ok(split_freshness(&self.signed_message())?.1)
+    }
+    /// The service and network the plain message was signed for, if it names them
+    pub fn domain(&self) -> Result<Option<Domain>, VoteError> {
+        signed_domain(&self.signed_message())
+    }
+    /// Chain id of a message signed as typed data, which has to match the network
+    pub fn typed_chain_id(&self) -> Option<u64> {
+        self.typed_data.as_ref().map(|typed| typed.chain_id)
+    }
+    /// The plain message, or the message the typed data stands for
+    fn signed_message(&self) -> String {
+        match &self.typed_data {
+            Some(typed) => typed.message(),
+            None => self.message.clone(),
+        }
+    }
     fn pub_key(&self) -> Result<Address, VoteError> {
-        let signature = Signature::from_str(&self.signature)?;
+        if let Some(typed) = &self.typed_data {
+            return typed_data::recover(&self.signature, typed);
+        }
+
+        let signature = parse_signature(&self.signature)?;
         let msg = format!(
             "\x19Ethereum Signed Message:\n{}{}",
             self.message.len(),
@@ -0,0 +1,168 @@
+use std::str::FromStr;
+
+use ethers::types::{Address, Signature};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::votes::VoteError;
+use crate::storage::{parse_address, Network};
+
+/// The constituency a voter takes part in
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    ToSchema,
+)]
+pub enum VoterClass {
+    /// Registered through a storage provider worker signature, weighted by storage power
+    #[default]
+    StorageProvider,
+    /// Vouched for by an authorized vote starter
+    CoreDev,
+    /// Self registered with an Ethereum signature
+    TokenHolder,
+}
+
+/// Raw json for registering a voter outside of the storage provider track
+///
+/// Message scheme
+///
+/// REGISTER: TokenHolder
+///
+/// REGISTER: CoreDev 0x...
+///
+/// Token holders sign for themselves while core devs are registered by an
+/// authorized vote starter signing for the core dev's address, given as 0x
+/// hex or as its f410 address on the network
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct ReceivedClassRegistration {
+    signature: String,
+    message: String,
+}
+
+impl ReceivedClassRegistration {
+    /// Returns a tuple of (signer, registered address, class)
+    pub fn auth(&self, ntw: Network) -> Result<(Address, Address, VoterClass), VoteError> {
+        let signer = self.pub_key()?;
+
+        let msg = match self.message.strip_prefix("REGISTER: ") {
+            Some(msg) => msg,
+            None => return Err(VoteError::InvalidMessageFormat),
+        };
+
+        match msg.split_whitespace().collect::<Vec<&str>>().as_slice() {
+            ["TokenHolder"] => Ok((signer, signer, VoterClass::TokenHolder)),
+            ["CoreDev", address] => match parse_address(ntw, address) {
+                Ok(address) => Ok((signer, address, VoterClass::CoreDev)),
+                Err(_) => Err(VoteError::InvalidMessageFormat),
+            },
+            _ => Err(VoteError::InvalidMessageFormat),
+        }
+    }
+    fn pub_key(&self) -> Result<Address, VoteError> {
+        let signature = Signature::from_str(&self.signature)?;
+        let msg = format!(
+            "\x19Ethereum Signed Message:\n{}{}",
+            self.message.len(),
+            self.message
+        );
+        let message_hash = ethers::utils::keccak256(msg);
+
+        let address = signature.recover(message_hash)?;
+
+        Ok(address)
+    }
+}
+
+#[cfg(test)]
+mod class_registration_tests {
+    use ethers::signers::{LocalWallet, Signer};
+
+    use super::*;
+
+    async fn signed(wallet: &LocalWallet, message: &str) -> ReceivedClassRegistration {
+        let signature = wallet.sign_message(message).await.unwrap();
+
+        ReceivedClassRegistration {
+            signature: format!("0x{}", signature),
+            message: message.to_string(),
+        }
+    }
+
+    fn wallet() -> LocalWallet {
+        "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+            .parse()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn class_registration_token_holder() {
+        let wallet = wallet();
+
+        let (signer, address, class) = signed(&wallet, "REGISTER: TokenHolder")
+            .await
+            .auth(Network::Mainnet)
+            .unwrap();
+
+        assert_eq!(signer, wallet.address());
+        assert_eq!(address, wallet.address());
+        assert_eq!(class, VoterClass::TokenHolder);
+    }
+
+    #[tokio::test]
+    async fn class_registration_core_dev() {
+        let wallet = wallet();
+        let dev = "0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56";
+
+        let (signer, address, class) = signed(&wallet, &format!("REGISTER: CoreDev {}", dev))
+            .await
+            .auth(Network::Mainnet)
+            .unwrap();
+
+        assert_eq!(signer, wallet.address());
+        assert_eq!(address, Address::from_str(dev).unwrap());
+        assert_eq!(class, VoterClass::CoreDev);
+    }
+
+    #[tokio::test]
+    async fn class_registration_core_dev_delegated() {
+        let wallet = wallet();
+        let dev = "f410f2oekwcmo2pueydmaq53eic2i62crtbeyuzx2gmy";
+
+        let (_, address, _) = signed(&wallet, &format!("REGISTER: CoreDev {}", dev))
+            .await
+            .auth(Network::Mainnet)
+            .unwrap();
+
+        assert_eq!(
+            address,
+            Address::from_str("0xd388ab098ed3e84c0d808776440b48f685198498").unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn class_registration_invalid() {
+        let wallet = wallet();
+
+        for message in [
+            "REGISTER: StorageProvider",
+            "REGISTER: CoreDev",
+            "REGISTER: CoreDev 0x1234",
+            "REGISTER: CoreDev t410f2oekwcmo2pueydmaq53eic2i62crtbeyuzx2gmy",
+            "TokenHolder",
+        ] {
+            let res = signed(&wallet, message).await.auth(Network::Mainnet);
+
+            assert!(matches!(res, Err(VoteError::InvalidMessageFormat)));
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use ethers::types::Address;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use super::{
+    domain::{signed_body, signed_domain, Domain},
+    nonce::{split_freshness, Freshness},
+    parser,
+    signature::parse_signature,
+    vote_registration::{verify_signature, VoteRegistrationError},
+};
+use crate::storage::{sp_id_format, verify_id, verify_owner, Network};
+
+/// Raw json to take a single storage provider off a registration
+///
+/// Message scheme is `REMOVE <storage provider id>`, optionally ending with
+/// the service and network it is signed for, see `Domain`, and a nonce and
+/// expiry, see `Freshness`
+///
+/// The voter signs the message with their ethereum key. The storage provider
+/// signs it with its worker or owner key instead, as `lotus wallet sign` takes
+/// the message hex encoded, and names that key in `worker_address`. Storage
+/// providers owned by a multisig are taken off by their voter
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct DelegateRemoval {
+    signature: String,
+    #[schema(example = "REMOVE f01234 SERVICE-fipvote NETWORK-mainnet")]
+    pub message: String,
+    #[serde(default)]
+    worker_address: Option<String>,
+}
+
+/// Who signed a removal
+#[derive(Debug, PartialEq)]
+pub enum Remover {
+    /// A voter taking the storage provider off their own registration
+    Voter(Address),
+    /// The worker or owner address of the storage provider, in lowercase
+    StorageProvider(String),
+}
+
+impl DelegateRemoval {
+    /// Returns a tuple of (signer, storage provider id) for a removal on `ntw`
+    ///
+    /// A storage provider signer still has to be verified as controlling the
+    /// storage provider, see [`Remover::verify`]
+    pub fn auth(&self, ntw: Network) -> Result<(Remover, u32), VoteRegistrationError> {
+        let message =
+            signed_body(&self.message).map_err(|_| VoteRegistrationError::InvalidMessageFormat)?;
+        let sp_id = parser::removal(message, ntw.prefix())
+            .map_err(|_| VoteRegistrationError::InvalidMessageFormat)?;
+
+        let remover = match &self.worker_address {
+            Some(worker_address) => {
+                let signed_ntw =
+                    verify_signature(worker_address, &self.signature, self.message.as_bytes())?;
+                if signed_ntw.prefix() != ntw.prefix() {
+                    return Err(VoteRegistrationError::InvalidWorkerAddress);
+                }
+                Remover::StorageProvider(worker_address.to_lowercase())
+            }
+            None => Remover::Voter(self.pub_key()?),
+        };
+
+        Ok((remover, sp_id))
+    }
+    /// The nonce and expiry the message was signed with, if any
+    pub fn freshness(&self) -> Result<Option<Freshness>, VoteRegistrationError> {
+        split_freshness(&self.message)
+            .map(|(_, freshness)| freshness)
+            .map_err(|_| VoteRegistrationError::InvalidMessageFormat)
+    }
+    /// The service and network the message was signed for, if it names them
+    pub fn domain(&self) -> Result<Option<Domain>, VoteRegistrationError> {
+        signed_domain(&self.message).map_err(|_| VoteRegistrationError::InvalidMessageFormat)
+    }
+    fn pub_key(&self) -> Result<Address, VoteRegistrationError> {
+        let signature = parse_signature(&self.signature)
+            .map_err(|_| VoteRegistrationError::SignatureMismatch)?;
+        let msg = format!(
+            "\x19Ethereum Signed Message:\n{}{}",
+            self.message.len(),
+            self.message
+        );
+        let message_hash = ethers::utils::keccak256(msg);
+
+        signature
+            .recover(message_hash)
+            .map_err(|_| VoteRegistrationError::SignatureMismatch)
+    }
+}
+
+impl Remover {
+    /// Checks that a storage provider signer is the worker or owner of
+    /// `sp_id`, voters are checked against their registration instead
+    pub async fn verify(&self, sp_id: u32, ntw: Network) -> Result<(), VoteRegistrationError> {
+        let worker_address = match self {
+            Remover::Voter(_) => return Ok(()),
+            Remover::StorageProvider(worker_address) => worker_address,
+        };
+
+        let sp_id = sp_id_format(ntw, sp_id);
+        let controlled = verify_id(sp_id.clone(), worker_address.clone(), ntw).await?
+            || verify_owner(sp_id.clone(), worker_address.clone(), ntw).await?;
+
+        match controlled {
+            true => Ok(()),
+            false => Err(VoteRegistrationError::NotStorageProvider(
+                worker_address.clone(),
+                sp_id,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::signers::{LocalWallet, Signer};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn delegate_removal_auth() {
+        let wallet: LocalWallet =
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+                .parse()
+                .unwrap();
+
+        let message = "REMOVE t06024 SERVICE-fipvote NETWORK-calibration".to_string();
+        let signature = wallet.sign_message(&message).await.unwrap();
+        let removal = DelegateRemoval {
+            signature: format!("0x{}", signature),
+            message,
+            worker_address: None,
+        };
+
+        assert_eq!(
+            removal.auth(Network::Testnet).unwrap(),
+            (Remover::Voter(wallet.address()), 6024)
+        );
+        assert_eq!(removal.domain().unwrap().unwrap().network, "calibration");
+
+        // The storage provider id has to be on the network of the removal
+        assert!(matches!(
+            removal.auth(Network::Mainnet),
+            Err(VoteRegistrationError::InvalidMessageFormat)
+        ));
+
+        // An ethereum signature is no worker signature
+        let removal = DelegateRemoval {
+            worker_address: Some(
+                "t3qejyqmrirddrsb2w2thbaco3q6emuljumlhuonp3al35g3kkzx4zpeecycw7gim2meegemwot3gp3qr6alpa"
+                    .to_string(),
+            ),
+            ..removal
+        };
+        assert!(removal.auth(Network::Testnet).is_err());
+    }
+}
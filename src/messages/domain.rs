@@ -0,0 +1,193 @@
+use thiserror::Error;
+
+use super::{nonce::split_freshness, parser, votes::VoteError};
+use crate::storage::Network;
+
+/// The service and network a plain text message is signed for
+///
+/// Message scheme is the message followed by `SERVICE-<service> NETWORK-<network>`,
+/// before the nonce and expiry when it has them, e.g.
+/// `YAY: FIP-1 SERVICE-fipvote NETWORK-mainnet NONCE-9f86d081884c7d659a2feaa0c55ad015 EXPIRES-1685000000`.
+/// A message naming them can't be replayed on another network or against
+/// another deployment of the service. Typed data is bound to its network by
+/// the chain id of its EIP-712 domain instead
+#[derive(Debug, Clone, PartialEq)]
+pub struct Domain {
+    pub service: String,
+    /// Name of the network as it was signed, which may not be a known network
+    pub network: String,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum DomainError {
+    #[error("Message was signed for {0} on {1}")]
+    Mismatch(String, String),
+    #[error("Message must end with the service and network it is signed for")]
+    Required,
+}
+
+/// What plain text messages have to be signed for
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomainRules {
+    /// Service identifier of this deployment
+    pub service: String,
+    /// Unix timestamp messages signed without a domain are accepted until,
+    /// `None` to accept them for as long as clients still sign them
+    pub legacy_until: Option<u64>,
+}
+
+impl Domain {
+    pub fn new(service: &str, ntw: Network) -> Self {
+        Self {
+            service: service.to_string(),
+            network: ntw.name().to_string(),
+        }
+    }
+
+    /// Appends the domain to the message to sign, before any nonce
+    pub fn sign_into(&self, message: &str) -> String {
+        format!(
+            "{} SERVICE-{} NETWORK-{}",
+            message, self.service, self.network
+        )
+    }
+
+    /// The network named by the domain, `None` if no network goes by its name
+    pub fn ntw(&self) -> Option<Network> {
+        self.network.parse().ok()
+    }
+}
+
+impl DomainRules {
+    /// Checks that a plain text message was signed for this service on `ntw`
+    ///
+    /// Messages signed before domains were added are accepted until the
+    /// compatibility window closes at `legacy_until`
+    pub fn check(
+        &self,
+        domain: Option<&Domain>,
+        ntw: Network,
+        now: u64,
+    ) -> Result<(), DomainError> {
+        match domain {
+            Some(domain) if domain.service == self.service && domain.ntw() == Some(ntw) => Ok(()),
+            Some(domain) => Err(DomainError::Mismatch(
+                domain.service.clone(),
+                domain.network.clone(),
+            )),
+            None => match self.legacy_until {
+                Some(until) if now > until => Err(DomainError::Required),
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+/// Splits the domain off a message whose nonce and expiry were split off
+///
+/// Messages signed without one are returned whole with `None`
+pub fn split_domain(message: &str) -> Result<(&str, Option<Domain>), VoteError> {
+    let (body, domain) = parser::domain(message)?;
+
+    Ok((
+        body,
+        domain.map(|(service, network)| Domain {
+            service: service.to_string(),
+            network: network.to_string(),
+        }),
+    ))
+}
+
+/// The signed message without its domain, nonce and expiry
+pub fn signed_body(message: &str) -> Result<&str, VoteError> {
+    let (message, _) = split_freshness(message)?;
+
+    Ok(split_domain(message)?.0)
+}
+
+/// The domain the message was signed for, if it names one
+pub fn signed_domain(message: &str) -> Result<Option<Domain>, VoteError> {
+    let (message, _) = split_freshness(message)?;
+
+    Ok(split_domain(message)?.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(legacy_until: Option<u64>) -> DomainRules {
+        DomainRules {
+            service: "fipvote".to_string(),
+            legacy_until,
+        }
+    }
+
+    #[test]
+    fn domain_sign_into() {
+        let domain = Domain::new("fipvote", Network::Testnet);
+        let signed = domain.sign_into("YAY: FIP-1");
+
+        assert_eq!(signed, "YAY: FIP-1 SERVICE-fipvote NETWORK-calibration");
+        assert_eq!(signed_body(&signed).unwrap(), "YAY: FIP-1");
+        assert_eq!(signed_domain(&signed).unwrap(), Some(domain));
+    }
+
+    #[test]
+    fn domain_signed_body() {
+        let signed = "FIP-1 ROUND-2 SERVICE-fipvote NETWORK-mainnet NONCE-9f86d081884c7d659a2feaa0c55ad015 EXPIRES-1685000000";
+
+        assert_eq!(signed_body(signed).unwrap(), "FIP-1 ROUND-2");
+        assert_eq!(
+            signed_domain(signed).unwrap(),
+            Some(Domain::new("fipvote", Network::Mainnet))
+        );
+
+        assert_eq!(signed_body("YAY: FIP-1").unwrap(), "YAY: FIP-1");
+        assert_eq!(signed_domain("YAY: FIP-1").unwrap(), None);
+        assert!(matches!(
+            signed_body("YAY: FIP-1 NETWORK-mainnet"),
+            Err(VoteError::InvalidMessageFormat)
+        ));
+    }
+
+    #[test]
+    fn domain_check() {
+        let mainnet = Domain::new("fipvote", Network::Mainnet);
+        assert_eq!(
+            rules(None).check(Some(&mainnet), Network::Mainnet, 0),
+            Ok(())
+        );
+
+        // A ballot signed for mainnet can't be replayed on calibration
+        assert_eq!(
+            rules(None).check(Some(&mainnet), Network::Testnet, 0),
+            Err(DomainError::Mismatch(
+                "fipvote".to_string(),
+                "mainnet".to_string()
+            ))
+        );
+        let staging = Domain::new("fipvote-staging", Network::Mainnet);
+        assert!(rules(None)
+            .check(Some(&staging), Network::Mainnet, 0)
+            .is_err());
+        let unknown = Domain {
+            service: "fipvote".to_string(),
+            network: "nowhere".to_string(),
+        };
+        assert!(rules(None)
+            .check(Some(&unknown), Network::Mainnet, 0)
+            .is_err());
+
+        // Messages without a domain are accepted until the window closes
+        assert_eq!(rules(None).check(None, Network::Mainnet, u64::MAX), Ok(()));
+        assert_eq!(
+            rules(Some(1000)).check(None, Network::Mainnet, 1000),
+            Ok(())
+        );
+        assert_eq!(
+            rules(Some(1000)).check(None, Network::Mainnet, 1001),
+            Err(DomainError::Required)
+        );
+    }
+}
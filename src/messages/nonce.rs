@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{parser, votes::VoteError};
+
+/// Seconds an issued nonce can be signed into a message before it expires
+pub const NONCE_TTL: u64 = 10 * 60;
+
+/// The nonce and expiry a signed message ends with
+///
+/// Message scheme is the message followed by `NONCE-XXX EXPIRES-TIMESTAMP`,
+/// with a nonce issued by `/filecoin/nonce` and the unix timestamp the message
+/// stops being accepted at, e.g. `YAY: FIP-1 NONCE-9f86d081884c7d659a2feaa0c55ad015 EXPIRES-1685000000`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Freshness {
+    pub nonce: [u8; 16],
+    pub expires_at: u64,
+}
+
+/// A nonce to sign into one message
+#[derive(Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+pub struct IssuedNonce {
+    /// Hex encoded, signed as `NONCE-XXX`
+    pub nonce: String,
+    /// Unix timestamp the nonce is forgotten at if it wasn't used
+    pub expires_at: u64,
+}
+
+impl IssuedNonce {
+    /// Appends the nonce and its expiry to the message to sign
+    pub fn sign_into(&self, message: &str) -> String {
+        format!(
+            "{} NONCE-{} EXPIRES-{}",
+            message, self.nonce, self.expires_at
+        )
+    }
+}
+
+impl Freshness {
+    /// Whether the message stopped being accepted before `now`
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at < now
+    }
+}
+
+/// Splits the nonce and expiry off the end of a signed message
+///
+/// Messages signed without them are returned whole with `None`
+pub fn split_freshness(message: &str) -> Result<(&str, Option<Freshness>), VoteError> {
+    parser::freshness(message).map_err(|_| VoteError::InvalidMessageFormat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nonce_sign_into() {
+        let issued = IssuedNonce {
+            nonce: hex::encode([0x9f; 16]),
+            expires_at: 1685000000,
+        };
+
+        let signed = issued.sign_into("YAY: FIP-1");
+
+        assert_eq!(
+            split_freshness(&signed).unwrap(),
+            (
+                "YAY: FIP-1",
+                Some(Freshness {
+                    nonce: [0x9f; 16],
+                    expires_at: 1685000000
+                })
+            )
+        );
+    }
+
+    #[test]
+    fn nonce_split_freshness() {
+        let nonce = [0x9f; 16];
+        let signed = format!("YAY: FIP-1 NONCE-{} EXPIRES-1685000000", hex::encode(nonce));
+
+        assert_eq!(
+            split_freshness(&signed).unwrap(),
+            (
+                "YAY: FIP-1",
+                Some(Freshness {
+                    nonce,
+                    expires_at: 1685000000
+                })
+            )
+        );
+        assert_eq!(split_freshness("YAY: FIP-1").unwrap(), ("YAY: FIP-1", None));
+        assert_eq!(
+            split_freshness("FIP-1 ROUND-2").unwrap(),
+            ("FIP-1 ROUND-2", None)
+        );
+
+        for message in [
+            "YAY: FIP-1 EXPIRES-1685000000".to_string(),
+            format!("YAY: FIP-1 NONCE-{} EXPIRES-soon", hex::encode(nonce)),
+            "YAY: FIP-1 NONCE-9f9f EXPIRES-1685000000".to_string(),
+            "YAY: FIP-1 NONCE-xyz EXPIRES-1685000000".to_string(),
+        ] {
+            assert!(matches!(
+                split_freshness(&message),
+                Err(VoteError::InvalidMessageFormat)
+            ));
+        }
+    }
+
+    #[test]
+    fn nonce_is_expired() {
+        let freshness = Freshness {
+            nonce: [0; 16],
+            expires_at: 1000,
+        };
+
+        assert!(!freshness.is_expired(999));
+        assert!(!freshness.is_expired(1000));
+        assert!(freshness.is_expired(1001));
+    }
+}
@@ -0,0 +1,757 @@
+//! Grammar of the plain text messages voters, vote starters and storage providers sign
+//!
+//! Every message is checked against a length cap and a character set before it
+//! is split. Tokens are separated by ASCII spaces only and numbers are plain
+//! ASCII digits that have to fit their type, so adversarial input is rejected
+//! instead of being misread
+use thiserror::Error;
+
+use super::{nonce::Freshness, role_grant::RoleChange};
+use crate::{fip::FipNumber, roles::Role};
+
+/// Longest ballot, vote start or other signed text message accepted
+pub const MAX_MESSAGE_LEN: usize = 1024;
+/// Longest registration accepted, enough for several hundred storage providers
+pub const MAX_REGISTRATION_LEN: usize = 16 * 1024;
+/// Longest ballot option, so a ballot on any option fits in a message
+pub const MAX_LABEL_LEN: usize = 256;
+/// Digits of `u64::MAX`
+const MAX_DIGITS: usize = 20;
+
+#[derive(Debug, Clone, Error, PartialEq)]
+pub enum ParseError {
+    #[error("Message is {0} bytes, longer than {1}")]
+    TooLong(usize, usize),
+    #[error("Message is empty")]
+    Empty,
+    #[error("Message is not valid UTF-8")]
+    NotUtf8,
+    #[error("Message contains the invalid character {0:?}")]
+    InvalidCharacter(char),
+    #[error("Message is missing the {0}")]
+    MissingToken(&'static str),
+    #[error("Invalid {0} in message")]
+    InvalidToken(&'static str),
+    #[error("Choice must be an option followed by a colon")]
+    InvalidChoice,
+    #[error("Unexpected {0:?} at the end of the message")]
+    TrailingToken(String),
+    #[error("FIP-{0} is voted on more than once")]
+    DuplicateFip(FipNumber),
+}
+
+/// Checks the length and characters of a message, returning it without surrounding spaces
+///
+/// Control characters, whitespace other than the ASCII space and invisible
+/// formatting characters are rejected so a message can't be split differently
+/// from how it is shown to the signer
+pub fn text(message: &str, max_len: usize) -> Result<&str, ParseError> {
+    if message.len() > max_len {
+        return Err(ParseError::TooLong(message.len(), max_len));
+    }
+    if let Some(c) = message.chars().find(|c| !is_allowed(*c)) {
+        return Err(ParseError::InvalidCharacter(c));
+    }
+
+    match message.trim_matches(' ') {
+        "" => Err(ParseError::Empty),
+        message => Ok(message),
+    }
+}
+
+fn is_allowed(c: char) -> bool {
+    let invisible = matches!(
+        c,
+        '\u{200b}'..='\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2060}'..='\u{2064}' | '\u{feff}'
+    );
+
+    c == ' ' || !(c.is_control() || c.is_whitespace() || invisible)
+}
+
+/// Splits the last token off, `None` for a message of a single token
+fn split_last(message: &str) -> Option<(&str, &str)> {
+    message
+        .rsplit_once(' ')
+        .map(|(rest, last)| (rest.trim_end_matches(' '), last))
+}
+
+/// A number written in plain ASCII digits, without a sign and no larger than `u64::MAX`
+pub fn number(digits: &str, name: &'static str) -> Result<u64, ParseError> {
+    if digits.is_empty() || digits.len() > MAX_DIGITS || !digits.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(ParseError::InvalidToken(name));
+    }
+
+    digits
+        .parse::<u64>()
+        .map_err(|_| ParseError::InvalidToken(name))
+}
+
+/// A FIP written as `FIP-123` or `FIP-0123`
+pub fn fip(token: &str) -> Result<FipNumber, ParseError> {
+    let invalid = ParseError::InvalidToken("FIP number");
+
+    let digits = token.strip_prefix("FIP-").ok_or(invalid.clone())?;
+    let num = number(digits, "FIP number")?;
+
+    u32::try_from(num)
+        .ok()
+        .and_then(|num| FipNumber::try_from(num).ok())
+        .ok_or(invalid)
+}
+
+/// A later round of a vote written as `ROUND-2`, the first round is never written
+pub fn round(token: &str) -> Result<u64, ParseError> {
+    let digits = token
+        .strip_prefix("ROUND-")
+        .ok_or(ParseError::InvalidToken("round"))?;
+
+    match number(digits, "round")? {
+        round if round > 1 => Ok(round),
+        _ => Err(ParseError::InvalidToken("round")),
+    }
+}
+
+/// A storage provider id such as `f01000`, with the prefix of the network and without leading zeros
+pub fn sp_id(token: &str, prefix: char) -> Result<u32, ParseError> {
+    let invalid = ParseError::InvalidToken("storage provider id");
+
+    let digits = token
+        .strip_prefix(|c: char| c.eq_ignore_ascii_case(&prefix))
+        .and_then(|rest| rest.strip_prefix('0'))
+        .ok_or(invalid.clone())?;
+    let id = number(digits, "storage provider id")?;
+
+    // Only one way to write each id, so the same storage provider can't be listed twice
+    if id.to_string() != digits {
+        return Err(invalid);
+    }
+
+    u32::try_from(id).map_err(|_| invalid)
+}
+
+/// The name of a ballot option, as it is written before the colon of a ballot
+pub fn label(label: &str) -> Result<&str, ParseError> {
+    if label.len() > MAX_LABEL_LEN {
+        return Err(ParseError::TooLong(label.len(), MAX_LABEL_LEN));
+    }
+    if let Some(c) = label.chars().find(|c| !is_allowed(*c)) {
+        return Err(ParseError::InvalidCharacter(c));
+    }
+    if label.is_empty() || label.contains(':') || label.trim_matches(' ') != label {
+        return Err(ParseError::InvalidChoice);
+    }
+
+    Ok(label)
+}
+
+/// Splits the nonce and expiry off the end of a signed message, see [`Freshness`]
+///
+/// Messages signed without them are returned whole with `None`
+pub fn freshness(message: &str) -> Result<(&str, Option<Freshness>), ParseError> {
+    let message = text(message, MAX_MESSAGE_LEN)?;
+
+    let (rest, expires_at) = match split_last(message) {
+        Some((rest, last)) => match last.strip_prefix("EXPIRES-") {
+            Some(expires_at) => (rest, number(expires_at, "expiry")?),
+            None => return Ok((message, None)),
+        },
+        None => return Ok((message, None)),
+    };
+
+    let (body, nonce) = split_last(rest).ok_or(ParseError::MissingToken("nonce"))?;
+    let nonce = nonce
+        .strip_prefix("NONCE-")
+        .ok_or(ParseError::MissingToken("nonce"))?;
+    let nonce: [u8; 16] = match nonce.len() == 32 && nonce.bytes().all(|b| b.is_ascii_hexdigit()) {
+        true => hex::decode(nonce)
+            .ok()
+            .and_then(|nonce| nonce.try_into().ok())
+            .ok_or(ParseError::InvalidToken("nonce"))?,
+        false => return Err(ParseError::InvalidToken("nonce")),
+    };
+
+    Ok((body, Some(Freshness { nonce, expires_at })))
+}
+
+/// Splits the service and network a message is signed for off its end,
+/// `<message> SERVICE-<service> NETWORK-<network>`, see [`super::domain::Domain`]
+///
+/// Takes the message once its nonce and expiry were split off. Messages signed
+/// without a domain are returned whole with `None`
+pub fn domain(message: &str) -> Result<(&str, Option<(&str, &str)>), ParseError> {
+    let message = message.trim_matches(' ');
+
+    let (rest, network) = match split_last(message) {
+        Some((rest, last)) => match last.strip_prefix("NETWORK-") {
+            Some(network) => (rest, network),
+            None => return Ok((message, None)),
+        },
+        None => return Ok((message, None)),
+    };
+
+    let (body, service) = split_last(rest).ok_or(ParseError::MissingToken("service"))?;
+    let service = service
+        .strip_prefix("SERVICE-")
+        .ok_or(ParseError::MissingToken("service"))?;
+    if service.is_empty() {
+        return Err(ParseError::InvalidToken("service"));
+    }
+    if network.is_empty() {
+        return Err(ParseError::InvalidToken("network"));
+    }
+
+    Ok((body, Some((service, network))))
+}
+
+/// Splits a ballot, `<choice>: FIP-<number>`, into the choice and the FIP
+///
+/// The choice is returned as written, matching it against the options of the
+/// vote is left to the caller
+pub fn ballot(message: &str) -> Result<(&str, FipNumber), ParseError> {
+    let message = text(message, MAX_MESSAGE_LEN)?;
+
+    let (choice, fip_number) = split_last(message).ok_or(ParseError::MissingToken("FIP number"))?;
+    let fip_number = fip(fip_number)?;
+    let choice = choice.strip_suffix(':').ok_or(ParseError::InvalidChoice)?;
+
+    Ok((label(choice)?, fip_number))
+}
+
+/// Splits a combined ballot, `<choice>: FIP-<number>, <choice>: FIP-<number>...`,
+/// into the choice and FIP of each ballot in the order they were written
+///
+/// A comma only ends a ballot right after its FIP number, so options with a
+/// comma in their name can still be voted on. Each FIP may only be listed once
+pub fn ballots(message: &str) -> Result<Vec<(&str, FipNumber)>, ParseError> {
+    let mut rest = text(message, MAX_MESSAGE_LEN)?;
+
+    let mut ballots: Vec<(&str, FipNumber)> = Vec::new();
+    loop {
+        let end = rest.match_indices(',').map(|(i, _)| i).find(|&i| {
+            rest[..i]
+                .trim_end_matches(' ')
+                .rsplit(' ')
+                .next()
+                .is_some_and(|token| fip(token).is_ok())
+        });
+        let (choice, fip_number) = ballot(&rest[..end.unwrap_or(rest.len())])?;
+
+        if ballots.iter().any(|(_, num)| *num == fip_number) {
+            return Err(ParseError::DuplicateFip(fip_number));
+        }
+        ballots.push((choice, fip_number));
+
+        match end {
+            Some(i) => rest = &rest[i + 1..],
+            None => return Ok(ballots),
+        }
+    }
+}
+
+/// Splits a vote start, `FIP-<number>` or `FIP-<number> ROUND-<round>`, into the FIP and the round
+pub fn start(message: &str) -> Result<(FipNumber, Option<u64>), ParseError> {
+    let message = text(message, MAX_MESSAGE_LEN)?;
+    let mut tokens = message.split(' ').filter(|token| !token.is_empty());
+
+    let fip_number = fip(tokens.next().ok_or(ParseError::Empty)?)?;
+    let round = tokens.next().map(round).transpose()?;
+
+    match tokens.next() {
+        Some(token) => Err(ParseError::TrailingToken(token.to_string())),
+        None => Ok((fip_number, round)),
+    }
+}
+
+/// Splits a hex decoded registration, `<voter> <storage provider>...`, into the
+/// voter and the ids of the storage providers
+///
+/// The message is ASCII only, storage providers are written with `prefix`, the
+/// prefix of the network, and at least one has to be listed
+pub fn registration(message: &[u8], prefix: char) -> Result<(&str, Vec<u32>), ParseError> {
+    if message.len() > MAX_REGISTRATION_LEN {
+        return Err(ParseError::TooLong(message.len(), MAX_REGISTRATION_LEN));
+    }
+    let message = std::str::from_utf8(message).map_err(|_| ParseError::NotUtf8)?;
+    if let Some(c) = message.chars().find(|c| !c.is_ascii()) {
+        return Err(ParseError::InvalidCharacter(c));
+    }
+    let message = text(message, MAX_REGISTRATION_LEN)?;
+
+    let mut tokens = message.split(' ').filter(|token| !token.is_empty());
+    let voter = tokens.next().ok_or(ParseError::Empty)?;
+    let sp_ids = tokens
+        .map(|token| sp_id(token, prefix))
+        .collect::<Result<Vec<u32>, ParseError>>()?;
+
+    match sp_ids.is_empty() {
+        true => Err(ParseError::MissingToken("storage provider id")),
+        false => Ok((voter, sp_ids)),
+    }
+}
+
+/// Splits a role change, `GRANT <role> <address>` or `REVOKE <role> <address>`,
+/// into the change, the role and the address as written
+///
+/// Only the ADMIN and STARTER roles are given out this way, voters hold their
+/// role by registering
+pub fn role_change(message: &str) -> Result<(RoleChange, Role, &str), ParseError> {
+    let message = text(message, MAX_MESSAGE_LEN)?;
+    let mut tokens = message.split(' ').filter(|token| !token.is_empty());
+
+    let change = match tokens.next().ok_or(ParseError::Empty)? {
+        "GRANT" => RoleChange::Grant,
+        "REVOKE" => RoleChange::Revoke,
+        _ => return Err(ParseError::InvalidToken("role change")),
+    };
+    let role = match tokens.next().ok_or(ParseError::MissingToken("role"))? {
+        "ADMIN" => Role::Admin,
+        "STARTER" => Role::Starter,
+        _ => return Err(ParseError::InvalidToken("role")),
+    };
+    let address = tokens.next().ok_or(ParseError::MissingToken("address"))?;
+
+    match tokens.next() {
+        Some(token) => Err(ParseError::TrailingToken(token.to_string())),
+        None => Ok((change, role, address)),
+    }
+}
+
+/// The FIP of a flush of its ballots, `FLUSH FIP-<number>`
+pub fn flush(message: &str) -> Result<FipNumber, ParseError> {
+    let message = text(message, MAX_MESSAGE_LEN)?;
+    let mut tokens = message.split(' ').filter(|token| !token.is_empty());
+
+    if tokens.next() != Some("FLUSH") {
+        return Err(ParseError::InvalidToken("flush"));
+    }
+    let fip_number = fip(tokens
+        .next()
+        .ok_or(ParseError::MissingToken("FIP number"))?)?;
+
+    match tokens.next() {
+        Some(token) => Err(ParseError::TrailingToken(token.to_string())),
+        None => Ok(fip_number),
+    }
+}
+
+/// The worker or multisig address whose delegation offer a voter accepts,
+/// `ACCEPT <address>`, as written
+pub fn acceptance(message: &str) -> Result<&str, ParseError> {
+    let message = text(message, MAX_MESSAGE_LEN)?;
+    let mut tokens = message.split(' ').filter(|token| !token.is_empty());
+
+    if tokens.next() != Some("ACCEPT") {
+        return Err(ParseError::InvalidToken("acceptance"));
+    }
+    let address = tokens.next().ok_or(ParseError::MissingToken("address"))?;
+
+    match tokens.next() {
+        Some(token) => Err(ParseError::TrailingToken(token.to_string())),
+        None => Ok(address),
+    }
+}
+
+/// The storage provider to take off a registration, `REMOVE <storage provider id>`
+pub fn removal(message: &str, prefix: char) -> Result<u32, ParseError> {
+    let message = text(message, MAX_MESSAGE_LEN)?;
+    let mut tokens = message.split(' ').filter(|token| !token.is_empty());
+
+    if tokens.next() != Some("REMOVE") {
+        return Err(ParseError::InvalidToken("removal"));
+    }
+    let id = tokens
+        .next()
+        .ok_or(ParseError::MissingToken("storage provider id"))?;
+    let id = sp_id(id, prefix)?;
+
+    match tokens.next() {
+        Some(token) => Err(ParseError::TrailingToken(token.to_string())),
+        None => Ok(id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn fip_number(num: u32) -> FipNumber {
+        FipNumber::try_from(num).unwrap()
+    }
+
+    #[test]
+    fn parser_ballot() {
+        assert_eq!(ballot("YAY: FIP-1").unwrap(), ("YAY", fip_number(1)));
+        assert_eq!(
+            ballot("  Need more discussion:   FIP-0123 ").unwrap(),
+            ("Need more discussion", fip_number(123))
+        );
+
+        for (message, err) in [
+            ("", ParseError::Empty),
+            ("YAY:FIP-1", ParseError::MissingToken("FIP number")),
+            ("YAY FIP-1", ParseError::InvalidChoice),
+            ("YAY : FIP-1", ParseError::InvalidChoice),
+            ("A: B: FIP-1", ParseError::InvalidChoice),
+            (": FIP-1", ParseError::InvalidChoice),
+            ("YAY: FIP-0", ParseError::InvalidToken("FIP number")),
+            ("YAY: FIP-+1", ParseError::InvalidToken("FIP number")),
+            ("YAY: FIP-FIP-1", ParseError::InvalidToken("FIP number")),
+            (
+                "YAY: FIP-4294967297",
+                ParseError::InvalidToken("FIP number"),
+            ),
+            (
+                "YAY: FIP-99999999999999999999999",
+                ParseError::InvalidToken("FIP number"),
+            ),
+            ("YAY:\0 FIP-1", ParseError::InvalidCharacter('\0')),
+            ("YAY:\tFIP-1", ParseError::InvalidCharacter('\t')),
+            (
+                "YAY:\u{2003}FIP-1",
+                ParseError::InvalidCharacter('\u{2003}'),
+            ),
+            (
+                "YAY\u{200b}: FIP-1",
+                ParseError::InvalidCharacter('\u{200b}'),
+            ),
+        ] {
+            assert_eq!(ballot(message), Err(err), "{:?}", message);
+        }
+
+        let long = format!("{}: FIP-1", "A".repeat(MAX_MESSAGE_LEN));
+        assert!(matches!(ballot(&long), Err(ParseError::TooLong(_, _))));
+    }
+
+    #[test]
+    fn parser_ballots() {
+        assert_eq!(
+            ballots("YAY: FIP-1, NAY: FIP-2,ABSTAIN: FIP-3").unwrap(),
+            vec![
+                ("YAY", fip_number(1)),
+                ("NAY", fip_number(2)),
+                ("ABSTAIN", fip_number(3))
+            ]
+        );
+        assert_eq!(ballots("YAY: FIP-1").unwrap(), vec![("YAY", fip_number(1))]);
+
+        // Commas in an option name don't end the ballot
+        assert_eq!(
+            ballots("Yes, with changes: FIP-1, NAY: FIP-2").unwrap(),
+            vec![("Yes, with changes", fip_number(1)), ("NAY", fip_number(2))]
+        );
+
+        for (message, err) in [
+            ("YAY: FIP-1,", ParseError::Empty),
+            (
+                "YAY: FIP-1, NAY: FIP-01",
+                ParseError::DuplicateFip(fip_number(1)),
+            ),
+            ("YAY: FIP-1 NAY: FIP-2", ParseError::InvalidChoice),
+            (
+                "YAY: FIP-1, NAY:FIP-2",
+                ParseError::MissingToken("FIP number"),
+            ),
+        ] {
+            assert_eq!(ballots(message), Err(err), "{:?}", message);
+        }
+
+        // Combined ballots only parse as a single ballot when they hold one
+        assert_eq!(
+            ballot("YAY: FIP-1, NAY: FIP-2"),
+            Err(ParseError::InvalidChoice)
+        );
+    }
+
+    #[test]
+    fn parser_start() {
+        assert_eq!(start("FIP-12").unwrap(), (fip_number(12), None));
+        assert_eq!(start("FIP-12  ROUND-3").unwrap(), (fip_number(12), Some(3)));
+
+        for (message, err) in [
+            ("FIP-12 ROUND-1", ParseError::InvalidToken("round")),
+            ("FIP-12 ROUND--3", ParseError::InvalidToken("round")),
+            (
+                "FIP-12 ROUND-18446744073709551616",
+                ParseError::InvalidToken("round"),
+            ),
+            ("XIP-12", ParseError::InvalidToken("FIP number")),
+            (
+                "FIP-12 ROUND-2 ROUND-3",
+                ParseError::TrailingToken("ROUND-3".to_string()),
+            ),
+        ] {
+            assert_eq!(start(message), Err(err), "{:?}", message);
+        }
+    }
+
+    #[test]
+    fn parser_freshness() {
+        let (body, freshness) =
+            freshness("YAY: FIP-1 NONCE-9f86d081884c7d659a2feaa0c55ad015 EXPIRES-1700000600")
+                .unwrap();
+
+        assert_eq!(body, "YAY: FIP-1");
+        assert_eq!(freshness.unwrap().expires_at, 1700000600);
+        assert_eq!(freshness("FIP-1").unwrap(), ("FIP-1", None));
+
+        for message in [
+            "YAY: FIP-1 NONCE-+f86d081884c7d659a2feaa0c55ad015 EXPIRES-1700000600",
+            "YAY: FIP-1 NONCE-9f86d081884c7d659a2feaa0c55ad015 EXPIRES--1",
+            "YAY: FIP-1 NONCE-9f86d081884c7d659a2feaa0c55ad015 EXPIRES-99999999999999999999",
+        ] {
+            assert!(freshness(message).is_err(), "{:?}", message);
+        }
+    }
+
+    #[test]
+    fn parser_domain() {
+        assert_eq!(
+            domain("YAY: FIP-1 SERVICE-fipvote NETWORK-mainnet").unwrap(),
+            ("YAY: FIP-1", Some(("fipvote", "mainnet")))
+        );
+        assert_eq!(
+            domain("0xabc t06024 SERVICE-fipvote  NETWORK-calibration ").unwrap(),
+            ("0xabc t06024", Some(("fipvote", "calibration")))
+        );
+        assert_eq!(domain("YAY: FIP-1").unwrap(), ("YAY: FIP-1", None));
+        assert_eq!(domain("FIP-1 ROUND-2").unwrap(), ("FIP-1 ROUND-2", None));
+
+        for (message, err) in [
+            (
+                "YAY: FIP-1 NETWORK-mainnet",
+                ParseError::MissingToken("service"),
+            ),
+            (
+                "YAY: FIP-1 fipvote NETWORK-mainnet",
+                ParseError::MissingToken("service"),
+            ),
+            (
+                "YAY: FIP-1 SERVICE- NETWORK-mainnet",
+                ParseError::InvalidToken("service"),
+            ),
+            (
+                "YAY: FIP-1 SERVICE-fipvote NETWORK-",
+                ParseError::InvalidToken("network"),
+            ),
+        ] {
+            assert_eq!(domain(message), Err(err), "{:?}", message);
+        }
+    }
+
+    #[test]
+    fn parser_registration() {
+        let message = b" 0xF2361D2A9A0677e8ffD1515d65CF5190eA20eB56 t06024 T01000";
+        assert_eq!(
+            registration(message, 't').unwrap(),
+            (
+                "0xF2361D2A9A0677e8ffD1515d65CF5190eA20eB56",
+                vec![6024, 1000]
+            )
+        );
+
+        for (message, err) in [
+            (
+                &b"0xabc"[..],
+                ParseError::MissingToken("storage provider id"),
+            ),
+            (
+                &b"0xabc f06024"[..],
+                ParseError::InvalidToken("storage provider id"),
+            ),
+            (
+                &b"0xabc t006024"[..],
+                ParseError::InvalidToken("storage provider id"),
+            ),
+            (
+                &b"0xabc t0+6024"[..],
+                ParseError::InvalidToken("storage provider id"),
+            ),
+            (
+                &b"0xabc t04294967296"[..],
+                ParseError::InvalidToken("storage provider id"),
+            ),
+            (
+                &b"0xabc t0\xc3\xa9"[..],
+                ParseError::InvalidCharacter('\u{e9}'),
+            ),
+            (&b"0xabc t0\xff"[..], ParseError::NotUtf8),
+            (&b"0xabc\0t06024"[..], ParseError::InvalidCharacter('\0')),
+        ] {
+            assert_eq!(registration(message, 't'), Err(err), "{:?}", message);
+        }
+
+        let long = vec![b' '; MAX_REGISTRATION_LEN + 1];
+        assert!(matches!(
+            registration(&long, 't'),
+            Err(ParseError::TooLong(_, _))
+        ));
+    }
+
+    #[test]
+    fn parser_sp_id() {
+        assert_eq!(sp_id("f00", 'f').unwrap(), 0);
+        assert_eq!(sp_id("F0123", 'f').unwrap(), 123);
+        assert_eq!(sp_id("f04294967295", 'f').unwrap(), u32::MAX);
+
+        for token in ["f0", "f1123", "f000", "f0123a", "t0123", "f0 123"] {
+            assert!(sp_id(token, 'f').is_err(), "{:?}", token);
+        }
+    }
+
+    #[test]
+    fn parser_role_change() {
+        assert_eq!(
+            role_change("GRANT STARTER 0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56").unwrap(),
+            (
+                RoleChange::Grant,
+                Role::Starter,
+                "0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56"
+            )
+        );
+        assert_eq!(
+            role_change(" REVOKE  ADMIN t410fabc").unwrap(),
+            (RoleChange::Revoke, Role::Admin, "t410fabc")
+        );
+
+        for (message, err) in [
+            ("GRANT", ParseError::MissingToken("role")),
+            ("GRANT STARTER", ParseError::MissingToken("address")),
+            (
+                "grant STARTER 0xabc",
+                ParseError::InvalidToken("role change"),
+            ),
+            ("GRANT VOTER 0xabc", ParseError::InvalidToken("role")),
+            ("GRANT Admin 0xabc", ParseError::InvalidToken("role")),
+            (
+                "GRANT ADMIN 0xabc 0xdef",
+                ParseError::TrailingToken("0xdef".to_string()),
+            ),
+        ] {
+            assert_eq!(role_change(message), Err(err), "{:?}", message);
+        }
+    }
+
+    #[test]
+    fn parser_flush() {
+        assert_eq!(flush("FLUSH FIP-7").unwrap(), fip_number(7));
+
+        for (message, err) in [
+            ("FIP-7", ParseError::InvalidToken("flush")),
+            ("FLUSH", ParseError::MissingToken("FIP number")),
+            ("FLUSH FIP-0", ParseError::InvalidToken("FIP number")),
+            (
+                "FLUSH FIP-7 ROUND-2",
+                ParseError::TrailingToken("ROUND-2".to_string()),
+            ),
+        ] {
+            assert_eq!(flush(message), Err(err), "{:?}", message);
+        }
+    }
+
+    #[test]
+    fn parser_acceptance() {
+        assert_eq!(acceptance("ACCEPT f3abc").unwrap(), "f3abc");
+
+        for (message, err) in [
+            ("f3abc", ParseError::InvalidToken("acceptance")),
+            ("ACCEPT", ParseError::MissingToken("address")),
+            (
+                "ACCEPT f3abc f3def",
+                ParseError::TrailingToken("f3def".to_string()),
+            ),
+        ] {
+            assert_eq!(acceptance(message), Err(err), "{:?}", message);
+        }
+    }
+
+    #[test]
+    fn parser_removal() {
+        assert_eq!(removal("REMOVE f01234", 'f').unwrap(), 1234);
+
+        for (message, err) in [
+            ("f01234", ParseError::InvalidToken("removal")),
+            ("REMOVE", ParseError::MissingToken("storage provider id")),
+            (
+                "REMOVE t01234",
+                ParseError::InvalidToken("storage provider id"),
+            ),
+            (
+                "REMOVE f01234 f05678",
+                ParseError::TrailingToken("f05678".to_string()),
+            ),
+        ] {
+            assert_eq!(removal(message, 'f'), Err(err), "{:?}", message);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn parser_never_panics(message in any::<String>(), bytes in any::<Vec<u8>>()) {
+            let _ = ballot(&message);
+            let _ = start(&message);
+            let _ = freshness(&message);
+            let _ = domain(&message);
+            let _ = label(&message);
+            let _ = role_change(&message);
+            let _ = flush(&message);
+            let _ = acceptance(&message);
+            let _ = removal(&message, 'f');
+            let _ = registration(&bytes, 'f');
+            let _ = registration(message.as_bytes(), 't');
+        }
+
+        #[test]
+        fn parser_ballot_round_trip(choice in "[A-Za-z0-9]([A-Za-z0-9 ]{0,30}[A-Za-z0-9])?", num in 1..=9999u32) {
+            let message = format!("{}: FIP-{}", choice, num);
+
+            prop_assert_eq!(ballot(&message), Ok((choice.as_str(), fip_number(num))));
+        }
+
+        #[test]
+        fn parser_start_round_trip(num in 1..=9999u32, round in 2..u64::MAX) {
+            let message = format!("FIP-{} ROUND-{}", num, round);
+
+            prop_assert_eq!(start(&message), Ok((fip_number(num), Some(round))));
+        }
+
+        #[test]
+        fn parser_freshness_round_trip(nonce in any::<[u8; 16]>(), expires_at in any::<u64>()) {
+            let message = format!("YAY: FIP-1 NONCE-{} EXPIRES-{}", hex::encode(nonce), expires_at);
+
+            prop_assert_eq!(
+                freshness(&message),
+                Ok(("YAY: FIP-1", Some(Freshness { nonce, expires_at })))
+            );
+        }
+
+        #[test]
+        fn parser_registration_round_trip(sp_ids in prop::collection::vec(any::<u32>(), 1..50)) {
+            let mut message = "0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56".to_string();
+            for sp_id in sp_ids.iter() {
+                message.push_str(&format!(" f0{}", sp_id));
+            }
+
+            prop_assert_eq!(
+                registration(message.as_bytes(), 'f'),
+                Ok(("0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56", sp_ids))
+            );
+        }
+
+        #[test]
+        fn parser_rejects_large_numbers(num in (u32::MAX as u64 + 1)..) {
+            prop_assert!(fip(&format!("FIP-{}", num)).is_err());
+            prop_assert!(sp_id(&format!("f0{}", num), 'f').is_err());
+        }
+
+        #[test]
+        fn parser_rejects_control_characters(c in prop_oneof![0u32..0x20, 0x7fu32..0xa0].prop_map(|c| char::from_u32(c).unwrap()), at in 0..10usize) {
+            let mut message = "YAY: FIP-1".to_string();
+            message.insert(at.min(message.len()), c);
+
+            prop_assert_eq!(ballot(&message), Err(ParseError::InvalidCharacter(c)));
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::str::FromStr;
+
+use ethers::types::{Address, Signature};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{
+    domain::{split_domain, Domain},
+    votes::VoteError,
+};
+use crate::storage::Network;
+
+/// Seconds a signed renewal is accepted for before or after its timestamp
+pub const RENEWAL_WINDOW: u64 = 10 * 60;
+
+/// Raw json for a registered voter to renew their registration
+///
+/// Message scheme is `RENEW: NETWORK TIMESTAMP` with the network name and the
+/// unix timestamp the message was signed at, e.g. `RENEW: mainnet 1685000000`,
+/// optionally ending with the service and network it is signed for, see `Domain`
+///
+/// The registration is renewed from the signed timestamp, so replaying a
+/// message renews it to the same expiry, and stale messages are rejected
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct ReceivedRenewal {
+    signature: String,
+    message: String,
+}
+
+/// When a renewed registration expires
+#[derive(Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+pub struct RenewalReceipt {
+    /// Unix timestamp, `None` when registrations do not expire
+    pub expires_at: Option<u64>,
+}
+
+impl ReceivedRenewal {
+    /// Returns a tuple of (signer, network, signed at)
+    pub fn auth(&self) -> Result<(Address, Network, u64), VoteError> {
+        let signer = self.pub_key()?;
+        let (ntw, signed_at) = self.msg_details()?;
+
+        Ok((signer, ntw, signed_at))
+    }
+    /// The service and network the message was signed for, if it names them
+    pub fn domain(&self) -> Result<Option<Domain>, VoteError> {
+        Ok(split_domain(&self.message)?.1)
+    }
+    fn msg_details(&self) -> Result<(Network, u64), VoteError> {
+        let (message, _) = split_domain(&self.message)?;
+        let msg: Vec<&str> = message.split_whitespace().collect();
+
+        let (ntw, signed_at) = match msg.as_slice() {
+            ["RENEW:", ntw, signed_at] => (*ntw, *signed_at),
+            _ => return Err(VoteError::InvalidMessageFormat),
+        };
+
+        let ntw = match Network::from_str(ntw) {
+            Ok(ntw) => ntw,
+            Err(_) => return Err(VoteError::InvalidMessageFormat),
+        };
+        let signed_at = match u64::from_str(signed_at) {
+            Ok(signed_at) => signed_at,
+            Err(_) => return Err(VoteError::InvalidMessageFormat),
+        };
+
+        Ok((ntw, signed_at))
+    }
+    fn pub_key(&self) -> Result<Address, VoteError> {
+        let signature = Signature::from_str(&self.signature)?;
+        let msg = format!(
+            "\x19Ethereum Signed Message:\n{}{}",
+            self.message.len(),
+            self.message
+        );
+        let message_hash = ethers::utils::keccak256(msg);
+
+        let address = signature.recover(message_hash)?;
+
+        Ok(address)
+    }
+}
+
+/// Whether a renewal signed at `signed_at` is recent enough to accept at `now`
+pub fn is_fresh(signed_at: u64, now: u64) -> bool {
+    signed_at.abs_diff(now) <= RENEWAL_WINDOW
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn renewal(message: &str) -> ReceivedRenewal {
+        ReceivedRenewal {
+            signature: String::new(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn renewal_msg_details() {
+        assert_eq!(
+            renewal("RENEW: mainnet 1685000000").msg_details().unwrap(),
+            (Network::Mainnet, 1685000000)
+        );
+        assert_eq!(
+            renewal("RENEW: calibration 42").msg_details().unwrap(),
+            (Network::Testnet, 42)
+        );
+
+        for message in [
+            "RENEW:",
+            "RENEW: mainnet",
+            "RENEW: testnet 42",
+            "RENEW: mainnet -42",
+            "RENEW: mainnet 42 43",
+            "REGISTER: mainnet 42",
+        ] {
+            assert!(matches!(
+                renewal(message).msg_details(),
+                Err(VoteError::InvalidMessageFormat)
+            ));
+        }
+    }
+
+    #[test]
+    fn renewal_is_fresh() {
+        assert!(is_fresh(1000, 1000));
+        assert!(is_fresh(1000, 1000 + RENEWAL_WINDOW));
+        assert!(is_fresh(1000 + RENEWAL_WINDOW, 1000));
+        assert!(!is_fresh(1000, 1001 + RENEWAL_WINDOW));
+    }
+
+    #[test]
+    fn renewal_domain() {
+        let signed = renewal("RENEW: mainnet 1685000000 SERVICE-fipvote NETWORK-mainnet");
+
+        assert_eq!(
+            signed.msg_details().unwrap(),
+            (Network::Mainnet, 1685000000)
+        );
+        assert_eq!(
+            signed.domain().unwrap(),
+            Some(Domain::new("fipvote", Network::Mainnet))
+        );
+        assert_eq!(renewal("RENEW: mainnet 42").domain().unwrap(), None);
+    }
+}
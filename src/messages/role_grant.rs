@@ -0,0 +1,109 @@
+use ethers::types::Address;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use super::{
+    nonce::{split_freshness, Freshness},
+    parser,
+    signature::parse_signature,
+    votes::VoteError,
+};
+use crate::{
+    roles::Role,
+    storage::{parse_address, Network},
+};
+
+/// Whether a role is given to an address or taken from it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoleChange {
+    Grant,
+    Revoke,
+}
+
+/// Raw json for an admin to grant or revoke a role on a network
+///
+/// Message scheme is `GRANT <ROLE> 0x...` or `REVOKE <ROLE> 0x...`, the role
+/// being `ADMIN` or `STARTER` and the address given as 0x hex or as its f410
+/// address on the network, optionally ending with a nonce and expiry, see `Freshness`
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct RoleGrant {
+    signature: String,
+    #[schema(example = "GRANT STARTER 0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56")]
+    pub message: String,
+}
+
+impl RoleGrant {
+    /// Returns a tuple of (signer, change, role, address)
+    pub fn auth(&self, ntw: Network) -> Result<(Address, RoleChange, Role, Address), VoteError> {
+        let signer = self.pub_key()?;
+        let (change, role, address) = self.parse(ntw)?;
+
+        Ok((signer, change, role, address))
+    }
+    /// The nonce and expiry the message was signed with, if any
+    pub fn freshness(&self) -> Result<Option<Freshness>, VoteError> {
+        Ok(split_freshness(&self.message)?.1)
+    }
+    fn parse(&self, ntw: Network) -> Result<(RoleChange, Role, Address), VoteError> {
+        let (message, _) = split_freshness(&self.message)?;
+        let (change, role, address) = parser::role_change(message)?;
+        let address = match parse_address(ntw, address) {
+            Ok(address) => address,
+            Err(_) => return Err(VoteError::InvalidMessageFormat),
+        };
+
+        Ok((change, role, address))
+    }
+    fn pub_key(&self) -> Result<Address, VoteError> {
+        let signature = parse_signature(&self.signature)?;
+        let msg = format!(
+            "\x19Ethereum Signed Message:\n{}{}",
+            self.message.len(),
+            self.message
+        );
+        let message_hash = ethers::utils::keccak256(msg);
+
+        let address = signature.recover(message_hash)?;
+
+        Ok(address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::signers::{LocalWallet, Signer};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn role_grant_auth() {
+        let wallet: LocalWallet =
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+                .parse()
+                .unwrap();
+        let granted = Address::from_low_u64_be(42);
+
+        let message = format!("REVOKE STARTER {:?}", granted);
+        let signature = wallet.sign_message(&message).await.unwrap();
+        let grant = RoleGrant {
+            signature: format!("0x{}", signature),
+            message,
+        };
+
+        assert_eq!(
+            grant.auth(Network::Testnet).unwrap(),
+            (wallet.address(), RoleChange::Revoke, Role::Starter, granted)
+        );
+
+        for message in ["GRANT STARTER", "GRANT VOTER 0xabc", "GRANT ADMIN f01234"] {
+            let grant = RoleGrant {
+                signature: String::new(),
+                message: message.to_string(),
+            };
+            assert!(matches!(
+                grant.parse(Network::Testnet),
+                Err(VoteError::InvalidMessageFormat)
+            ));
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use ethers::types::{Address, Signature, U256};
+
+use super::votes::VoteError;
+
+/// Recovers the address that signed a plain text message as an Ethereum
+/// signed message, the way wallets sign with `personal_sign`
+pub fn recover_signer(signature: &str, message: &str) -> Result<Address, VoteError> {
+    let signature = parse_signature(signature)?;
+    let msg = format!("\x19Ethereum Signed Message:\n{}{}", message.len(), message);
+    let message_hash = ethers::utils::keccak256(msg);
+
+    Ok(signature.recover(message_hash)?)
+}
+
+/// Parses a hex encoded signature in any of the encodings wallets produce
+///
+/// Takes 65 byte `r ‖ s ‖ v` signatures whose `v` is 0 or 1, 27 or 28, or
+/// adjusted for a chain id as in EIP-155, in which case it may take more than
+/// one byte, as well as 64 byte EIP-2098 compact signatures. `v` is normalized
+/// to 27 or 28 so every encoding of a signature parses to the same value
+pub fn parse_signature(signature: &str) -> Result<Signature, VoteError> {
+    let hex = signature.strip_prefix("0x").unwrap_or(signature);
+    let bytes = match hex::decode(hex) {
+        Ok(bytes) => bytes,
+        Err(_) => return Err(VoteError::InvalidSignature),
+    };
+
+    let (r, s, parity) = match bytes.len() {
+        // The top bit of `s` holds the parity of `y`
+        64 => {
+            let mut s = bytes[32..].to_vec();
+            let parity = s[0] >> 7;
+            s[0] &= 0x7f;
+            (
+                U256::from_big_endian(&bytes[..32]),
+                U256::from_big_endian(&s),
+                parity as u64,
+            )
+        }
+        65..=72 => {
+            let v = bytes[64..].iter().fold(0u64, |v, b| (v << 8) | *b as u64);
+            let parity = match v {
+                0 | 1 => v,
+                27 | 28 => v - 27,
+                v if v >= 35 => (v - 35) % 2,
+                _ => return Err(VoteError::InvalidSignature),
+            };
+            (
+                U256::from_big_endian(&bytes[..32]),
+                U256::from_big_endian(&bytes[32..64]),
+                parity,
+            )
+        }
+        _ => return Err(VoteError::InvalidSignature),
+    };
+
+    Ok(Signature {
+        r,
+        s,
+        v: 27 + parity,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ethers::types::Address;
+
+    use super::*;
+
+    /// `YAY: FIP-1` signed by the test voter
+    const SIGNATURE: &str = "67ae6539cd110b9a043e3836303771d8a8ec13c7c688f369cc1a8a9f997128bf207319c7e94a60f9739c51510cb483c8f0c2efa32147690ae8221c08d34352ec1b";
+
+    fn signer(signature: &Signature) -> Address {
+        let msg = "\x19Ethereum Signed Message:\n10YAY: FIP-1";
+
+        signature.recover(ethers::utils::keccak256(msg)).unwrap()
+    }
+
+    #[test]
+    fn signature_variants() {
+        let expected = Signature::from_str(SIGNATURE).unwrap();
+        let voter = Address::from_str("0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56").unwrap();
+        assert_eq!(signer(&expected), voter);
+
+        let rs = &SIGNATURE[..128];
+        let parity = expected.v - 27;
+
+        // r and yParity·s with the parity in the top bit of s
+        let mut compact = hex::decode(rs).unwrap();
+        compact[32] |= (parity as u8) << 7;
+
+        for signature in [
+            format!("0x{}", SIGNATURE),
+            SIGNATURE.to_uppercase(),
+            format!("{}{:02x}", rs, parity),
+            format!("{}{:02x}", rs, 37 + parity),
+            // EIP-155 v of mainnet, 314 * 2 + 35, takes two bytes
+            format!("{}{:04x}", rs, 663 + parity),
+            hex::encode(compact),
+        ] {
+            let parsed = parse_signature(&signature).unwrap();
+
+            assert_eq!(parsed, expected, "{}", signature);
+            assert_eq!(signer(&parsed), voter);
+        }
+
+        for signature in [
+            "0xzz".to_string(),
+            SIGNATURE[..126].to_string(),
+            format!("{}{:02x}", rs, 29),
+            format!("{}{}", SIGNATURE, "00".repeat(8)),
+        ] {
+            assert!(matches!(
+                parse_signature(&signature),
+                Err(VoteError::InvalidSignature)
+            ));
+        }
+    }
+
+    #[test]
+    fn signature_recover_signer() {
+        let voter = Address::from_str("0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56").unwrap();
+
+        assert_eq!(recover_signer(SIGNATURE, "YAY: FIP-1").unwrap(), voter);
+        assert_ne!(recover_signer(SIGNATURE, "NAY: FIP-1").unwrap(), voter);
+    }
+}
@@ -0,0 +1,130 @@
+use std::str::FromStr;
+
+use ethers::types::{Address, Signature};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::storage::Network;
+
+use super::votes::VoteError;
+
+/// Raw json for a voter to remove storage providers from their registration
+///
+/// Message scheme is `UNREGISTER:` followed by either the storage provider
+/// id's delimited by spaces or `ALL` to remove the registration on every network
+///
+/// UNREGISTER: f0xxxx f0xxxx
+/// UNREGISTER: ALL
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct ReceivedUnregistration {
+    signature: String,
+    message: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Unregistration {
+    /// Remove the voter's registration on every network
+    All,
+    /// Remove the storage providers from the voter's registration on the network
+    StorageProviders(Network, Vec<u32>),
+}
+
+impl ReceivedUnregistration {
+    /// Returns a tuple of (signer, unregistration)
+    pub fn auth(&self) -> Result<(Address, Unregistration), VoteError> {
+        let signer = self.pub_key()?;
+        let unregistration = self.msg_details()?;
+
+        Ok((signer, unregistration))
+    }
+    fn msg_details(&self) -> Result<Unregistration, VoteError> {
+        let msg: Vec<&str> = self.message.split_whitespace().collect();
+
+        let sp_ids = match msg.split_first() {
+            Some((&"UNREGISTER:", sp_ids)) => sp_ids,
+            _ => return Err(VoteError::InvalidMessageFormat),
+        };
+
+        if let [all] = sp_ids {
+            if *all == "ALL" {
+                return Ok(Unregistration::All);
+            }
+        }
+
+        let (prefix, ntw) = match sp_ids.first().and_then(|id| id.chars().next()) {
+            Some('f') => ('f', Network::Mainnet),
+            Some('t') => ('t', Network::Testnet),
+            _ => return Err(VoteError::InvalidMessageFormat),
+        };
+
+        let mut ids = Vec::with_capacity(sp_ids.len());
+        for sp_id in sp_ids {
+            // Every storage provider id must belong to the same network
+            let id = match sp_id.strip_prefix(prefix) {
+                Some(id) => id,
+                None => return Err(VoteError::InvalidMessageFormat),
+            };
+            match u32::from_str(id) {
+                Ok(id) => ids.push(id),
+                Err(_) => return Err(VoteError::InvalidMessageFormat),
+            }
+        }
+
+        Ok(Unregistration::StorageProviders(ntw, ids))
+    }
+    fn pub_key(&self) -> Result<Address, VoteError> {
+        let signature = Signature::from_str(&self.signature)?;
+        let msg = format!(
+            "\x19Ethereum Signed Message:\n{}{}",
+            self.message.len(),
+            self.message
+        );
+        let message_hash = ethers::utils::keccak256(msg);
+
+        let address = signature.recover(message_hash)?;
+
+        Ok(address)
+    }
+}
+
+#[cfg(test)]
+mod unregistration_tests {
+    use super::*;
+
+    fn unregistration(message: &str) -> ReceivedUnregistration {
+        ReceivedUnregistration {
+            signature: String::new(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn unregistration_msg_details() {
+        let res = unregistration("UNREGISTER: ALL").msg_details();
+
+        assert_eq!(res.unwrap(), Unregistration::All);
+
+        let res = unregistration("UNREGISTER: t06024 t01234").msg_details();
+
+        assert_eq!(
+            res.unwrap(),
+            Unregistration::StorageProviders(Network::Testnet, vec![6024, 1234])
+        );
+    }
+
+    #[test]
+    fn unregistration_msg_details_invalid() {
+        let messages = vec![
+            "UNREGISTER:",
+            "REGISTER: f01234",
+            "UNREGISTER: f01234 t01234",
+            "UNREGISTER: f0abc",
+            "UNREGISTER: ALL f01234",
+        ];
+        for message in messages {
+            let res = unregistration(message).msg_details();
+
+            assert!(res.is_err());
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::str::FromStr;
+
+use ethers::types::{Address, Signature};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use utoipa::ToSchema;
+
+use super::{
+    domain::{split_domain, Domain},
+    votes::VoteError,
+};
+use crate::fip::FipNumber;
+
+/// How a vote starter ended a vote before its voting period was over
+///
+/// Endings are stored by name, any name this version doesn't know decodes to
+/// `Unknown` so a vote ended by a newer version can still be read
+#[derive(Debug, Clone, Copy, PartialEq, ToSchema)]
+pub enum VoteEnding {
+    /// The ballots cast so far are the final results
+    Concluded,
+    /// The vote has no results, for example because the FIP was withdrawn
+    Cancelled,
+    /// Ended by a newer version in a way this version doesn't know about
+    Unknown,
+}
+
+impl Serialize for VoteEnding {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            VoteEnding::Concluded => serializer.serialize_str("Concluded"),
+            VoteEnding::Cancelled => serializer.serialize_str("Cancelled"),
+            VoteEnding::Unknown => serializer.serialize_str("Unknown"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for VoteEnding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match String::deserialize(deserializer)?.as_str() {
+            "Concluded" => Ok(VoteEnding::Concluded),
+            "Cancelled" => Ok(VoteEnding::Cancelled),
+            _ => Ok(VoteEnding::Unknown),
+        }
+    }
+}
+
+/// Raw json for a vote starter to end a vote early
+///
+/// Message scheme is `FIP-XXX CONCLUDE` or `FIP-XXX CANCEL`, with the round
+/// between the two for later rounds, e.g. `FIP-XXX ROUND-2 CANCEL`, optionally
+/// ending with the service and network it is signed for, see `Domain`
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct VoteEnd {
+    signature: String,
+    pub message: String,
+}
+
+impl VoteEnd {
+    /// Returns a tuple of (signer, fip, round, ending)
+    ///
+    /// The round is that of the signed message, so a message ending one round
+    /// can't be replayed to end a later one
+    pub fn auth(&self) -> Result<(Address, FipNumber, u64, VoteEnding), VoteError> {
+        let signer = self.pub_key()?;
+        let (fip, round, ending) = self.parse()?;
+
+        Ok((signer, fip, round, ending))
+    }
+    /// The service and network the message was signed for, if it names them
+    pub fn domain(&self) -> Result<Option<Domain>, VoteError> {
+        Ok(split_domain(&self.message)?.1)
+    }
+    fn parse(&self) -> Result<(FipNumber, u64, VoteEnding), VoteError> {
+        let (message, _) = split_domain(&self.message)?;
+        let parts: Vec<&str> = message.split_whitespace().collect();
+        let (fip, round, ending) = match parts.as_slice() {
+            [fip, ending] => (*fip, None, *ending),
+            [fip, round, ending] => (*fip, Some(*round), *ending),
+            _ => return Err(VoteError::InvalidMessageFormat),
+        };
+
+        let fip = match fip.strip_prefix("FIP-").map(|f| f.parse::<FipNumber>()) {
+            Some(Ok(fip)) => fip,
+            _ => return Err(VoteError::InvalidMessageFormat),
+        };
+        let round = match round.map(|r| r.strip_prefix("ROUND-").map(|r| r.parse::<u64>())) {
+            None => 1,
+            Some(Some(Ok(round))) if round > 1 => round,
+            _ => return Err(VoteError::InvalidMessageFormat),
+        };
+        let ending = match ending {
+            "CONCLUDE" => VoteEnding::Concluded,
+            "CANCEL" => VoteEnding::Cancelled,
+            _ => return Err(VoteError::InvalidMessageFormat),
+        };
+
+        Ok((fip, round, ending))
+    }
+    fn pub_key(&self) -> Result<Address, VoteError> {
+        let signature = Signature::from_str(&self.signature)?;
+        let msg = format!(
+            "\x19Ethereum Signed Message:\n{}{}",
+            self.message.len(),
+            self.message
+        );
+        let message_hash = ethers::utils::keccak256(msg);
+
+        let address = signature.recover(message_hash)?;
+
+        Ok(address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Network;
+
+    fn end(message: &str) -> VoteEnd {
+        VoteEnd {
+            signature: String::new(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn vote_end_parse() {
+        let fip = FipNumber::try_from(123u32).unwrap();
+
+        assert_eq!(
+            end("FIP-123 CONCLUDE").parse().unwrap(),
+            (fip, 1, VoteEnding::Concluded)
+        );
+        assert_eq!(
+            end("FIP-123 ROUND-2 CANCEL").parse().unwrap(),
+            (fip, 2, VoteEnding::Cancelled)
+        );
+
+        for message in [
+            "FIP-123",
+            "FIP-123 END",
+            "FIP-123 ROUND-1 CANCEL",
+            "FIP-123 CANCEL ROUND-2",
+            "123 CANCEL",
+            "FIP-123 ROUND-2 CANCEL NOW",
+        ] {
+            assert!(matches!(
+                end(message).parse(),
+                Err(VoteError::InvalidMessageFormat)
+            ));
+        }
+    }
+
+    #[test]
+    fn vote_end_ending_encoding() {
+        // Endings as written by the derived encoding before `Unknown` existed
+        for (json, ending) in [
+            (r#""Concluded""#, VoteEnding::Concluded),
+            (r#""Cancelled""#, VoteEnding::Cancelled),
+        ] {
+            assert_eq!(serde_json::from_str::<VoteEnding>(json).unwrap(), ending);
+            assert_eq!(serde_json::to_string(&ending).unwrap(), json);
+        }
+
+        assert_eq!(
+            serde_json::from_str::<VoteEnding>(r#""Paused""#).unwrap(),
+            VoteEnding::Unknown
+        );
+    }
+
+    #[test]
+    fn vote_end_domain() {
+        let fip = FipNumber::try_from(123u32).unwrap();
+        let signed = end("FIP-123 CANCEL SERVICE-fipvote NETWORK-calibration");
+
+        assert_eq!(signed.parse().unwrap(), (fip, 1, VoteEnding::Cancelled));
+        assert_eq!(
+            signed.domain().unwrap(),
+            Some(Domain::new("fipvote", Network::Testnet))
+        );
+        assert_eq!(end("FIP-123 CANCEL").domain().unwrap(), None);
+        assert!(matches!(
+            end("FIP-123 CANCEL NETWORK-mainnet").parse(),
+            Err(VoteError::InvalidMessageFormat)
+        ));
+    }
+}
@@ -0,0 +1,135 @@
+use std::str::FromStr;
+
+use ethers::types::{Address, Signature};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use super::{
+    domain::{split_domain, Domain},
+    votes::VoteError,
+};
+use crate::fip::FipNumber;
+
+/// Longest a vote can be extended by, in seconds
+pub const MAX_VOTE_EXTENSION: u64 = 365 * 24 * 60 * 60;
+
+/// Raw json for a vote starter to extend a vote in progress
+///
+/// Message scheme is `FIP-XXX EXTEND-SECONDS`, with the round between the two
+/// for later rounds, e.g. `FIP-XXX ROUND-2 EXTEND-86400`, optionally ending
+/// with the service and network it is signed for, see `Domain`
+///
+/// The seconds are the total extension of the round past the configured vote
+/// length rather than an increment, so replaying a message changes nothing
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct VoteExtension {
+    signature: String,
+    pub message: String,
+}
+
+impl VoteExtension {
+    /// Returns a tuple of (signer, fip, round, extension)
+    pub fn auth(&self) -> Result<(Address, FipNumber, u64, u64), VoteError> {
+        let signer = self.pub_key()?;
+        let (fip, round, extension) = self.parse()?;
+
+        Ok((signer, fip, round, extension))
+    }
+    /// The service and network the message was signed for, if it names them
+    pub fn domain(&self) -> Result<Option<Domain>, VoteError> {
+        Ok(split_domain(&self.message)?.1)
+    }
+    fn parse(&self) -> Result<(FipNumber, u64, u64), VoteError> {
+        let (message, _) = split_domain(&self.message)?;
+        let parts: Vec<&str> = message.split_whitespace().collect();
+        let (fip, round, extension) = match parts.as_slice() {
+            [fip, extension] => (*fip, None, *extension),
+            [fip, round, extension] => (*fip, Some(*round), *extension),
+            _ => return Err(VoteError::InvalidMessageFormat),
+        };
+
+        let fip = match fip.strip_prefix("FIP-").map(|f| f.parse::<FipNumber>()) {
+            Some(Ok(fip)) => fip,
+            _ => return Err(VoteError::InvalidMessageFormat),
+        };
+        let round = match round.map(|r| r.strip_prefix("ROUND-").map(|r| r.parse::<u64>())) {
+            None => 1,
+            Some(Some(Ok(round))) if round > 1 => round,
+            _ => return Err(VoteError::InvalidMessageFormat),
+        };
+        let extension = match extension.strip_prefix("EXTEND-").map(|e| e.parse::<u64>()) {
+            Some(Ok(extension)) if extension > 0 && extension <= MAX_VOTE_EXTENSION => extension,
+            _ => return Err(VoteError::InvalidMessageFormat),
+        };
+
+        Ok((fip, round, extension))
+    }
+    fn pub_key(&self) -> Result<Address, VoteError> {
+        let signature = Signature::from_str(&self.signature)?;
+        let msg = format!(
+            "\x19Ethereum Signed Message:\n{}{}",
+            self.message.len(),
+            self.message
+        );
+        let message_hash = ethers::utils::keccak256(msg);
+
+        let address = signature.recover(message_hash)?;
+
+        Ok(address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Network;
+
+    fn extension(message: &str) -> VoteExtension {
+        VoteExtension {
+            signature: String::new(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn vote_extension_parse() {
+        let fip = FipNumber::try_from(123u32).unwrap();
+
+        assert_eq!(
+            extension("FIP-123 EXTEND-3600").parse().unwrap(),
+            (fip, 1, 3600)
+        );
+        assert_eq!(
+            extension("FIP-123 ROUND-2 EXTEND-86400").parse().unwrap(),
+            (fip, 2, 86400)
+        );
+
+        let too_long = format!("FIP-123 EXTEND-{}", MAX_VOTE_EXTENSION + 1);
+        for message in [
+            "FIP-123",
+            "FIP-123 EXTEND-0",
+            "FIP-123 EXTEND--1",
+            "FIP-123 EXTEND-1h",
+            "FIP-123 ROUND-1 EXTEND-3600",
+            too_long.as_str(),
+        ] {
+            assert!(matches!(
+                extension(message).parse(),
+                Err(VoteError::InvalidMessageFormat)
+            ));
+        }
+    }
+
+    #[test]
+    fn vote_extension_domain() {
+        let fip = FipNumber::try_from(123u32).unwrap();
+        let signed = extension("FIP-123 ROUND-2 EXTEND-86400 SERVICE-fipvote NETWORK-mainnet");
+
+        assert_eq!(signed.parse().unwrap(), (fip, 2, 86400));
+        assert_eq!(
+            signed.domain().unwrap(),
+            Some(Domain::new("fipvote", Network::Mainnet))
+        );
+        assert_eq!(extension("FIP-123 EXTEND-3600").domain().unwrap(), None);
+    }
+}
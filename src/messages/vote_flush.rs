@@ -0,0 +1,42 @@
+use ethers::types::Address;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use super::{
+    domain::{signed_body, signed_domain, Domain},
+    nonce::{split_freshness, Freshness},
+    parser,
+    signature::recover_signer,
+    votes::VoteError,
+};
+use crate::fip::FipNumber;
+
+/// Raw json for an admin to remove every ballot cast on a vote
+///
+/// Message scheme is `FLUSH FIP-XXX`, optionally ending with the service and
+/// network it is signed for, see `Domain`, and a nonce and expiry, see `Freshness`
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct VoteFlush {
+    signature: String,
+    #[schema(example = "FLUSH FIP-1")]
+    pub message: String,
+}
+
+impl VoteFlush {
+    /// Returns a tuple of (signer, fip)
+    pub fn auth(&self) -> Result<(Address, FipNumber), VoteError> {
+        let signer = recover_signer(&self.signature, &self.message)?;
+        let message = signed_body(&self.message)?;
+        let fip = parser::flush(message)?;
+
+        Ok((signer, fip))
+    }
+    /// The nonce and expiry the message was signed with, if any
+    pub fn freshness(&self) -> Result<Option<Freshness>, VoteError> {
+        Ok(split_freshness(&self.message)?.1)
+    }
+    /// The service and network the message was signed for, if it names them
+    pub fn domain(&self) -> Result<Option<Domain>, VoteError> {
+        signed_domain(&self.message)
+    }
+}
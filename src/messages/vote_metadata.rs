@@ -0,0 +1,182 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use ethers::types::{Address, Signature};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{
+    domain::{split_domain, Domain},
+    votes::VoteError,
+};
+use crate::fip::FipNumber;
+
+/// Language used when a request does not ask for one the vote has
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// Human-readable description of a vote in a single language
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct VoteMetadata {
+    pub title: String,
+    pub description: String,
+}
+
+/// Raw json for a vote starter to add or replace the metadata of a vote
+///
+/// Message scheme is a json encoded `MetadataUpdate`, optionally followed by
+/// the service and network it is signed for, see `Domain`
+///
+/// {"fip_number":123,"language":"en","title":"...","description":"..."}
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct ReceivedMetadataUpdate {
+    signature: String,
+    message: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MetadataUpdate {
+    pub fip_number: FipNumber,
+    pub language: String,
+    pub title: String,
+    pub description: String,
+}
+
+impl ReceivedMetadataUpdate {
+    /// Returns a tuple of (signer, update)
+    pub fn auth(&self) -> Result<(Address, MetadataUpdate), VoteError> {
+        let signer = self.pub_key()?;
+        let (message, _) = split_domain(&self.message)?;
+        let update: MetadataUpdate = match serde_json::from_str(message) {
+            Ok(update) => update,
+            Err(_) => return Err(VoteError::InvalidMessageFormat),
+        };
+
+        Ok((signer, update))
+    }
+    /// The service and network the message was signed for, if it names them
+    pub fn domain(&self) -> Result<Option<Domain>, VoteError> {
+        Ok(split_domain(&self.message)?.1)
+    }
+    fn pub_key(&self) -> Result<Address, VoteError> {
+        let signature = Signature::from_str(&self.signature)?;
+        let msg = format!(
+            "\x19Ethereum Signed Message:\n{}{}",
+            self.message.len(),
+            self.message
+        );
+        let message_hash = ethers::utils::keccak256(msg);
+
+        let address = signature.recover(message_hash)?;
+
+        Ok(address)
+    }
+}
+
+impl MetadataUpdate {
+    pub fn metadata(&self) -> VoteMetadata {
+        VoteMetadata {
+            title: self.title.clone(),
+            description: self.description.clone(),
+        }
+    }
+}
+
+/// Picks the metadata best matching an `Accept-Language` header
+///
+/// Languages are tried in order of preference, first by exact tag and then by
+/// primary subtag, falling back to the default language and then to any language
+pub fn select_language(
+    accept_language: &str,
+    available: &BTreeMap<String, VoteMetadata>,
+) -> Option<(String, VoteMetadata)> {
+    let mut preferred: Vec<(String, f32)> = accept_language
+        .split(',')
+        .filter_map(|part| {
+            let mut pieces = part.trim().split(';');
+            let tag = pieces.next()?.trim().to_lowercase();
+            if tag.is_empty() {
+                return None;
+            }
+            let quality = pieces
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((tag, quality))
+        })
+        .filter(|(_, quality)| *quality > 0.0)
+        .collect();
+    preferred.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let find = |wanted: &str| {
+        available
+            .iter()
+            .find(|(lang, _)| lang.to_lowercase() == wanted)
+            .or_else(|| {
+                available.iter().find(|(lang, _)| {
+                    lang.to_lowercase().split('-').next() == wanted.split('-').next()
+                })
+            })
+            .map(|(lang, metadata)| (lang.clone(), metadata.clone()))
+    };
+
+    for (tag, _) in preferred.iter() {
+        if tag == "*" {
+            break;
+        }
+        if let Some(found) = find(tag) {
+            return Some(found);
+        }
+    }
+
+    find(DEFAULT_LANGUAGE).or_else(|| {
+        available
+            .iter()
+            .next()
+            .map(|(lang, metadata)| (lang.clone(), metadata.clone()))
+    })
+}
+
+#[cfg(test)]
+mod vote_metadata_tests {
+    use super::*;
+
+    fn available() -> BTreeMap<String, VoteMetadata> {
+        let mut available = BTreeMap::new();
+        for lang in ["en", "pt-BR", "zh"] {
+            available.insert(
+                lang.to_string(),
+                VoteMetadata {
+                    title: format!("{} title", lang),
+                    description: format!("{} description", lang),
+                },
+            );
+        }
+        available
+    }
+
+    #[test]
+    fn vote_metadata_select_language() {
+        let available = available();
+
+        let (lang, _) = select_language("zh;q=0.9, pt-BR", &available).unwrap();
+        assert_eq!(lang, "pt-BR");
+
+        let (lang, _) = select_language("pt-PT", &available).unwrap();
+        assert_eq!(lang, "pt-BR");
+
+        let (lang, _) = select_language("de, zh;q=0.5", &available).unwrap();
+        assert_eq!(lang, "zh");
+
+        let (lang, _) = select_language("de", &available).unwrap();
+        assert_eq!(lang, "en");
+
+        let (lang, _) = select_language("", &available).unwrap();
+        assert_eq!(lang, "en");
+    }
+
+    #[test]
+    fn vote_metadata_select_language_empty() {
+        let res = select_language("en", &BTreeMap::new());
+
+        assert!(res.is_none());
+    }
+}
@@ -1,12 +1,26 @@
-use std::{num::ParseIntError, str::FromStr};
+use std::num::ParseIntError;
 
 use bls_signatures::{PublicKey, Serialize, Signature};
-use ethers::types::Address;
+use ethers::{
+    core::k256::{
+        ecdsa::{RecoveryId, Signature as SecpSignature, VerifyingKey},
+        elliptic_curve::sec1::ToEncodedPoint,
+    },
+    types::Address,
+};
 use regex::Regex;
 use serde::Deserialize;
 use thiserror::Error;
+use utoipa::ToSchema;
 
-use crate::storage::{verify_id, Network, StorageFetchError};
+use super::{
+    domain::{Domain, DomainError, DomainRules},
+    parser,
+};
+use crate::storage::{
+    fetch_msig_state, lookup_id, parse_address, sp_id_format, verify_id, verify_msig_owner,
+    verify_owner, MsigState, Network, StorageFetchError, StoragePowerProvider,
+};
 
 #[derive(Debug, Error)]
 pub enum VoteRegistrationError {
@@ -14,7 +28,7 @@ pub enum VoteRegistrationError {
     SignatureMismatch,
     #[error("Invalid message format")]
     InvalidMessageFormat,
-    #[error("Worker address: {0} does not control {1}")]
+    #[error("Worker or owner address: {0} does not control {1}")]
     NotStorageProvider(String, String),
     #[error(transparent)]
     StorageFetchError(#[from] StorageFetchError),
@@ -28,6 +42,12 @@ pub enum VoteRegistrationError {
     InvalidAddress,
     #[error("Invalid storage provider id")]
     InvalidStorageProviderId(#[from] ParseIntError),
+    #[error("Multisig approvals: {0} do not meet threshold: {1}")]
+    ThresholdNotMet(u64, u64),
+    #[error("Network does not match the network the message was signed for")]
+    NetworkMismatch,
+    #[error(transparent)]
+    Domain(#[from] DomainError),
 }
 
 /// Raw json to authorize an ethereum address
@@ -37,11 +57,40 @@ pub enum VoteRegistrationError {
 /// the list of storage provider id's delimited by spaces
 ///
 /// 0xabcdef0123456789 f0xxxx f0xxxx
-#[derive(Deserialize)]
+///
+/// The voter can also be given as its f410 address on the network, which is
+/// registered as the 0x address it maps to so ballots signed with it count
+///
+/// Storage providers that can't sign with their worker key can sign with the
+/// key of their owner instead, `worker_address` is then the owner address
+///
+/// If the storage providers are owned by a multisig (f2) actor then
+/// `worker_address` is the multisig address and the message is signed
+/// by its signers in `approvals` instead of `signature`
+///
+/// The message can end with the service and network it is signed for, see
+/// `Domain`, before it is hex encoded
+///
+/// The network is taken from the prefix of `worker_address` unless the domain
+/// or `network` names a configured network whose addresses use the same prefix
+#[derive(Deserialize, ToSchema)]
 pub struct ReceivedVoterRegistration {
+    #[serde(default)]
     signature: String,
     worker_address: String,
     message: String,
+    #[serde(default)]
+    approvals: Vec<MsigApproval>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>, example = "butterfly")]
+    network: Option<Network>,
+}
+
+/// A multisig signer's approval of a voter registration
+#[derive(Deserialize, ToSchema)]
+pub struct MsigApproval {
+    signer: String,
+    signature: String,
 }
 
 /// This struct represents an authorized eth address to vote on behalf
@@ -51,6 +100,43 @@ pub struct VoterRegistration {
     authorized_voter: Address,
     network: Network,
     sp_ids: Vec<u32>,
+    /// Worker, owner or multisig owner address that proved control of the storage providers
+    worker_address: String,
+}
+
+/// A registration whose signatures were checked, waiting for the worker or
+/// multisig address to be verified as controlling its storage providers
+#[derive(serde::Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PendingRegistration {
+    pub address: Address,
+    pub network: Network,
+    pub sp_ids: Vec<u32>,
+    /// Worker or multisig owner address as it was signed with
+    pub worker_address: String,
+    /// Whether the voter accepted the delegation, see [`crate::registrations::DelegationOffer`]
+    #[serde(default)]
+    pub accepted: bool,
+}
+
+/// Returned from a successful registration so the voter can check the weight
+/// they were registered with
+#[derive(serde::Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct RegistrationReceipt {
+    pub address: Address,
+    pub storage_providers: Vec<StorageProviderReceipt>,
+    /// Sum of the storage power of every storage provider in bytes
+    pub voting_power: u128,
+    /// Unix timestamp the registration has to be renewed by, left out if it never expires
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+#[derive(serde::Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct StorageProviderReceipt {
+    pub sp_id: String,
+    pub worker_address: String,
+    /// Raw byte power at the time of registration
+    pub storage_size: u128,
 }
 
 impl VoterRegistration {
@@ -63,108 +149,398 @@ impl VoterRegistration {
     pub fn sp_ids(&self) -> Vec<u32> {
         self.sp_ids.clone()
     }
+    /// The worker or multisig address the registration was signed with
+    pub fn worker_address(&self) -> String {
+        self.worker_address.clone()
+    }
+
+    /// Fetches the current power of every storage provider in the registration
+    /// from `power`, the provider its ballots are weighted with
+    pub async fn receipt(
+        &self,
+        power: &dyn StoragePowerProvider,
+    ) -> Result<RegistrationReceipt, StorageFetchError> {
+        let powers = power.storage_amounts(&self.sp_ids, self.network).await?;
+
+        let mut storage_providers = Vec::with_capacity(self.sp_ids.len());
+        let mut voting_power = 0;
+        for sp_id in self.sp_ids.iter() {
+            let storage_size = powers[sp_id];
+            voting_power += storage_size;
+
+            storage_providers.push(StorageProviderReceipt {
+                sp_id: sp_id_format(self.network, *sp_id),
+                worker_address: self.worker_address.clone(),
+                storage_size,
+            });
+        }
+
+        Ok(RegistrationReceipt {
+            address: self.authorized_voter,
+            storage_providers,
+            voting_power,
+            expires_at: None,
+        })
+    }
+}
+
+impl PendingRegistration {
+    fn new(address: Address, network: Network, sp_ids: Vec<u32>, worker_address: String) -> Self {
+        let mut new_ids: Vec<u32> = Vec::new();
+        for id in sp_ids {
+            if !new_ids.contains(&id) {
+                new_ids.push(id);
+            }
+        }
+
+        Self {
+            address,
+            network,
+            sp_ids: new_ids,
+            worker_address,
+            accepted: false,
+        }
+    }
+
+    /// Checks that the worker or multisig address controls every storage
+    /// provider of the registration, with one chain lookup per storage provider
+    ///
+    /// An address that isn't the worker of a storage provider is checked
+    /// against its owner, so either key can sign the registration
+    pub async fn verify(&self) -> Result<VoterRegistration, VoteRegistrationError> {
+        let msig = is_msig_address(&self.worker_address);
+
+        for id in self.sp_ids.iter() {
+            let sp_id = sp_id_format(self.network, *id);
+            let controlled = match msig {
+                true => {
+                    verify_msig_owner(sp_id.clone(), self.worker_address.clone(), self.network)
+                        .await?
+                }
+                false => {
+                    verify_id(sp_id.clone(), self.worker_address.clone(), self.network).await?
+                        || verify_owner(sp_id.clone(), self.worker_address.clone(), self.network)
+                            .await?
+                }
+            };
+            if !controlled {
+                return Err(VoteRegistrationError::NotStorageProvider(
+                    self.worker_address.clone(),
+                    sp_id,
+                ));
+            }
+        }
+
+        Ok(VoterRegistration {
+            authorized_voter: self.address,
+            network: self.network,
+            sp_ids: self.sp_ids.clone(),
+            worker_address: self.worker_address.to_lowercase(),
+        })
+    }
 }
 
 impl ReceivedVoterRegistration {
     pub async fn recover_vote_registration(
         &self,
     ) -> Result<VoterRegistration, VoteRegistrationError> {
-        let (pubkey, ntw) = self.pub_key()?;
+        self.recover_pending_registration().await?.verify().await
+    }
+
+    /// Checks the signatures of the registration, leaving its storage
+    /// providers to be verified with [`PendingRegistration::verify`]
+    pub async fn recover_pending_registration(
+        &self,
+    ) -> Result<PendingRegistration, VoteRegistrationError> {
+        if is_msig_address(&self.worker_address) {
+            return self.recover_msig_registration().await;
+        }
 
         let msg_hex = hex::decode(&self.message)?;
 
-        match pubkey.verify(self.sig()?, &msg_hex) {
-            true => (),
-            false => return Err(VoteRegistrationError::SignatureMismatch),
-        }
+        let ntw = verify_signature(&self.worker_address, &self.signature, &msg_hex)?;
+        let ntw = self.network(ntw)?;
+
+        let (address, sp_ids) = parse_message(&msg_hex, ntw)?;
 
-        let original = msg_hex
-            .to_ascii_lowercase()
-            .iter()
-            .map(|b| *b as char)
-            .collect::<String>();
-
-        let (addr, sp_ids) = match original
-            .split_whitespace()
-            .map(|s| s.to_string())
-            .collect::<Vec<String>>()
-            .split_first()
-        {
-            Some((address, sp_ids)) => (Address::from_str(address), sp_ids.to_vec()),
-            None => return Err(VoteRegistrationError::InvalidMessageFormat),
+        Ok(PendingRegistration::new(
+            address,
+            ntw,
+            sp_ids,
+            self.worker_address.clone(),
+        ))
+    }
+
+    /// Recovers a registration for storage providers owned by a multisig actor
+    ///
+    /// Every approval must be a valid signature over the message and enough
+    /// distinct signers of the multisig must approve to meet its threshold
+    async fn recover_msig_registration(
+        &self,
+    ) -> Result<PendingRegistration, VoteRegistrationError> {
+        let ntw = match self.worker_address.chars().next() {
+            Some('f') | Some('F') => Network::Mainnet,
+            _ => Network::Testnet,
         };
+        let ntw = self.network(ntw)?;
+
+        let msg_hex = hex::decode(&self.message)?;
+
+        let state = fetch_msig_state(self.worker_address.clone(), ntw).await?;
 
-        let address = match addr {
-            Ok(addr) => addr,
-            Err(_) => return Err(VoteRegistrationError::InvalidAddress),
+        let mut signer_ids: Vec<String> = Vec::new();
+        for approval in self.approvals.iter() {
+            let signer_ntw = verify_signature(&approval.signer, &approval.signature, &msg_hex)?;
+            if signer_ntw.prefix() != ntw.prefix() {
+                return Err(VoteRegistrationError::InvalidWorkerAddress);
+            }
+
+            signer_ids.push(lookup_id(approval.signer.clone(), ntw).await?);
+        }
+        check_threshold(&state, &signer_ids)?;
+
+        let (address, sp_ids) = parse_message(&msg_hex, ntw)?;
+
+        Ok(PendingRegistration::new(
+            address,
+            ntw,
+            sp_ids,
+            self.worker_address.clone(),
+        ))
+    }
+
+    /// The service and network the message was signed for, if it names them
+    pub fn domain(&self) -> Result<Option<Domain>, VoteRegistrationError> {
+        let msg_hex = hex::decode(&self.message)?;
+        let message = std::str::from_utf8(&msg_hex)
+            .map_err(|_| VoteRegistrationError::InvalidMessageFormat)?;
+        let (_, domain) =
+            parser::domain(message).map_err(|_| VoteRegistrationError::InvalidMessageFormat)?;
+
+        Ok(domain.map(|(service, network)| Domain {
+            service: service.to_string(),
+            network: network.to_string(),
+        }))
+    }
+
+    /// Checks that the message was signed for this service on `ntw`
+    pub fn check_domain(
+        &self,
+        ntw: Network,
+        rules: &DomainRules,
+        now: u64,
+    ) -> Result<(), VoteRegistrationError> {
+        Ok(rules.check(self.domain()?.as_ref(), ntw, now)?)
+    }
+
+    /// The network named in the registration, or the one of the worker address prefix
+    ///
+    /// A network the message was signed for takes the place of `network`,
+    /// which has to agree with it when both are given
+    fn network(&self, address_ntw: Network) -> Result<Network, VoteRegistrationError> {
+        let signed = self.domain()?.and_then(|domain| domain.ntw());
+        let named = match (signed, self.network) {
+            (Some(signed), Some(named)) if signed != named => {
+                return Err(VoteRegistrationError::NetworkMismatch)
+            }
+            (signed, named) => signed.or(named),
         };
 
-        let mut new_ids: Vec<u32> = Vec::new();
-        for sp_id in sp_ids.clone() {
-            match verify_id(sp_id.clone(), self.worker_address.clone(), ntw).await? {
-                true => (),
-                false => {
-                    return Err(VoteRegistrationError::NotStorageProvider(
-                        self.worker_address.clone(),
-                        sp_id.clone(),
-                    ))
-                }
-            };
-            let id = u32::from_str(&sp_id[1..])?;
-            new_ids.push(id);
+        match named {
+            Some(ntw) if ntw.prefix() == address_ntw.prefix() => Ok(ntw),
+            Some(_) => Err(VoteRegistrationError::InvalidWorkerAddress),
+            None => Ok(address_ntw),
         }
+    }
+}
 
-        Ok(VoterRegistration {
-            authorized_voter: address,
-            network: ntw,
-            sp_ids: new_ids,
-        })
+/// Verifies the signature over the message was made by the key behind the
+/// BLS (f3) or secp256k1 (f1) address and returns the network of the address
+pub(crate) fn verify_signature(
+    address: &str,
+    signature: &str,
+    msg: &[u8],
+) -> Result<Network, VoteRegistrationError> {
+    if is_secp_address(address) {
+        return verify_secp_signature(address, signature, msg);
+    }
+
+    let (pubkey, ntw) = bls_pub_key(address)?;
+
+    match pubkey.verify(bls_sig(signature)?, msg) {
+        true => Ok(ntw),
+        false => Err(VoteRegistrationError::SignatureMismatch),
+    }
+}
+
+/// Checks that enough distinct signers of the multisig approved to meet its threshold
+///
+/// Approvals by addresses that don't sign for the multisig don't count
+fn check_threshold(state: &MsigState, signer_ids: &[String]) -> Result<(), VoteRegistrationError> {
+    let mut approved: Vec<&String> = Vec::new();
+    for signer_id in signer_ids {
+        if state.signers.contains(signer_id) && !approved.contains(&signer_id) {
+            approved.push(signer_id);
+        }
+    }
+
+    match approved.len() as u64 {
+        count if count < state.threshold => Err(VoteRegistrationError::ThresholdNotMet(
+            count,
+            state.threshold,
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Splits the decoded message into the authorized voter and storage provider
+/// id's, leaving out the domain it was signed for
+fn parse_message(
+    msg_hex: &[u8],
+    ntw: Network,
+) -> Result<(Address, Vec<u32>), VoteRegistrationError> {
+    let message =
+        std::str::from_utf8(msg_hex).map_err(|_| VoteRegistrationError::InvalidMessageFormat)?;
+    let (message, _) =
+        parser::domain(message).map_err(|_| VoteRegistrationError::InvalidMessageFormat)?;
+    let (address, sp_ids) = parser::registration(message.as_bytes(), ntw.prefix())
+        .map_err(|_| VoteRegistrationError::InvalidMessageFormat)?;
+
+    match parse_address(ntw, &address.to_ascii_lowercase()) {
+        Ok(addr) => Ok((addr, sp_ids)),
+        Err(_) => Err(VoteRegistrationError::InvalidAddress),
+    }
+}
+
+/// The message the worker or multisig signers sign to register `voter` for the storage providers
+///
+/// Sent hex encoded, as `lotus wallet sign` takes it
+pub fn registration_message(voter: Address, sp_ids: &[u32], ntw: Network) -> String {
+    let mut message = format!("{:?}", voter);
+    for sp_id in sp_ids {
+        message.push(' ');
+        message.push_str(&sp_id_format(ntw, *sp_id));
+    }
+
+    message
+}
+
+/// Whether the address is a multisig (f2) actor
+pub fn is_msig_address(address: &str) -> bool {
+    let msig_base32 = Regex::new(r"(?i)^[ft][2][A-Z2-7]{39}$").unwrap();
+
+    msig_base32.is_match(address)
+}
+
+fn is_secp_address(address: &str) -> bool {
+    let secp_base32 = Regex::new(r"(?i)^[ft][1][A-Z2-7]{39}$").unwrap();
+
+    secp_base32.is_match(address)
+}
+
+/// Lotus signs the blake2b-256 hash of the message with a recoverable
+/// signature, the address payload is the blake2b-160 hash of the public key
+fn verify_secp_signature(
+    address: &str,
+    signature: &str,
+    msg: &[u8],
+) -> Result<Network, VoteRegistrationError> {
+    let ntw = match address.chars().next() {
+        Some('f') | Some('F') => Network::Mainnet,
+        _ => Network::Testnet,
+    };
+
+    let payload = match base32::decode(base32::Alphabet::RFC4648 { padding: false }, &address[2..])
+    {
+        Some(bytes) if bytes.len() == 24 => bytes[..20].to_vec(),
+        _ => return Err(VoteRegistrationError::InvalidWorkerAddress),
+    };
+
+    // Lotus prefixes the signature with its type byte
+    let bytes = match signature.get(2..) {
+        Some(sig) => hex::decode(sig)?,
+        None => return Err(VoteRegistrationError::SignatureMismatch),
+    };
+    if bytes.len() != 65 {
+        return Err(VoteRegistrationError::SignatureMismatch);
     }
 
-    fn pub_key(&self) -> Result<(PublicKey, Network), VoteRegistrationError> {
-        let testnet_base32 = Regex::new(r"(?i)^[t][3][A-Z2-7]{84}$").unwrap();
-        let mainnet_base32 = Regex::new(r"(?i)^[f][3][A-Z2-7]{84}$").unwrap();
+    let sig = match SecpSignature::from_slice(&bytes[..64]) {
+        Ok(sig) => sig,
+        Err(_) => return Err(VoteRegistrationError::SignatureMismatch),
+    };
+    let recovery_id = match RecoveryId::from_byte(bytes[64]) {
+        Some(recovery_id) => recovery_id,
+        None => return Err(VoteRegistrationError::SignatureMismatch),
+    };
+
+    let digest = blake2b(32, msg);
+    let pubkey = match VerifyingKey::recover_from_prehash(&digest, &sig, recovery_id) {
+        Ok(pubkey) => pubkey,
+        Err(_) => return Err(VoteRegistrationError::SignatureMismatch),
+    };
+
+    match blake2b(20, pubkey.to_encoded_point(false).as_bytes()) == payload {
+        true => Ok(ntw),
+        false => Err(VoteRegistrationError::SignatureMismatch),
+    }
+}
 
-        let ntw: Network;
+fn blake2b(hash_length: usize, data: &[u8]) -> Vec<u8> {
+    blake2b_simd::Params::new()
+        .hash_length(hash_length)
+        .hash(data)
+        .as_bytes()
+        .to_vec()
+}
 
-        let bytes = match testnet_base32.is_match(&self.worker_address) {
+fn bls_pub_key(address: &str) -> Result<(PublicKey, Network), VoteRegistrationError> {
+    let testnet_base32 = Regex::new(r"(?i)^[t][3][A-Z2-7]{84}$").unwrap();
+    let mainnet_base32 = Regex::new(r"(?i)^[f][3][A-Z2-7]{84}$").unwrap();
+
+    let ntw: Network;
+
+    let bytes = match testnet_base32.is_match(address) {
+        true => {
+            match base32::decode(
+                base32::Alphabet::RFC4648 { padding: false },
+                &address[2..address.len() - 6],
+            ) {
+                Some(bytes) => {
+                    ntw = Network::Testnet;
+                    bytes
+                }
+                None => return Err(VoteRegistrationError::InvalidWorkerAddress),
+            }
+        }
+        false => match mainnet_base32.is_match(address) {
             true => {
                 match base32::decode(
                     base32::Alphabet::RFC4648 { padding: false },
-                    &self.worker_address[2..self.worker_address.len() - 6],
+                    &address[2..address.len() - 6],
                 ) {
                     Some(bytes) => {
-                        ntw = Network::Testnet;
+                        ntw = Network::Mainnet;
                         bytes
                     }
                     None => return Err(VoteRegistrationError::InvalidWorkerAddress),
                 }
             }
-            false => match mainnet_base32.is_match(&self.worker_address) {
-                true => {
-                    match base32::decode(
-                        base32::Alphabet::RFC4648 { padding: false },
-                        &self.worker_address[2..self.worker_address.len() - 6],
-                    ) {
-                        Some(bytes) => {
-                            ntw = Network::Mainnet;
-                            bytes
-                        }
-                        None => return Err(VoteRegistrationError::InvalidWorkerAddress),
-                    }
-                }
-                false => return Err(VoteRegistrationError::InvalidWorkerAddress),
-            },
-        };
+            false => return Err(VoteRegistrationError::InvalidWorkerAddress),
+        },
+    };
 
-        Ok((PublicKey::from_bytes(bytes.as_slice())?, ntw))
-    }
+    Ok((PublicKey::from_bytes(bytes.as_slice())?, ntw))
+}
 
-    fn sig(&self) -> Result<Signature, VoteRegistrationError> {
-        let bytes = hex::decode(&self.signature[2..])?;
+fn bls_sig(signature: &str) -> Result<Signature, VoteRegistrationError> {
+    // Lotus prefixes the signature with its type byte
+    let bytes = match signature.get(2..) {
+        Some(sig) => hex::decode(sig)?,
+        None => return Err(VoteRegistrationError::SignatureMismatch),
+    };
 
-        Ok(Signature::from_bytes(bytes.as_slice())?)
-    }
+    Ok(Signature::from_bytes(bytes.as_slice())?)
 }
 
 pub mod test_voter_registration {
@@ -173,28 +549,82 @@ pub mod test_voter_registration {
         ReceivedVoterRegistration {
             signature: "0299f5c42a957809d0bd80cb29986b811fbacd1ed84b5995f1d21c6a7063cada725fe0c643bbcdc4082b078d1420fc9e7d08f9c28c9dbf4597183dd92c2fa2ff7727eee2e6f84fb24134051005ea93b3bfe5e294d2e1413bf111440afdadfa0744".to_string(), 
             worker_address: "t3qejyqmrirddrsb2w2thbaco3q6emuljumlhuonp3al35g3kkzx4zpeecycw7gim2meegemwot3gp3qr6alpa".to_string(), 
-            message: "2030784632333631443241394130363737653866664431353135643635434635313930654132306542353620743036303234".to_string() 
+            message: "2030784632333631443241394130363737653866664431353135643635434635313930654132306542353620743036303234".to_string(),
+            approvals: Vec::new(),
+            network: None,
         }
     }
 }
 
 #[cfg(test)]
 mod vote_registration_tests {
+    use std::str::FromStr;
+
     use super::test_voter_registration::test_reg;
     use super::*;
 
+    use ethers::core::k256::ecdsa::SigningKey;
+
+    use crate::test_redis::{StaticPower, TEST_SP_ID, TEST_SP_POWER};
+
+    /// Builds the f1/t1 address of a secp256k1 key
+    fn secp_address(prefix: char, key: &SigningKey) -> String {
+        let pubkey = key.verifying_key().to_encoded_point(false);
+        let payload = blake2b(20, pubkey.as_bytes());
+
+        let checksum = blake2b(4, &[&[1u8][..], payload.as_slice()].concat());
+
+        let encoded = base32::encode(
+            base32::Alphabet::RFC4648 { padding: false },
+            &[payload, checksum].concat(),
+        );
+        format!("{}1{}", prefix, encoded.to_lowercase())
+    }
+
+    /// Signs the message the same way `lotus wallet sign` does
+    fn secp_sign(key: &SigningKey, msg: &[u8]) -> String {
+        let (sig, recovery_id) = key.sign_prehash_recoverable(&blake2b(32, msg)).unwrap();
+
+        let mut bytes = sig.to_bytes().to_vec();
+        bytes.push(recovery_id.to_byte());
+        format!("01{}", hex::encode(bytes))
+    }
+
     #[test]
     fn vote_registration_sig() {
         let reg = test_reg();
-        let sig = reg.sig();
+        let sig = bls_sig(&reg.signature);
 
         assert!(sig.is_ok());
     }
 
+    #[test]
+    fn vote_registration_secp_signature() {
+        let key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let msg = hex::decode(test_reg().message).unwrap();
+
+        for (prefix, ntw) in [('f', Network::Mainnet), ('t', Network::Testnet)] {
+            let address = secp_address(prefix, &key);
+            assert!(is_secp_address(&address));
+
+            let signature = secp_sign(&key, &msg);
+
+            let res = verify_signature(&address, &signature, &msg);
+            assert_eq!(res.unwrap(), ntw);
+
+            let res = verify_signature(&address, &signature, b"another message");
+            assert!(res.is_err());
+        }
+
+        let other = SigningKey::from_slice(&[9u8; 32]).unwrap();
+        let res = verify_signature(&secp_address('f', &other), &secp_sign(&key, &msg), &msg);
+        assert!(res.is_err());
+    }
+
     #[test]
     fn vote_registration_pub_key() {
         let reg = test_reg();
-        let pub_key = reg.pub_key();
+        let pub_key = bls_pub_key(&reg.worker_address);
 
         assert!(pub_key.is_ok());
 
@@ -203,6 +633,91 @@ mod vote_registration_tests {
         assert_eq!(ntw, Network::Testnet);
     }
 
+    #[test]
+    fn vote_registration_is_msig_address() {
+        assert!(is_msig_address("f2kg3awbpnxxhlfyrbdr6x7mhxgsiljqoqkwxbgjq"));
+        assert!(is_msig_address("t2kg3awbpnxxhlfyrbdr6x7mhxgsiljqoqkwxbgjq"));
+        assert!(!is_msig_address(&test_reg().worker_address));
+        assert!(!is_msig_address("f01240"));
+    }
+
+    #[test]
+    fn vote_registration_parse_message() {
+        let reg = test_reg();
+        let msg_hex = hex::decode(&reg.message).unwrap();
+
+        let (address, sp_ids) = parse_message(&msg_hex, Network::Testnet).unwrap();
+
+        assert_eq!(
+            address,
+            Address::from_str("0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56").unwrap()
+        );
+        assert_eq!(sp_ids, vec![6024]);
+    }
+
+    #[test]
+    fn vote_registration_registration_message() {
+        let voter = Address::from_str("0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56").unwrap();
+
+        let message = registration_message(voter, &[6024, 1000], Network::Testnet);
+        assert_eq!(
+            message,
+            "0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56 t06024 t01000"
+        );
+
+        let (address, sp_ids) = parse_message(message.as_bytes(), Network::Testnet).unwrap();
+        assert_eq!(address, voter);
+        assert_eq!(sp_ids, vec![6024, 1000]);
+    }
+
+    #[test]
+    fn vote_registration_parse_message_delegated() {
+        let address = Address::from_str("0xd388ab098ed3e84c0d808776440b48f685198498").unwrap();
+        let msg = b"t410f2oekwcmo2pueydmaq53eic2i62crtbeyuzx2gmy t06024";
+
+        let (parsed, _) = parse_message(msg, Network::Testnet).unwrap();
+        assert_eq!(parsed, address);
+
+        let res = parse_message(msg, Network::Mainnet);
+        assert!(matches!(res, Err(VoteRegistrationError::InvalidAddress)));
+    }
+
+    #[test]
+    fn vote_registration_msig_threshold() {
+        let state = MsigState {
+            signers: vec![
+                "t01001".to_string(),
+                "t01002".to_string(),
+                "t01003".to_string(),
+            ],
+            threshold: 2,
+        };
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<String>>();
+
+        let res = check_threshold(&state, &ids(&["t01001"]));
+        assert!(matches!(
+            res,
+            Err(VoteRegistrationError::ThresholdNotMet(1, 2))
+        ));
+
+        // The same signer approving twice counts once
+        let res = check_threshold(&state, &ids(&["t01001", "t01001"]));
+        assert!(matches!(
+            res,
+            Err(VoteRegistrationError::ThresholdNotMet(1, 2))
+        ));
+
+        // Only signers of the multisig count
+        let res = check_threshold(&state, &ids(&["t01001", "t09999"]));
+        assert!(matches!(
+            res,
+            Err(VoteRegistrationError::ThresholdNotMet(1, 2))
+        ));
+
+        assert!(check_threshold(&state, &ids(&["t01003", "t01001"])).is_ok());
+        assert!(check_threshold(&state, &ids(&["t01001", "t01002", "t01003"])).is_ok());
+    }
+
     #[tokio::test]
     async fn vote_registration_recover() {
         let reg = test_reg();
@@ -212,4 +727,56 @@ mod vote_registration_tests {
         println!("{:?}", res);
         assert!(res.is_ok());
     }
+
+    #[tokio::test]
+    async fn vote_registration_recover_pending() {
+        let reg = test_reg();
+
+        // Only the signature is checked, the storage providers are verified later
+        let pending = reg.recover_pending_registration().await.unwrap();
+        assert_eq!(
+            pending.address,
+            Address::from_str("0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56").unwrap()
+        );
+        assert_eq!(pending.network, Network::Testnet);
+        assert_eq!(pending.sp_ids, vec![6024]);
+        assert_eq!(pending.worker_address, reg.worker_address);
+
+        let pending = PendingRegistration::new(
+            pending.address,
+            Network::Testnet,
+            vec![6024, 1000, 6024],
+            reg.worker_address.clone(),
+        );
+        assert_eq!(pending.sp_ids, vec![6024, 1000]);
+    }
+
+    #[tokio::test]
+    async fn vote_registration_receipt() {
+        let pending = test_reg().recover_pending_registration().await.unwrap();
+        let registration = VoterRegistration {
+            authorized_voter: pending.address,
+            network: pending.network,
+            sp_ids: pending.sp_ids,
+            worker_address: pending.worker_address,
+        };
+
+        let receipt = registration
+            .receipt(&StaticPower::new([(TEST_SP_ID, TEST_SP_POWER)]))
+            .await
+            .unwrap();
+
+        assert_eq!(receipt.address, registration.address());
+        assert_eq!(receipt.storage_providers.len(), 1);
+        assert_eq!(receipt.storage_providers[0].sp_id, "t06024");
+        assert_eq!(
+            receipt.storage_providers[0].worker_address,
+            test_reg().worker_address
+        );
+        assert_eq!(receipt.storage_providers[0].storage_size, TEST_SP_POWER);
+        assert_eq!(
+            receipt.voting_power,
+            receipt.storage_providers[0].storage_size
+        );
+    }
 }
@@ -1,39 +1,188 @@
-use std::str::FromStr;
+use std::collections::{BTreeMap, BTreeSet};
 
-use ethers::types::{Address, Signature};
-use serde::Deserialize;
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-use super::votes::VoteError;
+use super::{
+    domain::{signed_body, signed_domain, Domain},
+    nonce::{split_freshness, Freshness},
+    parser,
+    signature::parse_signature,
+    typed_data::{self, TypedMessage, TypedVoteStart},
+    vote_metadata::VoteMetadata,
+    votes::VoteError,
+};
+use crate::{fip::FipNumber, tally::Weighting};
 
-#[derive(Deserialize, Debug)]
+/// Most options a vote can be started with
+pub const MAX_BALLOT_OPTIONS: usize = 16;
+
+/// Participation and approval a vote needs to pass
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+pub struct QuorumRules {
+    /// Raw byte power in bytes that has to vote, abstentions included
+    #[serde(default)]
+    pub min_storage_size: u128,
+    /// Share of the Yay and Nay storage that has to vote Yay, in percent
+    pub pass_percentage: u8,
+}
+
+/// The message a vote starter signs to start `round` of a vote, such as `FIP-123 ROUND-2`
+pub fn start_message(fip_number: FipNumber, round: u64) -> String {
+    match round {
+        0 | 1 => format!("FIP-{}", fip_number),
+        round => format!("FIP-{} ROUND-{}", fip_number, round),
+    }
+}
+
+/// Raw json for a vote starter to start a vote
+///
+/// The message can end with the service and network it is signed for, see
+/// `Domain`, and then a nonce and expiry, see `Freshness`
+///
+/// The message can be signed as EIP-712 typed data instead, sent as
+/// `typed_data` in place of the message
+#[derive(Deserialize, Debug, ToSchema)]
 pub struct VoteStart {
     signature: String,
+    #[serde(default)]
     pub message: String,
+    #[serde(default)]
+    typed_data: Option<TypedVoteStart>,
+    /// Title and description of the vote keyed by language
+    ///
+    /// Not part of the signed message, so a start carrying it is rejected
+    /// rather than storing metadata anyone could have swapped in
+    #[serde(default)]
+    metadata: BTreeMap<String, VoteMetadata>,
+    /// Options voters choose between instead of Yay, Nay or Abstain
+    #[serde(default)]
+    pub options: Vec<String>,
+    /// Quorum and pass threshold the results are judged against
+    #[serde(default)]
+    pub quorum: Option<QuorumRules>,
+    /// How the power of each storage provider is weighted, raw byte power when left out
+    #[serde(default)]
+    pub weighting: Option<Weighting>,
+    /// Raw byte power in bytes the storage providers of a voter need between
+    /// them for the ballot to be counted, every ballot is when left out
+    #[serde(default)]
+    pub min_power: Option<u128>,
+    /// Unix timestamp the vote opens for ballots at, right away when left out
+    #[serde(default)]
+    pub opens_at: Option<u64>,
 }
 
 impl VoteStart {
     /// Returns a tuple of (signer, fip)
-    pub fn auth(&self) -> Result<(Address, u32), VoteError> {
+    pub fn auth(&self) -> Result<(Address, FipNumber), VoteError> {
         let signer = self.pub_key()?;
         let fip = self.fip()?;
 
         Ok((signer, fip))
     }
-    fn fip(&self) -> Result<u32, VoteError> {
-        // Message is in the format "FIP-XXX"
-        let fip = match self.message.split('-').nth(1) {
-            Some(fip) => fip,
-            None => return Err(VoteError::InvalidMessageFormat),
-        };
-        // convert to u32
-        let fip = match fip.parse::<u32>() {
-            Ok(fip) => fip,
-            Err(_) => return Err(VoteError::InvalidMessageFormat),
-        };
-        Ok(fip)
+    /// The nonce and expiry the message was signed with, if any
+    pub fn freshness(&self) -> Result<Option<Freshness>, VoteError> {
+        Ok(split_freshness(&self.signed_message())?.1)
+    }
+    /// The service and network the plain message was signed for, if it names them
+    pub fn domain(&self) -> Result<Option<Domain>, VoteError> {
+        signed_domain(&self.signed_message())
+    }
+    /// Chain id of a message signed as typed data, which has to match the network
+    pub fn typed_chain_id(&self) -> Option<u64> {
+        self.typed_data.as_ref().map(|typed| typed.chain_id)
+    }
+    /// The plain message, or the message the typed data stands for
+    pub fn signed_message(&self) -> String {
+        match &self.typed_data {
+            Some(typed) => typed.message(),
+            None => self.message.clone(),
+        }
+    }
+    /// The round being started, `None` for the first round of a vote
+    ///
+    /// Later rounds carry the round number in the signed message so an old
+    /// start message can't be replayed to restart a vote
+    pub fn round(&self) -> Result<Option<u64>, VoteError> {
+        Ok(self.parts()?.1)
+    }
+    /// The options of the ballot, empty for a Yay, Nay or Abstain vote
+    ///
+    /// A ballot has between 2 and `MAX_BALLOT_OPTIONS` options with distinct
+    /// names, ignoring case, that can't contain `:` since it ends the option in
+    /// a signed vote, see [`parser::label`]
+    pub fn options(&self) -> Result<Vec<String>, VoteError> {
+        let options: Vec<String> = self.options.iter().map(|o| o.trim().to_string()).collect();
+        if options.is_empty() {
+            return Ok(options);
+        }
+
+        let distinct: BTreeSet<String> = options.iter().map(|o| o.to_ascii_lowercase()).collect();
+        if options.len() < 2
+            || options.len() > MAX_BALLOT_OPTIONS
+            || distinct.len() != options.len()
+            || options.iter().any(|o| parser::label(o).is_err())
+        {
+            return Err(VoteError::InvalidBallotOptions);
+        }
+
+        Ok(options)
+    }
+    /// The quorum rules of the vote, pass thresholds only apply to Yay and Nay
+    /// so they can't be combined with ballot options
+    pub fn quorum(&self) -> Result<Option<QuorumRules>, VoteError> {
+        match self.quorum {
+            Some(rules) if rules.pass_percentage == 0 || rules.pass_percentage > 100 => {
+                Err(VoteError::InvalidQuorumRules)
+            }
+            Some(_) if !self.options.is_empty() => Err(VoteError::InvalidQuorumRules),
+            rules => Ok(rules),
+        }
+    }
+    /// How the power behind the ballots is weighted, `None` when the vote
+    /// keeps the weighting it has
+    pub fn weighting(&self) -> Result<Option<Weighting>, VoteError> {
+        match self.weighting {
+            Some(weighting) if weighting.validate().is_err() => Err(VoteError::InvalidWeighting),
+            weighting => Ok(weighting),
+        }
+    }
+    /// Checks that the start carries no metadata, which is only taken signed
+    /// through `/filecoin/votemetadata`
+    pub fn check_metadata(&self) -> Result<(), VoteError> {
+        match self.metadata.is_empty() {
+            true => Ok(()),
+            false => Err(VoteError::UnsignedMetadata),
+        }
+    }
+    /// When a vote scheduled to open later opens, `None` when it opens right away
+    ///
+    /// The discussion period has to end after `now`, votes can't be started
+    /// in the past to shorten their voting period
+    pub fn opens_at(&self, now: u64) -> Result<Option<u64>, VoteError> {
+        match self.opens_at {
+            Some(opens_at) if opens_at <= now => Err(VoteError::InvalidOpeningTime(opens_at)),
+            opens_at => Ok(opens_at),
+        }
+    }
+    fn fip(&self) -> Result<FipNumber, VoteError> {
+        Ok(self.parts()?.0)
+    }
+    /// Message is in the format "FIP-XXX" or "FIP-XXX ROUND-N"
+    fn parts(&self) -> Result<(FipNumber, Option<u64>), VoteError> {
+        let signed = self.signed_message();
+        let message = signed_body(&signed)?;
+
+        parser::start(message).map_err(|_| VoteError::InvalidMessageFormat)
     }
     fn pub_key(&self) -> Result<Address, VoteError> {
-        let signature = Signature::from_str(&self.signature)?;
+        if let Some(typed) = &self.typed_data {
+            return typed_data::recover(&self.signature, typed);
+        }
+
+        let signature = parse_signature(&self.signature)?;
         let msg = format!(
             "\x19Ethereum Signed Message:\n{}{}",
             self.message.len(),
@@ -46,3 +195,185 @@ impl VoteStart {
         Ok(address)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start(message: &str) -> VoteStart {
+        VoteStart {
+            signature: String::new(),
+            message: message.to_string(),
+            typed_data: None,
+            metadata: BTreeMap::new(),
+            options: Vec::new(),
+            quorum: None,
+            weighting: None,
+            min_power: None,
+            opens_at: None,
+        }
+    }
+
+    #[test]
+    fn vote_start_start_message() {
+        let fip = FipNumber::try_from(123u32).unwrap();
+
+        for round in [1, 2, 7] {
+            let start = start(&start_message(fip, round));
+
+            assert_eq!(start.fip().unwrap(), fip);
+            assert_eq!(start.round().unwrap(), Some(round).filter(|r| *r > 1));
+        }
+        assert_eq!(start_message(fip, 1), "FIP-123");
+    }
+
+    #[test]
+    fn vote_start_round() {
+        let fip = FipNumber::try_from(123u32).unwrap();
+
+        assert_eq!(start("FIP-123").fip().unwrap(), fip);
+        assert_eq!(start("FIP-123").round().unwrap(), None);
+
+        assert_eq!(start("FIP-123 ROUND-2").fip().unwrap(), fip);
+        assert_eq!(start("FIP-123 ROUND-2").round().unwrap(), Some(2));
+
+        // A nonce and expiry at the end don't change the round
+        let fresh = format!(
+            "FIP-123 ROUND-2 NONCE-{} EXPIRES-1685000000",
+            "00".repeat(16)
+        );
+        assert_eq!(start(&fresh).fip().unwrap(), fip);
+        assert_eq!(start(&fresh).round().unwrap(), Some(2));
+        assert_eq!(
+            start(&fresh).freshness().unwrap().unwrap().expires_at,
+            1685000000
+        );
+
+        for message in ["FIP-123 ROUND-1", "FIP-123 ROUND-x", "FIP-123 2"] {
+            assert!(matches!(
+                start(message).round(),
+                Err(VoteError::InvalidMessageFormat)
+            ));
+        }
+    }
+
+    #[test]
+    fn vote_start_options() {
+        let with_options = |options: &[&str]| VoteStart {
+            options: options.iter().map(|o| o.to_string()).collect(),
+            ..start("FIP-123")
+        };
+
+        assert!(start("FIP-123").options().unwrap().is_empty());
+        assert_eq!(
+            with_options(&[" Approve ", "Reject"]).options().unwrap(),
+            vec!["Approve", "Reject"]
+        );
+
+        let too_many: Vec<String> = (0..=MAX_BALLOT_OPTIONS).map(|i| i.to_string()).collect();
+        let too_many: Vec<&str> = too_many.iter().map(|o| o.as_str()).collect();
+
+        for options in [
+            &["Approve"][..],
+            &["Approve", "approve"],
+            &["Approve", ""],
+            &["Approve", "Option: A"],
+            too_many.as_slice(),
+        ] {
+            assert!(matches!(
+                with_options(options).options(),
+                Err(VoteError::InvalidBallotOptions)
+            ));
+        }
+    }
+
+    #[test]
+    fn vote_start_quorum() {
+        let with_quorum = |pass_percentage: u8| VoteStart {
+            quorum: Some(QuorumRules {
+                min_storage_size: 1 << 50,
+                pass_percentage,
+            }),
+            ..start("FIP-123")
+        };
+
+        assert_eq!(start("FIP-123").quorum().unwrap(), None);
+        assert_eq!(
+            with_quorum(67).quorum().unwrap().unwrap().pass_percentage,
+            67
+        );
+
+        for pass_percentage in [0, 101] {
+            assert!(matches!(
+                with_quorum(pass_percentage).quorum(),
+                Err(VoteError::InvalidQuorumRules)
+            ));
+        }
+
+        let with_options = VoteStart {
+            options: vec!["Approve".to_string(), "Reject".to_string()],
+            ..with_quorum(50)
+        };
+        assert!(matches!(
+            with_options.quorum(),
+            Err(VoteError::InvalidQuorumRules)
+        ));
+    }
+
+    #[test]
+    fn vote_start_weighting() {
+        let with_weighting = |weighting| VoteStart {
+            weighting: Some(weighting),
+            ..start("FIP-123")
+        };
+
+        assert_eq!(start("FIP-123").weighting().unwrap(), None);
+        assert_eq!(
+            with_weighting(Weighting::Capped(10)).weighting().unwrap(),
+            Some(Weighting::Capped(10))
+        );
+        assert!(matches!(
+            with_weighting(Weighting::Capped(0)).weighting(),
+            Err(VoteError::InvalidWeighting)
+        ));
+    }
+
+    #[test]
+    fn vote_start_metadata() {
+        assert!(start("FIP-123").check_metadata().is_ok());
+
+        let mut with_metadata = start("FIP-123");
+        with_metadata.metadata.insert(
+            "en".to_string(),
+            VoteMetadata {
+                title: "Title".to_string(),
+                description: "Description".to_string(),
+            },
+        );
+        assert!(matches!(
+            with_metadata.check_metadata(),
+            Err(VoteError::UnsignedMetadata)
+        ));
+    }
+
+    #[test]
+    fn vote_start_opens_at() {
+        let with_opens_at = |opens_at| VoteStart {
+            opens_at: Some(opens_at),
+            ..start("FIP-123")
+        };
+        let now = 1700000000;
+
+        assert_eq!(start("FIP-123").opens_at(now).unwrap(), None);
+        assert_eq!(
+            with_opens_at(now + 86400).opens_at(now).unwrap(),
+            Some(now + 86400)
+        );
+        for opens_at in [now - 1, now] {
+            assert!(matches!(
+                with_opens_at(opens_at).opens_at(now),
+                Err(VoteError::InvalidOpeningTime(t)) if t == opens_at
+            ));
+        }
+    }
+}
@@ -1,15 +1,37 @@
-use std::str::FromStr;
+use std::collections::BTreeMap;
 
 use ethers::{prelude::*, types::Address};
 use redis::{from_redis_value, FromRedisValue, ToRedisArgs};
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use thiserror::Error;
-
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+use utoipa::ToSchema;
+
+use super::{
+    class_registration::VoterClass,
+    domain::{signed_body, signed_domain, Domain},
+    nonce::{split_freshness, Freshness},
+    parser::{self, ParseError},
+    signature::parse_signature,
+    typed_data::{self, TypedMessage, TypedVote},
+};
+use crate::{
+    fip::FipNumber,
+    storage::{sp_id_format, Network},
+};
+
+/// A ballot choice
+///
+/// Known options are encoded by name in json and by byte in redis values. Any
+/// other byte decodes to `Unknown` so ballots written by a newer version with
+/// options this version doesn't know about can still be read. Votes started
+/// with their own list of options are cast for a `Named` option instead
+#[derive(Debug, PartialEq, Clone)]
 pub enum VoteOption {
     Yay,
     Nay,
     Abstain,
+    Unknown(u8),
+    Named(String),
 }
 
 #[derive(Debug, Error)]
@@ -18,66 +40,246 @@ pub enum VoteError {
     SignatureError(#[from] SignatureError),
     #[error("Invalid message format")]
     InvalidMessageFormat,
+    #[error("Invalid signature encoding")]
+    InvalidSignature,
     #[error("Invalid vote option")]
     InvalidVoteOption,
+    #[error("Invalid ballot options")]
+    InvalidBallotOptions,
+    #[error("Invalid quorum rules")]
+    InvalidQuorumRules,
+    #[error("Invalid weighting, caps are between 1 and 100 percent")]
+    InvalidWeighting,
+    #[error("Metadata isn't covered by the signature, set it through /filecoin/votemetadata")]
+    UnsignedMetadata,
+    #[error("Votes can only be scheduled to open later, not at {0}")]
+    InvalidOpeningTime(u64),
+    #[error("Vote is for FIP-{1} but was submitted for FIP-{0}")]
+    FipMismatch(FipNumber, FipNumber),
+    #[error("FIP-{0} is voted on more than once")]
+    DuplicateFip(FipNumber),
+}
+
+impl From<ParseError> for VoteError {
+    fn from(e: ParseError) -> Self {
+        match e {
+            ParseError::InvalidChoice => VoteError::InvalidVoteOption,
+            ParseError::DuplicateFip(fip) => VoteError::DuplicateFip(fip),
+            _ => VoteError::InvalidMessageFormat,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
 pub struct Vote {
     choice: VoteOption,
     address: Address,
-    fip: u32,
+    fip: FipNumber,
+    /// Unix time the ballot was recorded, 0 for ballots recorded before it was kept
+    #[serde(default, skip_serializing_if = "is_default")]
+    recorded_at: u64,
+    /// The signed message and signature, so anyone can recover the voter
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    message: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    signature: String,
+    /// Chain id the ballot was signed for as EIP-712 typed data, `None` for a personal message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    typed_chain_id: Option<u64>,
+    /// Raw byte power of each storage provider the ballot was cast for
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    storage_providers: BTreeMap<u32, u128>,
+    /// Token holder balance in attoFIL the ballot was cast with, 0 for other voters
+    #[serde(default, skip_serializing_if = "is_default")]
+    fil: u128,
+    /// Class the voter was registered as when the ballot was cast, ballots
+    /// recorded before it was kept were cast by storage providers
+    #[serde(default, skip_serializing_if = "is_default")]
+    class: VoterClass,
+}
+
+/// A recorded ballot as published for third party verification
+#[derive(Serialize, Debug, ToSchema)]
+pub struct BallotAudit {
+    /// The voter address, or the hex encoded keccak256 hash of it when anonymized
+    pub voter: String,
+    pub choice: VoteOption,
+    pub recorded_at: u64,
+    /// Left out when anonymized since they identify the voter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// Set when the signature is over the EIP-712 typed data of `message`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub typed_chain_id: Option<u64>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage_providers: BTreeMap<u32, u128>,
+    /// Total raw byte power the ballot was cast with
+    pub storage_size: u128,
+}
+
+/// The power a ballot added to its choice
+#[derive(Serialize, Debug, ToSchema)]
+pub struct BallotWeight {
+    pub voter: String,
+    pub choice: VoteOption,
+    /// Storage providers the ballot was cast for, such as `f01000`
+    pub sp_ids: Vec<String>,
+    /// Raw byte power of the storage providers
+    pub power: u128,
+    /// What the ballot added to the storage size of its choice, the power
+    /// weighted as the vote was started with
+    pub weighted_power: u128,
+}
+
+/// Ballots recorded before the audit fields existed serialize as they always did
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
 }
 
 /// Message scheme
 ///
 /// YAY: FIP-xxx
-#[derive(Deserialize, Default)]
+///
+/// Votes with their own options are cast with the option name instead, for
+/// example `OPTION-A: FIP-xxx`
+///
+/// Either can end with the service and network it is signed for, see
+/// `Domain`, and then a nonce and expiry, see `Freshness`
+///
+/// Several FIPs can be voted on with one signature by separating their
+/// ballots with commas, `YAY: FIP-1, NAY: FIP-2, ABSTAIN: FIP-3`, which are
+/// sent to `/filecoin/vote/batch`
+///
+/// Ballots can be signed as EIP-712 typed data instead, sent as `typed_data`
+/// in place of the message
+#[derive(Deserialize, Default, ToSchema)]
 pub struct ReceivedVote {
     signature: String,
+    #[serde(default)]
     message: String,
+    #[serde(default)]
+    typed_data: Option<TypedVote>,
 }
 
 impl ReceivedVote {
+    /// Recovers a ballot for a Yay, Nay or Abstain vote
     pub fn vote(&self) -> Result<Vote, VoteError> {
-        let (choice, fip) = self.msg_details()?;
+        self.vote_on(&[])
+    }
+    /// Recovers a ballot for a vote started with `options`, which is empty
+    /// for a Yay, Nay or Abstain vote
+    pub fn vote_on(&self, options: &[String]) -> Result<Vote, VoteError> {
+        let (choice, fip) = self.msg_details(options)?;
         let address = self.pub_key()?;
 
         Ok(Vote {
             choice,
             address,
             fip,
+            recorded_at: 0,
+            message: self.signed_message(),
+            signature: self.signature.clone(),
+            typed_chain_id: self.typed_chain_id(),
+            storage_providers: BTreeMap::new(),
+            fil: 0,
+            class: VoterClass::StorageProvider,
         })
     }
-    fn msg_details(&self) -> Result<(VoteOption, u32), VoteError> {
-        let msg: Vec<String> = self
-            .message
-            .split_whitespace()
-            .map(|s| s.to_string())
-            .collect();
+    /// Recovers the ballot on each FIP of a combined ballot, in the order
+    /// they were written
+    ///
+    /// `options` holds the options of each vote started with its own, the
+    /// others are voted on with Yay, Nay or Abstain
+    pub fn votes_on(
+        &self,
+        options: &BTreeMap<FipNumber, Vec<String>>,
+    ) -> Result<Vec<Vote>, VoteError> {
+        let signed = self.signed_message();
+        let message = signed_body(&signed)?;
+        let address = self.pub_key()?;
 
-        let (choice, fip_str) = match msg.as_slice() {
-            [choice, fip] => (choice, fip),
-            _ => return Err(VoteError::InvalidMessageFormat),
-        };
+        parser::ballots(message)?
+            .into_iter()
+            .map(|(label, fip)| {
+                let options = options.get(&fip).map(Vec::as_slice).unwrap_or_default();
+
+                Ok(Vote {
+                    choice: choice(label, options)?,
+                    address,
+                    fip,
+                    recorded_at: 0,
+                    message: signed.clone(),
+                    signature: self.signature.clone(),
+                    typed_chain_id: self.typed_chain_id(),
+                    storage_providers: BTreeMap::new(),
+                    fil: 0,
+                    class: VoterClass::StorageProvider,
+                })
+            })
+            .collect()
+    }
+    /// The FIPs a combined ballot votes on, in the order they were written
+    pub fn fips(&self) -> Result<Vec<FipNumber>, VoteError> {
+        let signed = self.signed_message();
+        let message = signed_body(&signed)?;
+
+        Ok(parser::ballots(message)?
+            .into_iter()
+            .map(|(_, fip)| fip)
+            .collect())
+    }
+    /// Identifies the ballot by the hash of its signature so retries of the
+    /// same submission map to the same receipt
+    pub fn receipt_id(&self) -> Result<String, VoteError> {
+        let signature = parse_signature(&self.signature)?;
 
-        let choice = match choice.as_str() {
-            "YAY:" => Ok(VoteOption::Yay),
-            "NAY:" => Ok(VoteOption::Nay),
-            "ABSTAIN:" => Ok(VoteOption::Abstain),
-            _ => Err(VoteError::InvalidVoteOption),
-        }?;
+        Ok(hex::encode(ethers::utils::keccak256(signature.to_vec())))
+    }
+    /// Identifies the ballot on one FIP of a combined ballot, which all share a signature
+    pub fn batch_receipt_id(&self, fip: FipNumber) -> Result<String, VoteError> {
+        let mut bytes = parse_signature(&self.signature)?.to_vec();
+        bytes.extend(fip.get().to_be_bytes());
 
-        let fip = fip_str
-            .strip_prefix("FIP-")
-            .ok_or(VoteError::InvalidMessageFormat)?
-            .parse::<u32>()
-            .map_err(|_| VoteError::InvalidMessageFormat)?;
+        Ok(hex::encode(ethers::utils::keccak256(bytes)))
+    }
+    /// The address that signed the ballot
+    pub fn signer(&self) -> Result<Address, VoteError> {
+        self.pub_key()
+    }
+    /// The nonce and expiry the ballot was signed with, if any
+    pub fn freshness(&self) -> Result<Option<Freshness>, VoteError> {
+        Ok(split_freshness(&self.signed_message())?.1)
+    }
+    /// The service and network the plain message was signed for, if it names them
+    pub fn domain(&self) -> Result<Option<Domain>, VoteError> {
+        signed_domain(&self.signed_message())
+    }
+    /// Chain id of a ballot signed as typed data, which has to match the network of the voter
+    pub fn typed_chain_id(&self) -> Option<u64> {
+        self.typed_data.as_ref().map(|typed| typed.chain_id)
+    }
+    /// The plain message, or the message the typed data stands for
+    fn signed_message(&self) -> String {
+        match &self.typed_data {
+            Some(typed) => typed.message(),
+            None => self.message.clone(),
+        }
+    }
+    fn msg_details(&self, options: &[String]) -> Result<(VoteOption, FipNumber), VoteError> {
+        let signed = self.signed_message();
+        let message = signed_body(&signed)?;
+        let (label, fip) = parser::ballot(message)?;
 
-        Ok((choice, fip))
+        Ok((choice(label, options)?, fip))
     }
     fn pub_key(&self) -> Result<Address, VoteError> {
-        let signature = Signature::from_str(&self.signature)?;
+        if let Some(typed) = &self.typed_data {
+            return typed_data::recover(&self.signature, typed);
+        }
+
+        let signature = parse_signature(&self.signature)?;
         let msg = format!(
             "\x19Ethereum Signed Message:\n{}{}",
             self.message.len(),
@@ -91,6 +293,23 @@ impl ReceivedVote {
     }
 }
 
+/// The choice a ballot label stands for on a vote started with `options`
+fn choice(label: &str, options: &[String]) -> Result<VoteOption, VoteError> {
+    match options {
+        [] => match label {
+            "YAY" => Ok(VoteOption::Yay),
+            "NAY" => Ok(VoteOption::Nay),
+            "ABSTAIN" => Ok(VoteOption::Abstain),
+            _ => Err(VoteError::InvalidVoteOption),
+        },
+        options => options
+            .iter()
+            .find(|option| option.eq_ignore_ascii_case(label))
+            .map(|option| VoteOption::Named(option.clone()))
+            .ok_or(VoteError::InvalidVoteOption),
+    }
+}
+
 impl Vote {
     pub fn choice(&self) -> VoteOption {
         self.choice.clone()
@@ -99,6 +318,99 @@ impl Vote {
     pub fn voter(&self) -> Address {
         self.address
     }
+
+    pub fn fip(&self) -> FipNumber {
+        self.fip
+    }
+
+    /// Chain id the ballot was signed for as typed data, `None` for a personal message
+    pub fn typed_chain_id(&self) -> Option<u64> {
+        self.typed_chain_id
+    }
+
+    /// Unix time the ballot was recorded, 0 for ballots recorded before it was kept
+    pub fn recorded_at(&self) -> u64 {
+        self.recorded_at
+    }
+
+    /// Ids of the storage providers the ballot was cast for
+    pub fn storage_providers(&self) -> impl Iterator<Item = u32> + '_ {
+        self.storage_providers.keys().copied()
+    }
+
+    /// Raw byte power of each storage provider the ballot was cast for
+    pub fn storage_powers(&self) -> &BTreeMap<u32, u128> {
+        &self.storage_providers
+    }
+
+    /// Token holder balance in attoFIL the ballot was cast with, 0 for other
+    /// voters and ballots recorded before it was kept
+    pub fn fil(&self) -> u128 {
+        self.fil
+    }
+
+    /// Class the voter was registered as when the ballot was cast
+    pub fn class(&self) -> VoterClass {
+        self.class
+    }
+
+    /// Stamps the ballot with the time, voter class, storage power and balance it was recorded with
+    pub fn record(
+        &mut self,
+        recorded_at: u64,
+        class: VoterClass,
+        storage_providers: BTreeMap<u32, u128>,
+        fil: u128,
+    ) {
+        self.recorded_at = recorded_at;
+        self.class = class;
+        self.storage_providers = storage_providers;
+        self.fil = fil;
+    }
+
+    pub fn audit(&self, anonymize: bool) -> BallotAudit {
+        let storage_size = self.storage_providers.values().sum();
+
+        if anonymize {
+            return BallotAudit {
+                voter: hex::encode(ethers::utils::keccak256(self.address)),
+                choice: self.choice(),
+                recorded_at: self.recorded_at,
+                message: None,
+                signature: None,
+                typed_chain_id: None,
+                storage_providers: BTreeMap::new(),
+                storage_size,
+            };
+        }
+
+        BallotAudit {
+            voter: format!("{:?}", self.address),
+            choice: self.choice(),
+            recorded_at: self.recorded_at,
+            message: Some(self.message.clone()),
+            signature: Some(self.signature.clone()),
+            typed_chain_id: self.typed_chain_id,
+            storage_providers: self.storage_providers.clone(),
+            storage_size,
+        }
+    }
+
+    /// The storage providers behind the ballot and the power it was counted
+    /// with, `weighted_power` being the weight of the ballot
+    pub fn weight(&self, ntw: Network, weighted_power: u128) -> BallotWeight {
+        BallotWeight {
+            voter: format!("{:?}", self.address),
+            choice: self.choice(),
+            sp_ids: self
+                .storage_providers
+                .keys()
+                .map(|sp_id| sp_id_format(ntw, *sp_id))
+                .collect(),
+            power: self.storage_providers.values().sum(),
+            weighted_power,
+        }
+    }
 }
 
 impl From<u8> for VoteOption {
@@ -107,7 +419,7 @@ impl From<u8> for VoteOption {
             0 => VoteOption::Yay,
             1 => VoteOption::Nay,
             2 => VoteOption::Abstain,
-            _ => panic!("Invalid vote option"),
+            byte => VoteOption::Unknown(byte),
         }
     }
 }
@@ -118,22 +430,70 @@ impl From<VoteOption> for u8 {
             VoteOption::Yay => 0,
             VoteOption::Nay => 1,
             VoteOption::Abstain => 2,
+            VoteOption::Unknown(byte) => byte,
+            // Named options only exist in json ballots and have no byte
+            VoteOption::Named(_) => u8::MAX,
+        }
+    }
+}
+
+impl Serialize for VoteOption {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            VoteOption::Yay => serializer.serialize_str("Yay"),
+            VoteOption::Nay => serializer.serialize_str("Nay"),
+            VoteOption::Abstain => serializer.serialize_str("Abstain"),
+            VoteOption::Unknown(byte) => serializer.serialize_u8(*byte),
+            VoteOption::Named(name) => {
+                serializer.serialize_newtype_variant("VoteOption", 4, "Named", name)
+            }
         }
     }
 }
 
+impl<'de> Deserialize<'de> for VoteOption {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct VoteOptionVisitor;
+
+        impl<'de> de::Visitor<'de> for VoteOptionVisitor {
+            type Value = VoteOption;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a vote option name or byte")
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                match u8::try_from(v) {
+                    Ok(byte) => Ok(byte.into()),
+                    Err(_) => Err(E::custom("Invalid vote option")),
+                }
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                match v {
+                    "Yay" => Ok(VoteOption::Yay),
+                    "Nay" => Ok(VoteOption::Nay),
+                    "Abstain" => Ok(VoteOption::Abstain),
+                    _ => Err(E::custom("Invalid vote option")),
+                }
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                match map.next_entry::<String, String>()? {
+                    Some((key, name)) if key == "Named" => Ok(VoteOption::Named(name)),
+                    _ => Err(de::Error::custom("Invalid vote option")),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(VoteOptionVisitor)
+    }
+}
+
 impl FromRedisValue for VoteOption {
     fn from_redis_value(v: &redis::Value) -> redis::RedisResult<Self> {
         let s: u8 = from_redis_value(v)?;
-        match s {
-            0 => Ok(VoteOption::Yay),
-            1 => Ok(VoteOption::Nay),
-            2 => Ok(VoteOption::Abstain),
-            _ => Err(redis::RedisError::from((
-                redis::ErrorKind::TypeError,
-                "Invalid vote option",
-            ))),
-        }
+        Ok(s.into())
     }
 }
 
@@ -142,11 +502,7 @@ impl ToRedisArgs for VoteOption {
     where
         W: ?Sized + redis::RedisWrite,
     {
-        let val = match self {
-            VoteOption::Yay => 0u8,
-            VoteOption::Nay => 1u8,
-            VoteOption::Abstain => 2u8,
-        };
+        let val: u8 = self.clone().into();
 
         val.write_redis_args(out);
     }
@@ -166,12 +522,27 @@ impl FromRedisValue for Vote {
 
         let address = Address::from_slice(&args[1..21]);
 
-        let fip = u32::from_be_bytes(args[21..25].try_into().unwrap());
+        let fip = match FipNumber::try_from(u32::from_be_bytes(args[21..25].try_into().unwrap())) {
+            Ok(fip) => fip,
+            Err(_) => {
+                return Err(redis::RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Invalid vote FIP number",
+                )))
+            }
+        };
 
         Ok(Vote {
             choice,
             address,
             fip,
+            recorded_at: 0,
+            message: String::new(),
+            signature: String::new(),
+            typed_chain_id: None,
+            storage_providers: BTreeMap::new(),
+            fil: 0,
+            class: VoterClass::StorageProvider,
         })
     }
 }
@@ -183,7 +554,7 @@ impl ToRedisArgs for Vote {
     {
         let mut args = Vec::with_capacity(25);
         let choice: u8 = self.choice.clone().into();
-        let fip = self.fip.to_be_bytes().to_vec();
+        let fip = self.fip.get().to_be_bytes().to_vec();
         let addr = self.address.as_fixed_bytes().to_vec();
 
         args.push(choice);
@@ -204,6 +575,8 @@ impl std::fmt::Display for Vote {
             VoteOption::Yay => "Yay",
             VoteOption::Nay => "Nay",
             VoteOption::Abstain => "Abstain",
+            VoteOption::Unknown(_) => "Unknown",
+            VoteOption::Named(ref name) => name.as_str(),
         };
         write!(f, "{} voted {} on FIP-{}", self.address, vote, self.fip)
     }
@@ -215,6 +588,21 @@ impl PartialEq for Vote {
     }
 }
 
+/// The message a voter signs to cast `choice` on the FIP, such as `YAY: FIP-123`
+///
+/// Returns `None` for options this version can't cast
+pub fn vote_message(choice: &VoteOption, fip_number: FipNumber) -> Option<String> {
+    let label = match choice {
+        VoteOption::Yay => "YAY",
+        VoteOption::Nay => "NAY",
+        VoteOption::Abstain => "ABSTAIN",
+        VoteOption::Named(name) => name,
+        VoteOption::Unknown(_) => return None,
+    };
+
+    Some(format!("{}: FIP-{}", label, fip_number))
+}
+
 pub mod test_votes {
     use super::*;
 
@@ -319,18 +707,82 @@ pub mod test_votes {
             VoteOption::Yay => yay(num),
             VoteOption::Nay => nay(num),
             VoteOption::Abstain => abstain(num),
+            VoteOption::Unknown(_) | VoteOption::Named(_) => {
+                panic!("No signed fixtures for unknown or named options")
+            }
         }
     }
+
+    /// The body `POST /filecoin/vote` takes for the signed fixture
+    pub fn test_vote_body(choice: VoteOption, num: u32) -> serde_json::Value {
+        let vote = test_vote(choice, num);
+        serde_json::json!({ "signature": vote.signature, "message": vote.message })
+    }
 }
 
 #[cfg(test)]
 mod votes_test {
+    use std::str::FromStr;
+
+    use ethers::signers::LocalWallet;
     use redis::Value;
 
     use super::test_votes::test_vote;
 
     use super::*;
 
+    #[test]
+    fn votes_vote_message() {
+        let fip = FipNumber::try_from(123u32).unwrap();
+        let options = ["Option A".to_string(), "Option B".to_string()];
+
+        for (choice, options) in [
+            (VoteOption::Yay, &[][..]),
+            (VoteOption::Nay, &[][..]),
+            (VoteOption::Abstain, &[][..]),
+            (VoteOption::Named("Option B".to_string()), &options[..]),
+        ] {
+            let vote = ReceivedVote {
+                message: vote_message(&choice, fip).unwrap(),
+                ..Default::default()
+            };
+
+            assert_eq!(vote.msg_details(options).unwrap(), (choice, fip));
+        }
+
+        assert_eq!(vote_message(&VoteOption::Yay, fip).unwrap(), "YAY: FIP-123");
+        assert_eq!(vote_message(&VoteOption::Unknown(7), fip), None);
+    }
+
+    #[test]
+    fn votes_receipt_id() {
+        let vote = test_vote(VoteOption::Yay, 1u32);
+        let mut prefixed = test_vote(VoteOption::Yay, 1u32);
+        prefixed.signature = prefixed.signature.trim_start_matches("0x").to_uppercase();
+
+        let id = vote.receipt_id().unwrap();
+
+        assert_eq!(id.len(), 64);
+        assert_eq!(id, prefixed.receipt_id().unwrap());
+        assert_ne!(id, test_vote(VoteOption::Nay, 1u32).receipt_id().unwrap());
+    }
+
+    #[test]
+    fn votes_signature_variants() {
+        let vote = test_vote(VoteOption::Yay, 1u32);
+        let (rs, v) = vote.signature.split_at(130);
+        let parity = u8::from_str_radix(v, 16).unwrap() - 27;
+
+        // Hardware wallets return v as 0 or 1, some wallets adjust it for the chain id
+        for v in [parity as u64, 37 + parity as u64] {
+            let mut variant = test_vote(VoteOption::Yay, 1u32);
+            variant.signature = format!("{}{:02x}", rs, v);
+
+            assert_eq!(variant.signer().unwrap(), vote.signer().unwrap());
+            assert_eq!(variant.receipt_id().unwrap(), vote.receipt_id().unwrap());
+        }
+    }
+
     #[test]
     fn votes_pub_key() {
         let vote = test_vote(VoteOption::Yay, 1u32);
@@ -339,7 +791,7 @@ mod votes_test {
 
         assert!(res.is_ok());
 
-        let res = vote.msg_details();
+        let res = vote.msg_details(&[]);
 
         assert!(res.is_ok());
     }
@@ -352,14 +804,14 @@ mod votes_test {
             for num in fip_nums.clone() {
                 let vote = test_vote(option.clone(), num);
 
-                let res = vote.msg_details();
+                let res = vote.msg_details(&[]);
 
                 assert!(res.is_ok());
 
                 let (option1, fip) = res.unwrap();
 
                 assert_eq!(option1, option);
-                assert_eq!(fip, num);
+                assert_eq!(fip.get(), num);
             }
         }
     }
@@ -377,7 +829,7 @@ mod votes_test {
 
         assert_eq!(recovered_vote.choice, VoteOption::Yay);
         assert_eq!(recovered_vote.address, real_addr);
-        assert_eq!(recovered_vote.fip, 1u32);
+        assert_eq!(recovered_vote.fip.get(), 1u32);
 
         println!("{:?}", recovered_vote.address);
 
@@ -391,7 +843,7 @@ mod votes_test {
 
         assert_eq!(recovered_vote.choice, VoteOption::Nay);
         assert_eq!(recovered_vote.address, real_addr);
-        assert_eq!(recovered_vote.fip, 1u32);
+        assert_eq!(recovered_vote.fip.get(), 1u32);
 
         let vote = test_vote(VoteOption::Abstain, 1u32);
 
@@ -403,7 +855,7 @@ mod votes_test {
 
         assert_eq!(recovered_vote.choice, VoteOption::Abstain);
         assert_eq!(recovered_vote.address, real_addr);
-        assert_eq!(recovered_vote.fip, 1u32);
+        assert_eq!(recovered_vote.fip.get(), 1u32);
     }
 
     #[tokio::test]
@@ -433,6 +885,259 @@ mod votes_test {
 
         assert_eq!(recovered_vote.choice, VoteOption::Yay);
         assert_eq!(recovered_vote.address, real_addr);
-        assert_eq!(recovered_vote.fip, 1u32);
+        assert_eq!(recovered_vote.fip.get(), 1u32);
+    }
+
+    /// Ballots as written by the derived encoding before `Unknown` existed
+    const LEGACY_VOTES_JSON: &str = r#"[{"choice":"Yay","address":"0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56","fip":1},{"choice":"Abstain","address":"0x3b9705f0ef88ee74b9924e34a5af578d2e24f300","fip":1}]"#;
+
+    #[test]
+    fn votes_legacy_json_fixture() {
+        let votes: Vec<Vote> = serde_json::from_str(LEGACY_VOTES_JSON).unwrap();
+
+        assert_eq!(votes[0].choice, VoteOption::Yay);
+        assert_eq!(votes[1].choice, VoteOption::Abstain);
+
+        // Known options keep their legacy encoding
+        assert_eq!(serde_json::to_string(&votes).unwrap(), LEGACY_VOTES_JSON);
+    }
+
+    #[test]
+    fn votes_legacy_bytes_fixture() {
+        let mut bytes = vec![1u8];
+        bytes.extend_from_slice(
+            Address::from_str("0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56")
+                .unwrap()
+                .as_bytes(),
+        );
+        bytes.extend_from_slice(&1u32.to_be_bytes());
+
+        let vote = Vote::from_redis_value(&redis::Value::Data(bytes)).unwrap();
+
+        assert_eq!(vote.choice, VoteOption::Nay);
+        assert_eq!(vote.fip.get(), 1);
+    }
+
+    #[test]
+    fn votes_audit() {
+        let mut vote = test_vote(VoteOption::Yay, 1u32).vote().unwrap();
+        vote.record(
+            1700000000,
+            VoterClass::StorageProvider,
+            BTreeMap::from([(6024, 2048), (6025, 1024)]),
+            0,
+        );
+
+        let audit = vote.audit(false);
+
+        assert_eq!(audit.voter, "0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56");
+        assert_eq!(audit.recorded_at, 1700000000);
+        assert_eq!(audit.message.as_deref(), Some("YAY: FIP-1"));
+        assert_eq!(audit.storage_size, 3072);
+
+        // The record survives a round trip through the database encoding
+        let json = serde_json::to_string(&vote).unwrap();
+        let stored: Vote = serde_json::from_str(&json).unwrap();
+        assert_eq!(stored.storage_providers, vote.storage_providers);
+
+        let anonymous = vote.audit(true);
+
+        assert_eq!(anonymous.voter.len(), 64);
+        assert!(anonymous.signature.is_none());
+        assert!(anonymous.storage_providers.is_empty());
+        assert_eq!(anonymous.storage_size, 3072);
+    }
+
+    #[test]
+    fn votes_weight() {
+        let mut vote = test_vote(VoteOption::Nay, 1u32).vote().unwrap();
+        vote.record(
+            1700000000,
+            VoterClass::StorageProvider,
+            BTreeMap::from([(6024, 2048), (6025, 1024)]),
+            0,
+        );
+
+        let weight = vote.weight(Network::Mainnet, 55);
+
+        assert_eq!(weight.voter, "0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56");
+        assert_eq!(weight.choice, VoteOption::Nay);
+        assert_eq!(weight.sp_ids, vec!["f06024", "f06025"]);
+        assert_eq!(weight.power, 3072);
+        assert_eq!(weight.weighted_power, 55);
+    }
+
+    #[test]
+    fn votes_unknown_option() {
+        let json = r#"{"choice":7,"address":"0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56","fip":1}"#;
+        let vote: Vote = serde_json::from_str(json).unwrap();
+
+        assert_eq!(vote.choice, VoteOption::Unknown(7));
+        assert_eq!(serde_json::to_string(&vote).unwrap(), json);
+
+        assert_eq!(VoteOption::from(7u8), VoteOption::Unknown(7));
+        assert_eq!(u8::from(VoteOption::Unknown(7)), 7);
+
+        let res = VoteOption::from_redis_value(&redis::Value::Int(7));
+
+        assert_eq!(res.unwrap(), VoteOption::Unknown(7));
+
+        let res = serde_json::from_str::<VoteOption>(r#""Maybe""#);
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn votes_named_option() {
+        let options = vec!["Approve".to_string(), "Need more discussion".to_string()];
+        let vote = |message: &str| ReceivedVote {
+            signature: String::new(),
+            message: message.to_string(),
+            typed_data: None,
+        };
+
+        let (choice, fip) = vote("NEED MORE DISCUSSION: FIP-12")
+            .msg_details(&options)
+            .unwrap();
+
+        assert_eq!(
+            choice,
+            VoteOption::Named("Need more discussion".to_string())
+        );
+        assert_eq!(fip.get(), 12);
+
+        // Only the options of the ballot are accepted once it has any
+        for message in ["YAY: FIP-12", "Reject: FIP-12", "Approve FIP-12"] {
+            assert!(matches!(
+                vote(message).msg_details(&options),
+                Err(VoteError::InvalidVoteOption)
+            ));
+        }
+        assert!(matches!(
+            vote("APPROVE: FIP-12").msg_details(&[]),
+            Err(VoteError::InvalidVoteOption)
+        ));
+
+        let json = serde_json::to_string(&choice).unwrap();
+
+        assert_eq!(json, r#"{"Named":"Need more discussion"}"#);
+        assert_eq!(serde_json::from_str::<VoteOption>(&json).unwrap(), choice);
+    }
+
+    #[test]
+    fn votes_freshness() {
+        let nonce = "ab".repeat(16);
+        let vote = |message: String| ReceivedVote {
+            signature: String::new(),
+            message,
+            typed_data: None,
+        };
+
+        let signed = vote(format!("NAY: FIP-7 NONCE-{} EXPIRES-1685000000", nonce));
+        let (choice, fip) = signed.msg_details(&[]).unwrap();
+
+        assert_eq!(choice, VoteOption::Nay);
+        assert_eq!(fip.get(), 7);
+        assert_eq!(signed.freshness().unwrap().unwrap().nonce, [0xab; 16]);
+
+        // The nonce is split off before the option is matched
+        let options = vec!["Approve".to_string()];
+        let signed = vote(format!("APPROVE: FIP-7 NONCE-{} EXPIRES-1", nonce));
+
+        assert_eq!(
+            signed.msg_details(&options).unwrap().0,
+            VoteOption::Named("Approve".to_string())
+        );
+        assert!(test_vote(VoteOption::Yay, 1).freshness().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn votes_votes_on() {
+        let wallet: LocalWallet =
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+                .parse()
+                .unwrap();
+        let message = "YAY: FIP-1, APPROVE: FIP-2, ABSTAIN: FIP-3";
+        let signature = wallet.sign_message(message).await.unwrap();
+        let received = ReceivedVote {
+            signature: format!("0x{}", signature),
+            message: message.to_string(),
+            typed_data: None,
+        };
+        let fip = |num: u32| FipNumber::try_from(num).unwrap();
+
+        assert_eq!(received.fips().unwrap(), vec![fip(1), fip(2), fip(3)]);
+
+        let options = BTreeMap::from([(fip(2), vec!["Approve".to_string()])]);
+        let votes = received.votes_on(&options).unwrap();
+
+        assert_eq!(
+            votes.iter().map(Vote::choice).collect::<Vec<_>>(),
+            vec![
+                VoteOption::Yay,
+                VoteOption::Named("Approve".to_string()),
+                VoteOption::Abstain
+            ]
+        );
+        assert!(votes.iter().all(|vote| vote.address == wallet.address()));
+        assert_eq!(votes[1].fip, fip(2));
+        assert_eq!(votes[1].message, message);
+
+        // Each ballot gets a receipt of its own
+        let ids = [fip(1), fip(2), fip(3)].map(|num| received.batch_receipt_id(num).unwrap());
+
+        assert_ne!(ids[0], ids[1]);
+        assert_ne!(ids[0], received.receipt_id().unwrap());
+
+        // Every choice has to be on the ballot of its vote
+        assert!(matches!(
+            received.votes_on(&BTreeMap::new()),
+            Err(VoteError::InvalidVoteOption)
+        ));
+
+        let received = ReceivedVote {
+            message: "YAY: FIP-1, NAY: FIP-1".to_string(),
+            ..received
+        };
+        assert!(matches!(
+            received.fips(),
+            Err(VoteError::DuplicateFip(num)) if num == fip(1)
+        ));
+    }
+
+    #[test]
+    fn votes_typed_data() {
+        let wallet: LocalWallet =
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+                .parse()
+                .unwrap();
+        let typed = TypedVote {
+            chain_id: 314,
+            choice: "NAY".to_string(),
+            fip: 3,
+            nonce: String::new(),
+            expires: 0,
+        };
+        let signature = wallet
+            .sign_hash(H256::from(typed_data::digest(&typed)))
+            .unwrap();
+
+        let received = ReceivedVote {
+            signature: format!("0x{}", signature),
+            message: String::new(),
+            typed_data: Some(typed),
+        };
+        let vote = received.vote().unwrap();
+
+        assert_eq!(vote.choice, VoteOption::Nay);
+        assert_eq!(vote.fip.get(), 3);
+        assert_eq!(vote.address, wallet.address());
+        assert_eq!(received.typed_chain_id(), Some(314));
+
+        // The audit names the plain message the typed data stands for
+        let audit = vote.audit(false);
+
+        assert_eq!(audit.message.as_deref(), Some("NAY: FIP-3"));
+        assert_eq!(audit.typed_chain_id, Some(314));
     }
 }
@@ -0,0 +1,203 @@
+use std::fmt::Write;
+
+use actix_web::{web, HttpResponse, Responder};
+
+use crate::{
+    errors::*,
+    messages::votes::VoteOption,
+    redis::VoteStatus,
+    storage::Network,
+    store::{StoreFactory, VoteStore},
+    Args,
+};
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+const CHOICES: [(VoteOption, &str); 3] = [
+    (VoteOption::Yay, "yay"),
+    (VoteOption::Nay, "nay"),
+    (VoteOption::Abstain, "abstain"),
+];
+
+/// Live gauges for a single active vote
+struct ActiveVote {
+    network: &'static str,
+    fip: u32,
+    time_remaining: u64,
+    ballots: [u64; 3],
+    weight: [u128; 3],
+}
+
+/// Registers the metrics endpoint, reading from the stores `F` opens
+pub fn routes<F: StoreFactory>(cfg: &mut web::ServiceConfig) {
+    cfg.route("/metrics", web::get().to(get_metrics::<F>));
+}
+
+/// Exports per-vote gauges in the OpenMetrics text format
+///
+/// Only votes that are still in progress are reported, labeled by network and FIP
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "meta",
+    responses(
+        (status = 200, description = "Prometheus metrics of the votes in progress", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_metrics<F: StoreFactory>(
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    let mut active = Vec::new();
+    for ntw in Network::all() {
+        let votes = match redis.active_votes(ntw, config.vote_length()) {
+            Ok(votes) => votes,
+            Err(e) => {
+                let res = format!("{}: {}", ACTIVE_VOTES_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::InternalServerError().body(res);
+            }
+        };
+
+        for fip in votes {
+            let time_remaining = match redis.vote_status(fip, config.vote_length(), ntw) {
+                Ok(VoteStatus::InProgress(time_left)) => time_left,
+                // The vote concluded between the two lookups
+                Ok(_) => continue,
+                Err(e) => {
+                    let res = format!("{}: {}", VOTE_STATUS_ERROR, e);
+                    println!("{}", res);
+                    return HttpResponse::InternalServerError().body(res);
+                }
+            };
+
+            let results = match redis.vote_results(fip, config.vote_length(), ntw).await {
+                Ok(results) => results,
+                Err(e) => {
+                    let res = format!("{}: {}", VOTE_RESULTS_ERROR, e);
+                    println!("{}", res);
+                    return HttpResponse::InternalServerError().body(res);
+                }
+            };
+
+            active.push(ActiveVote {
+                network: ntw.name(),
+                fip: fip.get(),
+                time_remaining,
+                ballots: CHOICES.map(|(choice, _)| results.ballots(&choice)),
+                weight: CHOICES.map(|(choice, _)| results.storage_size(&choice)),
+            });
+        }
+    }
+
+    HttpResponse::Ok()
+        .content_type(CONTENT_TYPE)
+        .body(render(&active))
+}
+
+fn render(active: &[ActiveVote]) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "# TYPE fip_vote_weight_bytes gauge").unwrap();
+    writeln!(out, "# UNIT fip_vote_weight_bytes bytes").unwrap();
+    writeln!(
+        out,
+        "# HELP fip_vote_weight_bytes Storage power behind each choice of an active vote"
+    )
+    .unwrap();
+    for vote in active {
+        for (i, (_, choice)) in CHOICES.iter().enumerate() {
+            writeln!(
+                out,
+                "fip_vote_weight_bytes{{network=\"{}\",fip=\"{}\",choice=\"{}\"}} {}",
+                vote.network, vote.fip, choice, vote.weight[i]
+            )
+            .unwrap();
+        }
+    }
+
+    writeln!(out, "# TYPE fip_vote_ballots gauge").unwrap();
+    writeln!(
+        out,
+        "# HELP fip_vote_ballots Ballots cast for each choice of an active vote"
+    )
+    .unwrap();
+    for vote in active {
+        for (i, (_, choice)) in CHOICES.iter().enumerate() {
+            writeln!(
+                out,
+                "fip_vote_ballots{{network=\"{}\",fip=\"{}\",choice=\"{}\"}} {}",
+                vote.network, vote.fip, choice, vote.ballots[i]
+            )
+            .unwrap();
+        }
+    }
+
+    writeln!(out, "# TYPE fip_vote_time_remaining_seconds gauge").unwrap();
+    writeln!(out, "# UNIT fip_vote_time_remaining_seconds seconds").unwrap();
+    writeln!(
+        out,
+        "# HELP fip_vote_time_remaining_seconds Seconds until an active vote concludes"
+    )
+    .unwrap();
+    for vote in active {
+        writeln!(
+            out,
+            "fip_vote_time_remaining_seconds{{network=\"{}\",fip=\"{}\"}} {}",
+            vote.network, vote.fip, vote.time_remaining
+        )
+        .unwrap();
+    }
+
+    writeln!(out, "# EOF").unwrap();
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics_render() {
+        let active = vec![ActiveVote {
+            network: "calibration",
+            fip: 5,
+            time_remaining: 42,
+            ballots: [1, 0, 2],
+            weight: [10240000, 0, 34359738368],
+        }];
+
+        let out = render(&active);
+
+        assert!(out.contains(
+            "fip_vote_weight_bytes{network=\"calibration\",fip=\"5\",choice=\"yay\"} 10240000"
+        ));
+        assert!(out
+            .contains("fip_vote_ballots{network=\"calibration\",fip=\"5\",choice=\"abstain\"} 2"));
+        assert!(
+            out.contains("fip_vote_time_remaining_seconds{network=\"calibration\",fip=\"5\"} 42")
+        );
+        assert!(out.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn metrics_render_empty() {
+        let out = render(&[]);
+
+        assert!(out.contains("# TYPE fip_vote_ballots gauge"));
+        assert!(!out.contains("fip=\""));
+        assert!(out.ends_with("# EOF\n"));
+    }
+}
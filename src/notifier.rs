@@ -0,0 +1,608 @@
+//! Announces votes in Slack and Discord channels
+//!
+//! Posts to incoming webhooks when a vote starts, with its deadline, and again
+//! when it concludes, with the final tallies. Reminders listing the voters who
+//! haven't voted yet can be sent at set times before the deadline. Each
+//! announcement is recorded in the database once sent, so restarts and several
+//! instances don't repeat it
+use std::{sync::Arc, time::Duration};
+
+use ethers::types::Address;
+use redis::RedisError;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+use url::Url;
+
+use crate::{
+    clock::{Clock, SystemClock},
+    fip::FipNumber,
+    messages::{vote_metadata::select_language, votes::VoteOption},
+    redis::{Redis, VoteOutcome, VoteResults, VoteStatus},
+    storage::Network,
+    store::{StoreError, VoteStore},
+    Args,
+};
+
+/// How often votes are checked for announcements to send
+const NOTIFY_INTERVAL: Duration = Duration::from_secs(60);
+/// Most voters a reminder lists by address, Discord messages are at most 2000 characters
+const MAX_REMINDED_VOTERS: usize = 20;
+
+#[derive(Debug, Error)]
+pub enum NotifyError {
+    #[error("Redis error: {0}")]
+    Redis(#[from] RedisError),
+    #[error("Store error: {0}")]
+    Store(#[from] StoreError),
+    #[error("Error posting to webhook: {0}")]
+    Reqwest(#[from] reqwest::Error),
+}
+
+/// What a vote was announced for, recorded once the announcement was sent
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Announcement {
+    Start,
+    Conclusion,
+    /// Sent this many seconds before the deadline
+    Reminder(u64),
+}
+
+impl Announcement {
+    pub fn name(&self) -> String {
+        match self {
+            Announcement::Start => "start".to_string(),
+            Announcement::Conclusion => "conclusion".to_string(),
+            Announcement::Reminder(before) => format!("reminder:{}", before),
+        }
+    }
+}
+
+/// An incoming webhook announcements are posted to
+#[derive(Debug, Clone)]
+pub enum Webhook {
+    Slack(Url),
+    Discord(Url),
+}
+
+impl Webhook {
+    fn url(&self) -> &Url {
+        match self {
+            Webhook::Slack(url) | Webhook::Discord(url) => url,
+        }
+    }
+
+    /// Json body of the webhook request announcing the notice
+    pub fn body(&self, notice: &Notice) -> Value {
+        match self {
+            Webhook::Slack(_) => json!({ "text": notice.text(self) }),
+            // Titles come from vote starters and the FIPs repository, never ping anyone
+            Webhook::Discord(_) => json!({
+                "content": notice.text(self),
+                "allowed_mentions": { "parse": [] },
+            }),
+        }
+    }
+
+    fn bold(&self, text: &str) -> String {
+        match self {
+            Webhook::Slack(_) => format!("*{}*", text),
+            Webhook::Discord(_) => format!("**{}**", text),
+        }
+    }
+
+    /// A timestamp both apps show in the reader's timezone
+    fn date(&self, timestamp: u64) -> String {
+        match self {
+            Webhook::Slack(_) => format!(
+                "<!date^{}^{{date_short_pretty}} at {{time}}|{} UTC>",
+                timestamp,
+                utc_date(timestamp)
+            ),
+            Webhook::Discord(_) => format!("<t:{}:F>", timestamp),
+        }
+    }
+}
+
+/// A vote event worth announcing
+#[derive(Debug, Clone)]
+pub enum Notice {
+    Started {
+        fip_number: FipNumber,
+        ntw: Network,
+        title: Option<String>,
+        /// Timestamp the vote concludes at
+        deadline: u64,
+    },
+    Concluded {
+        fip_number: FipNumber,
+        ntw: Network,
+        title: Option<String>,
+        results: VoteResults,
+        /// Named options of the vote, empty for a Yay, Nay or Abstain vote
+        options: Vec<String>,
+    },
+    Reminder {
+        fip_number: FipNumber,
+        ntw: Network,
+        title: Option<String>,
+        /// Timestamp the vote concludes at
+        deadline: u64,
+        /// Registered voters who have not voted yet
+        voters: Vec<Address>,
+    },
+}
+
+impl Notice {
+    /// The message in the markup of the webhook's app
+    pub fn text(&self, webhook: &Webhook) -> String {
+        match self {
+            Notice::Started {
+                fip_number,
+                ntw,
+                title,
+                deadline,
+            } => format!(
+                "Voting on {} has started on {}\nVoting closes {}",
+                heading(webhook, *fip_number, title),
+                ntw.name(),
+                webhook.date(*deadline)
+            ),
+            Notice::Concluded {
+                fip_number,
+                ntw,
+                title,
+                results,
+                options,
+            } => {
+                let mut text = format!(
+                    "Voting on {} has concluded on {}",
+                    heading(webhook, *fip_number, title),
+                    ntw.name()
+                );
+                if let Some(outcome) = results.outcome() {
+                    text.push_str(&format!(": {}", webhook.bold(outcome_text(outcome))));
+                }
+
+                let choices = match options.is_empty() {
+                    true => vec![
+                        ("Yay".to_string(), VoteOption::Yay),
+                        ("Nay".to_string(), VoteOption::Nay),
+                        ("Abstain".to_string(), VoteOption::Abstain),
+                    ],
+                    false => options
+                        .iter()
+                        .map(|name| (name.clone(), VoteOption::Named(name.clone())))
+                        .collect(),
+                };
+                for (name, choice) in choices {
+                    text.push_str(&format!(
+                        "\n{}: {} ballots, {}",
+                        name,
+                        results.ballots(&choice),
+                        format_bytes(results.storage_size(&choice))
+                    ));
+                }
+
+                text
+            }
+            Notice::Reminder {
+                fip_number,
+                ntw,
+                title,
+                deadline,
+                voters,
+            } => {
+                let mut text = format!(
+                    "Voting on {} closes {} on {}\n{} registered voters have not voted yet",
+                    heading(webhook, *fip_number, title),
+                    webhook.date(*deadline),
+                    ntw.name(),
+                    voters.len()
+                );
+
+                let listed: Vec<String> = voters
+                    .iter()
+                    .take(MAX_REMINDED_VOTERS)
+                    .map(|voter| format!("{:?}", voter))
+                    .collect();
+                text.push_str(&format!(": {}", listed.join(", ")));
+                if voters.len() > MAX_REMINDED_VOTERS {
+                    text.push_str(&format!(" and {} more", voters.len() - MAX_REMINDED_VOTERS));
+                }
+
+                text
+            }
+        }
+    }
+}
+
+/// The reminder due for a vote with `time_left` seconds to go, the last one
+/// whose time has come
+///
+/// Reminders that were due earlier are skipped rather than sent all at once,
+/// such as for votes shorter than the earliest reminder
+pub fn due_reminder(reminders: &[u64], time_left: u64) -> Option<u64> {
+    reminders
+        .iter()
+        .copied()
+        .filter(|before| time_left <= *before)
+        .min()
+}
+
+/// `FIP-0036` followed by the title when there is one
+fn heading(webhook: &Webhook, fip_number: FipNumber, title: &Option<String>) -> String {
+    let fip = webhook.bold(&format!("FIP-{:04}", fip_number.get()));
+
+    match title {
+        Some(title) => format!("{} ({})", fip, title),
+        None => fip,
+    }
+}
+
+fn outcome_text(outcome: VoteOutcome) -> &'static str {
+    match outcome {
+        VoteOutcome::Passed => "Passed",
+        VoteOutcome::Failed => "Failed",
+        VoteOutcome::QuorumNotMet => "Quorum not met",
+    }
+}
+
+/// Storage power in binary units such as `1.50 PiB`
+fn format_bytes(bytes: u128) -> String {
+    const UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+    let mut unit = 0;
+    let mut value = bytes as f64;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{} B", bytes),
+        _ => format!("{:.2} {}", value, UNITS[unit]),
+    }
+}
+
+/// `2023-11-14 22:13` for clients that can't render a localized date
+fn utc_date(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let (hour, minute) = (timestamp % 86400 / 3600, timestamp % 3600 / 60);
+
+    // Days since the epoch to a civil date, from Howard Hinnant's date algorithms
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{}-{:02}-{:02} {:02}:{:02}", year, month, day, hour, minute)
+}
+
+/// Sends the announcements of every vote to the configured webhooks
+pub struct Notifier {
+    client: Client,
+    webhooks: Vec<Webhook>,
+    redis_path: Url,
+    vote_length: u64,
+    grace_period: u64,
+    /// Seconds before the deadline of each vote reminders are sent at
+    reminders: Vec<u64>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Notifier {
+    /// Returns `None` unless a Slack or Discord webhook is configured
+    pub fn from_args(args: &Args) -> Option<Self> {
+        let webhooks: Vec<Webhook> = args
+            .slack_webhook()
+            .map(Webhook::Slack)
+            .into_iter()
+            .chain(args.discord_webhook().map(Webhook::Discord))
+            .collect();
+        if webhooks.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            client: Client::new(),
+            webhooks,
+            redis_path: args.redis_path(),
+            vote_length: args.vote_length(),
+            grace_period: args.vote_grace_period(),
+            reminders: args.vote_reminders(),
+            clock: Arc::new(SystemClock),
+        })
+    }
+
+    /// Times votes with `clock` instead of the system time
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Checks for votes to announce every minute until the server stops
+    pub fn spawn(self) {
+        actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(NOTIFY_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.notify_votes().await {
+                    println!("Error announcing votes: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Announces every vote that started or concluded since the last run, and
+    /// the reminders that came due
+    ///
+    /// Only votes whose start was announced are reminded of and announced
+    /// again once they conclude, so enabling the notifier doesn't announce
+    /// every past vote
+    pub async fn notify_votes(&self) -> Result<(), NotifyError> {
+        let mut redis = Redis::new(self.redis_path.clone())?;
+        redis.set_clock(self.clock.clone());
+        redis.set_grace_period(self.grace_period);
+
+        for ntw in Network::all() {
+            for fip_number in redis.active_votes(ntw, self.vote_length)? {
+                if redis.announced(fip_number, ntw, Announcement::Start)? {
+                    continue;
+                }
+                let time_left = match redis.vote_status(fip_number, self.vote_length, ntw)? {
+                    VoteStatus::InProgress(time_left) => time_left,
+                    _ => continue,
+                };
+
+                let notice = Notice::Started {
+                    fip_number,
+                    ntw,
+                    title: title(&mut redis, fip_number, ntw)?,
+                    deadline: self.clock.now() + time_left,
+                };
+                self.announce(&mut redis, &notice, Announcement::Start)
+                    .await?;
+            }
+
+            for fip_number in redis.active_votes(ntw, self.vote_length)? {
+                let time_left = match redis.vote_status(fip_number, self.vote_length, ntw)? {
+                    VoteStatus::InProgress(time_left) => time_left,
+                    _ => continue,
+                };
+                let before = match due_reminder(&self.reminders, time_left) {
+                    Some(before) => before,
+                    None => continue,
+                };
+                let reminder = Announcement::Reminder(before);
+                if !redis.announced(fip_number, ntw, Announcement::Start)?
+                    || redis.announced(fip_number, ntw, reminder)?
+                {
+                    continue;
+                }
+
+                // Everyone voted, there is nobody to remind
+                let voters = redis.yet_to_vote(fip_number, ntw)?;
+                if voters.is_empty() {
+                    continue;
+                }
+
+                let notice = Notice::Reminder {
+                    fip_number,
+                    ntw,
+                    title: title(&mut redis, fip_number, ntw)?,
+                    deadline: self.clock.now() + time_left,
+                    voters,
+                };
+                self.announce(&mut redis, &notice, reminder).await?;
+            }
+
+            for fip_number in redis.concluded_votes(ntw, self.vote_length)? {
+                if !redis.announced(fip_number, ntw, Announcement::Start)?
+                    || redis.announced(fip_number, ntw, Announcement::Conclusion)?
+                {
+                    continue;
+                }
+
+                let notice = Notice::Concluded {
+                    fip_number,
+                    ntw,
+                    title: title(&mut redis, fip_number, ntw)?,
+                    results: redis
+                        .vote_results(fip_number, self.vote_length, ntw)
+                        .await?,
+                    options: redis.vote_options(fip_number, ntw)?,
+                };
+                self.announce(&mut redis, &notice, Announcement::Conclusion)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Posts the notice to every webhook, recording it as sent if any accepted it
+    ///
+    /// Nothing is recorded when every webhook failed, so it is sent again on the next run
+    async fn announce(
+        &self,
+        redis: &mut impl VoteStore,
+        notice: &Notice,
+        announcement: Announcement,
+    ) -> Result<(), NotifyError> {
+        let (fip_number, ntw) = match notice {
+            Notice::Started {
+                fip_number, ntw, ..
+            }
+            | Notice::Concluded {
+                fip_number, ntw, ..
+            }
+            | Notice::Reminder {
+                fip_number, ntw, ..
+            } => (*fip_number, *ntw),
+        };
+
+        let mut sent = false;
+        for webhook in &self.webhooks {
+            match self.post(webhook, notice).await {
+                Ok(()) => sent = true,
+                Err(e) => println!(
+                    "Error announcing the {} of FIP-{} on {}: {}",
+                    announcement.name(),
+                    fip_number,
+                    ntw.name(),
+                    e
+                ),
+            }
+        }
+
+        if sent {
+            redis.set_announced(fip_number, ntw, announcement)?;
+        }
+        Ok(())
+    }
+
+    async fn post(&self, webhook: &Webhook, notice: &Notice) -> Result<(), NotifyError> {
+        self.client
+            .post(webhook.url().clone())
+            .json(&webhook.body(notice))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// The title from the FIPs repository, or else the one the vote was started with
+fn title(
+    redis: &mut impl VoteStore,
+    fip_number: FipNumber,
+    ntw: Network,
+) -> Result<Option<String>, StoreError> {
+    if let Some(info) = redis.fip_info(fip_number)? {
+        return Ok(Some(info.title));
+    }
+
+    let available = redis.vote_metadata(fip_number, ntw)?;
+    Ok(select_language("", &available).map(|(_, metadata)| metadata.title))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slack() -> Webhook {
+        Webhook::Slack(Url::parse("https://hooks.slack.com/services/T/B/X").unwrap())
+    }
+
+    fn discord() -> Webhook {
+        Webhook::Discord(Url::parse("https://discord.com/api/webhooks/1/x").unwrap())
+    }
+
+    fn fip(num: u32) -> FipNumber {
+        FipNumber::try_from(num).unwrap()
+    }
+
+    #[test]
+    fn notifier_started() {
+        let notice = Notice::Started {
+            fip_number: fip(36),
+            ntw: Network::Mainnet,
+            title: Some("Sector Duration Multiple".to_string()),
+            deadline: 1700000000,
+        };
+
+        assert_eq!(
+            slack().body(&notice),
+            json!({ "text": "Voting on *FIP-0036* (Sector Duration Multiple) has started on mainnet\nVoting closes <!date^1700000000^{date_short_pretty} at {time}|2023-11-14 22:13 UTC>" })
+        );
+        assert_eq!(
+            discord().body(&notice),
+            json!({
+                "content": "Voting on **FIP-0036** (Sector Duration Multiple) has started on mainnet\nVoting closes <t:1700000000:F>",
+                "allowed_mentions": { "parse": [] },
+            })
+        );
+    }
+
+    #[test]
+    fn notifier_concluded() {
+        let results: VoteResults = serde_json::from_value(json!({
+            "yay": 3,
+            "nay": 1,
+            "abstain": 0,
+            "yay_storage_size": 1688849860263936u128,
+            "nay_storage_size": 1024,
+            "abstain_storage_size": 0,
+            "outcome": "Passed",
+        }))
+        .unwrap();
+        let notice = Notice::Concluded {
+            fip_number: fip(1),
+            ntw: Network::Testnet,
+            title: None,
+            results,
+            options: Vec::new(),
+        };
+
+        assert_eq!(
+            notice.text(&discord()),
+            "Voting on **FIP-0001** has concluded on calibration: **Passed**\nYay: 3 ballots, 1.50 PiB\nNay: 1 ballots, 1.00 KiB\nAbstain: 0 ballots, 0 B"
+        );
+    }
+
+    #[test]
+    fn notifier_reminder() {
+        let voters: Vec<Address> = (1..=22).map(Address::from_low_u64_be).collect();
+        let notice = Notice::Reminder {
+            fip_number: fip(36),
+            ntw: Network::Mainnet,
+            title: None,
+            deadline: 1700000000,
+            voters: voters[..2].to_vec(),
+        };
+
+        assert_eq!(
+            notice.text(&discord()),
+            "Voting on **FIP-0036** closes <t:1700000000:F> on mainnet\n2 registered voters have not voted yet: 0x0000000000000000000000000000000000000001, 0x0000000000000000000000000000000000000002"
+        );
+
+        // Long lists are cut short
+        let notice = Notice::Reminder {
+            fip_number: fip(36),
+            ntw: Network::Mainnet,
+            title: None,
+            deadline: 1700000000,
+            voters,
+        };
+        let text = notice.text(&slack());
+        assert!(text.contains("22 registered voters"));
+        assert!(text.ends_with("0x0000000000000000000000000000000000000014 and 2 more"));
+    }
+
+    #[test]
+    fn notifier_due_reminder() {
+        let reminders = [259200, 86400, 3600];
+
+        assert_eq!(due_reminder(&reminders, 300000), None);
+        assert_eq!(due_reminder(&reminders, 259200), Some(259200));
+        assert_eq!(due_reminder(&reminders, 100000), Some(259200));
+        assert_eq!(due_reminder(&reminders, 86400), Some(86400));
+        assert_eq!(due_reminder(&reminders, 60), Some(3600));
+        assert_eq!(due_reminder(&[], 60), None);
+    }
+
+    #[test]
+    fn notifier_utc_date() {
+        assert_eq!(utc_date(0), "1970-01-01 00:00");
+        assert_eq!(utc_date(951782400), "2000-02-29 00:00");
+        assert_eq!(utc_date(1700000000), "2023-11-14 22:13");
+    }
+}
@@ -0,0 +1,284 @@
+use serde_json::json;
+use utoipa::{
+    openapi::{
+        security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+        ObjectBuilder, OneOfBuilder, RefOr, Schema, SchemaType,
+    },
+    Modify, OpenApi, ToSchema,
+};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::{
+    admin::{self, RegistrationDump},
+    api::types::{
+        ChoiceDto, CohortDto, RegistrationDto, ResultsDto, SilenceDto, StorageProviderDto,
+        TurnoutDto, VoteDto, VotingPowerDto,
+    },
+    attestation::{self, Attestation},
+    fip::FipNumber,
+    fip_registry::FipInfo,
+    get::{self, ActiveVote},
+    limits::{self, ClassQuota, EndpointClass},
+    messages::{
+        acceptance::DelegationAcceptance,
+        auth::VoterAuthorization,
+        class_registration::{ReceivedClassRegistration, VoterClass},
+        delegate_removal::DelegateRemoval,
+        nonce::IssuedNonce,
+        renewal::{ReceivedRenewal, RenewalReceipt},
+        role_grant::RoleGrant,
+        typed_data::{TypedStarterAuthorization, TypedVote, TypedVoteStart},
+        unregistration::ReceivedUnregistration,
+        vote_end::{VoteEnd, VoteEnding},
+        vote_extension::VoteExtension,
+        vote_flush::VoteFlush,
+        vote_metadata::{ReceivedMetadataUpdate, VoteMetadata},
+        vote_registration::{
+            MsigApproval, ReceivedVoterRegistration, RegistrationReceipt, StorageProviderReceipt,
+        },
+        vote_start::{QuorumRules, VoteStart},
+        votes::{BallotAudit, BallotWeight, ReceivedVote, Vote, VoteOption},
+    },
+    metrics, post,
+    redis::{
+        ClassTally, CohortSilence, OptionTally, RegisteredVoter, RoundDelta, SilenceReport,
+        Turnout, VoteOutcome, VoteReceipt, VoteResults, VoterBallot, VoterPage,
+    },
+    registrations::{BulkRegistration, DelegationOffer, RegistrationStatus},
+    roles::{self, Role},
+    sse,
+    storage::SpCohort,
+    tally::Weighting,
+    templates::{self, MessageTemplate, TemplateKind},
+    validate::{self, Check, Problem, VoteVerdict},
+};
+
+/// Specification of every endpoint but the e2e ones, served at `/api-docs/openapi.json`
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get::get_votes,
+        get::get_vote_receipt,
+        sse::get_vote_stream,
+        get::get_vote_audit,
+        get::get_vote_breakdown,
+        attestation::get_vote_attestation,
+        get::get_voting_power,
+        get::get_voting_power_at,
+        get::get_vote_starters,
+        get::get_delegates,
+        get::get_voters,
+        get::get_voter_history,
+        get::get_concluded_votes,
+        get::get_stale_votes,
+        get::get_active_votes,
+        get::get_all_concluded_votes,
+        get::get_vote_stats,
+        get::get_turnout,
+        get::get_vote_metadata,
+        metrics::get_metrics,
+        limits::get_limits,
+        post::issue_nonce,
+        templates::get_message_template,
+        post::register_vote,
+        post::register_vote_batch,
+        validate::validate_vote,
+        post::register_voter,
+        post::register_voter_bulk,
+        get::get_registration_status,
+        get::get_delegation_offers,
+        post::accept_delegation,
+        post::register_voter_class,
+        post::renew_registration,
+        post::unregister_voter,
+        post::unregister_voter_bulk,
+        post::remove_delegate,
+        post::register_vote_starter,
+        post::unregister_vote_starter,
+        post::start_vote,
+        post::end_vote,
+        post::extend_vote,
+        post::update_vote_metadata,
+        roles::get_roles,
+        roles::update_role,
+        roles::flush_vote,
+        admin::delete_vote,
+        admin::conclude_vote,
+        admin::get_raw_votes,
+        admin::get_registrations,
+        admin::restore_registrations,
+    ),
+    components(schemas(
+        AddressSchema,
+        FipNumber,
+        VoteOption,
+        ReceivedVote,
+        TypedVote,
+        VoteVerdict,
+        Problem,
+        Check,
+        IssuedNonce,
+        MessageTemplate,
+        TemplateKind,
+        Vote,
+        BallotAudit,
+        BallotWeight,
+        VoteStart,
+        TypedVoteStart,
+        QuorumRules,
+        Weighting,
+        VoteEnd,
+        VoteEnding,
+        VoteExtension,
+        VoteMetadata,
+        ReceivedMetadataUpdate,
+        VoterAuthorization,
+        TypedStarterAuthorization,
+        Role,
+        RoleGrant,
+        VoteFlush,
+        VoterClass,
+        ReceivedClassRegistration,
+        ReceivedRenewal,
+        RenewalReceipt,
+        ReceivedUnregistration,
+        DelegateRemoval,
+        ReceivedVoterRegistration,
+        MsigApproval,
+        RegistrationReceipt,
+        StorageProviderReceipt,
+        RegistrationStatus,
+        BulkRegistration,
+        DelegationOffer,
+        DelegationAcceptance,
+        VoteResults,
+        FipInfo,
+        ActiveVote,
+        VoteOutcome,
+        RoundDelta,
+        ClassTally,
+        OptionTally,
+        VoteReceipt,
+        VoterPage,
+        RegisteredVoter,
+        VoterBallot,
+        Turnout,
+        SilenceReport,
+        CohortSilence,
+        SpCohort,
+        Attestation,
+        RegistrationDump,
+        EndpointClass,
+        ClassQuota,
+        VoteDto,
+        StorageProviderDto,
+        ResultsDto,
+        ChoiceDto,
+        RegistrationDto,
+        VotingPowerDto,
+        TurnoutDto,
+        SilenceDto,
+        CohortDto,
+    )),
+    modifiers(&AdminToken),
+    tags(
+        (name = "votes", description = "Casting ballots and reading results"),
+        (name = "starters", description = "Starting and managing votes, signed by a vote starter"),
+        (name = "roles", description = "Admins granting roles and flushing votes, signed by an admin"),
+        (name = "voters", description = "Voter registration and voting power"),
+        (name = "admin", description = "Operator endpoints, disabled unless an admin token is configured"),
+        (name = "meta", description = "Metrics and rate limits of the backend"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Swagger UI at `/swagger-ui/`, reading the specification from `/api-docs/openapi.json`
+pub fn swagger_ui() -> SwaggerUi {
+    SwaggerUi::new("/swagger-ui/{_:.*}").url("/api-docs/openapi.json", ApiDoc::openapi())
+}
+
+/// Adds the bearer token the admin endpoints are authorized with
+struct AdminToken;
+
+impl Modify for AdminToken {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "admin_token",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+        }
+    }
+}
+
+/// Ethereum addresses are serialized as 0x prefixed hex strings
+struct AddressSchema;
+
+impl<'s> ToSchema<'s> for AddressSchema {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        (
+            "Address",
+            ObjectBuilder::new()
+                .schema_type(SchemaType::String)
+                .example(Some(json!("0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56")))
+                .into(),
+        )
+    }
+}
+
+impl<'s> ToSchema<'s> for FipNumber {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        (
+            "FipNumber",
+            ObjectBuilder::new()
+                .schema_type(SchemaType::Integer)
+                .minimum(Some(1.0))
+                .example(Some(json!(42)))
+                .into(),
+        )
+    }
+}
+
+impl<'s> ToSchema<'s> for VoteOption {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        (
+            "VoteOption",
+            OneOfBuilder::new()
+                .item(
+                    ObjectBuilder::new()
+                        .schema_type(SchemaType::String)
+                        .enum_values(Some(["Yay", "Nay", "Abstain"])),
+                )
+                .item(
+                    ObjectBuilder::new()
+                        .property(
+                            "Named",
+                            ObjectBuilder::new().schema_type(SchemaType::String),
+                        )
+                        .required("Named"),
+                )
+                .into(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn openapi_documents_every_endpoint() {
+        let doc = ApiDoc::openapi();
+
+        assert!(doc.paths.paths.contains_key("/filecoin/vote"));
+        assert!(doc.paths.paths.contains_key("/filecoin/vote/receipt/{id}"));
+        assert!(doc.paths.paths.contains_key("/admin/registrations"));
+        assert_eq!(doc.paths.paths.len(), 46);
+
+        let components = doc.components.unwrap();
+        assert!(components.schemas.contains_key("Address"));
+        assert!(components.schemas.contains_key("VoteResults"));
+        assert!(components.schemas.contains_key("ResultsDto"));
+        assert!(components.security_schemes.contains_key("admin_token"));
+    }
+}
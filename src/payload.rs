@@ -0,0 +1,115 @@
+use std::{future::Future, rc::Rc};
+
+use actix_web::{
+    dev::{Extensions, Service, ServiceRequest, ServiceResponse},
+    error::{InternalError, JsonPayloadError},
+    web, Error, HttpRequest, HttpResponse,
+};
+
+use crate::errors::*;
+
+/// Largest body of a single signed message
+const MESSAGE_LIMIT: usize = 16 * 1024;
+/// Largest body of a registration, which lists every storage provider of the voter
+const REGISTRATION_LIMIT: usize = 64 * 1024;
+/// Largest body of a bulk registration, which holds up to
+/// `MAX_BULK_REGISTRATIONS` registrations
+const BULK_REGISTRATION_LIMIT: usize = 4 * 1024 * 1024;
+/// Largest body of a message carrying the title and description of a vote
+const METADATA_LIMIT: usize = 256 * 1024;
+/// Largest body of a dump of every registration on a network
+const RESTORE_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Largest body and deserialization error of each endpoint taking a json body
+fn route(path: &str) -> (usize, &'static str) {
+    match path {
+        "/filecoin/vote" | "/filecoin/vote/batch" | "/filecoin/vote/validate" => {
+            (MESSAGE_LIMIT, VOTE_DESERIALIZE_ERROR)
+        }
+        "/filecoin/startvote" => (METADATA_LIMIT, VOTE_DESERIALIZE_ERROR),
+        "/filecoin/endvote" => (MESSAGE_LIMIT, VOTE_END_DESERIALIZE_ERROR),
+        "/filecoin/extendvote" => (MESSAGE_LIMIT, VOTE_EXTENSION_DESERIALIZE_ERROR),
+        "/filecoin/votemetadata" => (METADATA_LIMIT, VOTE_METADATA_DESERIALIZE_ERROR),
+        "/filecoin/registerstarter" | "/filecoin/unregisterstarter" => {
+            (MESSAGE_LIMIT, VOTER_AUTH_DESERIALIZE_ERROR)
+        }
+        "/filecoin/register" | "/filecoin/unregister" => {
+            (REGISTRATION_LIMIT, VOTE_DESERIALIZE_ERROR)
+        }
+        "/filecoin/roles" => (MESSAGE_LIMIT, ROLE_GRANT_DESERIALIZE_ERROR),
+        "/filecoin/flushvote" => (MESSAGE_LIMIT, VOTE_FLUSH_DESERIALIZE_ERROR),
+        "/filecoin/registerclass" => (MESSAGE_LIMIT, CLASS_REGISTRATION_DESERIALIZE_ERROR),
+        "/filecoin/renew" => (MESSAGE_LIMIT, RENEWAL_DESERIALIZE_ERROR),
+        "/filecoin/unregister/bulk" => (REGISTRATION_LIMIT, UNREGISTRATION_DESERIALIZE_ERROR),
+        "/filecoin/register/bulk" => (BULK_REGISTRATION_LIMIT, VOTE_DESERIALIZE_ERROR),
+        "/admin/registrations" => (RESTORE_LIMIT, SERDE_ERROR),
+        _ => (MESSAGE_LIMIT, SERDE_ERROR),
+    }
+}
+
+/// How json bodies are read on `path`
+///
+/// Bodies over the limit of the endpoint are refused from their
+/// `Content-Length` before they are read, and bodies without an
+/// `application/json` content type before anything is read
+pub fn json_config(path: &str) -> web::JsonConfig {
+    let (limit, _) = route(path);
+
+    web::JsonConfig::default()
+        .limit(limit)
+        .content_type_required(true)
+        .error_handler(json_error)
+}
+
+/// Answers a body that could not be read with the error of its endpoint
+fn json_error(err: JsonPayloadError, req: &HttpRequest) -> Error {
+    let res = match &err {
+        JsonPayloadError::OverflowKnownLength { .. } | JsonPayloadError::Overflow { .. } => {
+            let res = format!("{}: {}", PAYLOAD_TOO_LARGE_ERROR, err);
+            HttpResponse::PayloadTooLarge().body(res)
+        }
+        JsonPayloadError::ContentType => {
+            HttpResponse::UnsupportedMediaType().body(CONTENT_TYPE_ERROR)
+        }
+        _ => {
+            let (_, message) = route(req.path());
+            HttpResponse::BadRequest().body(format!("{}: {}", message, err))
+        }
+    };
+    println!("{} for {}: {}", res.status(), req.path(), err);
+
+    InternalError::from_response(err, res).into()
+}
+
+/// Middleware that reads the json body of every request with the limit of its endpoint
+pub fn middleware<S>(
+    mut req: ServiceRequest,
+    srv: &S,
+) -> impl Future<Output = Result<ServiceResponse, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = Error>,
+{
+    let mut data = Extensions::new();
+    data.insert(json_config(req.path()));
+    req.add_data_container(Rc::new(data));
+
+    srv.call(req)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_route_limits() {
+        assert_eq!(route("/filecoin/vote").0, MESSAGE_LIMIT);
+        assert_eq!(route("/filecoin/startvote").0, METADATA_LIMIT);
+        assert_eq!(route("/filecoin/unregister/bulk").0, REGISTRATION_LIMIT);
+        assert_eq!(route("/filecoin/register/bulk").0, BULK_REGISTRATION_LIMIT);
+        assert_eq!(route("/admin/registrations").0, RESTORE_LIMIT);
+
+        // Endpoints keep the error they answered malformed bodies with
+        assert_eq!(route("/filecoin/endvote").1, VOTE_END_DESERIALIZE_ERROR);
+        assert_eq!(route("/e2e/power"), (MESSAGE_LIMIT, SERDE_ERROR));
+    }
+}
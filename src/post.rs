@@ -1,49 +1,150 @@
-use actix_web::{post, web, HttpResponse, Responder};
+use std::collections::{BTreeMap, HashMap};
+
+use actix_web::{web, HttpResponse, Responder};
+use ethers::types::Address;
 
 use crate::{
+    authorized_voters,
     errors::*,
+    fip::FipNumber,
+    fip_registry::FipRegistry,
     messages::{
-        auth::VoterAuthorization, vote_registration::ReceivedVoterRegistration,
-        vote_start::VoteStart, votes::ReceivedVote,
+        acceptance::DelegationAcceptance,
+        auth::VoterAuthorization,
+        class_registration::{ReceivedClassRegistration, VoterClass},
+        delegate_removal::{DelegateRemoval, Remover},
+        domain::Domain,
+        nonce::{Freshness, NONCE_TTL},
+        renewal::{is_fresh, ReceivedRenewal, RenewalReceipt},
+        role_grant::RoleChange,
+        unregistration::{ReceivedUnregistration, Unregistration},
+        vote_end::VoteEnd,
+        vote_extension::VoteExtension,
+        vote_metadata::ReceivedMetadataUpdate,
+        vote_registration::ReceivedVoterRegistration,
+        vote_start::VoteStart,
+        votes::{ReceivedVote, VoteError},
     },
-    redis::{Redis, VoteStatus},
-    storage::Network,
+    redis::VoteStatus,
+    registrations::{recover_bulk, BulkRegistration, RegistrationStatus, MAX_BULK_REGISTRATIONS},
+    roles::may_change_starters,
+    storage::{fetch_fil_balance, sp_id_format, Network},
+    store::{StoreError, StoreFactory, VoteStore},
     Args, FipParams, NtwParams,
 };
 
-#[post("/filecoin/vote")]
-async fn register_vote(
-    body: web::Bytes,
+/// Registers the endpoints that sign changes into the stores `F` opens
+pub fn routes<F: StoreFactory>(cfg: &mut web::ServiceConfig) {
+    cfg.route("/filecoin/vote", web::post().to(register_vote::<F>))
+        .route(
+            "/filecoin/vote/batch",
+            web::post().to(register_vote_batch::<F>),
+        )
+        .route("/filecoin/startvote", web::post().to(start_vote::<F>))
+        .route("/filecoin/endvote", web::post().to(end_vote::<F>))
+        .route("/filecoin/extendvote", web::post().to(extend_vote::<F>))
+        .route(
+            "/filecoin/votemetadata",
+            web::post().to(update_vote_metadata::<F>),
+        )
+        .route(
+            "/filecoin/registerstarter",
+            web::post().to(register_vote_starter::<F>),
+        )
+        .route(
+            "/filecoin/unregisterstarter",
+            web::post().to(unregister_vote_starter::<F>),
+        )
+        .route("/filecoin/register", web::post().to(register_voter::<F>))
+        .route(
+            "/filecoin/register/bulk",
+            web::post().to(register_voter_bulk::<F>),
+        )
+        .route(
+            "/filecoin/delegations/accept",
+            web::post().to(accept_delegation::<F>),
+        )
+        .route(
+            "/filecoin/registerclass",
+            web::post().to(register_voter_class::<F>),
+        )
+        .route("/filecoin/nonce", web::post().to(issue_nonce::<F>))
+        .route("/filecoin/renew", web::post().to(renew_registration::<F>))
+        .route(
+            "/filecoin/unregister",
+            web::post().to(unregister_voter::<F>),
+        )
+        .route(
+            "/filecoin/unregister/bulk",
+            web::post().to(unregister_voter_bulk::<F>),
+        )
+        .route(
+            "/filecoin/delegates/remove",
+            web::post().to(remove_delegate::<F>),
+        );
+}
+
+#[utoipa::path(
+    post,
+    path = "/filecoin/vote",
+    tag = "votes",
+    params(FipParams),
+    request_body = ReceivedVote,
+    responses(
+        (status = 200, description = "The ballot was recorded", body = VoteReceipt),
+        (status = 400, description = "Invalid ballot", body = String),
+        (status = 403, description = "The voter is not registered, the registration expired, or the vote has not opened yet", body = String),
+        (status = 404, description = "The vote or the FIP does not exist", body = String),
+        (status = 409, description = "The vote concluded or was cancelled", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn register_vote<F: StoreFactory>(
+    vote: web::Json<ReceivedVote>,
     query_params: web::Query<FipParams>,
     config: web::Data<Args>,
+    store: web::Data<F>,
 ) -> impl Responder {
     let num = query_params.fip_number;
 
-    println!("Vote received for FIP: {}, {:?}", num, body);
-    // Deserialize the body into the vote struct
-    let vote: ReceivedVote = match serde_json::from_slice(&body) {
-        Ok(v) => v,
+    let vote = vote.into_inner();
+
+    // Recover the vote
+    let receipt_id = match vote.receipt_id() {
+        Ok(id) => id,
         Err(e) => {
-            let res = format!("{}: {}", VOTE_DESERIALIZE_ERROR, e);
+            let res = format!("{}: {}", VOTE_RECOVER_ERROR, e);
             println!("{}", res);
             return HttpResponse::BadRequest().body(res);
         }
     };
-
-    // Recover the vote
-    let vote = match vote.vote() {
-        Ok(vote) => vote,
+    let voter = match vote.signer() {
+        Ok(voter) => voter,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    let freshness = match vote.freshness() {
+        Ok(freshness) => freshness,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    let domain = match vote.domain() {
+        Ok(domain) => domain,
         Err(e) => {
             let res = format!("{}: {}", VOTE_RECOVER_ERROR, e);
             println!("{}", res);
             return HttpResponse::BadRequest().body(res);
         }
     };
-
-    let voter = vote.voter();
 
     // Open a connection to the redis database
-    let mut redis = match Redis::new(config.redis_path()) {
+    let mut redis = match store.open() {
         Ok(redis) => redis,
         Err(e) => {
             let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
@@ -52,83 +153,279 @@ async fn register_vote(
         }
     };
 
-    let ntw = match redis.network(voter) {
-        Ok(ntw) => ntw,
+    // A retry of a submission that already landed gets the original receipt
+    match redis.vote_receipt(&receipt_id) {
+        Ok(Some(receipt)) => {
+            println!("Vote receipt {} returned for FIP: {}", receipt.id, num);
+            return HttpResponse::Ok().json(receipt);
+        }
+        Ok(None) => (),
         Err(e) => {
-            let res = format!("{}: {}", VOTER_NOT_REGISTERED_NETWORK, e);
+            let res = format!("{}: {}", VOTE_RECEIPT_ERROR, e);
             println!("{}", res);
             return HttpResponse::InternalServerError().body(res);
         }
-    };
+    }
 
-    let status = match redis.vote_status(num, config.vote_length(), ntw) {
-        Ok(status) => status,
+    if let Err(res) = check_voter(&mut redis, voter) {
+        return res;
+    }
+
+    let (ntw, options) = match check_ballot(
+        &mut redis,
+        voter,
+        num,
+        vote.typed_chain_id(),
+        domain.as_ref(),
+        &config,
+    ) {
+        Ok(ballot) => ballot,
+        Err(res) => return res,
+    };
+    let vote = match vote.vote_on(&options) {
+        Ok(vote) => vote,
         Err(e) => {
-            let res = format!("{}: {}", VOTE_STATUS_ERROR, e);
+            let res = format!("{}: {}", VOTE_RECOVER_ERROR, e);
             println!("{}", res);
-            return HttpResponse::InternalServerError().body(res);
+            return HttpResponse::BadRequest().body(res);
         }
     };
 
-    match status {
-        VoteStatus::InProgress(_) => (),
-        VoteStatus::Concluded => {
-            let resp = format!("Vote concluded for FIP: {}", num);
-            println!("{}", resp);
-            return HttpResponse::Forbidden().body(resp);
-        }
-        VoteStatus::DoesNotExist => (),
+    // Make sure the signed FIP matches the FIP being voted on
+    if vote.fip() != num {
+        let res = format!(
+            "{}: {}",
+            FIP_MISMATCH_ERROR,
+            VoteError::FipMismatch(num, vote.fip())
+        );
+        println!("{}", res);
+        return HttpResponse::BadRequest().body(res);
+    }
+
+    if let Err(res) = check_open(&mut redis, num, ntw, &config) {
+        return res;
+    }
+    if let Err(res) = check_known_fip(&mut redis, num, &config).await {
+        return res;
     }
 
-    let choice = vote.choice();
+    // Uses up the nonce, so only once nothing else can reject the ballot
+    if let Err(res) = check_freshness(&mut redis, freshness, &config) {
+        return res;
+    }
 
     // Add the vote to the database
-    match redis.add_vote(num, vote, voter, config.vote_length()).await {
-        Ok(_) => (),
+    let receipt = match redis
+        .submit_vote(
+            num,
+            vote,
+            voter,
+            config.vote_length(),
+            config.allow_revote(),
+            receipt_id,
+        )
+        .await
+    {
+        Ok(receipt) => receipt,
         Err(e) => {
             let res = format!("{}: {}", VOTE_ADD_ERROR, e);
             println!("{}", res);
             return HttpResponse::InternalServerError().body(res);
         }
-    }
+    };
 
-    println!("Vote ({:?}) added for FIP: {}", choice, num);
+    println!("Vote ({:?}) added for FIP: {}", receipt.choice, num);
 
-    HttpResponse::Ok().finish()
+    HttpResponse::Ok().json(receipt)
 }
 
-#[post("/filecoin/startvote")]
-async fn start_vote(
-    body: web::Bytes,
-    query_params: web::Query<NtwParams>,
+#[utoipa::path(
+    post,
+    path = "/filecoin/vote/batch",
+    tag = "votes",
+    request_body = ReceivedVote,
+    responses(
+        (status = 200, description = "Every ballot was recorded, with a receipt for each in the order they were signed", body = [VoteReceipt]),
+        (status = 400, description = "Invalid combined ballot", body = String),
+        (status = 403, description = "The voter is not registered, the registration expired, or a vote has not opened yet", body = String),
+        (status = 404, description = "A vote or FIP does not exist", body = String),
+        (status = 409, description = "A vote concluded or was cancelled", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn register_vote_batch<F: StoreFactory>(
+    vote: web::Json<ReceivedVote>,
     config: web::Data<Args>,
+    store: web::Data<F>,
 ) -> impl Responder {
-    println!("Vote start received");
+    let vote = vote.into_inner();
+
+    // Recover the FIPs voted on
+    let fips = match vote.fips() {
+        Ok(fips) => fips,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
 
-    let ntw = match query_params.network.as_str() {
-        "mainnet" => Network::Mainnet,
-        "calibration" => Network::Testnet,
-        _ => {
-            let res = format!("{}: {}", INVALID_NETWORK, query_params.network);
+    let receipt_ids = match fips
+        .iter()
+        .map(|num| vote.batch_receipt_id(*num))
+        .collect::<Result<Vec<String>, VoteError>>()
+    {
+        Ok(ids) => ids,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    let voter = match vote.signer() {
+        Ok(voter) => voter,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    let freshness = match vote.freshness() {
+        Ok(freshness) => freshness,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    let domain = match vote.domain() {
+        Ok(domain) => domain,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_RECOVER_ERROR, e);
             println!("{}", res);
             return HttpResponse::BadRequest().body(res);
         }
     };
 
-    // Deserialize the body into the vote start struct
-    let start: VoteStart = match serde_json::from_slice(&body) {
-        Ok(v) => v,
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    // A retry of a submission that already landed gets the original receipts
+    let mut landed = Vec::new();
+    for id in &receipt_ids {
+        match redis.vote_receipt(id) {
+            Ok(receipt) => landed.extend(receipt),
+            Err(e) => {
+                let res = format!("{}: {}", VOTE_RECEIPT_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::InternalServerError().body(res);
+            }
+        }
+    }
+    if landed.len() == receipt_ids.len() {
+        println!("Vote receipts returned for FIPs: {:?}", fips);
+        return HttpResponse::Ok().json(landed);
+    }
+
+    if let Err(res) = check_voter(&mut redis, voter) {
+        return res;
+    }
+
+    // Every FIP is checked before any ballot is recorded
+    let mut networks = Vec::with_capacity(fips.len());
+    let mut options = BTreeMap::new();
+    for &num in &fips {
+        match check_ballot(
+            &mut redis,
+            voter,
+            num,
+            vote.typed_chain_id(),
+            domain.as_ref(),
+            &config,
+        ) {
+            Ok((ntw, fip_options)) => {
+                networks.push(ntw);
+                options.insert(num, fip_options);
+            }
+            Err(res) => return res,
+        }
+    }
+    let votes = match vote.votes_on(&options) {
+        Ok(votes) => votes,
         Err(e) => {
-            let res = format!("{}: {}", VOTE_DESERIALIZE_ERROR, e);
+            let res = format!("{}: {}", VOTE_RECOVER_ERROR, e);
             println!("{}", res);
             return HttpResponse::BadRequest().body(res);
         }
     };
+    for (&num, &ntw) in fips.iter().zip(&networks) {
+        if let Err(res) = check_open(&mut redis, num, ntw, &config) {
+            return res;
+        }
+        if let Err(res) = check_known_fip(&mut redis, num, &config).await {
+            return res;
+        }
+    }
+
+    // Uses up the nonce, so only once nothing else can reject the ballots
+    if let Err(res) = check_freshness(&mut redis, freshness, &config) {
+        return res;
+    }
+
+    let receipts = match redis
+        .add_vote_batch(
+            votes.into_iter().zip(receipt_ids).collect(),
+            voter,
+            config.vote_length(),
+            config.allow_revote(),
+        )
+        .await
+    {
+        Ok(receipts) => receipts,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_ADD_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
 
-    println!("Vote start received for FIP: {}", start.message);
+    println!("Votes added for FIPs: {:?}", fips);
+
+    HttpResponse::Ok().json(receipts)
+}
+
+#[utoipa::path(
+    post,
+    path = "/filecoin/startvote",
+    tag = "starters",
+    params(NtwParams),
+    request_body = VoteStart,
+    responses(
+        (status = 200, description = "The vote started, the body is the vote length in seconds", body = String),
+        (status = 400, description = "Invalid start message, rules or opening time", body = String),
+        (status = 404, description = "The FIP is not in the FIPs repository", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn start_vote<F: StoreFactory>(
+    start: web::Json<VoteStart>,
+    query_params: web::Query<NtwParams>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+
+    let start = start.into_inner();
 
     // Open a connection to the redis database
-    let mut redis = match Redis::new(config.redis_path()) {
+    let mut redis = match store.open() {
         Ok(redis) => redis,
         Err(e) => {
             let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
@@ -145,65 +442,216 @@ async fn start_vote(
             return HttpResponse::BadRequest().body(res);
         }
     };
+    if let Err(res) = check_chain_id(start.typed_chain_id(), ntw) {
+        return res;
+    }
+    let domain = match start.domain() {
+        Ok(domain) => domain,
+        Err(e) => {
+            let res = format!("{}: {}", VOTER_AUTH_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    if let Err(res) = check_domain(
+        domain.as_ref(),
+        start.typed_chain_id(),
+        ntw,
+        &config,
+        redis.now(),
+    ) {
+        return res;
+    }
 
-    match redis.vote_exists(ntw, fip) {
-        Ok(true) => {
-            let res = format!("{}: {}", VOTE_ALREADY_EXISTS, fip);
+    let round = match start.round() {
+        Ok(round) => round,
+        Err(e) => {
+            let res = format!("{}: {}", VOTER_AUTH_ERROR, e);
             println!("{}", res);
-            return HttpResponse::Ok().body(res);
+            return HttpResponse::BadRequest().body(res);
         }
-        Ok(false) => (),
+    };
+
+    let options = match start.options() {
+        Ok(options) => options,
         Err(e) => {
-            let res = format!("{}: {}", VOTE_EXISTS_ERROR, e);
+            let res = format!("{}: {}", VOTE_OPTIONS_INVALID_ERROR, e);
             println!("{}", res);
-            return HttpResponse::InternalServerError().body(res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+
+    let quorum = match start.quorum() {
+        Ok(quorum) => quorum,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_QUORUM_INVALID_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+
+    let weighting = match start.weighting() {
+        Ok(weighting) => weighting,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_WEIGHTING_INVALID_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
         }
+    };
+
+    if let Err(e) = start.check_metadata() {
+        let res = format!("{}: {}", VOTE_METADATA_INVALID_ERROR, e);
+        println!("{}", res);
+        return HttpResponse::BadRequest().body(res);
     }
 
-    match redis.start_vote(fip, starter, ntw) {
-        Ok(_) => (),
+    let opens_at = match start.opens_at(redis.now()) {
+        Ok(opens_at) => opens_at,
         Err(e) => {
-            let res = format!("{}: {}", VOTE_START_ERROR, e);
+            let res = format!("{}: {}", VOTE_OPENS_AT_INVALID_ERROR, e);
             println!("{}", res);
-            return HttpResponse::InternalServerError().body(res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+
+    let freshness = match start.freshness() {
+        Ok(freshness) => freshness,
+        Err(e) => {
+            let res = format!("{}: {}", VOTER_AUTH_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+
+    // Catches typos such as FIP-9999 before a vote is started on them
+    if let Err(res) = check_known_fip(&mut redis, fip, &config).await {
+        return res;
+    }
+    if let Err(res) = check_freshness(&mut redis, freshness, &config) {
+        return res;
+    }
+
+    match round {
+        // Later rounds keep the options, quorum rules, weighting and minimum
+        // power of the first round and open as soon as they start
+        Some(_)
+            if !options.is_empty()
+                || quorum.is_some()
+                || weighting.is_some()
+                || start.min_power.is_some()
+                || opens_at.is_some() =>
+        {
+            let res = format!(
+                "{}: {}",
+                VOTE_OPTIONS_INVALID_ERROR, VOTE_OPTIONS_ROUND_ERROR
+            );
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+        // Later rounds restart a concluded vote instead of creating a new one
+        Some(round) => match redis
+            .start_round(fip, starter, ntw, round, config.vote_length())
+            .await
+        {
+            Ok(_) => (),
+            Err(e) => {
+                let res = format!("{}: {}", VOTE_START_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::BadRequest().body(res);
+            }
+        },
+        None => {
+            match redis.vote_exists(ntw, fip) {
+                Ok(true) => {
+                    let res = format!("{}: {}", VOTE_ALREADY_EXISTS, fip);
+                    println!("{}", res);
+                    return HttpResponse::Ok().body(res);
+                }
+                Ok(false) => (),
+                Err(e) => {
+                    let res = format!("{}: {}", VOTE_EXISTS_ERROR, e);
+                    println!("{}", res);
+                    return HttpResponse::InternalServerError().body(res);
+                }
+            }
+
+            // Stored before the vote starts so no ballot is recorded without them
+            let rules = redis
+                .set_vote_options(fip, ntw, &options)
+                .and_then(|_| redis.set_vote_quorum(fip, ntw, quorum))
+                .and_then(|_| redis.set_vote_weighting(fip, ntw, weighting.unwrap_or_default()))
+                .and_then(|_| redis.set_vote_min_power(fip, ntw, start.min_power.unwrap_or(0)));
+            match rules {
+                Ok(_) => (),
+                Err(e) => {
+                    let res = format!("{}: {}", VOTE_START_ERROR, e);
+                    println!("{}", res);
+                    return HttpResponse::InternalServerError().body(res);
+                }
+            }
+
+            let started = match opens_at {
+                Some(opens_at) => redis.schedule_vote(fip, starter, ntw, opens_at).await,
+                None => redis.start_vote(fip, starter, ntw).await,
+            };
+            match started {
+                Ok(_) => (),
+                Err(e) => {
+                    let res = format!("{}: {}", VOTE_START_ERROR, e);
+                    println!("{}", res);
+                    return HttpResponse::InternalServerError().body(res);
+                }
+            }
         }
     }
 
     HttpResponse::Ok().body(config.vote_length().to_string())
 }
 
-#[post("/filecoin/registerstarter")]
-async fn register_vote_starter(
+/// Ends a vote in progress early, either concluding it with the ballots cast
+/// so far or cancelling it
+#[utoipa::path(
+    post,
+    path = "/filecoin/endvote",
+    tag = "starters",
+    params(NtwParams),
+    request_body = VoteEnd,
+    responses(
+        (status = 200, description = "The vote was concluded or cancelled"),
+        (status = 400, description = "Invalid message, or the vote is not in progress", body = String),
+        (status = 404, description = "The vote does not exist"),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn end_vote<F: StoreFactory>(
+    end: web::Json<VoteEnd>,
     query_params: web::Query<NtwParams>,
-    body: web::Bytes,
     config: web::Data<Args>,
+    store: web::Data<F>,
 ) -> impl Responder {
-    println!("Vote starter registration received");
-    let ntw = match query_params.network.as_str() {
-        "mainnet" => Network::Mainnet,
-        "calibration" => Network::Testnet,
-        _ => return HttpResponse::BadRequest().body(INVALID_NETWORK),
-    };
+    let ntw = query_params.network;
 
-    let auth: VoterAuthorization = match serde_json::from_slice(&body) {
-        Ok(auth) => auth,
+    let end = end.into_inner();
+
+    let domain = match end.domain() {
+        Ok(domain) => domain,
         Err(e) => {
-            let res = format!("{}: {}", VOTER_AUTH_DESERIALIZE_ERROR, e);
+            let res = format!("{}: {}", VOTER_AUTH_ERROR, e);
             println!("{}", res);
             return HttpResponse::BadRequest().body(res);
         }
     };
 
-    let (signer, new_signer) = match auth.auth() {
-        Ok(signer) => signer,
+    let (signer, fip, round, ending) = match end.auth() {
+        Ok(auth) => auth,
         Err(e) => {
-            let res = format!("{}: {}", VOTER_AUTH_RECOVER_ERROR, e);
+            let res = format!("{}: {}", VOTER_AUTH_ERROR, e);
             println!("{}", res);
             return HttpResponse::BadRequest().body(res);
         }
     };
 
-    let mut redis = match Redis::new(config.redis_path()) {
+    let mut redis = match store.open() {
         Ok(redis) => redis,
         Err(e) => {
             let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
@@ -212,57 +660,80 @@ async fn register_vote_starter(
         }
     };
 
-    match redis.is_authorized_starter(signer, ntw) {
+    if let Err(res) = check_domain(domain.as_ref(), None, ntw, &config, redis.now()) {
+        return res;
+    }
+
+    match redis.vote_exists(ntw, fip) {
         Ok(true) => (),
-        Ok(false) => {
-            let res = format!("{}: {}", VOTER_NOT_AUTHORIZED_ERROR, signer);
-            println!("{}", res);
-            return HttpResponse::BadRequest().body(res);
-        }
+        Ok(false) => return HttpResponse::NotFound().finish(),
         Err(e) => {
-            let res = format!("{}: {}", VOTER_AUTH_ERROR, e);
+            let res = format!("{}: {}", VOTE_EXISTS_ERROR, e);
             println!("{}", res);
             return HttpResponse::InternalServerError().body(res);
         }
     }
 
-    match redis.register_voter_starter(new_signer, ntw) {
+    match redis
+        .end_vote(fip, signer, ntw, round, ending, config.vote_length())
+        .await
+    {
         Ok(_) => (),
         Err(e) => {
-            let res = format!("{}: {}", VOTE_ADD_ERROR, e);
+            let res = format!("{}: {}", VOTE_END_ERROR, e);
             println!("{}", res);
-            return HttpResponse::InternalServerError().body(res);
+            return HttpResponse::BadRequest().body(res);
         }
     }
 
+    println!("Vote {:?} early for FIP: {}", ending, fip);
+
     HttpResponse::Ok().finish()
 }
 
-#[post("/filecoin/register")]
-async fn register_voter(body: web::Bytes, config: web::Data<Args>) -> impl Responder {
-    println!("Voter registration received");
+/// Extends the deadline of a vote in progress, returning the seconds left
+#[utoipa::path(
+    post,
+    path = "/filecoin/extendvote",
+    tag = "starters",
+    params(NtwParams),
+    request_body = VoteExtension,
+    responses(
+        (status = 200, description = "The vote was extended, the body is the seconds left", body = String),
+        (status = 400, description = "Invalid message, or the vote is not in progress", body = String),
+        (status = 404, description = "The vote does not exist"),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn extend_vote<F: StoreFactory>(
+    extension: web::Json<VoteExtension>,
+    query_params: web::Query<NtwParams>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+
+    let extension = extension.into_inner();
 
-    // Deserialize the body into the vote struct
-    let reg: ReceivedVoterRegistration = match serde_json::from_slice(&body) {
-        Ok(v) => v,
+    let domain = match extension.domain() {
+        Ok(domain) => domain,
         Err(e) => {
-            let res = format!("{}: {}", VOTE_DESERIALIZE_ERROR, e);
+            let res = format!("{}: {}", VOTER_AUTH_ERROR, e);
             println!("{}", res);
             return HttpResponse::BadRequest().body(res);
         }
     };
 
-    let registration = match reg.recover_vote_registration().await {
-        Ok(registration) => registration,
+    let (signer, fip, round, extension) = match extension.auth() {
+        Ok(auth) => auth,
         Err(e) => {
-            let res = format!("{}: {}", VOTE_RECOVER_ERROR, e);
+            let res = format!("{}: {}", VOTER_AUTH_ERROR, e);
             println!("{}", res);
             return HttpResponse::BadRequest().body(res);
         }
     };
 
-    // Open a connection to the redis database
-    let mut redis = match Redis::new(config.redis_path()) {
+    let mut redis = match store.open() {
         Ok(redis) => redis,
         Err(e) => {
             let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
@@ -271,46 +742,89 @@ async fn register_voter(body: web::Bytes, config: web::Data<Args>) -> impl Respo
         }
     };
 
-    // Add the vote to the database
-    match redis.register_voter(
-        registration.address(),
-        registration.ntw(),
-        registration.sp_ids(),
-    ) {
-        Ok(_) => (),
+    if let Err(res) = check_domain(domain.as_ref(), None, ntw, &config, redis.now()) {
+        return res;
+    }
+
+    match redis.vote_exists(ntw, fip) {
+        Ok(true) => (),
+        Ok(false) => return HttpResponse::NotFound().finish(),
         Err(e) => {
-            let res = format!("{}: {}", VOTE_ADD_ERROR, e);
+            let res = format!("{}: {}", VOTE_EXISTS_ERROR, e);
             println!("{}", res);
             return HttpResponse::InternalServerError().body(res);
         }
     }
 
-    HttpResponse::Ok().finish()
+    match redis
+        .extend_vote(fip, signer, ntw, round, extension, config.vote_length())
+        .await
+    {
+        Ok(_) => (),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_EXTENSION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    }
+
+    println!("Vote extended by {}s for FIP: {}", extension, fip);
+
+    match redis.vote_status(fip, config.vote_length(), ntw) {
+        Ok(VoteStatus::InProgress(time_left)) => HttpResponse::Ok().body(time_left.to_string()),
+        // The vote concluded right after it was extended
+        Ok(_) => HttpResponse::Ok().body("0"),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_STATUS_ERROR, e);
+            println!("{}", res);
+            HttpResponse::InternalServerError().body(res)
+        }
+    }
 }
 
-#[post("/filecoin/unregister")]
-async fn unregister_voter(body: web::Bytes, config: web::Data<Args>) -> impl Responder {
-    println!("Voter unregistration received");
+#[utoipa::path(
+    post,
+    path = "/filecoin/votemetadata",
+    tag = "starters",
+    params(NtwParams),
+    request_body = ReceivedMetadataUpdate,
+    responses(
+        (status = 200, description = "The metadata was stored"),
+        (status = 400, description = "Invalid network or request", body = String),
+        (status = 403, description = "The signer is not a vote starter", body = String),
+        (status = 404, description = "The vote does not exist"),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn update_vote_metadata<F: StoreFactory>(
+    update: web::Json<ReceivedMetadataUpdate>,
+    query_params: web::Query<NtwParams>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+
+    let update = update.into_inner();
 
-    let reg: ReceivedVoterRegistration = match serde_json::from_slice(&body) {
-        Ok(v) => v,
+    let domain = match update.domain() {
+        Ok(domain) => domain,
         Err(e) => {
-            let res = format!("{}: {}", VOTE_DESERIALIZE_ERROR, e);
+            let res = format!("{}: {}", VOTER_AUTH_ERROR, e);
             println!("{}", res);
             return HttpResponse::BadRequest().body(res);
         }
     };
 
-    let registration = match reg.recover_vote_registration().await {
-        Ok(registration) => registration,
+    let (signer, update) = match update.auth() {
+        Ok(auth) => auth,
         Err(e) => {
-            let res = format!("{}: {}", VOTE_RECOVER_ERROR, e);
+            let res = format!("{}: {}", VOTER_AUTH_ERROR, e);
             println!("{}", res);
             return HttpResponse::BadRequest().body(res);
         }
     };
 
-    let mut redis = match Redis::new(config.redis_path()) {
+    let mut redis = match store.open() {
         Ok(redis) => redis,
         Err(e) => {
             let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
@@ -319,14 +833,1373 @@ async fn unregister_voter(body: web::Bytes, config: web::Data<Args>) -> impl Res
         }
     };
 
-    match redis.unregister_voter(registration.address(), registration.ntw()) {
-        Ok(_) => (),
+    if let Err(res) = check_domain(domain.as_ref(), None, ntw, &config, redis.now()) {
+        return res;
+    }
+
+    // Only vote starters may change the metadata of a vote
+    match redis.is_authorized_starter(signer, ntw) {
+        Ok(true) => (),
+        Ok(false) if authorized_voters().contains(&signer) => (),
+        Ok(false) => {
+            let res = format!("{}: {}", VOTER_NOT_AUTHORIZED_ERROR, signer);
+            println!("{}", res);
+            return HttpResponse::Forbidden().body(res);
+        }
         Err(e) => {
-            let res = format!("{}: {}", VOTE_ADD_ERROR, e);
+            let res = format!("{}: {}", VOTER_AUTH_ERROR, e);
             println!("{}", res);
             return HttpResponse::InternalServerError().body(res);
         }
     }
 
-    HttpResponse::Ok().finish()
+    match redis.vote_exists(ntw, update.fip_number) {
+        Ok(true) => (),
+        Ok(false) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_EXISTS_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    }
+
+    match redis
+        .set_vote_metadata(
+            update.fip_number,
+            ntw,
+            update.language.clone(),
+            update.metadata(),
+        )
+        .await
+    {
+        Ok(_) => (),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_METADATA_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    }
+
+    println!(
+        "Vote metadata ({}) updated for FIP: {}",
+        update.language, update.fip_number
+    );
+
+    HttpResponse::Ok().finish()
+}
+
+#[utoipa::path(
+    post,
+    path = "/filecoin/registerstarter",
+    tag = "starters",
+    params(NtwParams),
+    request_body = VoterAuthorization,
+    responses(
+        (status = 200, description = "The address can start votes"),
+        (status = 400, description = "Invalid authorization, or the signer is not a vote starter", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn register_vote_starter<F: StoreFactory>(
+    query_params: web::Query<NtwParams>,
+    auth: web::Json<VoterAuthorization>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+
+    let auth = auth.into_inner();
+
+    let (signer, new_signer) = match auth.auth(ntw) {
+        Ok(signer) => signer,
+        Err(e) => {
+            let res = format!("{}: {}", VOTER_AUTH_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    if let Err(res) = check_chain_id(auth.typed_chain_id(), ntw) {
+        return res;
+    }
+    let domain = match auth.domain() {
+        Ok(domain) => domain,
+        Err(e) => {
+            let res = format!("{}: {}", VOTER_AUTH_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    if let Err(res) = check_domain(
+        domain.as_ref(),
+        auth.typed_chain_id(),
+        ntw,
+        &config,
+        store.clock().now(),
+    ) {
+        return res;
+    }
+
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    match may_change_starters(&mut redis, signer, RoleChange::Grant, ntw) {
+        Ok(true) => (),
+        Ok(false) => {
+            let res = format!("{}: {}", VOTER_NOT_AUTHORIZED_ERROR, signer);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+        Err(e) => {
+            let res = format!("{}: {}", VOTER_AUTH_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    }
+
+    let freshness = match auth.freshness() {
+        Ok(freshness) => freshness,
+        Err(e) => {
+            let res = format!("{}: {}", VOTER_AUTH_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    if let Err(res) = check_freshness(&mut redis, freshness, &config) {
+        return res;
+    }
+
+    match redis.register_voter_starter(new_signer, ntw) {
+        Ok(_) => (),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_ADD_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    }
+
+    HttpResponse::Ok().finish()
+}
+
+#[utoipa::path(
+    post,
+    path = "/filecoin/unregisterstarter",
+    tag = "starters",
+    params(NtwParams),
+    request_body = VoterAuthorization,
+    responses(
+        (status = 200, description = "The address can no longer start votes"),
+        (status = 400, description = "Invalid authorization, or the starter can't be removed", body = String),
+        (status = 403, description = "The signer is not an admin", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn unregister_vote_starter<F: StoreFactory>(
+    query_params: web::Query<NtwParams>,
+    auth: web::Json<VoterAuthorization>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+
+    let auth = auth.into_inner();
+
+    let (signer, removed) = match auth.revocation(ntw) {
+        Ok(signer) => signer,
+        Err(e) => {
+            let res = format!("{}: {}", VOTER_AUTH_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    if let Err(res) = check_chain_id(auth.typed_chain_id(), ntw) {
+        return res;
+    }
+    let domain = match auth.domain() {
+        Ok(domain) => domain,
+        Err(e) => {
+            let res = format!("{}: {}", VOTER_AUTH_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    if let Err(res) = check_domain(
+        domain.as_ref(),
+        auth.typed_chain_id(),
+        ntw,
+        &config,
+        store.clock().now(),
+    ) {
+        return res;
+    }
+
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    // Only admins may remove starters, see `may_change_starters`
+    match may_change_starters(&mut redis, signer, RoleChange::Revoke, ntw) {
+        Ok(true) => (),
+        Ok(false) => {
+            let res = format!("{}: {:?}", NOT_ADMIN_ERROR, signer);
+            println!("{}", res);
+            return HttpResponse::Forbidden().body(res);
+        }
+        Err(e) => {
+            let res = format!("{}: {}", VOTER_AUTH_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    }
+
+    let freshness = match auth.freshness() {
+        Ok(freshness) => freshness,
+        Err(e) => {
+            let res = format!("{}: {}", VOTER_AUTH_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    if let Err(res) = check_freshness(&mut redis, freshness, &config) {
+        return res;
+    }
+
+    match redis.remove_voter_starters(removed, ntw) {
+        Ok(_) => (),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_STARTER_REMOVE_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    }
+
+    HttpResponse::Ok().finish()
+}
+
+/// Queues a registration once its signature checks out, its storage providers
+/// are verified in the background, see [`crate::registrations`]
+#[utoipa::path(
+    post,
+    path = "/filecoin/register",
+    tag = "voters",
+    request_body = ReceivedVoterRegistration,
+    responses(
+        (status = 202, description = "The registration was queued, its progress is served by /filecoin/register/status", body = RegistrationStatus),
+        (status = 400, description = "Invalid registration", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn register_voter<F: StoreFactory>(
+    reg: web::Json<ReceivedVoterRegistration>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let reg = reg.into_inner();
+
+    let pending = match reg.recover_pending_registration().await {
+        Ok(pending) => pending,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    if let Err(e) = reg.check_domain(pending.network, &config.domain_rules(), store.clock().now()) {
+        let res = format!("{}: {}", DOMAIN_ERROR, e);
+        println!("{}", res);
+        return HttpResponse::BadRequest().body(res);
+    }
+
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    let status = match redis.queue_registration(&pending) {
+        Ok(status) => status,
+        Err(e) => {
+            let res = format!("{}: {}", REGISTRATION_QUEUE_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    println!(
+        "Queued the registration of {:?} for {} storage providers",
+        pending.address,
+        pending.sp_ids.len()
+    );
+
+    HttpResponse::Accepted().json(status)
+}
+
+/// Queues many registrations at once for services registering storage
+/// providers on behalf of several voters
+///
+/// One registration that doesn't check out doesn't reject the others, each
+/// gets its own outcome in the order they were sent
+#[utoipa::path(
+    post,
+    path = "/filecoin/register/bulk",
+    tag = "voters",
+    request_body = [ReceivedVoterRegistration],
+    responses(
+        (status = 200, description = "Whether each registration was queued or rejected", body = [BulkRegistration]),
+        (status = 400, description = "Too many registrations", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn register_voter_bulk<F: StoreFactory>(
+    regs: web::Json<Vec<ReceivedVoterRegistration>>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let regs = regs.into_inner();
+
+    if regs.len() > MAX_BULK_REGISTRATIONS {
+        let res = format!(
+            "{}: {} registrations, at most {}",
+            BULK_REGISTRATION_SIZE_ERROR,
+            regs.len(),
+            MAX_BULK_REGISTRATIONS
+        );
+        println!("{}", res);
+        return HttpResponse::BadRequest().body(res);
+    }
+
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    let mut results = Vec::new();
+    for recovered in recover_bulk(regs, &config.domain_rules(), redis.now()).await {
+        let pending = match recovered {
+            Ok(pending) => pending,
+            Err(e) => {
+                let error = format!("{}: {}", VOTE_RECOVER_ERROR, e);
+                results.push(BulkRegistration {
+                    address: None,
+                    status: RegistrationStatus::Failed { error },
+                });
+                continue;
+            }
+        };
+
+        let status = match redis.queue_registration(&pending) {
+            Ok(status) => status,
+            Err(e) => {
+                let res = format!("{}: {}", REGISTRATION_QUEUE_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::InternalServerError().body(res);
+            }
+        };
+        results.push(BulkRegistration {
+            address: Some(pending.address),
+            status,
+        });
+    }
+
+    let queued = results
+        .iter()
+        .filter(|result| result.address.is_some())
+        .count();
+    println!("Queued {} of {} registrations", queued, results.len());
+
+    HttpResponse::Ok().json(results)
+}
+
+/// Accepts the delegation a storage provider offered the voter, queueing the
+/// registration so its storage providers are verified again before it counts
+#[utoipa::path(
+    post,
+    path = "/filecoin/delegations/accept",
+    tag = "voters",
+    params(NtwParams),
+    request_body = DelegationAcceptance,
+    responses(
+        (status = 202, description = "The registration was queued, its progress is served by /filecoin/register/status", body = RegistrationStatus),
+        (status = 400, description = "Invalid acceptance", body = String),
+        (status = 404, description = "The worker address offered the voter no delegation", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn accept_delegation<F: StoreFactory>(
+    query_params: web::Query<NtwParams>,
+    acceptance: web::Json<DelegationAcceptance>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+
+    let acceptance = acceptance.into_inner();
+
+    let (voter, worker_address) = match acceptance.auth() {
+        Ok(auth) => auth,
+        Err(e) => {
+            let res = format!("{}: {}", ACCEPTANCE_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    let (freshness, domain) = match acceptance
+        .freshness()
+        .and_then(|freshness| Ok((freshness, acceptance.domain()?)))
+    {
+        Ok(signed) => signed,
+        Err(e) => {
+            let res = format!("{}: {}", ACCEPTANCE_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    if let Err(res) = check_domain(domain.as_ref(), None, ntw, &config, store.clock().now()) {
+        return res;
+    }
+
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    if let Err(res) = check_freshness(&mut redis, freshness, &config) {
+        return res;
+    }
+
+    // Taken off the offers so it is only accepted once
+    let offer = match redis.take_delegation_offer(voter, ntw, &worker_address) {
+        Ok(Some(offer)) => offer,
+        Ok(None) => {
+            let res = format!("{}: {}", DELEGATION_OFFER_NOT_FOUND, worker_address);
+            println!("{}", res);
+            return HttpResponse::NotFound().body(res);
+        }
+        Err(e) => {
+            let res = format!("{}: {}", DELEGATION_OFFERS_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    let status = match redis.queue_registration(&offer.accept(voter, ntw)) {
+        Ok(status) => status,
+        Err(e) => {
+            let res = format!("{}: {}", REGISTRATION_QUEUE_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    println!(
+        "{:?} accepted the delegation of {} on {}",
+        voter,
+        worker_address,
+        ntw.name()
+    );
+
+    HttpResponse::Accepted().json(status)
+}
+
+#[utoipa::path(
+    post,
+    path = "/filecoin/registerclass",
+    tag = "voters",
+    params(NtwParams),
+    request_body = ReceivedClassRegistration,
+    responses(
+        (status = 200, description = "The voter was registered"),
+        (status = 400, description = "Invalid registration or a token holder without a FIL balance", body = String),
+        (status = 500, description = "Database or chain error", body = String),
+    )
+)]
+async fn register_voter_class<F: StoreFactory>(
+    query_params: web::Query<NtwParams>,
+    reg: web::Json<ReceivedClassRegistration>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+
+    let reg = reg.into_inner();
+
+    let (signer, voter, class) = match reg.auth(ntw) {
+        Ok(auth) => auth,
+        Err(e) => {
+            let res = format!("{}: {}", CLASS_REGISTRATION_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+
+    // Token holders register themselves, so they have to hold some FIL
+    if class == VoterClass::TokenHolder {
+        match fetch_fil_balance(voter, ntw).await {
+            Ok(0) => {
+                let res = format!("{}: {}", TOKEN_HOLDER_BALANCE_ERROR, voter);
+                println!("{}", res);
+                return HttpResponse::BadRequest().body(res);
+            }
+            Ok(_) => (),
+            Err(e) => {
+                let res = format!("{}: {}", FIL_BALANCE_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::InternalServerError().body(res);
+            }
+        }
+    }
+
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    // Core devs are vouched for by a vote starter
+    if class == VoterClass::CoreDev {
+        match redis.is_authorized_starter(signer, ntw) {
+            Ok(true) => (),
+            Ok(false) => {
+                let res = format!("{}: {}", VOTER_NOT_AUTHORIZED_ERROR, signer);
+                println!("{}", res);
+                return HttpResponse::Unauthorized().body(res);
+            }
+            Err(e) => {
+                let res = format!("{}: {}", VOTER_AUTH_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::InternalServerError().body(res);
+            }
+        }
+    }
+
+    let expires_at = config.registration_expiry(redis.now());
+    match redis
+        .register_voter_class(voter, ntw, class)
+        .and_then(|_| redis.set_registration_expiry(voter, ntw, expires_at))
+    {
+        Ok(_) => (),
+        Err(e) => {
+            let res = format!("{}: {}", CLASS_REGISTRATION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    }
+
+    println!("Registered {} as {:?} on {:?}", voter, class, ntw);
+
+    HttpResponse::Ok().finish()
+}
+
+#[utoipa::path(
+    post,
+    path = "/filecoin/nonce",
+    tag = "votes",
+    responses(
+        (status = 200, description = "A nonce to end the next signed ballot, vote start or starter change with", body = IssuedNonce),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn issue_nonce<F: StoreFactory>(store: web::Data<F>) -> impl Responder {
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    match redis.issue_nonce(NONCE_TTL) {
+        Ok(nonce) => HttpResponse::Ok().json(nonce),
+        Err(e) => {
+            let res = format!("{}: {}", NONCE_ERROR, e);
+            println!("{}", res);
+            HttpResponse::InternalServerError().body(res)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/filecoin/renew",
+    tag = "voters",
+    request_body = ReceivedRenewal,
+    responses(
+        (status = 200, description = "The registration was renewed", body = RenewalReceipt),
+        (status = 400, description = "Invalid or stale renewal, or the voter is not registered", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn renew_registration<F: StoreFactory>(
+    renewal: web::Json<ReceivedRenewal>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let renewal = renewal.into_inner();
+
+    let (voter, ntw, signed_at) = match renewal.auth() {
+        Ok(auth) => auth,
+        Err(e) => {
+            let res = format!("{}: {}", RENEWAL_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+
+    let domain = match renewal.domain() {
+        Ok(domain) => domain,
+        Err(e) => {
+            let res = format!("{}: {}", RENEWAL_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    if let Err(res) = check_domain(domain.as_ref(), None, ntw, &config, store.clock().now()) {
+        return res;
+    }
+
+    // Old renewals can't keep a registration alive after its key was lost
+    if !is_fresh(signed_at, store.clock().now()) {
+        println!("{}: {}", RENEWAL_STALE_ERROR, voter);
+        return HttpResponse::BadRequest().body(RENEWAL_STALE_ERROR);
+    }
+
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    let expires_at =
+        match redis.renew_registration(voter, ntw, config.registration_expiry(signed_at)) {
+            Ok(expires_at) => expires_at,
+            Err(e) => {
+                let res = format!("{}: {}", RENEWAL_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::BadRequest().body(res);
+            }
+        };
+
+    println!("Renewed {} on {:?} until {:?}", voter, ntw, expires_at);
+
+    HttpResponse::Ok().json(RenewalReceipt { expires_at })
+}
+
+#[utoipa::path(
+    post,
+    path = "/filecoin/unregister",
+    tag = "voters",
+    request_body = ReceivedVoterRegistration,
+    responses(
+        (status = 200, description = "The voter was unregistered"),
+        (status = 400, description = "Invalid registration", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn unregister_voter<F: StoreFactory>(
+    reg: web::Json<ReceivedVoterRegistration>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let reg = reg.into_inner();
+
+    let registration = match reg.recover_vote_registration().await {
+        Ok(registration) => registration,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    if let Err(e) = reg.check_domain(
+        registration.ntw(),
+        &config.domain_rules(),
+        store.clock().now(),
+    ) {
+        let res = format!("{}: {}", DOMAIN_ERROR, e);
+        println!("{}", res);
+        return HttpResponse::BadRequest().body(res);
+    }
+
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    match redis.unregister_voter(registration.address(), registration.ntw()) {
+        Ok(_) => (),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_ADD_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    }
+
+    HttpResponse::Ok().finish()
+}
+
+#[utoipa::path(
+    post,
+    path = "/filecoin/unregister/bulk",
+    tag = "voters",
+    request_body = ReceivedUnregistration,
+    responses(
+        (status = 200, description = "Storage providers left on each network", body = HashMap<String, Vec<String>>),
+        (status = 400, description = "Invalid unregistration", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn unregister_voter_bulk<F: StoreFactory>(
+    unreg: web::Json<ReceivedUnregistration>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let unreg = unreg.into_inner();
+
+    let (voter, unregistration) = match unreg.auth() {
+        Ok(auth) => auth,
+        Err(e) => {
+            let res = format!("{}: {}", UNREGISTRATION_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    let res = match unregistration {
+        Unregistration::All => redis.unregister_voter_all(voter),
+        Unregistration::StorageProviders(ntw, sp_ids) => {
+            redis.unregister_storage_providers(voter, ntw, &sp_ids)
+        }
+    };
+
+    match res {
+        Ok(_) => (),
+        Err(e) => {
+            let res = format!("{}: {}", VOTER_UNREGISTER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    }
+
+    // Return what is left of the registration on each network
+    let mut registration = HashMap::new();
+    for ntw in Network::all() {
+        let delegates = match redis.voter_delegates(voter, ntw) {
+            Ok(delegates) => delegates,
+            Err(e) => {
+                let res = format!("{}: {}", VOTER_DELEGATES_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::InternalServerError().body(res);
+            }
+        };
+        let dgts = delegates
+            .into_iter()
+            .map(|d| sp_id_format(ntw, d))
+            .collect::<Vec<String>>();
+        registration.insert(ntw.name(), dgts);
+    }
+
+    println!("Registration: {:?} for address: {}", registration, voter);
+
+    HttpResponse::Ok().json(registration)
+}
+
+/// Takes a single storage provider off a registration, leaving the rest of it
+///
+/// Signed by the voter, the storage provider is taken off their registration.
+/// Signed by the storage provider, it is taken off every registration it is
+/// delegated to
+#[utoipa::path(
+    post,
+    path = "/filecoin/delegates/remove",
+    tag = "voters",
+    params(NtwParams),
+    request_body = DelegateRemoval,
+    responses(
+        (status = 200, description = "Storage providers left in each registration the storage provider was taken off", body = HashMap<String, Vec<String>>),
+        (status = 400, description = "Invalid removal", body = String),
+        (status = 404, description = "The storage provider is not delegated to the voter", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn remove_delegate<F: StoreFactory>(
+    query_params: web::Query<NtwParams>,
+    removal: web::Json<DelegateRemoval>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+
+    let removal = removal.into_inner();
+
+    let (remover, sp_id) = match removal.auth(ntw) {
+        Ok(auth) => auth,
+        Err(e) => {
+            let res = format!("{}: {}", DELEGATE_REMOVAL_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    let (freshness, domain) = match removal
+        .freshness()
+        .and_then(|freshness| Ok((freshness, removal.domain()?)))
+    {
+        Ok(signed) => signed,
+        Err(e) => {
+            let res = format!("{}: {}", DELEGATE_REMOVAL_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    if let Err(res) = check_domain(domain.as_ref(), None, ntw, &config, store.clock().now()) {
+        return res;
+    }
+    if let Err(e) = remover.verify(sp_id, ntw).await {
+        let res = format!("{}: {}", DELEGATE_REMOVAL_RECOVER_ERROR, e);
+        println!("{}", res);
+        return HttpResponse::BadRequest().body(res);
+    }
+
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    // A voter only reaches their own registration, a storage provider every
+    // registration it is delegated to
+    let voters = match remover {
+        Remover::Voter(voter) => match redis.voter_delegates(voter, ntw) {
+            Ok(delegates) if delegates.contains(&sp_id) => vec![voter],
+            Ok(_) => Vec::new(),
+            Err(e) => {
+                let res = format!("{}: {}", VOTER_DELEGATES_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::InternalServerError().body(res);
+            }
+        },
+        Remover::StorageProvider(_) => match redis.delegators(sp_id, ntw) {
+            Ok(voters) => voters,
+            Err(e) => {
+                let res = format!("{}: {}", VOTER_DELEGATES_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::InternalServerError().body(res);
+            }
+        },
+    };
+    if voters.is_empty() {
+        let res = format!("{}: {}", DELEGATE_NOT_FOUND, sp_id_format(ntw, sp_id));
+        println!("{}", res);
+        return HttpResponse::NotFound().body(res);
+    }
+
+    if let Err(res) = check_freshness(&mut redis, freshness, &config) {
+        return res;
+    }
+
+    let mut registrations = HashMap::new();
+    for voter in voters {
+        if let Err(e) = redis.unregister_storage_providers(voter, ntw, &[sp_id]) {
+            let res = format!("{}: {}", VOTER_UNREGISTER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+
+        // Return what is left of each registration
+        let delegates = match redis.voter_delegates(voter, ntw) {
+            Ok(delegates) => delegates,
+            Err(e) => {
+                let res = format!("{}: {}", VOTER_DELEGATES_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::InternalServerError().body(res);
+            }
+        };
+        let dgts = delegates
+            .into_iter()
+            .map(|d| sp_id_format(ntw, d))
+            .collect::<Vec<String>>();
+        registrations.insert(format!("{:?}", voter), dgts);
+    }
+
+    println!(
+        "Removed {} from {:?} on {}",
+        sp_id_format(ntw, sp_id),
+        registrations.keys(),
+        ntw.name()
+    );
+
+    HttpResponse::Ok().json(registrations)
+}
+
+/// Checks the expiry of a signed message and uses up its nonce
+///
+/// Messages signed without a nonce are accepted unless the server runs with `--require-nonce`
+pub(crate) fn check_freshness(
+    redis: &mut impl VoteStore,
+    freshness: Option<Freshness>,
+    config: &Args,
+) -> Result<(), HttpResponse> {
+    let freshness = match freshness {
+        Some(freshness) => freshness,
+        None if config.require_nonce() => {
+            println!("{}", NONCE_REQUIRED_ERROR);
+            return Err(HttpResponse::BadRequest().body(NONCE_REQUIRED_ERROR));
+        }
+        None => return Ok(()),
+    };
+
+    if freshness.is_expired(redis.now()) {
+        println!("{}", MESSAGE_EXPIRED_ERROR);
+        return Err(HttpResponse::BadRequest().body(MESSAGE_EXPIRED_ERROR));
+    }
+
+    match redis.consume_nonce(freshness.nonce) {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            println!("{}", NONCE_INVALID_ERROR);
+            Err(HttpResponse::BadRequest().body(NONCE_INVALID_ERROR))
+        }
+        Err(e) => {
+            let res = format!("{}: {}", NONCE_CHECK_ERROR, e);
+            println!("{}", res);
+            Err(HttpResponse::InternalServerError().body(res))
+        }
+    }
+}
+
+/// Checks that the voter is registered on any network
+fn check_voter(redis: &mut impl VoteStore, voter: Address) -> Result<(), HttpResponse> {
+    match redis.voter_networks(voter) {
+        Ok(ntws) if !ntws.is_empty() => Ok(()),
+        Ok(_) => {
+            let res = format!("{}: {:?}", VOTER_NOT_REGISTERED_NETWORK, voter);
+            println!("{}", res);
+            Err(HttpResponse::Forbidden().body(res))
+        }
+        Err(e) => {
+            let res = format!("{}: {}", VOTER_AUTH_ERROR, e);
+            println!("{}", res);
+            Err(HttpResponse::InternalServerError().body(res))
+        }
+    }
+}
+
+/// Finds the network a ballot on the FIP counts on and the options it can be
+/// cast for, checking that the voter may vote there
+fn check_ballot(
+    redis: &mut impl VoteStore,
+    voter: Address,
+    num: FipNumber,
+    typed_chain_id: Option<u64>,
+    domain: Option<&Domain>,
+    config: &Args,
+) -> Result<(Network, Vec<String>), HttpResponse> {
+    // Each registration of the voter is its own, the ballot counts toward the
+    // vote it was cast on, or the network it was signed for
+    let chain_id =
+        typed_chain_id.or_else(|| domain.and_then(Domain::ntw).map(|ntw| ntw.chain_id()));
+    let ntw = match redis.ballot_network(voter, num, chain_id, config.vote_length()) {
+        Ok(ntw) => ntw,
+        Err(StoreError::NotAuthorized(_)) => {
+            let res = format!("{}: {:?}", VOTER_NOT_REGISTERED_NETWORK, voter);
+            println!("{}", res);
+            return Err(HttpResponse::Forbidden().body(res));
+        }
+        Err(e @ StoreError::Invalid(_)) => {
+            let res = format!("{}: {}", BALLOT_NETWORK_ERROR, e);
+            println!("{}", res);
+            return Err(HttpResponse::BadRequest().body(res));
+        }
+        Err(e) => {
+            let res = format!("{}: {}", VOTER_AUTH_ERROR, e);
+            println!("{}", res);
+            return Err(HttpResponse::InternalServerError().body(res));
+        }
+    };
+
+    // Ballots are only valid on the network they were signed for
+    check_chain_id(typed_chain_id, ntw)?;
+    check_domain(domain, typed_chain_id, ntw, config, redis.now())?;
+
+    // Expired registrations have to be renewed before the voter can vote again
+    match redis.is_registration_expired(voter, ntw) {
+        Ok(false) => (),
+        Ok(true) => {
+            println!("{}: {}", REGISTRATION_EXPIRED_ERROR, voter);
+            return Err(HttpResponse::Forbidden().body(REGISTRATION_EXPIRED_ERROR));
+        }
+        Err(e) => {
+            let res = format!("{}: {}", VOTER_AUTH_ERROR, e);
+            println!("{}", res);
+            return Err(HttpResponse::InternalServerError().body(res));
+        }
+    }
+
+    // The choice is checked against the options the vote was started with
+    match redis.vote_options(num, ntw) {
+        Ok(options) => Ok((ntw, options)),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_OPTIONS_ERROR, e);
+            println!("{}", res);
+            Err(HttpResponse::InternalServerError().body(res))
+        }
+    }
+}
+
+/// Checks that the FIP is in the FIPs repository, by its synced metadata or
+/// else by fetching it, unless FIP syncing is disabled
+async fn check_known_fip(
+    redis: &mut impl VoteStore,
+    fip_number: FipNumber,
+    config: &Args,
+) -> Result<(), HttpResponse> {
+    let registry = match FipRegistry::from_args(config) {
+        Some(registry) => registry,
+        None => return Ok(()),
+    };
+
+    match registry.lookup(redis, fip_number).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => {
+            let res = format!("{}: FIP-{}", FIP_NOT_FOUND_ERROR, fip_number);
+            println!("{}", res);
+            Err(HttpResponse::NotFound().body(res))
+        }
+        Err(e) => {
+            let res = format!("{}: {}", FIP_LOOKUP_ERROR, e);
+            println!("{}", res);
+            Err(HttpResponse::InternalServerError().body(res))
+        }
+    }
+}
+
+/// Checks that the vote takes ballots
+fn check_open(
+    redis: &mut impl VoteStore,
+    num: FipNumber,
+    ntw: Network,
+    config: &Args,
+) -> Result<(), HttpResponse> {
+    // The same check concludes the vote, so a ballot taken here is never left
+    // out of the results
+    let open = match redis.is_open(num, config.vote_length(), ntw, redis.now()) {
+        Ok(open) => open,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_STATUS_ERROR, e);
+            println!("{}", res);
+            return Err(HttpResponse::InternalServerError().body(res));
+        }
+    };
+
+    if open {
+        return Ok(());
+    }
+
+    // Each way the vote can't take the ballot has its own status and error
+    let status = match redis.vote_status(num, config.vote_length(), ntw) {
+        Ok(status) => status,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_STATUS_ERROR, e);
+            println!("{}", res);
+            return Err(HttpResponse::InternalServerError().body(res));
+        }
+    };
+    println!("{:?} for FIP: {} on {:?}", status, num, ntw);
+
+    Err(match status {
+        VoteStatus::Scheduled(opens_in) => {
+            HttpResponse::Forbidden().body(format!("{}: {}", VOTE_NOT_OPEN_ERROR, opens_in))
+        }
+        VoteStatus::Cancelled => HttpResponse::Conflict().body(VOTE_CANCELLED),
+        VoteStatus::DoesNotExist => HttpResponse::NotFound().body(VOTE_NOT_FOUND_ERROR),
+        VoteStatus::Unknown => HttpResponse::Conflict().body(VOTE_STATUS_UNKNOWN),
+        // Closed in the instant between the two checks
+        VoteStatus::Concluded | VoteStatus::InProgress(_) => {
+            HttpResponse::Conflict().body(VOTE_CONCLUDED_ERROR)
+        }
+    })
+}
+
+/// Checks that a message signed as typed data was signed for the network it is used on
+fn check_chain_id(chain_id: Option<u64>, ntw: Network) -> Result<(), HttpResponse> {
+    match chain_id {
+        Some(chain_id) if chain_id != ntw.chain_id() => {
+            let res = format!("{}: {}", CHAIN_ID_MISMATCH_ERROR, chain_id);
+            println!("{}", res);
+            Err(HttpResponse::BadRequest().body(res))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks that a plain text message was signed for this service on the
+/// network it is used on, typed data is bound to its network by its chain id
+pub(crate) fn check_domain(
+    domain: Option<&Domain>,
+    typed_chain_id: Option<u64>,
+    ntw: Network,
+    config: &Args,
+    now: u64,
+) -> Result<(), HttpResponse> {
+    if typed_chain_id.is_some() {
+        return Ok(());
+    }
+
+    match config.domain_rules().check(domain, ntw, now) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let res = format!("{}: {}", DOMAIN_ERROR, e);
+            println!("{}", res);
+            Err(HttpResponse::BadRequest().body(res))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use actix_web::{body, http::StatusCode, test, App};
+    use clap::Parser;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::{
+        clock::MockClock,
+        fip_registry::FipInfo,
+        get,
+        memory_store::MemoryStore,
+        messages::{
+            nonce::IssuedNonce,
+            votes::{test_votes::test_vote_body, VoteOption},
+        },
+        redis::{VoteReceipt, VoteResults},
+        test_redis::{StaticPower, TEST_SP_ID, TEST_SP_POWER},
+    };
+
+    fn fip(num: u32) -> FipNumber {
+        FipNumber::try_from(num).unwrap()
+    }
+
+    /// Serves a FIPs repository that has none of the FIPs, returning its url
+    async fn empty_fip_repo() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/FIPS", listener.local_addr().unwrap());
+
+        actix_web::rt::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request).await;
+                let _ = stream
+                    .write_all(
+                        b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                    )
+                    .await;
+            }
+        });
+
+        url
+    }
+
+    #[actix_web::test]
+    async fn post_issue_nonce() {
+        let mut store = MemoryStore::default();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(store.clone()))
+                .configure(routes::<MemoryStore>),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/filecoin/nonce")
+            .to_request();
+        let issued: IssuedNonce = test::call_and_read_body_json(&app, req).await;
+
+        let nonce: [u8; 16] = hex::decode(&issued.nonce).unwrap().try_into().unwrap();
+        assert!(store.nonce_issued(nonce).unwrap());
+        assert!(store.consume_nonce(nonce).unwrap());
+        assert!(!store.nonce_issued(nonce).unwrap());
+    }
+
+    #[actix_web::test]
+    async fn post_check_freshness() {
+        let mut store = MemoryStore::default();
+        let clock = Arc::new(MockClock::new(1_700_000_000));
+        store.set_clock(clock.clone());
+        let config = Args::parse_from(["filecoin-vote"]);
+
+        let issue = |store: &mut MemoryStore| {
+            let issued = store.issue_nonce(60).unwrap();
+            Freshness {
+                nonce: hex::decode(&issued.nonce).unwrap().try_into().unwrap(),
+                expires_at: issued.expires_at,
+            }
+        };
+
+        let fresh = issue(&mut store);
+        assert!(check_freshness(&mut store, Some(fresh), &config).is_ok());
+
+        // Expiry is judged by the clock of the store
+        let stale = issue(&mut store);
+        clock.advance(61);
+        let res = check_freshness(&mut store, Some(stale), &config).unwrap_err();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body::to_bytes(res.into_body()).await.unwrap(),
+            MESSAGE_EXPIRED_ERROR
+        );
+    }
+
+    #[actix_web::test]
+    async fn post_check_known_fip() {
+        let mut store = MemoryStore::default();
+        let repo = empty_fip_repo().await;
+        let config = Args::parse_from(["filecoin-vote", "--fip-repo-url", repo.as_str()]);
+
+        // Synced FIPs are known without asking the repository
+        let info = FipInfo {
+            title: "Title".to_string(),
+            status: "Draft".to_string(),
+            discussions_to: None,
+            url: format!("{}/fip-0005.md", repo),
+        };
+        store.set_fip_info(fip(5), &info).unwrap();
+        assert!(check_known_fip(&mut store, fip(5), &config).await.is_ok());
+
+        // FIP-9999 is in range but not in the repository
+        let res = check_known_fip(&mut store, fip(9999), &config)
+            .await
+            .unwrap_err();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            body::to_bytes(res.into_body()).await.unwrap(),
+            format!("{}: FIP-9999", FIP_NOT_FOUND_ERROR)
+        );
+        assert_eq!(store.fip_info(fip(9999)).unwrap(), None);
+
+        // Without syncing there is nothing to check the FIP against
+        let config = Args::parse_from(["filecoin-vote", "--fip-sync-interval", "0"]);
+        assert!(check_known_fip(&mut store, fip(9999), &config)
+            .await
+            .is_ok());
+    }
+
+    #[actix_web::test]
+    async fn post_register_vote_results() {
+        let config = Args::parse_from([
+            "filecoin-vote",
+            "--vote-length",
+            "60",
+            "--fip-sync-interval",
+            "0",
+        ]);
+        let clock = Arc::new(MockClock::new(1_700_000_000));
+        let mut store = MemoryStore::default();
+        store.set_clock(clock.clone());
+        store.set_power_provider(StaticPower::new([(TEST_SP_ID, TEST_SP_POWER)]));
+
+        // The signer of the fixture ballots
+        let ntw = Network::Testnet;
+        let voter = Address::from_str("0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56").unwrap();
+        store.register_voter(voter, ntw, vec![TEST_SP_ID]).unwrap();
+        store
+            .start_vote(fip(2), authorized_voters()[0], ntw)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(store.clone()))
+                .configure(routes::<MemoryStore>)
+                .configure(get::routes::<MemoryStore>),
+        )
+        .await;
+
+        let vote = || {
+            test::TestRequest::post()
+                .uri("/filecoin/vote?fip_number=2")
+                .set_json(test_vote_body(VoteOption::Yay, 2))
+                .to_request()
+        };
+        let receipt: VoteReceipt = test::call_and_read_body_json(&app, vote()).await;
+        assert_eq!(receipt.address, voter);
+        assert_eq!(receipt.choice, VoteOption::Yay);
+
+        // A retry gets the original receipt without counting the ballot twice
+        let retried: VoteReceipt = test::call_and_read_body_json(&app, vote()).await;
+        assert_eq!(retried, receipt);
+        assert_eq!(store.voter_history(voter, ntw).unwrap().len(), 1);
+
+        // Results are served once the vote concluded
+        clock.advance(61);
+        let req = test::TestRequest::get()
+            .uri("/filecoin/vote?network=calibration&fip_number=2")
+            .to_request();
+        let results: VoteResults = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(results.yay, 1);
+        assert_eq!(results.yay_storage_size, TEST_SP_POWER);
+        assert_eq!((results.nay, results.abstain), (0, 0));
+
+        // Ballots are no longer taken
+        let req = test::TestRequest::post()
+            .uri("/filecoin/vote?fip_number=2")
+            .set_json(test_vote_body(VoteOption::Nay, 2))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+    }
 }
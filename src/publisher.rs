@@ -0,0 +1,255 @@
+use std::{sync::Arc, time::Duration};
+
+use ethers::{
+    abi::{self, Token},
+    middleware::SignerMiddleware,
+    providers::{Http, Middleware, Provider, ProviderError},
+    signers::{LocalWallet, Signer},
+    types::{Address, Bytes, Eip1559TransactionRequest, H256, U256, U64},
+    utils::{id, keccak256},
+};
+use redis::RedisError;
+use thiserror::Error;
+use tokio::{sync::watch, task::JoinHandle};
+use url::Url;
+
+use crate::{
+    attestation::{load_wallet, AttestationError, AttestedResults},
+    clock::{Clock, SystemClock},
+    fip::FipNumber,
+    redis::Redis,
+    storage::Network,
+    store::{StoreError, VoteStore},
+    Args,
+};
+
+/// How often concluded votes are checked for unpublished results
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Function of the results contract that records a concluded vote
+const PUBLISH_FUNCTION: &str = "publishResults(uint32,string,bytes32)";
+
+#[derive(Debug, Error)]
+pub enum PublishError {
+    #[error("{0}")]
+    Key(#[from] AttestationError),
+    #[error("Invalid RPC url: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("Redis error: {0}")]
+    Redis(#[from] RedisError),
+    #[error("Store error: {0}")]
+    Store(#[from] StoreError),
+    #[error("Error serializing results: {0}")]
+    Serde(#[from] serde_json::Error),
+    #[error("Provider error: {0}")]
+    Provider(#[from] ProviderError),
+    #[error("Error sending transaction: {0}")]
+    Transaction(String),
+    #[error("Transaction {0:?} was dropped")]
+    Dropped(H256),
+    #[error("Transaction {0:?} reverted")]
+    Reverted(H256),
+}
+
+/// Submits the results of concluded votes to a contract on FEVM
+///
+/// The contract receives the keccak256 hash of the same json the attestation
+/// endpoint signs, so the published hash can be checked against the served results
+pub struct Publisher {
+    client: SignerMiddleware<Provider<Http>, LocalWallet>,
+    contract: Address,
+    redis_path: Url,
+    vote_length: u64,
+    grace_period: u64,
+    clock: Arc<dyn Clock>,
+}
+
+impl Publisher {
+    /// Returns `None` unless both a contract and a key are configured
+    pub fn from_args(args: &Args) -> Result<Option<Self>, PublishError> {
+        let (contract, key) = match (args.publish_contract(), args.publish_key()) {
+            (Some(contract), Some(key)) => (contract, key),
+            _ => return Ok(None),
+        };
+
+        let wallet = load_wallet(&key)?.with_chain_id(args.publish_chain_id());
+        let provider = Provider::<Http>::try_from(args.publish_rpc().as_str())?;
+
+        Ok(Some(Self {
+            client: SignerMiddleware::new(provider, wallet),
+            contract,
+            redis_path: args.redis_path(),
+            vote_length: args.vote_length(),
+            grace_period: args.vote_grace_period(),
+            clock: Arc::new(SystemClock),
+        }))
+    }
+
+    pub fn address(&self) -> Address {
+        self.client.address()
+    }
+
+    /// Times votes with `clock` instead of the system time
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Publishes the results of every concluded vote in the background
+    ///
+    /// A run is never interrupted, so a transaction already sent is recorded
+    /// as published before the task stops
+    pub fn spawn(self) -> PublisherHandle {
+        let (shutdown, mut stopping) = watch::channel(false);
+
+        let task = actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(PUBLISH_INTERVAL);
+            loop {
+                let stop = tokio::select! {
+                    _ = interval.tick() => false,
+                    _ = stopping.changed() => true,
+                };
+                if let Err(e) = self.publish_concluded().await {
+                    println!("Error publishing results: {}", e);
+                }
+                if stop {
+                    break;
+                }
+            }
+        });
+
+        PublisherHandle { shutdown, task }
+    }
+
+    /// Publishes every concluded vote that has not been published yet
+    ///
+    /// A vote that fails to publish is retried on the next run without
+    /// holding up the others
+    pub async fn publish_concluded(&self) -> Result<(), PublishError> {
+        let mut redis = Redis::new(self.redis_path.clone())?;
+        redis.set_clock(self.clock.clone());
+        redis.set_grace_period(self.grace_period);
+
+        for ntw in Network::all() {
+            for fip_number in redis.concluded_votes(ntw, self.vote_length)? {
+                if redis.published_results(fip_number, ntw)?.is_some() {
+                    continue;
+                }
+
+                match self.publish(&mut redis, fip_number, ntw).await {
+                    Ok(tx_hash) => {
+                        redis.set_published_results(fip_number, ntw, tx_hash)?;
+                        println!(
+                            "Published results of FIP-{} on {} in {:?}",
+                            fip_number,
+                            ntw.name(),
+                            tx_hash
+                        );
+                    }
+                    Err(e) => println!(
+                        "Error publishing results of FIP-{} on {}: {}",
+                        fip_number,
+                        ntw.name(),
+                        e
+                    ),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn publish(
+        &self,
+        redis: &mut impl VoteStore,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<H256, PublishError> {
+        let results = redis
+            .vote_results(fip_number, self.vote_length, ntw)
+            .await?;
+        let message = AttestedResults::new(fip_number, ntw, results).message()?;
+
+        let tx = Eip1559TransactionRequest::new()
+            .to(self.contract)
+            .data(calldata(fip_number, ntw, keccak256(message)));
+
+        let pending = self
+            .client
+            .send_transaction(tx, None)
+            .await
+            .map_err(|e| PublishError::Transaction(e.to_string()))?;
+        let tx_hash = pending.tx_hash();
+
+        match pending.await? {
+            Some(receipt) if receipt.status == Some(U64::one()) => Ok(tx_hash),
+            Some(_) => Err(PublishError::Reverted(tx_hash)),
+            None => Err(PublishError::Dropped(tx_hash)),
+        }
+    }
+}
+
+/// Stops the background publishing started by [`Publisher::spawn`]
+pub struct PublisherHandle {
+    shutdown: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl PublisherHandle {
+    /// Lets a run in progress finish and publishes anything concluded since the
+    /// last one before stopping, giving up once the timeout passes
+    pub async fn shutdown(self, timeout: Duration) {
+        let _ = self.shutdown.send(true);
+        if actix_web::rt::time::timeout(timeout, self.task)
+            .await
+            .is_err()
+        {
+            println!("Timed out waiting for results to finish publishing");
+        }
+    }
+}
+
+/// Encodes the call recording the results hash of a vote
+fn calldata(fip_number: FipNumber, ntw: Network, results_hash: [u8; 32]) -> Bytes {
+    let mut data = id(PUBLISH_FUNCTION).to_vec();
+    data.extend(abi::encode(&[
+        Token::Uint(U256::from(fip_number.get())),
+        Token::String(ntw.name().to_string()),
+        Token::FixedBytes(results_hash.to_vec()),
+    ]));
+    data.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::abi::ParamType;
+
+    use super::*;
+
+    #[test]
+    fn publisher_calldata() {
+        let fip_number = FipNumber::try_from(1).unwrap();
+        let hash = keccak256("results");
+
+        let data = calldata(fip_number, Network::Testnet, hash);
+
+        assert_eq!(data[..4], id(PUBLISH_FUNCTION));
+
+        let tokens = abi::decode(
+            &[
+                ParamType::Uint(32),
+                ParamType::String,
+                ParamType::FixedBytes(32),
+            ],
+            &data[4..],
+        )
+        .unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Uint(U256::from(1)),
+                Token::String("calibration".to_string()),
+                Token::FixedBytes(hash.to_vec()),
+            ]
+        );
+    }
+}
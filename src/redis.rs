@@ -1,44 +1,265 @@
 extern crate redis;
 
-use std::{mem::MaybeUninit, time};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use ethers::types::Address;
+use async_trait::async_trait;
+use ethers::types::{Address, H256};
 use redis::{Commands, Connection, RedisError};
 use serde::{Deserialize, Serialize};
 use url::Url;
+use utoipa::ToSchema;
 
 use crate::{
     authorized_voters,
-    messages::votes::{Vote, VoteOption},
-    storage::{fetch_storage_amount, Network},
+    clock::{Clock, SystemClock},
+    events::{Event, LoggedEvent, RecordedBallot},
+    fip::FipNumber,
+    fip_registry::FipInfo,
+    hot_cache,
+    messages::{
+        class_registration::VoterClass,
+        nonce::IssuedNonce,
+        vote_end::VoteEnding,
+        vote_metadata::VoteMetadata,
+        vote_registration::{is_msig_address, PendingRegistration},
+        vote_start::QuorumRules,
+        votes::{Vote, VoteOption},
+    },
+    notifier::Announcement,
+    registrations::{DelegationOffer, RegistrationStatus},
+    storage::{
+        fetch_fil_balance, fetch_owner_address, fetch_worker_address, sp_id_format, ChainPower,
+        Network, SpCohort, StoragePowerProvider,
+    },
+    store::{StoreError, StoreFactory, VoteStore},
+    tally::{self, checked_mul, ChoicePower, PowerMap, TallyError, TallyRules, Weighting},
+    Args,
 };
 
 pub struct Redis {
     con: Connection,
+    /// Where ballots are weighted from, the chain unless a test stubs it
+    power: Arc<dyn StoragePowerProvider>,
+    /// Where votes and registrations are timed from, the system time unless a test stubs it
+    clock: Arc<dyn Clock>,
+    /// Seconds ballots are taken for after a vote ends
+    grace_period: u64,
+    /// Whether the connection is to a read replica, see [`Redis::replica`]
+    read_only: bool,
 }
 
 #[derive(Debug, PartialEq)]
 pub enum VoteStatus {
     DoesNotExist,
+    /// Started to open for ballots in this many seconds
+    Scheduled(u64),
+    /// Takes ballots, ends in this many seconds and is 0 during the grace period
     InProgress(u64),
     Concluded,
+    /// Ended by a vote starter without results
+    Cancelled,
+    /// Ended by a newer version in a way this version doesn't know about, it
+    /// takes no ballots and has no results this version can tally
+    Unknown,
+}
+
+impl VoteStatus {
+    /// Where a vote that opens at `opens_at` for `vote_length` seconds is at `now`
+    ///
+    /// Ballots arriving up to `grace_period` seconds after the end are still
+    /// taken, and the vote only concludes once they no longer are. A vote that
+    /// would end past the last representable second never ends rather than
+    /// wrapping around to having ended
+    pub fn at(now: u64, opens_at: u64, vote_length: u64, grace_period: u64) -> VoteStatus {
+        if now < opens_at {
+            return VoteStatus::Scheduled(opens_at - now);
+        }
+
+        let ends_at = opens_at.saturating_add(vote_length);
+        match now < ends_at.saturating_add(grace_period) {
+            true => VoteStatus::InProgress(ends_at.saturating_sub(now)),
+            false => VoteStatus::Concluded,
+        }
+    }
 }
 
+/// Record of a vote starter ending a vote before its voting period was over
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EarlyEnd {
+    pub ending: VoteEnding,
+    pub signer: Address,
+    /// Unix timestamp the vote was ended at
+    pub ended_at: u64,
+}
+
+/// Version of the key layout written by this build, see [`Redis::migrate`]
+pub const SCHEMA_VERSION: u64 = 6;
+
+/// Every key written by this crate starts with this prefix
+const KEY_PREFIX: &str = "fipvote:";
+
+/// Seconds the status of a registration is kept for after it last changed
+const REGISTRATION_STATUS_TTL: u64 = 7 * 24 * 60 * 60;
+
+/// Milliseconds a vote lock expires after, in case the instance holding it stops
+const LOCK_TTL: u64 = 10_000;
+/// Milliseconds to wait for a vote lock held by another instance before giving up
+const LOCK_WAIT: u64 = 5_000;
+/// Milliseconds between attempts to take a vote lock
+const LOCK_RETRY: u64 = 20;
+
+/// Releases a lock only if it is still held with the token it was taken with,
+/// so a lock that expired and was taken by another instance is left alone
+const UNLOCK_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
 enum LookupKey {
-    /// FIP number to vector of all votes
-    Votes(u32, Network),
+    /// FIP number to the list of ballots
+    Votes(FipNumber, Network),
     /// FIP number to timestamp of vote start
-    Timestamp(u32, Network),
+    Timestamp(FipNumber, Network),
     /// Network and voter address to voter registration
     Voter(Network, Address),
-    /// The voter authorized to start a vote on that network
+    /// Set of voters authorized to start a vote on that network
     VoteStarters(Network),
-    /// All FIP votes on the network,
+    /// Set of addresses allowed to grant and revoke roles on that network
+    Admins(Network),
+    /// Set of all FIP votes on the network
     AllVotes(Network),
-    /// VoteChoice and FIP number to total storage amount
-    Storage(VoteOption, Network, u32),
-    /// The network the address belongs to
+    /// FIP number to a hash of the total storage amount behind each choice
+    Storage(Network, FipNumber),
+    /// The network the address last registered on, only read when migrating to version 4
     Network(Address),
+    /// Set of all registered voters on the network
+    RegisteredVoters(Network),
+    /// FIP number to the registered storage that did not vote
+    Silence(FipNumber, Network),
+    /// FIP number to the vote title and description in every language
+    Metadata(FipNumber, Network),
+    /// FIP number to the results stored once the vote concluded
+    FinalResults(FipNumber, Network),
+    /// Network and voter address to the class the voter registered as
+    VoterClass(Network, Address),
+    /// FIP number to a hash of the total token holder balance in attoFIL behind each choice
+    Fil(Network, FipNumber),
+    /// FIP number to the round the vote is in, unset for the first round
+    Round(FipNumber, Network),
+    /// FIP number to the results and ballots of the round before the current one
+    PreviousRound(FipNumber, Network),
+    /// Signature hash to the receipt of the ballot it was submitted with
+    Receipt([u8; 32]),
+    /// Version of the key layout the data is stored in
+    SchemaVersion,
+    /// FIP number to the transaction that published the results on-chain
+    Published(FipNumber, Network),
+    /// FIP number to the options of a vote that is not Yay, Nay or Abstain
+    Options(FipNumber, Network),
+    /// FIP number to the quorum and pass threshold of the vote
+    Quorum(FipNumber, Network),
+    /// FIP number to how the power behind the ballots is weighted, unset for raw byte power
+    Weighting(FipNumber, Network),
+    /// FIP number to the raw byte power a ballot needs to be counted, unset for votes counting every ballot
+    MinPower(FipNumber, Network),
+    /// FIP number to how the current round was ended early, unset if it ran its full length
+    Ended(FipNumber, Network),
+    /// FIP number to the seconds the current round runs past the configured vote length
+    Extension(FipNumber, Network),
+    /// Network and voter address to a hash of the ballot the voter last cast on each FIP
+    History(Network, Address),
+    /// Network and voter address to the timestamp the registration expires at, unset if it never does
+    Expiry(Network, Address),
+    /// Network and voter address to the worker or multisig address that signed the registration
+    Worker(Network, Address),
+    /// Issued nonce that has not been signed into a message yet, to the timestamp it expires at
+    Nonce([u8; 16]),
+    /// List of registrations waiting for their storage providers to be verified
+    RegistrationQueue,
+    /// Voter address to where their last registration is at
+    RegistrationStatus(Address),
+    /// Stream of every change made to the database, see [`crate::events`]
+    Events,
+    /// FIP number to the token of the instance changing the vote, see [`Redis::with_vote_lock`]
+    Lock(FipNumber, Network),
+    /// FIP number to its title, status and discussion link, synced from the FIPs repository
+    FipInfo(FipNumber),
+    /// FIP number to the set of announcements sent to Slack and Discord for the current round
+    Announced(FipNumber, Network),
+    /// Counter bumped by every change to the database, tagging the results served from it
+    ResultsVersion,
+    /// Network and voter address to a hash of the delegations offered to the voter by worker address
+    Offers(Network, Address),
+    /// Network and storage provider id to the set of voters it is delegated to
+    Delegators(Network, u32),
+}
+
+/// Opens connections to the primary and the read replica the server was started with
+#[derive(Clone)]
+pub struct RedisFactory {
+    primary: Url,
+    replica: Option<Url>,
+    clock: Arc<dyn Clock>,
+    /// Seconds ballots are taken for after a vote ends
+    grace_period: u64,
+}
+
+impl RedisFactory {
+    pub fn new(primary: Url, replica: Option<Url>) -> Self {
+        Self {
+            primary,
+            replica,
+            clock: Arc::new(SystemClock),
+            grace_period: 0,
+        }
+    }
+
+    pub fn from_args(args: &Args) -> Self {
+        let mut factory = Self::new(args.redis_path(), args.redis_replica_path());
+        factory.set_grace_period(args.vote_grace_period());
+
+        factory
+    }
+
+    /// Times every connection it opens with `clock` instead of the system time
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Takes ballots for `secs` seconds after a vote ends on every connection it opens
+    pub fn set_grace_period(&mut self, secs: u64) {
+        self.grace_period = secs;
+    }
+}
+
+impl StoreFactory for RedisFactory {
+    type Store = Redis;
+
+    fn open(&self) -> Result<Redis, StoreError> {
+        let mut redis = Redis::new(self.primary.clone())?;
+        redis.set_clock(self.clock.clone());
+        redis.set_grace_period(self.grace_period);
+
+        Ok(redis)
+    }
+
+    fn reader(&self) -> Result<Redis, StoreError> {
+        let mut redis = Redis::reader(self.primary.clone(), self.replica.clone())?;
+        redis.set_clock(self.clock.clone());
+        redis.set_grace_period(self.grace_period);
+
+        Ok(redis)
+    }
+
+    fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
 }
 
 impl Redis {
@@ -46,765 +267,6466 @@ impl Redis {
         let client = redis::Client::open(path.into())?;
         let con = client.get_connection()?;
 
-        Ok(Self { con })
+        Ok(Self {
+            con,
+            power: Arc::new(ChainPower),
+            clock: Arc::new(SystemClock),
+            grace_period: 0,
+            read_only: false,
+        })
     }
 
-    /*~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~/
-    /                                 INITIALIZATION                                 /
-    /~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~*/
+    /// Opens a connection to a read replica of the database
+    ///
+    /// Results and reports that are stored the first time they are read are
+    /// worked out without being stored on a replica, leaving that to the primary
+    pub fn replica(path: impl Into<Url>) -> Result<Redis, RedisError> {
+        let mut redis = Self::new(path)?;
+        redis.read_only = true;
 
-    /// Starts a new vote in the database but does not add any votes into the database
-    pub fn start_vote(
-        &mut self,
-        fip_number: impl Into<u32>,
-        signer: Address,
-        ntw: Network,
-    ) -> Result<(), RedisError> {
-        let num = fip_number.into();
+        Ok(redis)
+    }
 
-        // Check if signer is authorized to start a vote
-        if !self.is_authorized_starter(signer, ntw)? && !authorized_voters().contains(&signer) {
-            return Err(RedisError::from((
-                redis::ErrorKind::TypeError,
-                "Signer is not authorized to start a vote",
-            )));
+    /// Opens a connection for read only requests, to the replica when one is configured
+    pub fn reader(primary: impl Into<Url>, replica: Option<Url>) -> Result<Redis, RedisError> {
+        match replica {
+            Some(replica) => Self::replica(replica),
+            None => Self::new(primary),
         }
+    }
 
-        // Check if vote already exists
-        if self.vote_exists(ntw, num)? {
-            return Err(RedisError::from((
-                redis::ErrorKind::TypeError,
-                "Vote already exists",
-            )));
-        }
+    /// Weights ballots with the power from `power` instead of the chain
+    pub fn set_power_provider(&mut self, power: impl StoragePowerProvider + 'static) {
+        self.power = Arc::new(power);
+    }
 
-        self.register_vote_to_all_votes(num, ntw)?;
+    /// Takes ballots for `secs` seconds after a vote ends, none are taken by default
+    pub fn set_grace_period(&mut self, secs: u64) {
+        self.grace_period = secs;
+    }
 
-        // Set a map of FIP to timestamp of vote start
-        let time_key = LookupKey::Timestamp(num, ntw).to_bytes();
-        let timestamp = time::SystemTime::now()
-            .duration_since(time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        // After this is set then the vote is considered started
-        self.con.set::<Vec<u8>, u64, ()>(time_key, timestamp)?;
+    /// Writes the changes queued on `pipe` in one MULTI/EXEC with their event,
+    /// so a change is never applied without being logged or logged without
+    /// being applied
+    fn write_logged(&mut self, mut pipe: redis::Pipeline, event: Event) -> Result<(), RedisError> {
+        pipe.atomic();
+        queue_event(&mut pipe, self.clock.now(), &event);
+        pipe.query::<()>(&mut self.con)?;
+
+        invalidate_own(&event);
 
         Ok(())
     }
 
-    /// Registers a voter in the database
+    /// Runs `f` while holding the lock of a vote
     ///
-    /// * Creates a lookup from voters address to their respective network
-    /// * Creates a lookup from voters address to their authorized storage providers
-    pub fn register_voter(
+    /// Replicas sharing one database take the lock before reading and writing
+    /// the state of a vote, so a vote is concluded once per round and a change
+    /// made by one replica is not lost to another. The lock is taken with
+    /// `SET NX PX` and expires on its own if the replica holding it stops.
+    /// Waiting for it sleeps on the runtime, so the worker keeps serving other
+    /// requests meanwhile
+    async fn with_vote_lock<T>(
         &mut self,
-        voter: Address,
+        fip_number: FipNumber,
         ntw: Network,
-        sp_ids: Vec<u32>,
-    ) -> Result<(), RedisError> {
-        let key = LookupKey::Voter(ntw, voter).to_bytes();
-
-        self.set_network(ntw, voter)?;
-
-        self.con.set::<Vec<u8>, Vec<u32>, ()>(key, sp_ids)?;
-
-        Ok(())
-    }
+        f: impl FnOnce(&mut Self) -> Result<T, RedisError>,
+    ) -> Result<T, RedisError> {
+        let key = LookupKey::Lock(fip_number, ntw).to_key();
+        let token = hex::encode(ethers::core::rand::random::<[u8; 16]>());
+
+        let started = Instant::now();
+        loop {
+            let locked: Option<String> = redis::cmd("SET")
+                .arg(&key)
+                .arg(&token)
+                .arg("NX")
+                .arg("PX")
+                .arg(LOCK_TTL)
+                .query(&mut self.con)?;
+            if locked.is_some() {
+                break;
+            }
 
-    pub fn unregister_voter(&mut self, voter: Address, ntw: Network) -> Result<(), RedisError> {
-        let key = LookupKey::Voter(ntw, voter).to_bytes();
+            if started.elapsed() >= Duration::from_millis(LOCK_WAIT) {
+                return Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Vote is being changed by another instance",
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(LOCK_RETRY)).await;
+        }
 
-        // Remove the voter from the network lookup
-        self.remove_network(voter)?;
+        let res = f(self);
 
-        self.con.del::<Vec<u8>, ()>(key)?;
+        let unlocked = redis::Script::new(UNLOCK_SCRIPT)
+            .key(&key)
+            .arg(&token)
+            .invoke::<u64>(&mut self.con);
 
-        Ok(())
+        let res = res?;
+        unlocked?;
+        Ok(res)
     }
 
-    pub fn register_voter_starter(
+    /// Results of a concluded round, tallied and stored if they weren't yet
+    ///
+    /// Results are stored as the round concludes, by [`crate::conclusions`]
+    /// or an early end, reads only store them when they get there first.
+    /// Must be called with the lock of the vote held
+    fn concluded_results(
         &mut self,
-        voter: Address,
+        fip_number: FipNumber,
         ntw: Network,
-    ) -> Result<(), RedisError> {
-        let key = LookupKey::VoteStarters(ntw).to_bytes();
+    ) -> Result<VoteResults, RedisError> {
+        let key = LookupKey::FinalResults(fip_number, ntw).to_key();
 
-        let mut current_voters = self.voter_starters(ntw)?;
+        if let Some(results) = self.con.get::<String, Option<String>>(key.clone())? {
+            return Ok(serde_json::from_str(results.as_str()).unwrap());
+        }
 
-        current_voters.push(voter);
+        let results = self.final_results(fip_number, ntw)?;
 
-        current_voters.sort();
-        current_voters.dedup();
+        let str_results = serde_json::to_string(&results).unwrap();
+        self.con.set::<String, String, ()>(key, str_results)?;
 
-        let new_bytes = current_voters
-            .into_iter()
-            .flat_map(|v| v.as_fixed_bytes().to_vec())
-            .collect::<Vec<u8>>();
+        Ok(results)
+    }
 
-        self.con.set::<Vec<u8>, Vec<u8>, ()>(key, new_bytes)?;
+    /// Number of voters registered as the vote concluded that didn't vote, and
+    /// the storage providers delegated to them, `None` once the report is stored
+    ///
+    /// Must be called with the lock of the vote held, so registrations made
+    /// after the vote concluded are left out
+    fn silent_delegates(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Option<(u64, Vec<u32>)>, RedisError> {
+        let key = LookupKey::Silence(fip_number, ntw).to_key();
+        if self.con.exists::<String, bool>(key)? {
+            return Ok(None);
+        }
 
-        Ok(())
-    }
+        let voted = self
+            .votes(fip_number, ntw)?
+            .iter()
+            .map(|v| v.voter())
+            .collect::<Vec<Address>>();
+
+        let mut silent_voters = 0;
+        let mut sp_ids = Vec::new();
+        for voter in self.registered_voters(ntw)? {
+            if voted.contains(&voter) {
+                continue;
+            }
 
-    /// Creates a lookup from the voter to the network they are voting on
-    fn set_network(&mut self, ntw: Network, voter: Address) -> Result<(), RedisError> {
-        let key: Vec<u8> = LookupKey::Network(voter).to_bytes();
-        self.con.set::<Vec<u8>, Network, ()>(key, ntw)?;
-        Ok(())
+            silent_voters += 1;
+            sp_ids.extend(self.voter_delegates(voter, ntw)?);
+        }
+
+        Ok(Some((silent_voters, sp_ids)))
     }
 
-    /*~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~/
-    /                                     GETTERS                                    /
-    /~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~*/
+    /// Weighs the storage providers of the silent voters of `round` and stores
+    /// the report, unless the next round started or another instance stored it first
+    async fn store_silence_report(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        round: u64,
+        silent_voters: u64,
+        sp_ids: Vec<u32>,
+    ) -> Result<(), RedisError> {
+        let powers = self.ballot_storage(&sp_ids, ntw).await?;
 
-    pub fn vote_exists(&mut self, ntw: Network, fip: u32) -> Result<bool, RedisError> {
-        let key = LookupKey::Timestamp(fip, ntw).to_bytes();
+        let report = SilenceReport::new(silent_voters, sp_ids.iter().map(|sp_id| powers[sp_id]));
+        let str_report = serde_json::to_string(&report).unwrap();
 
-        self.con.exists(key)
+        self.with_vote_lock(fip_number, ntw, |redis| {
+            if redis.vote_round(fip_number, ntw)? != round {
+                return Ok(());
+            }
+            let key = LookupKey::Silence(fip_number, ntw).to_key();
+            redis.con.set_nx::<String, String, ()>(key, str_report)
+        })
+        .await
     }
 
-    pub fn is_authorized_starter(
+    /// Tally of a vote that hasn't concluded, read through the hot cache
+    fn running_tally(
         &mut self,
-        voter: Address,
+        fip_number: FipNumber,
         ntw: Network,
-    ) -> Result<bool, RedisError> {
-        let voters = self.voter_starters(ntw)?;
+    ) -> Result<VoteResults, RedisError> {
+        if let Some(results) = hot_cache::tally(fip_number, ntw) {
+            return Ok(results);
+        }
 
-        Ok(voters.contains(&voter))
+        let generation = hot_cache::generation();
+        let results = self.tally_votes(fip_number, ntw, None)?;
+        hot_cache::insert_tally(fip_number, ntw, results.clone(), generation);
+
+        Ok(results)
     }
 
-    pub fn is_registered(&mut self, voter: Address, ntw: Network) -> bool {
-        let key = LookupKey::Voter(ntw, voter).to_bytes();
+    /// Tallies a concluded round against its quorum, with how it changed from the round before
+    fn final_results(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<VoteResults, RedisError> {
+        let quorum = self.vote_quorum(fip_number, ntw)?;
+        let mut results = self.tally_votes(fip_number, ntw, quorum)?;
 
-        match self.con.get::<Vec<u8>, Vec<u32>>(key) {
-            Ok(sp_ids) => !sp_ids.is_empty(),
-            Err(_) => false,
+        if let Some(previous) = self.previous_round(fip_number, ntw)? {
+            let ballots = self.votes(fip_number, ntw)?;
+            results.delta = Some(RoundDelta::new(&previous, &results, &ballots));
         }
+
+        Ok(results)
     }
 
-    /// Returns a json blob of the vote results for the FIP number
+    /// Writes the ballots together, either all of them are recorded or none are
     ///
-    pub fn vote_results(
+    /// The ballots and tallies are read and written in one transaction that is
+    /// retried whenever a concurrent submission touches them first. A ballot
+    /// whose receipt was already written by a concurrent retry is skipped
+    fn record_ballots(
         &mut self,
-        fip_number: impl Into<u32>,
-        ntw: Network,
-    ) -> Result<VoteResults, RedisError> {
-        let mut yay = 0;
-        let mut nay = 0;
-        let mut abstain = 0;
-
-        let num = fip_number.into();
+        ballots: Vec<(RecordedBallot, Option<VoteReceipt>)>,
+        allow_revote: bool,
+    ) -> Result<(), RedisError> {
+        let mut writes = Vec::with_capacity(ballots.len());
+        for (ballot, receipt) in ballots {
+            let round = self.vote_round(ballot.fip_number, ballot.ntw)?;
+            writes.push(BallotWrite::new(ballot, receipt, round));
+        }
 
-        let votes = self.votes(num, ntw)?;
+        let watched: Vec<String> = writes.iter().flat_map(BallotWrite::watched).collect();
+        let now = self.clock.now();
+
+        let res = redis::transaction(&mut self.con, &watched, |con, pipe| {
+            let mut written = Vec::with_capacity(writes.len());
+            for write in &writes {
+                let queued = write.queue(con, pipe, allow_revote)?;
+                if queued {
+                    queue_event(pipe, now, &write.event(allow_revote));
+                }
+                written.push(queued);
+            }
 
-        for vote in votes {
-            match vote.choice() {
-                VoteOption::Yay => yay += 1,
-                VoteOption::Nay => nay += 1,
-                VoteOption::Abstain => abstain += 1,
+            // Nothing is written when a concurrent retry landed first
+            if !written.contains(&true) {
+                return Ok(Some(written));
             }
+
+            pipe.query::<Option<()>>(con)
+                .map(|res| res.map(|()| written.clone()))
+        });
+
+        // A rejected ballot leaves the keys watched on the connection
+        if res.is_err() {
+            redis::cmd("UNWATCH").query::<()>(&mut self.con)?;
         }
 
-        let results = VoteResults {
-            yay,
-            nay,
-            abstain,
-            yay_storage_size: self.get_storage(num, VoteOption::Yay, ntw)?,
-            nay_storage_size: self.get_storage(num, VoteOption::Nay, ntw)?,
-            abstain_storage_size: self.get_storage(num, VoteOption::Abstain, ntw)?,
-        };
+        for (write, written) in writes.iter().zip(res?) {
+            if written {
+                invalidate_own(&write.event(allow_revote));
+            }
+        }
 
-        Ok(results)
+        Ok(())
     }
 
-    pub fn vote_status(
+    /// Where the vote is at `now`, the one place ballots and the conclusion
+    /// of a vote are timed from so they never disagree
+    fn vote_status_at(
         &mut self,
-        fip_number: impl Into<u32>,
-        vote_length: impl Into<u64>,
+        fip_number: FipNumber,
+        vote_length: u64,
         ntw: Network,
+        now: u64,
     ) -> Result<VoteStatus, RedisError> {
-        let num = fip_number.into();
-
         // Check if the FIP number has a timestamp
-        if !self.vote_exists(ntw, num)? {
+        if !self.vote_exists(ntw, fip_number)? {
             return Ok(VoteStatus::DoesNotExist);
         }
 
-        let vote_length = vote_length.into();
+        match self.vote_ending(fip_number, ntw)? {
+            Some(ended) if ended.ending == VoteEnding::Cancelled => {
+                return Ok(VoteStatus::Cancelled)
+            }
+            Some(ended) if ended.ending == VoteEnding::Unknown => return Ok(VoteStatus::Unknown),
+            Some(_) => return Ok(VoteStatus::Concluded),
+            None => (),
+        }
 
-        let timestamp: u64 = self.vote_start(num, ntw)?;
+        let vote_length = vote_length.saturating_add(self.vote_extension(fip_number, ntw)?);
 
-        let now = time::SystemTime::now()
-            .duration_since(time::UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_secs();
+        let timestamp: u64 = self.vote_start(fip_number, ntw)?;
 
-        if now < timestamp + vote_length {
-            let time_left = vote_length - (now - timestamp);
-            Ok(VoteStatus::InProgress(time_left))
-        } else {
-            Ok(VoteStatus::Concluded)
-        }
+        Ok(VoteStatus::at(
+            now,
+            timestamp,
+            vote_length,
+            self.grace_period,
+        ))
     }
 
-    pub fn active_votes(
+    /*~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~/
+    /                                 INITIALIZATION                                 /
+    /~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~*/
+
+    /// Queues the removal of the voters registration on the network
+    /// Unregisters the voter from every network of `ntws` at once
+    ///
+    /// The registrations are watched while their delegations are read, so a
+    /// registration landing in between retries the removal instead of being
+    /// partly wiped. `event` is logged in the same transaction
+    fn unregister_networks(
         &mut self,
-        ntw: Network,
-        vote_length: impl Into<u64>,
-    ) -> Result<Vec<u32>, RedisError> {
-        let all_votes = self.all_votes(ntw)?;
+        voter: Address,
+        ntws: &[Network],
+        event: Event,
+    ) -> Result<(), RedisError> {
+        let watched: Vec<String> = ntws
+            .iter()
+            .flat_map(|&ntw| registration_keys(voter, ntw))
+            .collect();
+        let now = self.clock.now();
+
+        let res = redis::transaction(&mut self.con, &watched, |con, pipe| {
+            let mut delegators = Vec::new();
+            for &ntw in ntws {
+                let sp_ids = read_delegates(con, voter, ntw)?;
+                delegators.extend(
+                    sp_ids
+                        .iter()
+                        .map(|sp_id| LookupKey::Delegators(ntw, *sp_id).to_key()),
+                );
+                queue_unregister_voter(pipe, voter, ntw, &sp_ids);
+            }
+            if !delegators.is_empty() {
+                redis::cmd("WATCH").arg(&delegators).query::<()>(con)?;
+            }
+            queue_event(pipe, now, &event);
 
-        let vote_length = vote_length.into();
+            pipe.query::<Option<()>>(con)
+        });
 
-        let mut active_votes = Vec::new();
-        for vote in all_votes {
-            let status = self.vote_status(vote, vote_length, ntw)?;
-            if let VoteStatus::InProgress(_) = status {
-                active_votes.push(vote);
-            }
+        // A failed read leaves the keys watched on the connection
+        if res.is_err() {
+            redis::cmd("UNWATCH").query::<()>(&mut self.con)?;
         }
-        Ok(active_votes)
-    }
-
-    pub fn concluded_votes(
-        &mut self,
-        ntw: Network,
-        vote_length: impl Into<u64>,
-    ) -> Result<Vec<u32>, RedisError> {
-        let all_votes = self.all_votes(ntw)?;
+        res?;
 
-        let vote_length = vote_length.into();
+        invalidate_own(&event);
 
-        let mut concluded_votes = Vec::new();
-        for vote in all_votes {
-            let status = self.vote_status(vote, vote_length, ntw)?;
-            if let VoteStatus::Concluded = status {
-                concluded_votes.push(vote);
-            }
-        }
-        Ok(concluded_votes)
+        Ok(())
     }
 
-    pub fn voter_delegates(
+    /*~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~/
+    /                                     GETTERS                                    /
+    /~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~*/
+
+    /// Counts the ballots and storage behind each choice, judged against
+    /// `quorum` when it is given
+    fn tally_votes(
         &mut self,
-        voter: Address,
+        fip_number: FipNumber,
         ntw: Network,
-    ) -> Result<Vec<u32>, RedisError> {
-        let key = LookupKey::Voter(ntw, voter).to_bytes();
-        let delegates: Vec<u32> = match self.con.get::<Vec<u8>, Vec<u32>>(key) {
-            Ok(d) => d,
-            Err(e) => match e.kind() {
-                redis::ErrorKind::TypeError => Vec::new(),
-                _ => return Err(e),
-            },
-        };
-        Ok(delegates)
-    }
+        quorum: Option<QuorumRules>,
+    ) -> Result<VoteResults, RedisError> {
+        let votes = self.votes(fip_number, ntw)?;
+        let power = self.power_map(fip_number, ntw)?;
 
-    pub fn voter_starters(&mut self, ntw: Network) -> Result<Vec<Address>, RedisError> {
-        let key = LookupKey::VoteStarters(ntw).to_bytes();
+        let rules = TallyRules {
+            weighting: self.vote_weighting(fip_number, ntw)?,
+            quorum,
+            min_power: self.vote_min_power(fip_number, ntw)?,
+        };
 
-        let bytes: Vec<u8> = self.con.get::<Vec<u8>, Vec<u8>>(key)?;
+        Ok(tally::compute(&votes, &power, &rules)?)
+    }
 
-        if bytes.len() % 20 != 0 {
-            return Err(RedisError::from((
-                redis::ErrorKind::TypeError,
-                "Error retrieving vote starters, invalid length",
-            )));
-        }
-        let addr_length = bytes.len() / 20;
+    /// The raw storage and FIL recorded behind each choice as ballots were cast
+    fn power_map(&mut self, fip_number: FipNumber, ntw: Network) -> Result<PowerMap, RedisError> {
+        let mut power = PowerMap {
+            yay: self.choice_power(fip_number, VoteOption::Yay, ntw)?,
+            nay: self.choice_power(fip_number, VoteOption::Nay, ntw)?,
+            abstain: self.choice_power(fip_number, VoteOption::Abstain, ntw)?,
+            options: BTreeMap::new(),
+        };
 
-        let mut starters: Vec<Address> = Vec::with_capacity(addr_length);
-        for i in 0..addr_length {
-            let start = i * 20;
-            let end = start + 20;
-            let addr = Address::from_slice(&bytes[start..end]);
-            starters.push(addr);
+        // Every option of the ballot is listed, even those nobody voted for
+        for name in self.vote_options(fip_number, ntw)? {
+            let option = self.choice_power(fip_number, VoteOption::Named(name.clone()), ntw)?;
+            power.options.insert(name, option);
         }
 
-        Ok(starters)
+        Ok(power)
     }
 
-    fn get_storage(
+    fn choice_power(
         &mut self,
-        fip_number: u32,
+        fip_number: FipNumber,
         vote: VoteOption,
         ntw: Network,
-    ) -> Result<u128, RedisError> {
-        let key = LookupKey::Storage(vote, ntw, fip_number).to_bytes();
-        let storage_bytes: Vec<u8> = self.con.get::<Vec<u8>, Vec<u8>>(key)?;
-        if storage_bytes.is_empty() {
-            return Ok(0);
-        }
-        if storage_bytes.len() != 16 {
-            return Err(RedisError::from((
-                redis::ErrorKind::TypeError,
-                "Error retrieving storage size",
-            )));
-        }
-        let storage = u128::from_be_bytes(storage_bytes.try_into().unwrap());
-        Ok(storage)
+    ) -> Result<ChoicePower, RedisError> {
+        Ok(ChoicePower {
+            storage_size: self.get_storage(fip_number, vote.clone(), ntw)?,
+            fil: self.get_fil(fip_number, vote, ntw)?,
+        })
     }
 
-    fn vote_start(&mut self, fip_number: impl Into<u32>, ntw: Network) -> Result<u64, RedisError> {
-        let key = LookupKey::Timestamp(fip_number.into(), ntw).to_bytes();
-        let timestamp: u64 = self.con.get::<Vec<u8>, u64>(key)?;
-        Ok(timestamp)
+    fn get_fil(
+        &mut self,
+        fip_number: FipNumber,
+        vote: VoteOption,
+        ntw: Network,
+    ) -> Result<u128, RedisError> {
+        let key = LookupKey::Fil(ntw, fip_number).to_key();
+        read_tally(&mut self.con, &key, &vote)
     }
 
-    fn votes(&mut self, fip_number: impl Into<u32>, ntw: Network) -> Result<Vec<Vote>, RedisError> {
-        let key = LookupKey::Votes(fip_number.into(), ntw).to_bytes();
-        let votes: Vec<Vote> = match self.con.get::<Vec<u8>, String>(key) {
-            Ok(v) => serde_json::from_str(v.as_str()).unwrap(),
-            Err(e) => match e.kind() {
-                redis::ErrorKind::TypeError => Vec::new(),
-                _ => return Err(e),
-            },
-        };
-        Ok(votes)
+    fn get_storage(
+        &mut self,
+        fip_number: FipNumber,
+        vote: VoteOption,
+        ntw: Network,
+    ) -> Result<u128, RedisError> {
+        let key = LookupKey::Storage(ntw, fip_number).to_key();
+        read_tally(&mut self.con, &key, &vote)
     }
 
-    pub fn network(&mut self, voter: Address) -> Result<Network, RedisError> {
-        let key = LookupKey::Network(voter).to_bytes();
-        let ntw: Network = self.con.get::<Vec<u8>, Network>(key)?;
-        Ok(ntw)
-    }
+    /// Reads a set of addresses in ascending order
+    fn address_set(&mut self, key: String) -> Result<Vec<Address>, RedisError> {
+        let members: Vec<Vec<u8>> = self.con.smembers(key)?;
 
-    pub fn all_votes(&mut self, ntw: Network) -> Result<Vec<u32>, RedisError> {
-        let key = LookupKey::AllVotes(ntw).to_bytes();
+        let mut addresses = Vec::with_capacity(members.len());
+        for bytes in members {
+            if bytes.len() != 20 {
+                return Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Error retrieving addresses, invalid length",
+                )));
+            }
+            addresses.push(Address::from_slice(&bytes));
+        }
+        addresses.sort();
 
-        let votes: Vec<u32> = match self.con.get::<Vec<u8>, String>(key) {
-            Ok(v) => serde_json::from_str(v.as_str()).unwrap(),
-            Err(e) => match e.kind() {
-                redis::ErrorKind::TypeError => Vec::new(),
-                _ => return Err(e),
-            },
-        };
-        Ok(votes)
+        Ok(addresses)
     }
 
     /*~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~/
     /                                     SETTERS                                    /
     /~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~*/
 
-    pub async fn add_vote<T>(
+    async fn insert_vote(
         &mut self,
-        fip_number: T,
+        fip_number: FipNumber,
         vote: Vote,
         voter: Address,
-        vote_length: impl Into<u64>,
-    ) -> Result<(), RedisError>
-    where
-        T: Into<u32>,
-    {
-        let num: u32 = fip_number.into();
-        let ntw = self.network(voter)?;
+        vote_length: u64,
+        allow_revote: bool,
+        receipt: Option<&VoteReceipt>,
+    ) -> Result<(), RedisError> {
+        let ballot = self
+            .prepare_ballot(fip_number, vote, voter, vote_length.into(), allow_revote)
+            .await?;
 
-        // If the vote is not active, throw an error
-        if !self.is_vote_active(num, ntw, vote_length)? {
+        self.record_ballot(ballot, allow_revote, receipt)
+    }
+
+    /// Checks that the ballot can be cast and weighs it with the power of the
+    /// voter, without writing anything
+    async fn prepare_ballot(
+        &mut self,
+        fip_number: FipNumber,
+        vote: Vote,
+        voter: Address,
+        vote_length: u64,
+        allow_revote: bool,
+    ) -> Result<RecordedBallot, RedisError> {
+        // If the signed vote is for a different FIP, throw an error
+        if vote.fip() != fip_number {
             return Err(RedisError::from((
                 redis::ErrorKind::TypeError,
-                "Vote is not active",
+                "Vote does not match requested FIP",
             )));
         }
 
-        // Fetch the storage provider Id's that the voter is authorized for
-        let authorized = self.voter_delegates(voter, ntw)?;
-
-        // If the voter is not authorized for any storage providers, throw an error
-        if authorized.is_empty() {
+        // Only options this version can tally are accepted
+        if let VoteOption::Unknown(_) = vote.choice() {
             return Err(RedisError::from((
                 redis::ErrorKind::TypeError,
-                "Voter is not authorized for any storage providers",
+                "Unsupported vote option",
             )));
         }
 
-        let key = LookupKey::Votes(num, ntw).to_bytes();
+        let ntw = self.ballot_network(voter, fip_number, vote.typed_chain_id(), vote_length)?;
 
-        let mut votes = self.votes(num, ntw)?;
-
-        // If this vote is a duplicate throw an error
-        if votes.contains(&vote) {
+        if self.is_registration_expired(voter, ntw)? {
             return Err(RedisError::from((
                 redis::ErrorKind::TypeError,
-                "Vote already exists",
+                "Voter registration has expired",
             )));
         }
 
-        // Add the storage providers power to their vote choice for the respective FIP
-        for sp_id in authorized {
-            self.add_storage(sp_id, ntw, vote.choice(), num).await?;
+        // Votes started with their own options only accept those
+        let options = self.vote_options(fip_number, ntw)?;
+        let on_ballot = match vote.choice() {
+            VoteOption::Named(name) => options.contains(&name),
+            _ => options.is_empty(),
+        };
+        if !on_ballot {
+            return Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Vote option is not on the ballot",
+            )));
         }
 
-        // Add the vote to the list of votes
-        votes.push(vote);
-        let votes = serde_json::to_string(&votes).unwrap();
-        self.con.set::<Vec<u8>, String, ()>(key.clone(), votes)?;
+        // If the vote is not active, throw an error
+        if !self.is_open(fip_number, vote_length, ntw, self.clock.now())? {
+            return Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Vote is not active",
+            )));
+        }
 
-        Ok(())
-    }
+        // Fetch the storage provider Id's that the voter is authorized for
+        let class = self.voter_class(voter, ntw)?;
+        let authorized = match class {
+            VoterClass::StorageProvider => self.voter_delegates(voter, ntw)?,
+            _ => Vec::new(),
+        };
 
-    fn is_vote_active(
+        // If a storage provider voter is not authorized for any storage providers, throw an error
+        if class == VoterClass::StorageProvider && authorized.is_empty() {
+            return Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Voter is not authorized for any storage providers",
+            )));
+        }
+
+        // Fail before asking the chain, the check is repeated in the transaction
+        if !allow_revote && self.votes(fip_number, ntw)?.contains(&vote) {
+            return Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Vote already exists",
+            )));
+        }
+
+        // Everything is fetched from the chain before anything is written so a
+        // request cancelled while waiting on the chain leaves no partial state
+        let authorized = match self.voter_worker(voter, ntw)? {
+            Some(worker) if !authorized.is_empty() => {
+                ballot_delegates(voter, &authorized, &worker, ntw).await?
+            }
+            _ => authorized,
+        };
+        if class == VoterClass::StorageProvider && authorized.is_empty() {
+            return Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Worker key of every storage provider changed since registration",
+            )));
+        }
+
+        let powers = self.ballot_storage(&authorized, ntw).await?;
+        let storage = tally::checked_sum(powers.values().copied())?;
+        let fil = match class {
+            VoterClass::TokenHolder => ballot_fil(voter, ntw).await?,
+            _ => 0,
+        };
+
+        // Asking the chain may have taken the ballot past the close of the
+        // vote, it is judged by the time it is recorded at
+        let recorded_at = self.clock.now();
+        if !self.is_open(fip_number, vote_length, ntw, recorded_at)? {
+            return Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Vote is not active",
+            )));
+        }
+
+        // Kept with the ballot so the tally can be audited later
+        let mut vote = vote;
+        vote.record(recorded_at, class, powers, fil);
+
+        Ok(RecordedBallot {
+            fip_number,
+            ntw,
+            voter,
+            class,
+            vote,
+            storage,
+            fil,
+        })
+    }
+
+    /// Storage power of each storage provider a ballot is cast for
+    async fn ballot_storage(
+        &self,
+        sp_ids: &[u32],
+        ntw: Network,
+    ) -> Result<BTreeMap<u32, u128>, RedisError> {
+        match self.power.storage_amounts(sp_ids, ntw).await {
+            Ok(powers) => Ok(powers),
+            Err(_) => Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Error fetching storage amount",
+            ))),
+        }
+    }
+
+    /*~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~/
+    /                                   MIGRATIONS                                   /
+    /~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~*/
+
+    /// Upgrades data written by older versions to the current key layout
+    ///
+    /// Runs on startup before anything is served and returns the version the
+    /// data was stored in. Every step skips keys that are already upgraded so
+    /// an interrupted migration is picked up again on the next start
+    pub fn migrate(&mut self) -> Result<u64, RedisError> {
+        let version = self.schema_version()?;
+
+        if version < 1 {
+            self.migrate_native_structures()?;
+        }
+
+        if version < 2 {
+            self.migrate_string_keys()?;
+        }
+
+        if version < 3 {
+            self.migrate_voter_history()?;
+        }
+
+        if version < 4 {
+            self.migrate_network_lookup()?;
+        }
+
+        if version < 5 {
+            self.migrate_delegators()?;
+        }
+
+        if version < 6 {
+            self.migrate_ballot_classes()?;
+        }
+
+        if version < SCHEMA_VERSION {
+            let key = LookupKey::SchemaVersion.to_key();
+            self.con.set::<String, u64, ()>(key, SCHEMA_VERSION)?;
+        }
+
+        Ok(version)
+    }
+
+    /// Version of the key layout the data is stored in, 0 for an empty database
+    pub fn schema_version(&mut self) -> Result<u64, RedisError> {
+        let key = LookupKey::SchemaVersion.to_key();
+        match self.con.get::<String, Option<u64>>(key)? {
+            Some(version) => Ok(version),
+            // Versions before 2 kept the version under a binary key
+            None => Ok(self
+                .con
+                .get::<Vec<u8>, Option<u64>>(LookupKey::SchemaVersion.legacy_bytes())?
+                .unwrap_or(0)),
+        }
+    }
+
+    /// Version 1 moves serialized lists to native lists, sets and hashes
+    ///
+    /// Ballots become a list, the vote, vote starter and registered voter lists
+    /// become sets and the tallies of each vote become one hash per network
+    fn migrate_native_structures(&mut self) -> Result<(), RedisError> {
+        let mut legacy_tallies = Vec::new();
+
+        for ntw in [Network::Mainnet, Network::Testnet] {
+            let all_votes_key = LookupKey::AllVotes(ntw).legacy_bytes();
+            let legacy_all_votes = self.key_type(&all_votes_key)? == "string";
+
+            let fips: Vec<FipNumber> = if legacy_all_votes {
+                let str_votes: String = self.con.get(&all_votes_key)?;
+                decode_legacy(&str_votes)?
+            } else {
+                let fips: Vec<u32> = self.con.smembers(&all_votes_key)?;
+                fips.into_iter().filter_map(|f| f.try_into().ok()).collect()
+            };
+
+            for &fip in &fips {
+                self.migrate_vote(fip, ntw, &mut legacy_tallies)?;
+            }
+
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+
+            if legacy_all_votes {
+                pipe.del(&all_votes_key).ignore();
+                for fip in &fips {
+                    pipe.sadd(&all_votes_key, fip.get()).ignore();
+                }
+            }
+
+            let starters_key = LookupKey::VoteStarters(ntw).legacy_bytes();
+            if self.key_type(&starters_key)? == "string" {
+                let bytes: Vec<u8> = self.con.get(&starters_key)?;
+                pipe.del(&starters_key).ignore();
+                for starter in bytes.chunks_exact(20) {
+                    pipe.sadd(&starters_key, starter).ignore();
+                }
+            }
+
+            let voters_key = LookupKey::RegisteredVoters(ntw).legacy_bytes();
+            if self.key_type(&voters_key)? == "string" {
+                let str_voters: String = self.con.get(&voters_key)?;
+                let voters: Vec<Address> = decode_legacy(&str_voters)?;
+                pipe.del(&voters_key).ignore();
+                for voter in voters {
+                    pipe.sadd(&voters_key, voter.as_bytes()).ignore();
+                }
+            }
+
+            pipe.query::<()>(&mut self.con)?;
+        }
+
+        // Removed once every network is copied since mainnet abstain and
+        // testnet yay storage shared a key in the old layout
+        if !legacy_tallies.is_empty() {
+            self.con.del::<Vec<Vec<u8>>, ()>(legacy_tallies)?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves the ballots of a vote to a list and copies its tallies to hashes
+    ///
+    /// The old tally keys are collected to be removed by the caller
+    fn migrate_vote(
         &mut self,
-        fip_number: impl Into<u32>,
+        fip_number: FipNumber,
         ntw: Network,
-        vote_length: impl Into<u64>,
-    ) -> Result<bool, RedisError> {
-        let active_votes = self.active_votes(ntw, vote_length)?;
+        legacy_tallies: &mut Vec<Vec<u8>>,
+    ) -> Result<(), RedisError> {
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+
+        let votes_key = LookupKey::Votes(fip_number, ntw).legacy_bytes();
+        let votes: Vec<Vote> = if self.key_type(&votes_key)? == "string" {
+            let str_votes: String = self.con.get(&votes_key)?;
+            let votes: Vec<Vote> = decode_legacy(&str_votes)?;
+            pipe.del(&votes_key).ignore();
+            for vote in &votes {
+                pipe.rpush(&votes_key, serde_json::to_string(vote).unwrap())
+                    .ignore();
+            }
+            votes
+        } else {
+            let ballots: Vec<String> = self.con.lrange(&votes_key, 0, -1)?;
+            ballots
+                .iter()
+                .map(|v| decode_legacy(v))
+                .collect::<Result<_, _>>()?
+        };
+
+        for choice in [VoteOption::Yay, VoteOption::Nay, VoteOption::Abstain] {
+            // A choice nobody picked on this network has no tally, which keeps
+            // the storage key shared between networks out of the wrong one
+            if !votes.iter().any(|v| v.choice() == choice) {
+                continue;
+            }
+
+            let tallies = [
+                (
+                    legacy_tally_key(fip_number, ntw, &choice, false),
+                    LookupKey::Storage(ntw, fip_number),
+                ),
+                (
+                    legacy_tally_key(fip_number, ntw, &choice, true),
+                    LookupKey::Fil(ntw, fip_number),
+                ),
+            ];
+
+            for (legacy, current) in tallies {
+                if self.key_type(&legacy)? != "string" {
+                    continue;
+                }
+
+                let total: Vec<u8> = self.con.get(&legacy)?;
+                pipe.hset(current.legacy_bytes(), tally_field(&choice), total)
+                    .ignore();
+                legacy_tallies.push(legacy);
+            }
+        }
+
+        pipe.query::<()>(&mut self.con)
+    }
+
+    /// Version 2 moves every key from the binary layout to namespaced strings
+    ///
+    /// Keys are renamed in place so values are never decoded or rewritten
+    fn migrate_string_keys(&mut self) -> Result<(), RedisError> {
+        let keys: Vec<Vec<u8>> = self.con.scan()?.collect();
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+
+        for key in keys {
+            if key.starts_with(KEY_PREFIX.as_bytes()) {
+                continue;
+            }
+            if let Some(lookup) = LookupKey::from_legacy_bytes(&key) {
+                pipe.rename(key, lookup.to_key()).ignore();
+            }
+        }
+
+        pipe.query::<()>(&mut self.con)
+    }
+
+    /// Version 3 indexes the ballots already cast by voter
+    fn migrate_voter_history(&mut self) -> Result<(), RedisError> {
+        for ntw in [Network::Mainnet, Network::Testnet] {
+            for fip_number in self.all_votes(ntw)? {
+                let mut rounds = vec![(
+                    self.vote_round(fip_number, ntw)?,
+                    self.votes(fip_number, ntw)?,
+                )];
+                if let Some(previous) = self.previous_round(fip_number, ntw)? {
+                    rounds.push((previous.round, previous.ballots));
+                }
+
+                let mut pipe = redis::pipe();
+                pipe.atomic();
+
+                // The latest round comes first and is never overwritten
+                for (round, ballots) in rounds {
+                    for ballot in &ballots {
+                        pipe.hset_nx(
+                            LookupKey::History(ntw, ballot.voter()).to_key(),
+                            fip_number.get(),
+                            serde_json::to_string(&VoterBallot::recorded(
+                                fip_number, round, ballot,
+                            ))
+                            .unwrap(),
+                        )
+                        .ignore();
+                    }
+                }
+
+                pipe.query::<()>(&mut self.con)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Version 4 drops the lookup from a voter to a single network
+    ///
+    /// Registrations were already kept per network, the lookup only decided
+    /// which of them a ballot counted toward and is now worked out per ballot
+    fn migrate_network_lookup(&mut self) -> Result<(), RedisError> {
+        let keys: Vec<String> = self
+            .con
+            .scan_match(format!("{}network:*", KEY_PREFIX))?
+            .collect();
+
+        for key in keys {
+            self.con.del::<String, ()>(key)?;
+        }
+
+        Ok(())
+    }
+
+    /// Version 5 indexes the voters each storage provider is delegated to
+    fn migrate_delegators(&mut self) -> Result<(), RedisError> {
+        for ntw in Network::all() {
+            for voter in self.registered_voters(ntw)? {
+                let sp_ids = self.voter_delegates(voter, ntw)?;
+
+                let mut pipe = redis::pipe();
+                pipe.atomic();
+                queue_delegators(&mut pipe, voter, ntw, &[], &sp_ids);
+                pipe.query::<()>(&mut self.con)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Version 6 stamps ballots with the class of their voter
+    ///
+    /// Ballots were tallied by the class their voter had when the tally was
+    /// read, ballots without a class are read as cast by a storage provider so
+    /// only those of other classes are rewritten
+    fn migrate_ballot_classes(&mut self) -> Result<(), RedisError> {
+        for ntw in Network::all() {
+            for fip_number in self.all_votes(ntw)? {
+                let key = LookupKey::Votes(fip_number, ntw).to_key();
+                let ballots: Vec<String> = self.con.lrange(&key, 0, -1)?;
+
+                let mut pipe = redis::pipe();
+                pipe.atomic();
+                for (index, ballot) in ballots.iter().enumerate() {
+                    let stamped = serde_json::from_str::<serde_json::Value>(ballot)
+                        .map(|value| value.get("class").is_some())
+                        .unwrap_or(true);
+                    if stamped {
+                        continue;
+                    }
+
+                    let mut vote: Vote = match serde_json::from_str(ballot) {
+                        Ok(vote) => vote,
+                        Err(_) => continue,
+                    };
+                    let class = self.voter_class(vote.voter(), ntw)?;
+                    if class == VoterClass::StorageProvider {
+                        continue;
+                    }
+
+                    let (recorded_at, powers, fil) = (
+                        vote.recorded_at(),
+                        vote.storage_powers().clone(),
+                        vote.fil(),
+                    );
+                    vote.record(recorded_at, class, powers, fil);
+                    pipe.lset(&key, index as isize, serde_json::to_string(&vote).unwrap())
+                        .ignore();
+                }
+                pipe.query::<()>(&mut self.con)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The type of the value stored at the key, `none` when it is not set
+    fn key_type(&mut self, key: &[u8]) -> Result<String, RedisError> {
+        redis::cmd("TYPE").arg(key).query(&mut self.con)
+    }
+}
+
+impl Redis {
+    /*~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~/
+    /                                 INITIALIZATION                                 /
+    /~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~*/
+
+    pub async fn start_vote(
+        &mut self,
+        fip_number: FipNumber,
+        signer: Address,
+        ntw: Network,
+    ) -> Result<(), RedisError> {
+        self.schedule_vote(fip_number, signer, ntw, self.clock.now())
+            .await
+    }
+
+    pub async fn schedule_vote(
+        &mut self,
+        fip_number: FipNumber,
+        signer: Address,
+        ntw: Network,
+        opens_at: u64,
+    ) -> Result<(), RedisError> {
+        // Check if signer is authorized to start a vote
+        if !self.is_authorized_starter(signer, ntw)? && !authorized_voters().contains(&signer) {
+            return Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Signer is not authorized to start a vote",
+            )));
+        }
+
+        self.with_vote_lock(fip_number, ntw, |redis| {
+            // Check if vote already exists
+            if redis.vote_exists(ntw, fip_number)? {
+                return Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Vote already exists",
+                )));
+            }
+
+            let mut pipe = redis::pipe();
+            pipe.sadd(LookupKey::AllVotes(ntw).to_key(), fip_number.get())
+                .ignore()
+                // Set a map of FIP to timestamp of vote start, which is when
+                // it opens for ballots. After this is set then the vote is
+                // considered started
+                .set(LookupKey::Timestamp(fip_number, ntw).to_key(), opens_at)
+                .ignore();
+
+            let scheduled = (opens_at > redis.clock.now()).then_some(opens_at);
+            redis.write_logged(
+                pipe,
+                Event::VoteStarted {
+                    fip_number,
+                    ntw,
+                    signer,
+                    opens_at: scheduled,
+                },
+            )
+        })
+        .await
+    }
+
+    pub async fn start_round(
+        &mut self,
+        fip_number: FipNumber,
+        signer: Address,
+        ntw: Network,
+        round: u64,
+        vote_length: u64,
+    ) -> Result<(), RedisError> {
+        // Check if signer is authorized to start a vote
+        if !self.is_authorized_starter(signer, ntw)? && !authorized_voters().contains(&signer) {
+            return Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Signer is not authorized to start a vote",
+            )));
+        }
+
+        self.with_vote_lock(fip_number, ntw, |redis| {
+            if redis.vote_status(fip_number, vote_length, ntw)? != VoteStatus::Concluded {
+                return Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Only a concluded vote can start a new round",
+                )));
+            }
+
+            // Rejects replayed start messages for rounds that already ran
+            let current = redis.vote_round(fip_number, ntw)?;
+            if round != current + 1 {
+                return Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Round does not follow the current round",
+                )));
+            }
+
+            let previous = RoundSummary {
+                round: current,
+                results: redis.concluded_results(fip_number, ntw)?,
+                ballots: redis.votes(fip_number, ntw)?,
+            };
+
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+
+            pipe.del(LookupKey::Votes(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Silence(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::FinalResults(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Published(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Announced(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Ended(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Extension(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Storage(ntw, fip_number).to_key())
+                .ignore()
+                .del(LookupKey::Fil(ntw, fip_number).to_key())
+                .ignore()
+                .set(
+                    LookupKey::PreviousRound(fip_number, ntw).to_key(),
+                    serde_json::to_string(&previous).unwrap(),
+                )
+                .ignore()
+                .set(LookupKey::Round(fip_number, ntw).to_key(), round)
+                .ignore()
+                // After this is set then the new round is considered started
+                .set(
+                    LookupKey::Timestamp(fip_number, ntw).to_key(),
+                    redis.clock.now(),
+                )
+                .ignore();
+
+            redis.write_logged(
+                pipe,
+                Event::RoundStarted {
+                    fip_number,
+                    ntw,
+                    signer,
+                    round,
+                    vote_length,
+                },
+            )
+        })
+        .await
+    }
+
+    pub async fn end_vote(
+        &mut self,
+        fip_number: FipNumber,
+        signer: Address,
+        ntw: Network,
+        round: u64,
+        ending: VoteEnding,
+        vote_length: u64,
+    ) -> Result<(), RedisError> {
+        // Check if signer is authorized to start a vote
+        if !self.is_authorized_starter(signer, ntw)? && !authorized_voters().contains(&signer) {
+            return Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Signer is not authorized to end a vote",
+            )));
+        }
+
+        self.with_vote_lock(fip_number, ntw, |redis| {
+            // A scheduled vote has no ballots to conclude with, but can be called off
+            match redis.vote_status(fip_number, vote_length, ntw)? {
+                VoteStatus::InProgress(_) => (),
+                VoteStatus::Scheduled(_) if ending == VoteEnding::Cancelled => (),
+                _ => {
+                    return Err(RedisError::from((
+                        redis::ErrorKind::TypeError,
+                        "Only a vote in progress can be ended, or a scheduled one cancelled",
+                    )))
+                }
+            }
+
+            // Rejects replayed end messages of earlier rounds
+            if round != redis.vote_round(fip_number, ntw)? {
+                return Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Round is not the current round",
+                )));
+            }
+
+            let ended = EarlyEnd {
+                ending,
+                signer,
+                ended_at: redis.clock.now(),
+            };
+            redis.set_vote_ending(fip_number, ntw, Some(&ended))?;
+
+            // Concluded with the ballots cast so far, which are final from here
+            if ending == VoteEnding::Concluded {
+                redis.concluded_results(fip_number, ntw)?;
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn extend_vote(
+        &mut self,
+        fip_number: FipNumber,
+        signer: Address,
+        ntw: Network,
+        round: u64,
+        extension: u64,
+        vote_length: u64,
+    ) -> Result<(), RedisError> {
+        // Check if signer is authorized to start a vote
+        if !self.is_authorized_starter(signer, ntw)? && !authorized_voters().contains(&signer) {
+            return Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Signer is not authorized to extend a vote",
+            )));
+        }
+
+        self.with_vote_lock(fip_number, ntw, |redis| {
+            if !matches!(
+                redis.vote_status(fip_number, vote_length, ntw)?,
+                VoteStatus::InProgress(_)
+            ) {
+                return Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Only a vote in progress can be extended",
+                )));
+            }
+
+            // Rejects replayed extension messages of earlier rounds
+            if round != redis.vote_round(fip_number, ntw)? {
+                return Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Round is not the current round",
+                )));
+            }
+
+            if extension <= redis.vote_extension(fip_number, ntw)? {
+                return Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Extension is not longer than the current extension",
+                )));
+            }
+
+            redis.set_vote_extension(fip_number, ntw, extension)
+        })
+        .await
+    }
+
+    pub async fn import_vote(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        started_at: u64,
+        ballots: &[Vote],
+        results: &VoteResults,
+        metadata: &BTreeMap<String, VoteMetadata>,
+    ) -> Result<(), RedisError> {
+        self.with_vote_lock(fip_number, ntw, |redis| {
+            if redis.vote_exists(ntw, fip_number)? {
+                return Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Vote already exists",
+                )));
+            }
+
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+
+            pipe.sadd(LookupKey::AllVotes(ntw).to_key(), fip_number.get())
+                .ignore();
+
+            if !ballots.is_empty() {
+                let ballots: Vec<String> = ballots
+                    .iter()
+                    .map(|v| serde_json::to_string(v).unwrap())
+                    .collect();
+                pipe.rpush(LookupKey::Votes(fip_number, ntw).to_key(), ballots)
+                    .ignore();
+            }
+
+            // A voter history already holding the FIP came from a later round
+            for ballot in ballots {
+                pipe.hset_nx(
+                    LookupKey::History(ntw, ballot.voter()).to_key(),
+                    fip_number.get(),
+                    serde_json::to_string(&VoterBallot::recorded(fip_number, 1, ballot)).unwrap(),
+                )
+                .ignore();
+            }
+
+            let named = results.options.keys().cloned().map(VoteOption::Named);
+            for choice in [VoteOption::Yay, VoteOption::Nay, VoteOption::Abstain]
+                .into_iter()
+                .chain(named)
+            {
+                let storage = results.storage_size(&choice).to_be_bytes().to_vec();
+                let fil = results.fil(&choice).to_be_bytes().to_vec();
+                pipe.hset(
+                    LookupKey::Storage(ntw, fip_number).to_key(),
+                    tally_field(&choice),
+                    storage,
+                )
+                .ignore()
+                .hset(
+                    LookupKey::Fil(ntw, fip_number).to_key(),
+                    tally_field(&choice),
+                    fil,
+                )
+                .ignore();
+            }
+
+            if !metadata.is_empty() {
+                pipe.set(
+                    LookupKey::Metadata(fip_number, ntw).to_key(),
+                    serde_json::to_string(metadata).unwrap(),
+                )
+                .ignore();
+            }
+
+            pipe.set(LookupKey::Timestamp(fip_number, ntw).to_key(), started_at)
+                .ignore();
+
+            redis.write_logged(
+                pipe,
+                Event::VoteImported {
+                    fip_number,
+                    ntw,
+                    started_at,
+                    ballots: ballots.to_vec(),
+                    results: results.clone(),
+                    metadata: metadata.clone(),
+                },
+            )
+        })
+        .await
+    }
+
+    pub fn register_voter(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        sp_ids: Vec<u32>,
+    ) -> Result<(), RedisError> {
+        let previous = self.voter_delegates(voter, ntw)?;
+
+        let mut pipe = redis::pipe();
+        pipe.sadd(LookupKey::RegisteredVoters(ntw).to_key(), voter.as_bytes())
+            .ignore()
+            .set(LookupKey::Voter(ntw, voter).to_key(), sp_ids.clone())
+            .ignore()
+            .del(LookupKey::VoterClass(ntw, voter).to_key())
+            .ignore();
+        queue_delegators(&mut pipe, voter, ntw, &previous, &sp_ids);
+
+        self.write_logged(pipe, Event::VoterRegistered { voter, ntw, sp_ids })
+    }
+
+    pub fn register_voter_class(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        class: VoterClass,
+    ) -> Result<(), RedisError> {
+        if class == VoterClass::StorageProvider {
+            return Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Storage providers must register with a worker signature",
+            )));
+        }
+
+        if self.is_registered(voter, ntw) {
+            return Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Voter is already registered as a storage provider",
+            )));
+        }
+
+        let str_class = serde_json::to_string(&class).unwrap();
+
+        let mut pipe = redis::pipe();
+        pipe.sadd(LookupKey::RegisteredVoters(ntw).to_key(), voter.as_bytes())
+            .ignore()
+            .set(LookupKey::VoterClass(ntw, voter).to_key(), str_class)
+            .ignore();
+
+        self.write_logged(pipe, Event::VoterClassRegistered { voter, ntw, class })
+    }
+
+    pub fn unregister_voter(&mut self, voter: Address, ntw: Network) -> Result<(), RedisError> {
+        self.unregister_networks(voter, &[ntw], Event::VoterUnregistered { voter, ntw })
+    }
+
+    pub fn unregister_storage_providers(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        sp_ids: &[u32],
+    ) -> Result<(), RedisError> {
+        let watched = registration_keys(voter, ntw);
+        let event = Event::StorageProvidersUnregistered {
+            voter,
+            ntw,
+            sp_ids: sp_ids.to_vec(),
+        };
+        let now = self.clock.now();
+
+        let res = redis::transaction(&mut self.con, &watched, |con, pipe| {
+            let previous = read_delegates(con, voter, ntw)?;
+            let mut delegates = previous.clone();
+            delegates.retain(|id| !sp_ids.contains(id));
+
+            if delegates.is_empty() {
+                queue_unregister_voter(pipe, voter, ntw, &previous);
+            } else {
+                let key = LookupKey::Voter(ntw, voter).to_key();
+                pipe.set(key, delegates).ignore();
+                queue_delegators(pipe, voter, ntw, sp_ids, &[]);
+            }
+            queue_event(pipe, now, &event);
+
+            pipe.query::<Option<()>>(con)
+        });
+
+        // A failed read leaves the keys watched on the connection
+        if res.is_err() {
+            redis::cmd("UNWATCH").query::<()>(&mut self.con)?;
+        }
+        res?;
+
+        invalidate_own(&event);
+
+        Ok(())
+    }
+
+    pub fn unregister_voter_all(&mut self, voter: Address) -> Result<(), RedisError> {
+        self.unregister_networks(voter, &Network::all(), Event::VoterRemoved { voter })
+    }
+
+    pub fn register_voter_starter(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<(), RedisError> {
+        let mut pipe = redis::pipe();
+        pipe.sadd(LookupKey::VoteStarters(ntw).to_key(), voter.as_bytes())
+            .ignore();
+
+        self.write_logged(pipe, Event::StarterRegistered { voter, ntw })
+    }
+
+    pub fn register_admin(&mut self, voter: Address, ntw: Network) -> Result<(), RedisError> {
+        let mut pipe = redis::pipe();
+        pipe.sadd(LookupKey::Admins(ntw).to_key(), voter.as_bytes())
+            .ignore();
+
+        self.write_logged(pipe, Event::AdminRegistered { voter, ntw })
+    }
+
+    /*~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~/
+    /                                     GETTERS                                    /
+    /~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~*/
+
+    pub fn vote_exists(&mut self, ntw: Network, fip: FipNumber) -> Result<bool, RedisError> {
+        let key = LookupKey::Timestamp(fip, ntw).to_key();
+
+        self.con.exists(key)
+    }
+
+    pub fn is_authorized_starter(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<bool, RedisError> {
+        let key = LookupKey::VoteStarters(ntw).to_key();
+
+        self.con.sismember(key, voter.as_bytes())
+    }
+
+    pub fn is_admin(&mut self, voter: Address, ntw: Network) -> Result<bool, RedisError> {
+        let key = LookupKey::Admins(ntw).to_key();
+
+        self.con.sismember(key, voter.as_bytes())
+    }
+
+    pub fn is_registered(&mut self, voter: Address, ntw: Network) -> bool {
+        let key = LookupKey::Voter(ntw, voter).to_key();
+
+        match self.con.get::<String, Vec<u32>>(key) {
+            Ok(sp_ids) => !sp_ids.is_empty(),
+            Err(_) => false,
+        }
+    }
+
+    pub fn voter_worker(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<Option<String>, RedisError> {
+        let key = LookupKey::Worker(ntw, voter).to_key();
+        self.con.get(key)
+    }
+
+    pub fn set_voter_worker(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        worker_address: Option<&str>,
+    ) -> Result<(), RedisError> {
+        let key = LookupKey::Worker(ntw, voter).to_key();
+
+        let mut pipe = redis::pipe();
+        match worker_address {
+            Some(worker_address) => pipe.set(key, worker_address).ignore(),
+            None => pipe.del(key).ignore(),
+        };
+
+        self.write_logged(
+            pipe,
+            Event::WorkerChanged {
+                voter,
+                ntw,
+                worker_address: worker_address.map(str::to_string),
+            },
+        )
+    }
+
+    pub fn registration_expiry(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<Option<u64>, RedisError> {
+        let key = LookupKey::Expiry(ntw, voter).to_key();
+        self.con.get(key)
+    }
+
+    pub fn is_registration_expired(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<bool, RedisError> {
+        Ok(match self.registration_expiry(voter, ntw)? {
+            Some(expires_at) => expires_at <= self.clock.now(),
+            None => false,
+        })
+    }
+
+    pub fn set_registration_expiry(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        expires_at: Option<u64>,
+    ) -> Result<(), RedisError> {
+        let key = LookupKey::Expiry(ntw, voter).to_key();
+
+        let mut pipe = redis::pipe();
+        match expires_at {
+            Some(expires_at) => pipe.set(key, expires_at).ignore(),
+            None => pipe.del(key).ignore(),
+        };
+
+        self.write_logged(
+            pipe,
+            Event::ExpiryChanged {
+                voter,
+                ntw,
+                expires_at,
+            },
+        )
+    }
+
+    pub fn renew_registration(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        expires_at: Option<u64>,
+    ) -> Result<Option<u64>, RedisError> {
+        let voters_key = LookupKey::RegisteredVoters(ntw).to_key();
+        if !self
+            .con
+            .sismember::<String, &[u8], bool>(voters_key, voter.as_bytes())?
+        {
+            return Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Voter is not registered for this network",
+            )));
+        }
+
+        let expires_at = match (self.registration_expiry(voter, ntw)?, expires_at) {
+            (Some(current), Some(expires_at)) => Some(current.max(expires_at)),
+            (_, expires_at) => expires_at,
+        };
+        self.set_registration_expiry(voter, ntw, expires_at)?;
+
+        Ok(expires_at)
+    }
+
+    pub fn queue_registration(
+        &mut self,
+        pending: &PendingRegistration,
+    ) -> Result<RegistrationStatus, RedisError> {
+        let status = RegistrationStatus::Pending {
+            queued_at: self.clock.now(),
+        };
+        // Set first so the status is there by the time the registration is processed
+        self.set_registration_status(pending.address, &status)?;
+
+        let key = LookupKey::RegistrationQueue.to_key();
+        let pending = serde_json::to_string(pending).unwrap();
+        self.con.rpush::<String, String, ()>(key, pending)?;
+
+        Ok(status)
+    }
+
+    pub fn next_registration(&mut self) -> Result<Option<PendingRegistration>, RedisError> {
+        let key = LookupKey::RegistrationQueue.to_key();
+
+        match redis::cmd("LPOP")
+            .arg(key)
+            .query::<Option<String>>(&mut self.con)?
+        {
+            Some(pending) => match serde_json::from_str(pending.as_str()) {
+                Ok(pending) => Ok(Some(pending)),
+                Err(_) => Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Error decoding queued registration",
+                ))),
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub fn registration_status(
+        &mut self,
+        voter: Address,
+    ) -> Result<Option<RegistrationStatus>, RedisError> {
+        let key = LookupKey::RegistrationStatus(voter).to_key();
+
+        match self.con.get::<String, Option<String>>(key)? {
+            Some(status) => match serde_json::from_str(status.as_str()) {
+                Ok(status) => Ok(Some(status)),
+                Err(_) => Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Error decoding registration status",
+                ))),
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_registration_status(
+        &mut self,
+        voter: Address,
+        status: &RegistrationStatus,
+    ) -> Result<(), RedisError> {
+        let key = LookupKey::RegistrationStatus(voter).to_key();
+        let status = serde_json::to_string(status).unwrap();
+
+        self.con
+            .set_ex::<String, String, ()>(key, status, REGISTRATION_STATUS_TTL as usize)
+    }
+
+    pub fn offer_delegation(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        offer: &DelegationOffer,
+    ) -> Result<(), RedisError> {
+        let key = LookupKey::Offers(ntw, voter).to_key();
+        let value = serde_json::to_string(offer).unwrap();
+
+        let mut pipe = redis::pipe();
+        pipe.hset(key, &offer.worker_address, value).ignore();
+
+        self.write_logged(
+            pipe,
+            Event::DelegationOffered {
+                voter,
+                ntw,
+                offer: offer.clone(),
+            },
+        )
+    }
+
+    pub fn delegation_offers(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<Vec<DelegationOffer>, RedisError> {
+        let key = LookupKey::Offers(ntw, voter).to_key();
+
+        let mut offers = self
+            .con
+            .hvals::<String, Vec<String>>(key)?
+            .iter()
+            .map(|offer| serde_json::from_str::<DelegationOffer>(offer))
+            .collect::<Result<Vec<DelegationOffer>, _>>()
+            .map_err(|_| {
+                RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Error decoding delegation offer",
+                ))
+            })?;
+        offers.sort_by_key(|offer| offer.offered_at);
+
+        Ok(offers)
+    }
+
+    pub fn take_delegation_offer(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        worker_address: &str,
+    ) -> Result<Option<DelegationOffer>, RedisError> {
+        let key = LookupKey::Offers(ntw, voter).to_key();
+        let event = Event::DelegationTaken {
+            voter,
+            ntw,
+            worker_address: worker_address.to_string(),
+        };
+        let now = self.clock.now();
+
+        // Retried if the offer changes between reading and removing it, so
+        // two acceptances can't both take it
+        let res = redis::transaction(&mut self.con, &[key.clone()], |con, pipe| {
+            let offer: Option<String> = con.hget(&key, worker_address)?;
+            let offer = match offer {
+                Some(offer) => offer,
+                None => return Ok(Some(None)),
+            };
+
+            pipe.hdel(&key, worker_address).ignore();
+            queue_event(pipe, now, &event);
+
+            pipe.query::<Option<()>>(con)
+                .map(|res| res.map(|()| Some(offer.clone())))
+        });
+
+        // A failed read leaves the key watched on the connection
+        if res.is_err() {
+            redis::cmd("UNWATCH").query::<()>(&mut self.con)?;
+        }
+        let offer = match res? {
+            Some(offer) => offer,
+            None => return Ok(None),
+        };
+
+        invalidate_own(&event);
+
+        match serde_json::from_str(&offer) {
+            Ok(offer) => Ok(Some(offer)),
+            Err(_) => Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Error decoding delegation offer",
+            ))),
+        }
+    }
+
+    pub fn issue_nonce(&mut self, ttl: u64) -> Result<IssuedNonce, RedisError> {
+        let nonce: [u8; 16] = ethers::core::rand::random();
+        let expires_at = self.clock.now().saturating_add(ttl);
+
+        let key = LookupKey::Nonce(nonce).to_key();
+        self.con
+            .set_ex::<String, u64, ()>(key, expires_at, ttl as usize)?;
+
+        Ok(IssuedNonce {
+            nonce: hex::encode(nonce),
+            expires_at,
+        })
+    }
+
+    pub fn consume_nonce(&mut self, nonce: [u8; 16]) -> Result<bool, RedisError> {
+        let key = LookupKey::Nonce(nonce).to_key();
+        let removed: u64 = self.con.del(key)?;
+
+        Ok(removed == 1)
+    }
+
+    pub fn nonce_issued(&mut self, nonce: [u8; 16]) -> Result<bool, RedisError> {
+        let key = LookupKey::Nonce(nonce).to_key();
+
+        self.con.exists(key)
+    }
+
+    pub fn events(
+        &mut self,
+        after: Option<&str>,
+        count: usize,
+    ) -> Result<Vec<LoggedEvent>, RedisError> {
+        // Ids are `milliseconds-sequence`, the range starts just after the given one
+        let start = match after.and_then(|id| id.split_once('-')) {
+            Some((millis, seq)) => match seq.parse::<u64>() {
+                Ok(seq) => format!("{}-{}", millis, seq + 1),
+                Err(_) => {
+                    return Err(RedisError::from((
+                        redis::ErrorKind::TypeError,
+                        "Invalid event id",
+                    )))
+                }
+            },
+            None => "-".to_string(),
+        };
+
+        let entries: Vec<(String, Vec<String>)> = redis::cmd("XRANGE")
+            .arg(LookupKey::Events.to_key())
+            .arg(start)
+            .arg("+")
+            .arg("COUNT")
+            .arg(count)
+            .query(&mut self.con)?;
+
+        entries
+            .into_iter()
+            .map(|(id, fields)| {
+                let mut timestamp = None;
+                let mut event = None;
+                for field in fields.chunks(2) {
+                    match field {
+                        [name, value] if name == "timestamp" => timestamp = value.parse().ok(),
+                        [name, value] if name == "event" => {
+                            event = serde_json::from_str(value).ok()
+                        }
+                        _ => (),
+                    }
+                }
+
+                match (timestamp, event) {
+                    (Some(timestamp), Some(event)) => Ok(LoggedEvent {
+                        id,
+                        timestamp,
+                        event,
+                    }),
+                    _ => Err(RedisError::from((
+                        redis::ErrorKind::TypeError,
+                        "Invalid entry in the event log",
+                    ))),
+                }
+            })
+            .collect()
+    }
+
+    pub async fn vote_results(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: u64,
+        ntw: Network,
+    ) -> Result<VoteResults, RedisError> {
+        if self.vote_status(fip_number, vote_length, ntw)? != VoteStatus::Concluded {
+            return self.running_tally(fip_number, ntw);
+        }
+
+        // Stored results are read without the lock
+        let key = LookupKey::FinalResults(fip_number, ntw).to_key();
+        if let Some(results) = self.con.get::<String, Option<String>>(key)? {
+            return Ok(serde_json::from_str(results.as_str()).unwrap());
+        }
+
+        // A replica can't take the lock or store the results, the primary
+        // stores them once it concludes the vote
+        if self.read_only {
+            return self.final_results(fip_number, ntw);
+        }
+
+        self.with_vote_lock(fip_number, ntw, |redis| {
+            // Another instance may have started the next round in the meantime
+            if redis.vote_status(fip_number, vote_length, ntw)? != VoteStatus::Concluded {
+                return redis.tally_votes(fip_number, ntw, None);
+            }
+            redis.concluded_results(fip_number, ntw)
+        })
+        .await
+    }
+
+    pub fn vote_status(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: impl Into<u64>,
+        ntw: Network,
+    ) -> Result<VoteStatus, RedisError> {
+        let now = self.clock.now();
+        self.vote_status_at(fip_number, vote_length.into(), ntw, now)
+    }
+
+    pub fn is_open(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: impl Into<u64>,
+        ntw: Network,
+        now: u64,
+    ) -> Result<bool, RedisError> {
+        let status = self.vote_status_at(fip_number, vote_length.into(), ntw, now)?;
+
+        Ok(matches!(status, VoteStatus::InProgress(_)))
+    }
+
+    pub fn active_votes(
+        &mut self,
+        ntw: Network,
+        vote_length: impl Into<u64>,
+    ) -> Result<Vec<FipNumber>, RedisError> {
+        let all_votes = self.all_votes(ntw)?;
+
+        let vote_length = vote_length.into();
+
+        let mut active_votes = Vec::new();
+        for vote in all_votes {
+            let status = self.vote_status(vote, vote_length, ntw)?;
+            if let VoteStatus::InProgress(_) = status {
+                active_votes.push(vote);
+            }
+        }
+        Ok(active_votes)
+    }
+
+    pub fn concluded_votes(
+        &mut self,
+        ntw: Network,
+        vote_length: impl Into<u64>,
+    ) -> Result<Vec<FipNumber>, RedisError> {
+        let all_votes = self.all_votes(ntw)?;
+
+        let vote_length = vote_length.into();
+
+        let mut concluded_votes = Vec::new();
+        for vote in all_votes {
+            let status = self.vote_status(vote, vote_length, ntw)?;
+            if let VoteStatus::Concluded = status {
+                concluded_votes.push(vote);
+            }
+        }
+        Ok(concluded_votes)
+    }
+
+    pub fn stale_votes(
+        &mut self,
+        ntw: Network,
+        vote_length: impl Into<u64>,
+    ) -> Result<Vec<FipNumber>, RedisError> {
+        let vote_length = vote_length.into();
+
+        let mut stale_votes = Vec::new();
+        for vote in self.concluded_votes(ntw, vote_length)? {
+            let results = LookupKey::FinalResults(vote, ntw).to_key();
+            let silence = LookupKey::Silence(vote, ntw).to_key();
+            if self
+                .con
+                .exists::<Vec<String>, usize>(vec![results, silence])?
+                < 2
+            {
+                stale_votes.push(vote);
+            }
+        }
+        Ok(stale_votes)
+    }
+
+    pub async fn conclude_vote(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: u64,
+        ntw: Network,
+    ) -> Result<Option<VoteResults>, RedisError> {
+        let concluded = self
+            .with_vote_lock(fip_number, ntw, |redis| {
+                if redis.vote_status(fip_number, vote_length, ntw)? != VoteStatus::Concluded {
+                    return Ok(None);
+                }
+                let results = redis.concluded_results(fip_number, ntw)?;
+                let silent = redis.silent_delegates(fip_number, ntw)?;
+                Ok(Some((results, redis.vote_round(fip_number, ntw)?, silent)))
+            })
+            .await?;
+
+        let (results, round, silent) = match concluded {
+            Some(concluded) => concluded,
+            None => return Ok(None),
+        };
+        if let Some((silent_voters, sp_ids)) = silent {
+            self.store_silence_report(fip_number, ntw, round, silent_voters, sp_ids)
+                .await?;
+        }
+
+        Ok(Some(results))
+    }
+
+    pub fn voter_delegates(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<Vec<u32>, RedisError> {
+        read_delegates(&mut self.con, voter, ntw)
+    }
+
+    pub fn delegators(&mut self, sp_id: u32, ntw: Network) -> Result<Vec<Address>, RedisError> {
+        let key = LookupKey::Delegators(ntw, sp_id).to_key();
+        self.address_set(key)
+    }
+
+    pub fn voter_starters(&mut self, ntw: Network) -> Result<Vec<Address>, RedisError> {
+        let key = LookupKey::VoteStarters(ntw).to_key();
+        self.address_set(key)
+    }
+
+    pub fn admins(&mut self, ntw: Network) -> Result<Vec<Address>, RedisError> {
+        let key = LookupKey::Admins(ntw).to_key();
+        self.address_set(key)
+    }
+
+    pub fn vote_start(&mut self, fip_number: FipNumber, ntw: Network) -> Result<u64, RedisError> {
+        let key = LookupKey::Timestamp(fip_number, ntw).to_key();
+        let timestamp: u64 = self.con.get::<String, u64>(key)?;
+        Ok(timestamp)
+    }
+
+    pub fn votes(&mut self, fip_number: FipNumber, ntw: Network) -> Result<Vec<Vote>, RedisError> {
+        let key = LookupKey::Votes(fip_number, ntw).to_key();
+        read_votes(&mut self.con, &key)
+    }
+
+    pub fn vote_receipt(&mut self, id: &str) -> Result<Option<VoteReceipt>, RedisError> {
+        let key = match receipt_key(id) {
+            Some(id) => LookupKey::Receipt(id).to_key(),
+            None => return Ok(None),
+        };
+
+        match self.con.get::<String, Option<String>>(key)? {
+            Some(receipt) => match serde_json::from_str(receipt.as_str()) {
+                Ok(receipt) => Ok(Some(receipt)),
+                Err(_) => Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Error decoding vote receipt",
+                ))),
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub fn voter_history(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<Vec<VoterBallot>, RedisError> {
+        let key = LookupKey::History(ntw, voter).to_key();
+        let ballots: Vec<String> = self.con.hvals(key)?;
+
+        let mut history = ballots
+            .iter()
+            .map(|b| serde_json::from_str::<VoterBallot>(b))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| {
+                RedisError::from((redis::ErrorKind::TypeError, "Error decoding voter history"))
+            })?;
+        history.sort_by_key(|b| b.fip_number);
+
+        Ok(history)
+    }
+
+    pub fn vote_round(&mut self, fip_number: FipNumber, ntw: Network) -> Result<u64, RedisError> {
+        let key = LookupKey::Round(fip_number, ntw).to_key();
+
+        Ok(self.con.get::<String, Option<u64>>(key)?.unwrap_or(1))
+    }
+
+    pub fn previous_round(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Option<RoundSummary>, RedisError> {
+        let key = LookupKey::PreviousRound(fip_number, ntw).to_key();
+
+        match self.con.get::<String, Option<String>>(key)? {
+            Some(previous) => match serde_json::from_str(previous.as_str()) {
+                Ok(previous) => Ok(Some(previous)),
+                Err(_) => Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Error decoding previous round",
+                ))),
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub fn published_results(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Option<H256>, RedisError> {
+        let key = LookupKey::Published(fip_number, ntw).to_key();
+
+        match self.con.get::<String, Option<String>>(key)? {
+            Some(tx_hash) => match tx_hash.parse() {
+                Ok(tx_hash) => Ok(Some(tx_hash)),
+                Err(_) => Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Error decoding published transaction",
+                ))),
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_published_results(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        tx_hash: H256,
+    ) -> Result<(), RedisError> {
+        let mut pipe = redis::pipe();
+        pipe.set(
+            LookupKey::Published(fip_number, ntw).to_key(),
+            format!("{:?}", tx_hash),
+        )
+        .ignore();
+
+        self.write_logged(
+            pipe,
+            Event::ResultsPublished {
+                fip_number,
+                ntw,
+                tx_hash,
+            },
+        )
+    }
+
+    pub fn announced(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        announcement: Announcement,
+    ) -> Result<bool, RedisError> {
+        let key = LookupKey::Announced(fip_number, ntw).to_key();
+
+        self.con.sismember(key, announcement.name())
+    }
+
+    pub fn set_announced(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        announcement: Announcement,
+    ) -> Result<(), RedisError> {
+        let mut pipe = redis::pipe();
+        pipe.sadd(
+            LookupKey::Announced(fip_number, ntw).to_key(),
+            announcement.name(),
+        )
+        .ignore();
+
+        self.write_logged(
+            pipe,
+            Event::VoteAnnounced {
+                fip_number,
+                ntw,
+                announcement,
+            },
+        )
+    }
+
+    pub fn voter_networks(&mut self, voter: Address) -> Result<Vec<Network>, RedisError> {
+        let mut ntws = Vec::new();
+        for ntw in Network::all() {
+            let key = LookupKey::RegisteredVoters(ntw).to_key();
+            if self.con.sismember(key, voter.as_bytes())? {
+                ntws.push(ntw);
+            }
+        }
+
+        Ok(ntws)
+    }
+
+    pub fn ballot_network(
+        &mut self,
+        voter: Address,
+        fip_number: FipNumber,
+        chain_id: Option<u64>,
+        vote_length: u64,
+    ) -> Result<Network, RedisError> {
+        let mut ntws = self.voter_networks(voter)?;
+        if ntws.len() > 1 {
+            if let Some(chain_id) = chain_id {
+                ntws.retain(|ntw| ntw.chain_id() == chain_id);
+            }
+        }
+        if ntws.len() > 1 {
+            let (mut voting, mut ended) = (Vec::new(), Vec::new());
+            for &ntw in &ntws {
+                match self.vote_status(fip_number, vote_length, ntw)? {
+                    VoteStatus::InProgress(_) => voting.push(ntw),
+                    VoteStatus::DoesNotExist => (),
+                    _ => ended.push(ntw),
+                }
+            }
+
+            // Without a vote in progress the ballot is refused by whichever vote it could be for
+            ntws = match (voting.is_empty(), ended.first()) {
+                (false, _) => voting,
+                (true, Some(&ntw)) => vec![ntw],
+                (true, None) => vec![ntws[0]],
+            };
+        }
+
+        match ntws.as_slice() {
+            [ntw] => Ok(*ntw),
+            [] => Err(RedisError::from((
+                redis::ErrorKind::AuthenticationFailed,
+                "Voter is not registered on the network of the ballot",
+            ))),
+            _ => Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "FIP is being voted on in several networks the voter is registered on, sign the ballot as typed data",
+            ))),
+        }
+    }
+
+    pub fn voter_class(&mut self, voter: Address, ntw: Network) -> Result<VoterClass, RedisError> {
+        let key = LookupKey::VoterClass(ntw, voter).to_key();
+
+        match self.con.get::<String, Option<String>>(key)? {
+            Some(class) => match serde_json::from_str(class.as_str()) {
+                Ok(class) => Ok(class),
+                Err(_) => Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Error decoding voter class",
+                ))),
+            },
+            None => Ok(VoterClass::StorageProvider),
+        }
+    }
+
+    pub fn registered_voters(&mut self, ntw: Network) -> Result<Vec<Address>, RedisError> {
+        let key = LookupKey::RegisteredVoters(ntw).to_key();
+        self.address_set(key)
+    }
+
+    pub fn voter_page(
+        &mut self,
+        ntw: Network,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<VoterPage, RedisError> {
+        let voters = self.registered_voters(ntw)?;
+        let total = voters.len();
+
+        let mut page = Vec::new();
+        for address in voters
+            .into_iter()
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+        {
+            let delegates = self
+                .voter_delegates(address, ntw)?
+                .into_iter()
+                .map(|id| sp_id_format(ntw, id))
+                .collect();
+            page.push(RegisteredVoter { address, delegates });
+        }
+
+        Ok(VoterPage {
+            total,
+            voters: page,
+        })
+    }
+
+    pub fn turnout(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        network_storage_size: u128,
+    ) -> Result<Turnout, RedisError> {
+        let votes = self.votes(fip_number, ntw)?;
+        let storage_providers: BTreeSet<u32> =
+            votes.iter().flat_map(|v| v.storage_providers()).collect();
+
+        let storage_size = self
+            .tally_votes(fip_number, ntw, None)?
+            .total_storage_size();
+
+        Ok(Turnout::new(
+            votes.len() as u64,
+            storage_providers.len() as u64,
+            storage_size,
+            network_storage_size,
+        ))
+    }
+
+    pub fn yet_to_vote(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Vec<Address>, RedisError> {
+        let round = self.vote_round(fip_number, ntw)?;
+
+        let mut voters = Vec::new();
+        for voter in self.registered_voters(ntw)? {
+            let key = LookupKey::History(ntw, voter).to_key();
+            let ballot = self
+                .con
+                .hget::<String, u32, Option<String>>(key, fip_number.get())?;
+
+            // A ballot of an earlier round doesn't count toward this one
+            let voted = match ballot {
+                Some(ballot) => {
+                    serde_json::from_str::<VoterBallot>(&ballot)
+                        .map_err(|_| {
+                            RedisError::from((
+                                redis::ErrorKind::TypeError,
+                                "Error decoding voter history",
+                            ))
+                        })?
+                        .round
+                        == round
+                }
+                None => false,
+            };
+            if !voted {
+                voters.push(voter);
+            }
+        }
+
+        Ok(voters)
+    }
+
+    pub fn silence_report(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: u64,
+        ntw: Network,
+    ) -> Result<Option<SilenceReport>, RedisError> {
+        if self.vote_status(fip_number, vote_length, ntw)? != VoteStatus::Concluded {
+            return Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Vote is not concluded",
+            )));
+        }
+
+        let key = LookupKey::Silence(fip_number, ntw).to_key();
+
+        Ok(self
+            .con
+            .get::<String, Option<String>>(key)?
+            .map(|report| serde_json::from_str(report.as_str()).unwrap()))
+    }
+
+    pub fn vote_metadata(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<BTreeMap<String, VoteMetadata>, RedisError> {
+        if let Some(metadata) = hot_cache::metadata(fip_number, ntw) {
+            return Ok(metadata);
+        }
+
+        let key = LookupKey::Metadata(fip_number, ntw).to_key();
+        let generation = hot_cache::generation();
+
+        let metadata: BTreeMap<String, VoteMetadata> =
+            match self.con.get::<String, Option<String>>(key)? {
+                Some(m) => serde_json::from_str(m.as_str()).unwrap(),
+                None => BTreeMap::new(),
+            };
+        hot_cache::insert_metadata(fip_number, ntw, metadata.clone(), generation);
+
+        Ok(metadata)
+    }
+
+    pub fn fip_info(&mut self, fip_number: FipNumber) -> Result<Option<FipInfo>, RedisError> {
+        let key = LookupKey::FipInfo(fip_number).to_key();
+
+        match self.con.get::<String, Option<String>>(key)? {
+            Some(info) => match serde_json::from_str(&info) {
+                Ok(info) => Ok(Some(info)),
+                Err(_) => Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Error decoding FIP info",
+                ))),
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub fn results_version(&mut self) -> Result<u64, RedisError> {
+        let key = LookupKey::ResultsVersion.to_key();
+
+        Ok(self.con.get::<String, Option<u64>>(key)?.unwrap_or(0))
+    }
+
+    pub fn vote_options(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Vec<String>, RedisError> {
+        let key = LookupKey::Options(fip_number, ntw).to_key();
+
+        match self.con.get::<String, Option<String>>(key)? {
+            Some(options) => match serde_json::from_str(options.as_str()) {
+                Ok(options) => Ok(options),
+                Err(_) => Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Error decoding ballot options",
+                ))),
+            },
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn vote_quorum(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Option<QuorumRules>, RedisError> {
+        let key = LookupKey::Quorum(fip_number, ntw).to_key();
+
+        match self.con.get::<String, Option<String>>(key)? {
+            Some(rules) => match serde_json::from_str(rules.as_str()) {
+                Ok(rules) => Ok(Some(rules)),
+                Err(_) => Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Error decoding quorum rules",
+                ))),
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub fn vote_weighting(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Weighting, RedisError> {
+        let key = LookupKey::Weighting(fip_number, ntw).to_key();
+
+        match self.con.get::<String, Option<String>>(key)? {
+            Some(weighting) => serde_json::from_str(weighting.as_str()).map_err(|_| {
+                RedisError::from((redis::ErrorKind::TypeError, "Error decoding weighting"))
+            }),
+            None => Ok(Weighting::Raw),
+        }
+    }
+
+    pub fn vote_min_power(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<u128, RedisError> {
+        let key = LookupKey::MinPower(fip_number, ntw).to_key();
+
+        match self.con.get::<String, Option<String>>(key)? {
+            Some(min_power) => min_power.parse().map_err(|_| {
+                RedisError::from((redis::ErrorKind::TypeError, "Error decoding minimum power"))
+            }),
+            None => Ok(0),
+        }
+    }
+
+    pub fn vote_extension(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<u64, RedisError> {
+        let key = LookupKey::Extension(fip_number, ntw).to_key();
+
+        Ok(self.con.get::<String, Option<u64>>(key)?.unwrap_or(0))
+    }
+
+    pub fn vote_ending(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Option<EarlyEnd>, RedisError> {
+        let key = LookupKey::Ended(fip_number, ntw).to_key();
+
+        match self.con.get::<String, Option<String>>(key)? {
+            Some(ended) => match serde_json::from_str(ended.as_str()) {
+                Ok(ended) => Ok(Some(ended)),
+                Err(_) => Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Error decoding vote ending",
+                ))),
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub fn all_votes(&mut self, ntw: Network) -> Result<Vec<FipNumber>, RedisError> {
+        let key = LookupKey::AllVotes(ntw).to_key();
+
+        let mut fips: Vec<u32> = self.con.smembers(key)?;
+        fips.sort();
+
+        fips.into_iter()
+            .map(|fip| match FipNumber::try_from(fip) {
+                Ok(fip) => Ok(fip),
+                Err(_) => Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Error decoding stored FIP number",
+                ))),
+            })
+            .collect()
+    }
+
+    pub fn power_provider(&self) -> Arc<dyn StoragePowerProvider> {
+        self.power.clone()
+    }
+
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    pub fn now(&self) -> u64 {
+        self.clock.now()
+    }
+
+    /*~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~/
+    /                                     SETTERS                                    /
+    /~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~*/
+
+    pub async fn add_vote(
+        &mut self,
+        fip_number: FipNumber,
+        vote: Vote,
+        voter: Address,
+        vote_length: u64,
+        allow_revote: bool,
+    ) -> Result<(), RedisError> {
+        self.insert_vote(fip_number, vote, voter, vote_length, allow_revote, None)
+            .await
+    }
+
+    pub async fn submit_vote(
+        &mut self,
+        fip_number: FipNumber,
+        vote: Vote,
+        voter: Address,
+        vote_length: u64,
+        allow_revote: bool,
+        receipt_id: String,
+    ) -> Result<VoteReceipt, RedisError> {
+        if let Some(receipt) = self.vote_receipt(&receipt_id)? {
+            return Ok(receipt);
+        }
+
+        let receipt = VoteReceipt {
+            id: receipt_id,
+            fip_number,
+            choice: vote.choice(),
+            address: voter,
+            recorded_at: self.clock.now(),
+        };
+
+        self.insert_vote(
+            fip_number,
+            vote,
+            voter,
+            vote_length,
+            allow_revote,
+            Some(&receipt),
+        )
+        .await?;
+
+        // A concurrent retry may have recorded the ballot first
+        Ok(self.vote_receipt(&receipt.id)?.unwrap_or(receipt))
+    }
+
+    pub async fn add_vote_batch(
+        &mut self,
+        votes: Vec<(Vote, String)>,
+        voter: Address,
+        vote_length: u64,
+        allow_revote: bool,
+    ) -> Result<Vec<VoteReceipt>, RedisError> {
+        let mut landed = Vec::with_capacity(votes.len());
+        for (_, id) in &votes {
+            landed.extend(self.vote_receipt(id)?);
+        }
+        if !votes.is_empty() && landed.len() == votes.len() {
+            return Ok(landed);
+        }
+
+        let recorded_at = self.clock.now();
+        let mut ballots = Vec::with_capacity(votes.len());
+        for (vote, id) in votes {
+            let receipt = VoteReceipt {
+                id,
+                fip_number: vote.fip(),
+                choice: vote.choice(),
+                address: voter,
+                recorded_at,
+            };
+            let ballot = self
+                .prepare_ballot(vote.fip(), vote, voter, vote_length, allow_revote)
+                .await?;
+            ballots.push((ballot, Some(receipt)));
+        }
+
+        let receipts: Vec<VoteReceipt> = ballots
+            .iter()
+            .filter_map(|(_, receipt)| receipt.clone())
+            .collect();
+        self.record_ballots(ballots, allow_revote)?;
+
+        // A concurrent retry may have recorded some of the ballots first
+        receipts
+            .into_iter()
+            .map(|receipt| Ok(self.vote_receipt(&receipt.id)?.unwrap_or(receipt)))
+            .collect()
+    }
+
+    pub fn record_ballot(
+        &mut self,
+        ballot: RecordedBallot,
+        allow_revote: bool,
+        receipt: Option<&VoteReceipt>,
+    ) -> Result<(), RedisError> {
+        self.record_ballots(vec![(ballot, receipt.cloned())], allow_revote)
+    }
+
+    pub async fn set_vote_metadata(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        language: String,
+        metadata: VoteMetadata,
+    ) -> Result<(), RedisError> {
+        self.with_vote_lock(fip_number, ntw, |redis| {
+            let key = LookupKey::Metadata(fip_number, ntw).to_key();
+
+            let mut all_metadata = redis.vote_metadata(fip_number, ntw)?;
+            all_metadata.insert(language.clone(), metadata.clone());
+
+            let str_metadata = serde_json::to_string(&all_metadata).unwrap();
+
+            let mut pipe = redis::pipe();
+            pipe.set(key, str_metadata).ignore();
+
+            redis.write_logged(
+                pipe,
+                Event::MetadataChanged {
+                    fip_number,
+                    ntw,
+                    language,
+                    metadata,
+                },
+            )
+        })
+        .await
+    }
+
+    pub fn set_fip_info(
+        &mut self,
+        fip_number: FipNumber,
+        info: &FipInfo,
+    ) -> Result<(), RedisError> {
+        let key = LookupKey::FipInfo(fip_number).to_key();
+        let str_info = serde_json::to_string(info).unwrap();
+
+        // Synced over and over, the results only change with the info
+        let previous: Option<String> = self.con.getset(key, &str_info)?;
+        if previous.as_ref() != Some(&str_info) {
+            self.con
+                .incr::<String, u64, ()>(LookupKey::ResultsVersion.to_key(), 1)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_vote_options(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        options: &[String],
+    ) -> Result<(), RedisError> {
+        let key = LookupKey::Options(fip_number, ntw).to_key();
+
+        let mut pipe = redis::pipe();
+        match options.is_empty() {
+            true => pipe.del(key).ignore(),
+            false => pipe
+                .set(key, serde_json::to_string(options).unwrap())
+                .ignore(),
+        };
+
+        self.write_logged(
+            pipe,
+            Event::OptionsChanged {
+                fip_number,
+                ntw,
+                options: options.to_vec(),
+            },
+        )
+    }
+
+    pub fn set_vote_quorum(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        rules: Option<QuorumRules>,
+    ) -> Result<(), RedisError> {
+        let key = LookupKey::Quorum(fip_number, ntw).to_key();
+
+        let mut pipe = redis::pipe();
+        match rules {
+            Some(rules) => pipe
+                .set(key, serde_json::to_string(&rules).unwrap())
+                .ignore(),
+            None => pipe.del(key).ignore(),
+        };
+
+        self.write_logged(
+            pipe,
+            Event::QuorumChanged {
+                fip_number,
+                ntw,
+                rules,
+            },
+        )
+    }
+
+    pub fn set_vote_weighting(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        weighting: Weighting,
+    ) -> Result<(), RedisError> {
+        let key = LookupKey::Weighting(fip_number, ntw).to_key();
+
+        let mut pipe = redis::pipe();
+        match weighting {
+            Weighting::Raw => pipe.del(key).ignore(),
+            weighting => pipe
+                .set(key, serde_json::to_string(&weighting).unwrap())
+                .ignore(),
+        };
+
+        self.write_logged(
+            pipe,
+            Event::WeightingChanged {
+                fip_number,
+                ntw,
+                weighting,
+            },
+        )
+    }
+
+    pub fn set_vote_min_power(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        min_power: u128,
+    ) -> Result<(), RedisError> {
+        let key = LookupKey::MinPower(fip_number, ntw).to_key();
+
+        let mut pipe = redis::pipe();
+        match min_power {
+            0 => pipe.del(key).ignore(),
+            // Kept as a string since redis integers are 64 bits
+            min_power => pipe.set(key, min_power.to_string()).ignore(),
+        };
+
+        self.write_logged(
+            pipe,
+            Event::MinPowerChanged {
+                fip_number,
+                ntw,
+                min_power,
+            },
+        )
+    }
+
+    pub fn set_vote_ending(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        ended: Option<&EarlyEnd>,
+    ) -> Result<(), RedisError> {
+        let key = LookupKey::Ended(fip_number, ntw).to_key();
+
+        let mut pipe = redis::pipe();
+        match ended {
+            Some(ended) => pipe
+                .set(key, serde_json::to_string(ended).unwrap())
+                .ignore(),
+            None => pipe.del(key).ignore(),
+        };
+
+        self.write_logged(
+            pipe,
+            Event::EndingChanged {
+                fip_number,
+                ntw,
+                ended: ended.cloned(),
+            },
+        )
+    }
+
+    pub fn set_vote_extension(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        extension: u64,
+    ) -> Result<(), RedisError> {
+        let key = LookupKey::Extension(fip_number, ntw).to_key();
+
+        let mut pipe = redis::pipe();
+        match extension {
+            0 => pipe.del(key).ignore(),
+            extension => pipe.set(key, extension).ignore(),
+        };
+
+        self.write_logged(
+            pipe,
+            Event::ExtensionChanged {
+                fip_number,
+                ntw,
+                extension,
+            },
+        )
+    }
+
+    pub fn remove_voter_starters(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<(), RedisError> {
+        let key = LookupKey::VoteStarters(ntw).to_key();
+        let event = Event::StarterRemoved { voter, ntw };
+        let now = self.clock.now();
+
+        // Retried if a concurrent removal changes the starters between the check and the removal
+        redis::transaction(&mut self.con, &[key.clone()], |con, pipe| {
+            if !con.sismember::<&String, &[u8], bool>(&key, voter.as_bytes())? {
+                return Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Address is not a vote starter",
+                )));
+            }
+            if con.scard::<&String, u64>(&key)? <= 1 {
+                return Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Cannot remove the last vote starter",
+                )));
+            }
+
+            pipe.srem(&key, voter.as_bytes()).ignore();
+            queue_event(pipe, now, &event);
+
+            pipe.query::<Option<()>>(con)
+        })?;
+
+        invalidate_own(&event);
+
+        Ok(())
+    }
+
+    pub fn remove_admin(&mut self, voter: Address, ntw: Network) -> Result<(), RedisError> {
+        let key = LookupKey::Admins(ntw).to_key();
+        let event = Event::AdminRemoved { voter, ntw };
+        let now = self.clock.now();
+
+        // Retried if a concurrent removal changes the admins between the check and the removal
+        redis::transaction(&mut self.con, &[key.clone()], |con, pipe| {
+            if !con.sismember::<&String, &[u8], bool>(&key, voter.as_bytes())? {
+                return Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Address is not an admin",
+                )));
+            }
+            if con.scard::<&String, u64>(&key)? <= 1 {
+                return Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Cannot remove the last admin",
+                )));
+            }
+
+            pipe.srem(&key, voter.as_bytes()).ignore();
+            queue_event(pipe, now, &event);
+
+            pipe.query::<Option<()>>(con)
+        })?;
+
+        invalidate_own(&event);
+
+        Ok(())
+    }
+
+    pub fn flush_vote(&mut self, fip_number: FipNumber, ntw: Network) -> Result<(), RedisError> {
+        let mut pipe = redis::pipe();
+        pipe.del(LookupKey::Votes(fip_number, ntw).to_key())
+            .ignore();
+
+        self.write_logged(pipe, Event::BallotsFlushed { fip_number, ntw })
+    }
+
+    pub async fn delete_vote(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<(), RedisError> {
+        self.with_vote_lock(fip_number, ntw, |redis| {
+            // Every voter history on the network, since voters of earlier rounds
+            // are no longer in the list of ballots
+            let histories: Vec<String> = redis
+                .con
+                .scan_match(format!("{}{}:history:*", KEY_PREFIX, ntw.name()))?
+                .collect();
+
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+
+            for history in histories {
+                pipe.hdel(history, fip_number.get()).ignore();
+            }
+
+            pipe.del(LookupKey::Votes(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Timestamp(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Silence(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Metadata(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Options(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Quorum(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Weighting(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::MinPower(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Ended(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Extension(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::FinalResults(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Published(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Announced(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Round(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::PreviousRound(fip_number, ntw).to_key())
+                .ignore()
+                .del(LookupKey::Storage(ntw, fip_number).to_key())
+                .ignore()
+                .del(LookupKey::Fil(ntw, fip_number).to_key())
+                .ignore()
+                .srem(LookupKey::AllVotes(ntw).to_key(), fip_number.get())
+                .ignore();
+
+            redis.write_logged(pipe, Event::VoteDeleted { fip_number, ntw })
+        })
+        .await
+    }
+
+    pub fn flush_all(&mut self) -> Result<(), RedisError> {
+        // The results version keeps counting, so tags from before the flush
+        // don't match the emptied database once it counts up to them again
+        let version_key = LookupKey::ResultsVersion.to_key();
+
+        let keys: Vec<Vec<u8>> = self.con.keys("*")?;
+
+        let mut pipe = redis::pipe();
+        for key in keys {
+            if key == version_key.as_bytes() {
+                continue;
+            }
+            pipe.del(key).ignore();
+        }
+
+        self.write_logged(pipe, Event::DatabaseFlushed)
+    }
+}
+
+/// Reports the errors of the methods above as [`StoreError`], so code built on
+/// the store doesn't depend on Redis
+#[async_trait(?Send)]
+impl VoteStore for Redis {
+    async fn start_vote(
+        &mut self,
+        fip_number: FipNumber,
+        signer: Address,
+        ntw: Network,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::start_vote(self, fip_number, signer, ntw).await?)
+    }
+
+    async fn schedule_vote(
+        &mut self,
+        fip_number: FipNumber,
+        signer: Address,
+        ntw: Network,
+        opens_at: u64,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::schedule_vote(self, fip_number, signer, ntw, opens_at).await?)
+    }
+
+    async fn start_round(
+        &mut self,
+        fip_number: FipNumber,
+        signer: Address,
+        ntw: Network,
+        round: u64,
+        vote_length: u64,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::start_round(self, fip_number, signer, ntw, round, vote_length).await?)
+    }
+
+    async fn end_vote(
+        &mut self,
+        fip_number: FipNumber,
+        signer: Address,
+        ntw: Network,
+        round: u64,
+        ending: VoteEnding,
+        vote_length: u64,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::end_vote(self, fip_number, signer, ntw, round, ending, vote_length).await?)
+    }
+
+    async fn extend_vote(
+        &mut self,
+        fip_number: FipNumber,
+        signer: Address,
+        ntw: Network,
+        round: u64,
+        extension: u64,
+        vote_length: u64,
+    ) -> Result<(), StoreError> {
+        Ok(
+            Redis::extend_vote(self, fip_number, signer, ntw, round, extension, vote_length)
+                .await?,
+        )
+    }
+
+    async fn import_vote(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        started_at: u64,
+        ballots: &[Vote],
+        results: &VoteResults,
+        metadata: &BTreeMap<String, VoteMetadata>,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::import_vote(
+            self, fip_number, ntw, started_at, ballots, results, metadata,
+        )
+        .await?)
+    }
+
+    fn register_voter(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        sp_ids: Vec<u32>,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::register_voter(self, voter, ntw, sp_ids)?)
+    }
+
+    fn register_voter_class(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        class: VoterClass,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::register_voter_class(self, voter, ntw, class)?)
+    }
+
+    fn unregister_voter(&mut self, voter: Address, ntw: Network) -> Result<(), StoreError> {
+        Ok(Redis::unregister_voter(self, voter, ntw)?)
+    }
+
+    fn unregister_storage_providers(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        sp_ids: &[u32],
+    ) -> Result<(), StoreError> {
+        Ok(Redis::unregister_storage_providers(
+            self, voter, ntw, sp_ids,
+        )?)
+    }
+
+    fn unregister_voter_all(&mut self, voter: Address) -> Result<(), StoreError> {
+        Ok(Redis::unregister_voter_all(self, voter)?)
+    }
+
+    fn register_voter_starter(&mut self, voter: Address, ntw: Network) -> Result<(), StoreError> {
+        Ok(Redis::register_voter_starter(self, voter, ntw)?)
+    }
+
+    fn register_admin(&mut self, voter: Address, ntw: Network) -> Result<(), StoreError> {
+        Ok(Redis::register_admin(self, voter, ntw)?)
+    }
+
+    fn vote_exists(&mut self, ntw: Network, fip: FipNumber) -> Result<bool, StoreError> {
+        Ok(Redis::vote_exists(self, ntw, fip)?)
+    }
+
+    fn is_authorized_starter(&mut self, voter: Address, ntw: Network) -> Result<bool, StoreError> {
+        Ok(Redis::is_authorized_starter(self, voter, ntw)?)
+    }
+
+    fn is_admin(&mut self, voter: Address, ntw: Network) -> Result<bool, StoreError> {
+        Ok(Redis::is_admin(self, voter, ntw)?)
+    }
+
+    fn is_registered(&mut self, voter: Address, ntw: Network) -> bool {
+        Redis::is_registered(self, voter, ntw)
+    }
+
+    fn voter_worker(&mut self, voter: Address, ntw: Network) -> Result<Option<String>, StoreError> {
+        Ok(Redis::voter_worker(self, voter, ntw)?)
+    }
+
+    fn set_voter_worker(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        worker_address: Option<&str>,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::set_voter_worker(self, voter, ntw, worker_address)?)
+    }
+
+    fn registration_expiry(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<Option<u64>, StoreError> {
+        Ok(Redis::registration_expiry(self, voter, ntw)?)
+    }
+
+    fn is_registration_expired(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<bool, StoreError> {
+        Ok(Redis::is_registration_expired(self, voter, ntw)?)
+    }
+
+    fn set_registration_expiry(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        expires_at: Option<u64>,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::set_registration_expiry(
+            self, voter, ntw, expires_at,
+        )?)
+    }
+
+    fn renew_registration(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        expires_at: Option<u64>,
+    ) -> Result<Option<u64>, StoreError> {
+        Ok(Redis::renew_registration(self, voter, ntw, expires_at)?)
+    }
+
+    fn queue_registration(
+        &mut self,
+        pending: &PendingRegistration,
+    ) -> Result<RegistrationStatus, StoreError> {
+        Ok(Redis::queue_registration(self, pending)?)
+    }
+
+    fn next_registration(&mut self) -> Result<Option<PendingRegistration>, StoreError> {
+        Ok(Redis::next_registration(self)?)
+    }
+
+    fn registration_status(
+        &mut self,
+        voter: Address,
+    ) -> Result<Option<RegistrationStatus>, StoreError> {
+        Ok(Redis::registration_status(self, voter)?)
+    }
+
+    fn set_registration_status(
+        &mut self,
+        voter: Address,
+        status: &RegistrationStatus,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::set_registration_status(self, voter, status)?)
+    }
+
+    fn offer_delegation(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        offer: &DelegationOffer,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::offer_delegation(self, voter, ntw, offer)?)
+    }
+
+    fn delegation_offers(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<Vec<DelegationOffer>, StoreError> {
+        Ok(Redis::delegation_offers(self, voter, ntw)?)
+    }
+
+    fn take_delegation_offer(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        worker_address: &str,
+    ) -> Result<Option<DelegationOffer>, StoreError> {
+        Ok(Redis::take_delegation_offer(
+            self,
+            voter,
+            ntw,
+            worker_address,
+        )?)
+    }
+
+    fn issue_nonce(&mut self, ttl: u64) -> Result<IssuedNonce, StoreError> {
+        Ok(Redis::issue_nonce(self, ttl)?)
+    }
+
+    fn consume_nonce(&mut self, nonce: [u8; 16]) -> Result<bool, StoreError> {
+        Ok(Redis::consume_nonce(self, nonce)?)
+    }
+
+    fn nonce_issued(&mut self, nonce: [u8; 16]) -> Result<bool, StoreError> {
+        Ok(Redis::nonce_issued(self, nonce)?)
+    }
+
+    fn events(
+        &mut self,
+        after: Option<&str>,
+        count: usize,
+    ) -> Result<Vec<LoggedEvent>, StoreError> {
+        Ok(Redis::events(self, after, count)?)
+    }
+
+    async fn vote_results(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: u64,
+        ntw: Network,
+    ) -> Result<VoteResults, StoreError> {
+        Ok(Redis::vote_results(self, fip_number, vote_length, ntw).await?)
+    }
+
+    fn vote_status(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: impl Into<u64>,
+        ntw: Network,
+    ) -> Result<VoteStatus, StoreError> {
+        Ok(Redis::vote_status(self, fip_number, vote_length, ntw)?)
+    }
+
+    fn is_open(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: impl Into<u64>,
+        ntw: Network,
+        now: u64,
+    ) -> Result<bool, StoreError> {
+        Ok(Redis::is_open(self, fip_number, vote_length, ntw, now)?)
+    }
+
+    fn active_votes(
+        &mut self,
+        ntw: Network,
+        vote_length: impl Into<u64>,
+    ) -> Result<Vec<FipNumber>, StoreError> {
+        Ok(Redis::active_votes(self, ntw, vote_length)?)
+    }
+
+    fn concluded_votes(
+        &mut self,
+        ntw: Network,
+        vote_length: impl Into<u64>,
+    ) -> Result<Vec<FipNumber>, StoreError> {
+        Ok(Redis::concluded_votes(self, ntw, vote_length)?)
+    }
+
+    fn stale_votes(
+        &mut self,
+        ntw: Network,
+        vote_length: impl Into<u64>,
+    ) -> Result<Vec<FipNumber>, StoreError> {
+        Ok(Redis::stale_votes(self, ntw, vote_length)?)
+    }
+
+    async fn conclude_vote(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: u64,
+        ntw: Network,
+    ) -> Result<Option<VoteResults>, StoreError> {
+        Ok(Redis::conclude_vote(self, fip_number, vote_length, ntw).await?)
+    }
+
+    fn voter_delegates(&mut self, voter: Address, ntw: Network) -> Result<Vec<u32>, StoreError> {
+        Ok(Redis::voter_delegates(self, voter, ntw)?)
+    }
+
+    fn delegators(&mut self, sp_id: u32, ntw: Network) -> Result<Vec<Address>, StoreError> {
+        Ok(Redis::delegators(self, sp_id, ntw)?)
+    }
+
+    fn voter_starters(&mut self, ntw: Network) -> Result<Vec<Address>, StoreError> {
+        Ok(Redis::voter_starters(self, ntw)?)
+    }
+
+    fn admins(&mut self, ntw: Network) -> Result<Vec<Address>, StoreError> {
+        Ok(Redis::admins(self, ntw)?)
+    }
+
+    fn vote_start(&mut self, fip_number: FipNumber, ntw: Network) -> Result<u64, StoreError> {
+        Ok(Redis::vote_start(self, fip_number, ntw)?)
+    }
+
+    fn votes(&mut self, fip_number: FipNumber, ntw: Network) -> Result<Vec<Vote>, StoreError> {
+        Ok(Redis::votes(self, fip_number, ntw)?)
+    }
+
+    fn vote_receipt(&mut self, id: &str) -> Result<Option<VoteReceipt>, StoreError> {
+        Ok(Redis::vote_receipt(self, id)?)
+    }
+
+    fn voter_history(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<Vec<VoterBallot>, StoreError> {
+        Ok(Redis::voter_history(self, voter, ntw)?)
+    }
+
+    fn vote_round(&mut self, fip_number: FipNumber, ntw: Network) -> Result<u64, StoreError> {
+        Ok(Redis::vote_round(self, fip_number, ntw)?)
+    }
+
+    fn previous_round(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Option<RoundSummary>, StoreError> {
+        Ok(Redis::previous_round(self, fip_number, ntw)?)
+    }
+
+    fn published_results(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Option<H256>, StoreError> {
+        Ok(Redis::published_results(self, fip_number, ntw)?)
+    }
+
+    fn set_published_results(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        tx_hash: H256,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::set_published_results(
+            self, fip_number, ntw, tx_hash,
+        )?)
+    }
+
+    fn announced(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        announcement: Announcement,
+    ) -> Result<bool, StoreError> {
+        Ok(Redis::announced(self, fip_number, ntw, announcement)?)
+    }
+
+    fn set_announced(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        announcement: Announcement,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::set_announced(self, fip_number, ntw, announcement)?)
+    }
+
+    fn voter_networks(&mut self, voter: Address) -> Result<Vec<Network>, StoreError> {
+        Ok(Redis::voter_networks(self, voter)?)
+    }
+
+    fn ballot_network(
+        &mut self,
+        voter: Address,
+        fip_number: FipNumber,
+        chain_id: Option<u64>,
+        vote_length: u64,
+    ) -> Result<Network, StoreError> {
+        Ok(Redis::ballot_network(
+            self,
+            voter,
+            fip_number,
+            chain_id,
+            vote_length,
+        )?)
+    }
+
+    fn voter_class(&mut self, voter: Address, ntw: Network) -> Result<VoterClass, StoreError> {
+        Ok(Redis::voter_class(self, voter, ntw)?)
+    }
+
+    fn registered_voters(&mut self, ntw: Network) -> Result<Vec<Address>, StoreError> {
+        Ok(Redis::registered_voters(self, ntw)?)
+    }
+
+    fn voter_page(
+        &mut self,
+        ntw: Network,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<VoterPage, StoreError> {
+        Ok(Redis::voter_page(self, ntw, offset, limit)?)
+    }
+
+    fn turnout(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        network_storage_size: u128,
+    ) -> Result<Turnout, StoreError> {
+        Ok(Redis::turnout(self, fip_number, ntw, network_storage_size)?)
+    }
+
+    fn yet_to_vote(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Vec<Address>, StoreError> {
+        Ok(Redis::yet_to_vote(self, fip_number, ntw)?)
+    }
+
+    fn silence_report(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: u64,
+        ntw: Network,
+    ) -> Result<Option<SilenceReport>, StoreError> {
+        Ok(Redis::silence_report(self, fip_number, vote_length, ntw)?)
+    }
+
+    fn vote_metadata(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<BTreeMap<String, VoteMetadata>, StoreError> {
+        Ok(Redis::vote_metadata(self, fip_number, ntw)?)
+    }
+
+    fn fip_info(&mut self, fip_number: FipNumber) -> Result<Option<FipInfo>, StoreError> {
+        Ok(Redis::fip_info(self, fip_number)?)
+    }
+
+    fn results_version(&mut self) -> Result<u64, StoreError> {
+        Ok(Redis::results_version(self)?)
+    }
+
+    fn vote_options(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Vec<String>, StoreError> {
+        Ok(Redis::vote_options(self, fip_number, ntw)?)
+    }
+
+    fn vote_quorum(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Option<QuorumRules>, StoreError> {
+        Ok(Redis::vote_quorum(self, fip_number, ntw)?)
+    }
+
+    fn vote_weighting(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Weighting, StoreError> {
+        Ok(Redis::vote_weighting(self, fip_number, ntw)?)
+    }
+
+    fn vote_min_power(&mut self, fip_number: FipNumber, ntw: Network) -> Result<u128, StoreError> {
+        Ok(Redis::vote_min_power(self, fip_number, ntw)?)
+    }
+
+    fn vote_extension(&mut self, fip_number: FipNumber, ntw: Network) -> Result<u64, StoreError> {
+        Ok(Redis::vote_extension(self, fip_number, ntw)?)
+    }
+
+    fn vote_ending(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Option<EarlyEnd>, StoreError> {
+        Ok(Redis::vote_ending(self, fip_number, ntw)?)
+    }
+
+    fn all_votes(&mut self, ntw: Network) -> Result<Vec<FipNumber>, StoreError> {
+        Ok(Redis::all_votes(self, ntw)?)
+    }
+
+    fn power_provider(&self) -> Arc<dyn StoragePowerProvider> {
+        Redis::power_provider(self)
+    }
+
+    fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        Redis::set_clock(self, clock)
+    }
+
+    fn now(&self) -> u64 {
+        Redis::now(self)
+    }
+
+    async fn add_vote(
+        &mut self,
+        fip_number: FipNumber,
+        vote: Vote,
+        voter: Address,
+        vote_length: u64,
+        allow_revote: bool,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::add_vote(self, fip_number, vote, voter, vote_length, allow_revote).await?)
+    }
+
+    async fn submit_vote(
+        &mut self,
+        fip_number: FipNumber,
+        vote: Vote,
+        voter: Address,
+        vote_length: u64,
+        allow_revote: bool,
+        receipt_id: String,
+    ) -> Result<VoteReceipt, StoreError> {
+        Ok(Redis::submit_vote(
+            self,
+            fip_number,
+            vote,
+            voter,
+            vote_length,
+            allow_revote,
+            receipt_id,
+        )
+        .await?)
+    }
+
+    async fn add_vote_batch(
+        &mut self,
+        votes: Vec<(Vote, String)>,
+        voter: Address,
+        vote_length: u64,
+        allow_revote: bool,
+    ) -> Result<Vec<VoteReceipt>, StoreError> {
+        Ok(Redis::add_vote_batch(self, votes, voter, vote_length, allow_revote).await?)
+    }
+
+    fn record_ballot(
+        &mut self,
+        ballot: RecordedBallot,
+        allow_revote: bool,
+        receipt: Option<&VoteReceipt>,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::record_ballot(self, ballot, allow_revote, receipt)?)
+    }
+
+    fn set_fip_info(&mut self, fip_number: FipNumber, info: &FipInfo) -> Result<(), StoreError> {
+        Ok(Redis::set_fip_info(self, fip_number, info)?)
+    }
+
+    async fn set_vote_metadata(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        language: String,
+        metadata: VoteMetadata,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::set_vote_metadata(self, fip_number, ntw, language, metadata).await?)
+    }
+
+    fn set_vote_options(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        options: &[String],
+    ) -> Result<(), StoreError> {
+        Ok(Redis::set_vote_options(self, fip_number, ntw, options)?)
+    }
+
+    fn set_vote_quorum(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        rules: Option<QuorumRules>,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::set_vote_quorum(self, fip_number, ntw, rules)?)
+    }
+
+    fn set_vote_weighting(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        weighting: Weighting,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::set_vote_weighting(self, fip_number, ntw, weighting)?)
+    }
+
+    fn set_vote_min_power(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        min_power: u128,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::set_vote_min_power(self, fip_number, ntw, min_power)?)
+    }
+
+    fn set_vote_ending(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        ended: Option<&EarlyEnd>,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::set_vote_ending(self, fip_number, ntw, ended)?)
+    }
+
+    fn set_vote_extension(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        extension: u64,
+    ) -> Result<(), StoreError> {
+        Ok(Redis::set_vote_extension(self, fip_number, ntw, extension)?)
+    }
+
+    fn remove_voter_starters(&mut self, voter: Address, ntw: Network) -> Result<(), StoreError> {
+        Ok(Redis::remove_voter_starters(self, voter, ntw)?)
+    }
+
+    fn remove_admin(&mut self, voter: Address, ntw: Network) -> Result<(), StoreError> {
+        Ok(Redis::remove_admin(self, voter, ntw)?)
+    }
+
+    fn flush_vote(&mut self, fip_number: FipNumber, ntw: Network) -> Result<(), StoreError> {
+        Ok(Redis::flush_vote(self, fip_number, ntw)?)
+    }
+
+    async fn delete_vote(&mut self, fip_number: FipNumber, ntw: Network) -> Result<(), StoreError> {
+        Ok(Redis::delete_vote(self, fip_number, ntw).await?)
+    }
+
+    fn flush_all(&mut self) -> Result<(), StoreError> {
+        Ok(Redis::flush_all(self)?)
+    }
+}
+
+impl LookupKey {
+    /// Namespaced key such as `fipvote:mainnet:votes:1`, readable from redis-cli
+    fn to_key(&self) -> String {
+        match self {
+            LookupKey::Votes(fip, ntw) => format!("{}{}:votes:{}", KEY_PREFIX, ntw.name(), fip),
+            LookupKey::Timestamp(fip, ntw) => {
+                format!("{}{}:started:{}", KEY_PREFIX, ntw.name(), fip)
+            }
+            LookupKey::Voter(ntw, voter) => {
+                format!("{}{}:voter:{:?}", KEY_PREFIX, ntw.name(), voter)
+            }
+            LookupKey::VoteStarters(ntw) => format!("{}{}:starters", KEY_PREFIX, ntw.name()),
+            LookupKey::Admins(ntw) => format!("{}{}:admins", KEY_PREFIX, ntw.name()),
+            LookupKey::AllVotes(ntw) => format!("{}{}:fips", KEY_PREFIX, ntw.name()),
+            LookupKey::Storage(ntw, fip) => {
+                format!("{}{}:storage:{}", KEY_PREFIX, ntw.name(), fip)
+            }
+            LookupKey::Network(voter) => format!("{}network:{:?}", KEY_PREFIX, voter),
+            LookupKey::RegisteredVoters(ntw) => format!("{}{}:voters", KEY_PREFIX, ntw.name()),
+            LookupKey::Silence(fip, ntw) => {
+                format!("{}{}:silence:{}", KEY_PREFIX, ntw.name(), fip)
+            }
+            LookupKey::Metadata(fip, ntw) => {
+                format!("{}{}:metadata:{}", KEY_PREFIX, ntw.name(), fip)
+            }
+            LookupKey::FinalResults(fip, ntw) => {
+                format!("{}{}:results:{}", KEY_PREFIX, ntw.name(), fip)
+            }
+            LookupKey::VoterClass(ntw, voter) => {
+                format!("{}{}:class:{:?}", KEY_PREFIX, ntw.name(), voter)
+            }
+            LookupKey::Fil(ntw, fip) => format!("{}{}:fil:{}", KEY_PREFIX, ntw.name(), fip),
+            LookupKey::Round(fip, ntw) => format!("{}{}:round:{}", KEY_PREFIX, ntw.name(), fip),
+            LookupKey::PreviousRound(fip, ntw) => {
+                format!("{}{}:previous_round:{}", KEY_PREFIX, ntw.name(), fip)
+            }
+            LookupKey::Receipt(id) => format!("{}receipt:{}", KEY_PREFIX, hex::encode(id)),
+            LookupKey::SchemaVersion => format!("{}schema_version", KEY_PREFIX),
+            LookupKey::Published(fip, ntw) => {
+                format!("{}{}:published:{}", KEY_PREFIX, ntw.name(), fip)
+            }
+            LookupKey::Options(fip, ntw) => {
+                format!("{}{}:options:{}", KEY_PREFIX, ntw.name(), fip)
+            }
+            LookupKey::Quorum(fip, ntw) => format!("{}{}:quorum:{}", KEY_PREFIX, ntw.name(), fip),
+            LookupKey::Weighting(fip, ntw) => {
+                format!("{}{}:weighting:{}", KEY_PREFIX, ntw.name(), fip)
+            }
+            LookupKey::MinPower(fip, ntw) => {
+                format!("{}{}:min_power:{}", KEY_PREFIX, ntw.name(), fip)
+            }
+            LookupKey::Ended(fip, ntw) => format!("{}{}:ended:{}", KEY_PREFIX, ntw.name(), fip),
+            LookupKey::Extension(fip, ntw) => {
+                format!("{}{}:extension:{}", KEY_PREFIX, ntw.name(), fip)
+            }
+            LookupKey::History(ntw, voter) => {
+                format!("{}{}:history:{:?}", KEY_PREFIX, ntw.name(), voter)
+            }
+            LookupKey::Expiry(ntw, voter) => {
+                format!("{}{}:expires:{:?}", KEY_PREFIX, ntw.name(), voter)
+            }
+            LookupKey::Worker(ntw, voter) => {
+                format!("{}{}:worker:{:?}", KEY_PREFIX, ntw.name(), voter)
+            }
+            LookupKey::Nonce(nonce) => format!("{}nonce:{}", KEY_PREFIX, hex::encode(nonce)),
+            LookupKey::RegistrationQueue => format!("{}registration_queue", KEY_PREFIX),
+            LookupKey::RegistrationStatus(voter) => {
+                format!("{}registration:{:?}", KEY_PREFIX, voter)
+            }
+            LookupKey::Events => format!("{}events", KEY_PREFIX),
+            LookupKey::ResultsVersion => format!("{}results_version", KEY_PREFIX),
+            LookupKey::Lock(fip, ntw) => format!("{}{}:lock:{}", KEY_PREFIX, ntw.name(), fip),
+            LookupKey::FipInfo(fip) => format!("{}fip:{}", KEY_PREFIX, fip),
+            LookupKey::Announced(fip, ntw) => {
+                format!("{}{}:announced:{}", KEY_PREFIX, ntw.name(), fip)
+            }
+            LookupKey::Offers(ntw, voter) => {
+                format!("{}{}:offers:{:?}", KEY_PREFIX, ntw.name(), voter)
+            }
+            LookupKey::Delegators(ntw, sp_id) => {
+                format!("{}{}:delegators:{}", KEY_PREFIX, ntw.name(), sp_id)
+            }
+        }
+    }
+
+    /// Binary key used before version 2, only read when migrating
+    fn legacy_bytes(&self) -> Vec<u8> {
+        let (lookup_type, fip) = match self {
+            LookupKey::Votes(fip, ntw) => (legacy_network_byte(*ntw), fip),
+            LookupKey::Timestamp(fip, ntw) => (9 + legacy_network_byte(*ntw), fip),
+            LookupKey::Silence(fip, ntw) => (11 + legacy_network_byte(*ntw), fip),
+            LookupKey::Metadata(fip, ntw) => (13 + legacy_network_byte(*ntw), fip),
+            LookupKey::FinalResults(fip, ntw) => (15 + legacy_network_byte(*ntw), fip),
+            LookupKey::Round(fip, ntw) => (23 + legacy_network_byte(*ntw), fip),
+            LookupKey::PreviousRound(fip, ntw) => (25 + legacy_network_byte(*ntw), fip),
+            LookupKey::Storage(ntw, fip) => (27 + legacy_network_byte(*ntw), fip),
+            LookupKey::Fil(ntw, fip) => (29 + legacy_network_byte(*ntw), fip),
+            LookupKey::Voter(ntw, voter) => return address_bytes(legacy_network_byte(*ntw), voter),
+            LookupKey::Network(voter) => return address_bytes(2, voter),
+            LookupKey::VoterClass(ntw, voter) => {
+                return address_bytes(3 + legacy_network_byte(*ntw), voter)
+            }
+            LookupKey::VoteStarters(ntw) => {
+                return vec![8, 0, 0, 8, 1, 3, 5, legacy_network_byte(*ntw)]
+            }
+            LookupKey::AllVotes(ntw) => {
+                return vec![8, 0, 0, 8, 1, 3, 187, legacy_network_byte(*ntw)]
+            }
+            LookupKey::RegisteredVoters(ntw) => {
+                return vec![8, 0, 0, 8, 1, 3, 42, legacy_network_byte(*ntw)]
+            }
+            LookupKey::SchemaVersion => return vec![8, 0, 0, 8, 1, 3, 118, 0],
+            LookupKey::Admins(..)
+            | LookupKey::Published(..)
+            | LookupKey::Options(..)
+            | LookupKey::Quorum(..)
+            | LookupKey::Weighting(..)
+            | LookupKey::MinPower(..)
+            | LookupKey::Ended(..)
+            | LookupKey::Extension(..)
+            | LookupKey::History(..)
+            | LookupKey::Expiry(..)
+            | LookupKey::Worker(..)
+            | LookupKey::Nonce(..)
+            | LookupKey::RegistrationQueue
+            | LookupKey::RegistrationStatus(..)
+            | LookupKey::Events
+            | LookupKey::Lock(..)
+            | LookupKey::FipInfo(..)
+            | LookupKey::Announced(..)
+            | LookupKey::ResultsVersion
+            | LookupKey::Offers(..)
+            | LookupKey::Delegators(..) => {
+                unreachable!(
+                    "Admins, published results, vote rules, voter histories, nonces, queued registrations, the event log, locks, synced FIPs, announcements, the results version, delegation offers and delegators postdate binary keys"
+                )
+            }
+            LookupKey::Receipt(id) => {
+                let mut bytes = vec![5];
+                bytes.extend_from_slice(id);
+                return bytes;
+            }
+        };
+        fip_bytes(*fip, lookup_type)
+    }
+
+    /// Decodes a binary key used before version 2
+    ///
+    /// Returns `None` for keys of other layouts, such as the tallies removed in version 1
+    fn from_legacy_bytes(bytes: &[u8]) -> Option<LookupKey> {
+        let network = |byte: u8| match byte {
+            0 => Some(Network::Mainnet),
+            1 => Some(Network::Testnet),
+            _ => None,
+        };
+
+        match bytes.len() {
+            5 => {
+                let fip = u32::from_be_bytes(bytes[..4].try_into().ok()?);
+                let fip = FipNumber::try_from(fip).ok()?;
+                let lookup_type = bytes[4];
+                let key = match lookup_type {
+                    0 | 1 => LookupKey::Votes(fip, network(lookup_type)?),
+                    9 | 10 => LookupKey::Timestamp(fip, network(lookup_type - 9)?),
+                    11 | 12 => LookupKey::Silence(fip, network(lookup_type - 11)?),
+                    13 | 14 => LookupKey::Metadata(fip, network(lookup_type - 13)?),
+                    15 | 16 => LookupKey::FinalResults(fip, network(lookup_type - 15)?),
+                    23 | 24 => LookupKey::Round(fip, network(lookup_type - 23)?),
+                    25 | 26 => LookupKey::PreviousRound(fip, network(lookup_type - 25)?),
+                    27 | 28 => LookupKey::Storage(network(lookup_type - 27)?, fip),
+                    29 | 30 => LookupKey::Fil(network(lookup_type - 29)?, fip),
+                    _ => return None,
+                };
+                Some(key)
+            }
+            8 if bytes[..6] == [8, 0, 0, 8, 1, 3] => {
+                let ntw = network(bytes[7]);
+                match bytes[6] {
+                    5 => Some(LookupKey::VoteStarters(ntw?)),
+                    187 => Some(LookupKey::AllVotes(ntw?)),
+                    42 => Some(LookupKey::RegisteredVoters(ntw?)),
+                    118 => Some(LookupKey::SchemaVersion),
+                    _ => None,
+                }
+            }
+            21 => {
+                let voter = Address::from_slice(&bytes[1..]);
+                match bytes[0] {
+                    0 | 1 => Some(LookupKey::Voter(network(bytes[0])?, voter)),
+                    2 => Some(LookupKey::Network(voter)),
+                    3 | 4 => Some(LookupKey::VoterClass(network(bytes[0] - 3)?, voter)),
+                    _ => None,
+                }
+            }
+            33 if bytes[0] == 5 => Some(LookupKey::Receipt(bytes[1..].try_into().ok()?)),
+            _ => None,
+        }
+    }
+}
+
+/// Storage providers whose worker or owner still uses the key that signed the
+/// registration
+///
+/// Storage providers that rotated the key are left out of the ballot until
+/// they register again with the new key. Registrations approved by a multisig
+/// are tied to its owner rather than a worker key and are kept as is
+pub(crate) async fn ballot_delegates(
+    voter: Address,
+    sp_ids: &[u32],
+    worker_address: &str,
+    ntw: Network,
+) -> Result<Vec<u32>, RedisError> {
+    if is_msig_address(worker_address) {
+        return Ok(sp_ids.to_vec());
+    }
+
+    let mut delegates = Vec::with_capacity(sp_ids.len());
+    for &sp_id in sp_ids {
+        let signed_by_worker = match fetch_worker_address(sp_id, ntw).await {
+            Ok(worker) => worker.is_some_and(|worker| worker.eq_ignore_ascii_case(worker_address)),
+            Err(_) => {
+                return Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Error fetching worker address",
+                )))
+            }
+        };
+
+        // Only looked up for registrations the worker didn't sign
+        let signed_by_owner = match signed_by_worker {
+            true => false,
+            false => match fetch_owner_address(sp_id, ntw).await {
+                Ok(owner) => owner.is_some_and(|owner| owner.eq_ignore_ascii_case(worker_address)),
+                Err(_) => {
+                    return Err(RedisError::from((
+                        redis::ErrorKind::TypeError,
+                        "Error fetching owner address",
+                    )))
+                }
+            },
+        };
+
+        match signed_by_worker || signed_by_owner {
+            true => delegates.push(sp_id),
+            false => println!(
+                "Worker and owner keys of {} changed since {:?} registered, leaving it out of the ballot",
+                sp_id_format(ntw, sp_id),
+                voter
+            ),
+        }
+    }
+
+    Ok(delegates)
+}
+
+/// FIL balance a token holder's ballot is weighted by
+async fn ballot_fil(voter: Address, ntw: Network) -> Result<u128, RedisError> {
+    match fetch_fil_balance(voter, ntw).await {
+        Ok(fil) => Ok(fil),
+        Err(_) => Err(RedisError::from((
+            redis::ErrorKind::TypeError,
+            "Error fetching FIL balance",
+        ))),
+    }
+}
+
+/// Decodes a receipt id, which is the hex encoded hash of the ballot signature
+fn receipt_key(id: &str) -> Option<[u8; 32]> {
+    hex::decode(id).ok()?.try_into().ok()
+}
+
+/// A ballot with the keys it is read from and written to in its transaction
+struct BallotWrite {
+    ballot: RecordedBallot,
+    receipt: Option<VoteReceipt>,
+    history: VoterBallot,
+    history_key: String,
+    votes_key: String,
+    storage_key: String,
+    fil_key: String,
+    results_key: String,
+    receipt_lookup: Option<String>,
+}
+
+impl BallotWrite {
+    fn new(mut ballot: RecordedBallot, receipt: Option<VoteReceipt>, round: u64) -> Self {
+        // Ballots logged before the class and balance were kept on them carry
+        // them beside the vote, the stored ballot keeps them for the tally and
+        // for a revote to take back
+        let (recorded_at, powers) = (
+            ballot.vote.recorded_at(),
+            ballot.vote.storage_powers().clone(),
+        );
+        ballot
+            .vote
+            .record(recorded_at, ballot.class, powers, ballot.fil);
+
+        let RecordedBallot {
+            fip_number,
+            ntw,
+            voter,
+            storage,
+            fil,
+            ..
+        } = ballot;
+
+        let history = VoterBallot {
+            fip_number,
+            round,
+            choice: ballot.vote.choice(),
+            storage_size: storage,
+            fil,
+            recorded_at: ballot.vote.recorded_at(),
+        };
+        let receipt_lookup = receipt
+            .as_ref()
+            .and_then(|r| receipt_key(&r.id))
+            .map(|id| LookupKey::Receipt(id).to_key());
+
+        Self {
+            history,
+            history_key: LookupKey::History(ntw, voter).to_key(),
+            votes_key: LookupKey::Votes(fip_number, ntw).to_key(),
+            storage_key: LookupKey::Storage(ntw, fip_number).to_key(),
+            fil_key: LookupKey::Fil(ntw, fip_number).to_key(),
+            results_key: LookupKey::FinalResults(fip_number, ntw).to_key(),
+            receipt_lookup,
+            ballot,
+            receipt,
+        }
+    }
+
+    /// Keys a concurrent change to would invalidate the transaction
+    fn watched(&self) -> Vec<String> {
+        let mut watched = vec![
+            self.votes_key.clone(),
+            self.storage_key.clone(),
+            self.fil_key.clone(),
+            self.results_key.clone(),
+        ];
+        watched.extend(self.receipt_lookup.clone());
+
+        watched
+    }
+
+    /// The event the ballot is logged with once written
+    fn event(&self, allow_revote: bool) -> Event {
+        Event::BallotRecorded {
+            ballot: self.ballot.clone(),
+            allow_revote,
+            receipt: self.receipt.clone(),
+        }
+    }
+
+    /// Queues the writes of the ballot on the transaction
+    ///
+    /// Returns false without queueing anything when a concurrent retry of the
+    /// same submission already landed
+    fn queue(
+        &self,
+        con: &mut Connection,
+        pipe: &mut redis::Pipeline,
+        allow_revote: bool,
+    ) -> Result<bool, RedisError> {
+        let vote = &self.ballot.vote;
+
+        // A concurrent retry of the same submission already landed
+        if let Some(key) = &self.receipt_lookup {
+            if con.exists(key)? {
+                return Ok(false);
+            }
+        }
+
+        // Results another instance stored once the vote concluded no longer change
+        if con.exists(&self.results_key)? {
+            return Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Vote is not active",
+            )));
+        }
+
+        // If this vote is a duplicate either replace the previous vote or throw an error
+        let previous = match read_votes(con, &self.votes_key)?
+            .into_iter()
+            .find(|v| v == vote)
+        {
+            Some(_) if !allow_revote => {
+                return Err(RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "Vote already exists",
+                )));
+            }
+            previous => previous,
+        };
+        let previous_choice = previous.as_ref().map(|v| v.choice());
+        let previous_class = previous.as_ref().map(|v| v.class());
+
+        // The previous choice loses the weight its ballot was recorded with,
+        // the voter's power may have changed since
+        let mut storage_totals = Vec::new();
+        let mut fil_totals = Vec::new();
+        for choice in previous_choice.iter().chain([vote.choice()].iter()) {
+            if storage_totals.iter().any(|(c, _)| c == choice) {
+                continue;
+            }
+            storage_totals.push((choice.clone(), read_tally(con, &self.storage_key, choice)?));
+            fil_totals.push((choice.clone(), read_tally(con, &self.fil_key, choice)?));
+        }
+        let (previous_storage, previous_fil) = match &previous {
+            Some(previous) => (
+                tally::checked_sum(previous.storage_powers().values().copied())?,
+                previous.fil(),
+            ),
+            None => (0, 0),
+        };
+        move_weight(
+            &mut storage_totals,
+            previous_choice.as_ref().map(|c| (c, previous_storage)),
+            &vote.choice(),
+            self.ballot.storage,
+        )?;
+        move_weight(
+            &mut fil_totals,
+            previous_choice.as_ref().map(|c| (c, previous_fil)),
+            &vote.choice(),
+            self.ballot.fil,
+        )?;
+
+        // Replace the previous ballot in the list of votes
+        if let Some(previous) = previous {
+            pipe.lrem(
+                &self.votes_key,
+                1,
+                serde_json::to_string(&previous).unwrap(),
+            )
+            .ignore();
+        }
+        pipe.rpush(&self.votes_key, serde_json::to_string(vote).unwrap())
+            .ignore();
+
+        // A voter whose class changed since their previous ballot takes its
+        // weight back from the tally of the class it was cast as
+        let counts = |class| self.ballot.class == class || previous_class == Some(class);
+        if counts(VoterClass::StorageProvider) {
+            for (choice, total) in storage_totals {
+                pipe.hset(
+                    &self.storage_key,
+                    tally_field(&choice),
+                    total.to_be_bytes().to_vec(),
+                )
+                .ignore();
+            }
+        }
+
+        if counts(VoterClass::TokenHolder) {
+            for (choice, total) in fil_totals {
+                pipe.hset(
+                    &self.fil_key,
+                    tally_field(&choice),
+                    total.to_be_bytes().to_vec(),
+                )
+                .ignore();
+            }
+        }
+
+        if let (Some(receipt), Some(key)) = (&self.receipt, &self.receipt_lookup) {
+            pipe.set(key, serde_json::to_string(receipt).unwrap())
+                .ignore();
+        }
+
+        pipe.hset(
+            &self.history_key,
+            self.ballot.fip_number.get(),
+            serde_json::to_string(&self.history).unwrap(),
+        )
+        .ignore();
+
+        Ok(true)
+    }
+}
+
+/// Moves a ballot from the previous choice, with the weight the previous
+/// ballot was recorded with, to the new choice with its own weight
+///
+/// A previous choice holding less than the previous ballot was recorded with
+/// or a total that would pass `u128::MAX` is rejected so the ballot isn't
+/// recorded
+fn move_weight(
+    totals: &mut [(VoteOption, u128)],
+    previous: Option<(&VoteOption, u128)>,
+    choice: &VoteOption,
+    weight: u128,
+) -> Result<(), TallyError> {
+    for (c, total) in totals.iter_mut() {
+        if let Some((previous, previous_weight)) = previous {
+            if previous == &*c {
+                *total = total
+                    .checked_sub(previous_weight)
+                    .ok_or(TallyError::Underflow)?;
+            }
+        }
+        if c == choice {
+            *total = total.checked_add(weight).ok_or(TallyError::Overflow)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Storage providers the voter is registered for on the network
+fn read_delegates(
+    con: &mut Connection,
+    voter: Address,
+    ntw: Network,
+) -> Result<Vec<u32>, RedisError> {
+    let key = LookupKey::Voter(ntw, voter).to_key();
+    match con.get::<String, Vec<u32>>(key) {
+        Ok(delegates) => Ok(delegates),
+        Err(e) => match e.kind() {
+            redis::ErrorKind::TypeError => Ok(Vec::new()),
+            _ => Err(e),
+        },
+    }
+}
+
+/// Keys of the registration of the voter on the network
+fn registration_keys(voter: Address, ntw: Network) -> [String; 4] {
+    [
+        LookupKey::Voter(ntw, voter).to_key(),
+        LookupKey::VoterClass(ntw, voter).to_key(),
+        LookupKey::Expiry(ntw, voter).to_key(),
+        LookupKey::Worker(ntw, voter).to_key(),
+    ]
+}
+
+/// Queues removing the registration of the voter on the network, who was
+/// registered for `sp_ids`
+fn queue_unregister_voter(
+    pipe: &mut redis::Pipeline,
+    voter: Address,
+    ntw: Network,
+    sp_ids: &[u32],
+) {
+    queue_delegators(pipe, voter, ntw, sp_ids, &[]);
+
+    for key in registration_keys(voter, ntw) {
+        pipe.del(key).ignore();
+    }
+    pipe.srem(LookupKey::RegisteredVoters(ntw).to_key(), voter.as_bytes())
+        .ignore();
+}
+
+/// Queues appending a change to the event log, to be written in the same
+/// MULTI/EXEC as the change itself
+///
+/// Bumps the results version along with it, since every change that can
+/// alter a response is logged, and publishes what it left stale in the hot
+/// cache of every server. `now` is when the change was made
+fn queue_event(pipe: &mut redis::Pipeline, now: u64, event: &Event) {
+    pipe.cmd("XADD")
+        .arg(LookupKey::Events.to_key())
+        .arg("*")
+        .arg("timestamp")
+        .arg(now)
+        .arg("event")
+        .arg(serde_json::to_string(event).unwrap())
+        .ignore()
+        .incr(LookupKey::ResultsVersion.to_key(), 1)
+        .ignore()
+        .cmd("PUBLISH")
+        .arg(hot_cache::CHANNEL)
+        .arg(serde_json::to_string(&event.invalidation()).unwrap())
+        .ignore();
+}
+
+/// Drops what a written change left stale from the hot cache of this server,
+/// so it reads its own changes right away instead of once the publish arrives
+fn invalidate_own(event: &Event) {
+    hot_cache::invalidate(event.invalidation());
+}
+
+/// Queues moving the voter off the delegators of `removed` and onto those of `added`
+fn queue_delegators(
+    pipe: &mut redis::Pipeline,
+    voter: Address,
+    ntw: Network,
+    removed: &[u32],
+    added: &[u32],
+) {
+    for sp_id in removed {
+        pipe.srem(
+            LookupKey::Delegators(ntw, *sp_id).to_key(),
+            voter.as_bytes(),
+        )
+        .ignore();
+    }
+    for sp_id in added {
+        pipe.sadd(
+            LookupKey::Delegators(ntw, *sp_id).to_key(),
+            voter.as_bytes(),
+        )
+        .ignore();
+    }
+}
+
+/// Reads the list of ballots stored at the key
+fn read_votes(con: &mut Connection, key: &str) -> Result<Vec<Vote>, RedisError> {
+    let ballots: Vec<String> = con.lrange(key, 0, -1)?;
+
+    ballots
+        .iter()
+        .map(|v| match serde_json::from_str(v.as_str()) {
+            Ok(vote) => Ok(vote),
+            Err(_) => Err(RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Error decoding stored votes",
+            ))),
+        })
+        .collect()
+}
+
+/// Reads the total behind a choice from the tally hash stored at the key
+fn read_tally(con: &mut Connection, key: &str, choice: &VoteOption) -> Result<u128, RedisError> {
+    let bytes: Vec<u8> = con.hget(key, tally_field(choice))?;
+    if bytes.is_empty() {
+        return Ok(0);
+    }
+    match bytes.try_into() {
+        Ok(bytes) => Ok(u128::from_be_bytes(bytes)),
+        Err(_) => Err(RedisError::from((
+            redis::ErrorKind::TypeError,
+            "Error retrieving vote tally",
+        ))),
+    }
+}
+
+/// Decodes a json blob written by the layout before version 1
+fn decode_legacy<T: serde::de::DeserializeOwned>(json: &str) -> Result<T, RedisError> {
+    match serde_json::from_str(json) {
+        Ok(value) => Ok(value),
+        Err(_) => Err(RedisError::from((
+            redis::ErrorKind::TypeError,
+            "Error decoding data to migrate",
+        ))),
+    }
+}
+
+/// Field of a choice in the storage and FIL tally hashes
+///
+/// The built in choices keep the numeric fields they were first stored with
+fn tally_field(choice: &VoteOption) -> String {
+    match choice {
+        VoteOption::Yay => 0.to_string(),
+        VoteOption::Nay => 1.to_string(),
+        VoteOption::Abstain => 2.to_string(),
+        VoteOption::Unknown(byte) => (0x80 | byte).to_string(),
+        VoteOption::Named(name) => format!("named:{}", name),
+    }
+}
+
+/// Byte a network was written as in binary keys, which predate configured networks
+fn legacy_network_byte(ntw: Network) -> u8 {
+    match ntw {
+        Network::Mainnet => 0,
+        Network::Testnet => 1,
+        Network::Configured(_) => unreachable!("Configured networks postdate binary keys"),
+    }
+}
+
+/// Binary key of a FIP lookup before version 2
+fn fip_bytes(fip: FipNumber, lookup_type: u8) -> Vec<u8> {
+    let mut bytes = fip.get().to_be_bytes().to_vec();
+    bytes.push(lookup_type);
+    bytes
+}
+
+/// Binary key of an address lookup before version 2
+fn address_bytes(lookup_type: u8, voter: &Address) -> Vec<u8> {
+    let mut bytes = vec![lookup_type];
+    bytes.extend_from_slice(voter.as_bytes());
+    bytes
+}
+
+/// Binary key each tally was kept under before version 1 moved them to hashes
+///
+/// Storage keys collide between mainnet abstain and testnet yay
+fn legacy_tally_key(fip: FipNumber, ntw: Network, choice: &VoteOption, fil: bool) -> Vec<u8> {
+    let choice = u8::from(choice.clone());
+    let lookup_type = match fil {
+        false => (choice + 2) * (legacy_network_byte(ntw) + 1),
+        true => 17 + choice * 2 + legacy_network_byte(ntw),
+    };
+    fip_bytes(fip, lookup_type)
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, ToSchema)]
+pub struct VoteResults {
+    pub(crate) yay: u64,
+    pub(crate) nay: u64,
+    pub(crate) abstain: u64,
+    pub(crate) yay_storage_size: u128,
+    pub(crate) nay_storage_size: u128,
+    pub(crate) abstain_storage_size: u128,
+    /// Token holder balances behind each choice in attoFIL
+    #[serde(default)]
+    pub(crate) yay_fil: u128,
+    #[serde(default)]
+    pub(crate) nay_fil: u128,
+    #[serde(default)]
+    pub(crate) abstain_fil: u128,
+    /// Tallies of each option of a vote that is not Yay, Nay or Abstain
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) options: BTreeMap<String, OptionTally>,
+    /// Ballots per voter class, storage sizes only count storage providers
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) classes: BTreeMap<VoterClass, ClassTally>,
+    /// Movement since the previous round, set once a later round concludes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    delta: Option<RoundDelta>,
+    /// Set once a vote started with quorum rules concludes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    quorum_met: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    outcome: Option<VoteOutcome>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata: Option<VoteMetadata>,
+    /// Title, status and discussion link from the FIPs repository, unset until synced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fip: Option<FipInfo>,
+    /// How the storage sizes were weighted, left out for raw byte power so
+    /// results attested before weightings existed keep their hash
+    #[serde(default, skip_serializing_if = "Weighting::is_raw")]
+    pub(crate) weighting: Weighting,
+    /// Ballots of storage providers left out of the tally for being cast with
+    /// less power than the vote requires
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub(crate) below_min_power: u64,
+}
+
+/// Results tallied before minimum powers existed serialize as they always did
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// Proof that a ballot was recorded
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct VoteReceipt {
+    pub id: String,
+    pub fip_number: FipNumber,
+    pub choice: VoteOption,
+    pub address: Address,
+    /// Unix timestamp the ballot was recorded at
+    pub recorded_at: u64,
+}
+
+/// A ballot as kept in the history of the voter who cast it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct VoterBallot {
+    pub fip_number: FipNumber,
+    pub round: u64,
+    pub choice: VoteOption,
+    /// Raw byte power the ballot was counted with, 0 unless cast by a storage provider
+    pub storage_size: u128,
+    /// Balance in attoFIL the ballot was counted with, 0 unless cast by a token holder
+    pub fil: u128,
+    /// Unix timestamp the ballot was recorded at
+    pub recorded_at: u64,
+}
+
+impl VoterBallot {
+    /// Rebuilds the history entry of a ballot that is already recorded
+    ///
+    /// Balances are not kept with ballots, so token holder ballots are listed
+    /// without the FIL they were counted with
+    fn recorded(fip_number: FipNumber, round: u64, ballot: &Vote) -> Self {
+        let audit = ballot.audit(false);
+
+        Self {
+            fip_number,
+            round,
+            choice: audit.choice,
+            storage_size: audit.storage_size,
+            fil: 0,
+            recorded_at: audit.recorded_at,
+        }
+    }
+}
+
+/// A page of the voters registered on a network
+#[derive(Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+pub struct VoterPage {
+    /// Number of registered voters across every page
+    pub total: usize,
+    pub voters: Vec<RegisteredVoter>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+pub struct RegisteredVoter {
+    pub address: Address,
+    /// Storage providers the voter votes for, empty for voters without storage
+    pub delegates: Vec<String>,
+}
+
+/// The outcome of a round kept when the next round starts
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RoundSummary {
+    pub round: u64,
+    pub results: VoteResults,
+    pub ballots: Vec<Vote>,
+}
+
+/// How a round moved compared to the round before it
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, ToSchema)]
+pub struct RoundDelta {
+    pub previous_round: u64,
+    pub yay: i64,
+    pub nay: i64,
+    pub abstain: i64,
+    pub yay_storage_size: i128,
+    pub nay_storage_size: i128,
+    pub abstain_storage_size: i128,
+    /// Change in the number of ballots cast
+    pub turnout: i64,
+    /// Voters that voted in both rounds
+    pub returning_voters: u64,
+    /// Returning voters whose choice changed
+    pub changed_voters: u64,
+    /// Voters that only voted in this round
+    pub new_voters: u64,
+    /// Voters that only voted in the previous round
+    pub departed_voters: u64,
+}
+
+impl RoundDelta {
+    pub fn new(previous: &RoundSummary, results: &VoteResults, ballots: &[Vote]) -> Self {
+        let before = &previous.results;
+
+        let mut delta = RoundDelta {
+            previous_round: previous.round,
+            yay: results.yay as i64 - before.yay as i64,
+            nay: results.nay as i64 - before.nay as i64,
+            abstain: results.abstain as i64 - before.abstain as i64,
+            yay_storage_size: results.yay_storage_size as i128 - before.yay_storage_size as i128,
+            nay_storage_size: results.nay_storage_size as i128 - before.nay_storage_size as i128,
+            abstain_storage_size: results.abstain_storage_size as i128
+                - before.abstain_storage_size as i128,
+            turnout: ballots.len() as i64 - previous.ballots.len() as i64,
+            ..Default::default()
+        };
+
+        for ballot in ballots {
+            match previous
+                .ballots
+                .iter()
+                .find(|b| b.voter() == ballot.voter())
+            {
+                Some(old) => {
+                    delta.returning_voters += 1;
+                    if old.choice() != ballot.choice() {
+                        delta.changed_voters += 1;
+                    }
+                }
+                None => delta.new_voters += 1,
+            }
+        }
+        delta.departed_voters =
+            (previous.ballots.len() as u64).saturating_sub(delta.returning_voters);
+
+        delta
+    }
+}
+
+/// Whether a vote passed its quorum rules
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+pub enum VoteOutcome {
+    Passed,
+    Failed,
+    QuorumNotMet,
+}
+
+/// Ballots cast by a single voter class
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, ToSchema)]
+pub struct ClassTally {
+    pub yay: u64,
+    pub nay: u64,
+    pub abstain: u64,
+    /// Ballots for each option of a vote that is not Yay, Nay or Abstain
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub options: BTreeMap<String, u64>,
+}
+
+/// Ballots, storage and token holder balance behind an option of a vote
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, ToSchema)]
+pub struct OptionTally {
+    pub votes: u64,
+    pub storage_size: u128,
+    pub fil: u128,
+}
+
+impl VoteResults {
+    pub fn set_metadata(&mut self, metadata: Option<VoteMetadata>) {
+        self.metadata = metadata;
+    }
+
+    pub fn set_fip(&mut self, fip: Option<FipInfo>) {
+        self.fip = fip;
+    }
+
+    /// How the storage behind each choice was weighted
+    pub fn weighting(&self) -> Weighting {
+        self.weighting
+    }
+
+    /// Number of ballots left out of the tally for being under the minimum power
+    pub fn below_min_power(&self) -> u64 {
+        self.below_min_power
+    }
+
+    /// Judges the storage behind each choice against the quorum rules
+    ///
+    /// Every ballot counts towards the quorum, the pass threshold is the share
+    /// of the Yay and Nay storage that voted Yay
+    pub fn judge(&mut self, rules: &QuorumRules) -> Result<(), TallyError> {
+        let decided = tally::checked_sum([self.yay_storage_size, self.nay_storage_size])?;
+        let participation = tally::checked_sum([decided, self.abstain_storage_size])?;
+        let quorum_met = participation >= rules.min_storage_size;
+
+        let outcome = if !quorum_met {
+            VoteOutcome::QuorumNotMet
+        } else if decided > 0
+            && checked_mul(self.yay_storage_size, 100)?
+                >= checked_mul(decided, rules.pass_percentage as u128)?
+        {
+            VoteOutcome::Passed
+        } else {
+            VoteOutcome::Failed
+        };
+
+        self.quorum_met = Some(quorum_met);
+        self.outcome = Some(outcome);
+
+        Ok(())
+    }
+
+    /// How the vote fared against its quorum rules, `None` for votes without any
+    pub fn outcome(&self) -> Option<VoteOutcome> {
+        self.outcome
+    }
+
+    /// Whether the storage that voted met the quorum, `None` for votes without rules
+    pub fn quorum_met(&self) -> Option<bool> {
+        self.quorum_met
+    }
+
+    /// Yay, Nay and Abstain followed by the options the vote was started with
+    pub fn choices(&self) -> Vec<VoteOption> {
+        let mut choices = vec![VoteOption::Yay, VoteOption::Nay, VoteOption::Abstain];
+        choices.extend(self.options.keys().cloned().map(VoteOption::Named));
+        choices
+    }
+
+    /// Storage behind every choice, named options included, staying at
+    /// `u128::MAX` rather than wrapping
+    pub fn total_storage_size(&self) -> u128 {
+        [
+            self.yay_storage_size,
+            self.nay_storage_size,
+            self.abstain_storage_size,
+        ]
+        .into_iter()
+        .chain(self.options.values().map(|o| o.storage_size))
+        .fold(0, u128::saturating_add)
+    }
+
+    /// Number of ballots cast for the choice
+    pub fn ballots(&self, choice: &VoteOption) -> u64 {
+        match choice {
+            VoteOption::Yay => self.yay,
+            VoteOption::Nay => self.nay,
+            VoteOption::Abstain => self.abstain,
+            VoteOption::Unknown(_) => 0,
+            VoteOption::Named(name) => self.options.get(name).map_or(0, |o| o.votes),
+        }
+    }
+
+    /// Storage power in bytes behind the choice
+    pub fn storage_size(&self, choice: &VoteOption) -> u128 {
+        match choice {
+            VoteOption::Yay => self.yay_storage_size,
+            VoteOption::Nay => self.nay_storage_size,
+            VoteOption::Abstain => self.abstain_storage_size,
+            VoteOption::Unknown(_) => 0,
+            VoteOption::Named(name) => self.options.get(name).map_or(0, |o| o.storage_size),
+        }
+    }
+
+    /// Token holder balance in attoFIL behind the choice
+    pub fn fil(&self, choice: &VoteOption) -> u128 {
+        match choice {
+            VoteOption::Yay => self.yay_fil,
+            VoteOption::Nay => self.nay_fil,
+            VoteOption::Abstain => self.abstain_fil,
+            VoteOption::Unknown(_) => 0,
+            VoteOption::Named(name) => self.options.get(name).map_or(0, |o| o.fil),
+        }
+    }
+}
+
+/// Participation in a vote compared to the power of the whole network
+#[derive(Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+pub struct Turnout {
+    pub voters: u64,
+    /// Distinct storage providers the ballots were cast for
+    pub storage_providers: u64,
+    /// Raw byte power in bytes behind the ballots
+    pub storage_size: u128,
+    /// Raw byte power in bytes of the whole network
+    pub network_storage_size: u128,
+    /// Share of the network power that voted, in percent
+    pub turnout_percentage: f64,
+}
+
+impl Turnout {
+    pub fn new(
+        voters: u64,
+        storage_providers: u64,
+        storage_size: u128,
+        network_storage_size: u128,
+    ) -> Self {
+        let turnout_percentage = match network_storage_size {
+            0 => 0.0,
+            total => storage_size as f64 * 100.0 / total as f64,
+        };
+
+        Self {
+            voters,
+            storage_providers,
+            storage_size,
+            network_storage_size,
+            turnout_percentage,
+        }
+    }
+}
+
+/// Registered storage power that did not take part in a vote
+#[derive(Serialize, Deserialize, Debug, Default, Clone, ToSchema)]
+pub struct SilenceReport {
+    silent_voters: u64,
+    silent_storage_size: u128,
+    cohorts: BTreeMap<SpCohort, CohortSilence>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, ToSchema)]
+pub struct CohortSilence {
+    sp_count: u64,
+    storage_size: u128,
+}
+
+impl SilenceReport {
+    /// Report of the silent voters with the power of each storage provider
+    /// delegated to them
+    pub(crate) fn new(silent_voters: u64, powers: impl IntoIterator<Item = u128>) -> Self {
+        let mut report = Self {
+            silent_voters,
+            ..Self::default()
+        };
+        for power in powers {
+            report.add_storage_provider(power);
+        }
+
+        report
+    }
+
+    pub fn silent_voters(&self) -> u64 {
+        self.silent_voters
+    }
+
+    pub fn silent_storage_size(&self) -> u128 {
+        self.silent_storage_size
+    }
+
+    pub fn cohorts(&self) -> &BTreeMap<SpCohort, CohortSilence> {
+        &self.cohorts
+    }
+
+    fn add_storage_provider(&mut self, power: u128) {
+        self.silent_storage_size += power;
+
+        let cohort = self.cohorts.entry(SpCohort::from_power(power)).or_default();
+        cohort.sp_count += 1;
+        cohort.storage_size += power;
+    }
+}
+
+impl CohortSilence {
+    pub fn sp_count(&self) -> u64 {
+        self.sp_count
+    }
+
+    pub fn storage_size(&self) -> u128 {
+        self.storage_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, str::FromStr, time};
+
+    use clap::Parser;
+    use ethers::signers::{LocalWallet, Signer};
+
+    use super::*;
+
+    use crate::{
+        clock::MockClock,
+        fip::MAX_FIP_NUMBER,
+        messages::{
+            vote_registration::test_voter_registration::*,
+            votes::{test_votes::*, ReceivedVote},
+        },
+        test_redis::{StaticPower, TestRedis, TEST_SP_ID, TEST_SP_POWER},
+    };
+
+    async fn redis() -> TestRedis {
+        let mut redis = TestRedis::start();
+
+        // Registered from the signature alone, the worker is not looked up on the chain
+        let pending = test_reg().recover_pending_registration().await.unwrap();
+        redis
+            .register_voter(pending.address, pending.network, pending.sp_ids)
+            .unwrap();
+
+        redis
+    }
+
+    /// Stubs the clock of the store, returning it so tests can move time forward
+    fn mock_clock(redis: &mut Redis) -> Arc<MockClock> {
+        let clock = Arc::new(MockClock::from_system());
+        redis.set_clock(clock.clone());
+        clock
+    }
+
+    fn voter() -> Address {
+        Address::from_str("0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56").unwrap()
+    }
+
+    fn vote_starter() -> Address {
+        authorized_voters()[0]
+    }
+
+    fn networks() -> Vec<Network> {
+        vec![Network::Mainnet, Network::Testnet]
+    }
+
+    fn fip(num: u32) -> FipNumber {
+        FipNumber::try_from(num).unwrap()
+    }
+
+    /// Lists the vote among all votes of the network without starting it
+    fn register_vote_to_all_votes(
+        redis: &mut Redis,
+        fip: FipNumber,
+        ntw: Network,
+    ) -> Result<(), RedisError> {
+        let key = LookupKey::AllVotes(ntw).to_key();
+
+        redis.con.sadd(key, fip.get())
+    }
+
+    #[tokio::test]
+    async fn redis_votes() {
+        let mut redis = redis().await;
+
+        let res = redis.votes(fip(5), Network::Testnet);
+
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test]
+    async fn redis_start_vote() {
+        let mut redis = redis().await;
+
+        let starter = voter();
+
+        for ntw in networks() {
+            let res = redis.start_vote(fip(5), starter, ntw).await;
+
+            assert!(res.is_ok());
+
+            let res = redis.vote_status(fip(5), 60u64, ntw);
+
+            assert!(res.is_ok());
+
+            let status = res.unwrap();
+
+            assert_eq!(status, VoteStatus::InProgress(60u64));
+
+            let res = redis.active_votes(ntw, 69u64);
+            assert!(res.is_ok());
+
+            let active_votes = res.unwrap();
+            assert!(active_votes.contains(&fip(5)));
+        }
+    }
+
+    #[tokio::test]
+    async fn redis_register_voter() {
+        let mut redis = redis().await;
+
+        let res = redis.register_voter(vote_starter(), Network::Mainnet, vec![1u32]);
+
+        assert!(res.is_ok());
+
+        assert_eq!(
+            redis.voter_networks(vote_starter()).unwrap(),
+            vec![Network::Mainnet]
+        );
+
+        let delegates = redis.voter_delegates(vote_starter(), Network::Mainnet);
+
+        assert!(delegates.is_ok());
+
+        let delegates = delegates.unwrap();
+
+        assert_eq!(delegates, vec![1u32]);
+    }
+
+    #[tokio::test]
+    async fn redis_unregister_voter() {
+        let mut redis = redis().await;
+
+        redis
+            .register_voter(vote_starter(), Network::Mainnet, vec![1u32])
+            .unwrap();
+
+        let res = redis.unregister_voter(vote_starter(), Network::Mainnet);
+
+        assert!(res.is_ok());
+
+        assert!(redis.voter_networks(vote_starter()).unwrap().is_empty());
+
+        let delegates = redis.voter_delegates(vote_starter(), Network::Mainnet);
+
+        assert!(delegates.is_ok());
+        assert!(delegates.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn redis_ballot_network() {
+        let mut redis = redis().await;
+
+        // Registering on mainnet keeps the calibration registration
+        redis
+            .register_voter(voter(), Network::Mainnet, vec![1u32])
+            .unwrap();
+        assert_eq!(
+            redis.voter_networks(voter()).unwrap(),
+            vec![Network::Mainnet, Network::Testnet]
+        );
+        assert_eq!(
+            redis.voter_delegates(voter(), Network::Testnet).unwrap(),
+            vec![TEST_SP_ID]
+        );
+
+        redis
+            .start_vote(fip(5), vote_starter(), Network::Testnet)
+            .await
+            .unwrap();
+        assert_eq!(
+            redis.ballot_network(voter(), fip(5), None, 60).unwrap(),
+            Network::Testnet
+        );
+
+        // A FIP voted on in both networks needs the chain id of a typed ballot
+        redis
+            .start_vote(fip(5), vote_starter(), Network::Mainnet)
+            .await
+            .unwrap();
+        assert!(redis.ballot_network(voter(), fip(5), None, 60).is_err());
+        assert_eq!(
+            redis
+                .ballot_network(voter(), fip(5), Some(314), 60)
+                .unwrap(),
+            Network::Mainnet
+        );
+        assert_eq!(
+            redis
+                .ballot_network(voter(), fip(5), Some(314159), 60)
+                .unwrap(),
+            Network::Testnet
+        );
+
+        // Voters registered on neither network aren't authorized to vote
+        assert_eq!(
+            redis
+                .ballot_network(voter(), fip(5), Some(1), 60)
+                .unwrap_err()
+                .kind(),
+            redis::ErrorKind::AuthenticationFailed
+        );
+        assert_eq!(
+            redis
+                .ballot_network(Address::zero(), fip(5), None, 60)
+                .unwrap_err()
+                .kind(),
+            redis::ErrorKind::AuthenticationFailed
+        );
+    }
+
+    #[tokio::test]
+    async fn redis_register_voter_starter() {
+        let mut redis = redis().await;
+
+        for ntw in networks() {
+            let res = redis.register_voter_starter(voter(), ntw);
+
+            assert!(res.is_ok());
+
+            let res = redis.voter_starters(ntw);
+
+            assert!(res.is_ok());
+            assert!(res.unwrap().contains(&voter()));
+        }
+    }
+
+    #[tokio::test]
+    async fn redis_remove_voter_starters() {
+        let mut redis = redis().await;
+        let ntw = Network::Testnet;
+
+        redis.register_voter_starter(vote_starter(), ntw).unwrap();
+
+        // The last starter stays
+        assert!(redis.remove_voter_starters(vote_starter(), ntw).is_err());
+
+        redis.register_voter_starter(voter(), ntw).unwrap();
+        redis.remove_voter_starters(voter(), ntw).unwrap();
+
+        assert_eq!(redis.voter_starters(ntw).unwrap(), vec![vote_starter()]);
+        assert!(redis.remove_voter_starters(voter(), ntw).is_err());
+    }
+
+    #[tokio::test]
+    async fn redis_admins() {
+        let mut redis = redis().await;
+        let ntw = Network::Testnet;
+
+        redis.register_admin(vote_starter(), ntw).unwrap();
+
+        assert!(redis.is_admin(vote_starter(), ntw).unwrap());
+        assert!(!redis.is_admin(vote_starter(), Network::Mainnet).unwrap());
+        assert!(!redis.is_admin(voter(), ntw).unwrap());
+
+        // The last admin stays
+        assert!(redis.remove_admin(vote_starter(), ntw).is_err());
+
+        redis.register_admin(voter(), ntw).unwrap();
+        redis.remove_admin(vote_starter(), ntw).unwrap();
+
+        assert_eq!(redis.admins(ntw).unwrap(), vec![voter()]);
+        assert!(!redis.is_authorized_starter(voter(), ntw).unwrap());
+    }
+
+    #[tokio::test]
+    async fn redis_voter_page() {
+        let mut redis = redis().await;
+        let ntw = Network::Testnet;
+
+        redis.register_voter(voter(), ntw, vec![1u32, 2]).unwrap();
+        redis
+            .register_voter_class(vote_starter(), ntw, VoterClass::CoreDev)
+            .unwrap();
+
+        let mut addresses = vec![voter(), vote_starter()];
+        addresses.sort();
+
+        let page = redis.voter_page(ntw, 0, None).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(
+            page.voters.iter().map(|v| v.address).collect::<Vec<_>>(),
+            addresses
+        );
+
+        let voter_entry = page.voters.iter().find(|v| v.address == voter()).unwrap();
+        assert_eq!(voter_entry.delegates, vec!["t01", "t02"]);
+
+        let page = redis.voter_page(ntw, 1, Some(1)).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.voters.len(), 1);
+        assert_eq!(page.voters[0].address, addresses[1]);
+
+        assert!(redis.voter_page(ntw, 2, Some(1)).unwrap().voters.is_empty());
+    }
+
+    #[tokio::test]
+    async fn redis_turnout() {
+        let mut redis = redis().await;
+        let ntw = Network::Testnet;
+
+        redis.start_vote(fip(1), vote_starter(), ntw).await.unwrap();
+        let vote = test_vote(VoteOption::Yay, 1u32).vote().unwrap();
+        redis
+            .add_vote(fip(1), vote, voter(), 60u64, false)
+            .await
+            .unwrap();
+
+        let results = redis.vote_results(fip(1), 60u64, ntw).await.unwrap();
+        let turnout = redis
+            .turnout(fip(1), ntw, results.total_storage_size() * 4)
+            .unwrap();
+
+        assert_eq!(turnout.voters, 1);
+        assert_eq!(turnout.storage_size, results.total_storage_size());
+        assert_eq!(turnout.network_storage_size, turnout.storage_size * 4);
+        if turnout.storage_size > 0 {
+            assert_eq!(turnout.turnout_percentage, 25.0);
+        }
+
+        assert_eq!(Turnout::new(0, 0, 0, 0).turnout_percentage, 0.0);
+    }
+
+    #[tokio::test]
+    async fn redis_registered_voters() {
+        let mut redis = redis().await;
+
+        for ntw in networks() {
+            redis
+                .register_voter(vote_starter(), ntw, vec![1u32])
+                .unwrap();
+
+            let res = redis.registered_voters(ntw).unwrap();
+
+            assert!(res.contains(&vote_starter()));
+
+            redis.unregister_voter(vote_starter(), ntw).unwrap();
+
+            let res = redis.registered_voters(ntw).unwrap();
+
+            assert!(!res.contains(&vote_starter()));
+        }
+    }
+
+    #[tokio::test]
+    async fn redis_unregister_storage_providers() {
+        let mut redis = redis().await;
+
+        let ntw = Network::Mainnet;
+
+        redis
+            .register_voter(vote_starter(), ntw, vec![1u32])
+            .unwrap();
+
+        let res = redis.unregister_storage_providers(vote_starter(), ntw, &[2u32]);
+
+        assert!(res.is_ok());
+        assert_eq!(
+            redis.voter_delegates(vote_starter(), ntw).unwrap(),
+            vec![1u32]
+        );
+
+        let res = redis.unregister_storage_providers(vote_starter(), ntw, &[1u32]);
+
+        assert!(res.is_ok());
+        assert!(redis
+            .voter_delegates(vote_starter(), ntw)
+            .unwrap()
+            .is_empty());
+        assert!(!redis
+            .registered_voters(ntw)
+            .unwrap()
+            .contains(&vote_starter()));
+        assert!(redis.voter_networks(vote_starter()).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn redis_delegators() {
+        let mut redis = redis().await;
+
+        let ntw = Network::Testnet;
+        assert_eq!(redis.delegators(TEST_SP_ID, ntw).unwrap(), vec![voter()]);
+
+        // Registering again moves the voter off the storage providers it left out
+        redis
+            .register_voter(voter(), ntw, vec![1u32, 2u32])
+            .unwrap();
+        assert!(redis.delegators(TEST_SP_ID, ntw).unwrap().is_empty());
+        redis
+            .register_voter(vote_starter(), ntw, vec![2u32])
+            .unwrap();
+        let mut delegators = redis.delegators(2, ntw).unwrap();
+        delegators.sort();
+        let mut expected = vec![voter(), vote_starter()];
+        expected.sort();
+        assert_eq!(delegators, expected);
+
+        redis
+            .unregister_storage_providers(voter(), ntw, &[2u32])
+            .unwrap();
+        assert_eq!(redis.delegators(2, ntw).unwrap(), vec![vote_starter()]);
+        assert_eq!(redis.delegators(1, ntw).unwrap(), vec![voter()]);
+
+        redis.unregister_voter(vote_starter(), ntw).unwrap();
+        assert!(redis.delegators(2, ntw).unwrap().is_empty());
+        redis.unregister_voter_all(voter()).unwrap();
+        assert!(redis.delegators(1, ntw).unwrap().is_empty());
+
+        // Registrations written before version 5 are indexed when migrating
+        redis.register_voter(voter(), ntw, vec![3u32]).unwrap();
+        redis
+            .con
+            .del::<String, ()>(LookupKey::Delegators(ntw, 3).to_key())
+            .unwrap();
+        redis
+            .con
+            .set::<String, u64, ()>(LookupKey::SchemaVersion.to_key(), 4)
+            .unwrap();
+        assert_eq!(redis.migrate().unwrap(), 4);
+        assert_eq!(redis.delegators(3, ntw).unwrap(), vec![voter()]);
+    }
+
+    #[tokio::test]
+    async fn redis_unregister_voter_all() {
+        let mut redis = redis().await;
+
+        let res = redis.unregister_voter_all(voter());
+
+        assert!(res.is_ok());
+
+        for ntw in networks() {
+            assert!(!redis.is_registered(voter(), ntw));
+            assert!(!redis.registered_voters(ntw).unwrap().contains(&voter()));
+        }
+    }
+
+    #[tokio::test]
+    async fn redis_vote_metadata() {
+        let mut redis = redis().await;
+
+        let ntw = Network::Testnet;
+
+        let res = redis.vote_metadata(fip(42), ntw).unwrap();
+
+        assert!(res.is_empty());
+
+        let metadata = VoteMetadata {
+            title: "Title".to_string(),
+            description: "Description".to_string(),
+        };
+        for lang in ["en", "es"] {
+            redis
+                .set_vote_metadata(fip(42), ntw, lang.to_string(), metadata.clone())
+                .await
+                .unwrap();
+        }
+
+        let res = redis.vote_metadata(fip(42), ntw).unwrap();
+
+        assert_eq!(res.len(), 2);
+        assert_eq!(res["es"], metadata);
+    }
+
+    #[tokio::test]
+    async fn redis_fip_info() {
+        let mut redis = redis().await;
+
+        assert_eq!(redis.fip_info(fip(42)).unwrap(), None);
+
+        let info = FipInfo {
+            title: "Title".to_string(),
+            status: "Draft".to_string(),
+            discussions_to: None,
+            url: "https://example.com/fip-0042.md".to_string(),
+        };
+        redis.set_fip_info(fip(42), &info).unwrap();
+
+        assert_eq!(redis.fip_info(fip(42)).unwrap(), Some(info));
+        assert_eq!(redis.fip_info(fip(43)).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn redis_results_version() {
+        let mut redis = redis().await;
+
+        let registered = redis.results_version().unwrap();
+        assert!(registered > 0);
+
+        redis
+            .start_vote(fip(5), vote_starter(), Network::Testnet)
+            .await
+            .unwrap();
+        let started = redis.results_version().unwrap();
+        assert!(started > registered);
+
+        // Syncing the same FIP info again changes nothing
+        let info = FipInfo {
+            title: "Title".to_string(),
+            status: "Draft".to_string(),
+            discussions_to: None,
+            url: "https://example.com/fip-0005.md".to_string(),
+        };
+        redis.set_fip_info(fip(5), &info).unwrap();
+        let synced = redis.results_version().unwrap();
+        assert!(synced > started);
+        redis.set_fip_info(fip(5), &info).unwrap();
+        assert_eq!(redis.results_version().unwrap(), synced);
+
+        // Reading doesn't count as a change
+        redis
+            .vote_results(fip(5), 60u64, Network::Testnet)
+            .await
+            .unwrap();
+        assert_eq!(redis.results_version().unwrap(), synced);
+
+        redis.flush_all().unwrap();
+        assert!(redis.results_version().unwrap() > synced);
+    }
+
+    #[tokio::test]
+    async fn redis_announced() {
+        let mut redis = redis().await;
+
+        let num = fip(7);
+        let ntw = Network::Mainnet;
+        redis.start_vote(num, vote_starter(), ntw).await.unwrap();
+
+        assert!(!redis.announced(num, ntw, Announcement::Start).unwrap());
+
+        redis.set_announced(num, ntw, Announcement::Start).unwrap();
+
+        assert!(redis.announced(num, ntw, Announcement::Start).unwrap());
+        assert!(!redis.announced(num, ntw, Announcement::Conclusion).unwrap());
+        assert!(!redis
+            .announced(num, ntw, Announcement::Reminder(3600))
+            .unwrap());
+        assert!(!redis
+            .announced(num, Network::Testnet, Announcement::Start)
+            .unwrap());
+
+        redis.delete_vote(num, ntw).await.unwrap();
+
+        assert!(!redis.announced(num, ntw, Announcement::Start).unwrap());
+    }
+
+    #[tokio::test]
+    async fn redis_silence_report() {
+        let mut redis = redis().await;
+        let clock = mock_clock(&mut redis);
+
+        let num = fip(6);
+        let vote_length = 1u64;
+        let ntw = Network::Testnet;
+
+        redis.start_vote(num, vote_starter(), ntw).await.unwrap();
+
+        let res = redis.silence_report(num, vote_length, ntw);
+
+        assert!(res.is_err());
+
+        clock.advance(vote_length + 1);
+
+        // Nothing is reported until the conclusion stores the report
+        assert!(redis
+            .silence_report(num, vote_length, ntw)
+            .unwrap()
+            .is_none());
+        assert_eq!(redis.stale_votes(ntw, vote_length).unwrap(), vec![num]);
+
+        redis.conclude_vote(num, vote_length, ntw).await.unwrap();
+
+        let report = redis
+            .silence_report(num, vote_length, ntw)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(report.silent_voters, 1);
+        assert_eq!(report.silent_storage_size, TEST_SP_POWER);
+        assert_eq!(report.cohorts[&SpCohort::Small].sp_count, 1);
+        assert!(redis.stale_votes(ntw, vote_length).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn redis_is_registered() {
+        let mut redis = redis().await;
+
+        for ntw in networks() {
+            let res = redis.is_registered(vote_starter(), ntw);
+
+            assert!(!res);
+
+            let res = redis.register_voter(vote_starter(), ntw, vec![1u32]);
+            assert!(res.is_ok());
+
+            let res = redis.is_registered(vote_starter(), ntw);
+
+            assert!(res);
+
+            let res = redis.unregister_voter(vote_starter(), ntw);
+
+            assert!(res.is_ok());
+
+            let res = redis.is_registered(vote_starter(), ntw);
+
+            assert!(!res);
+        }
+    }
+
+    #[tokio::test]
+    async fn redis_registration_expiry() {
+        let mut redis = redis().await;
+
+        let voter = Address::from_low_u64_be(4048);
+        let ntw = Network::Testnet;
+
+        // Only registered voters can renew
+        assert!(redis.renew_registration(voter, ntw, Some(1)).is_err());
+
+        redis
+            .register_voter_class(voter, ntw, VoterClass::CoreDev)
+            .unwrap();
+        assert!(!redis.is_registration_expired(voter, ntw).unwrap());
+
+        let now = redis.now();
+        redis
+            .set_registration_expiry(voter, ntw, Some(now - 1))
+            .unwrap();
+        assert!(redis.is_registration_expired(voter, ntw).unwrap());
+
+        // Renewing to an earlier expiry keeps the later one
+        let expires_at = now + 60;
+        assert_eq!(
+            redis
+                .renew_registration(voter, ntw, Some(expires_at))
+                .unwrap(),
+            Some(expires_at)
+        );
+        assert_eq!(
+            redis.renew_registration(voter, ntw, Some(1)).unwrap(),
+            Some(expires_at)
+        );
+        assert!(!redis.is_registration_expired(voter, ntw).unwrap());
+
+        // Unregistering removes the expiry
+        redis.unregister_voter(voter, ntw).unwrap();
+        assert_eq!(redis.registration_expiry(voter, ntw).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn redis_nonce() {
+        let mut redis = redis().await;
+
+        let issued = redis.issue_nonce(60).unwrap();
+        let nonce: [u8; 16] = hex::decode(&issued.nonce).unwrap().try_into().unwrap();
+
+        assert!(issued.expires_at >= redis.now() + 59);
+        assert_ne!(redis.issue_nonce(60).unwrap().nonce, issued.nonce);
+
+        // A nonce is only accepted once, and only if it was issued
+        assert!(redis.consume_nonce(nonce).unwrap());
+        assert!(!redis.consume_nonce(nonce).unwrap());
+        assert!(!redis.consume_nonce([0; 16]).unwrap());
+    }
+
+    #[tokio::test]
+    async fn redis_registration_queue() {
+        let mut redis = redis().await;
+
+        let pending = test_reg().recover_pending_registration().await.unwrap();
+        assert_eq!(redis.registration_status(voter()).unwrap(), None);
+
+        let status = redis.queue_registration(&pending).unwrap();
+        assert!(matches!(status, RegistrationStatus::Pending { .. }));
+        assert_eq!(redis.registration_status(voter()).unwrap(), Some(status));
+
+        // Each registration is taken off the queue once
+        assert_eq!(redis.next_registration().unwrap(), Some(pending));
+        assert_eq!(redis.next_registration().unwrap(), None);
+
+        let failed = RegistrationStatus::Failed {
+            error: "Invalid worker address".to_string(),
+        };
+        redis.set_registration_status(voter(), &failed).unwrap();
+        assert_eq!(redis.registration_status(voter()).unwrap(), Some(failed));
+    }
+
+    #[tokio::test]
+    async fn redis_delegation_offers() {
+        let mut redis = redis().await;
+        let ntw = Network::Testnet;
+        let offered = Address::from_low_u64_be(42);
+
+        let offer = |worker_address: &str, offered_at| DelegationOffer {
+            worker_address: worker_address.to_string(),
+            sp_ids: vec![1000, 1001],
+            offered_at,
+        };
+        redis
+            .offer_delegation(offered, ntw, &offer("t3second", 20))
+            .unwrap();
+        redis
+            .offer_delegation(offered, ntw, &offer("t3first", 10))
+            .unwrap();
+        assert_eq!(
+            redis.delegation_offers(offered, ntw).unwrap(),
+            vec![offer("t3first", 10), offer("t3second", 20)]
+        );
+        assert!(redis
+            .delegation_offers(offered, Network::Mainnet)
+            .unwrap()
+            .is_empty());
+
+        // An offer can only be accepted once, and doesn't register the voter
+        assert_eq!(
+            redis
+                .take_delegation_offer(offered, ntw, "t3first")
+                .unwrap(),
+            Some(offer("t3first", 10))
+        );
+        assert_eq!(
+            redis
+                .take_delegation_offer(offered, ntw, "t3first")
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            redis.delegation_offers(offered, ntw).unwrap(),
+            vec![offer("t3second", 20)]
+        );
+        assert!(!redis.is_registered(offered, ntw));
+    }
+
+    #[tokio::test]
+    async fn redis_event_log() {
+        let mut redis = redis().await;
+        let ntw = Network::Testnet;
+
+        redis.start_vote(fip(5), vote_starter(), ntw).await.unwrap();
+        redis.set_vote_extension(fip(5), ntw, 60).unwrap();
+
+        let events = redis.events(None, 10).unwrap();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0].event, Event::VoterRegistered { voter: v, .. } if v == voter()));
+        assert!(matches!(
+            events[1].event,
+            Event::VoteStarted { fip_number, signer, .. }
+                if fip_number == fip(5) && signer == vote_starter()
+        ));
+        assert!(matches!(
+            events[2].event,
+            Event::ExtensionChanged { extension: 60, .. }
+        ));
+        assert!(events[1].timestamp >= events[0].timestamp);
+
+        // Reading after an event skips it and every event before it
+        let later = redis.events(Some(&events[0].id), 10).unwrap();
+        assert_eq!(later.len(), 2);
+        assert_eq!(later[0].id, events[1].id);
+        assert!(redis.events(Some(&events[2].id), 10).unwrap().is_empty());
+        assert_eq!(redis.events(None, 1).unwrap().len(), 1);
+
+        // Rejected changes are not logged
+        assert!(redis.start_vote(fip(5), vote_starter(), ntw).await.is_err());
+        assert_eq!(redis.events(None, 10).unwrap().len(), 3);
+        assert!(redis
+            .take_delegation_offer(voter(), ntw, "t01000")
+            .unwrap()
+            .is_none());
+        assert_eq!(redis.events(None, 10).unwrap().len(), 3);
+
+        // The results version moves in the same transaction as each change
+        let version = redis.results_version().unwrap();
+        redis.set_vote_extension(fip(5), ntw, 120).unwrap();
+        assert_eq!(redis.results_version().unwrap(), version + 1);
+        assert_eq!(redis.events(None, 10).unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn redis_test_vote() {
+        let mut redis = redis().await;
+        let clock = mock_clock(&mut redis);
+
+        let num = fip(5);
+        let vote_length = 1u64;
+        let ntw = Network::Testnet;
+
+        redis.start_vote(num, vote_starter(), ntw).await.unwrap();
+
+        let active = redis.active_votes(ntw, vote_length).unwrap();
+        println!("{:?}", active);
+
+        assert!(active.contains(&num));
+
+        let vote = test_vote(VoteOption::Yay, num.get()).vote().unwrap();
+
+        redis
+            .add_vote(num, vote, voter(), vote_length, false)
+            .await
+            .unwrap();
+
+        clock.advance(vote_length + 1);
+
+        let active = redis.active_votes(ntw, vote_length).unwrap();
+
+        assert!(!active.contains(&num));
+
+        let concluded = redis.concluded_votes(ntw, vote_length).unwrap();
 
-        Ok(active_votes.contains(&fip_number.into()))
+        assert!(concluded.contains(&num));
     }
 
-    fn register_vote_to_all_votes(&mut self, fip: u32, ntw: Network) -> Result<(), RedisError> {
-        let key = LookupKey::AllVotes(ntw).to_bytes();
-        let mut votes = self.all_votes(ntw)?;
+    #[tokio::test]
+    async fn redis_yet_to_vote() {
+        let mut redis = redis().await;
+        let clock = mock_clock(&mut redis);
 
-        if !votes.contains(&fip) {
-            votes.push(fip);
-            let str_votes = serde_json::to_string(&votes).unwrap();
-            self.con.set::<Vec<u8>, String, ()>(key, str_votes)?;
-        }
+        let vote_length = 1u64;
+        let ntw = Network::Testnet;
 
-        Ok(())
+        redis.start_vote(fip(5), vote_starter(), ntw).await.unwrap();
+        assert_eq!(redis.yet_to_vote(fip(5), ntw).unwrap(), vec![voter()]);
+
+        let vote = test_vote(VoteOption::Yay, 5u32).vote().unwrap();
+        redis
+            .add_vote(fip(5), vote, voter(), vote_length, false)
+            .await
+            .unwrap();
+        assert!(redis.yet_to_vote(fip(5), ntw).unwrap().is_empty());
+
+        // Voters have to vote again in a new round
+        clock.advance(vote_length + 1);
+        redis
+            .start_round(fip(5), vote_starter(), ntw, 2, vote_length)
+            .await
+            .unwrap();
+        assert_eq!(redis.yet_to_vote(fip(5), ntw).unwrap(), vec![voter()]);
     }
 
-    pub fn remove_voter_starters(
-        &mut self,
-        voter: Address,
-        ntw: Network,
-    ) -> Result<(), RedisError> {
-        let key = LookupKey::VoteStarters(ntw).to_bytes();
-        let mut starters = self.voter_starters(ntw)?;
+    #[tokio::test]
+    async fn redis_stale_votes() {
+        let mut redis = redis().await;
+        let clock = mock_clock(&mut redis);
 
-        if starters.contains(&voter) {
-            starters.retain(|&x| x != voter);
+        let vote_length = 1u64;
+        let ntw = Network::Testnet;
 
-            let new_bytes = starters
-                .into_iter()
-                .flat_map(|v| v.as_fixed_bytes().to_vec())
-                .collect::<Vec<u8>>();
+        redis.start_vote(fip(5), vote_starter(), ntw).await.unwrap();
+        let vote = test_vote(VoteOption::Yay, 5u32).vote().unwrap();
+        redis
+            .add_vote(fip(5), vote, voter(), vote_length, false)
+            .await
+            .unwrap();
 
-            self.con.set::<Vec<u8>, Vec<u8>, ()>(key, new_bytes)?;
-        }
+        // Nothing is concluded while the vote is in progress
+        assert!(redis.stale_votes(ntw, vote_length).unwrap().is_empty());
+        assert!(redis
+            .conclude_vote(fip(5), vote_length, ntw)
+            .await
+            .unwrap()
+            .is_none());
 
-        Ok(())
-    }
+        clock.advance(vote_length + 1);
+        assert_eq!(redis.stale_votes(ntw, vote_length).unwrap(), vec![fip(5)]);
 
-    pub fn flush_vote(
-        &mut self,
-        fip_number: impl Into<u32>,
-        ntw: Network,
-    ) -> Result<(), RedisError> {
-        let key = LookupKey::Votes(fip_number.into(), ntw).to_bytes();
-        self.con.del::<Vec<u8>, ()>(key)?;
-        Ok(())
+        let results = redis
+            .conclude_vote(fip(5), vote_length, ntw)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(results.yay, 1);
+        assert!(redis.stale_votes(ntw, vote_length).unwrap().is_empty());
+        assert!(redis
+            .concluded_votes(ntw, vote_length)
+            .unwrap()
+            .contains(&fip(5)));
     }
 
-    pub fn flush_all(&mut self) -> Result<(), RedisError> {
-        let keys: Vec<Vec<u8>> = self.con.keys("*")?;
-        for key in keys {
-            self.con.del::<Vec<u8>, ()>(key)?;
-        }
-        Ok(())
-    }
+    #[tokio::test]
+    async fn redis_replica() {
+        let mut redis = redis().await;
+        let clock = mock_clock(&mut redis);
 
-    async fn add_storage(
-        &mut self,
-        sp_id: u32,
-        ntw: Network,
-        vote: VoteOption,
-        fip_number: u32,
-    ) -> Result<(), RedisError> {
-        let key = LookupKey::Storage(vote.clone(), ntw, fip_number).to_bytes();
+        let vote_length = 1u64;
+        let ntw = Network::Testnet;
 
-        let current_storage = self.get_storage(fip_number, vote, ntw)?;
+        redis.start_vote(fip(5), vote_starter(), ntw).await.unwrap();
+        let vote = test_vote(VoteOption::Yay, 5u32).vote().unwrap();
+        redis
+            .add_vote(fip(5), vote, voter(), vote_length, false)
+            .await
+            .unwrap();
+        clock.advance(vote_length + 1);
 
-        let new_storage = match fetch_storage_amount(sp_id, ntw).await {
-            Ok(s) => s,
-            Err(_) => {
-                return Err(RedisError::from((
-                    redis::ErrorKind::TypeError,
-                    "Error fetching storage amount",
-                )))
-            }
-        };
-        let storage = current_storage + new_storage;
-        let storage_bytes = storage.to_be_bytes().to_vec();
-        self.con
-            .set::<Vec<u8>, Vec<u8>, ()>(key.clone(), storage_bytes)?;
-        Ok(())
+        let mut replica = Redis::replica(redis.url()).unwrap();
+        replica.set_clock(clock.clone());
+        replica.set_power_provider(StaticPower::new([(TEST_SP_ID, TEST_SP_POWER)]));
+
+        // Results of a concluded vote are read without being stored
+        let results = replica
+            .vote_results(fip(5), vote_length, ntw)
+            .await
+            .unwrap();
+        assert_eq!(results.yay, 1);
+        assert_eq!(replica.stale_votes(ntw, vote_length).unwrap(), vec![fip(5)]);
+        assert!(replica
+            .silence_report(fip(5), vote_length, ntw)
+            .unwrap()
+            .is_none());
+        assert!(!redis
+            .con
+            .exists::<String, bool>(LookupKey::Silence(fip(5), ntw).to_key())
+            .unwrap());
+
+        // Once the primary stores them the replica reads them back
+        redis.conclude_vote(fip(5), vote_length, ntw).await.unwrap();
+        assert!(replica.stale_votes(ntw, vote_length).unwrap().is_empty());
+        assert_eq!(
+            replica
+                .vote_results(fip(5), vote_length, ntw)
+                .await
+                .unwrap()
+                .yay,
+            1
+        );
     }
 
-    /// Removes the lookup from the voter to the network they are voting on
-    fn remove_network(&mut self, voter: Address) -> Result<(), RedisError> {
-        let key: Vec<u8> = LookupKey::Network(voter).to_bytes();
-        self.con.del::<Vec<u8>, ()>(key)?;
-        Ok(())
+    #[tokio::test]
+    async fn redis_delete_vote() {
+        let mut redis = redis().await;
+
+        let ntw = Network::Testnet;
+
+        redis.start_vote(fip(5), vote_starter(), ntw).await.unwrap();
+        let vote = test_vote(VoteOption::Yay, 5u32).vote().unwrap();
+        redis
+            .add_vote(fip(5), vote, voter(), 69u64, false)
+            .await
+            .unwrap();
+
+        let tx_hash = H256::repeat_byte(7);
+        redis.set_published_results(fip(5), ntw, tx_hash).unwrap();
+        assert_eq!(redis.published_results(fip(5), ntw).unwrap(), Some(tx_hash));
+
+        let res = redis.delete_vote(fip(5), ntw).await;
+
+        assert!(res.is_ok());
+        assert!(!redis.vote_exists(ntw, fip(5)).unwrap());
+        assert!(redis.votes(fip(5), ntw).unwrap().is_empty());
+        assert!(!redis.all_votes(ntw).unwrap().contains(&fip(5)));
+        assert_eq!(redis.get_storage(fip(5), VoteOption::Yay, ntw).unwrap(), 0);
+        assert_eq!(redis.published_results(fip(5), ntw).unwrap(), None);
+        assert!(!redis
+            .voter_history(voter(), ntw)
+            .unwrap()
+            .iter()
+            .any(|b| b.fip_number == fip(5)));
+
+        // The vote can be started again from scratch
+        let res = redis.start_vote(fip(5), vote_starter(), ntw).await;
+
+        assert!(res.is_ok());
     }
-}
 
-impl LookupKey {
-    fn to_bytes(&self) -> Vec<u8> {
-        let (lookup_type, fip) = match self {
-            // The first bit will be 0 or 1
-            LookupKey::Votes(fip, ntw) => (*ntw as u8, fip),
-            // The first bit will range between 2 and 8
-            LookupKey::Storage(choice, ntw, fip) => {
-                let choice = match choice {
-                    VoteOption::Yay => 2,
-                    VoteOption::Nay => 3,
-                    VoteOption::Abstain => 4,
-                };
-                let nt = *ntw as u8 + 1; // 1 or 2
-                (choice * nt, fip)
-            }
-            // The first bit will be 9 or 10
-            LookupKey::Timestamp(fip, ntw) => (9 + *ntw as u8, fip),
-            LookupKey::Voter(ntw, voter) => {
-                let ntw = match ntw {
-                    Network::Mainnet => 0,
-                    Network::Testnet => 1,
-                };
-                let voter = voter.as_bytes();
-                let mut bytes = Vec::with_capacity(21);
-                bytes.push(ntw);
-                bytes.extend_from_slice(voter);
-                return bytes;
-            }
-            LookupKey::Network(voter) => {
-                let voter = voter.as_bytes();
-                let mut bytes = Vec::with_capacity(21);
-                bytes.push(2);
-                bytes.extend_from_slice(voter);
-                return bytes;
-            }
-            LookupKey::VoteStarters(ntw) => {
-                let bytes = vec![8, 0, 0, 8, 1, 3, 5, *ntw as u8];
-                return bytes;
-            }
-            LookupKey::AllVotes(ntw) => {
-                let bytes = vec![8, 0, 0, 8, 1, 3, 187, *ntw as u8];
-                return bytes;
-            }
-        };
-        let slice = unsafe {
-            let mut key = MaybeUninit::<[u8; 5]>::uninit();
-            let start = key.as_mut_ptr() as *mut u8;
-            (start.add(0) as *mut [u8; 4]).write(fip.to_be_bytes());
+    #[tokio::test]
+    async fn redis_final_results() {
+        let mut redis = redis().await;
+        let clock = mock_clock(&mut redis);
 
-            // This is the bit we set to 0 if we only want the token object
-            (start.add(4) as *mut [u8; 1]).write([lookup_type]);
+        let vote_length = 1u64;
+        let ntw = Network::Testnet;
 
-            key.assume_init()
-        };
-        Vec::from(slice)
+        redis.start_vote(fip(4), vote_starter(), ntw).await.unwrap();
+        let vote = test_vote(VoteOption::Yay, 4u32).vote().unwrap();
+        redis
+            .add_vote(fip(4), vote, voter(), vote_length, false)
+            .await
+            .unwrap();
+
+        clock.advance(vote_length + 1);
+
+        let results = redis.vote_results(fip(4), vote_length, ntw).await.unwrap();
+
+        assert_eq!(results.yay, 1);
+
+        // Concluded results are served from the stored tally
+        redis.flush_vote(fip(4), ntw).unwrap();
+        let res = redis.vote_results(fip(4), vote_length, ntw).await.unwrap();
+
+        assert_eq!(res.yay, 1);
+        assert_eq!(res.yay_storage_size, results.yay_storage_size);
     }
-}
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct VoteResults {
-    yay: u64,
-    nay: u64,
-    abstain: u64,
-    yay_storage_size: u128,
-    nay_storage_size: u128,
-    abstain_storage_size: u128,
-}
+    #[tokio::test]
+    async fn redis_voter_class() {
+        let mut redis = redis().await;
 
-#[cfg(test)]
-mod tests {
-    use std::str::FromStr;
+        let ntw = Network::Testnet;
 
-    use super::*;
+        assert_eq!(
+            redis.voter_class(voter(), ntw).unwrap(),
+            VoterClass::StorageProvider
+        );
 
-    use crate::messages::{vote_registration::test_voter_registration::*, votes::test_votes::*};
+        // Storage providers can't take a second class
+        let res = redis.register_voter_class(voter(), ntw, VoterClass::CoreDev);
 
-    async fn redis() -> Redis {
-        let url = Url::parse("redis://127.0.0.1:6379").unwrap();
-        let mut redis = Redis::new(url).unwrap();
+        assert!(res.is_err());
 
-        redis.flush_all().unwrap();
+        let res = redis.register_voter_class(vote_starter(), ntw, VoterClass::StorageProvider);
+
+        assert!(res.is_err());
 
-        let vote_reg = test_reg().recover_vote_registration().await.unwrap();
         redis
-            .register_voter(vote_reg.address(), vote_reg.ntw(), vote_reg.sp_ids())
+            .register_voter_class(vote_starter(), ntw, VoterClass::CoreDev)
             .unwrap();
 
+        assert_eq!(
+            redis.voter_class(vote_starter(), ntw).unwrap(),
+            VoterClass::CoreDev
+        );
+        assert!(redis
+            .registered_voters(ntw)
+            .unwrap()
+            .contains(&vote_starter()));
+
+        redis.unregister_voter(vote_starter(), ntw).unwrap();
+
+        assert_eq!(
+            redis.voter_class(vote_starter(), ntw).unwrap(),
+            VoterClass::StorageProvider
+        );
+    }
+
+    #[tokio::test]
+    async fn redis_class_tally() {
+        let mut redis = redis().await;
+
+        let ntw = Network::Testnet;
+        redis.start_vote(fip(2), vote_starter(), ntw).await.unwrap();
+
+        let vote = test_vote(VoteOption::Yay, 2u32).vote().unwrap();
         redis
+            .add_vote(fip(2), vote, voter(), 69u64, false)
+            .await
+            .unwrap();
+
+        let results = redis.vote_results(fip(2), 69u64, ntw).await.unwrap();
+
+        assert_eq!(results.yay, 1);
+        assert_eq!(
+            results.classes[&VoterClass::StorageProvider],
+            ClassTally {
+                yay: 1,
+                nay: 0,
+                abstain: 0,
+                options: BTreeMap::new(),
+            }
+        );
+        assert!(!results.classes.contains_key(&VoterClass::CoreDev));
     }
 
-    fn voter() -> Address {
-        Address::from_str("0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56").unwrap()
+    #[tokio::test]
+    async fn redis_revote_class_change() {
+        let mut redis = redis().await;
+
+        let ntw = Network::Testnet;
+        redis.start_vote(fip(2), vote_starter(), ntw).await.unwrap();
+
+        let vote = test_vote(VoteOption::Yay, 2u32).vote().unwrap();
+        redis
+            .add_vote(fip(2), vote, voter(), 69u64, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            redis.get_storage(fip(2), VoteOption::Yay, ntw).unwrap(),
+            TEST_SP_POWER
+        );
+
+        // Changing class doesn't move the ballot already cast
+        redis.unregister_voter(voter(), ntw).unwrap();
+        redis
+            .register_voter_class(voter(), ntw, VoterClass::CoreDev)
+            .unwrap();
+        let results = redis.vote_results(fip(2), 69u64, ntw).await.unwrap();
+        assert_eq!(results.classes[&VoterClass::StorageProvider].yay, 1);
+        assert!(!results.classes.contains_key(&VoterClass::CoreDev));
+
+        // A revote takes the storage back and counts for the new class
+        let vote = test_vote(VoteOption::Nay, 2u32).vote().unwrap();
+        redis
+            .add_vote(fip(2), vote, voter(), 69u64, true)
+            .await
+            .unwrap();
+        assert_eq!(redis.get_storage(fip(2), VoteOption::Yay, ntw).unwrap(), 0);
+        assert_eq!(redis.get_storage(fip(2), VoteOption::Nay, ntw).unwrap(), 0);
+
+        let results = redis.vote_results(fip(2), 69u64, ntw).await.unwrap();
+        assert!(!results.classes.contains_key(&VoterClass::StorageProvider));
+        assert_eq!(results.classes[&VoterClass::CoreDev].nay, 1);
     }
 
-    fn vote_starter() -> Address {
-        authorized_voters()[0]
+    #[tokio::test]
+    async fn redis_ballot_fil() {
+        let balance = fetch_fil_balance(voter(), Network::Testnet).await.unwrap();
+
+        let res = ballot_fil(voter(), Network::Testnet).await;
+
+        assert_eq!(res.unwrap(), balance);
     }
 
-    fn networks() -> Vec<Network> {
-        vec![Network::Mainnet, Network::Testnet]
+    #[test]
+    fn redis_move_weight() {
+        let mut totals = vec![(VoteOption::Yay, 100u128), (VoteOption::Nay, 50u128)];
+
+        move_weight(
+            &mut totals,
+            Some((&VoteOption::Yay, 30)),
+            &VoteOption::Nay,
+            30,
+        )
+        .unwrap();
+        assert_eq!(totals, vec![(VoteOption::Yay, 70), (VoteOption::Nay, 80)]);
+
+        // The weight grew since the previous ballot was cast, the previous
+        // choice only loses what that ballot added
+        move_weight(
+            &mut totals,
+            Some((&VoteOption::Yay, 30)),
+            &VoteOption::Nay,
+            90,
+        )
+        .unwrap();
+        assert_eq!(totals, vec![(VoteOption::Yay, 40), (VoteOption::Nay, 170)]);
+
+        // And when it shrank, none of it is left behind
+        move_weight(
+            &mut totals,
+            Some((&VoteOption::Nay, 90)),
+            &VoteOption::Yay,
+            10,
+        )
+        .unwrap();
+        assert_eq!(totals, vec![(VoteOption::Yay, 50), (VoteOption::Nay, 80)]);
+
+        let mut totals = vec![(VoteOption::Abstain, 10u128)];
+        move_weight(
+            &mut totals,
+            Some((&VoteOption::Abstain, 10)),
+            &VoteOption::Abstain,
+            25,
+        )
+        .unwrap();
+        assert_eq!(totals, vec![(VoteOption::Abstain, 25)]);
+
+        let mut totals = vec![(VoteOption::Yay, u128::MAX)];
+        assert_eq!(
+            move_weight(&mut totals, None, &VoteOption::Yay, 1),
+            Err(TallyError::Overflow)
+        );
+
+        // A total that lost track of the previous ballot isn't clamped to zero
+        let mut totals = vec![(VoteOption::Yay, 20u128), (VoteOption::Nay, 0u128)];
+        assert_eq!(
+            move_weight(
+                &mut totals,
+                Some((&VoteOption::Yay, 30)),
+                &VoteOption::Nay,
+                30
+            ),
+            Err(TallyError::Underflow)
+        );
     }
 
     #[tokio::test]
-    async fn redis_votes() {
-        let mut redis = redis().await;
+    async fn redis_add_vote_cancelled() {
+        let ntw = Network::Testnet;
 
-        let res = redis.votes(5u32, Network::Testnet);
+        // Cancels the request at every point from before the first chain lookup to completion
+        for delay in [0, 1, 5, 20, 100, 500, 5000] {
+            let mut redis = redis().await;
+            redis.start_vote(fip(1), vote_starter(), ntw).await.unwrap();
+            let vote = test_vote(VoteOption::Yay, 1u32).vote().unwrap();
 
-        assert!(res.is_ok());
+            let res = tokio::time::timeout(
+                time::Duration::from_millis(delay),
+                redis.add_vote(fip(1), vote, voter(), 60u64, false),
+            )
+            .await;
+
+            let votes = redis.votes(fip(1), ntw).unwrap();
+            let storage = redis.get_storage(fip(1), VoteOption::Yay, ntw).unwrap();
+
+            match res {
+                Ok(res) => {
+                    res.unwrap();
+                    assert_eq!(votes.len(), 1);
+                    assert!(storage > 0);
+                }
+                Err(_) => {
+                    assert!(votes.is_empty());
+                    assert_eq!(storage, 0);
+                }
+            }
+        }
     }
 
     #[tokio::test]
-    async fn redis_start_vote() {
+    async fn redis_submit_vote() {
         let mut redis = redis().await;
+        let ntw = Network::Testnet;
 
-        let starter = voter();
+        redis.start_vote(fip(3), vote_starter(), ntw).await.unwrap();
+        let received = test_vote(VoteOption::Abstain, 3u32);
+        let id = received.receipt_id().unwrap();
+
+        let receipt = redis
+            .submit_vote(
+                fip(3),
+                received.vote().unwrap(),
+                voter(),
+                60u64,
+                false,
+                id.clone(),
+            )
+            .await
+            .unwrap();
 
-        for ntw in networks() {
-            let res = redis.start_vote(5u32, starter, ntw);
+        assert_eq!(receipt.id, id);
+        assert_eq!(receipt.choice, VoteOption::Abstain);
+        assert_eq!(redis.vote_receipt(&id).unwrap(), Some(receipt));
+
+        // A retry gets the same receipt back instead of a duplicate vote error
+        let retry = redis
+            .submit_vote(
+                fip(3),
+                received.vote().unwrap(),
+                voter(),
+                60u64,
+                false,
+                id.clone(),
+            )
+            .await
+            .unwrap();
 
-            assert!(res.is_ok());
+        assert_eq!(redis.vote_receipt(&id).unwrap(), Some(retry));
+        assert_eq!(redis.votes(fip(3), ntw).unwrap().len(), 1);
 
-            let res = redis.vote_status(5u32, 60u64, ntw);
+        assert_eq!(redis.vote_receipt("not a receipt").unwrap(), None);
+    }
 
-            assert!(res.is_ok());
+    #[tokio::test]
+    async fn redis_add_vote_concurrent() {
+        let mut redis = redis().await;
+        let mut other = Redis::new(redis.url()).unwrap();
+        let ntw = Network::Testnet;
 
-            let status = res.unwrap();
+        redis.start_vote(fip(4), vote_starter(), ntw).await.unwrap();
+        let vote = || test_vote(VoteOption::Yay, 4u32).vote().unwrap();
 
-            assert_eq!(status, VoteStatus::InProgress(60u64));
+        let (first, second) = tokio::join!(
+            redis.add_vote(fip(4), vote(), voter(), 60u64, true),
+            other.add_vote(fip(4), vote(), voter(), 60u64, true),
+        );
+        first.unwrap();
+        second.unwrap();
 
-            let res = redis.active_votes(ntw, 69u64);
-            assert!(res.is_ok());
+        // The ballot and its storage are only counted once
+        let sp_ids = redis.voter_delegates(voter(), ntw).unwrap();
+        let storage: u128 = redis
+            .ballot_storage(&sp_ids, ntw)
+            .await
+            .unwrap()
+            .values()
+            .sum();
+        assert_eq!(redis.votes(fip(4), ntw).unwrap().len(), 1);
+        assert_eq!(
+            redis.get_storage(fip(4), VoteOption::Yay, ntw).unwrap(),
+            storage
+        );
+    }
 
-            let active_votes = res.unwrap();
-            assert!(active_votes.contains(&5u32));
-        }
+    #[tokio::test]
+    async fn redis_vote_lock() {
+        let mut redis = redis().await;
+        let mut other = Redis::new(redis.url()).unwrap();
+        let ntw = Network::Testnet;
+
+        // A vote held by another instance is not changed until it is released
+        let lock = LookupKey::Lock(fip(4), ntw).to_key();
+        other
+            .con
+            .set_ex::<String, &str, ()>(lock.clone(), "other", 60)
+            .unwrap();
+        assert!(redis.start_vote(fip(4), vote_starter(), ntw).await.is_err());
+        assert!(!redis.vote_exists(ntw, fip(4)).unwrap());
+
+        other.con.del::<String, ()>(lock.clone()).unwrap();
+        redis.start_vote(fip(4), vote_starter(), ntw).await.unwrap();
+        assert!(!redis.con.exists::<String, bool>(lock).unwrap());
+
+        // Ballots are refused once another instance stored the concluded results
+        redis.vote_results(fip(4), 0u64, ntw).await.unwrap();
+        let ballot = RecordedBallot {
+            fip_number: fip(4),
+            ntw,
+            voter: voter(),
+            class: VoterClass::StorageProvider,
+            vote: test_vote(VoteOption::Yay, 4u32).vote().unwrap(),
+            storage: TEST_SP_POWER,
+            fil: 0,
+        };
+        assert!(redis.record_ballot(ballot, false, None).is_err());
+        assert!(redis.votes(fip(4), ntw).unwrap().is_empty());
     }
 
     #[tokio::test]
-    async fn redis_register_voter() {
+    async fn redis_vote_options() {
         let mut redis = redis().await;
+        let ntw = Network::Testnet;
+        let options = vec!["Approve".to_string(), "Reject".to_string()];
 
-        let res = redis.register_voter(vote_starter(), Network::Mainnet, vec![1u32]);
+        let wallet: LocalWallet =
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+                .parse()
+                .unwrap();
+        redis
+            .register_voter_class(wallet.address(), ntw, VoterClass::CoreDev)
+            .unwrap();
 
-        assert!(res.is_ok());
+        redis.set_vote_options(fip(3), ntw, &options).unwrap();
+        redis.start_vote(fip(3), vote_starter(), ntw).await.unwrap();
 
-        let ntw = redis.network(vote_starter());
+        assert_eq!(redis.vote_options(fip(3), ntw).unwrap(), options);
 
-        assert!(ntw.is_ok());
+        let message = "APPROVE: FIP-3";
+        let signature = wallet.sign_message(message).await.unwrap();
+        let vote: ReceivedVote = serde_json::from_value(serde_json::json!({
+            "signature": format!("0x{}", signature),
+            "message": message,
+        }))
+        .unwrap();
+        let vote = vote.vote_on(&options).unwrap();
+        redis
+            .add_vote(fip(3), vote, wallet.address(), 60u64, false)
+            .await
+            .unwrap();
 
-        let delegates = redis.voter_delegates(vote_starter(), Network::Mainnet);
+        // Yay, Nay and Abstain are not on this ballot
+        let vote = test_vote(VoteOption::Yay, 3u32).vote().unwrap();
+        assert!(redis
+            .add_vote(fip(3), vote, voter(), 60u64, false)
+            .await
+            .is_err());
 
-        assert!(delegates.is_ok());
+        let results = redis.vote_results(fip(3), 60u64, ntw).await.unwrap();
 
-        let delegates = delegates.unwrap();
+        assert_eq!(results.yay, 0);
+        assert_eq!(
+            results.ballots(&VoteOption::Named("Approve".to_string())),
+            1
+        );
+        assert_eq!(results.options["Reject"], OptionTally::default());
+        assert_eq!(results.classes[&VoterClass::CoreDev].options["Approve"], 1);
 
-        assert_eq!(delegates, vec![1u32]);
+        redis.delete_vote(fip(3), ntw).await.unwrap();
+
+        assert!(redis.vote_options(fip(3), ntw).unwrap().is_empty());
     }
 
     #[tokio::test]
-    async fn redis_unregister_voter() {
+    async fn redis_vote_weighting() {
         let mut redis = redis().await;
+        let ntw = Network::Testnet;
 
         redis
-            .register_voter(vote_starter(), Network::Mainnet, vec![1u32])
+            .set_vote_weighting(fip(4), ntw, Weighting::Sqrt)
             .unwrap();
+        redis.start_vote(fip(4), vote_starter(), ntw).await.unwrap();
 
-        let res = redis.unregister_voter(vote_starter(), Network::Mainnet);
-
-        assert!(res.is_ok());
-
-        let ntw = redis.network(vote_starter());
+        let vote = test_vote(VoteOption::Yay, 4u32).vote().unwrap();
+        redis
+            .add_vote(fip(4), vote, voter(), 60u64, false)
+            .await
+            .unwrap();
 
-        assert!(ntw.is_err());
+        // Storage sizes are weighted, the stored tallies stay raw byte power
+        let results = redis.vote_results(fip(4), 60u64, ntw).await.unwrap();
+        assert_eq!(results.weighting(), Weighting::Sqrt);
+        assert_eq!(results.yay_storage_size, TEST_SP_POWER.isqrt());
+        assert_eq!(
+            redis.get_storage(fip(4), VoteOption::Yay, ntw).unwrap(),
+            TEST_SP_POWER
+        );
 
-        let delegates = redis.voter_delegates(vote_starter(), Network::Mainnet);
+        let json = serde_json::to_value(&results).unwrap();
+        assert_eq!(json["weighting"], "sqrt");
 
-        assert!(delegates.is_ok());
-        assert!(delegates.unwrap().is_empty());
+        redis.delete_vote(fip(4), ntw).await.unwrap();
+        assert_eq!(redis.vote_weighting(fip(4), ntw).unwrap(), Weighting::Raw);
     }
 
     #[tokio::test]
-    async fn redis_register_voter_starter() {
+    async fn redis_vote_min_power() {
         let mut redis = redis().await;
+        let ntw = Network::Testnet;
 
-        for ntw in networks() {
-            let res = redis.register_voter_starter(voter(), ntw);
+        redis
+            .set_vote_min_power(fip(4), ntw, TEST_SP_POWER + 1)
+            .unwrap();
+        redis.start_vote(fip(4), vote_starter(), ntw).await.unwrap();
+
+        let vote = test_vote(VoteOption::Yay, 4u32).vote().unwrap();
+        redis
+            .add_vote(fip(4), vote, voter(), 60u64, false)
+            .await
+            .unwrap();
+
+        // The ballot is recorded but not counted
+        assert_eq!(redis.votes(fip(4), ntw).unwrap().len(), 1);
+        let results = redis.vote_results(fip(4), 60u64, ntw).await.unwrap();
+        assert_eq!(results.below_min_power(), 1);
+        assert_eq!(results.yay, 0);
+        assert_eq!(results.yay_storage_size, 0);
 
-            assert!(res.is_ok());
+        let json = serde_json::to_value(&results).unwrap();
+        assert_eq!(json["below_min_power"], 1);
 
-            let res = redis.voter_starters(ntw);
+        redis.delete_vote(fip(4), ntw).await.unwrap();
+        assert_eq!(redis.vote_min_power(fip(4), ntw).unwrap(), 0);
+    }
 
-            assert!(res.is_ok());
-            assert!(res.unwrap().contains(&voter()));
+    #[test]
+    fn redis_judge_quorum() {
+        let results = |yay: u128, nay: u128, abstain: u128| -> VoteResults {
+            serde_json::from_value(serde_json::json!({
+                "yay": 1,
+                "nay": 1,
+                "abstain": 1,
+                "yay_storage_size": yay,
+                "nay_storage_size": nay,
+                "abstain_storage_size": abstain,
+            }))
+            .unwrap()
+        };
+        let rules = QuorumRules {
+            min_storage_size: 100,
+            pass_percentage: 60,
+        };
+
+        for (yay, nay, abstain, quorum_met, outcome) in [
+            (60, 40, 0, true, VoteOutcome::Passed),
+            (59, 41, 0, true, VoteOutcome::Failed),
+            (0, 0, 100, true, VoteOutcome::Failed),
+            (60, 20, 19, false, VoteOutcome::QuorumNotMet),
+        ] {
+            let mut results = results(yay, nay, abstain);
+            results.judge(&rules).unwrap();
+
+            assert_eq!(results.quorum_met, Some(quorum_met));
+            assert_eq!(results.outcome, Some(outcome));
         }
     }
 
     #[tokio::test]
-    async fn redis_is_registered() {
+    async fn redis_vote_rounds() {
         let mut redis = redis().await;
+        let ntw = Network::Testnet;
 
-        for ntw in networks() {
-            let res = redis.is_registered(vote_starter(), ntw);
+        redis.start_vote(fip(4), vote_starter(), ntw).await.unwrap();
+        let vote = test_vote(VoteOption::Yay, 4u32).vote().unwrap();
+        redis
+            .add_vote(fip(4), vote, voter(), 60u64, false)
+            .await
+            .unwrap();
 
-            assert!(!res);
+        // The vote must conclude and the round must follow the current one
+        assert!(redis
+            .start_round(fip(4), vote_starter(), ntw, 2, 60u64)
+            .await
+            .is_err());
+        assert!(redis
+            .start_round(fip(4), vote_starter(), ntw, 3, 0u64)
+            .await
+            .is_err());
 
-            let res = redis.register_voter(vote_starter(), ntw, vec![1u32]);
-            assert!(res.is_ok());
+        redis
+            .start_round(fip(4), vote_starter(), ntw, 2, 0u64)
+            .await
+            .unwrap();
 
-            let res = redis.is_registered(vote_starter(), ntw);
+        assert_eq!(redis.vote_round(fip(4), ntw).unwrap(), 2);
+        assert!(redis.votes(fip(4), ntw).unwrap().is_empty());
+        assert_eq!(redis.previous_round(fip(4), ntw).unwrap().unwrap().round, 1);
 
-            assert!(res);
+        let vote = test_vote(VoteOption::Nay, 4u32).vote().unwrap();
+        redis
+            .add_vote(fip(4), vote, voter(), 60u64, false)
+            .await
+            .unwrap();
 
-            let res = redis.unregister_voter(vote_starter(), ntw);
+        let results = redis.vote_results(fip(4), 0u64, ntw).await.unwrap();
+
+        assert_eq!(
+            results.delta.unwrap(),
+            RoundDelta {
+                previous_round: 1,
+                yay: -1,
+                nay: 1,
+                abstain: 0,
+                yay_storage_size: -(results.nay_storage_size as i128),
+                nay_storage_size: results.nay_storage_size as i128,
+                abstain_storage_size: 0,
+                turnout: 0,
+                returning_voters: 1,
+                changed_voters: 1,
+                new_voters: 0,
+                departed_voters: 0,
+            }
+        );
+    }
 
-            assert!(res.is_ok());
+    #[tokio::test]
+    async fn redis_unknown_ending() {
+        let mut redis = redis().await;
+        let ntw = Network::Testnet;
 
-            let res = redis.is_registered(vote_starter(), ntw);
+        redis.start_vote(fip(5), vote_starter(), ntw).await.unwrap();
 
-            assert!(!res);
-        }
+        // An ending written by a newer version
+        let ended = format!(
+            r#"{{"ending":"Paused","signer":"{:?}","ended_at":1700000000}}"#,
+            vote_starter()
+        );
+        redis
+            .con
+            .set::<String, String, ()>(LookupKey::Ended(fip(5), ntw).to_key(), ended)
+            .unwrap();
+
+        let ending = redis.vote_ending(fip(5), ntw).unwrap().unwrap();
+        assert_eq!(ending.ending, VoteEnding::Unknown);
+        assert_eq!(
+            redis.vote_status(fip(5), 60u64, ntw).unwrap(),
+            VoteStatus::Unknown
+        );
+
+        // Neither taking ballots nor tallied as concluded
+        let vote = test_vote(VoteOption::Yay, 5u32).vote().unwrap();
+        assert!(redis
+            .add_vote(fip(5), vote, voter(), 60u64, false)
+            .await
+            .is_err());
+        assert!(redis.stale_votes(ntw, 60u64).unwrap().is_empty());
     }
 
     #[tokio::test]
-    async fn redis_test_vote() {
+    async fn redis_end_vote() {
         let mut redis = redis().await;
-
-        let fip = 5u32;
-        let vote_length = 1u64;
         let ntw = Network::Testnet;
 
-        redis.start_vote(fip, vote_starter(), ntw).unwrap();
+        redis
+            .start_vote(fip(88), vote_starter(), ntw)
+            .await
+            .unwrap();
+
+        // Only starters can end a vote, and only in its current round
+        assert!(redis
+            .end_vote(
+                fip(88),
+                Address::zero(),
+                ntw,
+                1,
+                VoteEnding::Cancelled,
+                60u64
+            )
+            .await
+            .is_err());
+        assert!(redis
+            .end_vote(
+                fip(88),
+                vote_starter(),
+                ntw,
+                2,
+                VoteEnding::Cancelled,
+                60u64
+            )
+            .await
+            .is_err());
 
-        let active = redis.active_votes(ntw, vote_length).unwrap();
-        println!("{:?}", active);
+        redis
+            .end_vote(
+                fip(88),
+                vote_starter(),
+                ntw,
+                1,
+                VoteEnding::Cancelled,
+                60u64,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            redis.vote_status(fip(88), 60u64, ntw).unwrap(),
+            VoteStatus::Cancelled
+        );
+        assert!(redis.concluded_votes(ntw, 60u64).unwrap().is_empty());
+
+        // A vote that is no longer in progress can't be ended again
+        assert!(redis
+            .end_vote(
+                fip(88),
+                vote_starter(),
+                ntw,
+                1,
+                VoteEnding::Concluded,
+                60u64
+            )
+            .await
+            .is_err());
 
-        assert!(active.contains(&fip));
+        redis.set_vote_ending(fip(88), ntw, None).unwrap();
+        redis
+            .end_vote(
+                fip(88),
+                vote_starter(),
+                ntw,
+                1,
+                VoteEnding::Concluded,
+                60u64,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            redis.vote_status(fip(88), 60u64, ntw).unwrap(),
+            VoteStatus::Concluded
+        );
+        // The results are stored as it concludes
+        assert!(redis.stale_votes(ntw, 60u64).unwrap().is_empty());
+
+        let ended = redis.vote_ending(fip(88), ntw).unwrap().unwrap();
+        assert_eq!(ended.ending, VoteEnding::Concluded);
+        assert_eq!(ended.signer, vote_starter());
+
+        // The next round runs its full length
+        redis
+            .start_round(fip(88), vote_starter(), ntw, 2, 60u64)
+            .await
+            .unwrap();
+        assert_eq!(redis.vote_ending(fip(88), ntw).unwrap(), None);
+        assert!(matches!(
+            redis.vote_status(fip(88), 60u64, ntw).unwrap(),
+            VoteStatus::InProgress(_)
+        ));
+    }
 
-        let vote = test_vote(VoteOption::Yay, fip).vote().unwrap();
+    #[tokio::test]
+    async fn redis_extend_vote() {
+        let mut redis = redis().await;
+        let ntw = Network::Testnet;
 
         redis
-            .add_vote(fip, vote, voter(), vote_length)
+            .start_vote(fip(89), vote_starter(), ntw)
             .await
             .unwrap();
 
-        // wait 1 second
-        tokio::time::sleep(time::Duration::from_secs(vote_length + 1)).await;
+        assert!(redis
+            .extend_vote(fip(89), Address::zero(), ntw, 1, 60, 60u64)
+            .await
+            .is_err());
+        assert!(redis
+            .extend_vote(fip(89), vote_starter(), ntw, 2, 60, 60u64)
+            .await
+            .is_err());
 
-        let active = redis.active_votes(ntw, vote_length).unwrap();
+        redis
+            .extend_vote(fip(89), vote_starter(), ntw, 1, 60, 60u64)
+            .await
+            .unwrap();
+        assert_eq!(redis.vote_extension(fip(89), ntw).unwrap(), 60);
+
+        // The vote runs past the configured length
+        assert!(matches!(
+            redis.vote_status(fip(89), 0u64, ntw).unwrap(),
+            VoteStatus::InProgress(_)
+        ));
+        match redis.vote_status(fip(89), 60u64, ntw).unwrap() {
+            VoteStatus::InProgress(time_left) => assert!(time_left > 60),
+            status => panic!("Unexpected status: {:?}", status),
+        }
 
-        assert!(!active.contains(&fip));
+        // A replayed or shorter extension changes nothing
+        assert!(redis
+            .extend_vote(fip(89), vote_starter(), ntw, 1, 60, 60u64)
+            .await
+            .is_err());
+        assert!(redis
+            .extend_vote(fip(89), vote_starter(), ntw, 1, 30, 60u64)
+            .await
+            .is_err());
+        assert_eq!(redis.vote_extension(fip(89), ntw).unwrap(), 60);
+    }
 
-        let concluded = redis.concluded_votes(ntw, vote_length).unwrap();
+    #[tokio::test]
+    async fn redis_schedule_vote() {
+        let mut redis = redis().await;
+        let clock = mock_clock(&mut redis);
+        let ntw = Network::Testnet;
+        let opens_at = clock.now() + 3600;
 
-        assert!(concluded.contains(&fip));
+        redis
+            .schedule_vote(fip(91), vote_starter(), ntw, opens_at)
+            .await
+            .unwrap();
+        assert_eq!(redis.vote_start(fip(91), ntw).unwrap(), opens_at);
+        assert!(matches!(
+            redis.vote_status(fip(91), 60u64, ntw).unwrap(),
+            VoteStatus::Scheduled(opens_in) if opens_in > 3590
+        ));
+        assert!(!redis.active_votes(ntw, 60u64).unwrap().contains(&fip(91)));
+
+        // The voting period starts once the vote opens
+        let now = clock.now();
+        clock.set(opens_at + 10);
+        assert_eq!(
+            redis.vote_status(fip(91), 60u64, ntw).unwrap(),
+            VoteStatus::InProgress(50)
+        );
+        clock.set(now);
+
+        // A scheduled vote can only be called off
+        assert!(redis
+            .end_vote(
+                fip(91),
+                vote_starter(),
+                ntw,
+                1,
+                VoteEnding::Concluded,
+                60u64
+            )
+            .await
+            .is_err());
+        redis
+            .end_vote(
+                fip(91),
+                vote_starter(),
+                ntw,
+                1,
+                VoteEnding::Cancelled,
+                60u64,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            redis.vote_status(fip(91), 60u64, ntw).unwrap(),
+            VoteStatus::Cancelled
+        );
     }
 
     #[tokio::test]
     async fn redis_get_storage() {
         let mut redis = redis().await;
 
-        let res = redis.get_storage(49u32, VoteOption::Yay, Network::Testnet);
+        let res = redis.get_storage(fip(49), VoteOption::Yay, Network::Testnet);
 
         println!("{:?}", res);
 
@@ -812,30 +6734,35 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn redis_add_storage() {
+    async fn redis_ballot_storage() {
         let mut redis = redis().await;
 
+        let res = redis.ballot_storage(&[TEST_SP_ID], Network::Testnet).await;
+        assert_eq!(res.unwrap()[&TEST_SP_ID], TEST_SP_POWER);
+
+        redis.set_power_provider(StaticPower::new([(TEST_SP_ID, 42)]));
         let res = redis
-            .add_storage(6024u32, Network::Testnet, VoteOption::Yay, 5u32)
+            .ballot_storage(&[TEST_SP_ID, 1000], Network::Testnet)
             .await;
-
-        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), BTreeMap::from([(TEST_SP_ID, 42), (1000, 0)]));
     }
 
     #[tokio::test]
     async fn redis_storage() {
         let mut redis = redis().await;
+        let ntw = Network::Testnet;
 
-        let res = redis
-            .add_storage(6024, Network::Testnet, VoteOption::Yay, 831u32)
-            .await;
-
-        assert!(res.is_ok());
+        redis.start_vote(fip(5), vote_starter(), ntw).await.unwrap();
+        let vote = test_vote(VoteOption::Yay, 5u32).vote().unwrap();
+        redis
+            .add_vote(fip(5), vote, voter(), 60u64, false)
+            .await
+            .unwrap();
 
-        let res = redis.get_storage(831u32, VoteOption::Yay, Network::Testnet);
+        let res = redis.get_storage(fip(5), VoteOption::Yay, ntw);
 
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), 10240000u128);
+        assert_eq!(res.unwrap(), TEST_SP_POWER);
     }
 
     #[tokio::test]
@@ -845,13 +6772,14 @@ mod tests {
         let vote = test_vote(VoteOption::Yay, 4u32).vote().unwrap();
 
         redis
-            .start_vote(4u32, vote_starter(), Network::Testnet)
+            .start_vote(fip(4), vote_starter(), Network::Testnet)
+            .await
             .unwrap();
-        let res = redis.add_vote(4u32, vote, voter(), 69u64).await;
+        let res = redis.add_vote(fip(4), vote, voter(), 69u64, false).await;
         println!("{:?}", res);
         assert!(res.is_ok());
 
-        let res = redis.vote_start(4u32, Network::Testnet);
+        let res = redis.vote_start(fip(4), Network::Testnet);
 
         match res {
             Ok(_) => {}
@@ -859,31 +6787,120 @@ mod tests {
         }
     }
 
+    #[test]
+    fn redis_vote_status_at() {
+        assert_eq!(VoteStatus::at(90, 100, 60, 0), VoteStatus::Scheduled(10));
+        assert_eq!(VoteStatus::at(100, 100, 60, 0), VoteStatus::InProgress(60));
+        assert_eq!(VoteStatus::at(159, 100, 60, 0), VoteStatus::InProgress(1));
+
+        // Just expired, and long expired, without underflowing
+        assert_eq!(VoteStatus::at(160, 100, 60, 0), VoteStatus::Concluded);
+        assert_eq!(VoteStatus::at(u64::MAX, 100, 60, 0), VoteStatus::Concluded);
+
+        // An end past the last second doesn't wrap around to concluded
+        assert_eq!(
+            VoteStatus::at(200, 100, u64::MAX, 0),
+            VoteStatus::InProgress(u64::MAX - 200)
+        );
+
+        // Ballots are still taken in the grace period, with no time left
+        assert_eq!(VoteStatus::at(160, 100, 60, 5), VoteStatus::InProgress(0));
+        assert_eq!(VoteStatus::at(164, 100, 60, 5), VoteStatus::InProgress(0));
+        assert_eq!(VoteStatus::at(165, 100, 60, 5), VoteStatus::Concluded);
+        assert_eq!(VoteStatus::at(159, 100, 60, 5), VoteStatus::InProgress(1));
+    }
+
+    #[test]
+    fn redis_factory_grace_period() {
+        let server = TestRedis::start();
+        let args = Args::parse_from([
+            "filecoin-vote",
+            "--redis-path",
+            server.url().as_str(),
+            "--vote-grace-period",
+            "5",
+        ]);
+        let factory = RedisFactory::from_args(&args);
+
+        assert_eq!(factory.open().unwrap().grace_period, 5);
+        assert_eq!(factory.reader().unwrap().grace_period, 5);
+        assert_eq!(Redis::new(server.url()).unwrap().grace_period, 0);
+    }
+
+    #[tokio::test]
+    async fn redis_grace_period() {
+        let mut redis = redis().await;
+        let clock = mock_clock(&mut redis);
+        redis.set_grace_period(5);
+
+        let num = fip(8);
+        let vote_length = 10u64;
+        let ntw = Network::Testnet;
+
+        redis.start_vote(num, vote_starter(), ntw).await.unwrap();
+        let opened_at = clock.now();
+
+        // A ballot arriving just after the end is taken and counted
+        clock.advance(vote_length + 1);
+        assert!(redis.is_open(num, vote_length, ntw, clock.now()).unwrap());
+        assert_eq!(
+            redis.vote_status(num, vote_length, ntw).unwrap(),
+            VoteStatus::InProgress(0)
+        );
+        assert!(!redis
+            .concluded_votes(ntw, vote_length)
+            .unwrap()
+            .contains(&num));
+
+        let vote = test_vote(VoteOption::Yay, num.get()).vote().unwrap();
+        redis
+            .add_vote(num, vote.clone(), voter(), vote_length, true)
+            .await
+            .unwrap();
+
+        // Once it is over the vote concludes with the late ballot in it
+        clock.advance(5);
+        assert!(!redis.is_open(num, vote_length, ntw, clock.now()).unwrap());
+        assert!(redis.is_open(num, vote_length, ntw, opened_at).unwrap());
+        assert!(redis
+            .concluded_votes(ntw, vote_length)
+            .unwrap()
+            .contains(&num));
+        assert_eq!(
+            redis.vote_results(num, vote_length, ntw).await.unwrap().yay,
+            1
+        );
+
+        assert!(redis
+            .add_vote(num, vote, voter(), vote_length, true)
+            .await
+            .is_err());
+    }
+
     #[tokio::test]
     async fn redis_vote_status() {
         let mut redis = redis().await;
+        let clock = mock_clock(&mut redis);
 
         let vote = test_vote(VoteOption::Yay, 3u32).vote().unwrap();
 
         redis
-            .start_vote(3u32, vote_starter(), Network::Testnet)
+            .start_vote(fip(3), vote_starter(), Network::Testnet)
+            .await
             .unwrap();
-        let res = redis.add_vote(3u32, vote, voter(), 69u64).await;
+        let res = redis.add_vote(fip(3), vote, voter(), 69u64, false).await;
         assert!(res.is_ok());
 
-        let vote_start = redis.vote_start(3u32, Network::Testnet).unwrap();
+        let vote_start = redis.vote_start(fip(3), Network::Testnet).unwrap();
 
-        tokio::time::sleep(time::Duration::from_secs(2)).await;
+        clock.advance(2);
 
-        let time_now = time::SystemTime::now()
-            .duration_since(time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        let time_now = clock.now();
 
         let ongoing = time_now - vote_start + 1;
         let concluded = time_now - vote_start - 1;
 
-        let res = redis.vote_status(3u32, ongoing, Network::Testnet);
+        let res = redis.vote_status(fip(3), ongoing, Network::Testnet);
 
         match res {
             Ok(_) => {}
@@ -891,7 +6908,7 @@ mod tests {
         }
         assert_eq!(res.unwrap(), VoteStatus::InProgress(1));
 
-        let res = redis.vote_status(3u32, concluded, Network::Testnet);
+        let res = redis.vote_status(fip(3), concluded, Network::Testnet);
 
         match res {
             Ok(_) => {}
@@ -899,7 +6916,7 @@ mod tests {
         }
         assert_eq!(res.unwrap(), VoteStatus::Concluded);
 
-        let res = redis.vote_status(1234089398u32, concluded, Network::Testnet);
+        let res = redis.vote_status(fip(9998), concluded, Network::Testnet);
 
         match res {
             Ok(_) => {}
@@ -908,6 +6925,61 @@ mod tests {
         assert_eq!(res.unwrap(), VoteStatus::DoesNotExist);
     }
 
+    #[tokio::test]
+    async fn redis_add_vote_batch() {
+        let mut redis = redis().await;
+        let ntw = Network::Testnet;
+        let id = |byte: u8| hex::encode([byte; 32]);
+
+        for num in [1, 2, 3] {
+            redis
+                .start_vote(fip(num), vote_starter(), ntw)
+                .await
+                .unwrap();
+        }
+
+        let batch = vec![
+            (test_vote(VoteOption::Yay, 1).vote().unwrap(), id(1)),
+            (test_vote(VoteOption::Nay, 2).vote().unwrap(), id(2)),
+        ];
+        let receipts = redis
+            .add_vote_batch(batch.clone(), voter(), 60, false)
+            .await
+            .unwrap();
+
+        assert_eq!(receipts.len(), 2);
+        assert_eq!(receipts[1].fip_number, fip(2));
+        assert_eq!(receipts[1].choice, VoteOption::Nay);
+        assert_eq!(redis.votes(fip(1), ntw).unwrap().len(), 1);
+        assert_eq!(
+            redis.get_storage(fip(2), VoteOption::Nay, ntw).unwrap(),
+            TEST_SP_POWER
+        );
+
+        // A retry gets the same receipts without recording anything again
+        let logged = redis.events(None, 100).unwrap().len();
+        let retried = redis
+            .add_vote_batch(batch, voter(), 60, false)
+            .await
+            .unwrap();
+
+        assert_eq!(retried, receipts);
+        assert_eq!(redis.events(None, 100).unwrap().len(), logged);
+
+        // FIP-4 was never started so the ballot on FIP-3 isn't recorded either
+        let batch = vec![
+            (test_vote(VoteOption::Yay, 3).vote().unwrap(), id(3)),
+            (test_vote(VoteOption::Yay, 4).vote().unwrap(), id(4)),
+        ];
+
+        assert!(redis
+            .add_vote_batch(batch, voter(), 60, false)
+            .await
+            .is_err());
+        assert!(redis.votes(fip(3), ntw).unwrap().is_empty());
+        assert_eq!(redis.vote_receipt(&id(3)).unwrap(), None);
+    }
+
     #[tokio::test]
     async fn redis_add_vote() {
         let mut redis = redis().await;
@@ -915,58 +6987,428 @@ mod tests {
         let vote = test_vote(VoteOption::Yay, 2u32).vote().unwrap();
 
         redis
-            .start_vote(2u32, vote_starter(), Network::Testnet)
+            .start_vote(fip(2), vote_starter(), Network::Testnet)
+            .await
             .unwrap();
 
-        let res = redis.add_vote(2u32, vote, voter(), 69u64).await;
+        let res = redis.add_vote(fip(2), vote, voter(), 69u64, false).await;
 
         match res {
             Ok(_) => {}
             Err(e) => panic!("Error: {}", e),
         }
 
-        let res = redis.vote_results(2u32, Network::Testnet);
+        let res = redis.vote_results(fip(2), 69u64, Network::Testnet).await;
 
         assert!(res.is_ok());
 
         let results: VoteResults = res.unwrap();
 
         assert_eq!(results.yay, 1);
-        assert_eq!(results.yay_storage_size, 10240000u128);
+        assert_eq!(results.yay_storage_size, TEST_SP_POWER);
+
+        // The ballot is kept in the history of the voter
+        let history = redis.voter_history(voter(), Network::Testnet).unwrap();
+        let ballot = history.iter().find(|b| b.fip_number == fip(2)).unwrap();
+
+        assert_eq!(ballot.round, 1);
+        assert_eq!(ballot.choice, VoteOption::Yay);
+        assert_eq!(ballot.storage_size, TEST_SP_POWER);
     }
 
     #[tokio::test]
-    async fn redis_test_duplicate_vote_start() {
+    async fn redis_add_vote_rotated_worker() {
+        let mut redis = redis().await;
+
+        let ntw = Network::Testnet;
+        let delegate = Address::from_low_u64_be(4049);
+        redis.delete_vote(fip(90), ntw).await.unwrap();
+        redis
+            .start_vote(fip(90), vote_starter(), ntw)
+            .await
+            .unwrap();
+        redis.register_voter(delegate, ntw, vec![6024]).unwrap();
+
+        // The worker of t06024 no longer uses the key that signed the registration
+        redis
+            .set_voter_worker(
+                delegate,
+                ntw,
+                Some("t1rotatedworkeraddressxxxxxxxxxxxxxxxxxx"),
+            )
+            .unwrap();
+        let vote = test_vote(VoteOption::Yay, 90u32).vote().unwrap();
+        let res = redis.add_vote(fip(90), vote, delegate, 69u64, false).await;
+
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("Worker key of every storage provider changed"));
+
+        redis
+            .set_voter_worker(
+                delegate,
+                ntw,
+                Some("t3qejyqmrirddrsb2w2thbaco3q6emuljumlhuonp3al35g3kkzx4zpeecycw7gim2meegemwot3gp3qr6alpa"),
+            )
+            .unwrap();
+        let vote = test_vote(VoteOption::Yay, 90u32).vote().unwrap();
+        let res = redis.add_vote(fip(90), vote, delegate, 69u64, false).await;
+
+        assert!(res.is_ok());
+        assert_eq!(
+            redis.get_storage(fip(90), VoteOption::Yay, ntw).unwrap(),
+            TEST_SP_POWER
+        );
+
+        redis.delete_vote(fip(90), ntw).await.unwrap();
+        redis.unregister_voter(delegate, ntw).unwrap();
+    }
+
+    #[tokio::test]
+    async fn redis_revote() {
+        let mut redis = redis().await;
+
+        let ntw = Network::Testnet;
+
+        redis.start_vote(fip(5), vote_starter(), ntw).await.unwrap();
+
+        let yay = test_vote(VoteOption::Yay, 5u32).vote().unwrap();
+        redis
+            .add_vote(fip(5), yay, voter(), 69u64, false)
+            .await
+            .unwrap();
+
+        let nay = test_vote(VoteOption::Nay, 5u32).vote().unwrap();
+        let res = redis.add_vote(fip(5), nay, voter(), 69u64, false).await;
+
+        assert!(res.is_err());
+
+        let nay = test_vote(VoteOption::Nay, 5u32).vote().unwrap();
+        let res = redis.add_vote(fip(5), nay, voter(), 69u64, true).await;
+
+        assert!(res.is_ok());
+
+        let results = redis.vote_results(fip(5), 69u64, ntw).await.unwrap();
+
+        assert_eq!(results.yay, 0);
+        assert_eq!(results.nay, 1);
+        assert_eq!(results.yay_storage_size, 0);
+        assert_eq!(results.nay_storage_size, TEST_SP_POWER);
+    }
+
+    #[tokio::test]
+    async fn redis_revote_power_changed() {
         let mut redis = redis().await;
+        let ntw = Network::Testnet;
+
+        redis.start_vote(fip(5), vote_starter(), ntw).await.unwrap();
+        let yay = test_vote(VoteOption::Yay, 5u32).vote().unwrap();
+        redis
+            .add_vote(fip(5), yay, voter(), 69u64, true)
+            .await
+            .unwrap();
 
+        // The yay ballot takes back the power it was cast with, not what the
+        // storage provider has now
+        redis.set_power_provider(StaticPower::new([(TEST_SP_ID, TEST_SP_POWER / 4)]));
+        let nay = test_vote(VoteOption::Nay, 5u32).vote().unwrap();
         redis
-            .register_vote_to_all_votes(1u32, Network::Testnet)
+            .add_vote(fip(5), nay, voter(), 69u64, true)
+            .await
             .unwrap();
 
+        let results = redis.vote_results(fip(5), 69u64, ntw).await.unwrap();
+        assert_eq!(results.yay_storage_size, 0);
+        assert_eq!(results.nay_storage_size, TEST_SP_POWER / 4);
+    }
+
+    #[tokio::test]
+    async fn redis_add_vote_fip_mismatch() {
+        let mut redis = redis().await;
+
+        let vote = test_vote(VoteOption::Yay, 3u32).vote().unwrap();
+
         redis
-            .register_vote_to_all_votes(3u32, Network::Testnet)
+            .start_vote(fip(7), vote_starter(), Network::Testnet)
+            .await
+            .unwrap();
+
+        let res = redis.add_vote(fip(7), vote, voter(), 69u64, false).await;
+
+        assert!(res.is_err());
+
+        let res = redis
+            .vote_results(fip(7), 69u64, Network::Testnet)
+            .await
             .unwrap();
+
+        assert_eq!(res.yay, 0);
+    }
+
+    #[tokio::test]
+    async fn redis_test_duplicate_vote_start() {
+        let mut redis = redis().await;
+
+        register_vote_to_all_votes(&mut redis, fip(1), Network::Testnet).unwrap();
+
+        register_vote_to_all_votes(&mut redis, fip(3), Network::Testnet).unwrap();
     }
 
     #[tokio::test]
     async fn redis_vote_exists() {
         let mut redis = redis().await;
 
-        let res = redis.vote_exists(Network::Testnet, 129u32);
+        let res = redis.vote_exists(Network::Testnet, fip(129));
 
         assert!(res.is_ok());
         assert!(!res.unwrap());
 
         redis
-            .start_vote(129u32, vote_starter(), Network::Testnet)
+            .start_vote(fip(129), vote_starter(), Network::Testnet)
+            .await
             .unwrap();
 
-        let res = redis.vote_exists(Network::Testnet, 129u32);
+        let res = redis.vote_exists(Network::Testnet, fip(129));
 
         assert!(res.is_ok());
         assert!(res.unwrap());
     }
 
+    #[test]
+    fn redis_legacy_keys() {
+        let keys = [
+            LookupKey::Votes(fip(1), Network::Testnet),
+            LookupKey::Storage(Network::Mainnet, fip(9999)),
+            LookupKey::Voter(Network::Testnet, voter()),
+            LookupKey::Network(voter()),
+            LookupKey::VoterClass(Network::Mainnet, voter()),
+            LookupKey::AllVotes(Network::Testnet),
+            LookupKey::SchemaVersion,
+            LookupKey::Receipt([7; 32]),
+        ];
+
+        for key in keys {
+            let decoded = LookupKey::from_legacy_bytes(&key.legacy_bytes()).unwrap();
+            assert_eq!(decoded.to_key(), key.to_key());
+        }
+
+        assert_eq!(
+            LookupKey::Votes(fip(1), Network::Testnet).to_key(),
+            "fipvote:calibration:votes:1"
+        );
+        assert_eq!(
+            LookupKey::Voter(Network::Mainnet, voter()).to_key(),
+            "fipvote:mainnet:voter:0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56"
+        );
+        // Tallies dropped in version 1 are not renamed
+        let tally = legacy_tally_key(fip(1), Network::Mainnet, &VoteOption::Yay, false);
+        assert!(LookupKey::from_legacy_bytes(&tally).is_none());
+    }
+
+    #[test]
+    fn redis_key_uniqueness() {
+        let mut keys = HashSet::new();
+        let mut tallies = HashSet::new();
+
+        for num in 1..=MAX_FIP_NUMBER {
+            for ntw in networks() {
+                let fip = fip(num);
+                let lookups = [
+                    LookupKey::Votes(fip, ntw),
+                    LookupKey::Timestamp(fip, ntw),
+                    LookupKey::Storage(ntw, fip),
+                    LookupKey::Silence(fip, ntw),
+                    LookupKey::Metadata(fip, ntw),
+                    LookupKey::FinalResults(fip, ntw),
+                    LookupKey::Fil(ntw, fip),
+                    LookupKey::Round(fip, ntw),
+                    LookupKey::PreviousRound(fip, ntw),
+                    LookupKey::Published(fip, ntw),
+                    LookupKey::Options(fip, ntw),
+                    LookupKey::Quorum(fip, ntw),
+                    LookupKey::Weighting(fip, ntw),
+                    LookupKey::MinPower(fip, ntw),
+                    LookupKey::Ended(fip, ntw),
+                    LookupKey::Extension(fip, ntw),
+                    LookupKey::Lock(fip, ntw),
+                ];
+                for lookup in lookups {
+                    assert!(keys.insert(lookup.to_key()));
+                }
+
+                // Every choice gets its own field in the tally hashes
+                for choice in [VoteOption::Yay, VoteOption::Nay, VoteOption::Abstain] {
+                    let field = tally_field(&choice);
+                    let storage = LookupKey::Storage(ntw, fip).to_key();
+                    let fil = LookupKey::Fil(ntw, fip).to_key();
+                    assert!(tallies.insert((storage, field.clone())));
+                    assert!(tallies.insert((fil, field)));
+                }
+            }
+        }
+
+        for ntw in networks() {
+            for lookup in [
+                LookupKey::VoteStarters(ntw),
+                LookupKey::Admins(ntw),
+                LookupKey::AllVotes(ntw),
+                LookupKey::RegisteredVoters(ntw),
+                LookupKey::Voter(ntw, voter()),
+                LookupKey::VoterClass(ntw, voter()),
+                LookupKey::History(ntw, voter()),
+                LookupKey::Expiry(ntw, voter()),
+                LookupKey::Worker(ntw, voter()),
+                LookupKey::Offers(ntw, voter()),
+                LookupKey::Delegators(ntw, TEST_SP_ID),
+            ] {
+                assert!(keys.insert(lookup.to_key()));
+            }
+        }
+        assert!(keys.insert(LookupKey::Network(voter()).to_key()));
+        assert!(keys.insert(LookupKey::Receipt([0; 32]).to_key()));
+        assert!(keys.insert(LookupKey::Nonce([0; 16]).to_key()));
+        assert!(keys.insert(LookupKey::RegistrationQueue.to_key()));
+        assert!(keys.insert(LookupKey::RegistrationStatus(voter()).to_key()));
+        assert!(keys.insert(LookupKey::Events.to_key()));
+        assert!(keys.insert(LookupKey::ResultsVersion.to_key()));
+        assert!(keys.insert(LookupKey::SchemaVersion.to_key()));
+    }
+
+    #[tokio::test]
+    async fn redis_migrate() {
+        let mut redis = redis().await;
+        redis.flush_all().unwrap();
+
+        // Write a vote the way versions before 1 stored it
+        let ntw = Network::Testnet;
+        let vote = test_vote(VoteOption::Yay, 1u32).vote().unwrap();
+        let legacy = [
+            (
+                LookupKey::AllVotes(ntw).legacy_bytes(),
+                serde_json::to_vec(&[fip(1)]).unwrap(),
+            ),
+            (
+                LookupKey::Votes(fip(1), ntw).legacy_bytes(),
+                serde_json::to_vec(&[&vote]).unwrap(),
+            ),
+            (
+                legacy_tally_key(fip(1), ntw, &VoteOption::Yay, false),
+                2048u128.to_be_bytes().to_vec(),
+            ),
+            (
+                LookupKey::VoteStarters(ntw).legacy_bytes(),
+                vote_starter().as_bytes().to_vec(),
+            ),
+            (
+                LookupKey::RegisteredVoters(ntw).legacy_bytes(),
+                serde_json::to_vec(&[voter()]).unwrap(),
+            ),
+            (
+                LookupKey::Network(voter()).legacy_bytes(),
+                ntw.name().as_bytes().to_vec(),
+            ),
+        ];
+        for (key, value) in legacy {
+            redis.con.set::<Vec<u8>, Vec<u8>, ()>(key, value).unwrap();
+        }
+
+        assert_eq!(redis.schema_version().unwrap(), 0);
+        assert_eq!(redis.migrate().unwrap(), 0);
+        assert_eq!(redis.schema_version().unwrap(), SCHEMA_VERSION);
+
+        assert_eq!(redis.all_votes(ntw).unwrap(), vec![fip(1)]);
+        assert_eq!(redis.votes(fip(1), ntw).unwrap().len(), 1);
+        assert_eq!(
+            redis.get_storage(fip(1), VoteOption::Yay, ntw).unwrap(),
+            2048
+        );
+        assert_eq!(redis.voter_starters(ntw).unwrap(), vec![vote_starter()]);
+        assert_eq!(redis.registered_voters(ntw).unwrap(), vec![voter()]);
+        assert_eq!(redis.voter_networks(voter()).unwrap(), vec![ntw]);
+        assert!(!redis
+            .con
+            .exists::<String, bool>(LookupKey::Network(voter()).to_key())
+            .unwrap());
+        assert!(!redis
+            .con
+            .exists::<Vec<u8>, bool>(legacy_tally_key(fip(1), ntw, &VoteOption::Yay, false))
+            .unwrap());
+
+        assert!(redis
+            .con
+            .exists::<&str, bool>("fipvote:calibration:votes:1")
+            .unwrap());
+
+        let history = redis.voter_history(vote.voter(), ntw).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].fip_number, fip(1));
+        assert_eq!(history[0].choice, VoteOption::Yay);
+
+        // Running again leaves upgraded data alone
+        assert_eq!(redis.migrate().unwrap(), SCHEMA_VERSION);
+        assert_eq!(redis.votes(fip(1), ntw).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn redis_migrate_ballot_classes() {
+        let mut redis = redis().await;
+        redis.flush_all().unwrap();
+
+        // Ballots of version 5 were stored without the class of their voter
+        let ntw = Network::Testnet;
+        let ballot = |voter: u64| -> Vote {
+            serde_json::from_value(serde_json::json!({
+                "choice": VoteOption::Yay,
+                "address": Address::from_low_u64_be(voter),
+                "fip": 3,
+            }))
+            .unwrap()
+        };
+        let (core_dev, provider) = (ballot(1), ballot(2));
+        register_vote_to_all_votes(&mut redis, fip(3), ntw).unwrap();
+        for vote in [&core_dev, &provider] {
+            redis
+                .con
+                .rpush::<String, String, ()>(
+                    LookupKey::Votes(fip(3), ntw).to_key(),
+                    serde_json::to_string(vote).unwrap(),
+                )
+                .unwrap();
+        }
+        redis
+            .con
+            .set::<String, String, ()>(
+                LookupKey::VoterClass(ntw, core_dev.voter()).to_key(),
+                serde_json::to_string(&VoterClass::CoreDev).unwrap(),
+            )
+            .unwrap();
+        redis
+            .con
+            .set::<String, u64, ()>(LookupKey::SchemaVersion.to_key(), 5)
+            .unwrap();
+
+        assert_eq!(redis.migrate().unwrap(), 5);
+
+        let classes: Vec<VoterClass> = redis
+            .votes(fip(3), ntw)
+            .unwrap()
+            .iter()
+            .map(|vote| vote.class())
+            .collect();
+        assert_eq!(
+            classes,
+            vec![VoterClass::CoreDev, VoterClass::StorageProvider]
+        );
+
+        // The ballot keeps the class it was cast as once the voter changes class
+        redis.unregister_voter(core_dev.voter(), ntw).unwrap();
+        assert_eq!(
+            redis.votes(fip(3), ntw).unwrap()[0].class(),
+            VoterClass::CoreDev
+        );
+    }
+
     #[tokio::test]
     async fn redis_register_to_all_votes() {
         let mut redis = redis().await;
@@ -975,18 +7417,14 @@ mod tests {
 
         assert!(res.is_empty());
 
-        redis
-            .register_vote_to_all_votes(87u32, Network::Testnet)
-            .unwrap();
+        register_vote_to_all_votes(&mut redis, fip(87), Network::Testnet).unwrap();
 
         let res = redis.all_votes(Network::Testnet).unwrap();
 
         assert_eq!(res.len(), 1);
-        assert_eq!(res[0], 87u32);
+        assert_eq!(res[0], fip(87));
 
-        redis
-            .register_vote_to_all_votes(87u32, Network::Testnet)
-            .unwrap();
+        register_vote_to_all_votes(&mut redis, fip(87), Network::Testnet).unwrap();
     }
 
     #[tokio::test]
@@ -995,14 +7433,15 @@ mod tests {
         let vote = test_vote(VoteOption::Yay, 1u32).vote().unwrap();
 
         redis
-            .start_vote(1u32, vote_starter(), Network::Testnet)
+            .start_vote(fip(1), vote_starter(), Network::Testnet)
+            .await
             .unwrap();
 
-        let res = redis.add_vote(1u32, vote, voter(), 69u64).await;
+        let res = redis.add_vote(fip(1), vote, voter(), 69u64, false).await;
         println!("{:?}", res);
         assert!(res.is_ok());
 
-        let res = redis.vote_results(1u32, Network::Testnet);
+        let res = redis.vote_results(fip(1), 69u64, Network::Testnet).await;
 
         match res {
             Ok(_) => {}
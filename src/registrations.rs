@@ -0,0 +1,320 @@
+//! Registrations waiting for their storage providers to be verified
+//!
+//! Checking that a worker controls a storage provider takes a chain lookup
+//! per storage provider, so registering many of them at once could outlast
+//! the request. A registration is queued once its signature checks out and
+//! verified in the background, and its progress is served by
+//! `/filecoin/register/status`
+use std::time::Duration;
+
+use ethers::types::Address;
+use futures_util::{stream, StreamExt};
+use redis::RedisError;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::{
+    errors::*,
+    messages::{
+        domain::DomainRules,
+        vote_registration::{
+            PendingRegistration, ReceivedVoterRegistration, RegistrationReceipt,
+            VoteRegistrationError,
+        },
+    },
+    redis::Redis,
+    storage::{Network, StorageFetchError},
+    store::{StoreError, VoteStore},
+    Args,
+};
+
+/// How often the queue is checked for new registrations
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Most registrations a bulk registration can hold
+pub const MAX_BULK_REGISTRATIONS: usize = 500;
+/// Registrations of a bulk registration whose signatures are checked at once,
+/// multisig approvals each take chain lookups
+const BULK_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Error)]
+pub enum RegistrationError {
+    #[error("{}: {}", VOTE_RECOVER_ERROR, .0)]
+    Verify(#[from] VoteRegistrationError),
+    #[error("{}: {}", VOTING_POWER_ERROR, .0)]
+    Power(#[from] StorageFetchError),
+    #[error("{}: {}", VOTE_ADD_ERROR, .0)]
+    Store(#[from] StoreError),
+}
+
+/// Where the last registration of an address is at
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum RegistrationStatus {
+    /// Waiting for its storage providers to be verified
+    Pending {
+        /// Unix timestamp the registration was queued at
+        queued_at: u64,
+    },
+    /// Verified and registered
+    Registered { receipt: RegistrationReceipt },
+    /// Verified, waiting for the voter to accept the delegation, see `--require-acceptance`
+    Offered { receipt: RegistrationReceipt },
+    /// Rejected, the voter has to register again
+    Failed { error: String },
+}
+
+/// Outcome of one registration of a bulk registration
+#[derive(Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct BulkRegistration {
+    /// The address being registered, left out if the registration couldn't be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub address: Option<Address>,
+    /// `pending` once queued, `failed` with the error it was rejected with
+    #[serde(flatten)]
+    pub status: RegistrationStatus,
+}
+
+/// A verified registration waiting for the voter to accept it
+///
+/// Offers are kept by the worker or multisig address that made them, a later
+/// offer from the same address replaces the earlier one
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct DelegationOffer {
+    /// Worker or multisig address that offered the delegation, named to accept it
+    pub worker_address: String,
+    /// Storage providers the voter would vote for
+    pub sp_ids: Vec<u32>,
+    /// Unix timestamp the storage providers were verified at
+    pub offered_at: u64,
+}
+
+impl DelegationOffer {
+    /// The registration queued again once the voter accepted the offer, its
+    /// storage providers are verified again before the voter is registered
+    pub fn accept(self, voter: Address, ntw: Network) -> PendingRegistration {
+        PendingRegistration {
+            address: voter,
+            network: ntw,
+            sp_ids: self.sp_ids,
+            worker_address: self.worker_address,
+            accepted: true,
+        }
+    }
+}
+
+/// Checks the signatures and domain of every registration, a few at a time,
+/// returning the results in the order the registrations were sent
+///
+/// Domains are checked as of `now`
+pub async fn recover_bulk(
+    regs: Vec<ReceivedVoterRegistration>,
+    rules: &DomainRules,
+    now: u64,
+) -> Vec<Result<PendingRegistration, VoteRegistrationError>> {
+    stream::iter(regs)
+        .map(|reg| async move {
+            let pending = reg.recover_pending_registration().await?;
+            reg.check_domain(pending.network, rules, now)?;
+
+            Ok::<_, VoteRegistrationError>(pending)
+        })
+        .buffered(BULK_CONCURRENCY)
+        .collect()
+        .await
+}
+
+/// Verifies queued registrations and registers the voters that pass
+pub struct RegistrationQueue {
+    config: Args,
+}
+
+impl RegistrationQueue {
+    pub fn from_args(args: &Args) -> Self {
+        Self {
+            config: args.clone(),
+        }
+    }
+
+    /// Works through the queue every poll interval until the server stops
+    pub fn spawn(self) {
+        actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.process().await {
+                    println!("Error processing registrations: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Verifies every queued registration, returning how many were processed
+    ///
+    /// Each registration is taken off the queue by one instance only, and a
+    /// rejected one is recorded as failed without holding up the others
+    pub async fn process(&self) -> Result<usize, RedisError> {
+        let mut redis = Redis::new(self.config.redis_path())?;
+
+        let mut processed = 0;
+        while let Some(pending) = redis.next_registration()? {
+            let status = match self.register(&mut redis, &pending).await {
+                Ok(status) => {
+                    let action = match status {
+                        RegistrationStatus::Offered { .. } => "Offered the delegation to",
+                        _ => "Registered",
+                    };
+                    println!(
+                        "{} {:?} on {} for {} storage providers",
+                        action,
+                        pending.address,
+                        pending.network.name(),
+                        pending.sp_ids.len()
+                    );
+                    status
+                }
+                Err(e) => {
+                    println!("{}", e);
+                    RegistrationStatus::Failed {
+                        error: e.to_string(),
+                    }
+                }
+            };
+            redis.set_registration_status(pending.address, &status)?;
+            processed += 1;
+        }
+
+        Ok(processed)
+    }
+
+    /// Registers the voter once the storage providers check out, or offers
+    /// them the delegation when the voter has to accept it first
+    async fn register(
+        &self,
+        redis: &mut impl VoteStore,
+        pending: &PendingRegistration,
+    ) -> Result<RegistrationStatus, RegistrationError> {
+        let registration = pending.verify().await?;
+
+        // Fetch the power before writing so a failed lookup leaves the registration untouched
+        let mut receipt = registration
+            .receipt(redis.power_provider().as_ref())
+            .await?;
+
+        if self.config.require_acceptance() && !pending.accepted {
+            let offer = DelegationOffer {
+                worker_address: registration.worker_address(),
+                sp_ids: registration.sp_ids(),
+                offered_at: redis.now(),
+            };
+            redis.offer_delegation(registration.address(), registration.ntw(), &offer)?;
+
+            return Ok(RegistrationStatus::Offered { receipt });
+        }
+
+        receipt.expires_at = self.config.registration_expiry(redis.now());
+
+        redis.register_voter(
+            registration.address(),
+            registration.ntw(),
+            registration.sp_ids(),
+        )?;
+        redis.set_voter_worker(
+            registration.address(),
+            registration.ntw(),
+            Some(&registration.worker_address()),
+        )?;
+        redis.set_registration_expiry(
+            registration.address(),
+            registration.ntw(),
+            receipt.expires_at,
+        )?;
+
+        Ok(RegistrationStatus::Registered { receipt })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registrations_bulk_registration() {
+        let queued = BulkRegistration {
+            address: Some(Address::zero()),
+            status: RegistrationStatus::Pending {
+                queued_at: 1700000000,
+            },
+        };
+        assert_eq!(
+            serde_json::to_string(&queued).unwrap(),
+            format!(
+                r#"{{"address":"{:?}","status":"pending","queued_at":1700000000}}"#,
+                Address::zero()
+            )
+        );
+
+        let rejected = BulkRegistration {
+            address: None,
+            status: RegistrationStatus::Failed {
+                error: "Invalid message format".to_string(),
+            },
+        };
+        assert_eq!(
+            serde_json::to_string(&rejected).unwrap(),
+            r#"{"status":"failed","error":"Invalid message format"}"#
+        );
+    }
+
+    #[test]
+    fn registrations_status() {
+        let status = RegistrationStatus::Pending {
+            queued_at: 1700000000,
+        };
+        assert_eq!(
+            serde_json::to_string(&status).unwrap(),
+            r#"{"status":"pending","queued_at":1700000000}"#
+        );
+
+        let failed: RegistrationStatus =
+            serde_json::from_str(r#"{"status":"failed","error":"Invalid worker address"}"#)
+                .unwrap();
+        assert_eq!(
+            failed,
+            RegistrationStatus::Failed {
+                error: "Invalid worker address".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn registrations_delegation_offer() {
+        let offer = DelegationOffer {
+            worker_address: "t3abc".to_string(),
+            sp_ids: vec![1000, 1001],
+            offered_at: 1700000000,
+        };
+
+        // Accepting queues the offer again as a registration that isn't offered twice
+        let pending = offer.accept(Address::zero(), Network::Testnet);
+        assert_eq!(pending.address, Address::zero());
+        assert_eq!(pending.network, Network::Testnet);
+        assert_eq!(pending.sp_ids, vec![1000, 1001]);
+        assert_eq!(pending.worker_address, "t3abc");
+        assert!(pending.accepted);
+
+        let offered = RegistrationStatus::Offered {
+            receipt: RegistrationReceipt {
+                address: Address::zero(),
+                storage_providers: Vec::new(),
+                voting_power: 0,
+                expires_at: None,
+            },
+        };
+        assert!(serde_json::to_string(&offered)
+            .unwrap()
+            .starts_with(r#"{"status":"offered""#));
+    }
+}
@@ -0,0 +1,158 @@
+use std::{cell::RefCell, future::Future, rc::Rc, time::Instant};
+
+use actix_web::{
+    dev::{Payload, Service, ServiceRequest, ServiceResponse},
+    web::{Bytes, BytesMut},
+    Error,
+};
+use futures_util::StreamExt;
+use serde_json::Value;
+
+/// Largest body kept for the log, larger bodies are logged by their size
+const CAPTURE_LIMIT: usize = 16 * 1024;
+/// Characters of a body printed before it is cut off
+const LOGGED_BODY_LENGTH: usize = 512;
+/// Written in place of signatures so logs can't be used to replay a message
+const REDACTED: &str = "[redacted]";
+
+/// The body of a request as the handler read it
+#[derive(Default)]
+struct Captured {
+    body: BytesMut,
+    size: usize,
+}
+
+impl Captured {
+    fn push(&mut self, chunk: &Bytes) {
+        self.size += chunk.len();
+        if self.size <= CAPTURE_LIMIT {
+            self.body.extend_from_slice(chunk);
+        }
+    }
+}
+
+/// The body as it is logged, json with every signature masked and cut off
+/// after `LOGGED_BODY_LENGTH` characters
+///
+/// Bodies that aren't json or were too large to keep are logged by their size
+/// only, since a signature in them can't be found to be masked
+pub fn redact(body: &[u8], size: usize) -> String {
+    if size == 0 {
+        return String::new();
+    }
+
+    let mut json = match serde_json::from_slice::<Value>(body) {
+        Ok(json) if size <= CAPTURE_LIMIT => json,
+        _ => return format!("<{} bytes>", size),
+    };
+    mask(&mut json, false);
+
+    let logged = json.to_string();
+    match logged.char_indices().nth(LOGGED_BODY_LENGTH) {
+        Some((end, _)) => format!("{}...", &logged[..end]),
+        None => logged,
+    }
+}
+
+/// Replaces the strings under every key naming a signature
+fn mask(value: &mut Value, secret: bool) {
+    match value {
+        Value::String(s) if secret => *s = REDACTED.to_string(),
+        Value::Array(values) => values.iter_mut().for_each(|v| mask(v, secret)),
+        Value::Object(fields) => {
+            for (key, v) in fields.iter_mut() {
+                mask(v, secret || key.to_ascii_lowercase().contains("signature"));
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Middleware logging the method, path, status, latency and redacted body of
+/// every request once it is answered
+///
+/// The body is copied as the handler reads it, so the limits of each endpoint
+/// still apply and bodies the handler doesn't read aren't logged
+pub fn middleware<S, B>(
+    enabled: bool,
+    mut req: ServiceRequest,
+    srv: &S,
+) -> impl Future<Output = Result<ServiceResponse<B>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    let captured = enabled.then(|| {
+        let captured = Rc::new(RefCell::new(Captured::default()));
+        let sink = captured.clone();
+        let payload = req.take_payload().inspect(move |chunk| {
+            if let Ok(chunk) = chunk {
+                sink.borrow_mut().push(chunk);
+            }
+        });
+        req.set_payload(Payload::Stream {
+            payload: Box::pin(payload),
+        });
+        captured
+    });
+
+    let method = req.method().clone();
+    let uri = req.uri().clone();
+    let start = Instant::now();
+    let fut = srv.call(req);
+
+    async move {
+        let res = fut.await;
+
+        if let Some(captured) = captured {
+            let status = match &res {
+                Ok(res) => res.status(),
+                Err(e) => e.as_response_error().status_code(),
+            };
+            let captured = captured.borrow();
+            println!(
+                "{} {} {} {}ms {}",
+                method,
+                uri,
+                status.as_u16(),
+                start.elapsed().as_millis(),
+                redact(&captured.body, captured.size)
+            );
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn request_log_redact() {
+        let logged =
+            |body: &[u8]| -> Value { serde_json::from_str(&redact(body, body.len())).unwrap() };
+
+        assert_eq!(
+            logged(br#"{"signature":"0x67ae6539cd","message":"YAY: FIP-1"}"#),
+            json!({"signature": "[redacted]", "message": "YAY: FIP-1"})
+        );
+
+        // Signatures of multisig approvals are masked wherever they are nested
+        assert_eq!(
+            logged(br#"[{"signatures":["0x01","0x02"],"sp_ids":[1000]}]"#),
+            json!([{"signatures": ["[redacted]", "[redacted]"], "sp_ids": [1000]}])
+        );
+
+        let long = format!(r#"{{"message":"{}"}}"#, "A".repeat(1000));
+        let logged = redact(long.as_bytes(), long.len());
+        assert_eq!(logged.len(), LOGGED_BODY_LENGTH + 3);
+        assert!(logged.ends_with("..."));
+
+        // Bodies that can't be searched for signatures are only counted
+        assert_eq!(redact(b"0x67ae6539cd", 12), "<12 bytes>");
+        assert_eq!(redact(b"", CAPTURE_LIMIT + 1), "<16385 bytes>");
+        assert_eq!(redact(b"", 0), "");
+    }
+}
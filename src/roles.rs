@@ -0,0 +1,398 @@
+//! Who may do what on a network
+//!
+//! Admins grant and revoke the admin and starter roles with signed messages
+//! and are the only ones allowed to flush the ballots of a vote. Starters
+//! start, end and describe votes, and voters are whoever registered to vote.
+//! The authorized voters loaded at startup hold every role but voter
+use actix_web::{web, HttpResponse, Responder};
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    authorized_voters,
+    errors::*,
+    messages::{
+        role_grant::{RoleChange, RoleGrant},
+        vote_flush::VoteFlush,
+    },
+    post::{check_domain, check_freshness},
+    storage::{parse_address, Network},
+    store::{StoreError, StoreFactory, VoteStore},
+    Args, NtwAddrParams, NtwParams,
+};
+
+/// Role an address holds on a network
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Grants and revokes roles and flushes votes
+    Admin,
+    /// Starts, ends and describes votes
+    Starter,
+    /// Registered to vote
+    Voter,
+}
+
+/// The roles the address holds on the network
+pub fn roles(
+    redis: &mut impl VoteStore,
+    address: Address,
+    ntw: Network,
+) -> Result<Vec<Role>, StoreError> {
+    let bootstrap = authorized_voters().contains(&address);
+
+    let mut roles = Vec::new();
+    if bootstrap || redis.is_admin(address, ntw)? {
+        roles.push(Role::Admin);
+    }
+    if bootstrap || redis.is_authorized_starter(address, ntw)? {
+        roles.push(Role::Starter);
+    }
+    if redis.is_registered(address, ntw) {
+        roles.push(Role::Voter);
+    }
+
+    Ok(roles)
+}
+
+/// Gives the role to the address or takes it away
+///
+/// Only admins and starters are changed this way, voters are added and
+/// removed by registering and unregistering
+pub fn change_role(
+    redis: &mut impl VoteStore,
+    change: RoleChange,
+    role: Role,
+    address: Address,
+    ntw: Network,
+) -> Result<(), StoreError> {
+    match (change, role) {
+        (RoleChange::Grant, Role::Admin) => redis.register_admin(address, ntw),
+        (RoleChange::Grant, Role::Starter) => redis.register_voter_starter(address, ntw),
+        (RoleChange::Revoke, Role::Admin) => redis.remove_admin(address, ntw),
+        (RoleChange::Revoke, Role::Starter) => redis.remove_voter_starters(address, ntw),
+        (_, Role::Voter) => Err(StoreError::Invalid(
+            "Voters register themselves".to_string(),
+        )),
+    }
+}
+
+/// Whether the signer may add or remove vote starters through
+/// `/filecoin/registerstarter` and `/filecoin/unregisterstarter`
+///
+/// Any starter or admin may add a starter, but only admins may remove one so
+/// a single starter can't lock the others out
+pub fn may_change_starters(
+    redis: &mut impl VoteStore,
+    signer: Address,
+    change: RoleChange,
+    ntw: Network,
+) -> Result<bool, StoreError> {
+    let roles = roles(redis, signer, ntw)?;
+
+    Ok(match change {
+        RoleChange::Grant => roles.contains(&Role::Admin) || roles.contains(&Role::Starter),
+        RoleChange::Revoke => roles.contains(&Role::Admin),
+    })
+}
+
+/// Registers the role endpoints, reading and writing the stores `F` opens
+pub fn routes<F: StoreFactory>(cfg: &mut web::ServiceConfig) {
+    cfg.route("/filecoin/roles", web::get().to(get_roles::<F>))
+        .route("/filecoin/roles", web::post().to(update_role::<F>))
+        .route("/filecoin/flushvote", web::post().to(flush_vote::<F>));
+}
+
+#[utoipa::path(
+    get,
+    path = "/filecoin/roles",
+    tag = "roles",
+    params(NtwAddrParams),
+    responses(
+        (status = 200, description = "The roles the address holds on the network", body = [Role]),
+        (status = 400, description = "Invalid address", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_roles<F: StoreFactory>(
+    query_params: web::Query<NtwAddrParams>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+
+    let address = match parse_address(ntw, &query_params.address) {
+        Ok(address) => address,
+        Err(e) => {
+            let res = format!("{}: {}", INVALID_ADDRESS, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    match roles(&mut redis, address, ntw) {
+        Ok(roles) => HttpResponse::Ok().json(roles),
+        Err(e) => {
+            let res = format!("{}: {}", ROLES_ERROR, e);
+            println!("{}", res);
+            HttpResponse::InternalServerError().body(res)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/filecoin/roles",
+    tag = "roles",
+    params(NtwParams),
+    request_body = RoleGrant,
+    responses(
+        (status = 200, description = "The role was granted or revoked"),
+        (status = 400, description = "Invalid role grant, or the last admin or starter can't be revoked", body = String),
+        (status = 403, description = "The signer is not an admin", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn update_role<F: StoreFactory>(
+    query_params: web::Query<NtwParams>,
+    grant: web::Json<RoleGrant>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+
+    let grant = grant.into_inner();
+
+    let (signer, change, role, address) = match grant.auth(ntw) {
+        Ok(auth) => auth,
+        Err(e) => {
+            let res = format!("{}: {}", ROLE_GRANT_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    if let Err(res) = check_admin(&mut redis, signer, ntw) {
+        return res;
+    }
+
+    let freshness = match grant.freshness() {
+        Ok(freshness) => freshness,
+        Err(e) => {
+            let res = format!("{}: {}", ROLE_GRANT_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    if let Err(res) = check_freshness(&mut redis, freshness, &config) {
+        return res;
+    }
+
+    if let Err(e) = change_role(&mut redis, change, role, address, ntw) {
+        let res = format!("{}: {}", ROLE_CHANGE_ERROR, e);
+        println!("{}", res);
+        return HttpResponse::BadRequest().body(res);
+    }
+
+    println!("{:?} {:?} {:?} on {:?}", change, role, address, ntw);
+
+    HttpResponse::Ok().finish()
+}
+
+#[utoipa::path(
+    post,
+    path = "/filecoin/flushvote",
+    tag = "roles",
+    params(NtwParams),
+    request_body = VoteFlush,
+    responses(
+        (status = 200, description = "Every ballot of the vote was removed"),
+        (status = 400, description = "Invalid vote flush", body = String),
+        (status = 403, description = "The signer is not an admin", body = String),
+        (status = 404, description = "The vote does not exist"),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn flush_vote<F: StoreFactory>(
+    query_params: web::Query<NtwParams>,
+    flush: web::Json<VoteFlush>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+
+    let flush = flush.into_inner();
+
+    let (signer, fip_number) = match flush.auth() {
+        Ok(auth) => auth,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_FLUSH_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    if let Err(res) = check_admin(&mut redis, signer, ntw) {
+        return res;
+    }
+
+    let domain = match flush.domain() {
+        Ok(domain) => domain,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_FLUSH_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    if let Err(res) = check_domain(domain.as_ref(), None, ntw, &config, redis.now()) {
+        return res;
+    }
+
+    let freshness = match flush.freshness() {
+        Ok(freshness) => freshness,
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_FLUSH_RECOVER_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+    if let Err(res) = check_freshness(&mut redis, freshness, &config) {
+        return res;
+    }
+
+    match redis.vote_exists(ntw, fip_number) {
+        Ok(true) => (),
+        Ok(false) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_EXISTS_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    }
+
+    if let Err(e) = redis.flush_vote(fip_number, ntw) {
+        let res = format!("{}: {}", VOTE_FLUSH_ERROR, e);
+        println!("{}", res);
+        return HttpResponse::InternalServerError().body(res);
+    }
+
+    println!(
+        "{:?} flushed the ballots of FIP-{} on {:?}",
+        signer, fip_number, ntw
+    );
+
+    HttpResponse::Ok().finish()
+}
+
+/// Checks that the signer is an admin of the network
+fn check_admin(
+    redis: &mut impl VoteStore,
+    signer: Address,
+    ntw: Network,
+) -> Result<(), HttpResponse> {
+    match redis.is_admin(signer, ntw) {
+        Ok(true) => Ok(()),
+        Ok(false) if authorized_voters().contains(&signer) => Ok(()),
+        Ok(false) => {
+            let res = format!("{}: {:?}", NOT_ADMIN_ERROR, signer);
+            println!("{}", res);
+            Err(HttpResponse::Forbidden().body(res))
+        }
+        Err(e) => {
+            let res = format!("{}: {}", ROLES_ERROR, e);
+            println!("{}", res);
+            Err(HttpResponse::InternalServerError().body(res))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_redis::TestRedis;
+
+    #[tokio::test]
+    async fn roles_change_role() {
+        let mut redis = TestRedis::start();
+        let ntw = Network::Testnet;
+        let address = Address::from_low_u64_be(42);
+
+        assert!(roles(&mut *redis, address, ntw).unwrap().is_empty());
+
+        change_role(&mut *redis, RoleChange::Grant, Role::Starter, address, ntw).unwrap();
+        change_role(&mut *redis, RoleChange::Grant, Role::Admin, address, ntw).unwrap();
+        assert_eq!(
+            roles(&mut *redis, address, ntw).unwrap(),
+            vec![Role::Admin, Role::Starter]
+        );
+        assert!(roles(&mut *redis, address, Network::Mainnet)
+            .unwrap()
+            .is_empty());
+
+        // The only admin and starter of the network stay
+        assert!(change_role(&mut *redis, RoleChange::Revoke, Role::Admin, address, ntw).is_err());
+        assert!(change_role(&mut *redis, RoleChange::Grant, Role::Voter, address, ntw).is_err());
+
+        let bootstrap = authorized_voters()[0];
+        assert_eq!(
+            roles(&mut *redis, bootstrap, ntw).unwrap(),
+            vec![Role::Admin, Role::Starter]
+        );
+    }
+
+    #[tokio::test]
+    async fn roles_may_change_starters() {
+        let mut redis = TestRedis::start();
+        let ntw = Network::Testnet;
+        let starter = Address::from_low_u64_be(42);
+        let admin = Address::from_low_u64_be(43);
+
+        redis.register_voter_starter(starter, ntw).unwrap();
+        redis.register_admin(admin, ntw).unwrap();
+
+        for (signer, change, allowed) in [
+            (starter, RoleChange::Grant, true),
+            (starter, RoleChange::Revoke, false),
+            (admin, RoleChange::Grant, true),
+            (admin, RoleChange::Revoke, true),
+            (authorized_voters()[0], RoleChange::Revoke, true),
+            (Address::zero(), RoleChange::Grant, false),
+        ] {
+            assert_eq!(
+                may_change_starters(&mut *redis, signer, change, ntw).unwrap(),
+                allowed,
+                "{:?} {:?}",
+                signer,
+                change
+            );
+        }
+    }
+}
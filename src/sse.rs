@@ -0,0 +1,209 @@
+//! Server-Sent Events stream of a vote in progress
+//!
+//! Sends the seconds left every tick and the tallies whenever they change, so
+//! the frontend doesn't have to poll `/filecoin/vote` while a vote is running
+use std::time::Duration;
+
+use actix_web::{http::header::ContentEncoding, web, web::Bytes, HttpResponse, Responder};
+use futures_util::stream;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{
+    errors::*,
+    fip::FipNumber,
+    redis::VoteStatus,
+    storage::Network,
+    store::{StoreError, StoreFactory, VoteStore},
+    Args, NtwFipParams,
+};
+
+/// Time between two countdown events
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// State of one client's stream, polled once every tick
+struct VoteStream<S> {
+    redis: S,
+    fip_number: FipNumber,
+    ntw: Network,
+    vote_length: u64,
+    /// Tallies last sent, so unchanged tallies aren't sent again
+    last_tally: Option<String>,
+    started: bool,
+    finished: bool,
+}
+
+/// Registers the vote stream endpoint, reading from the stores `F` opens
+pub fn routes<F: StoreFactory>(cfg: &mut web::ServiceConfig) {
+    cfg.route("/filecoin/vote/stream", web::get().to(get_vote_stream::<F>));
+}
+
+/// Streams the countdown and tallies of a vote until it concludes
+///
+/// Every tick sends a `countdown` event with the seconds left and a `tally`
+/// event when the results changed since the last one. Votes scheduled to open
+/// later send a `scheduled` event with the seconds until they open instead.
+/// The stream ends with a `concluded` event holding the final results, or
+/// `cancelled`
+#[utoipa::path(
+    get,
+    path = "/filecoin/vote/stream",
+    tag = "votes",
+    params(NtwFipParams),
+    responses(
+        (status = 200, description = "text/event-stream of countdown, tally, concluded and cancelled events", body = String),
+        (status = 400, description = "Invalid network or request", body = String),
+        (status = 404, description = "The vote does not exist"),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_vote_stream<F: StoreFactory>(
+    query_params: web::Query<NtwFipParams>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let ntw = query_params.network;
+    let num = query_params.fip_number;
+
+    // The connection is kept for as long as the client listens
+    let mut redis = match store.reader() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    match redis.vote_status(num, config.vote_length(), ntw) {
+        Ok(VoteStatus::DoesNotExist) => return HttpResponse::NotFound().finish(),
+        Ok(_) => (),
+        Err(e) => {
+            let res = format!("{}: {}", VOTE_STATUS_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    }
+
+    let state = VoteStream {
+        redis,
+        fip_number: num,
+        ntw,
+        vote_length: config.vote_length(),
+        last_tally: None,
+        started: false,
+        finished: false,
+    };
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        // Compressing would hold events back until enough of them are buffered
+        .insert_header(ContentEncoding::Identity)
+        .streaming(stream::unfold(state, |mut state| async move {
+            if state.finished {
+                return None;
+            }
+            if state.started {
+                tokio::time::sleep(TICK_INTERVAL).await;
+            }
+            state.started = true;
+
+            let events = state.tick().await;
+            Some((Ok::<_, std::io::Error>(Bytes::from(events)), state))
+        }))
+}
+
+impl<S: VoteStore> VoteStream<S> {
+    /// The events of one tick, marking the stream finished once the vote is over
+    async fn tick(&mut self) -> String {
+        let status = match self
+            .redis
+            .vote_status(self.fip_number, self.vote_length, self.ntw)
+        {
+            Ok(status) => status,
+            Err(e) => return self.fail(VOTE_STATUS_ERROR, e),
+        };
+
+        let time_left = match status {
+            VoteStatus::InProgress(time_left) => time_left,
+            // Nothing to tally until the vote opens
+            VoteStatus::Scheduled(opens_in) => {
+                return event(
+                    "scheduled",
+                    &json!({ "fip_number": self.fip_number, "opens_in": opens_in }),
+                );
+            }
+            VoteStatus::Concluded => {
+                self.finished = true;
+                return match self.results().await {
+                    Ok(results) => event("concluded", &results),
+                    Err(e) => self.fail(VOTE_RESULTS_ERROR, e),
+                };
+            }
+            VoteStatus::Cancelled | VoteStatus::DoesNotExist => {
+                self.finished = true;
+                return event("cancelled", &json!({ "fip_number": self.fip_number }));
+            }
+            VoteStatus::Unknown => return self.fail(VOTE_STATUS_ERROR, VOTE_STATUS_UNKNOWN),
+        };
+
+        let mut events = event(
+            "countdown",
+            &json!({ "fip_number": self.fip_number, "time_left": time_left }),
+        );
+
+        let results = match self.results().await {
+            Ok(results) => results,
+            Err(e) => return self.fail(VOTE_RESULTS_ERROR, e),
+        };
+        let tally = results.to_string();
+        if self.last_tally.as_ref() != Some(&tally) {
+            events.push_str(&event("tally", &results));
+            self.last_tally = Some(tally);
+        }
+
+        events
+    }
+
+    async fn results(&mut self) -> Result<serde_json::Value, StoreError> {
+        let results = self
+            .redis
+            .vote_results(self.fip_number, self.vote_length, self.ntw)
+            .await?;
+
+        Ok(serde_json::to_value(results).unwrap_or_default())
+    }
+
+    /// Ends the stream with an `error` event
+    fn fail(&mut self, message: &str, e: impl std::fmt::Display) -> String {
+        let res = format!("{}: {}", message, e);
+        println!("{}", res);
+
+        self.finished = true;
+        event("error", &res)
+    }
+}
+
+/// Formats a single event, the json data always fits on one line
+fn event(name: &str, data: &impl Serialize) -> String {
+    let data = serde_json::to_string(data).unwrap_or_default();
+
+    format!("event: {}\ndata: {}\n\n", name, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sse_event() {
+        let res = event("countdown", &json!({ "fip_number": 5, "time_left": 42 }));
+
+        assert_eq!(
+            res,
+            "event: countdown\ndata: {\"fip_number\":5,\"time_left\":42}\n\n"
+        );
+        assert_eq!(event("error", &"a\nb"), "event: error\ndata: \"a\\nb\"\n\n");
+    }
+}
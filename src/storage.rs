@@ -1,16 +1,98 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use clap::ValueEnum;
+use ethers::types::Address;
 use jsonrpc::Response;
-use redis::{FromRedisValue, ToRedisArgs};
+use redis::{Commands, FromRedisValue, ToRedisArgs};
 use reqwest::Client;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::{json, Value};
 use thiserror::Error;
+use tokio::task::JoinSet;
+use url::Url;
+use utoipa::ToSchema;
+
+use crate::clock::Clock;
 
 const MAINNET_RPC: &str = "https://api.chain.love/rpc/v0";
 const TESTNET_RPC: &str = "https://filecoin-calibration.chainup.net/rpc/v1";
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+const PIB: u128 = 1 << 50;
+
+/// Most power lookups kept in flight at once for a single batch
+const MAX_CONCURRENT_LOOKUPS: usize = 8;
+
+/// Most networks that can be added on top of mainnet and calibration
+const MAX_CONFIGURED_NETWORKS: usize = u8::MAX as usize + 1;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Network {
     Mainnet,
     Testnet,
+    /// A network from the networks file, by its position in the registry
+    Configured(u8),
+}
+
+/// A network such as butterfly or a local lotus devnet, read from the networks file
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// Name used in query parameters and redis keys
+    pub name: String,
+    /// Lotus JSON-RPC endpoint of the network
+    pub rpc: Url,
+    /// `f` or `t`, the prefix of addresses on the network
+    pub prefix: char,
+    /// Chain id typed messages are signed for
+    pub chain_id: u64,
+}
+
+/// Networks added on top of mainnet and calibration, never removed so
+/// `Network::Configured` stays valid for the life of the process
+static NETWORKS: RwLock<Vec<&'static NetworkConfig>> = RwLock::new(Vec::new());
+
+/// Where fetched storage power is cached between chain lookups
+#[derive(Copy, Clone, Debug, PartialEq, ValueEnum)]
+pub enum PowerCacheBackend {
+    /// Per process, lost on restart
+    Memory,
+    /// Shared by every server using the same database
+    Redis,
+}
+
+/// Storage power and worker keys fetched from the chain, kept until they are `ttl` old
+struct PowerCache {
+    ttl: Duration,
+    /// The shared cache, with the clock the power kept in it is aged by
+    redis: Option<(redis::Client, Arc<dyn Clock>)>,
+    entries: BTreeMap<(Network, u32), (u128, Instant)>,
+    workers: BTreeMap<(Network, u32), (String, Instant)>,
+}
+
+static POWER_CACHE: Mutex<PowerCache> = Mutex::new(PowerCache {
+    ttl: Duration::ZERO,
+    redis: None,
+    entries: BTreeMap::new(),
+    workers: BTreeMap::new(),
+});
+
+/// Storage providers grouped by their raw byte power
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+pub enum SpCohort {
+    /// Less than 1 PiB
+    Small,
+    /// Between 1 PiB and 10 PiB
+    Medium,
+    /// 10 PiB or more
+    Large,
 }
 
 #[derive(Debug, Error)]
@@ -21,6 +103,10 @@ pub enum StorageFetchError {
     Serde(#[from] serde_json::Error),
     #[error("no result")]
     NoResult,
+    #[error("lookup task failed")]
+    Join(#[from] tokio::task::JoinError),
+    #[error("no tipset at height {0}")]
+    NoTipset(u64),
 }
 
 pub async fn verify_id(
@@ -28,20 +114,238 @@ pub async fn verify_id(
     worker_address: String,
     ntw: Network,
 ) -> Result<bool, StorageFetchError> {
-    let client = Client::new();
+    let worker = lookup_miner_key(id, "Worker", ntw).await?;
+
+    Ok(worker.as_deref() == Some(worker_address.as_str()))
+}
+
+/// Checks that the address is the account key of the storage provider's
+/// owner, for storage providers that can't sign with their worker key
+pub async fn verify_owner(
+    id: String,
+    owner_address: String,
+    ntw: Network,
+) -> Result<bool, StorageFetchError> {
+    let owner = lookup_miner_key(id, "Owner", ntw).await?;
+
+    Ok(owner.as_deref() == Some(owner_address.as_str()))
+}
+
+/// The account key the storage provider's owner uses now, `None` if it has
+/// none such as when the owner is a multisig
+///
+/// Not cached, only registrations signed by the owner look it up
+pub async fn fetch_owner_address(
+    sp_id: u32,
+    ntw: Network,
+) -> Result<Option<String>, StorageFetchError> {
+    lookup_miner_key(sp_id_format(ntw, sp_id), "Owner", ntw).await
+}
+
+/// The account key the storage provider's worker uses now, `None` if it has none
+///
+/// Cached for as long as storage power so ballots don't look it up every time
+pub async fn fetch_worker_address(
+    sp_id: u32,
+    ntw: Network,
+) -> Result<Option<String>, StorageFetchError> {
+    let cached = POWER_CACHE
+        .lock()
+        .unwrap()
+        .get_worker(sp_id, ntw, Instant::now());
+    if let Some(worker) = cached {
+        return Ok(Some(worker));
+    }
+
+    let worker = lookup_miner_key(sp_id_format(ntw, sp_id), "Worker", ntw).await?;
+
+    if let Some(worker) = &worker {
+        POWER_CACHE
+            .lock()
+            .unwrap()
+            .insert_worker(sp_id, ntw, worker.clone(), Instant::now());
+    }
+
+    Ok(worker)
+}
+
+/// Resolves the `Worker` or `Owner` of the storage provider to its account key
+async fn lookup_miner_key(
+    id: String,
+    field: &str,
+    ntw: Network,
+) -> Result<Option<String>, StorageFetchError> {
+    let key_id = match rpc_request(ntw, "Filecoin.StateMinerInfo", json!([id, null])).await? {
+        Some(info) => match info[field].as_str() {
+            Some(key_id) => key_id.to_string(),
+            None => return Ok(None),
+        },
+        None => return Ok(None),
+    };
+
+    match rpc_request(ntw, "Filecoin.StateAccountKey", json!([key_id, null])).await? {
+        Some(key) => Ok(key.as_str().map(|key| key.to_string())),
+        None => Ok(None),
+    }
+}
 
-    let rpc = ntw.rpc();
+/// The signers and approval threshold of a multisig actor
+#[derive(Debug)]
+pub struct MsigState {
+    /// ID addresses of the signers
+    pub signers: Vec<String>,
+    pub threshold: u64,
+}
+
+/// Checks that the storage provider is owned by the multisig actor
+pub async fn verify_msig_owner(
+    id: String,
+    msig_address: String,
+    ntw: Network,
+) -> Result<bool, StorageFetchError> {
+    let owner_id = match rpc_request(ntw, "Filecoin.StateMinerInfo", json!([id, null])).await? {
+        Some(info) => match info["Owner"].as_str() {
+            Some(owner_id) => owner_id.to_string(),
+            None => return Ok(false),
+        },
+        None => return Ok(false),
+    };
+
+    let msig_id = lookup_id(msig_address, ntw).await?;
+
+    Ok(owner_id == msig_id)
+}
+
+/// Resolves an address to its ID address
+pub async fn lookup_id(address: String, ntw: Network) -> Result<String, StorageFetchError> {
+    match rpc_request(ntw, "Filecoin.StateLookupID", json!([address, null])).await? {
+        Some(id) => match id.as_str() {
+            Some(id) => Ok(id.to_string()),
+            None => Err(StorageFetchError::NoResult),
+        },
+        None => Err(StorageFetchError::NoResult),
+    }
+}
+
+pub async fn fetch_msig_state(
+    msig_address: String,
+    ntw: Network,
+) -> Result<MsigState, StorageFetchError> {
+    let actor =
+        match rpc_request(ntw, "Filecoin.StateReadState", json!([msig_address, null])).await? {
+            Some(actor) => actor,
+            None => return Err(StorageFetchError::NoResult),
+        };
+
+    let signers = match actor["State"]["Signers"].as_array() {
+        Some(signers) => signers
+            .iter()
+            .filter_map(|s| s.as_str().map(|s| s.to_string()))
+            .collect::<Vec<String>>(),
+        None => return Err(StorageFetchError::NoResult),
+    };
+
+    let threshold = match actor["State"]["NumApprovalsThreshold"].as_u64() {
+        Some(threshold) => threshold,
+        None => return Err(StorageFetchError::NoResult),
+    };
+
+    Ok(MsigState { signers, threshold })
+}
+
+/// Fetches the FIL balance of an Ethereum address in attoFIL
+///
+/// The balance is read from the f410 actor the address maps to, an address
+/// that never received FIL has no actor and a balance of 0
+pub async fn fetch_fil_balance(address: Address, ntw: Network) -> Result<u128, StorageFetchError> {
+    let delegated = delegated_address(ntw, address);
+
+    match rpc_request(ntw, "Filecoin.WalletBalance", json!([delegated])).await? {
+        Some(balance) => match balance.as_str().and_then(|b| b.parse::<u128>().ok()) {
+            Some(balance) => Ok(balance),
+            None => Err(StorageFetchError::NoResult),
+        },
+        None => Err(StorageFetchError::NoResult),
+    }
+}
+
+/// Fetches the height of the head of the chain, to check that the node answers
+pub async fn fetch_chain_height(ntw: Network) -> Result<u64, StorageFetchError> {
+    match rpc_request(ntw, "Filecoin.ChainHead", json!([])).await? {
+        Some(head) => head["Height"].as_u64().ok_or(StorageFetchError::NoResult),
+        None => Err(StorageFetchError::NoResult),
+    }
+}
+
+/// Parses an Ethereum address given either as 0x prefixed hex or as the f410
+/// delegated address it maps to on `ntw`, such as `f410f2oekwcmo2pueydmaq53eic2i62crtbeyuzx2gmy`
+pub fn parse_address(ntw: Network, address: &str) -> Result<Address, AddressError> {
+    if let Ok(parsed) = Address::from_str(address) {
+        return Ok(parsed);
+    }
+
+    let delegated = address.to_ascii_lowercase();
+    let payload = match delegated
+        .get(1..)
+        .and_then(|rest| rest.strip_prefix("410f"))
+    {
+        Some(payload) => payload,
+        None => return Err(AddressError::Invalid(address.to_string())),
+    };
+
+    let bytes = base32::decode(
+        base32::Alphabet::RFC4648 { padding: false },
+        &payload.to_ascii_uppercase(),
+    );
+    let parsed = match bytes {
+        Some(bytes) if bytes.len() == 24 => Address::from_slice(&bytes[..20]),
+        _ => return Err(AddressError::Invalid(address.to_string())),
+    };
+
+    // Formatting it again checks the checksum and the network together
+    if delegated_address(ntw, parsed) == delegated {
+        return Ok(parsed);
+    }
+    match delegated.chars().next() {
+        Some(prefix @ ('f' | 't')) if prefix != ntw.prefix() => {
+            Err(AddressError::WrongNetwork(address.to_string()))
+        }
+        _ => Err(AddressError::Invalid(address.to_string())),
+    }
+}
+
+/// Formats the f410 delegated address of an Ethereum address
+pub fn delegated_address(ntw: Network, address: Address) -> String {
+    // The checksum covers the protocol, the EAM namespace and the payload
+    let checksum = blake2b_simd::Params::new()
+        .hash_length(4)
+        .hash(&[&[4u8, 10u8][..], address.as_bytes()].concat())
+        .as_bytes()
+        .to_vec();
+
+    let encoded = base32::encode(
+        base32::Alphabet::RFC4648 { padding: false },
+        &[address.as_bytes(), checksum.as_slice()].concat(),
+    );
+
+    format!("{}410f{}", ntw.prefix(), encoded.to_lowercase())
+}
+
+/// Sends a single JSON-RPC request to the network and returns the parsed result
+async fn rpc_request(
+    ntw: Network,
+    method: &str,
+    params: Value,
+) -> Result<Option<Value>, StorageFetchError> {
+    let client = Client::new();
 
     let response = client
-        .post(rpc)
+        .post(ntw.rpc())
         .header("Content-Type", "application/json")
         .json(&json!({
             "jsonrpc": "2.0",
-            "method": "Filecoin.StateMinerInfo",
-            "params": [
-                id,
-                null
-            ],
+            "method": method,
+            "params": params,
             "id": 1
         }))
         .send()
@@ -49,27 +353,233 @@ pub async fn verify_id(
         .json::<Response>()
         .await?;
 
-    let worker_id = match response.result {
-        Some(w) => {
-            let parsed_result: Value = serde_json::from_str(w.to_string().as_str())?;
+    match response.result {
+        Some(result) => Ok(Some(serde_json::from_str(result.to_string().as_str())?)),
+        None => Ok(None),
+    }
+}
 
-            if let Some(worker_id) = parsed_result["Worker"].as_str() {
-                worker_id.to_string()
-            } else {
-                return Ok(false);
+/// Storage power fixtures keyed by network and storage provider id
+#[cfg(feature = "e2e")]
+static POWER_FIXTURES: RwLock<BTreeMap<(Network, u32), u128>> = RwLock::new(BTreeMap::new());
+
+/// Overrides the chain power returned for a storage provider
+#[cfg(feature = "e2e")]
+pub fn set_power_fixture(sp_id: u32, ntw: Network, power: u128) {
+    POWER_FIXTURES.write().unwrap().insert((ntw, sp_id), power);
+}
+
+/// Removes every storage power fixture
+#[cfg(feature = "e2e")]
+pub fn clear_power_fixtures() {
+    POWER_FIXTURES.write().unwrap().clear();
+}
+
+/// Caches storage power for `ttl` seconds, a ttl of 0 turns caching off
+///
+/// The redis backend shares the cache between servers using the same database,
+/// and ages the power kept in it by `clock`
+pub fn configure_power_cache(
+    ttl: u64,
+    backend: PowerCacheBackend,
+    redis_path: Url,
+    clock: Arc<dyn Clock>,
+) -> Result<(), redis::RedisError> {
+    let redis = match backend {
+        PowerCacheBackend::Memory => None,
+        PowerCacheBackend::Redis => Some((redis::Client::open(redis_path)?, clock)),
+    };
+
+    let mut cache = POWER_CACHE.lock().unwrap();
+    cache.ttl = Duration::from_secs(ttl);
+    cache.redis = redis;
+    cache.entries.clear();
+    cache.workers.clear();
+
+    Ok(())
+}
+
+pub async fn fetch_storage_amount(sp_id: u32, ntw: Network) -> Result<u128, StorageFetchError> {
+    #[cfg(feature = "e2e")]
+    {
+        let fixture = POWER_FIXTURES.read().unwrap().get(&(ntw, sp_id)).copied();
+        if let Some(power) = fixture {
+            return Ok(power);
+        }
+    }
+
+    let cached = POWER_CACHE.lock().unwrap().get(sp_id, ntw, Instant::now());
+    if let Some(power) = cached {
+        return Ok(power);
+    }
+
+    refresh_storage_amount(sp_id, ntw).await
+}
+
+/// Fetches the power from the chain even when it is cached, and caches it
+/// in place of the old power
+pub async fn refresh_storage_amount(sp_id: u32, ntw: Network) -> Result<u128, StorageFetchError> {
+    #[cfg(feature = "e2e")]
+    {
+        let fixture = POWER_FIXTURES.read().unwrap().get(&(ntw, sp_id)).copied();
+        if let Some(power) = fixture {
+            return Ok(power);
+        }
+    }
+
+    let power = fetch_miner_power(sp_id, ntw, Value::Null).await?;
+
+    POWER_CACHE
+        .lock()
+        .unwrap()
+        .insert(sp_id, ntw, power, Instant::now());
+
+    Ok(power)
+}
+
+/// Where the storage power ballots are weighted by comes from
+///
+/// The database reads power through this so tests can stub it instead of
+/// depending on what storage providers hold on the chain
+#[async_trait]
+pub trait StoragePowerProvider: Send + Sync {
+    /// Power of every storage provider, keyed by id
+    async fn storage_amounts(
+        &self,
+        sp_ids: &[u32],
+        ntw: Network,
+    ) -> Result<BTreeMap<u32, u128>, StorageFetchError>;
+
+    /// Power of every storage provider without reusing cached power
+    async fn refresh_storage_amounts(
+        &self,
+        sp_ids: &[u32],
+        ntw: Network,
+    ) -> Result<BTreeMap<u32, u128>, StorageFetchError> {
+        self.storage_amounts(sp_ids, ntw).await
+    }
+}
+
+/// Reads power from the chain, through the power cache
+pub struct ChainPower;
+
+#[async_trait]
+impl StoragePowerProvider for ChainPower {
+    async fn storage_amounts(
+        &self,
+        sp_ids: &[u32],
+        ntw: Network,
+    ) -> Result<BTreeMap<u32, u128>, StorageFetchError> {
+        fetch_storage_amounts(sp_ids, ntw).await
+    }
+
+    async fn refresh_storage_amounts(
+        &self,
+        sp_ids: &[u32],
+        ntw: Network,
+    ) -> Result<BTreeMap<u32, u128>, StorageFetchError> {
+        fetch_powers(sp_ids, ntw, PowerLookup::Refresh).await
+    }
+}
+
+/// Which power of the storage providers is fetched
+#[derive(Clone)]
+enum PowerLookup {
+    /// At the head, reusing cached power
+    Cached,
+    /// At the head, straight from the chain
+    Refresh,
+    /// At the tipset with this key
+    Tipset(Value),
+}
+
+/// Fetches the power of every storage provider concurrently
+///
+/// Duplicate ids are only looked up once and at most `MAX_CONCURRENT_LOOKUPS`
+/// requests are in flight at a time
+pub async fn fetch_storage_amounts(
+    sp_ids: &[u32],
+    ntw: Network,
+) -> Result<BTreeMap<u32, u128>, StorageFetchError> {
+    fetch_powers(sp_ids, ntw, PowerLookup::Cached).await
+}
+
+/// Fetches the power every storage provider had at the tipset at `height`
+///
+/// Snapshots are read straight from the chain since the cache only holds
+/// the power at the head
+pub async fn fetch_storage_amounts_at(
+    sp_ids: &[u32],
+    ntw: Network,
+    height: u64,
+) -> Result<BTreeMap<u32, u128>, StorageFetchError> {
+    let tipset = tipset_key(height, ntw).await?;
+    fetch_powers(sp_ids, ntw, PowerLookup::Tipset(tipset)).await
+}
+
+/// Resolves the key of the tipset at `height`, null rounds resolve to the tipset before them
+async fn tipset_key(height: u64, ntw: Network) -> Result<Value, StorageFetchError> {
+    let tipset = rpc_request(
+        ntw,
+        "Filecoin.ChainGetTipSetByHeight",
+        json!([height, null]),
+    )
+    .await?
+    .ok_or(StorageFetchError::NoTipset(height))?;
+
+    match &tipset["Cids"] {
+        Value::Array(cids) if !cids.is_empty() => Ok(tipset["Cids"].clone()),
+        _ => Err(StorageFetchError::NoTipset(height)),
+    }
+}
+
+/// Fetches every power concurrently
+async fn fetch_powers(
+    sp_ids: &[u32],
+    ntw: Network,
+    lookup: PowerLookup,
+) -> Result<BTreeMap<u32, u128>, StorageFetchError> {
+    let mut powers = BTreeMap::new();
+    let mut tasks = JoinSet::new();
+
+    for sp_id in sp_ids.iter().copied().collect::<BTreeSet<u32>>() {
+        if tasks.len() >= MAX_CONCURRENT_LOOKUPS {
+            if let Some(res) = tasks.join_next().await {
+                let (sp_id, power) = res?;
+                powers.insert(sp_id, power?);
             }
         }
-        None => return Ok(false),
-    };
 
+        let lookup = lookup.clone();
+        tasks.spawn(async move {
+            let power = match lookup {
+                PowerLookup::Cached => fetch_storage_amount(sp_id, ntw).await,
+                PowerLookup::Refresh => refresh_storage_amount(sp_id, ntw).await,
+                PowerLookup::Tipset(tipset) => fetch_miner_power(sp_id, ntw, tipset).await,
+            };
+            (sp_id, power)
+        });
+    }
+
+    while let Some(res) = tasks.join_next().await {
+        let (sp_id, power) = res?;
+        powers.insert(sp_id, power?);
+    }
+
+    Ok(powers)
+}
+
+/// Fetches the raw byte power of the whole network at the head
+pub async fn fetch_network_power(ntw: Network) -> Result<u128, StorageFetchError> {
+    let client = Client::new();
     let response = client
-        .post(rpc)
+        .post(ntw.rpc())
         .header("Content-Type", "application/json")
         .json(&json!({
             "jsonrpc": "2.0",
-            "method": "Filecoin.StateAccountKey",
+            "method": "Filecoin.StateMinerPower",
             "params": [
-                worker_id,
+                null,
                 null
             ],
             "id": 1
@@ -80,35 +590,37 @@ pub async fn verify_id(
         .await?;
 
     match response.result {
-        Some(w) => {
-            let parsed_result: Value = serde_json::from_str(w.to_string().as_str())?;
+        Some(result) => {
+            let parsed_result: Value = serde_json::from_str(result.to_string().as_str())?;
 
-            if let Some(rec_worker_address) = parsed_result.as_str() {
-                Ok(rec_worker_address == worker_address)
-            } else {
-                Ok(false)
+            match parsed_result["TotalPower"]["RawBytePower"].as_str() {
+                Some(power) => power.parse().map_err(|_| StorageFetchError::NoResult),
+                None => Err(StorageFetchError::NoResult),
             }
         }
-        None => Ok(false),
+        None => Err(StorageFetchError::NoResult),
     }
 }
 
-pub async fn fetch_storage_amount(sp_id: u32, ntw: Network) -> Result<u128, StorageFetchError> {
+/// Fetches the raw byte power of the storage provider from the chain
+///
+/// `tipset` is a tipset key, or null for the head
+async fn fetch_miner_power(
+    sp_id: u32,
+    ntw: Network,
+    tipset: Value,
+) -> Result<u128, StorageFetchError> {
     let client = Client::new();
-    let rpc = match ntw {
-        Network::Mainnet => MAINNET_RPC,
-        Network::Testnet => TESTNET_RPC,
-    };
     let sp_id = sp_id_format(ntw, sp_id);
     let response = client
-        .post(rpc)
+        .post(ntw.rpc())
         .header("Content-Type", "application/json")
         .json(&json!({
             "jsonrpc": "2.0",
             "method": "Filecoin.StateMinerPower",
             "params": [
                 sp_id,
-                null
+                tipset
             ],
             "id": 1
         }))
@@ -131,18 +643,311 @@ pub async fn fetch_storage_amount(sp_id: u32, ntw: Network) -> Result<u128, Stor
     }
 }
 
-fn sp_id_format(ntw: Network, id: u32) -> String {
-    match ntw {
-        Network::Mainnet => format!("f0{}", id),
-        Network::Testnet => format!("t0{}", id),
+impl PowerCache {
+    fn get(&mut self, sp_id: u32, ntw: Network, now: Instant) -> Option<u128> {
+        if self.ttl.is_zero() {
+            return None;
+        }
+
+        if let Some((client, clock)) = self.redis.as_ref() {
+            // A cache that can't be reached falls back to the chain
+            let mut con = client.get_connection().ok()?;
+            let cached = con
+                .get::<String, Option<String>>(redis_key(sp_id, ntw))
+                .ok()??;
+            // Power cached before the fetch time was kept is fetched again
+            let cached: CachedPower = serde_json::from_str(&cached).ok()?;
+            let age = clock.now().saturating_sub(cached.fetched_at);
+            return (age < self.ttl.as_secs()).then_some(cached.power);
+        }
+
+        match self.entries.get(&(ntw, sp_id)) {
+            Some((power, fetched)) if now.duration_since(*fetched) < self.ttl => Some(*power),
+            _ => None,
+        }
+    }
+
+    fn insert(&mut self, sp_id: u32, ntw: Network, power: u128, now: Instant) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        if let Some((client, clock)) = self.redis.as_ref() {
+            let cached = CachedPower {
+                power,
+                fetched_at: clock.now(),
+            };
+            if let Ok(mut con) = client.get_connection() {
+                let _ = con.set_ex::<String, String, ()>(
+                    redis_key(sp_id, ntw),
+                    serde_json::to_string(&cached).unwrap(),
+                    self.ttl.as_secs() as usize,
+                );
+            }
+            return;
+        }
+
+        self.entries
+            .retain(|_, (_, fetched)| now.duration_since(*fetched) < self.ttl);
+        self.entries.insert((ntw, sp_id), (power, now));
+    }
+
+    fn get_worker(&mut self, sp_id: u32, ntw: Network, now: Instant) -> Option<String> {
+        if self.ttl.is_zero() {
+            return None;
+        }
+
+        if let Some((client, _)) = self.redis.as_ref() {
+            let mut con = client.get_connection().ok()?;
+            return con
+                .get::<String, Option<String>>(worker_redis_key(sp_id, ntw))
+                .ok()?;
+        }
+
+        match self.workers.get(&(ntw, sp_id)) {
+            Some((worker, fetched)) if now.duration_since(*fetched) < self.ttl => {
+                Some(worker.clone())
+            }
+            _ => None,
+        }
+    }
+
+    fn insert_worker(&mut self, sp_id: u32, ntw: Network, worker: String, now: Instant) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        if let Some((client, _)) = self.redis.as_ref() {
+            if let Ok(mut con) = client.get_connection() {
+                let _ = con.set_ex::<String, String, ()>(
+                    worker_redis_key(sp_id, ntw),
+                    worker,
+                    self.ttl.as_secs() as usize,
+                );
+            }
+            return;
+        }
+
+        self.workers
+            .retain(|_, (_, fetched)| now.duration_since(*fetched) < self.ttl);
+        self.workers.insert((ntw, sp_id), (worker, now));
     }
 }
 
+/// Storage power kept in the redis cache with the time it was read from the chain
+#[derive(Serialize, Deserialize)]
+struct CachedPower {
+    power: u128,
+    fetched_at: u64,
+}
+
+/// Shares the namespace of the vote keys so every key of the crate is found under `fipvote:`
+fn redis_key(sp_id: u32, ntw: Network) -> String {
+    format!("fipvote:power_cache:{}", sp_id_format(ntw, sp_id))
+}
+
+fn worker_redis_key(sp_id: u32, ntw: Network) -> String {
+    format!("fipvote:worker_cache:{}", sp_id_format(ntw, sp_id))
+}
+
+pub fn sp_id_format(ntw: Network, id: u32) -> String {
+    format!("{}0{}", ntw.prefix(), id)
+}
+
 impl Network {
+    /// Mainnet, calibration and every configured network
+    pub fn all() -> Vec<Network> {
+        let configured = NETWORKS.read().unwrap().len();
+
+        [Network::Mainnet, Network::Testnet]
+            .into_iter()
+            .chain((0..configured).map(|i| Network::Configured(i as u8)))
+            .collect()
+    }
+
     pub fn rpc(&self) -> &'static str {
         match self {
             Network::Mainnet => MAINNET_RPC,
             Network::Testnet => TESTNET_RPC,
+            Network::Configured(_) => self.config().rpc.as_str(),
+        }
+    }
+
+    /// The name used for the network in query parameters
+    pub fn name(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet => "calibration",
+            Network::Configured(_) => self.config().name.as_str(),
+        }
+    }
+
+    /// Chain id of the network, which typed messages are signed for
+    pub fn chain_id(&self) -> u64 {
+        match self {
+            Network::Mainnet => 314,
+            Network::Testnet => 314159,
+            Network::Configured(_) => self.config().chain_id,
+        }
+    }
+
+    /// Prefix of the addresses on the network, `f` on mainnet and `t` on test networks
+    pub fn prefix(&self) -> char {
+        match self {
+            Network::Mainnet => 'f',
+            Network::Testnet => 't',
+            Network::Configured(_) => self.config().prefix,
+        }
+    }
+
+    /// The built in network addresses with `prefix` belong to, `None` for other prefixes
+    pub fn from_prefix(prefix: char) -> Option<Network> {
+        match prefix.to_ascii_lowercase() {
+            'f' => Some(Network::Mainnet),
+            't' => Some(Network::Testnet),
+            _ => None,
+        }
+    }
+
+    fn config(&self) -> &'static NetworkConfig {
+        match self {
+            Network::Configured(i) => NETWORKS.read().unwrap()[*i as usize],
+            _ => unreachable!("Mainnet and calibration are built in"),
+        }
+    }
+
+    /// Looks up a configured network by its name
+    fn configured(name: &str) -> Option<Network> {
+        NETWORKS
+            .read()
+            .unwrap()
+            .iter()
+            .position(|config| config.name == name)
+            .map(|i| Network::Configured(i as u8))
+    }
+}
+
+/// Adds a network to the registry so it can be named in requests
+///
+/// Registering the same network again returns the network already registered
+pub fn register_network(config: NetworkConfig) -> Result<Network, NetworkConfigError> {
+    let name_pattern = regex::Regex::new(r"^[a-z0-9][a-z0-9-]*$").unwrap();
+    if !name_pattern.is_match(&config.name) {
+        return Err(NetworkConfigError::InvalidName(config.name));
+    }
+    if !matches!(config.prefix, 'f' | 't') {
+        return Err(NetworkConfigError::InvalidPrefix(
+            config.name,
+            config.prefix,
+        ));
+    }
+
+    let mut networks = NETWORKS.write().unwrap();
+    if let Some(i) = networks.iter().position(|c| c.name == config.name) {
+        return match *networks[i] == config {
+            true => Ok(Network::Configured(i as u8)),
+            false => Err(NetworkConfigError::Duplicate(config.name)),
+        };
+    }
+    if matches!(
+        config.name.as_str(),
+        "mainnet" | "calibration" | "calibnet" | "testnet"
+    ) {
+        return Err(NetworkConfigError::Duplicate(config.name));
+    }
+    if networks.len() >= MAX_CONFIGURED_NETWORKS {
+        return Err(NetworkConfigError::TooMany);
+    }
+
+    // Leaked so names and endpoints can be handed out as `&'static str`
+    networks.push(Box::leak(Box::new(config)));
+
+    Ok(Network::Configured((networks.len() - 1) as u8))
+}
+
+/// Registers every network of a json file such as
+///
+/// ```json
+/// [{ "name": "butterfly", "rpc": "https://api.butterfly.fildev.network/rpc/v1", "prefix": "t", "chain_id": 3141592 }]
+/// ```
+pub fn load_networks(path: &Path) -> Result<Vec<Network>, NetworkConfigError> {
+    let file =
+        fs::read_to_string(path).map_err(|e| NetworkConfigError::Io(path.to_path_buf(), e))?;
+    let configs: Vec<NetworkConfig> = serde_json::from_str(&file)
+        .map_err(|e| NetworkConfigError::Parse(path.to_path_buf(), e))?;
+
+    configs.into_iter().map(register_network).collect()
+}
+
+#[derive(Debug, Error, PartialEq)]
+#[error("Unknown network {0}, expected mainnet, calibration or a configured network")]
+pub struct NetworkError(String);
+
+#[derive(Debug, Error)]
+pub enum NetworkConfigError {
+    #[error("Error reading {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("Error parsing {0}: {1}")]
+    Parse(PathBuf, serde_json::Error),
+    #[error("Network name {0} must be lowercase letters, digits and dashes")]
+    InvalidName(String),
+    #[error("Network {0} has address prefix {1}, expected f or t")]
+    InvalidPrefix(String, char),
+    #[error("Network {0} is already defined")]
+    Duplicate(String),
+    #[error("Too many networks configured")]
+    TooMany,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum AddressError {
+    #[error("{0} is neither a 0x nor an f410 address")]
+    Invalid(String),
+    #[error("{0} is an address on the other network")]
+    WrongNetwork(String),
+}
+
+/// Parses the name of a network in any case, along with the `calibnet` and
+/// `testnet` aliases Lotus and Glif use for calibration
+impl FromStr for Network {
+    type Err = NetworkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "mainnet" => Ok(Network::Mainnet),
+            "calibration" | "calibnet" | "testnet" => Ok(Network::Testnet),
+            name => Network::configured(name).ok_or_else(|| NetworkError(s.to_string())),
+        }
+    }
+}
+
+/// Networks are written by their query parameter name
+impl Serialize for Network {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.name())
+    }
+}
+
+/// Accepts the aliases of [`Network::from_str`] in query parameters and json bodies
+impl<'de> Deserialize<'de> for Network {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        Network::from_str(&name).map_err(de::Error::custom)
+    }
+}
+
+impl SpCohort {
+    pub fn from_power(power: u128) -> Self {
+        match power {
+            p if p < PIB => SpCohort::Small,
+            p if p < 10 * PIB => SpCohort::Medium,
+            _ => SpCohort::Large,
         }
     }
 }
@@ -155,6 +960,7 @@ impl ToRedisArgs for Network {
         match self {
             Network::Mainnet => "mainnet".write_redis_args(out),
             Network::Testnet => "testnet".write_redis_args(out),
+            Network::Configured(_) => self.name().write_redis_args(out),
         }
     }
 }
@@ -165,17 +971,19 @@ impl FromRedisValue for Network {
         match s.as_str() {
             "mainnet" => Ok(Network::Mainnet),
             "testnet" => Ok(Network::Testnet),
-            _ => Err(redis::RedisError::from((
-                redis::ErrorKind::TypeError,
-                "Unknown network",
-            ))),
+            name => Network::configured(name).ok_or_else(|| {
+                redis::RedisError::from((redis::ErrorKind::TypeError, "Unknown network"))
+            }),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::str::FromStr;
+
     use super::*;
+    use crate::{clock::MockClock, test_redis::TestRedis};
 
     #[tokio::test]
     async fn storage_fetch_storage_amount_mainnet() {
@@ -193,6 +1001,247 @@ mod tests {
         assert!(res.is_ok());
     }
 
+    #[test]
+    fn storage_delegated_address() {
+        let address = Address::from_str("0xd388ab098ed3e84c0d808776440b48f685198498").unwrap();
+
+        assert_eq!(
+            delegated_address(Network::Mainnet, address),
+            "f410f2oekwcmo2pueydmaq53eic2i62crtbeyuzx2gmy"
+        );
+        assert_eq!(
+            delegated_address(Network::Testnet, address),
+            "t410f2oekwcmo2pueydmaq53eic2i62crtbeyuzx2gmy"
+        );
+    }
+
+    #[test]
+    fn storage_parse_address() {
+        let address = Address::from_str("0xd388ab098ed3e84c0d808776440b48f685198498").unwrap();
+        let delegated = "f410f2oekwcmo2pueydmaq53eic2i62crtbeyuzx2gmy";
+
+        assert_eq!(
+            parse_address(
+                Network::Mainnet,
+                "0xd388ab098ed3e84c0d808776440b48f685198498"
+            ),
+            Ok(address)
+        );
+        assert_eq!(parse_address(Network::Mainnet, delegated), Ok(address));
+        assert_eq!(
+            parse_address(
+                Network::Testnet,
+                &delegated.replacen('f', "t", 1).to_uppercase()
+            ),
+            Ok(address)
+        );
+
+        assert_eq!(
+            parse_address(Network::Testnet, delegated),
+            Err(AddressError::WrongNetwork(delegated.to_string()))
+        );
+        for invalid in [
+            "f410f2oekwcmo2pueydmaq53eic2i62crtbeyuzx2gma",
+            "f410f2oekwcmo2pueydmaq53eic2i62crtbey",
+            "f01234",
+            "0xd388",
+            "",
+        ] {
+            assert_eq!(
+                parse_address(Network::Mainnet, invalid),
+                Err(AddressError::Invalid(invalid.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn storage_network_from_str() {
+        for name in ["mainnet", "Mainnet"] {
+            assert_eq!(Network::from_str(name), Ok(Network::Mainnet));
+        }
+        for name in ["calibration", "calibnet", "testnet", "CALIBNET"] {
+            assert_eq!(Network::from_str(name), Ok(Network::Testnet));
+        }
+        assert!(Network::from_str("devnet").is_err());
+
+        // Networks are still written by their query parameter name
+        let ntw: Network = serde_json::from_str("\"calibnet\"").unwrap();
+        assert_eq!(serde_json::to_string(&ntw).unwrap(), "\"calibration\"");
+        assert!(serde_json::from_str::<Network>("\"devnet\"").is_err());
+    }
+
+    #[test]
+    fn storage_register_network() {
+        let config = NetworkConfig {
+            name: "storage-test-devnet".to_string(),
+            rpc: Url::parse("http://127.0.0.1:1234/rpc/v1").unwrap(),
+            prefix: 't',
+            chain_id: 31415926,
+        };
+
+        let ntw = register_network(config.clone()).unwrap();
+
+        assert_eq!(register_network(config.clone()).unwrap(), ntw);
+        assert!(Network::all().contains(&ntw));
+        assert_eq!(Network::from_str("Storage-Test-Devnet"), Ok(ntw));
+        assert_eq!(ntw.rpc(), "http://127.0.0.1:1234/rpc/v1");
+        assert_eq!(ntw.chain_id(), 31415926);
+        assert_eq!(sp_id_format(ntw, 1000), "t01000");
+        assert_eq!(
+            serde_json::to_string(&ntw).unwrap(),
+            "\"storage-test-devnet\""
+        );
+
+        let changed = NetworkConfig {
+            chain_id: 1,
+            ..config.clone()
+        };
+        assert!(matches!(
+            register_network(changed),
+            Err(NetworkConfigError::Duplicate(_))
+        ));
+        for name in ["calibnet", "Devnet", "dev net", ""] {
+            let invalid = NetworkConfig {
+                name: name.to_string(),
+                ..config.clone()
+            };
+            assert!(register_network(invalid).is_err());
+        }
+        let invalid = NetworkConfig {
+            name: "storage-test-prefix".to_string(),
+            prefix: 'x',
+            ..config
+        };
+        assert!(matches!(
+            register_network(invalid),
+            Err(NetworkConfigError::InvalidPrefix(..))
+        ));
+    }
+
+    #[tokio::test]
+    async fn storage_fetch_fil_balance_testnet() {
+        let address = Address::from_str("0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56").unwrap();
+
+        let res = fetch_fil_balance(address, Network::Testnet).await;
+
+        println!("{:?}", res);
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test]
+    async fn storage_fetch_storage_amounts() {
+        let res = fetch_storage_amounts(&[6024, 6024], Network::Testnet)
+            .await
+            .unwrap();
+
+        assert_eq!(res.len(), 1);
+        assert!(res.contains_key(&6024));
+    }
+
+    #[tokio::test]
+    async fn storage_fetch_network_power() {
+        let network = fetch_network_power(Network::Testnet).await.unwrap();
+        let miner = fetch_storage_amount(6024u32, Network::Testnet)
+            .await
+            .unwrap();
+
+        assert!(network >= miner);
+    }
+
+    #[tokio::test]
+    async fn storage_fetch_storage_amounts_future_height() {
+        let res = fetch_storage_amounts_at(&[6024], Network::Testnet, u32::MAX as u64).await;
+
+        assert!(matches!(res, Err(StorageFetchError::NoTipset(_))));
+    }
+
+    #[test]
+    fn storage_power_cache_memory() {
+        let mut cache = PowerCache {
+            ttl: Duration::from_secs(60),
+            redis: None,
+            entries: BTreeMap::new(),
+            workers: BTreeMap::new(),
+        };
+        let now = Instant::now();
+
+        assert_eq!(cache.get(6024, Network::Testnet, now), None);
+
+        cache.insert(6024, Network::Testnet, 42, now);
+
+        assert_eq!(cache.get(6024, Network::Testnet, now), Some(42));
+        assert_eq!(cache.get(6024, Network::Mainnet, now), None);
+        assert_eq!(
+            cache.get(6024, Network::Testnet, now + Duration::from_secs(60)),
+            None
+        );
+
+        cache.insert_worker(6024, Network::Testnet, "t3worker".to_string(), now);
+
+        assert_eq!(
+            cache.get_worker(6024, Network::Testnet, now),
+            Some("t3worker".to_string())
+        );
+        assert_eq!(
+            cache.get_worker(6024, Network::Testnet, now + Duration::from_secs(60)),
+            None
+        );
+    }
+
+    #[test]
+    fn storage_power_cache_redis() {
+        let server = TestRedis::start();
+        let clock = Arc::new(MockClock::from_system());
+        let mut cache = PowerCache {
+            ttl: Duration::from_secs(60),
+            redis: Some((redis::Client::open(server.url()).unwrap(), clock.clone())),
+            entries: BTreeMap::new(),
+            workers: BTreeMap::new(),
+        };
+        let now = Instant::now();
+
+        cache.insert(1240, Network::Mainnet, 42, now);
+
+        assert_eq!(cache.get(1240, Network::Mainnet, now), Some(42));
+        assert_eq!(cache.get(1240, Network::Testnet, now), None);
+        assert!(cache.entries.is_empty());
+
+        // Power fetched longer than the ttl ago is fetched again
+        clock.advance(60);
+        assert_eq!(cache.get(1240, Network::Mainnet, now), None);
+
+        // As is power cached without its fetch time
+        let mut con = redis::Client::open(server.url())
+            .unwrap()
+            .get_connection()
+            .unwrap();
+        let _: () = con.set(redis_key(1240, Network::Mainnet), "42").unwrap();
+        assert_eq!(cache.get(1240, Network::Mainnet, now), None);
+    }
+
+    #[test]
+    fn storage_power_cache_disabled() {
+        let mut cache = PowerCache {
+            ttl: Duration::ZERO,
+            redis: None,
+            entries: BTreeMap::new(),
+            workers: BTreeMap::new(),
+        };
+        let now = Instant::now();
+
+        cache.insert(6024, Network::Testnet, 42, now);
+
+        assert_eq!(cache.get(6024, Network::Testnet, now), None);
+    }
+
+    #[test]
+    fn storage_sp_cohort_from_power() {
+        assert_eq!(SpCohort::from_power(0), SpCohort::Small);
+        assert_eq!(SpCohort::from_power(PIB - 1), SpCohort::Small);
+        assert_eq!(SpCohort::from_power(PIB), SpCohort::Medium);
+        assert_eq!(SpCohort::from_power(10 * PIB), SpCohort::Large);
+    }
+
     #[tokio::test]
     async fn storage_verify_id_testnet() {
         let res = verify_id("t06024".to_string(), "t3qejyqmrirddrsb2w2thbaco3q6emuljumlhuonp3al35g3kkzx4zpeecycw7gim2meegemwot3gp3qr6alpa".to_string(), Network::Testnet).await.unwrap();
@@ -200,6 +1249,32 @@ mod tests {
         assert!(res);
     }
 
+    #[tokio::test]
+    async fn storage_fetch_worker_address_testnet() {
+        let res = fetch_worker_address(6024, Network::Testnet).await.unwrap();
+
+        assert_eq!(res.as_deref(), Some("t3qejyqmrirddrsb2w2thbaco3q6emuljumlhuonp3al35g3kkzx4zpeecycw7gim2meegemwot3gp3qr6alpa"));
+    }
+
+    #[tokio::test]
+    async fn storage_verify_owner_testnet() {
+        let owner = fetch_owner_address(6024, Network::Testnet)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let res = verify_owner("t06024".to_string(), owner, Network::Testnet)
+            .await
+            .unwrap();
+        assert!(res);
+
+        // Compared against the account key, not the ID address
+        let res = verify_owner("t06024".to_string(), "t06024".to_string(), Network::Testnet)
+            .await
+            .unwrap();
+        assert!(!res);
+    }
+
     #[tokio::test]
     async fn storage_verify_id_mainnet() {
         let res = verify_id("f01240".to_string(), "f3wzxynjiptyogm442qg4cv74czijfzj7fzymqx6gmr6yw6oojhmlg7qavplholgoeyiyxh2zostfrnc2w2mxq".to_string(), Network::Mainnet).await.unwrap();
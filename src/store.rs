@@ -0,0 +1,710 @@
+//! The database behind the handlers
+//!
+//! Votes, registrations and tallies are kept through [`VoteStore`], which
+//! [`Redis`](crate::redis::Redis) implements as the default backend, so that
+//! another database can stand in for it without touching the code built on top.
+//! Handlers open their store through the [`StoreFactory`] they are given as
+//! `web::Data`, and are generic over it so tests can serve them from memory
+use std::{collections::BTreeMap, sync::Arc};
+
+use async_trait::async_trait;
+use ethers::types::{Address, H256};
+use redis::{ErrorKind, RedisError};
+use thiserror::Error;
+
+use crate::{
+    clock::Clock,
+    events::{LoggedEvent, RecordedBallot},
+    fip::FipNumber,
+    fip_registry::FipInfo,
+    messages::{
+        class_registration::VoterClass, nonce::IssuedNonce, vote_end::VoteEnding,
+        vote_metadata::VoteMetadata, vote_registration::PendingRegistration,
+        vote_start::QuorumRules, votes::Vote,
+    },
+    notifier::Announcement,
+    redis::{
+        EarlyEnd, RoundSummary, SilenceReport, Turnout, VoteReceipt, VoteResults, VoteStatus,
+        VoterBallot, VoterPage,
+    },
+    registrations::{DelegationOffer, RegistrationStatus},
+    storage::{Network, StoragePowerProvider},
+    tally::Weighting,
+};
+
+/// Why an operation on the store failed, whichever backend it is kept in
+#[derive(Debug, Error)]
+pub enum StoreError {
+    /// The change breaks a rule of the vote, registration or role it is made to
+    #[error("{0}")]
+    Invalid(String),
+    /// The signer or voter is not allowed to make the change
+    #[error("{0}")]
+    NotAuthorized(String),
+    /// The backend doesn't keep what the operation needs
+    #[error("{0} is not supported by this store")]
+    Unsupported(String),
+    /// The backend could not be reached or failed to carry out the operation
+    #[error("{0}")]
+    Backend(String),
+}
+
+/// Redis reports broken rules as type errors and unauthorized signers as
+/// failed authentications, see [`crate::redis::Redis`]
+impl From<RedisError> for StoreError {
+    fn from(e: RedisError) -> Self {
+        match e.kind() {
+            ErrorKind::TypeError => StoreError::Invalid(e.to_string()),
+            ErrorKind::AuthenticationFailed => StoreError::NotAuthorized(e.to_string()),
+            _ => StoreError::Backend(e.to_string()),
+        }
+    }
+}
+
+/// Opens the store each request works on
+pub trait StoreFactory: Send + Sync + 'static {
+    type Store: VoteStore + 'static;
+
+    /// Opens the store requests that write go to
+    fn open(&self) -> Result<Self::Store, StoreError>;
+
+    /// Opens the store read only requests go to, which may be a read replica
+    fn reader(&self) -> Result<Self::Store, StoreError> {
+        self.open()
+    }
+
+    /// Clock the stores it opens are timed by, handlers read the time from it
+    /// before a store is opened
+    fn clock(&self) -> Arc<dyn Clock>;
+}
+
+/// Every operation on votes, registrations and tallies
+///
+/// Errors are reported as [`StoreError`] whichever backend implements it,
+/// which the handlers print and return, telling invalid changes and
+/// unauthorized signers apart from failures of the backend
+#[async_trait(?Send)]
+pub trait VoteStore {
+    /*~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~/
+    /                                 INITIALIZATION                                 /
+    /~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~*/
+
+    /// Starts a new vote in the database but does not add any votes into the database
+    async fn start_vote(
+        &mut self,
+        fip_number: FipNumber,
+        signer: Address,
+        ntw: Network,
+    ) -> Result<(), StoreError>;
+
+    /// Starts a new vote that only takes ballots from `opens_at` on, leaving
+    /// time to discuss the FIP before voting begins
+    ///
+    /// The voting period is counted from `opens_at`
+    async fn schedule_vote(
+        &mut self,
+        fip_number: FipNumber,
+        signer: Address,
+        ntw: Network,
+        opens_at: u64,
+    ) -> Result<(), StoreError>;
+
+    /// Starts the next round of a concluded vote
+    ///
+    /// The results and ballots of the concluded round are kept so the results
+    /// of the new round can show how opinion moved
+    async fn start_round(
+        &mut self,
+        fip_number: FipNumber,
+        signer: Address,
+        ntw: Network,
+        round: u64,
+        vote_length: u64,
+    ) -> Result<(), StoreError>;
+
+    /// Ends the current round of a vote in progress before its voting period is over
+    ///
+    /// A concluded vote keeps the ballots cast so far as its results, a
+    /// cancelled vote has no results
+    async fn end_vote(
+        &mut self,
+        fip_number: FipNumber,
+        signer: Address,
+        ntw: Network,
+        round: u64,
+        ending: VoteEnding,
+        vote_length: u64,
+    ) -> Result<(), StoreError>;
+
+    /// Extends the current round of a vote in progress
+    ///
+    /// `extension` is the total time the round runs past the configured vote
+    /// length, so it must be longer than any earlier extension of the round
+    async fn extend_vote(
+        &mut self,
+        fip_number: FipNumber,
+        signer: Address,
+        ntw: Network,
+        round: u64,
+        extension: u64,
+        vote_length: u64,
+    ) -> Result<(), StoreError>;
+
+    /// Recreates a vote from an archive without fetching anything from the chain
+    ///
+    /// The ballots, start time, storage totals and metadata are written exactly
+    /// as archived in a single transaction, and the ballots are added to the
+    /// history of their voters
+    async fn import_vote(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        started_at: u64,
+        ballots: &[Vote],
+        results: &VoteResults,
+        metadata: &BTreeMap<String, VoteMetadata>,
+    ) -> Result<(), StoreError>;
+
+    /// Registers a voter in the database
+    ///
+    /// * Creates a lookup from voters address to their respective network
+    /// * Creates a lookup from voters address to their authorized storage providers
+    fn register_voter(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        sp_ids: Vec<u32>,
+    ) -> Result<(), StoreError>;
+
+    /// Registers a voter outside of the storage provider track
+    ///
+    /// These voters have no storage providers so their ballots are only counted
+    /// in the tally of their class
+    fn register_voter_class(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        class: VoterClass,
+    ) -> Result<(), StoreError>;
+
+    fn unregister_voter(&mut self, voter: Address, ntw: Network) -> Result<(), StoreError>;
+
+    /// Removes storage providers from the voters registration in a single transaction
+    ///
+    /// If no storage providers remain the voter is unregistered from the network
+    fn unregister_storage_providers(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        sp_ids: &[u32],
+    ) -> Result<(), StoreError>;
+
+    /// Removes the voters registration on every network in a single transaction
+    fn unregister_voter_all(&mut self, voter: Address) -> Result<(), StoreError>;
+
+    fn register_voter_starter(&mut self, voter: Address, ntw: Network) -> Result<(), StoreError>;
+
+    /// Lets the address grant and revoke roles on the network, see [`crate::roles`]
+    fn register_admin(&mut self, voter: Address, ntw: Network) -> Result<(), StoreError>;
+
+    /*~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~/
+    /                                     GETTERS                                    /
+    /~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~*/
+
+    fn vote_exists(&mut self, ntw: Network, fip: FipNumber) -> Result<bool, StoreError>;
+
+    fn is_authorized_starter(&mut self, voter: Address, ntw: Network) -> Result<bool, StoreError>;
+
+    fn is_admin(&mut self, voter: Address, ntw: Network) -> Result<bool, StoreError>;
+
+    fn is_registered(&mut self, voter: Address, ntw: Network) -> bool;
+
+    /// The worker or multisig address that signed the registration, `None` for
+    /// registrations made before it was kept
+    fn voter_worker(&mut self, voter: Address, ntw: Network) -> Result<Option<String>, StoreError>;
+
+    fn set_voter_worker(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        worker_address: Option<&str>,
+    ) -> Result<(), StoreError>;
+
+    /// Timestamp the registration expires at, `None` if it never does
+    fn registration_expiry(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<Option<u64>, StoreError>;
+
+    /// Whether the registration has expired and has to be renewed before voting
+    fn is_registration_expired(&mut self, voter: Address, ntw: Network)
+        -> Result<bool, StoreError>;
+
+    /// Sets the timestamp the registration expires at, `None` keeps it until it is removed
+    fn set_registration_expiry(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        expires_at: Option<u64>,
+    ) -> Result<(), StoreError>;
+
+    /// Renews the registration of a voter without checking their storage providers again
+    ///
+    /// An expiry later than `expires_at` is kept, so a replayed renewal can't
+    /// shorten the registration. Returns the expiry the registration ends up with
+    fn renew_registration(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        expires_at: Option<u64>,
+    ) -> Result<Option<u64>, StoreError>;
+
+    /// Queues a registration for its storage providers to be verified in the
+    /// background, see [`crate::registrations`]
+    fn queue_registration(
+        &mut self,
+        pending: &PendingRegistration,
+    ) -> Result<RegistrationStatus, StoreError>;
+
+    /// Takes the registration queued first off the queue, `None` once it is empty
+    fn next_registration(&mut self) -> Result<Option<PendingRegistration>, StoreError>;
+
+    /// Where the last registration of the voter is at, `None` if they haven't
+    /// registered recently
+    fn registration_status(
+        &mut self,
+        voter: Address,
+    ) -> Result<Option<RegistrationStatus>, StoreError>;
+
+    fn set_registration_status(
+        &mut self,
+        voter: Address,
+        status: &RegistrationStatus,
+    ) -> Result<(), StoreError>;
+
+    /// Keeps a verified registration until the voter accepts it, replacing
+    /// an earlier offer from the same worker or multisig address
+    fn offer_delegation(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        offer: &DelegationOffer,
+    ) -> Result<(), StoreError>;
+
+    /// Delegations offered to the voter that they haven't accepted yet, oldest first
+    fn delegation_offers(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<Vec<DelegationOffer>, StoreError>;
+
+    /// Removes the offer of the worker or multisig address, returning it so
+    /// it is accepted only once
+    fn take_delegation_offer(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+        worker_address: &str,
+    ) -> Result<Option<DelegationOffer>, StoreError>;
+
+    /// Issues a nonce that can be signed into one message within `ttl` seconds
+    fn issue_nonce(&mut self, ttl: u64) -> Result<IssuedNonce, StoreError>;
+
+    /// Uses up an issued nonce
+    ///
+    /// Returns false for nonces that were never issued, have expired or were
+    /// already used, so a signed message is only accepted once
+    fn consume_nonce(&mut self, nonce: [u8; 16]) -> Result<bool, StoreError>;
+
+    /// Whether the nonce was issued and is still unused, without using it up
+    fn nonce_issued(&mut self, nonce: [u8; 16]) -> Result<bool, StoreError>;
+
+    /// Reads up to `count` events of the log in the order they happened, starting after the event `after`
+    fn events(&mut self, after: Option<&str>, count: usize)
+        -> Result<Vec<LoggedEvent>, StoreError>;
+
+    /// Returns a json blob of the vote results for the FIP number
+    ///
+    /// Results of a concluded vote are tallied once and stored, later requests
+    /// are served from the stored results. Later rounds are compared against
+    /// the round before them at the same time
+    async fn vote_results(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: u64,
+        ntw: Network,
+    ) -> Result<VoteResults, StoreError>;
+
+    fn vote_status(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: impl Into<u64>,
+        ntw: Network,
+    ) -> Result<VoteStatus, StoreError>;
+
+    /// Whether the vote takes ballots at `now`, including the grace period
+    /// after it ends
+    ///
+    /// Ballots are accepted and votes concluded by this one check, so a ballot
+    /// is never taken by a vote whose results were already tallied
+    fn is_open(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: impl Into<u64>,
+        ntw: Network,
+        now: u64,
+    ) -> Result<bool, StoreError>;
+
+    fn active_votes(
+        &mut self,
+        ntw: Network,
+        vote_length: impl Into<u64>,
+    ) -> Result<Vec<FipNumber>, StoreError>;
+
+    fn concluded_votes(
+        &mut self,
+        ntw: Network,
+        vote_length: impl Into<u64>,
+    ) -> Result<Vec<FipNumber>, StoreError>;
+
+    /// Votes past their deadline whose results or silence report have not
+    /// been stored yet, which the conclusion worker stores them for
+    fn stale_votes(
+        &mut self,
+        ntw: Network,
+        vote_length: impl Into<u64>,
+    ) -> Result<Vec<FipNumber>, StoreError>;
+
+    /// Tallies and stores the results of a vote past its deadline along with
+    /// its silence report, `None` when the vote has not concluded
+    async fn conclude_vote(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: u64,
+        ntw: Network,
+    ) -> Result<Option<VoteResults>, StoreError>;
+
+    fn voter_delegates(&mut self, voter: Address, ntw: Network) -> Result<Vec<u32>, StoreError>;
+
+    /// Voters the storage provider is delegated to, a storage provider that
+    /// registered again for another voter without leaving the first is
+    /// delegated to both
+    fn delegators(&mut self, sp_id: u32, ntw: Network) -> Result<Vec<Address>, StoreError>;
+
+    fn voter_starters(&mut self, ntw: Network) -> Result<Vec<Address>, StoreError>;
+
+    fn admins(&mut self, ntw: Network) -> Result<Vec<Address>, StoreError>;
+
+    fn vote_start(&mut self, fip_number: FipNumber, ntw: Network) -> Result<u64, StoreError>;
+
+    fn votes(&mut self, fip_number: FipNumber, ntw: Network) -> Result<Vec<Vote>, StoreError>;
+
+    /// Looks up the receipt of a ballot, `None` for ids that were never issued
+    fn vote_receipt(&mut self, id: &str) -> Result<Option<VoteReceipt>, StoreError>;
+
+    /// Every FIP the voter cast a ballot on, ordered by FIP number
+    ///
+    /// A vote that went to several rounds lists the ballot of the latest round
+    /// the voter took part in
+    fn voter_history(
+        &mut self,
+        voter: Address,
+        ntw: Network,
+    ) -> Result<Vec<VoterBallot>, StoreError>;
+
+    /// The round the vote is in, starting at 1
+    fn vote_round(&mut self, fip_number: FipNumber, ntw: Network) -> Result<u64, StoreError>;
+
+    fn previous_round(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Option<RoundSummary>, StoreError>;
+
+    /// Returns the transaction that published the results of the current round on-chain
+    fn published_results(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Option<H256>, StoreError>;
+
+    fn set_published_results(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        tx_hash: H256,
+    ) -> Result<(), StoreError>;
+
+    /// Whether the announcement was sent for the current round of the vote
+    fn announced(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        announcement: Announcement,
+    ) -> Result<bool, StoreError>;
+
+    fn set_announced(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        announcement: Announcement,
+    ) -> Result<(), StoreError>;
+
+    /// Every network the voter is registered on, each registration being its own
+    fn voter_networks(&mut self, voter: Address) -> Result<Vec<Network>, StoreError>;
+
+    /// The network of the vote a ballot of the voter on the FIP is counted toward
+    ///
+    /// A voter registered on one network votes there. A voter registered on
+    /// several votes on the network `chain_id` belongs to when the ballot was
+    /// signed as typed data, or else on the only one of them where the FIP is
+    /// being voted on. Ballots that could go to more than one vote are refused,
+    /// and voters not registered on the network of the ballot aren't authorized
+    fn ballot_network(
+        &mut self,
+        voter: Address,
+        fip_number: FipNumber,
+        chain_id: Option<u64>,
+        vote_length: u64,
+    ) -> Result<Network, StoreError>;
+
+    /// Returns the class the voter registered as
+    ///
+    /// Voters registered before classes existed are storage providers
+    fn voter_class(&mut self, voter: Address, ntw: Network) -> Result<VoterClass, StoreError>;
+
+    fn registered_voters(&mut self, ntw: Network) -> Result<Vec<Address>, StoreError>;
+
+    /// Returns the registered voters ordered by address, skipping `offset`
+    /// voters and returning at most `limit` of them
+    fn voter_page(
+        &mut self,
+        ntw: Network,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<VoterPage, StoreError>;
+
+    /// Counts the voters and storage that took part in a vote so far
+    ///
+    /// `network_storage_size` is the raw byte power of the whole network the
+    /// turnout is measured against
+    fn turnout(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        network_storage_size: u128,
+    ) -> Result<Turnout, StoreError>;
+
+    /// Registered voters who have not cast a ballot in the current round of
+    /// the vote, read from the ballot history of each voter
+    fn yet_to_vote(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Vec<Address>, StoreError>;
+
+    /// Returns the registered storage power that did not vote on a concluded FIP
+    ///
+    /// The report is stored with the results when the vote is concluded, from
+    /// the registrations at that moment, `None` until it is stored
+    fn silence_report(
+        &mut self,
+        fip_number: FipNumber,
+        vote_length: u64,
+        ntw: Network,
+    ) -> Result<Option<SilenceReport>, StoreError>;
+
+    /// Returns the vote title and description keyed by language
+    fn vote_metadata(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<BTreeMap<String, VoteMetadata>, StoreError>;
+
+    /// Returns the title, status and discussion link synced from the FIPs repository
+    fn fip_info(&mut self, fip_number: FipNumber) -> Result<Option<FipInfo>, StoreError>;
+
+    /// Returns a counter that grows with every change to the database, 0 before the first
+    ///
+    /// Responses built from the database are unchanged while it stays the same,
+    /// up to votes concluding as time passes
+    fn results_version(&mut self) -> Result<u64, StoreError>;
+
+    /// Returns the options the vote was started with, empty for a Yay, Nay or Abstain vote
+    fn vote_options(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Vec<String>, StoreError>;
+
+    /// Returns the quorum rules the vote was started with
+    fn vote_quorum(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Option<QuorumRules>, StoreError>;
+
+    /// Returns how the power behind the ballots of the vote is weighted
+    fn vote_weighting(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Weighting, StoreError>;
+
+    /// Returns the raw byte power a ballot of the vote needs to be counted, 0 when every ballot is
+    fn vote_min_power(&mut self, fip_number: FipNumber, ntw: Network) -> Result<u128, StoreError>;
+
+    /// Returns the seconds the current round runs past the configured vote length
+    fn vote_extension(&mut self, fip_number: FipNumber, ntw: Network) -> Result<u64, StoreError>;
+
+    /// Returns how the current round was ended early, `None` if it was not
+    fn vote_ending(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+    ) -> Result<Option<EarlyEnd>, StoreError>;
+
+    fn all_votes(&mut self, ntw: Network) -> Result<Vec<FipNumber>, StoreError>;
+
+    /// Where ballots are weighted from
+    fn power_provider(&self) -> Arc<dyn StoragePowerProvider>;
+
+    /// Times votes, registrations and nonces with `clock` instead of the system time
+    fn set_clock(&mut self, clock: Arc<dyn Clock>);
+
+    /// Current unix timestamp by the clock the store is timed by
+    fn now(&self) -> u64;
+
+    /*~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~/
+    /                                     SETTERS                                    /
+    /~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~*/
+
+    async fn add_vote(
+        &mut self,
+        fip_number: FipNumber,
+        vote: Vote,
+        voter: Address,
+        vote_length: u64,
+        allow_revote: bool,
+    ) -> Result<(), StoreError>;
+
+    /// Adds the vote once per signature
+    ///
+    /// Retrying a submission that already landed returns the original receipt
+    /// instead of an error, the receipt is written together with the ballot
+    async fn submit_vote(
+        &mut self,
+        fip_number: FipNumber,
+        vote: Vote,
+        voter: Address,
+        vote_length: u64,
+        allow_revote: bool,
+        receipt_id: String,
+    ) -> Result<VoteReceipt, StoreError>;
+
+    /// Adds the ballots of a combined ballot, each with the receipt id it is paired with
+    ///
+    /// Every ballot is checked and weighed before any is written, and they are
+    /// written in one transaction, so either all of them are recorded or none
+    /// are. Retrying a submission that already landed returns the original receipts
+    async fn add_vote_batch(
+        &mut self,
+        votes: Vec<(Vote, String)>,
+        voter: Address,
+        vote_length: u64,
+        allow_revote: bool,
+    ) -> Result<Vec<VoteReceipt>, StoreError>;
+
+    /// Writes a ballot whose weight was already fetched from the chain
+    ///
+    /// Adding a vote ends here once every check passed, replaying the event
+    /// log calls it directly with the ballots that were recorded
+    fn record_ballot(
+        &mut self,
+        ballot: RecordedBallot,
+        allow_revote: bool,
+        receipt: Option<&VoteReceipt>,
+    ) -> Result<(), StoreError>;
+
+    /// Replaces what the FIPs repository says about a FIP
+    ///
+    /// Not logged as an event, it is synced again from the repository
+    fn set_fip_info(&mut self, fip_number: FipNumber, info: &FipInfo) -> Result<(), StoreError>;
+
+    /// Adds or replaces the vote title and description for a language
+    async fn set_vote_metadata(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        language: String,
+        metadata: VoteMetadata,
+    ) -> Result<(), StoreError>;
+
+    /// Sets the options a vote is cast with, must be called before the vote starts
+    fn set_vote_options(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        options: &[String],
+    ) -> Result<(), StoreError>;
+
+    /// Sets the quorum rules of a vote, must be called before the vote starts
+    fn set_vote_quorum(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        rules: Option<QuorumRules>,
+    ) -> Result<(), StoreError>;
+
+    /// Sets how the power behind the ballots is weighted, must be called before the vote starts
+    fn set_vote_weighting(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        weighting: Weighting,
+    ) -> Result<(), StoreError>;
+
+    /// Sets the raw byte power a ballot needs to be counted, must be called before the vote starts
+    fn set_vote_min_power(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        min_power: u128,
+    ) -> Result<(), StoreError>;
+
+    /// Sets how the current round was ended early, `None` to let it run its full length
+    fn set_vote_ending(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        ended: Option<&EarlyEnd>,
+    ) -> Result<(), StoreError>;
+
+    /// Sets the seconds the current round runs past the configured vote length
+    fn set_vote_extension(
+        &mut self,
+        fip_number: FipNumber,
+        ntw: Network,
+        extension: u64,
+    ) -> Result<(), StoreError>;
+
+    /// Removes a vote starter, keeping at least one starter on the network
+    fn remove_voter_starters(&mut self, voter: Address, ntw: Network) -> Result<(), StoreError>;
+
+    /// Removes an admin, keeping at least one admin on the network
+    fn remove_admin(&mut self, voter: Address, ntw: Network) -> Result<(), StoreError>;
+
+    /// Removes the ballots of a vote, keeping the vote itself
+    fn flush_vote(&mut self, fip_number: FipNumber, ntw: Network) -> Result<(), StoreError>;
+
+    /// Deletes every trace of a vote in a single transaction
+    ///
+    /// Removes the ballots, start timestamp, storage totals, silence report,
+    /// metadata and the ballots kept in voter histories so the FIP can be
+    /// started again from scratch
+    async fn delete_vote(&mut self, fip_number: FipNumber, ntw: Network) -> Result<(), StoreError>;
+
+    /// Deletes everything in the database
+    fn flush_all(&mut self) -> Result<(), StoreError>;
+}
@@ -0,0 +1,670 @@
+//! Counting the ballots of a vote and the power behind each choice
+//!
+//! [`compute`] only works on what the store read for it, so the counting can
+//! be tested without a database.
+//!
+//! Raw byte power lets the largest storage providers decide a vote on their
+//! own. A vote can be started with a weighting that flattens their power,
+//! applied to each storage provider a ballot was cast for and not to the
+//! ballot as a whole, so splitting power across ballots doesn't change it.
+//!
+//! A vote can also require a minimum power so a storage provider can't cast
+//! many ballots from addresses holding almost no power. Ballots under it are
+//! recorded, but left out of the tally and only counted in
+//! `VoteResults::below_min_power`
+use std::collections::BTreeMap;
+
+use redis::RedisError;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::{
+    messages::{
+        class_registration::VoterClass,
+        vote_start::QuorumRules,
+        votes::{Vote, VoteOption},
+    },
+    redis::{OptionTally, VoteResults},
+    store::StoreError,
+};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum WeightingError {
+    #[error("The cap has to be between 1 and 100 percent, got {0}")]
+    InvalidCap(u8),
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TallyError {
+    #[error("Storage power total does not fit in 128 bits")]
+    Overflow,
+    /// A total is smaller than the ballot being taken back from it, so it
+    /// no longer matches the ballots it was summed from
+    #[error("Storage power total is smaller than a ballot counted in it")]
+    Underflow,
+}
+
+impl From<TallyError> for RedisError {
+    fn from(e: TallyError) -> Self {
+        RedisError::from((redis::ErrorKind::TypeError, "Error tallying", e.to_string()))
+    }
+}
+
+impl From<TallyError> for StoreError {
+    fn from(e: TallyError) -> Self {
+        StoreError::Invalid(format!("Error tallying: {}", e))
+    }
+}
+
+/// Sum of the powers, an error rather than a wrapped total when it doesn't fit
+pub fn checked_sum(powers: impl IntoIterator<Item = u128>) -> Result<u128, TallyError> {
+    powers.into_iter().try_fold(0u128, |total, power| {
+        total.checked_add(power).ok_or(TallyError::Overflow)
+    })
+}
+
+pub fn checked_mul(power: u128, factor: u128) -> Result<u128, TallyError> {
+    power.checked_mul(factor).ok_or(TallyError::Overflow)
+}
+
+/// How the power of each storage provider counts toward its choice
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Weighting {
+    /// Raw byte power in bytes
+    #[default]
+    Raw,
+    /// Square root of the raw byte power
+    Sqrt,
+    /// Raw byte power, up to this percent of all the power that voted
+    Capped(u8),
+}
+
+impl Weighting {
+    /// Checks the weighting can be started with
+    pub fn validate(&self) -> Result<(), WeightingError> {
+        match self {
+            Weighting::Capped(percent) if *percent == 0 || *percent > 100 => {
+                Err(WeightingError::InvalidCap(*percent))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn is_raw(&self) -> bool {
+        *self == Weighting::Raw
+    }
+
+    /// The weight of every ballot, in the order of `ballots`
+    ///
+    /// Caps are taken from the raw power of every ballot, whatever its choice
+    pub fn ballot_weights(&self, ballots: &[Vote]) -> Result<Vec<u128>, TallyError> {
+        let cap = match self {
+            Weighting::Capped(percent) => {
+                let total = checked_sum(
+                    ballots
+                        .iter()
+                        .flat_map(|ballot| ballot.storage_powers().values().copied()),
+                )?;
+                // Split so the share of the largest totals doesn't overflow
+                let percent = *percent as u128;
+                Some(total / 100 * percent + total % 100 * percent / 100)
+            }
+            _ => None,
+        };
+
+        ballots
+            .iter()
+            .map(|ballot| {
+                checked_sum(
+                    ballot
+                        .storage_powers()
+                        .values()
+                        .map(|power| self.weigh(*power, cap)),
+                )
+            })
+            .collect()
+    }
+
+    fn weigh(&self, power: u128, cap: Option<u128>) -> u128 {
+        match self {
+            Weighting::Raw => power,
+            Weighting::Sqrt => power.isqrt(),
+            Weighting::Capped(_) => power.min(cap.unwrap_or(power)),
+        }
+    }
+}
+
+/// Raw byte power and token holder balance the store recorded behind each
+/// choice as ballots were cast
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PowerMap {
+    pub yay: ChoicePower,
+    pub nay: ChoicePower,
+    pub abstain: ChoicePower,
+    /// Every option the vote was started with besides Yay, Nay and Abstain,
+    /// even those nobody voted for
+    pub options: BTreeMap<String, ChoicePower>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ChoicePower {
+    /// Raw byte power in bytes
+    pub storage_size: u128,
+    /// Token holder balance in attoFIL
+    pub fil: u128,
+}
+
+impl PowerMap {
+    /// The power behind the choice, `None` for choices the vote doesn't have
+    pub(crate) fn get_mut(&mut self, choice: &VoteOption) -> Option<&mut ChoicePower> {
+        match choice {
+            VoteOption::Yay => Some(&mut self.yay),
+            VoteOption::Nay => Some(&mut self.nay),
+            VoteOption::Abstain => Some(&mut self.abstain),
+            VoteOption::Named(name) => self.options.get_mut(name),
+            VoteOption::Unknown(_) => None,
+        }
+    }
+}
+
+/// How the ballots of a vote are counted, as the vote was started
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TallyRules {
+    pub weighting: Weighting,
+    /// Judged against once the vote concluded, `None` while it is in
+    /// progress or for votes started without rules
+    pub quorum: Option<QuorumRules>,
+    /// Raw byte power the storage providers of a ballot need between them
+    /// for it to be counted, 0 counts every ballot
+    pub min_power: u128,
+}
+
+impl TallyRules {
+    /// Whether the ballot is left out of the tally for being cast with less
+    /// power than the vote requires, only storage providers are held to it
+    pub fn excludes(&self, vote: &Vote) -> bool {
+        vote.class() == VoterClass::StorageProvider
+            && checked_sum(vote.storage_powers().values().copied())
+                .is_ok_and(|power| power < self.min_power)
+    }
+
+    /// The weight of every ballot, in the order of `votes`, 0 for the ballots
+    /// left out of the tally
+    ///
+    /// Caps are taken from the ballots that are counted
+    pub fn ballot_weights(&self, votes: &[Vote]) -> Result<Vec<u128>, TallyError> {
+        let counted: Vec<Vote> = votes
+            .iter()
+            .filter(|vote| !self.excludes(vote))
+            .cloned()
+            .collect();
+        let mut weights = self.weighting.ballot_weights(&counted)?.into_iter();
+
+        Ok(votes
+            .iter()
+            .map(|vote| match self.excludes(vote) {
+                true => 0,
+                false => weights.next().unwrap_or_default(),
+            })
+            .collect())
+    }
+}
+
+/// Counts the ballots behind each choice and the storage and FIL behind them
+///
+/// Raw storage comes from `power` as the store recorded it, weighted storage is
+/// summed from the power each ballot was recorded with. Ballots count in the
+/// tally of the class they were cast as. Ballots for options the vote doesn't have,
+/// or that a newer version added, are not counted, and ballots under the
+/// minimum power are only counted as such. Storage that doesn't fit in 128
+/// bits is an error rather than a wrapped total
+pub fn compute(
+    votes: &[Vote],
+    power: &PowerMap,
+    rules: &TallyRules,
+) -> Result<VoteResults, TallyError> {
+    let mut results = VoteResults {
+        yay_storage_size: power.yay.storage_size,
+        nay_storage_size: power.nay.storage_size,
+        abstain_storage_size: power.abstain.storage_size,
+        yay_fil: power.yay.fil,
+        nay_fil: power.nay.fil,
+        abstain_fil: power.abstain.fil,
+        options: power
+            .options
+            .iter()
+            .map(|(name, power)| {
+                let tally = OptionTally {
+                    votes: 0,
+                    storage_size: power.storage_size,
+                    fil: power.fil,
+                };
+                (name.clone(), tally)
+            })
+            .collect(),
+        weighting: rules.weighting,
+        ..Default::default()
+    };
+
+    for vote in votes {
+        if rules.excludes(vote) {
+            results.below_min_power += 1;
+            continue;
+        }
+
+        let tally = results.classes.entry(vote.class()).or_default();
+
+        match vote.choice() {
+            VoteOption::Yay => {
+                results.yay += 1;
+                tally.yay += 1;
+            }
+            VoteOption::Nay => {
+                results.nay += 1;
+                tally.nay += 1;
+            }
+            VoteOption::Abstain => {
+                results.abstain += 1;
+                tally.abstain += 1;
+            }
+            VoteOption::Named(name) => {
+                if let Some(option) = results.options.get_mut(&name) {
+                    option.votes += 1;
+                    *tally.options.entry(name).or_default() += 1;
+                }
+            }
+            VoteOption::Unknown(_) => (),
+        }
+    }
+
+    // Summed from the ballots when the weighting or the ballots left out
+    // change what the recorded power adds up to
+    if !rules.weighting.is_raw() || rules.min_power > 0 {
+        let mut weighted = PowerMap {
+            options: power
+                .options
+                .keys()
+                .map(|name| (name.clone(), ChoicePower::default()))
+                .collect(),
+            ..Default::default()
+        };
+        for (vote, weight) in votes.iter().zip(rules.ballot_weights(votes)?) {
+            if let Some(choice) = weighted.get_mut(&vote.choice()) {
+                choice.storage_size = checked_sum([choice.storage_size, weight])?;
+            }
+        }
+
+        results.yay_storage_size = weighted.yay.storage_size;
+        results.nay_storage_size = weighted.nay.storage_size;
+        results.abstain_storage_size = weighted.abstain.storage_size;
+        for (name, option) in results.options.iter_mut() {
+            option.storage_size = weighted.options[name].storage_size;
+        }
+    }
+
+    if let Some(quorum) = &rules.quorum {
+        results.judge(quorum)?;
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use ethers::types::Address;
+
+    use super::*;
+    use crate::{messages::votes::test_votes::test_vote, redis::VoteOutcome};
+
+    fn ballot(powers: &[(u32, u128)]) -> Vote {
+        let mut vote = test_vote(VoteOption::Yay, 1u32).vote().unwrap();
+        vote.record(
+            1700000000,
+            VoterClass::StorageProvider,
+            powers.iter().copied().collect::<BTreeMap<_, _>>(),
+            0,
+        );
+        vote
+    }
+
+    #[test]
+    fn tally_ballot_weights() {
+        let ballots = [ballot(&[(1000, 900), (1001, 16)]), ballot(&[(1002, 84)])];
+
+        assert_eq!(
+            Weighting::Raw.ballot_weights(&ballots).unwrap(),
+            vec![916, 84]
+        );
+        assert_eq!(
+            Weighting::Sqrt.ballot_weights(&ballots).unwrap(),
+            vec![34, 9]
+        );
+
+        // 10% of the 1000 bytes that voted
+        assert_eq!(
+            Weighting::Capped(10).ballot_weights(&ballots).unwrap(),
+            vec![116, 84]
+        );
+        assert_eq!(
+            Weighting::Capped(100).ballot_weights(&ballots).unwrap(),
+            vec![916, 84]
+        );
+
+        assert!(Weighting::Raw.ballot_weights(&[]).unwrap().is_empty());
+    }
+
+    /// A ballot of the voter for the choice, recorded with the power of each storage provider
+    fn cast(voter: u64, choice: VoteOption, powers: &[(u32, u128)]) -> Vote {
+        cast_as(VoterClass::StorageProvider, voter, choice, powers)
+    }
+
+    /// A ballot cast by a voter of the class
+    fn cast_as(class: VoterClass, voter: u64, choice: VoteOption, powers: &[(u32, u128)]) -> Vote {
+        let mut vote: Vote = serde_json::from_value(serde_json::json!({
+            "choice": choice,
+            "address": Address::from_low_u64_be(voter),
+            "fip": 1,
+        }))
+        .unwrap();
+        vote.record(
+            1700000000,
+            class,
+            powers.iter().copied().collect::<BTreeMap<_, _>>(),
+            0,
+        );
+        vote
+    }
+
+    fn named(name: &str) -> VoteOption {
+        VoteOption::Named(name.to_string())
+    }
+
+    fn power(storage_size: u128) -> ChoicePower {
+        ChoicePower {
+            storage_size,
+            fil: 0,
+        }
+    }
+
+    #[test]
+    fn tally_compute() {
+        let votes = [
+            cast(1, VoteOption::Yay, &[(1000, 900)]),
+            cast(2, VoteOption::Nay, &[(1001, 100)]),
+            cast_as(VoterClass::CoreDev, 3, VoteOption::Abstain, &[]),
+            cast_as(VoterClass::TokenHolder, 4, VoteOption::Yay, &[]),
+            cast(5, VoteOption::Unknown(9), &[(1002, 50)]),
+        ];
+        let power = PowerMap {
+            yay: ChoicePower {
+                storage_size: 900,
+                fil: 7,
+            },
+            nay: power(100),
+            ..Default::default()
+        };
+
+        let results = compute(&votes, &power, &TallyRules::default()).unwrap();
+
+        assert_eq!(results.yay, 2);
+        assert_eq!(results.nay, 1);
+        assert_eq!(results.abstain, 1);
+        assert_eq!(results.yay_storage_size, 900);
+        assert_eq!(results.nay_storage_size, 100);
+        assert_eq!(results.yay_fil, 7);
+        assert_eq!(results.total_storage_size(), 1000);
+
+        // Ballots count for the class they were cast as, unknown choices
+        // only open an empty tally for their class
+        assert_eq!(results.classes[&VoterClass::StorageProvider].yay, 1);
+        assert_eq!(results.classes[&VoterClass::StorageProvider].nay, 1);
+        assert_eq!(results.classes[&VoterClass::CoreDev].abstain, 1);
+        assert_eq!(results.classes[&VoterClass::TokenHolder].yay, 1);
+        assert!(results.outcome().is_none());
+
+        let results = compute(&[], &PowerMap::default(), &TallyRules::default()).unwrap();
+        assert_eq!(results.total_storage_size(), 0);
+        assert!(results.classes.is_empty());
+    }
+
+    #[test]
+    fn tally_compute_options() {
+        let votes = [
+            cast(1, named("A"), &[(1000, 10)]),
+            cast(2, named("A"), &[(1001, 20)]),
+            cast(3, named("C"), &[(1002, 30)]),
+        ];
+        let power = PowerMap {
+            options: BTreeMap::from([("A".to_string(), power(30)), ("B".to_string(), power(0))]),
+            ..Default::default()
+        };
+
+        let results = compute(&votes, &power, &TallyRules::default()).unwrap();
+
+        // Options nobody voted for are listed, ballots for options the vote
+        // doesn't have are not counted
+        assert_eq!(results.options["A"].votes, 2);
+        assert_eq!(results.options["A"].storage_size, 30);
+        assert_eq!(results.options["B"].votes, 0);
+        assert!(!results.options.contains_key("C"));
+        assert_eq!(
+            results.classes[&VoterClass::StorageProvider].options["A"],
+            2
+        );
+        assert!(!results.classes[&VoterClass::StorageProvider]
+            .options
+            .contains_key("C"));
+    }
+
+    #[test]
+    fn tally_compute_weighted() {
+        let votes = [
+            cast(1, VoteOption::Yay, &[(1000, 900), (1001, 16)]),
+            cast(2, VoteOption::Nay, &[(1002, 84)]),
+            cast(3, named("A"), &[(1003, 100)]),
+            cast(4, VoteOption::Abstain, &[]),
+        ];
+        let power = PowerMap {
+            yay: power(916),
+            nay: power(84),
+            options: BTreeMap::from([("A".to_string(), power(100))]),
+            ..Default::default()
+        };
+        let rules = TallyRules {
+            weighting: Weighting::Sqrt,
+            quorum: None,
+            min_power: 0,
+        };
+
+        let results = compute(&votes, &power, &rules).unwrap();
+
+        // Weighted from the ballots rather than the recorded power
+        assert_eq!(results.yay_storage_size, 34);
+        assert_eq!(results.nay_storage_size, 9);
+        assert_eq!(results.options["A"].storage_size, 10);
+        assert_eq!(results.abstain_storage_size, 0);
+        assert_eq!(results.weighting(), Weighting::Sqrt);
+    }
+
+    #[test]
+    fn tally_compute_duplicate_storage_providers() {
+        // A storage provider that moved to another voter between ballots is
+        // counted with each ballot it was recorded in
+        let votes = [
+            cast(1, VoteOption::Yay, &[(1000, 100)]),
+            cast(2, VoteOption::Nay, &[(1000, 100), (1001, 300)]),
+        ];
+        let rules = TallyRules {
+            weighting: Weighting::Capped(50),
+            quorum: None,
+            min_power: 0,
+        };
+
+        let results = compute(&votes, &PowerMap::default(), &rules).unwrap();
+
+        // Capped at 50% of the 500 bytes behind the ballots
+        assert_eq!(results.yay_storage_size, 100);
+        assert_eq!(results.nay_storage_size, 350);
+    }
+
+    #[test]
+    fn tally_compute_min_power() {
+        let votes = [
+            cast(1, VoteOption::Yay, &[(1000, 60), (1001, 40)]),
+            cast(2, VoteOption::Yay, &[(1002, 99)]),
+            cast(3, VoteOption::Nay, &[(1003, 100)]),
+            cast(4, VoteOption::Nay, &[]),
+            cast_as(VoterClass::CoreDev, 5, VoteOption::Abstain, &[]),
+        ];
+        let power = PowerMap {
+            yay: power(199),
+            nay: power(100),
+            ..Default::default()
+        };
+        let rules = TallyRules {
+            weighting: Weighting::Capped(50),
+            quorum: None,
+            min_power: 100,
+        };
+
+        let results = compute(&votes, &power, &rules).unwrap();
+
+        // The power of every storage provider of a ballot adds up to the
+        // minimum, voters of other classes aren't held to it
+        assert_eq!(results.below_min_power(), 2);
+        assert_eq!(results.yay, 1);
+        assert_eq!(results.nay, 1);
+        assert_eq!(results.abstain, 1);
+        assert_eq!(results.classes[&VoterClass::StorageProvider].yay, 1);
+
+        // Capped at 50% of the 200 bytes behind the ballots that are counted
+        assert_eq!(results.yay_storage_size, 100);
+        assert_eq!(results.nay_storage_size, 100);
+        assert_eq!(
+            rules.ballot_weights(&votes).unwrap(),
+            vec![100, 0, 100, 0, 0]
+        );
+
+        let results = compute(&votes, &power, &TallyRules::default()).unwrap();
+        assert_eq!(results.below_min_power(), 0);
+        assert_eq!(results.yay_storage_size, 199);
+    }
+
+    #[test]
+    fn tally_compute_quorum() {
+        let votes = [
+            cast(1, VoteOption::Yay, &[(1000, 60)]),
+            cast(2, VoteOption::Nay, &[(1001, 40)]),
+        ];
+        let power = PowerMap {
+            yay: power(60),
+            nay: power(40),
+            ..Default::default()
+        };
+        let rules = |min_storage_size| TallyRules {
+            weighting: Weighting::Raw,
+            quorum: Some(QuorumRules {
+                min_storage_size,
+                pass_percentage: 60,
+            }),
+            min_power: 0,
+        };
+
+        let results = compute(&votes, &power, &rules(100)).unwrap();
+        assert_eq!(results.quorum_met(), Some(true));
+        assert_eq!(results.outcome(), Some(VoteOutcome::Passed));
+
+        let results = compute(&votes, &power, &rules(101)).unwrap();
+        assert_eq!(results.outcome(), Some(VoteOutcome::QuorumNotMet));
+    }
+
+    #[test]
+    fn tally_compute_overflow() {
+        let votes = [
+            cast(1, VoteOption::Yay, &[(1000, u128::MAX), (1001, 1)]),
+            cast(2, VoteOption::Nay, &[(1002, 1)]),
+        ];
+
+        // A ballot's power and the total behind a choice don't wrap around
+        assert_eq!(
+            Weighting::Raw.ballot_weights(&votes),
+            Err(TallyError::Overflow)
+        );
+        let rules = TallyRules {
+            weighting: Weighting::Capped(100),
+            quorum: None,
+            min_power: 0,
+        };
+        assert_eq!(
+            compute(&votes, &PowerMap::default(), &rules).unwrap_err(),
+            TallyError::Overflow
+        );
+
+        // The largest powers are still weighted without overflowing
+        let votes = [
+            cast(1, VoteOption::Yay, &[(1000, u128::MAX)]),
+            cast(2, VoteOption::Nay, &[]),
+        ];
+        assert_eq!(
+            Weighting::Capped(50).ballot_weights(&votes).unwrap(),
+            vec![u128::MAX / 2, 0]
+        );
+        let rules = TallyRules {
+            weighting: Weighting::Sqrt,
+            quorum: None,
+            min_power: 0,
+        };
+        let results = compute(&votes, &PowerMap::default(), &rules).unwrap();
+        assert_eq!(results.yay_storage_size, u128::MAX.isqrt());
+
+        // Judging a total that doesn't fit is an error too
+        let power = PowerMap {
+            yay: power(u128::MAX / 2),
+            nay: power(u128::MAX / 2),
+            abstain: power(2),
+            ..Default::default()
+        };
+        let rules = TallyRules {
+            weighting: Weighting::Raw,
+            quorum: Some(QuorumRules {
+                min_storage_size: 0,
+                pass_percentage: 50,
+            }),
+            min_power: 0,
+        };
+        assert_eq!(
+            compute(&[], &power, &rules).unwrap_err(),
+            TallyError::Overflow
+        );
+        assert_eq!(checked_sum([u128::MAX, 0]), Ok(u128::MAX));
+        assert_eq!(checked_sum([u128::MAX, 1]), Err(TallyError::Overflow));
+    }
+
+    #[test]
+    fn tally_validate() {
+        assert!(Weighting::Sqrt.validate().is_ok());
+        assert!(Weighting::Capped(100).validate().is_ok());
+        assert_eq!(
+            Weighting::Capped(0).validate(),
+            Err(WeightingError::InvalidCap(0))
+        );
+        assert_eq!(
+            Weighting::Capped(101).validate(),
+            Err(WeightingError::InvalidCap(101))
+        );
+
+        // Started as `"sqrt"` or `{"capped": 10}`
+        assert_eq!(
+            serde_json::from_str::<Weighting>(r#"{"capped":10}"#).unwrap(),
+            Weighting::Capped(10)
+        );
+        assert_eq!(
+            serde_json::to_string(&Weighting::Sqrt).unwrap(),
+            r#""sqrt""#
+        );
+    }
+}
@@ -0,0 +1,327 @@
+//! The exact messages voters, vote starters and storage providers sign
+//!
+//! Built by the same code the messages are parsed with, so the frontend asks
+//! for the message instead of formatting it and drifting from what is accepted
+use actix_web::{web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::{
+    errors::*,
+    messages::{
+        domain::Domain,
+        nonce::{IssuedNonce, NONCE_TTL},
+        vote_registration::registration_message,
+        vote_start::start_message,
+        votes::{vote_message, VoteOption},
+    },
+    storage::{parse_address, Network},
+    store::{StoreFactory, VoteStore},
+    Args, TemplateParams,
+};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TemplateError {
+    #[error("{0} is required")]
+    MissingParam(&'static str),
+    #[error("{0} is not an option of the vote")]
+    InvalidChoice(String),
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("Invalid storage provider id: {0}")]
+    InvalidSpId(String),
+    #[error("Registrations are not signed with a nonce")]
+    NonceNotAccepted,
+}
+
+/// Message being asked for
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateKind {
+    /// A ballot, signed as the `message` of `POST /filecoin/vote`
+    Vote,
+    /// A registration, signed by the worker or multisig signers of the storage providers
+    Register,
+    /// A vote start, signed as the `message` of `POST /filecoin/startvote`
+    Start,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct MessageTemplate {
+    /// The message to sign, domain, nonce and expiry included
+    #[schema(example = "YAY: FIP-1 SERVICE-fipvote NETWORK-mainnet")]
+    pub message: String,
+    /// The message hex encoded for `lotus wallet sign`, only for registrations
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hex: Option<String>,
+    /// The nonce ending the message, when one was asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<IssuedNonce>,
+}
+
+/// Registers the message template endpoint, reading from the stores `F` opens
+pub fn routes<F: StoreFactory>(cfg: &mut web::ServiceConfig) {
+    cfg.route(
+        "/filecoin/messages/template",
+        web::get().to(get_message_template::<F>),
+    );
+}
+
+/// Returns the message to sign for a ballot, registration or vote start
+///
+/// A vote with named options needs the `network` to look the options up,
+/// without it only YAY, NAY and ABSTAIN are accepted. Given a `network` the
+/// message ends with the service and network it is signed for. With
+/// `nonce=true` a nonce is issued as by `POST /filecoin/nonce` and appended
+/// after it
+#[utoipa::path(
+    get,
+    path = "/filecoin/messages/template",
+    tag = "votes",
+    params(TemplateParams),
+    responses(
+        (status = 200, description = "The message to sign", body = MessageTemplate),
+        (status = 400, description = "Missing or invalid parameters", body = String),
+        (status = 500, description = "Database error", body = String),
+    )
+)]
+async fn get_message_template<F: StoreFactory>(
+    query_params: web::Query<TemplateParams>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let params = query_params.into_inner();
+
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    // Ballots on named options have to use the option as it was started
+    let options = match (params.kind, params.network, params.fip_number) {
+        (TemplateKind::Vote, Some(ntw), Some(num)) => match redis.vote_options(num, ntw) {
+            Ok(options) => options,
+            Err(e) => {
+                let res = format!("{}: {}", VOTE_OPTIONS_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::InternalServerError().body(res);
+            }
+        },
+        _ => Vec::new(),
+    };
+
+    let message = match template_message(&params, &options) {
+        Ok(message) => match params.network {
+            Some(ntw) => Domain::new(config.service_id(), ntw).sign_into(&message),
+            None => message,
+        },
+        Err(e) => {
+            let res = format!("{}: {}", MESSAGE_TEMPLATE_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::BadRequest().body(res);
+        }
+    };
+
+    let template = match params.nonce {
+        true => match redis.issue_nonce(NONCE_TTL) {
+            Ok(nonce) => MessageTemplate {
+                message: nonce.sign_into(&message),
+                hex: None,
+                nonce: Some(nonce),
+            },
+            Err(e) => {
+                let res = format!("{}: {}", NONCE_ERROR, e);
+                println!("{}", res);
+                return HttpResponse::InternalServerError().body(res);
+            }
+        },
+        false => MessageTemplate {
+            hex: (params.kind == TemplateKind::Register).then(|| hex::encode(&message)),
+            message,
+            nonce: None,
+        },
+    };
+
+    HttpResponse::Ok().json(template)
+}
+
+/// The message without a nonce, `options` being the options of the vote if it has any
+fn template_message(params: &TemplateParams, options: &[String]) -> Result<String, TemplateError> {
+    match params.kind {
+        TemplateKind::Vote => {
+            let fip_number = params
+                .fip_number
+                .ok_or(TemplateError::MissingParam("fip_number"))?;
+            let choice = params
+                .choice
+                .as_deref()
+                .ok_or(TemplateError::MissingParam("choice"))?;
+
+            let choice = parse_choice(choice, options)?;
+            vote_message(&choice, fip_number)
+                .ok_or_else(|| TemplateError::InvalidChoice(format!("{:?}", choice)))
+        }
+        TemplateKind::Register => {
+            if params.nonce {
+                return Err(TemplateError::NonceNotAccepted);
+            }
+            let ntw = params
+                .network
+                .ok_or(TemplateError::MissingParam("network"))?;
+            let address = params
+                .address
+                .as_deref()
+                .ok_or(TemplateError::MissingParam("address"))?;
+            let sp_ids = params
+                .sp_ids
+                .as_deref()
+                .ok_or(TemplateError::MissingParam("sp_ids"))?;
+
+            let voter = parse_address(ntw, address)
+                .map_err(|_| TemplateError::InvalidAddress(address.to_string()))?;
+            let sp_ids = parse_sp_ids(sp_ids, ntw)?;
+
+            Ok(registration_message(voter, &sp_ids, ntw))
+        }
+        TemplateKind::Start => {
+            let fip_number = params
+                .fip_number
+                .ok_or(TemplateError::MissingParam("fip_number"))?;
+
+            Ok(start_message(fip_number, params.round.unwrap_or(1)))
+        }
+    }
+}
+
+/// Matches the choice against the options of the vote, or YAY, NAY and ABSTAIN
+fn parse_choice(choice: &str, options: &[String]) -> Result<VoteOption, TemplateError> {
+    let choice = choice.trim();
+
+    if !options.is_empty() {
+        return options
+            .iter()
+            .find(|option| option.eq_ignore_ascii_case(choice))
+            .map(|option| VoteOption::Named(option.clone()))
+            .ok_or_else(|| TemplateError::InvalidChoice(choice.to_string()));
+    }
+
+    match choice.to_ascii_uppercase().as_str() {
+        "YAY" => Ok(VoteOption::Yay),
+        "NAY" => Ok(VoteOption::Nay),
+        "ABSTAIN" => Ok(VoteOption::Abstain),
+        _ => Err(TemplateError::InvalidChoice(choice.to_string())),
+    }
+}
+
+/// Reads a comma separated list of storage provider ids, with or without the network prefix
+fn parse_sp_ids(sp_ids: &str, ntw: Network) -> Result<Vec<u32>, TemplateError> {
+    let prefix = format!("{}0", ntw.prefix());
+
+    let sp_ids = sp_ids
+        .split(',')
+        .map(str::trim)
+        .filter(|sp_id| !sp_id.is_empty())
+        .map(|sp_id| {
+            sp_id
+                .strip_prefix(prefix.as_str())
+                .unwrap_or(sp_id)
+                .parse::<u32>()
+                .map_err(|_| TemplateError::InvalidSpId(sp_id.to_string()))
+        })
+        .collect::<Result<Vec<u32>, TemplateError>>()?;
+
+    match sp_ids.is_empty() {
+        true => Err(TemplateError::MissingParam("sp_ids")),
+        false => Ok(sp_ids),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fip::FipNumber;
+
+    fn params(kind: TemplateKind) -> TemplateParams {
+        TemplateParams {
+            kind,
+            fip_number: Some(FipNumber::try_from(5u32).unwrap()),
+            choice: None,
+            round: None,
+            network: Some(Network::Testnet),
+            address: None,
+            sp_ids: None,
+            nonce: false,
+        }
+    }
+
+    #[test]
+    fn templates_vote() {
+        let mut vote = params(TemplateKind::Vote);
+        vote.choice = Some("yay".to_string());
+        assert_eq!(template_message(&vote, &[]).unwrap(), "YAY: FIP-5");
+
+        let options = ["Option A".to_string(), "Option B".to_string()];
+        vote.choice = Some("option b".to_string());
+        assert_eq!(
+            template_message(&vote, &options).unwrap(),
+            "Option B: FIP-5"
+        );
+
+        vote.choice = Some("YAY".to_string());
+        assert_eq!(
+            template_message(&vote, &options),
+            Err(TemplateError::InvalidChoice("YAY".to_string()))
+        );
+
+        vote.choice = None;
+        assert_eq!(
+            template_message(&vote, &[]),
+            Err(TemplateError::MissingParam("choice"))
+        );
+    }
+
+    #[test]
+    fn templates_register() {
+        let mut register = params(TemplateKind::Register);
+        register.address = Some("0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56".to_string());
+        register.sp_ids = Some("t06024, 1000".to_string());
+        assert_eq!(
+            template_message(&register, &[]).unwrap(),
+            "0xf2361d2a9a0677e8ffd1515d65cf5190ea20eb56 t06024 t01000"
+        );
+
+        register.sp_ids = Some("f06024".to_string());
+        assert_eq!(
+            template_message(&register, &[]),
+            Err(TemplateError::InvalidSpId("f06024".to_string()))
+        );
+
+        register.sp_ids = Some("1000".to_string());
+        register.nonce = true;
+        assert_eq!(
+            template_message(&register, &[]),
+            Err(TemplateError::NonceNotAccepted)
+        );
+    }
+
+    #[test]
+    fn templates_start() {
+        let mut start = params(TemplateKind::Start);
+        assert_eq!(template_message(&start, &[]).unwrap(), "FIP-5");
+
+        start.round = Some(3);
+        assert_eq!(template_message(&start, &[]).unwrap(), "FIP-5 ROUND-3");
+
+        start.fip_number = None;
+        assert_eq!(
+            template_message(&start, &[]),
+            Err(TemplateError::MissingParam("fip_number"))
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Redis servers for the tests, each in a container of its own
+//!
+//! Every test starts an empty server on a free port instead of sharing the
+//! local one, so tests may run in parallel and never see each other's keys.
+//! Docker has to be running for the tests that use them
+//!
+//! Ballots are weighted with [`StaticPower`] rather than the chain, so tallies
+//! don't change with what the test storage provider holds
+use std::{
+    collections::BTreeMap,
+    ops::{Deref, DerefMut},
+    sync::OnceLock,
+};
+
+use async_trait::async_trait;
+use testcontainers::{clients::Cli, images::redis::Redis as RedisImage, Container};
+use url::Url;
+
+use crate::{
+    redis::Redis,
+    storage::{Network, StorageFetchError, StoragePowerProvider},
+};
+
+/// Port redis listens on inside the container
+const REDIS_PORT: u16 = 6379;
+
+/// Storage provider the test registration is signed for
+pub const TEST_SP_ID: u32 = 6024;
+/// Power the test storage provider has unless a test sets its own
+pub const TEST_SP_POWER: u128 = 10240000;
+
+/// Fixed storage power, storage providers it wasn't given have none
+#[derive(Default)]
+pub struct StaticPower(BTreeMap<u32, u128>);
+
+impl StaticPower {
+    pub fn new(powers: impl IntoIterator<Item = (u32, u128)>) -> Self {
+        Self(powers.into_iter().collect())
+    }
+}
+
+#[async_trait]
+impl StoragePowerProvider for StaticPower {
+    async fn storage_amounts(
+        &self,
+        sp_ids: &[u32],
+        _ntw: Network,
+    ) -> Result<BTreeMap<u32, u128>, StorageFetchError> {
+        Ok(sp_ids
+            .iter()
+            .map(|sp_id| (*sp_id, self.0.get(sp_id).copied().unwrap_or_default()))
+            .collect())
+    }
+}
+
+static DOCKER: OnceLock<Cli> = OnceLock::new();
+
+/// A connection to a server started for one test, which is removed once this is dropped
+pub struct TestRedis {
+    redis: Redis,
+    url: Url,
+    _container: Container<'static, RedisImage>,
+}
+
+impl TestRedis {
+    /// Starts an empty server and connects to it
+    pub fn start() -> Self {
+        let docker = DOCKER.get_or_init(Cli::default);
+        let container = docker.run(RedisImage::default());
+
+        let port = container.get_host_port_ipv4(REDIS_PORT);
+        let url = Url::parse(&format!("redis://127.0.0.1:{}", port)).unwrap();
+        let mut redis = Redis::new(url.clone()).unwrap();
+        redis.set_power_provider(StaticPower::new([(TEST_SP_ID, TEST_SP_POWER)]));
+
+        Self {
+            redis,
+            url,
+            _container: container,
+        }
+    }
+
+    /// Url of the server, for tests that open more connections to it
+    pub fn url(&self) -> Url {
+        self.url.clone()
+    }
+}
+
+impl Deref for TestRedis {
+    type Target = Redis;
+
+    fn deref(&self) -> &Redis {
+        &self.redis
+    }
+}
+
+impl DerefMut for TestRedis {
+    fn deref_mut(&mut self) -> &mut Redis {
+        &mut self.redis
+    }
+}
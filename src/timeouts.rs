@@ -0,0 +1,95 @@
+use std::{future::Future, time::Duration};
+
+use actix_web::{
+    body::BoxBody,
+    dev::{Service, ServiceRequest, ServiceResponse},
+    http::Method,
+    Error, HttpResponse,
+};
+
+use crate::errors::*;
+
+/// Endpoints that wait on the Filecoin RPC for storage power, worker
+/// addresses or the network size before answering
+const CHAIN_ROUTES: [(Method, &str); 11] = [
+    (Method::POST, "/filecoin/vote"),
+    (Method::POST, "/filecoin/vote/batch"),
+    (Method::POST, "/filecoin/vote/validate"),
+    (Method::POST, "/filecoin/register"),
+    (Method::POST, "/filecoin/register/bulk"),
+    (Method::POST, "/filecoin/unregister"),
+    (Method::GET, "/filecoin/votingpower"),
+    (Method::GET, "/filecoin/votingpower/at"),
+    (Method::GET, "/filecoin/votestats"),
+    (Method::GET, "/filecoin/turnout"),
+    (Method::POST, "/graphql"),
+];
+
+/// How long a request may take before it is answered with a timeout, `None`
+/// for requests that don't call the chain or when `rpc_timeout` is 0
+pub fn route_timeout(method: &Method, path: &str, rpc_timeout: u64) -> Option<Duration> {
+    if rpc_timeout == 0 {
+        return None;
+    }
+
+    CHAIN_ROUTES
+        .iter()
+        .any(|(m, p)| m == method && *p == path)
+        .then(|| Duration::from_secs(rpc_timeout))
+}
+
+/// Middleware that gives up on requests waiting on a slow chain node so
+/// they don't hold a worker, answering them with a 504
+pub fn middleware<S>(
+    rpc_timeout: u64,
+    req: ServiceRequest,
+    srv: &S,
+) -> impl Future<Output = Result<ServiceResponse<BoxBody>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error>,
+{
+    let timeout = route_timeout(req.method(), req.path(), rpc_timeout);
+    let http_req = req.request().clone();
+    let fut = srv.call(req);
+
+    async move {
+        let timeout = match timeout {
+            Some(timeout) => timeout,
+            None => return fut.await,
+        };
+
+        match tokio::time::timeout(timeout, fut).await {
+            Ok(res) => res,
+            Err(_) => {
+                println!("{} for {}", CHAIN_TIMEOUT_ERROR, http_req.path());
+                let res = HttpResponse::GatewayTimeout().body(CHAIN_TIMEOUT_ERROR);
+                Ok(ServiceResponse::new(http_req, res))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeouts_route_timeout() {
+        assert_eq!(
+            route_timeout(&Method::POST, "/filecoin/vote", 30),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            route_timeout(&Method::GET, "/filecoin/votingpower", 5),
+            Some(Duration::from_secs(5))
+        );
+
+        // Reading the ballots doesn't call the chain
+        assert_eq!(route_timeout(&Method::GET, "/filecoin/vote", 30), None);
+        assert_eq!(
+            route_timeout(&Method::POST, "/filecoin/startvote", 30),
+            None
+        );
+        assert_eq!(route_timeout(&Method::POST, "/filecoin/vote", 0), None);
+    }
+}
@@ -0,0 +1,306 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use rustls::{
+    server::{ClientHello, ResolvesServerCert},
+    sign::{self, CertifiedKey},
+    Certificate, PrivateKey, ServerConfig,
+};
+use rustls_pemfile::{certs, read_all, Item};
+use thiserror::Error;
+
+/// How often the certificate files are checked for renewal
+const RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Error)]
+pub enum TlsError {
+    #[error("Error reading {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("No certificates found in {0}")]
+    NoCertificates(PathBuf),
+    #[error("No private keys found in {0}")]
+    NoPrivateKey(PathBuf),
+    #[error("Unsupported private key in {0}")]
+    InvalidPrivateKey(PathBuf),
+    #[error("Unreadable certificate validity in {0}")]
+    InvalidValidity(PathBuf),
+}
+
+/// Serves the most recently loaded certificate and reloads it from disk
+/// whenever the certificate or key file changes
+struct CertReloader {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    current: RwLock<Arc<CertifiedKey>>,
+}
+
+/// Builds the server config for the certificate and key, watching both
+/// files so renewed certificates are picked up without a restart
+pub fn server_config(cert_path: PathBuf, key_path: PathBuf) -> Result<ServerConfig, TlsError> {
+    let certified_key = load_certified_key(&cert_path, &key_path)?;
+
+    let reloader = Arc::new(CertReloader {
+        cert_path,
+        key_path,
+        current: RwLock::new(Arc::new(certified_key)),
+    });
+    reloader.clone().watch();
+
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(reloader);
+
+    Ok(config)
+}
+
+/// Unix timestamp the certificate expires at, after loading it and the key as
+/// the server would
+pub fn certificate_expiry(cert_path: &Path, key_path: &Path) -> Result<u64, TlsError> {
+    let certified_key = load_certified_key(cert_path, key_path)?;
+
+    not_after(&certified_key.cert[0].0)
+        .ok_or_else(|| TlsError::InvalidValidity(cert_path.to_path_buf()))
+}
+
+impl CertReloader {
+    fn watch(self: Arc<Self>) {
+        thread::spawn(move || {
+            let mut last_modified = self.last_modified();
+            loop {
+                thread::sleep(RELOAD_INTERVAL);
+
+                let modified = self.last_modified();
+                if modified == last_modified {
+                    continue;
+                }
+
+                match load_certified_key(&self.cert_path, &self.key_path) {
+                    Ok(certified_key) => {
+                        *self.current.write().unwrap() = Arc::new(certified_key);
+                        last_modified = modified;
+                        println!("Reloaded TLS certificate from {:?}", self.cert_path);
+                    }
+                    // Keep serving the old certificate, the files may be mid-renewal
+                    Err(e) => println!("Error reloading TLS certificate: {}", e),
+                }
+            }
+        });
+    }
+
+    fn last_modified(&self) -> (Option<SystemTime>, Option<SystemTime>) {
+        let modified = |path: &Path| path.metadata().and_then(|m| m.modified()).ok();
+
+        (modified(&self.cert_path), modified(&self.key_path))
+    }
+}
+
+impl ResolvesServerCert for CertReloader {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(self.current.read().unwrap().clone())
+    }
+}
+
+fn load_certified_key(cert_path: &Path, key_path: &Path) -> Result<CertifiedKey, TlsError> {
+    let cert_chain = load_certs(cert_path)?;
+    let key = load_private_key(key_path)?;
+
+    let signing_key = match sign::any_supported_type(&key) {
+        Ok(signing_key) => signing_key,
+        Err(_) => return Err(TlsError::InvalidPrivateKey(key_path.to_path_buf())),
+    };
+
+    Ok(CertifiedKey::new(cert_chain, signing_key))
+}
+
+fn load_certs(path: &Path) -> Result<Vec<Certificate>, TlsError> {
+    let cert_file = &mut BufReader::new(open(path)?);
+
+    let cert_chain = match certs(cert_file) {
+        Ok(certs) => certs.into_iter().map(Certificate).collect::<Vec<_>>(),
+        Err(e) => return Err(TlsError::Io(path.to_path_buf(), e)),
+    };
+
+    if cert_chain.is_empty() {
+        return Err(TlsError::NoCertificates(path.to_path_buf()));
+    }
+
+    Ok(cert_chain)
+}
+
+fn load_private_key(path: &Path) -> Result<PrivateKey, TlsError> {
+    let key_file = &mut BufReader::new(open(path)?);
+
+    let items = match read_all(key_file) {
+        Ok(items) => items,
+        Err(e) => return Err(TlsError::Io(path.to_path_buf(), e)),
+    };
+
+    // Use the first private key in the file regardless of its encoding
+    items
+        .into_iter()
+        .find_map(|item| match item {
+            Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| TlsError::NoPrivateKey(path.to_path_buf()))
+}
+
+fn open(path: &Path) -> Result<File, TlsError> {
+    File::open(path).map_err(|e| TlsError::Io(path.to_path_buf(), e))
+}
+
+/// Reads the end of the validity period out of a DER encoded X.509 certificate
+///
+/// The validity follows the optional version, serial number, signature
+/// algorithm and issuer of the to be signed certificate
+fn not_after(cert: &[u8]) -> Option<u64> {
+    let (0x30, cert, _) = der(cert)? else {
+        return None;
+    };
+    let (0x30, mut tbs, _) = der(cert)? else {
+        return None;
+    };
+
+    // Version is an explicitly tagged [0], left out for version 1
+    if tbs.first() == Some(&0xa0) {
+        tbs = der(tbs)?.2;
+    }
+    for _ in 0..3 {
+        tbs = der(tbs)?.2;
+    }
+
+    let (0x30, validity, _) = der(tbs)? else {
+        return None;
+    };
+    let (_, _, validity) = der(validity)?;
+    let (tag, time, _) = der(validity)?;
+
+    let time = std::str::from_utf8(time).ok()?.strip_suffix('Z')?;
+    let time = match tag {
+        // UTCTime has a two digit year, 50 and above are in the 1900s
+        0x17 => {
+            let year: u64 = time.get(..2)?.parse().ok()?;
+            let century = if year >= 50 { "19" } else { "20" };
+            format!("{}{}", century, time)
+        }
+        // GeneralizedTime
+        0x18 => time.to_string(),
+        _ => return None,
+    };
+
+    unix_time(&time)
+}
+
+/// Splits the first element off DER encoded data into (tag, contents, rest)
+fn der(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&first, mut input) = input.split_first()?;
+
+    let len = match first {
+        0..=0x7f => first as usize,
+        0x81..=0x84 => {
+            let count = (first & 0x7f) as usize;
+            let bytes = input.get(..count)?;
+            input = &input[count..];
+            bytes.iter().fold(0, |len, &b| (len << 8) | b as usize)
+        }
+        _ => return None,
+    };
+
+    Some((tag, input.get(..len)?, &input[len..]))
+}
+
+/// Seconds since the epoch of a `YYYYMMDDHHMMSS` UTC time
+fn unix_time(time: &str) -> Option<u64> {
+    if time.len() != 14 {
+        return None;
+    }
+    let field = |range| time.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (field(0..4)?, field(4..6)?, field(6..8)?);
+    let (hour, minute, second) = (field(8..10)?, field(10..12)?, field(12..14)?);
+
+    // Days since 1970-01-01 of the civil date, counting years from March
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    u64::try_from(days * 86400 + hour * 3600 + minute * 60 + second).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tls_missing_files() {
+        let res = server_config(
+            PathBuf::from("/nonexistent/fullchain.pem"),
+            PathBuf::from("/nonexistent/privkey.pem"),
+        );
+
+        assert!(matches!(res, Err(TlsError::Io(_, _))));
+    }
+
+    #[test]
+    fn tls_malformed_files() {
+        let dir = std::env::temp_dir();
+        let cert_path = dir.join("fip-voting-test-cert.pem");
+        let key_path = dir.join("fip-voting-test-key.pem");
+        std::fs::write(&cert_path, "not a certificate").unwrap();
+        std::fs::write(&key_path, "not a key").unwrap();
+
+        let res = load_certs(&cert_path);
+        assert!(matches!(res, Err(TlsError::NoCertificates(_))));
+
+        let res = load_private_key(&key_path);
+        assert!(matches!(res, Err(TlsError::NoPrivateKey(_))));
+    }
+
+    #[test]
+    fn tls_not_after() {
+        // Certificate with only the fields before the validity, each empty
+        let cert = |version: &[u8], not_after: &[u8]| {
+            let validity = [
+                &[0x30, 15 + not_after.len() as u8][..],
+                &[0x17, 13],
+                b"230101000000Z",
+                not_after,
+            ]
+            .concat();
+            let tbs = [
+                version,
+                &[0x02, 0x01, 0x01, 0x30, 0x00, 0x30, 0x00],
+                &validity,
+            ]
+            .concat();
+            let cert = [&[0x30, tbs.len() as u8][..], &tbs].concat();
+            [&[0x30, cert.len() as u8][..], &cert].concat()
+        };
+
+        let utc = [&[0x17, 13][..], b"240101000000Z"].concat();
+        assert_eq!(
+            not_after(&cert(&[0xa0, 0x03, 0x02, 0x01, 0x02], &utc)),
+            Some(1704067200)
+        );
+        assert_eq!(not_after(&cert(&[], &utc)), Some(1704067200));
+
+        let utc = [&[0x17, 13][..], b"990228123015Z"].concat();
+        assert_eq!(not_after(&cert(&[], &utc)), Some(920205015));
+
+        let generalized = [&[0x18, 15][..], b"20500101000000Z"].concat();
+        assert_eq!(not_after(&cert(&[], &generalized)), Some(2524608000));
+
+        assert_eq!(not_after(&cert(&[], &[0x17, 0x02, b'2', b'4'])), None);
+        assert_eq!(not_after(b"not a certificate"), None);
+    }
+}
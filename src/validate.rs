@@ -0,0 +1,447 @@
+//! Preflight check of a ballot before it is cast
+//!
+//! Runs every check `POST /filecoin/vote` runs, from recovering the signer to
+//! the duplicate check, without writing anything or using up the nonce, so a
+//! wallet can show what is wrong with a ballot before the voter submits it
+use actix_web::{web, HttpResponse, Responder};
+use ethers::types::Address;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    errors::*,
+    fip::FipNumber,
+    messages::{
+        class_registration::VoterClass,
+        domain::Domain,
+        votes::{ReceivedVote, VoteError, VoteOption},
+    },
+    redis::{ballot_delegates, VoteStatus},
+    storage::{sp_id_format, Network},
+    store::{StoreError, StoreFactory, VoteStore},
+    Args, FipParams,
+};
+
+/// Whether a ballot would be accepted, and why not
+#[derive(Serialize, Debug, Default, ToSchema)]
+pub struct VoteVerdict {
+    /// True when no check failed and submitting the ballot now would record it
+    pub valid: bool,
+    #[schema(value_type = Option<String>)]
+    pub voter: Option<Address>,
+    #[schema(value_type = Option<String>, example = "mainnet")]
+    pub network: Option<Network>,
+    pub class: Option<VoterClass>,
+    pub choice: Option<VoteOption>,
+    /// Storage providers the ballot would be counted for
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub storage_providers: Vec<String>,
+    /// Every check that failed, empty for a valid ballot
+    pub problems: Vec<Problem>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Problem {
+    pub check: Check,
+    pub message: String,
+}
+
+/// The step of the pipeline a problem was found in
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Check {
+    /// The signer could not be recovered
+    Signature,
+    /// The signer is not registered or the registration expired
+    Registration,
+    /// Typed data was signed for another network
+    ChainId,
+    /// The message was signed for another service or network
+    Domain,
+    /// The choice is not on the ballot
+    Choice,
+    /// The ballot is signed for another FIP
+    Fip,
+    /// The vote is not in progress
+    VoteStatus,
+    /// The voter is not counted for any storage provider
+    Delegation,
+    /// The voter already voted and revotes are not allowed
+    Duplicate,
+    /// The nonce or expiry would be rejected
+    Nonce,
+}
+
+impl VoteVerdict {
+    fn problem(&mut self, check: Check, message: impl Into<String>) {
+        self.problems.push(Problem {
+            check,
+            message: message.into(),
+        });
+    }
+
+    fn finish(mut self) -> Self {
+        self.valid = self.problems.is_empty();
+        self
+    }
+}
+
+/// Registers the ballot validation endpoint, reading from the stores `F` opens
+pub fn routes<F: StoreFactory>(cfg: &mut web::ServiceConfig) {
+    cfg.route(
+        "/filecoin/vote/validate",
+        web::post().to(validate_vote::<F>),
+    );
+}
+
+/// Checks a ballot without casting it
+///
+/// Answers 200 with a verdict whether or not the ballot is valid, errors are
+/// only returned when the checks themselves could not run
+#[utoipa::path(
+    post,
+    path = "/filecoin/vote/validate",
+    tag = "votes",
+    params(FipParams),
+    request_body = ReceivedVote,
+    responses(
+        (status = 200, description = "Verdict on the ballot", body = VoteVerdict),
+        (status = 400, description = "Invalid request", body = String),
+        (status = 500, description = "Database or chain error", body = String),
+    )
+)]
+async fn validate_vote<F: StoreFactory>(
+    vote: web::Json<ReceivedVote>,
+    query_params: web::Query<FipParams>,
+    config: web::Data<Args>,
+    store: web::Data<F>,
+) -> impl Responder {
+    let num = query_params.fip_number;
+
+    println!("Vote validation requested for FIP: {}", num);
+
+    // Open a connection to the redis database
+    let mut redis = match store.open() {
+        Ok(redis) => redis,
+        Err(e) => {
+            let res = format!("{}: {}", OPEN_CONNECTION_ERROR, e);
+            println!("{}", res);
+            return HttpResponse::InternalServerError().body(res);
+        }
+    };
+
+    match validate(&mut redis, &vote, num, &config).await {
+        Ok(verdict) => {
+            println!("Vote verdict for FIP {}: {:?}", num, verdict.problems);
+            HttpResponse::Ok().json(verdict)
+        }
+        Err((message, e)) => {
+            let res = format!("{}: {}", message, e);
+            println!("{}", res);
+            HttpResponse::InternalServerError().body(res)
+        }
+    }
+}
+
+/// Runs the checks of `POST /filecoin/vote` in the same order
+///
+/// Checks that depend on the signer or its registration stop the pipeline
+/// when they fail, every other failure is collected
+pub async fn validate(
+    redis: &mut impl VoteStore,
+    vote: &ReceivedVote,
+    num: FipNumber,
+    config: &Args,
+) -> Result<VoteVerdict, (&'static str, StoreError)> {
+    let mut verdict = VoteVerdict::default();
+
+    let recovered = vote
+        .receipt_id()
+        .and_then(|id| Ok((id, vote.signer()?, vote.freshness()?, vote.domain()?)));
+    let (receipt_id, voter, freshness, domain) = match recovered {
+        Ok(recovered) => recovered,
+        Err(e) => {
+            verdict.problem(Check::Signature, format!("{}: {}", VOTE_RECOVER_ERROR, e));
+            return Ok(verdict.finish());
+        }
+    };
+    verdict.voter = Some(voter);
+
+    if let Some(receipt) = redis
+        .vote_receipt(&receipt_id)
+        .map_err(|e| (VOTE_RECEIPT_ERROR, e))?
+    {
+        verdict.problem(
+            Check::Duplicate,
+            format!("Ballot was already recorded with receipt {}", receipt.id),
+        );
+        return Ok(verdict.finish());
+    }
+
+    // An unregistered voter has no network to look anything else up in
+    let chain_id = vote.typed_chain_id().or_else(|| {
+        domain
+            .as_ref()
+            .and_then(Domain::ntw)
+            .map(|ntw| ntw.chain_id())
+    });
+    let ntw = match redis.ballot_network(voter, num, chain_id, config.vote_length()) {
+        Ok(ntw) => ntw,
+        Err(e @ (StoreError::Invalid(_) | StoreError::NotAuthorized(_))) => {
+            verdict.problem(
+                Check::Registration,
+                format!("{}: {}", VOTER_NOT_REGISTERED_NETWORK, e),
+            );
+            return Ok(verdict.finish());
+        }
+        Err(e) => return Err((VOTER_NOT_REGISTERED_NETWORK, e)),
+    };
+    verdict.network = Some(ntw);
+
+    if let Some(chain_id) = vote.typed_chain_id() {
+        if chain_id != ntw.chain_id() {
+            verdict.problem(
+                Check::ChainId,
+                format!("{}: {}", CHAIN_ID_MISMATCH_ERROR, chain_id),
+            );
+        }
+    } else if let Err(e) = config
+        .domain_rules()
+        .check(domain.as_ref(), ntw, redis.now())
+    {
+        verdict.problem(Check::Domain, format!("{}: {}", DOMAIN_ERROR, e));
+    }
+
+    if redis
+        .is_registration_expired(voter, ntw)
+        .map_err(|e| (VOTER_AUTH_ERROR, e))?
+    {
+        verdict.problem(Check::Registration, REGISTRATION_EXPIRED_ERROR);
+    }
+
+    let options = redis
+        .vote_options(num, ntw)
+        .map_err(|e| (VOTE_OPTIONS_ERROR, e))?;
+    let ballot = match vote.vote_on(&options) {
+        Ok(ballot) => ballot,
+        Err(e) => {
+            verdict.problem(Check::Choice, format!("{}: {}", VOTE_RECOVER_ERROR, e));
+            return Ok(verdict.finish());
+        }
+    };
+    verdict.choice = Some(ballot.choice());
+
+    if ballot.fip() != num {
+        verdict.problem(
+            Check::Fip,
+            format!(
+                "{}: {}",
+                FIP_MISMATCH_ERROR,
+                VoteError::FipMismatch(num, ballot.fip())
+            ),
+        );
+    }
+
+    match redis
+        .vote_status(num, config.vote_length(), ntw)
+        .map_err(|e| (VOTE_STATUS_ERROR, e))?
+    {
+        VoteStatus::InProgress(_) => (),
+        VoteStatus::Scheduled(opens_in) => verdict.problem(
+            Check::VoteStatus,
+            format!("Vote for FIP: {} opens in {} seconds", num, opens_in),
+        ),
+        VoteStatus::Concluded => verdict.problem(
+            Check::VoteStatus,
+            format!("Vote concluded for FIP: {}", num),
+        ),
+        VoteStatus::Cancelled => verdict.problem(
+            Check::VoteStatus,
+            format!("Vote cancelled for FIP: {}", num),
+        ),
+        VoteStatus::DoesNotExist => verdict.problem(
+            Check::VoteStatus,
+            format!("No vote in progress for FIP: {}", num),
+        ),
+        VoteStatus::Unknown => verdict.problem(
+            Check::VoteStatus,
+            format!("{} for FIP: {}", VOTE_STATUS_UNKNOWN, num),
+        ),
+    }
+
+    let class = redis
+        .voter_class(voter, ntw)
+        .map_err(|e| (VOTER_AUTH_ERROR, e))?;
+    verdict.class = Some(class);
+
+    if class == VoterClass::StorageProvider {
+        let authorized = redis
+            .voter_delegates(voter, ntw)
+            .map_err(|e| (VOTER_DELEGATES_ERROR, e))?;
+        // Storage providers whose worker key rotated are left out, as when casting
+        let delegates = match redis
+            .voter_worker(voter, ntw)
+            .map_err(|e| (VOTER_DELEGATES_ERROR, e))?
+        {
+            Some(worker) if !authorized.is_empty() => {
+                ballot_delegates(voter, &authorized, &worker, ntw)
+                    .await
+                    .map_err(|e| (VOTER_DELEGATES_ERROR, e))?
+            }
+            _ => authorized,
+        };
+
+        if delegates.is_empty() {
+            verdict.problem(
+                Check::Delegation,
+                "Voter is not counted for any storage providers",
+            );
+        }
+        verdict.storage_providers = delegates
+            .into_iter()
+            .map(|sp_id| sp_id_format(ntw, sp_id))
+            .collect();
+    }
+
+    if !config.allow_revote()
+        && redis
+            .votes(num, ntw)
+            .map_err(|e| (VOTE_STATUS_ERROR, e))?
+            .contains(&ballot)
+    {
+        verdict.problem(
+            Check::Duplicate,
+            format!("Vote already exists for FIP: {}", num),
+        );
+    }
+
+    // The nonce is only looked up, it is used up when the ballot is cast
+    match freshness {
+        Some(freshness) if freshness.is_expired(redis.now()) => {
+            verdict.problem(Check::Nonce, MESSAGE_EXPIRED_ERROR)
+        }
+        Some(freshness) => {
+            if !redis
+                .nonce_issued(freshness.nonce)
+                .map_err(|e| (NONCE_CHECK_ERROR, e))?
+            {
+                verdict.problem(Check::Nonce, NONCE_INVALID_ERROR);
+            }
+        }
+        None if config.require_nonce() => verdict.problem(Check::Nonce, NONCE_REQUIRED_ERROR),
+        None => (),
+    }
+
+    Ok(verdict.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use clap::Parser;
+
+    use super::*;
+    use crate::{
+        authorized_voters, events::RecordedBallot, messages::votes::test_votes::test_vote,
+        test_redis::TestRedis,
+    };
+
+    fn args(server: &TestRedis) -> Args {
+        Args::parse_from(["filecoin-vote", "--redis-path", server.url().as_str()])
+    }
+
+    fn checks(verdict: &VoteVerdict) -> Vec<Check> {
+        verdict.problems.iter().map(|p| p.check).collect()
+    }
+
+    #[tokio::test]
+    async fn validate_vote_pipeline() {
+        let mut server = TestRedis::start();
+        let config = args(&server);
+        let num = FipNumber::try_from(5).unwrap();
+        let ntw = Network::Testnet;
+        let received = test_vote(VoteOption::Yay, 5);
+        let voter = received.vote().unwrap().voter();
+
+        // Not registered yet
+        let verdict = validate(&mut *server, &received, num, &config)
+            .await
+            .unwrap();
+        assert!(!verdict.valid);
+        assert_eq!(verdict.voter, Some(voter));
+        assert_eq!(checks(&verdict), vec![Check::Registration]);
+
+        // Registered, but the vote hasn't started
+        server.register_voter(voter, ntw, vec![6024]).unwrap();
+        let verdict = validate(&mut *server, &received, num, &config)
+            .await
+            .unwrap();
+        assert_eq!(checks(&verdict), vec![Check::VoteStatus]);
+
+        server
+            .start_vote(num, authorized_voters()[0], ntw)
+            .await
+            .unwrap();
+        let verdict = validate(&mut *server, &received, num, &config)
+            .await
+            .unwrap();
+        assert!(verdict.valid, "{:?}", verdict.problems);
+        assert_eq!(verdict.network, Some(ntw));
+        assert_eq!(verdict.choice, Some(VoteOption::Yay));
+        assert_eq!(verdict.storage_providers, vec!["t06024".to_string()]);
+
+        // Signed for another FIP
+        let other = FipNumber::try_from(6).unwrap();
+        let verdict = validate(&mut *server, &received, other, &config)
+            .await
+            .unwrap();
+        assert!(checks(&verdict).contains(&Check::Fip));
+
+        // Nothing was written, the ballot is only a duplicate once it is cast
+        assert!(server.votes(num, ntw).unwrap().is_empty());
+        let mut ballot = received.vote().unwrap();
+        ballot.record(
+            server.now(),
+            VoterClass::StorageProvider,
+            BTreeMap::from([(6024, 1024)]),
+            0,
+        );
+        server
+            .record_ballot(
+                RecordedBallot {
+                    fip_number: num,
+                    ntw,
+                    voter,
+                    class: VoterClass::StorageProvider,
+                    vote: ballot,
+                    storage: 1024,
+                    fil: 0,
+                },
+                false,
+                None,
+            )
+            .unwrap();
+        let verdict = validate(&mut *server, &received, num, &config)
+            .await
+            .unwrap();
+        assert_eq!(checks(&verdict), vec![Check::Duplicate]);
+    }
+
+    #[tokio::test]
+    async fn validate_vote_bad_signature() {
+        let mut server = TestRedis::start();
+        let config = args(&server);
+        let num = FipNumber::try_from(5).unwrap();
+
+        let received: ReceivedVote =
+            serde_json::from_str(r#"{"signature":"0x00","message":"YAY: FIP-5"}"#).unwrap();
+
+        let verdict = validate(&mut *server, &received, num, &config)
+            .await
+            .unwrap();
+        assert!(!verdict.valid);
+        assert_eq!(verdict.voter, None);
+        assert_eq!(checks(&verdict), vec![Check::Signature]);
+    }
+}